@@ -0,0 +1,386 @@
+use engine::ecs::{ComponentRegistry, DetachedSubtree, EmitterSettings, EntityId, FieldValue, ParticleEmitter, World};
+use engine::math::Transform;
+use std::any::Any;
+
+use crate::history::{CommandHistory, EditorCommand};
+use crate::selection::Selection;
+
+fn entity_name(world: &World, entity: EntityId) -> String {
+    world.name(entity).unwrap_or("<unnamed>").to_string()
+}
+
+/// Put a detached subtree back, keeping it for a later attempt if that fails
+fn restore(world: &mut World, detached: &mut Option<DetachedSubtree>) -> bool {
+    let Some(subtree) = detached.take() else {
+        return false;
+    };
+    match world.restore_subtree(subtree) {
+        Ok(()) => true,
+        Err(subtree) => {
+            *detached = Some(subtree);
+            false
+        }
+    }
+}
+
+/// Change of one reflected component field (inspector edits)
+pub struct SetField {
+    entity: EntityId,
+    component: String,
+    field: String,
+    before: FieldValue,
+    after: FieldValue,
+}
+
+impl SetField {
+    pub fn new(entity: EntityId, component: &str, field: &str, before: FieldValue, after: FieldValue) -> Self {
+        SetField {
+            entity,
+            component: component.to_string(),
+            field: field.to_string(),
+            before,
+            after,
+        }
+    }
+
+    fn write(&self, world: &mut World, value: FieldValue) -> bool {
+        // The registry is taken out while writing so the World can be borrowed mutably
+        let Some(registry) = world.remove_resource::<ComponentRegistry>() else {
+            return false;
+        };
+        let written = registry.set_field(world, self.entity, &self.component, &self.field, value);
+        world.insert_resource(registry);
+        written
+    }
+}
+
+impl EditorCommand for SetField {
+    fn label(&self) -> String {
+        format!("Set {}.{}", self.component, self.field)
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        self.write(world, self.after)
+    }
+
+    fn revert(&mut self, world: &mut World) -> bool {
+        self.write(world, self.before)
+    }
+
+    fn merge(&mut self, next: &dyn EditorCommand) -> bool {
+        let Some(next) = next.as_any().downcast_ref::<SetField>() else {
+            return false;
+        };
+        if next.entity != self.entity || next.component != self.component || next.field != self.field {
+            return false;
+        }
+        self.after = next.after;
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Replacement of a whole Transform (gizmo drags)
+pub struct SetTransform {
+    entity: EntityId,
+    name: String,
+    before: Transform,
+    after: Transform,
+}
+
+impl SetTransform {
+    pub fn new(world: &World, entity: EntityId, before: Transform, after: Transform) -> Self {
+        SetTransform {
+            entity,
+            name: entity_name(world, entity),
+            before,
+            after,
+        }
+    }
+
+    fn write(&self, world: &mut World, transform: Transform) -> bool {
+        match world.get_mut::<Transform>(self.entity) {
+            Some(current) => {
+                *current = transform;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl EditorCommand for SetTransform {
+    fn label(&self) -> String {
+        format!("Transform {}", self.name)
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        self.write(world, self.after)
+    }
+
+    fn revert(&mut self, world: &mut World) -> bool {
+        self.write(world, self.before)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Replacement of a particle emitter's settings (curve, gradient and shape edits)
+pub struct SetEmitterSettings {
+    entity: EntityId,
+    name: String,
+    before: EmitterSettings,
+    after: EmitterSettings,
+}
+
+impl SetEmitterSettings {
+    pub fn new(world: &World, entity: EntityId, before: EmitterSettings, after: EmitterSettings) -> Self {
+        SetEmitterSettings {
+            entity,
+            name: entity_name(world, entity),
+            before,
+            after,
+        }
+    }
+
+    fn write(&self, world: &mut World, settings: &EmitterSettings) -> bool {
+        match world.get_mut::<ParticleEmitter>(self.entity) {
+            Some(emitter) => {
+                emitter.settings = settings.clone();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl EditorCommand for SetEmitterSettings {
+    fn label(&self) -> String {
+        format!("Edit particles of {}", self.name)
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        self.write(world, &self.after)
+    }
+
+    fn revert(&mut self, world: &mut World) -> bool {
+        self.write(world, &self.before)
+    }
+
+    fn merge(&mut self, next: &dyn EditorCommand) -> bool {
+        let Some(next) = next.as_any().downcast_ref::<SetEmitterSettings>() else {
+            return false;
+        };
+        if next.entity != self.entity {
+            return false;
+        }
+        self.after = next.after.clone();
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Creation of a new entity with a default Transform
+pub struct SpawnEntity {
+    name: String,
+    parent: Option<EntityId>,
+    spawned: Option<EntityId>,
+    /// The entity while undone
+    detached: Option<DetachedSubtree>,
+}
+
+impl SpawnEntity {
+    pub fn new(name: &str, parent: Option<EntityId>) -> Self {
+        SpawnEntity {
+            name: name.to_string(),
+            parent,
+            spawned: None,
+            detached: None,
+        }
+    }
+
+    /// Id of the created entity once applied
+    pub fn entity(&self) -> Option<EntityId> {
+        self.spawned
+    }
+}
+
+impl EditorCommand for SpawnEntity {
+    fn label(&self) -> String {
+        format!("Create {}", self.name)
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        // Redo brings back the same entity so later commands still point at it
+        if self.spawned.is_some() {
+            return restore(world, &mut self.detached);
+        }
+        let id = match self.parent {
+            Some(parent) if world.contains(parent) => world.spawn_child(parent, &self.name),
+            Some(_) => return false,
+            None => world.spawn(&self.name),
+        };
+        world.insert(id, Transform::new());
+        self.spawned = Some(id);
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) -> bool {
+        self.detached = self.spawned.and_then(|id| world.detach_subtree(id));
+        self.detached.is_some()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Removal of an entity and its descendants
+pub struct DeleteEntity {
+    entity: EntityId,
+    name: String,
+    /// The removed entities while applied
+    detached: Option<DetachedSubtree>,
+}
+
+impl DeleteEntity {
+    pub fn new(world: &World, entity: EntityId) -> Self {
+        DeleteEntity {
+            entity,
+            name: entity_name(world, entity),
+            detached: None,
+        }
+    }
+}
+
+impl EditorCommand for DeleteEntity {
+    fn label(&self) -> String {
+        format!("Delete {}", self.name)
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        self.detached = world.detach_subtree(self.entity);
+        self.detached.is_some()
+    }
+
+    fn revert(&mut self, world: &mut World) -> bool {
+        restore(world, &mut self.detached)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Move of an entity under another parent (or to the root)
+pub struct Reparent {
+    entity: EntityId,
+    name: String,
+    old_parent: Option<EntityId>,
+    old_index: usize,
+    new_parent: Option<EntityId>,
+}
+
+impl Reparent {
+    pub fn new(world: &World, entity: EntityId, new_parent: Option<EntityId>) -> Self {
+        Reparent {
+            entity,
+            name: entity_name(world, entity),
+            old_parent: world.parent(entity),
+            old_index: world.sibling_index(entity).unwrap_or(0),
+            new_parent,
+        }
+    }
+}
+
+impl EditorCommand for Reparent {
+    fn label(&self) -> String {
+        format!("Reparent {}", self.name)
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        self.new_parent != self.old_parent && world.set_parent(self.entity, self.new_parent)
+    }
+
+    fn revert(&mut self, world: &mut World) -> bool {
+        world.set_parent_at(self.entity, self.old_parent, Some(self.old_index))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Requests from outside the event loop (console commands), run on the next redraw
+#[derive(Clone, Debug)]
+pub enum EditorAction {
+    Undo,
+    Redo,
+    /// Create an entity under the selection (or at the root) and select it
+    Spawn(String),
+    DeleteSelected,
+    /// Move the selection under the named entity, or to the root with `None`
+    ReparentSelected(Option<String>),
+}
+
+fn find_by_name(world: &World, name: &str) -> Option<EntityId> {
+    let mut ids: Vec<_> = world.entity_ids().filter(|&id| world.name(id) == Some(name)).collect();
+    ids.sort();
+    ids.first().copied()
+}
+
+/// Run an action through the command history
+pub fn run_action(action: EditorAction, history: &mut CommandHistory, world: &mut World) {
+    let selected = world.resource::<Selection>().and_then(Selection::get).filter(|&id| world.contains(id));
+    match action {
+        EditorAction::Undo => {
+            history.undo(world);
+        }
+        EditorAction::Redo => {
+            history.redo(world);
+        }
+        EditorAction::Spawn(name) => {
+            let mut command = SpawnEntity::new(&name, selected);
+            if command.apply(world) {
+                let spawned = command.entity();
+                history.record(Box::new(command));
+                if let (Some(id), Some(selection)) = (spawned, world.resource_mut::<Selection>()) {
+                    selection.select(id);
+                }
+            }
+        }
+        EditorAction::DeleteSelected => {
+            if let Some(entity) = selected {
+                history.execute(Box::new(DeleteEntity::new(world, entity)), world);
+            }
+        }
+        EditorAction::ReparentSelected(parent) => {
+            let Some(entity) = selected else {
+                return;
+            };
+            let new_parent = match parent {
+                Some(name) => match find_by_name(world, &name) {
+                    Some(id) => Some(id),
+                    None => {
+                        log::warn!("No entity named {}", name);
+                        return;
+                    }
+                },
+                None => None,
+            };
+            if !history.execute(Box::new(Reparent::new(world, entity, new_parent)), world) {
+                log::warn!("Could not reparent {}", entity_name(world, entity));
+            }
+        }
+    }
+    // Actions are discrete, never merge them with what follows
+    history.seal();
+}
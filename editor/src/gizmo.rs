@@ -0,0 +1,281 @@
+use engine::ecs::{EntityId, World};
+use engine::gui::{Vec2, ViewportComponent};
+use engine::math::Transform;
+use engine::renderer::DebugLines;
+use glam::{Mat4, Vec3};
+
+/// Length of the gizmo axes in pixels
+const AXIS_PIXELS: f32 = 80.0;
+/// Half size of the center handle in pixels
+const CENTER_PIXELS: f32 = 7.0;
+/// Maximum distance in pixels between the mouse and a handle to pick it
+const PICK_PIXELS: f32 = 6.0;
+
+const X_COLOR: [f32; 3] = [0.95, 0.3, 0.3];
+const Y_COLOR: [f32; 3] = [0.3, 0.9, 0.35];
+const FREE_COLOR: [f32; 3] = [0.4, 0.6, 1.0];
+const HOVER_COLOR: [f32; 3] = [1.0, 0.9, 0.3];
+const OUTLINE_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+
+/// What dragging the gizmo changes (W/E/R in the editor)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Handle {
+    AxisX,
+    AxisY,
+    /// Both axes at once (center square)
+    Free,
+    /// Rotation ring
+    Ring,
+}
+
+struct Drag {
+    entity: EntityId,
+    handle: Handle,
+    /// World position of the mouse when the drag started
+    start_point: Vec2,
+    start: Transform,
+    /// Maps world space deltas into the parent space of the entity
+    parent_inverse: Mat4,
+}
+
+/// Transform of an entity before and after a finished gizmo drag
+pub struct GizmoEdit {
+    pub entity: EntityId,
+    pub before: Transform,
+    pub after: Transform,
+}
+
+/// Translate/rotate/scale handles drawn over the selected entity in the viewport
+pub struct Gizmo {
+    mode: GizmoMode,
+    hovered: Option<Handle>,
+    drag: Option<Drag>,
+}
+
+impl Gizmo {
+    pub fn new() -> Self {
+        Gizmo {
+            mode: GizmoMode::Translate,
+            hovered: None,
+            drag: None,
+        }
+    }
+
+    /// Switching modes is ignored while dragging
+    pub fn set_mode(&mut self, mode: GizmoMode) {
+        if self.drag.is_none() {
+            self.mode = mode;
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// World space origin and rotation of an entity
+    fn frame(world: &World, entity: EntityId) -> Option<(Vec2, f32)> {
+        if !world.has::<Transform>(entity) {
+            return None;
+        }
+        let matrix = world.world_matrix(entity);
+        let origin = matrix.transform_point3(Vec3::ZERO).truncate();
+        let x_axis = matrix.transform_vector3(Vec3::X).truncate();
+        Some((origin, x_axis.to_angle()))
+    }
+
+    /// Handle directions: world axes for translation, the entity's own axes for scaling
+    fn axes(&self, rotation: f32) -> (Vec2, Vec2) {
+        match self.mode {
+            GizmoMode::Scale => {
+                let x = Vec2::from_angle(rotation);
+                (x, x.perp())
+            }
+            _ => (Vec2::X, Vec2::Y),
+        }
+    }
+
+    fn handle_at(&self, viewport: &ViewportComponent, world: &World, entity: EntityId, mouse: Vec2) -> Option<Handle> {
+        let (origin, rotation) = Self::frame(world, entity)?;
+        let point = viewport.screen_to_world(mouse);
+        let length = viewport.pixels_to_world(AXIS_PIXELS);
+        let tolerance = viewport.pixels_to_world(PICK_PIXELS);
+        let offset = point - origin;
+
+        if self.mode == GizmoMode::Rotate {
+            return ((offset.length() - length).abs() <= tolerance).then_some(Handle::Ring);
+        }
+
+        let center = viewport.pixels_to_world(CENTER_PIXELS);
+        let (x_axis, y_axis) = self.axes(rotation);
+        if offset.dot(x_axis).abs() <= center && offset.dot(y_axis).abs() <= center {
+            return Some(Handle::Free);
+        }
+        for (handle, axis, side) in [(Handle::AxisX, x_axis, y_axis), (Handle::AxisY, y_axis, x_axis)] {
+            let along = offset.dot(axis);
+            if (0.0..=length).contains(&along) && offset.dot(side).abs() <= tolerance {
+                return Some(handle);
+            }
+        }
+        None
+    }
+
+    /// Update the highlighted handle under the mouse, true if it changed
+    pub fn hover(&mut self, viewport: &ViewportComponent, world: &World, selected: Option<EntityId>, mouse: Vec2) -> bool {
+        let hovered = selected.and_then(|entity| self.handle_at(viewport, world, entity, mouse));
+        std::mem::replace(&mut self.hovered, hovered) != hovered
+    }
+
+    /// Start dragging if the mouse is over a handle of the selected entity
+    /// Returns false if no handle was hit (the click should pick entities instead)
+    pub fn begin_drag(&mut self, viewport: &ViewportComponent, world: &World, selected: Option<EntityId>, mouse: Vec2) -> bool {
+        let Some(entity) = selected else {
+            return false;
+        };
+        let Some(handle) = self.handle_at(viewport, world, entity, mouse) else {
+            return false;
+        };
+        let Some(&start) = world.get::<Transform>(entity) else {
+            return false;
+        };
+
+        let parent_inverse = world.parent(entity).map(|p| world.world_matrix(p).inverse()).unwrap_or(Mat4::IDENTITY);
+        self.drag = Some(Drag {
+            entity,
+            handle,
+            start_point: viewport.screen_to_world(mouse),
+            start,
+            parent_inverse,
+        });
+        true
+    }
+
+    /// Apply the current drag to the entity's Transform
+    pub fn drag(&mut self, viewport: &ViewportComponent, world: &mut World, mouse: Vec2) {
+        let Some(drag) = &self.drag else {
+            return;
+        };
+        let Some((origin, rotation)) = Self::frame(world, drag.entity) else {
+            return;
+        };
+        let point = viewport.screen_to_world(mouse);
+        let (x_axis, y_axis) = self.axes(rotation);
+
+        let mut transform = drag.start;
+        match self.mode {
+            GizmoMode::Translate => {
+                let delta = point - drag.start_point;
+                let delta = match drag.handle {
+                    Handle::AxisX => x_axis * delta.dot(x_axis),
+                    Handle::AxisY => y_axis * delta.dot(y_axis),
+                    _ => delta,
+                };
+                let local = drag.parent_inverse.transform_vector3(delta.extend(0.0)).truncate();
+                transform.position = drag.start.position + local;
+            }
+            GizmoMode::Rotate => {
+                // The origin doesn't move while rotating, so measure around the current one
+                let from = drag.start_point - origin;
+                let to = point - origin;
+                if from.length_squared() > f32::EPSILON && to.length_squared() > f32::EPSILON {
+                    transform.rotation = drag.start.rotation + from.angle_to(to);
+                }
+            }
+            GizmoMode::Scale => {
+                let from = drag.start_point - origin;
+                let to = point - origin;
+                let ratio = |axis: Vec2| {
+                    let start = from.dot(axis);
+                    if start.abs() > f32::EPSILON { to.dot(axis) / start } else { 1.0 }
+                };
+                let factor = match drag.handle {
+                    Handle::AxisX => Vec2::new(ratio(x_axis), 1.0),
+                    Handle::AxisY => Vec2::new(1.0, ratio(y_axis)),
+                    _ => {
+                        let uniform = if from.length() > f32::EPSILON { to.length() / from.length() } else { 1.0 };
+                        Vec2::splat(uniform)
+                    }
+                };
+                transform.scale = drag.start.scale * factor;
+            }
+        }
+
+        if let Some(current) = world.get_mut::<Transform>(drag.entity) {
+            *current = transform;
+        }
+    }
+
+    /// Finish the drag, returning the change if the entity still exists
+    pub fn end_drag(&mut self, world: &World) -> Option<GizmoEdit> {
+        let drag = self.drag.take()?;
+        let after = *world.get::<Transform>(drag.entity)?;
+        Some(GizmoEdit {
+            entity: drag.entity,
+            before: drag.start,
+            after,
+        })
+    }
+
+    fn color(&self, handle: Handle, base: [f32; 3]) -> [f32; 3] {
+        let active = self.drag.as_ref().map(|d| d.handle).or(self.hovered);
+        if active == Some(handle) { HOVER_COLOR } else { base }
+    }
+
+    /// Queue the selection outline and the handles for the current mode
+    pub fn draw(&self, lines: &mut DebugLines, viewport: &ViewportComponent, world: &World, selected: Option<EntityId>) {
+        let Some(entity) = selected else {
+            return;
+        };
+        let Some((origin, rotation)) = Self::frame(world, entity) else {
+            return;
+        };
+
+        // Outline of the entity quad
+        let matrix = world.world_matrix(entity);
+        let corners: Vec<Vec2> = [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)]
+            .iter()
+            .map(|&(x, y)| matrix.transform_point3(Vec3::new(x, y, 0.0)).truncate())
+            .collect();
+        lines.polyline(&corners, true, OUTLINE_COLOR);
+
+        let length = viewport.pixels_to_world(AXIS_PIXELS);
+        let center = viewport.pixels_to_world(CENTER_PIXELS);
+        let (x_axis, y_axis) = self.axes(rotation);
+
+        match self.mode {
+            GizmoMode::Translate => {
+                let head = viewport.pixels_to_world(12.0);
+                lines.arrow(origin, origin + x_axis * length, head, self.color(Handle::AxisX, X_COLOR));
+                lines.arrow(origin, origin + y_axis * length, head, self.color(Handle::AxisY, Y_COLOR));
+                lines.rect(origin, Vec2::splat(center * 2.0), self.color(Handle::Free, FREE_COLOR));
+            }
+            GizmoMode::Rotate => {
+                let color = self.color(Handle::Ring, FREE_COLOR);
+                lines.circle(origin, length, 48, color);
+                lines.line(origin, origin + Vec2::from_angle(rotation) * length, color);
+            }
+            GizmoMode::Scale => {
+                let box_size = Vec2::splat(center * 1.2);
+                for (handle, axis, base) in [(Handle::AxisX, x_axis, X_COLOR), (Handle::AxisY, y_axis, Y_COLOR)] {
+                    let color = self.color(handle, base);
+                    let end = origin + axis * length;
+                    lines.line(origin, end, color);
+                    lines.rect(end, box_size, color);
+                }
+                lines.rect(origin, Vec2::splat(center * 2.0), self.color(Handle::Free, FREE_COLOR));
+            }
+        }
+    }
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
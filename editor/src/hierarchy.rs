@@ -0,0 +1,56 @@
+use anyhow::Result;
+use engine::ecs::{EntityId, World};
+use engine::gui::{TreeItem, TreeView};
+use engine::renderer::VulkanContext;
+use std::sync::Arc;
+
+use crate::selection::Selection;
+
+/// Flatten the World hierarchy into pre-order tree items
+fn build_items(world: &World) -> Vec<TreeItem> {
+    fn visit(world: &World, id: EntityId, depth: usize, items: &mut Vec<TreeItem>) {
+        let children = world.children(id);
+        items.push(TreeItem {
+            id: id.0 as u64,
+            label: world.name(id).unwrap_or("<unnamed>").to_string(),
+            depth,
+            has_children: !children.is_empty(),
+        });
+        for &child in children {
+            visit(world, child, depth + 1, items);
+        }
+    }
+
+    let mut items = Vec::with_capacity(world.entity_count());
+    for &root in world.roots() {
+        visit(world, root, 0, &mut items);
+    }
+    items
+}
+
+/// Sync the hierarchy tree with the World and the editor Selection
+/// Clicks in the tree update the Selection, outside Selection changes update the tree
+pub fn sync_hierarchy(tree: &mut TreeView, world: &mut World, context: &Arc<VulkanContext>) -> Result<()> {
+    tree.set_items(build_items(world));
+
+    if let Some(change) = tree.take_selection_change() {
+        if let Some(selection) = world.resource_mut::<Selection>() {
+            match change {
+                Some(id) => selection.select(EntityId(id as u32)),
+                None => selection.clear(),
+            }
+        }
+    }
+
+    // Reflect the current selection (which may have been changed elsewhere) in the tree,
+    // dropping selections that point at despawned entities
+    let selected = world.resource::<Selection>().and_then(Selection::get).filter(|&id| world.contains(id));
+    if selected.is_none() {
+        if let Some(selection) = world.resource_mut::<Selection>() {
+            selection.clear();
+        }
+    }
+    tree.set_selected(selected.map(|id| id.0 as u64));
+
+    tree.refresh(context)
+}
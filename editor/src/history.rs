@@ -0,0 +1,183 @@
+use anyhow::Result;
+use engine::ecs::World;
+use engine::gui::{TreeItem, TreeView};
+use engine::renderer::VulkanContext;
+use std::any::Any;
+use std::sync::Arc;
+
+/// Oldest commands are dropped beyond this many
+const HISTORY_LIMIT: usize = 256;
+/// Entries shown in the history panel before the current position
+const PANEL_CONTEXT: usize = 4;
+
+/// A mutating editor action that can be undone and redone
+pub trait EditorCommand {
+    /// Short description shown in the history panel
+    fn label(&self) -> String;
+
+    /// Perform (or redo) the change
+    /// Returns false if nothing changed, the command is then dropped
+    fn apply(&mut self, world: &mut World) -> bool;
+
+    /// Undo the change made by `apply`
+    fn revert(&mut self, world: &mut World) -> bool;
+
+    /// Fold the following command into this one (e.g. every step of a slider drag)
+    /// Returns false if the commands are unrelated
+    fn merge(&mut self, _next: &dyn EditorCommand) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Undo/redo stacks of editor commands
+///
+/// Consecutive commands are merged while the history is open; call `seal` when an
+/// interaction ends (mouse release) so the next edit starts a new entry.
+pub struct CommandHistory {
+    done: Vec<Box<dyn EditorCommand>>,
+    /// Undone commands, the next one to redo is last
+    undone: Vec<Box<dyn EditorCommand>>,
+    /// Whether the last command may absorb the next one
+    open: bool,
+    /// Undo and redo are ignored while locked (play mode)
+    locked: bool,
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        CommandHistory {
+            done: Vec::new(),
+            undone: Vec::new(),
+            open: false,
+            locked: false,
+        }
+    }
+
+    /// Apply a command and record it, clearing the redo stack
+    pub fn execute(&mut self, mut command: Box<dyn EditorCommand>, world: &mut World) -> bool {
+        if !command.apply(world) {
+            return false;
+        }
+        self.push(command);
+        true
+    }
+
+    /// Record a command whose change was already made (e.g. a finished gizmo drag)
+    pub fn record(&mut self, command: Box<dyn EditorCommand>) {
+        self.push(command);
+    }
+
+    fn push(&mut self, command: Box<dyn EditorCommand>) {
+        self.undone.clear();
+        if self.open {
+            if let Some(last) = self.done.last_mut() {
+                if last.merge(command.as_ref()) {
+                    return;
+                }
+            }
+        }
+        self.done.push(command);
+        if self.done.len() > HISTORY_LIMIT {
+            self.done.remove(0);
+        }
+        self.open = true;
+    }
+
+    /// Stop merging into the last command
+    pub fn seal(&mut self) {
+        self.open = false;
+    }
+
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+        self.open = false;
+    }
+
+    pub fn undo(&mut self, world: &mut World) -> bool {
+        if self.locked {
+            return false;
+        }
+        self.open = false;
+        let Some(mut command) = self.done.pop() else {
+            return false;
+        };
+        if !command.revert(world) {
+            log::warn!("Could not undo '{}'", command.label());
+        }
+        self.undone.push(command);
+        true
+    }
+
+    pub fn redo(&mut self, world: &mut World) -> bool {
+        if self.locked {
+            return false;
+        }
+        self.open = false;
+        let Some(mut command) = self.undone.pop() else {
+            return false;
+        };
+        if !command.apply(world) {
+            log::warn!("Could not redo '{}'", command.label());
+        }
+        self.done.push(command);
+        true
+    }
+
+    /// Number of applied commands, 0 is the state before the first one
+    pub fn position(&self) -> usize {
+        self.done.len()
+    }
+
+    /// Forget the commands applied after `position` (their changes were discarded elsewhere)
+    pub fn truncate(&mut self, position: usize) {
+        if self.done.len() > position {
+            self.done.truncate(position);
+            self.undone.clear();
+        }
+        self.open = false;
+    }
+
+    /// Undo or redo until `position` commands are applied
+    pub fn jump_to(&mut self, position: usize, world: &mut World) {
+        while self.position() > position && self.undo(world) {}
+        while self.position() < position && self.redo(world) {}
+    }
+
+    /// Labels of all commands in order, applied ones first
+    pub fn labels(&self) -> impl Iterator<Item = String> + '_ {
+        self.done.iter().chain(self.undone.iter().rev()).map(|c| c.label())
+    }
+}
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Show the command history in a list, clicking an entry undoes or redoes up to it
+///
+/// Item ids are history positions, "(start)" is the state before any command.
+/// Only the last few applied commands and the redo stack are listed.
+pub fn sync_history_panel(tree: &mut TreeView, history: &mut CommandHistory, world: &mut World, context: &Arc<VulkanContext>) -> Result<()> {
+    if let Some(Some(position)) = tree.take_selection_change() {
+        history.jump_to(position as usize, world);
+    }
+
+    let position = history.position();
+    let first = position.saturating_sub(PANEL_CONTEXT);
+    let mut items = Vec::new();
+    if first == 0 {
+        items.push(TreeItem { id: 0, label: "(start)".to_string(), depth: 0, has_children: false });
+    }
+    for (index, label) in history.labels().enumerate().skip(first) {
+        let label = if index < position { label } else { format!("{} (undone)", label) };
+        items.push(TreeItem { id: index as u64 + 1, label, depth: 0, has_children: false });
+    }
+    tree.set_items(items);
+    tree.set_selected(Some(position as u64));
+
+    tree.refresh(context)
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use engine::ecs::{ComponentRegistry, EntityId, FieldValue, World};
+use engine::gui::{Property, PropertyGrid, PropertyValue};
+use engine::renderer::VulkanContext;
+use std::sync::Arc;
+
+use crate::commands::SetField;
+use crate::history::CommandHistory;
+use crate::selection::Selection;
+
+const VEC2_AXES: [&str; 2] = ["x", "y"];
+
+/// Property rows for every reflected component of an entity
+///
+/// Keys are "Component.field" with an extra ".x"/".y" suffix for vector fields, which
+/// are edited one axis at a time. Colors use a single row with a color picker.
+fn build_properties(registry: &ComponentRegistry, world: &World, entity: EntityId) -> Vec<Property> {
+    let mut properties = Vec::new();
+    for component in registry.reflect(world, entity) {
+        properties.push(Property::header(component.name, component.name));
+        for (field, value) in component.fields {
+            let key = format!("{}.{}", component.name, field);
+            match value {
+                FieldValue::Float(v) => properties.push(Property::float(&key, field, v)),
+                FieldValue::Bool(v) => properties.push(Property::boolean(&key, field, v)),
+                FieldValue::Vec2(v) => {
+                    for (axis, v) in VEC2_AXES.iter().zip([v.x, v.y]) {
+                        let label = format!("{} {}", field, axis);
+                        properties.push(Property::float(&format!("{}.{}", key, axis), &label, v));
+                    }
+                }
+                FieldValue::Color(rgb) => properties.push(Property::color(&key, field, rgb)),
+            }
+        }
+    }
+    properties
+}
+
+/// Turn a single property edit into a command writing the component it came from
+fn edit_command(registry: &ComponentRegistry, world: &World, entity: EntityId, key: &str, value: PropertyValue) -> Option<SetField> {
+    let mut parts = key.split('.');
+    let (Some(component), Some(field)) = (parts.next(), parts.next()) else {
+        return None;
+    };
+    let channel = parts.next();
+
+    let current = registry
+        .reflect(world, entity)
+        .into_iter()
+        .find(|c| c.name == component)
+        .and_then(|c| c.fields.into_iter().find(|(name, _)| *name == field))
+        .map(|(_, value)| value);
+
+    let new_value = match (current, channel, value) {
+        (Some(FieldValue::Float(_)), None, PropertyValue::Float(v)) => FieldValue::Float(v),
+        (Some(FieldValue::Bool(_)), None, PropertyValue::Bool(v)) => FieldValue::Bool(v),
+        (Some(FieldValue::Vec2(mut vec)), Some(axis), PropertyValue::Float(v)) => {
+            match axis {
+                "x" => vec.x = v,
+                "y" => vec.y = v,
+                _ => return None,
+            }
+            FieldValue::Vec2(vec)
+        }
+        (Some(FieldValue::Color(_)), None, PropertyValue::Color(rgb)) => FieldValue::Color(rgb),
+        _ => return None,
+    };
+
+    Some(SetField::new(entity, component, field, current?, new_value))
+}
+
+/// Show the selected entity's components in the inspector and write user edits back into the World
+/// Edits go through the command history so they can be undone
+pub fn sync_inspector(grid: &mut PropertyGrid, world: &mut World, history: &mut CommandHistory, context: &Arc<VulkanContext>) -> Result<()> {
+    let selected = world.resource::<Selection>().and_then(Selection::get).filter(|&id| world.contains(id));
+    let edits = grid.take_edits();
+    let Some(entity) = selected else {
+        grid.set_properties(Vec::new());
+        return grid.refresh(context);
+    };
+
+    // One at a time, so several channels of the same field edited in a frame all stick
+    for (key, value) in edits {
+        let command = world
+            .resource::<ComponentRegistry>()
+            .and_then(|registry| edit_command(registry, world, entity, &key, value));
+        match command {
+            Some(command) => {
+                if !history.execute(Box::new(command), world) {
+                    log::warn!("Inspector could not write {}", key);
+                }
+            }
+            None => log::warn!("Inspector could not edit {}", key),
+        }
+    }
+
+    let properties = world
+        .resource::<ComponentRegistry>()
+        .map(|registry| build_properties(registry, world, entity))
+        .unwrap_or_default();
+    grid.set_properties(properties);
+    grid.refresh(context)
+}
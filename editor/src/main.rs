@@ -1,35 +1,86 @@
 use anyhow::Result;
+use glam::Vec3;
 use engine::{
-    gui::{ButtonComponent, PanelComponent, ContainerPanel, ComponentRef, UISystem, LayoutSpec, SizeSpec, HAlign, VAlign, TextComponent},
-    renderer::{Renderer, VulkanContext, FontAtlas},
-    window::EventLoop,
+    gui::{AccessTree, AntiAliasing, Bloom, ButtonComponent, ContainerPanel, ComponentRef, ConsoleComponent, CurveEditor, ContextMenu, FloatingWindow, GUIComponent, GradientDirection, GradientEditor, InputState, LayoutInspector, MenuBar, MenuItem, MinimapComponent, PanelBackground, PlotComponent, PlotStyle, ProfilerOverlay, PropertyGrid, StatsOverlay, TreeView, ViewportComponent, UISystem, LayoutSpec, SizeSpec, HAlign, VAlign, TextComponent, Rect, Vec2, WorldAnchor, WorldWidgetHandle},
+    config::EngineConfig,
+    cvars::{CVarValue, CVars},
+    ecs::{load_scene, run_state_machines, update_particles, update_spatial_index, update_timers, update_trails, Camera, ComponentRegistry, EntityId, ParticleEmitter, Schedule, Sprite, StateMachine, Timers, TrailRenderer, World},
+    math::{coords, Color, Gradient, Transform},
+    crash, logging, profiler,
+    renderer::{DebugLines, Recovery, Renderer, VulkanContext, FontAtlas},
+    storage::Settings,
+    window::{Lifecycle, LifecycleChange, ResizeTracker},
 };
+use accesskit_winit::ActionRequestEvent;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use std::sync::Arc;
 use winit::{
-    dpi::PhysicalSize,
-    event::{Event, WindowEvent},
-    window::WindowBuilder,
+    event::{ElementState, Event, MouseButton, MouseScrollDelta, StartCause, WindowEvent},
+    event_loop::{ControlFlow, EventLoopBuilder},
+    keyboard::{Key, ModifiersState, NamedKey},
 };
 
+mod commands;
+mod gizmo;
+mod hierarchy;
+mod history;
+mod inspector;
+mod particle_panel;
+mod play_mode;
+mod selection;
+
+use commands::{EditorAction, SetTransform};
+use gizmo::{Gizmo, GizmoMode};
+use history::CommandHistory;
+use play_mode::{PlayMode, PlayState};
+use selection::Selection;
+
+/// Directory name for the editor's settings
+const APP_NAME: &str = "engine-editor";
+
 fn main() -> Result<()> {
-    let event_loop = EventLoop::new()?;
+    logging::init(log::LevelFilter::Debug);
+    // Release builds tell the user where the report went
+    crash::install(APP_NAME, !cfg!(debug_assertions));
+    let mut settings = Settings::load(APP_NAME);
+    // Tunable values, edited from the console and the tweak panel, saved with the settings
+    let cvars = Rc::new(RefCell::new(CVars::new()));
+    if let Err(e) = cvars.borrow_mut().load(APP_NAME) {
+        log::warn!("{}, using default cvars", e);
+    }
+    // The user's window size and vsync, overridden by engine.toml and the command line
+    let mut config = EngineConfig {
+        title: "Vulkan Engine".to_string(),
+        window_width: settings.window_width,
+        window_height: settings.window_height,
+        vsync: settings.vsync,
+        ..EngineConfig::default()
+    };
+    config.apply_args(std::env::args().skip(1))?;
 
-    let window = WindowBuilder::new()
-        .with_title("Vulkan Engine")
-        .with_inner_size(PhysicalSize::new(1280, 720))
-        .build(&event_loop)?;
+    // Screen reader requests arrive as user events
+    let event_loop = EventLoopBuilder::<ActionRequestEvent>::with_user_event().build()?;
+
+    // Hidden until the accessibility adapter exists, it must be created before the window is shown
+    let window = config.window_builder().with_visible(false).build(&event_loop)?;
+    let access_adapter = accesskit_winit::Adapter::new(&window, || AccessTree::initial(APP_NAME), event_loop.create_proxy());
+    window.set_visible(true);
     let window = Arc::new(window);
 
     let window_size = window.inner_size();
-    println!(
+    log::info!(
         "Window physical size: {}x{} (DPI scale factor: {:.2})",
         window_size.width,
         window_size.height,
         window.scale_factor()
     );
 
-    let context = Arc::new(VulkanContext::new(window.clone())?);
+    let context = Arc::new(VulkanContext::with_validation(window.clone(), config.validation)?);
     let mut renderer = Some(Renderer::new(context.clone(), window_size.width, window_size.height)?);
+    if let Some(renderer) = renderer.as_mut() {
+        renderer.set_vsync(config.vsync);
+    }
 
     // Get the shared descriptor_set_layout for text rendering from the pipeline manager
     // This avoids creating redundant layouts - all TextComponents share this one layout
@@ -37,176 +88,661 @@ fn main() -> Result<()> {
         .get_descriptor_set_layout(engine::renderer::PipelineId::Text)
         .expect("Text pipeline should have descriptor_set_layout");
 
-    // Load font atlas at exact target font size
-    let font_atlas: Arc<FontAtlas> = Arc::new(FontAtlas::load(
-        "./assets/segoeui.ttf",
-        18.0,  // Font size in pixels
-        &context.device,
-        &context.instance,
-        context.physical_device,
-        context.queue_family_indices[0],
-    )?);
+    // Load font atlas at exact target font size, the renderer destroys it on shutdown
+    let font = renderer.as_mut().unwrap().load_font(&config.asset("segoeui.ttf").to_string_lossy(), 18.0)?;
+    let font_atlas: Arc<FontAtlas> = renderer.as_ref().unwrap().font(font).expect("Font was just loaded");
 
     //Entity1thisissometext
 
+    // The configured scene, or a demo one
+    let mut world = World::new();
+    world.insert_resource(Selection::default());
+    world.insert_resource(ComponentRegistry::with_engine_components());
+    world.insert_resource(Timers::new());
+    match config.scene_path() {
+        Some(scene) => {
+            let registry = world.remove_resource::<ComponentRegistry>().expect("Inserted above");
+            let result = load_scene(&mut world, &registry, &scene);
+            world.insert_resource(registry);
+            result?;
+        }
+        None => spawn_demo(&mut world),
+    }
+
+    // Game systems run while playing
+    let mut schedule = Schedule::new();
+    schedule.add_fn("timers", update_timers);
+    schedule.add_fn("state_machines", run_state_machines);
+    let mut play_mode = PlayMode::new();
+
     let mut ui = UISystem::new();
+    ui.set_context_menu(ContextMenu::new(&context, font_atlas.clone(), 18.0, text_descriptor_layout));
+    ui.set_layout_inspector(LayoutInspector::new(&context, font_atlas.clone(), 14.0, text_descriptor_layout)?);
+
+    // Offscreen scene views and cached panels draw their targets with the Image pipeline
+    let image_descriptor_layout = renderer.as_ref().unwrap()
+        .get_descriptor_set_layout(engine::renderer::PipelineId::Image)
+        .expect("Image pipeline should have descriptor_set_layout");
+    let color_format = renderer.as_ref().unwrap().color_format();
 
     // === MENU BAR (File, Edit, View, Help) ===
     let menu_row = ui.grid.add_row();
-    let mut menu_container = ContainerPanel::new(&context, [0.08, 0.08, 0.12])?;
+    let mut menu_container = ContainerPanel::new(&context, Color::WHITE)?;
+    // Only changes on hover and clicks, drawn from a texture in between
+    menu_container.enable_cache(&context, image_descriptor_layout)?;
+    let menu_gradient = Gradient::two(Color::srgb(0.12, 0.12, 0.17), Color::srgb(0.06, 0.06, 0.09));
+    menu_container.background_mut().set_background(&context, PanelBackground::Gradient(menu_gradient, GradientDirection::Vertical))?;
     
     // Create a single row in the menu container for horizontal layout
     let menu_items_row = menu_container.grid_mut().add_row();
     
-    // Create menu buttons
-    let mut file_button = ButtonComponent::new(&context, [0.2, 0.2, 0.22])?;
-    file_button.set_text(TextComponent::new("File", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
-    
-    let mut edit_button = ButtonComponent::new(&context, [0.2, 0.2, 0.22])?;
-    edit_button.set_text(TextComponent::new("Edit", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
-    
-    let mut view_button = ButtonComponent::new(&context, [0.2, 0.2, 0.22])?;
-    view_button.set_text(TextComponent::new("View", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
-    
-    let mut help_button = ButtonComponent::new(&context, [0.2, 0.2, 0.22])?;
-    help_button.set_text(TextComponent::new("Help", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
-    
-    // Menu button spec
+    // Menus, chosen items are handled by their action name at the start of the next redraw
+    let can_capture = renderer.as_ref().is_some_and(Renderer::can_capture);
+    let menu_bar = MenuBar::new(&context, font_atlas.clone(), 18.0, text_descriptor_layout, vec![
+        ("File", vec![
+            MenuItem::action("Save settings", "save_settings"),
+            MenuItem::separator(),
+            MenuItem::action("Quit", "quit"),
+        ]),
+        ("Edit", vec![
+            MenuItem::action("Undo", "undo").with_shortcut("Ctrl+Z"),
+            MenuItem::action("Redo", "redo").with_shortcut("Ctrl+Shift+Z"),
+            MenuItem::separator(),
+            MenuItem::action("New entity", "spawn"),
+            MenuItem::action("Delete", "delete").with_shortcut("Del"),
+        ]),
+        ("View", vec![
+            MenuItem::submenu("Panels", vec![
+                MenuItem::action("Console", "toggle_console").with_shortcut("`"),
+                MenuItem::action("Statistics", "toggle_stats").with_shortcut("F2"),
+                MenuItem::action("Profiler", "toggle_profiler").with_shortcut("F3"),
+                MenuItem::action("Frame times", "toggle_frame_times"),
+            ]),
+            MenuItem::action("Toggle 2D/3D", "toggle_camera").with_shortcut("F4"),
+            MenuItem::separator(),
+            MenuItem::action("Capture frame", "capture").with_shortcut("F12").with_enabled(can_capture),
+        ]),
+        ("Help", vec![MenuItem::action("About", "about")]),
+    ])?;
+    let menu_bar_width = menu_bar.measure(Vec2::splat(f32::MAX)).x;
+    let menu_bar_spec = LayoutSpec::new(SizeSpec::Fixed(menu_bar_width), SizeSpec::Percent(1.0))
+        .with_alignment(HAlign::Left, VAlign::Middle);
+    let menu_bar_handle = menu_container.grid_mut().add(menu_items_row, menu_bar, menu_bar_spec)?;
+
+    // Toolbar button spec
     let menu_button_spec = LayoutSpec::new(SizeSpec::Fixed(70.0), SizeSpec::Percent(1.0))
         .with_alignment(HAlign::Left, VAlign::Middle)
         .with_padding(0.0)
         .with_margin(0.0);
+
+    // Play mode toolbar
+    let mut play_button = ButtonComponent::new(&context, Color::srgb(0.2, 0.3, 0.22))?;
+    play_button.set_text(TextComponent::new("Play", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
+    let mut pause_button = ButtonComponent::new(&context, Color::srgb(0.2, 0.2, 0.22))?;
+    pause_button.set_text(TextComponent::new("Pause", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
+    let mut back_button = ButtonComponent::new(&context, Color::srgb(0.2, 0.2, 0.22))?;
+    back_button.set_text(TextComponent::new("Back", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
+    let mut step_button = ButtonComponent::new(&context, Color::srgb(0.2, 0.2, 0.22))?;
+    step_button.set_text(TextComponent::new("Step", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
+    let play_handle = menu_container.grid_mut().add(menu_items_row, play_button, menu_button_spec)?;
+    let pause_handle = menu_container.grid_mut().add(menu_items_row, pause_button, menu_button_spec)?;
+    let back_handle = menu_container.grid_mut().add(menu_items_row, back_button, menu_button_spec)?;
+    let step_handle = menu_container.grid_mut().add(menu_items_row, step_button, menu_button_spec)?;
     
-    menu_container.grid_mut().get_row_mut(menu_items_row).unwrap().add_component(Box::new(file_button), menu_button_spec);
-    menu_container.grid_mut().get_row_mut(menu_items_row).unwrap().add_component(Box::new(edit_button), menu_button_spec);
-    menu_container.grid_mut().get_row_mut(menu_items_row).unwrap().add_component(Box::new(view_button), menu_button_spec);
-    menu_container.grid_mut().get_row_mut(menu_items_row).unwrap().add_component(Box::new(help_button), menu_button_spec);
-    
-    // Wrap menu container
-    let (menu_wrapper, menu_handle) = ComponentRef::new(menu_container);
-    
-    let menu_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(18.0))
+    // Nested containers lay out their children when the outer grid lays them out
+    let menu_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(22.0))
         .with_alignment(HAlign::Left, VAlign::Top);
-    ui.grid.get_row_mut(menu_row).unwrap().add_component(Box::new(menu_wrapper), menu_spec);
+    let menu_handle = ui.grid.add(menu_row, menu_container, menu_spec)?;
 
     // === MAIN ROW: Left sidebar + Right content ===
     let main_row = ui.grid.add_row();
 
     // LEFT SIDEBAR CONTAINER (takes ~20% width)
-    let mut left_container = ContainerPanel::new(&context, [0.15, 0.15, 0.2])?;
+    let mut left_container = ContainerPanel::new(&context, Color::srgb(0.15, 0.15, 0.2))?;
     
-    // Add 3 rows to the sidebar container for ECS items
-    let sidebar_row1 = left_container.grid_mut().add_row();
-    let sidebar_row2 = left_container.grid_mut().add_row();
-    let sidebar_row3 = left_container.grid_mut().add_row();
-
-    // Create ECS buttons with text
-    let mut ecs_button1 = ButtonComponent::new(&context, [0.8, 0.0, 0.4])?;
-    ecs_button1.set_text(TextComponent::new("FPS: 0.0", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
-    let (fps_button_wrapper, fps_button) = ComponentRef::new(ecs_button1);
-    
-    let mut ecs_button2 = ButtonComponent::new(&context, [0.8, 0.0, 0.4])?;
-    ecs_button2.set_text(TextComponent::new("Entity 2", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
+    // Sidebar rows: entity hierarchy and inspector (share the remaining height), particles, history, cvars, stats, minimap, profiler
+    let sidebar_hierarchy_row = left_container.grid_mut().add_row();
+    let sidebar_inspector_row = left_container.grid_mut().add_row();
+    let sidebar_particle_shape_row = left_container.grid_mut().add_row();
+    let sidebar_particle_size_row = left_container.grid_mut().add_row();
+    let sidebar_particle_color_row = left_container.grid_mut().add_row();
+    let sidebar_history_row = left_container.grid_mut().add_row();
+    let sidebar_tweaks_row = left_container.grid_mut().add_row();
+    let sidebar_stats_row = left_container.grid_mut().add_row();
+    let sidebar_minimap_row = left_container.grid_mut().add_row();
+    let sidebar_profiler_row = left_container.grid_mut().add_row();
 
-    let mut ecs_button3 = ButtonComponent::new(&context, [0.8, 0.0, 0.4])?;
-    ecs_button3.set_text(TextComponent::new("Entity 3", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
+    // Entity hierarchy backed by the World
+    let hierarchy = TreeView::new(font_atlas.clone(), 18.0, text_descriptor_layout);
+    let (mut hierarchy_wrapper, hierarchy_handle) = ComponentRef::new(hierarchy);
+    hierarchy_wrapper.set_context_menu(vec![
+        MenuItem::action("New entity", "spawn"),
+        MenuItem::action("Delete", "delete").with_shortcut("Del"),
+    ]);
+    let hierarchy_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fraction(1.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    left_container.grid_mut().add(sidebar_hierarchy_row, hierarchy_wrapper, hierarchy_spec)?;
 
-    // Add buttons to sidebar rows
-    let button_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(30.0))
-        .with_alignment(HAlign::Center, VAlign::Top)
-        .with_padding(0.0)
-        .with_margin(0.0);
-    
-    left_container.grid_mut().get_row_mut(sidebar_row1).unwrap().add_component(Box::new(fps_button_wrapper), button_spec);
-    left_container.grid_mut().get_row_mut(sidebar_row2).unwrap().add_component(Box::new(ecs_button2), button_spec);
-    left_container.grid_mut().get_row_mut(sidebar_row3).unwrap().add_component(Box::new(ecs_button3), button_spec);
+    // Component inspector for the selected entity
+    let inspector = PropertyGrid::new(font_atlas.clone(), 18.0, text_descriptor_layout);
+    let (inspector_wrapper, inspector_handle) = ComponentRef::new(inspector);
+    let inspector_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fraction(1.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    left_container.grid_mut().add(sidebar_inspector_row, inspector_wrapper, inspector_spec)?;
+
+    // Particle emitter of the selected entity: shape (click to cycle), size over lifetime, color over lifetime
+    let mut particle_shape_button = ButtonComponent::new(&context, Color::srgb(0.2, 0.2, 0.22))?;
+    particle_shape_button.set_text(TextComponent::new("No emitter", font_atlas.clone(), 18.0, text_descriptor_layout, &context)?);
+    let (particle_shape_wrapper, particle_shape_handle) = ComponentRef::new(particle_shape_button);
+    let particle_shape_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(22.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    left_container.grid_mut().add(sidebar_particle_shape_row, particle_shape_wrapper, particle_shape_spec)?;
+    let (particle_size_wrapper, particle_size_handle) = ComponentRef::new(CurveEditor::new(&context, Default::default())?);
+    let particle_size_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(90.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    left_container.grid_mut().add(sidebar_particle_size_row, particle_size_wrapper, particle_size_spec)?;
+    let (particle_color_wrapper, particle_color_handle) = ComponentRef::new(GradientEditor::new(&context, Default::default())?);
+    let particle_color_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(32.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    left_container.grid_mut().add(sidebar_particle_color_row, particle_color_wrapper, particle_color_spec)?;
+
+    // Undo history, click an entry to go back or forward to it
+    let history_panel = TreeView::new(font_atlas.clone(), 18.0, text_descriptor_layout);
+    let (history_wrapper, history_handle) = ComponentRef::new(history_panel);
+    let history_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(144.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    left_container.grid_mut().add(sidebar_history_row, history_wrapper, history_spec)?;
+
+    // Tweak panel with a row per cvar
+    let tweaks = PropertyGrid::new(font_atlas.clone(), 18.0, text_descriptor_layout);
+    let (tweaks_wrapper, tweaks_handle) = ComponentRef::new(tweaks);
+    let tweaks_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(120.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    left_container.grid_mut().add(sidebar_tweaks_row, tweaks_wrapper, tweaks_spec)?;
+
+    // Statistics overlay (toggle with F2)
+    let stats_overlay = StatsOverlay::new(&context, font_atlas.clone(), 18.0, text_descriptor_layout)?;
+    let (stats_wrapper, stats_handle) = ComponentRef::new(stats_overlay);
+    let stats_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(120.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    left_container.grid_mut().add(sidebar_stats_row, stats_wrapper, stats_spec)?;
+
+    // Frame times of the last redraws in a tool window, hover to read one
+    let mut frame_plot = PlotComponent::new(&context, font_atlas.clone(), 18.0, text_descriptor_layout, 120)?;
+    frame_plot.set_style(PlotStyle::Bars);
+    frame_plot.set_range(Some(0.0), None);
+    frame_plot.set_label("Frame", "ms");
+    let (frame_plot_wrapper, frame_plot_handle) = ComponentRef::new(frame_plot);
+    let frame_window_rect = Rect::new(320.0, 40.0, 320.0, 120.0);
+    let mut frame_window = FloatingWindow::new(&context, "Frame times", font_atlas.clone(), 16.0, text_descriptor_layout, frame_window_rect)?;
+    let frame_plot_row = frame_window.grid_mut().add_row();
+    let frame_plot_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fraction(1.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    frame_window.grid_mut().add(frame_plot_row, frame_plot_wrapper, frame_plot_spec)?;
+    let frame_window_handle = ui.windows.add(frame_window);
+
+    // Overview of the scene around the origin, click or drag to move the viewport there
+    let mut minimap = MinimapComponent::new(&context, image_descriptor_layout, color_format)?;
+    minimap.camera_mut().zoom = 4.0;
+    let (minimap_wrapper, minimap_handle) = ComponentRef::new(minimap);
+    let minimap_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(120.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    left_container.grid_mut().add(sidebar_minimap_row, minimap_wrapper, minimap_spec)?;
+
+    // CPU profiler overlay at the bottom of the sidebar (toggle with F3 or `profiler`)
+    let profiler_overlay = ProfilerOverlay::new(&context, font_atlas.clone(), 18.0, text_descriptor_layout)?;
+    let (profiler_wrapper, profiler_handle) = ComponentRef::new(profiler_overlay);
+    let profiler_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(160.0))
+        .with_alignment(HAlign::Center, VAlign::Top);
+    left_container.grid_mut().add(sidebar_profiler_row, profiler_wrapper, profiler_spec)?;
 
-    // Wrap container for external access
-    let (container_wrapper, container_handle) = ComponentRef::new(left_container);
-    
     // Add left container to main row
-    let left_container_spec = LayoutSpec::new(SizeSpec::Percent(0.15), SizeSpec::Percent(1.0))
+    let left_container_spec = LayoutSpec::new(SizeSpec::Percent(0.15), SizeSpec::Fraction(1.0))
         .with_alignment(HAlign::Left, VAlign::Middle);
     
-    ui.grid.get_row_mut(main_row).unwrap().add_component(
-        Box::new(container_wrapper),
-        left_container_spec,
-    );
+    ui.grid.add(main_row, left_container, left_container_spec)?;
 
-    // RIGHT CONTENT PANEL (takes ~80% width)
-    let right_panel = PanelComponent::new(&context, [0.3, 0.3, 0.35])?;
-    let right_panel_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Percent(1.0))
+    // RIGHT CONTENT: scene viewport (the remaining width)
+    // Middle drag pans, right drag orbits, wheel zooms, F4 switches between 2D and 3D
+    let viewport = ViewportComponent::new(&context, image_descriptor_layout, color_format)?;
+    let (viewport_wrapper, viewport_handle) = ComponentRef::new(viewport);
+    let viewport_spec = LayoutSpec::new(SizeSpec::Fraction(1.0), SizeSpec::Fraction(1.0))
         .with_alignment(HAlign::Center, VAlign::Middle);
-    ui.grid.get_row_mut(main_row).unwrap().add_component(Box::new(right_panel), right_panel_spec);
+    ui.grid.add(main_row, viewport_wrapper, viewport_spec)?;
 
-    // Set initial bounds
-    ui.grid.set_bounds(0.0, 0.0, window_size.width as f32, window_size.height as f32);
-    
-    // Update nested container layouts
-    menu_handle.borrow_mut().update_grid_layout();
-    container_handle.borrow_mut().update_grid_layout();
+    {
+        let mut cvars = cvars.borrow_mut();
+        cvars.register_color("viewport.clear_color", viewport_handle.borrow().clear_color(), "background of the scene viewport");
+        cvars.register_float_range("camera.fov", 60.0, 20.0, 120.0, "vertical field of view of the 3D camera in degrees");
+        cvars.register_bool("stats.visible", true, "show the statistics overlay");
+        cvars.register_bool("viewport.fxaa", false, "smooth the edges of the scene with FXAA");
+        cvars.register_float_range("bloom.intensity", 0.0, 0.0, 4.0, "glow added around bright parts of the scene, 0 turns bloom off");
+        cvars.register_float_range("bloom.threshold", Bloom::new().threshold, 0.0, 1.0, "brightness above which the scene glows");
+        cvars.on_change("viewport.clear_color", {
+            let viewport_handle = viewport_handle.clone();
+            move |value| if let CVarValue::Color(color) = value {
+                viewport_handle.borrow_mut().set_clear_color(color);
+            }
+        })?;
+        cvars.on_change("camera.fov", {
+            let viewport_handle = viewport_handle.clone();
+            move |value| if let CVarValue::Float(degrees) = value {
+                viewport_handle.borrow_mut().camera_mut().fov = degrees.to_radians();
+            }
+        })?;
+        cvars.on_change("viewport.fxaa", {
+            let viewport_handle = viewport_handle.clone();
+            move |value| if let CVarValue::Bool(enabled) = value {
+                let anti_aliasing = if enabled { AntiAliasing::Fxaa } else { AntiAliasing::None };
+                viewport_handle.borrow_mut().set_anti_aliasing(anti_aliasing);
+            }
+        })?;
+        cvars.on_change("bloom.intensity", {
+            let viewport_handle = viewport_handle.clone();
+            move |value| if let CVarValue::Float(intensity) = value {
+                viewport_handle.borrow_mut().bloom_mut().intensity = intensity;
+            }
+        })?;
+        cvars.on_change("bloom.threshold", {
+            let viewport_handle = viewport_handle.clone();
+            move |value| if let CVarValue::Float(threshold) = value {
+                viewport_handle.borrow_mut().bloom_mut().threshold = threshold;
+            }
+        })?;
+        cvars.on_change("stats.visible", {
+            let stats_handle = stats_handle.clone();
+            move |value| if let CVarValue::Bool(visible) = value {
+                stats_handle.borrow_mut().set_visible(visible);
+            }
+        })?;
+    }
+
+    // === CONSOLE ROW (toggle with `) ===
+    let console_row = ui.grid.add_row();
+    let mut console = ConsoleComponent::new(&context, font_atlas.clone(), 18.0, text_descriptor_layout, 12)?;
+    console.register_cvars(cvars.clone());
+    let shared_fps = Rc::new(Cell::new(0.0f32));
+    console.register_command("fps", "print the current frame rate", {
+        let shared_fps = shared_fps.clone();
+        move |_| format!("FPS: {:.1}", shared_fps.get())
+    });
+    console.register_command("profiler", "toggle the CPU profiler overlay", {
+        let profiler_handle = profiler_handle.clone();
+        move |_| {
+            profiler_handle.borrow_mut().toggle_visible();
+            String::new()
+        }
+    });
+    console.register_command("trace", "trace start | trace stop <file.json> - chrome tracing capture", |args| {
+        match args {
+            ["start"] => {
+                profiler::set_trace_capture(true);
+                "Trace capture started".to_string()
+            }
+            ["stop", path] => {
+                profiler::set_trace_capture(false);
+                match profiler::write_chrome_trace(path) {
+                    Ok(()) => format!("Trace written to {}", path),
+                    Err(e) => format!("Trace failed: {}", e),
+                }
+            }
+            _ => "usage: trace start | trace stop <file.json>".to_string(),
+        }
+    });
+    // Scene editing commands are queued and run through the undo history on the next redraw
+    let pending_actions: Rc<RefCell<Vec<EditorAction>>> = Rc::new(RefCell::new(Vec::new()));
+    let queue_action = |action: EditorAction| {
+        let pending_actions = pending_actions.clone();
+        move |_: &[&str]| {
+            pending_actions.borrow_mut().push(action.clone());
+            String::new()
+        }
+    };
+    console.register_command("undo", "undo the last scene edit (Ctrl+Z)", queue_action(EditorAction::Undo));
+    console.register_command("redo", "redo the last undone edit (Ctrl+Shift+Z)", queue_action(EditorAction::Redo));
+    console.register_command("delete", "delete the selected entity (Delete)", queue_action(EditorAction::DeleteSelected));
+    console.register_command("spawn", "spawn [name] - create an entity under the selection", {
+        let pending_actions = pending_actions.clone();
+        move |args| {
+            let name = if args.is_empty() { "Entity".to_string() } else { args.join(" ") };
+            pending_actions.borrow_mut().push(EditorAction::Spawn(name));
+            String::new()
+        }
+    });
+    console.register_command("reparent", "reparent <name> | reparent none - move the selected entity", {
+        let pending_actions = pending_actions.clone();
+        move |args| match args {
+            [] => "usage: reparent <name> | reparent none".to_string(),
+            ["none"] => {
+                pending_actions.borrow_mut().push(EditorAction::ReparentSelected(None));
+                String::new()
+            }
+            _ => {
+                pending_actions.borrow_mut().push(EditorAction::ReparentSelected(Some(args.join(" "))));
+                String::new()
+            }
+        }
+    });
+    console.register_command("hex", "hex <#RRGGBB> - set the color picker open in the inspector", {
+        let inspector_handle = inspector_handle.clone();
+        move |args| {
+            let mut inspector = inspector_handle.borrow_mut();
+            let Some(picker) = inspector.open_color_picker() else {
+                return "No color picker is open".to_string();
+            };
+            match args {
+                [hex] if picker.set_hex(hex) => String::new(),
+                _ => "usage: hex <#RRGGBB>".to_string(),
+            }
+        }
+    });
+    let (console_wrapper, console_handle) = ComponentRef::new(console);
+    let console_spec = LayoutSpec::new(SizeSpec::Percent(1.0), SizeSpec::Fixed(180.0))
+        .with_alignment(HAlign::Left, VAlign::Bottom);
+    ui.grid.add(console_row, console_wrapper, console_spec)?;
+
+    // Set initial bounds, nested containers are laid out with it
+    ui.resize(window_size.width as f32, window_size.height as f32);
+    ui.set_resize_animation(Some(0.12));
 
-    println!("Vulkan Engine initialized!");
+    log::info!("Vulkan Engine initialized!");
 
     let mut frame_count = 0u32;
-    let mut last_resize_size: Option<(u32, u32)> = None;
+    let mut resizes = ResizeTracker::new();
+    // Surface loss while a mobile build is in the background
+    let mut lifecycle = Lifecycle::new();
     let mut mouse_pos = (0.0f32, 0.0f32);
+    // Pointer state for the UI's per-frame update
+    let mut input = InputState::new();
+    // Mouse button currently dragging the viewport camera
+    let mut camera_drag: Option<MouseButton> = None;
+    // Transform gizmo over the selected entity (W/E/R switch modes) and the lines it draws with
+    let mut gizmo = Gizmo::new();
+    let mut debug_lines = DebugLines::new(context.clone());
+    // Name of the selected entity floating above it in the viewport
+    let mut nameplate: Option<(EntityId, WorldWidgetHandle<TextComponent>)> = None;
+    // Undo/redo of scene edits, sealed after each mouse interaction so a drag is one entry
+    let mut history = CommandHistory::new();
+    let mut seal_history = false;
+    let mut modifiers = ModifiersState::empty();
     
     // FPS tracking
     let mut last_fps_update = std::time::Instant::now();
     let mut fps_frame_count = 0u32;
     let mut current_fps = 0.0f32;
+    let mut last_frame_time = std::time::Instant::now();
 
     event_loop.run(move |event, window_target| {
+        // Set by a close request or a renderer error that can't be recovered from
+        let mut shutdown = false;
+        if let Event::WindowEvent { event: window_event, .. } = &event {
+            access_adapter.process_event(&window, window_event);
+        }
+        match lifecycle.handle(&event) {
+            // The renderer was created with the window
+            Some(LifecycleChange::Started) | None => {}
+            Some(LifecycleChange::Suspended) => {
+                if let Some(ref mut r) = renderer {
+                    r.suspend();
+                }
+            }
+            Some(LifecycleChange::Resumed) => {
+                if let Some(ref mut r) = renderer {
+                    if let Err(e) = r.resume(&window) {
+                        log::error!("Could not resume rendering: {}", e);
+                        shutdown = true;
+                    }
+                }
+                window.request_redraw();
+            }
+        }
         match event {
             Event::WindowEvent {
                 event: window_event,
                 ..
             } => match window_event {
                 WindowEvent::CloseRequested => {
-                    // Clean up GPU resources in proper order before exiting
-                    unsafe { context.device.device_wait_idle().ok(); }
-                    ui.destroy(&context.device);
-                    font_atlas.destroy(&context.device);
-                    if let Some(r) = renderer.take() {
-                        drop(r);
-                    }
-                    window_target.exit();
+                    shutdown = true;
                 }
                 WindowEvent::Resized(new_size) => {
-                    last_resize_size = Some((new_size.width, new_size.height));
+                    resizes.resized(new_size.width, new_size.height);
                     window.request_redraw();
                 }
 
                 WindowEvent::CursorMoved { position, .. } => {
-                    let window_size = window.inner_size();
-                    let inverted_y = window_size.height as f32 - position.y as f32;
-                    mouse_pos = (position.x as f32, inverted_y);
-                    ui.handle_mouse_move(position.x as f32, inverted_y);
+                    let window_height = window.inner_size().height as f32;
+                    let ui_position = coords::window_to_ui(Vec2::new(position.x as f32, position.y as f32), window_height);
+                    let delta = ui_position - Vec2::new(mouse_pos.0, mouse_pos.1);
+                    mouse_pos = (ui_position.x, ui_position.y);
+                    input.mouse = ui_position;
+                    match camera_drag {
+                        Some(MouseButton::Middle) => viewport_handle.borrow_mut().camera_mut().pan(delta),
+                        Some(MouseButton::Right) => viewport_handle.borrow_mut().camera_mut().orbit(delta),
+                        _ => {}
+                    }
+                    let mouse = Vec2::new(mouse_pos.0, mouse_pos.1);
+                    // Only moves that change something on screen are worth a frame
+                    let mut changed = camera_drag.is_some();
+                    if gizmo.is_dragging() {
+                        gizmo.drag(&viewport_handle.borrow(), &mut world, mouse);
+                        changed = true;
+                    } else {
+                        let selected = world.resource::<Selection>().and_then(Selection::get);
+                        changed |= gizmo.hover(&viewport_handle.borrow(), &world, selected, mouse);
+                    }
+                    engine::profile_scope!("input");
+                    ui.handle_mouse_move(ui_position.x, ui_position.y);
+                    if ui.take_damage().is_some() || changed {
+                        window.request_redraw();
+                    }
+                }
+
+                WindowEvent::ModifiersChanged(new_modifiers) => {
+                    modifiers = new_modifiers.state();
+                }
+
+                WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
+                    engine::profile_scope!("input");
+                    // Open menus take the keys they navigate with
+                    if ui.handle_key(&event.logical_key) {
+                        window.request_redraw();
+                        return;
+                    }
+                    let command_key = modifiers.control_key() || modifiers.super_key();
+                    if let (true, Key::Character(c)) = (command_key, &event.logical_key) {
+                        if c.eq_ignore_ascii_case("z") && !gizmo.is_dragging() {
+                            let action = if modifiers.shift_key() { EditorAction::Redo } else { EditorAction::Undo };
+                            pending_actions.borrow_mut().push(action);
+                            window.request_redraw();
+                        }
+                        return;
+                    }
+                    if event.logical_key == Key::Named(NamedKey::F2) {
+                        cvars.borrow_mut().toggle("stats.visible").ok();
+                        window.request_redraw();
+                        return;
+                    }
+                    if event.logical_key == Key::Named(NamedKey::F3) {
+                        profiler_handle.borrow_mut().toggle_visible();
+                        window.request_redraw();
+                        return;
+                    }
+                    if event.logical_key == Key::Named(NamedKey::F4) {
+                        viewport_handle.borrow_mut().camera_mut().toggle_mode();
+                        window.request_redraw();
+                        return;
+                    }
+                    if event.logical_key == Key::Named(NamedKey::F6) {
+                        if let Some(inspector) = ui.layout_inspector_mut() {
+                            inspector.toggle_visible();
+                            window.request_redraw();
+                        }
+                        return;
+                    }
+                    if event.logical_key == Key::Named(NamedKey::F12) {
+                        if let Some(ref mut r) = renderer {
+                            if r.trigger_capture() {
+                                window.request_redraw();
+                            }
+                        }
+                        return;
+                    }
+                    let mut console = console_handle.borrow_mut();
+                    match &event.logical_key {
+                        Key::Character(c) if c.as_str() == "`" => console.toggle_visible(),
+                        Key::Character(c) if !console.is_visible() => {
+                            if settings.is_bound("gizmo_translate", c) {
+                                gizmo.set_mode(GizmoMode::Translate);
+                            } else if settings.is_bound("gizmo_rotate", c) {
+                                gizmo.set_mode(GizmoMode::Rotate);
+                            } else if settings.is_bound("gizmo_scale", c) {
+                                gizmo.set_mode(GizmoMode::Scale);
+                            }
+                        }
+                        Key::Named(NamedKey::Delete) if !console.is_visible() && !gizmo.is_dragging() => {
+                            pending_actions.borrow_mut().push(EditorAction::DeleteSelected);
+                        }
+                        _ if !console.is_visible() => {}
+                        Key::Named(NamedKey::Enter) => console.submit_input(),
+                        Key::Named(NamedKey::Backspace) => console.pop_input(),
+                        _ => {
+                            if let Some(text) = &event.text {
+                                console.push_input(text);
+                            }
+                        }
+                    }
                     window.request_redraw();
                 }
 
-                WindowEvent::MouseInput { state, .. } => match state {
+                WindowEvent::MouseInput { state, button, .. } => match state {
                     winit::event::ElementState::Pressed => {
-                        ui.handle_mouse_down(mouse_pos.0, mouse_pos.1);
+                        input.set_button(button, true);
+                        // A press while a menu is open only closes or uses the menu
+                        let in_viewport = !ui.has_popup() && viewport_handle.borrow().contains_point(Vec2::new(mouse_pos.0, mouse_pos.1));
+                        if in_viewport && matches!(button, MouseButton::Middle | MouseButton::Right) {
+                            camera_drag = Some(button);
+                        }
+                        if in_viewport && button == MouseButton::Left {
+                            // Clicks on a gizmo handle start a drag, anywhere else picks an entity
+                            let mouse = Vec2::new(mouse_pos.0, mouse_pos.1);
+                            let viewport = viewport_handle.borrow();
+                            let selected = world.resource::<Selection>().and_then(Selection::get);
+                            if !gizmo.begin_drag(&viewport, &world, selected, mouse) {
+                                let picked = match renderer.as_ref() {
+                                    Some(r) => viewport.pick(r, mouse).unwrap_or_else(|e| {
+                                        log::error!("Picking failed: {}", e);
+                                        None
+                                    }),
+                                    None => None,
+                                };
+                                if let Some(selection) = world.resource_mut::<Selection>() {
+                                    match picked {
+                                        Some(id) => selection.select(id),
+                                        None => selection.clear(),
+                                    }
+                                }
+                            }
+                        }
+                        let opened_menu = button == MouseButton::Right && !in_viewport && !ui.has_popup()
+                            && ui.open_context_menu(mouse_pos.0, mouse_pos.1).unwrap_or_else(|e| {
+                                log::error!("Failed to open context menu: {}", e);
+                                false
+                            });
+                        if !opened_menu {
+                            ui.handle_mouse_down(mouse_pos.0, mouse_pos.1);
+                        }
                         window.request_redraw();
                     }
 
                     winit::event::ElementState::Released => {
+                        input.set_button(button, false);
+                        if camera_drag == Some(button) {
+                            camera_drag = None;
+                        }
+                        if button == MouseButton::Left {
+                            if let Some(edit) = gizmo.end_drag(&world) {
+                                history.record(Box::new(SetTransform::new(&world, edit.entity, edit.before, edit.after)));
+                            }
+                            // Sealed after the next redraw, which still collects the last edits of a drag
+                            seal_history = true;
+                        }
                         ui.handle_mouse_up(mouse_pos.0, mouse_pos.1);
                         window.request_redraw();
                     }
                 },
 
+                WindowEvent::Touch(touch) => {
+                    let window_height = window.inner_size().height as f32;
+                    if ui.handle_touch(&touch, window_height) {
+                        window.request_redraw();
+                    }
+                }
+
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let mut viewport = viewport_handle.borrow_mut();
+                    if viewport.contains_point(Vec2::new(mouse_pos.0, mouse_pos.1)) {
+                        let steps = match delta {
+                            MouseScrollDelta::LineDelta(_, y) => y,
+                            MouseScrollDelta::PixelDelta(position) => position.y as f32 / 40.0,
+                        };
+                        viewport.camera_mut().zoom_by(steps);
+                        window.request_redraw();
+                    }
+                }
+
                 WindowEvent::RedrawRequested => {
-                    // Handle resize
-                    if let Some((width, height)) = last_resize_size.take() {
+                    profiler::begin_frame();
+
+                    // Handle resize, at most one size per interval while the window is dragged
+                    if let Some((width, height)) = resizes.take() {
                         if let Some(ref mut r) = renderer {
                             r.handle_resize(width, height, window.scale_factor() as f32);
                         }
-                        ui.grid.set_bounds(0.0, 0.0, width as f32, height as f32);
-                        menu_handle.borrow_mut().update_grid_layout();
-                        container_handle.borrow_mut().update_grid_layout();
+                        ui.resize(width as f32, height as f32);
+                    }
+
+                    let dt = last_frame_time.elapsed().as_secs_f32();
+                    last_frame_time = std::time::Instant::now();
+                    ui.update(dt, &input);
+
+                    // Items chosen in the menu bar or a context menu
+                    let menu_bar_action = ui.grid.get_mut(menu_handle)
+                        .map(ContainerPanel::grid_mut)
+                        .and_then(|menu| menu.get_mut(menu_bar_handle))
+                        .and_then(MenuBar::take_activated);
+                    for action in menu_bar_action.into_iter().chain(ui.take_menu_action()) {
+                        match action.as_str() {
+                            "save_settings" => {
+                                let size = window.inner_size();
+                                settings.window_width = size.width;
+                                settings.window_height = size.height;
+                                match settings.save(APP_NAME).and_then(|()| cvars.borrow().save(APP_NAME)) {
+                                    Ok(()) => log::info!("Settings saved"),
+                                    Err(e) => log::warn!("Could not save settings: {}", e),
+                                }
+                            }
+                            "quit" => shutdown = true,
+                            "undo" => pending_actions.borrow_mut().push(EditorAction::Undo),
+                            "redo" => pending_actions.borrow_mut().push(EditorAction::Redo),
+                            "spawn" => pending_actions.borrow_mut().push(EditorAction::Spawn("Entity".to_string())),
+                            "delete" => pending_actions.borrow_mut().push(EditorAction::DeleteSelected),
+                            "toggle_console" => console_handle.borrow_mut().toggle_visible(),
+                            "toggle_stats" => {
+                                cvars.borrow_mut().toggle("stats.visible").ok();
+                            }
+                            "toggle_profiler" => profiler_handle.borrow_mut().toggle_visible(),
+                            "toggle_frame_times" => {
+                                ui.windows.toggle(frame_window_handle);
+                            }
+                            "toggle_camera" => viewport_handle.borrow_mut().camera_mut().toggle_mode(),
+                            "capture" => {
+                                if let Some(ref mut r) = renderer {
+                                    r.trigger_capture();
+                                }
+                            }
+                            "about" => log::info!("{} {}", APP_NAME, env!("CARGO_PKG_VERSION")),
+                            _ => log::warn!("Unknown menu action '{}'", action),
+                        }
                     }
                     
                     // Update FPS counter
@@ -216,27 +752,218 @@ fn main() -> Result<()> {
                         current_fps = fps_frame_count as f32 / elapsed.as_secs_f32();
                         fps_frame_count = 0;
                         last_fps_update = std::time::Instant::now();
-                        
-                        // Update FPS button text
-                        let fps_text = format!("FPS: {:.1}", current_fps);
-                        fps_button.borrow_mut().update_text(&fps_text, &context).ok();
+                        shared_fps.set(current_fps);
                     }
 
+                    console_handle.borrow_mut().refresh(&context).ok();
+                    // Taken out first, actions may log to the console
+                    let actions = std::mem::take(&mut *pending_actions.borrow_mut());
+                    for action in actions {
+                        commands::run_action(action, &mut history, &mut world);
+                    }
+                    profiler_handle.borrow_mut().refresh(&context).ok();
+                    hierarchy::sync_hierarchy(&mut hierarchy_handle.borrow_mut(), &mut world, &context).ok();
+                    inspector::sync_inspector(&mut inspector_handle.borrow_mut(), &mut world, &mut history, &context).ok();
+                    cvars.borrow_mut().sync_panel(&mut tweaks_handle.borrow_mut(), &context).ok();
+                    particle_panel::sync_particle_panel(
+                        &mut particle_shape_handle.borrow_mut(),
+                        &mut particle_size_handle.borrow_mut(),
+                        &mut particle_color_handle.borrow_mut(),
+                        &mut world,
+                        &mut history,
+                        &context,
+                    ).ok();
+                    if seal_history {
+                        history.seal();
+                        seal_history = false;
+                    }
+                    history::sync_history_panel(&mut history_handle.borrow_mut(), &mut history, &mut world, &context).ok();
+                    // A new target size needs another frame to pick up the layout
+                    if viewport_handle.borrow_mut().refresh(&context).unwrap_or(false) {
+                        window.request_redraw();
+                    }
+                    if minimap_handle.borrow_mut().refresh(&context).unwrap_or(false) {
+                        window.request_redraw();
+                    }
+                    if let Some(focus) = minimap_handle.borrow_mut().take_clicked() {
+                        viewport_handle.borrow_mut().camera_mut().focus = focus;
+                    }
+
+                    // Play mode toolbar, Play doubles as Stop and Pause as Resume
+                    if let Some(toolbar) = ui.grid.get_mut(menu_handle).map(ContainerPanel::grid_mut) {
+                        if toolbar.get_mut(play_handle).is_some_and(ButtonComponent::take_clicked) {
+                            if play_mode.is_active() {
+                                play_mode.stop(&mut world, &mut history);
+                            } else {
+                                play_mode.play(&world, &mut history);
+                            }
+                        }
+                        if toolbar.get_mut(pause_handle).is_some_and(ButtonComponent::take_clicked) {
+                            play_mode.toggle_pause(&world, &mut history);
+                        }
+                        if toolbar.get_mut(back_handle).is_some_and(ButtonComponent::take_clicked) {
+                            play_mode.step_back(&mut world);
+                        }
+                        if toolbar.get_mut(step_handle).is_some_and(ButtonComponent::take_clicked) {
+                            play_mode.step(&world, &mut history);
+                        }
+                        let play_label = if play_mode.is_active() { "Stop" } else { "Play" };
+                        if let Some(play) = toolbar.get_mut(play_handle) {
+                            play.update_text(play_label, &context).ok();
+                        }
+                        let pause_label = if play_mode.state() == PlayState::Paused { "Resume" } else { "Pause" };
+                        if let Some(pause) = toolbar.get_mut(pause_handle) {
+                            pause.update_text(pause_label, &context).ok();
+                        }
+                    }
+                    play_mode.update(&mut schedule, &mut world, dt);
+                    // Every frame, scene edits move entities outside play mode too
+                    update_spatial_index(&mut world, dt);
+                    // Particles and trails also run while editing so they can be tuned live
+                    if play_mode.state() != PlayState::Paused {
+                        update_particles(&mut world, dt);
+                        update_trails(&mut world, dt);
+                    }
+                    if world.entity_ids().any(|id| world.has::<ParticleEmitter>(id) || world.has::<TrailRenderer>(id)) {
+                        window.request_redraw();
+                    }
+                    // Keep the game running without input
+                    if play_mode.state() == PlayState::Playing {
+                        window.request_redraw();
+                    }
+                    let selected = world.resource::<Selection>().and_then(Selection::get);
+                    if nameplate.map(|(entity, _)| entity) != selected {
+                        if let Some((_, label)) = nameplate.take() {
+                            ui.world_ui.remove(label);
+                        }
+                        if let Some(entity) = selected {
+                            let name = world.name(entity).unwrap_or_default();
+                            match TextComponent::new(name, font_atlas.clone(), 16.0, text_descriptor_layout, &context) {
+                                Ok(label) => {
+                                    // Above the top edge of an unscaled quad, kept inside the viewport
+                                    let anchor = WorldAnchor::entity(entity)
+                                        .with_offset(Vec3::new(0.0, 0.5, 0.0))
+                                        .with_screen_offset(Vec2::new(0.0, 12.0))
+                                        .with_clamp(4.0);
+                                    nameplate = Some((entity, ui.world_ui.add(label, anchor)));
+                                }
+                                Err(e) => log::warn!("Could not create nameplate: {}", e),
+                            }
+                        }
+                    }
+                    if let Some((entity, label)) = nameplate {
+                        let name = world.name(entity).unwrap_or_default().to_string();
+                        if let Some(label) = ui.world_ui.get_mut(label) {
+                            label.update_text(&name, &context).ok();
+                        }
+                    }
+                    {
+                        let viewport = viewport_handle.borrow();
+                        ui.world_ui.place(&world, viewport.view_projection(), viewport.rect());
+                    }
+                    if let Err(e) = viewport_handle.borrow_mut().update_trails(&context, &world) {
+                        log::error!("Failed to update trails: {}", e);
+                    }
+
+                    if let Some(ref r) = renderer {
+                        stats_handle.borrow_mut().set_entity_count(world.entity_count());
+                        stats_handle.borrow_mut().refresh(&context, dt, r.stats(), r.gpu_memory_used()).ok();
+                    }
+                    {
+                        let mut frame_plot = frame_plot_handle.borrow_mut();
+                        frame_plot.push(dt * 1000.0);
+                        frame_plot.refresh(&context).ok();
+                    }
+
+                    if let Err(e) = ui.update_geometry(&context) {
+                        log::error!("Failed to update UI geometry: {}", e);
+                    }
+                    // Only built while an assistive technology is listening
+                    access_adapter.update_if_active(|| ui.accessibility_tree(APP_NAME));
+
                     // Begin frame and render
                     if let Some(ref mut r) = renderer {
-                        if let Some(frame) = r.begin_frame() {
+                        let frame = match r.begin_frame() {
+                            Ok(frame) => frame,
+                            Err(error) => {
+                                shutdown = r.recover(error) == Recovery::Shutdown;
+                                if !shutdown {
+                                    window.request_redraw();
+                                }
+                                None
+                            }
+                        };
+                        if let Some(frame) = frame {
+                            {
+                                let viewport = viewport_handle.borrow();
+                                if let Some(targets) = viewport.scene_targets() {
+                                    let selected = world.resource::<Selection>().and_then(Selection::get);
+                                    gizmo.draw(&mut debug_lines, &viewport, &world, selected);
+                                    frame.render_to_targets(&targets, |ctx| {
+                                        ctx.begin_label("Scene");
+                                        let result = viewport.render_scene(ctx, r, &world);
+                                        ctx.end_label();
+                                        result?;
+                                        ctx.begin_label("Debug lines");
+                                        let result = debug_lines.flush(ctx, r, viewport.view_projection());
+                                        ctx.end_label();
+                                        result
+                                    }).ok();
+                                }
+                            }
+                            if let Err(e) = viewport_handle.borrow_mut().render_post(&frame, r, &context, &world) {
+                                log::error!("Failed to apply post effects: {}", e);
+                            }
+                            {
+                                let minimap = minimap_handle.borrow();
+                                if let Some(targets) = minimap.scene_targets() {
+                                    frame.render_to_targets(&targets, |ctx| {
+                                        ctx.begin_label("Minimap");
+                                        let result = minimap.render_scene(ctx, r, &world);
+                                        ctx.end_label();
+                                        result
+                                    }).ok();
+                                }
+                            }
+                            if let Err(e) = ui.render_offscreen(&frame, r, &context) {
+                                log::error!("Failed to render UI caches: {}", e);
+                            }
+                            // The whole UI is drawn, so pending damage is covered
+                            ui.take_damage();
+                            frame.render_ctx.begin_label("UI");
                             ui.render(&frame.render_ctx, r).ok();
+                            frame.render_ctx.end_label();
 
                             frame_count += 1;
                             if frame_count % 60 == 0 {
-                                println!("Frames: {} | FPS: {:.1}", frame_count, current_fps);
+                                log::debug!("Frames: {} | FPS: {:.1}", frame_count, current_fps);
                             }
                         }
                     }
+
+                    // Wake up for UI animations and held back resizes even without input
+                    match [ui.next_update(), resizes.next_update()].into_iter().flatten().reduce(f32::min) {
+                        Some(seconds) => window_target.set_control_flow(ControlFlow::WaitUntil(
+                            std::time::Instant::now() + std::time::Duration::from_secs_f32(seconds),
+                        )),
+                        None => window_target.set_control_flow(ControlFlow::Wait),
+                    }
+
+                    // Background work queued for this frame must be done before the next one
+                    engine::tasks::wait_frame();
+                    profiler::end_frame();
                 }
                 _ => {}
             },
 
+            Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
+                window.request_redraw();
+            }
+
+            Event::UserEvent(ActionRequestEvent { request, .. }) if ui.handle_access_action(&request) => {
+                window.request_redraw();
+            }
+
             //Event::AboutToWait => {
             //    window.request_redraw();
             //}            
@@ -244,7 +971,84 @@ fn main() -> Result<()> {
             // Later: add a flag here for continuous mode when game preview is active
             _ => {}
         }
+
+        if shutdown && renderer.is_some() {
+            let size = window.inner_size();
+            settings.window_width = size.width;
+            settings.window_height = size.height;
+            if let Err(e) = settings.save(APP_NAME) {
+                log::warn!("Could not save settings: {}", e);
+            }
+            if let Err(e) = cvars.borrow().save(APP_NAME) {
+                log::warn!("Could not save cvars: {}", e);
+            }
+
+            // Clean up GPU resources in proper order before exiting
+            unsafe { context.device().device_wait_idle().ok(); }
+            ui.destroy(context.device());
+            debug_lines.destroy(context.device());
+            if let Some(r) = renderer.take() {
+                drop(r);
+            }
+            window_target.exit();
+        }
     })?;
 
     Ok(())
 }
+
+/// A player with a camera and a ghost chasing them once they come close
+fn spawn_demo(world: &mut World) {
+    let player = world.spawn("Player");
+    world.insert(player, Transform { position: Vec2::new(-3.0, 0.0), ..Transform::new() });
+    world.insert(player, Sprite { color: Color::srgb(0.2, 0.45, 0.9) });
+    let camera = world.spawn_child(player, "Camera");
+    world.insert(camera, Transform { position: Vec2::new(0.0, 1.5), scale: Vec2::splat(0.5), ..Transform::new() });
+    world.insert(camera, Camera::default());
+    let ghost = world.spawn("Ghost");
+    world.insert(ghost, Transform { position: Vec2::new(3.0, 1.0), ..Transform::new() });
+    world.insert(ghost, Sprite { color: Color::srgb(0.8, 0.8, 0.85) });
+    let lantern = world.spawn_child(ghost, "Lantern");
+    world.insert(lantern, Transform { position: Vec2::new(0.0, 0.75), scale: Vec2::splat(0.25), ..Transform::new() });
+    world.insert(lantern, ParticleEmitter::default());
+    world.insert(lantern, TrailRenderer::default());
+    world.spawn("Level");
+
+    // The ghost spins in place until the player comes close, then chases them
+    let distance_to_player = move |world: &World, id| {
+        let position = |id| world.get::<Transform>(id).map(|t| t.position);
+        position(id).zip(position(player)).map_or(f32::MAX, |(a, b)| a.distance(b))
+    };
+    let ghost_ai = StateMachine::new("wander")
+        .on_update("wander", |world, id, dt| {
+            if let Some(transform) = world.get_mut::<Transform>(id) {
+                transform.rotation += dt;
+            }
+        })
+        .on_update("chase", move |world, id, dt| {
+            let Some(target) = world.get::<Transform>(player).map(|t| t.position) else {
+                return;
+            };
+            if let Some(transform) = world.get_mut::<Transform>(id) {
+                let step = (target - transform.position).clamp_length_max(1.5 * dt);
+                transform.position += step;
+            }
+        })
+        .on_enter("chase", move |world, _| {
+            log::info!("Ghost started chasing the player");
+            // The lantern flares up for a moment
+            if let Some(emitter) = world.get_mut::<ParticleEmitter>(lantern) {
+                emitter.settings.spawn_rate *= 4.0;
+            }
+            if let Some(timers) = world.resource_mut::<Timers>() {
+                timers.after_for(lantern, 0.5, move |world| {
+                    if let Some(emitter) = world.get_mut::<ParticleEmitter>(lantern) {
+                        emitter.settings.spawn_rate /= 4.0;
+                    }
+                });
+            }
+        })
+        .transition("wander", "chase", move |world, id| distance_to_player(world, id) < 4.0)
+        .transition("chase", "wander", move |world, id| distance_to_player(world, id) > 6.0);
+    world.insert(ghost, ghost_ai);
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use engine::ecs::{EmissionShape, ParticleEmitter, World};
+use engine::gui::{ButtonComponent, CurveEditor, GradientEditor};
+use engine::renderer::VulkanContext;
+use std::sync::Arc;
+
+use crate::commands::SetEmitterSettings;
+use crate::history::CommandHistory;
+use crate::selection::Selection;
+
+/// Show the selected emitter's shape, size curve and color gradient, writing user edits
+/// back through the command history. Scalar settings are edited in the inspector.
+pub fn sync_particle_panel(
+    shape_button: &mut ButtonComponent,
+    size_editor: &mut CurveEditor,
+    color_editor: &mut GradientEditor,
+    world: &mut World,
+    history: &mut CommandHistory,
+    context: &Arc<VulkanContext>,
+) -> Result<()> {
+    let shape_clicked = shape_button.take_clicked();
+    let size_edit = size_editor.take_changed();
+    let color_edit = color_editor.take_changed();
+
+    let selected = world.resource::<Selection>().and_then(Selection::get);
+    let Some((entity, before)) = selected.and_then(|id| Some((id, world.get::<ParticleEmitter>(id)?.settings.clone()))) else {
+        size_editor.set_visible(false);
+        color_editor.set_visible(false);
+        return shape_button.update_text("No emitter", context);
+    };
+
+    let mut after = before.clone();
+    if let Some(size) = size_edit {
+        after.size = size;
+    }
+    if let Some(color) = color_edit {
+        after.color = color;
+    }
+    if shape_clicked {
+        let next = EmissionShape::ALL.iter().position(|&shape| shape == after.shape).map_or(0, |i| i + 1);
+        after.shape = EmissionShape::ALL[next % EmissionShape::ALL.len()];
+    }
+    if after != before && !history.execute(Box::new(SetEmitterSettings::new(world, entity, before, after.clone())), world) {
+        log::warn!("Could not edit the particle emitter");
+    }
+
+    // Another emitter was selected or the settings were undone, show what's in the World now
+    let Some(settings) = world.get::<ParticleEmitter>(entity).map(|emitter| &emitter.settings) else {
+        return Ok(());
+    };
+    if size_editor.curve() != &settings.size {
+        let (_, max) = settings.size.value_range();
+        size_editor.set_curve(settings.size.clone());
+        size_editor.set_value_range(0.0, (max * 1.25).max(0.5));
+    }
+    if color_editor.gradient() != &settings.color {
+        color_editor.set_gradient(settings.color.clone());
+    }
+    size_editor.set_visible(true);
+    color_editor.set_visible(true);
+    shape_button.update_text(&format!("Shape: {}", settings.shape.name()), context)?;
+
+    size_editor.refresh(context)?;
+    color_editor.refresh(context)
+}
@@ -0,0 +1,161 @@
+use engine::ecs::{ComponentRegistry, Rewind, Schedule, Timers, World, WorldSnapshot};
+
+use crate::history::CommandHistory;
+
+/// Time step of a single Step while paused
+const STEP_SECONDS: f32 = 1.0 / 60.0;
+/// Longest frame fed to the systems, the editor idles between redraws before playing
+const MAX_FRAME_SECONDS: f32 = 0.1;
+/// Played frames that can be stepped back through, 10 seconds at 60 fps
+const REWIND_FRAMES: usize = 600;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayState {
+    Editing,
+    Playing,
+    Paused,
+}
+
+/// Runs the game systems on the edited World and puts the World back when stopped
+///
+/// Starting to play snapshots the World and locks the undo history, stopping restores
+/// the snapshot and drops the commands recorded while playing. The registered components
+/// of each played frame are recorded too, so Back can step through them while paused.
+pub struct PlayMode {
+    state: PlayState,
+    snapshot: Option<WorldSnapshot>,
+    rewind: Rewind,
+    /// Frames run since play started
+    frame: u32,
+    /// History position when play started
+    history_position: usize,
+    step_requested: bool,
+}
+
+impl PlayMode {
+    pub fn new() -> Self {
+        PlayMode {
+            state: PlayState::Editing,
+            snapshot: None,
+            rewind: Rewind::new(REWIND_FRAMES),
+            frame: 0,
+            history_position: 0,
+            step_requested: false,
+        }
+    }
+
+    pub fn state(&self) -> PlayState {
+        self.state
+    }
+
+    /// Playing or paused
+    pub fn is_active(&self) -> bool {
+        self.state != PlayState::Editing
+    }
+
+    fn start(&mut self, world: &World, history: &mut CommandHistory) {
+        self.snapshot = Some(world.snapshot());
+        self.frame = 0;
+        self.record(world);
+        self.history_position = history.position();
+        history.set_locked(true);
+        log::info!("Play mode started");
+    }
+
+    /// Start playing, or resume when paused
+    pub fn play(&mut self, world: &World, history: &mut CommandHistory) {
+        if self.state == PlayState::Editing {
+            self.start(world, history);
+        }
+        self.state = PlayState::Playing;
+    }
+
+    pub fn pause(&mut self) {
+        if self.state == PlayState::Playing {
+            self.state = PlayState::Paused;
+        }
+    }
+
+    pub fn toggle_pause(&mut self, world: &World, history: &mut CommandHistory) {
+        match self.state {
+            PlayState::Playing => self.pause(),
+            PlayState::Paused => self.play(world, history),
+            PlayState::Editing => {}
+        }
+    }
+
+    /// Advance a single frame, entering paused play mode first when editing
+    pub fn step(&mut self, world: &World, history: &mut CommandHistory) {
+        if self.state == PlayState::Editing {
+            self.start(world, history);
+        }
+        self.state = PlayState::Paused;
+        self.step_requested = true;
+    }
+
+    /// Go back one played frame, pausing first
+    /// Returns false when there is no earlier frame recorded.
+    pub fn step_back(&mut self, world: &mut World) -> bool {
+        if self.state == PlayState::Editing || self.rewind.len() < 2 {
+            return false;
+        }
+        self.state = PlayState::Paused;
+        self.step_requested = false;
+        let Some(registry) = world.remove_resource::<ComponentRegistry>() else {
+            return false;
+        };
+        if let Some(frame) = self.rewind.rewind(1, world, &registry) {
+            self.frame = frame;
+        }
+        world.insert_resource(registry);
+        true
+    }
+
+    /// Remember the registered components of the current frame
+    fn record(&mut self, world: &World) {
+        if let Some(registry) = world.resource::<ComponentRegistry>() {
+            self.rewind.record(self.frame, world, registry);
+        }
+    }
+
+    /// Restore the World as it was before playing
+    pub fn stop(&mut self, world: &mut World, history: &mut CommandHistory) {
+        if self.state == PlayState::Editing {
+            return;
+        }
+        if let Some(snapshot) = self.snapshot.take() {
+            world.restore_snapshot(snapshot);
+        }
+        // Timers scheduled while playing would fire against the restored scene
+        if let Some(timers) = world.resource_mut::<Timers>() {
+            *timers = Timers::new();
+        }
+        self.rewind.clear();
+        history.set_locked(false);
+        history.truncate(self.history_position);
+        self.state = PlayState::Editing;
+        self.step_requested = false;
+        log::info!("Play mode stopped, scene restored");
+    }
+
+    /// Run the systems for this frame if playing (or stepping)
+    /// Returns true if they ran, the caller should keep redrawing
+    pub fn update(&mut self, schedule: &mut Schedule, world: &mut World, dt: f32) -> bool {
+        let dt = match self.state {
+            PlayState::Playing => dt.min(MAX_FRAME_SECONDS),
+            PlayState::Paused if self.step_requested => STEP_SECONDS,
+            _ => return false,
+        };
+        self.step_requested = false;
+        schedule.run(world, dt);
+        self.frame += 1;
+        self.record(world);
+        true
+    }
+}
+
+impl Default for PlayMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
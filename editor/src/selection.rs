@@ -0,0 +1,21 @@
+use engine::ecs::EntityId;
+
+/// Editor-wide selection, stored as a World resource so every panel sees the same entity
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    selected: Option<EntityId>,
+}
+
+impl Selection {
+    pub fn get(&self) -> Option<EntityId> {
+        self.selected
+    }
+
+    pub fn select(&mut self, id: EntityId) {
+        self.selected = Some(id);
+    }
+
+    pub fn clear(&mut self) {
+        self.selected = None;
+    }
+}
@@ -0,0 +1,77 @@
+/* C interface of the engine, built with the `capi` feature. See src/capi/mod.rs. */
+#ifndef ENGINE_H
+#define ENGINE_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Returned for missing entities and widgets */
+#define ENGINE_NONE UINT32_MAX
+
+typedef struct Engine Engine;
+
+typedef struct EngineConfig {
+    const char *title;
+    uint32_t width;
+    uint32_t height;
+    /* TrueType font for widgets the host adds, NULL for none */
+    const char *font_path;
+    float font_size;
+} EngineConfig;
+
+/* A rect in window pixels with the origin at the top left */
+typedef struct EngineRect {
+    float x;
+    float y;
+    float width;
+    float height;
+} EngineRect;
+
+/* Message of the last failed call on this thread, NULL if none failed yet */
+const char *engine_last_error(void);
+
+/* Open a window with an empty world, NULL on failure */
+Engine *engine_create(const EngineConfig *config);
+void engine_destroy(Engine *engine);
+
+/* Replace the world with a scene file (RON, see ecs::load_scene) */
+bool engine_load_scene(Engine *engine, const char *path);
+/* Load a scene file next to the loaded ones, its id or ENGINE_NONE */
+uint32_t engine_load_scene_additive(Engine *engine, const char *path);
+/* Despawn the entities of a scene of engine_load_scene_additive */
+bool engine_unload_scene(Engine *engine, uint32_t scene);
+/* Handle window events, run the game systems and draw a frame, false once the window closed */
+bool engine_tick(Engine *engine);
+
+/* Input, positions in window pixels with the origin at the top left */
+void engine_mouse_move(Engine *engine, float x, float y);
+/* 0 left, 1 right, 2 middle */
+bool engine_mouse_button(Engine *engine, uint32_t button, bool pressed);
+/* A character ("a") or a key name ("Enter", "Escape", "ArrowUp", ...), true if the UI used it */
+bool engine_key(Engine *engine, const char *key);
+
+/* Scene queries */
+uint32_t engine_entity_count(const Engine *engine);
+uint32_t engine_find_entity(const Engine *engine, const char *name);
+bool engine_entity_position(const Engine *engine, uint32_t entity, float *x, float *y);
+bool engine_set_entity_position(Engine *engine, uint32_t entity, float x, float y);
+
+/* Widgets above the scene, need EngineConfig.font_path */
+uint32_t engine_ui_add_button(Engine *engine, const char *label);
+uint32_t engine_ui_add_label(Engine *engine, const char *text);
+bool engine_ui_set_text(Engine *engine, uint32_t widget, const char *text);
+bool engine_ui_take_clicked(Engine *engine, uint32_t widget);
+
+/* UI elements by accessible name (a button's label, a text) */
+bool engine_ui_find(Engine *engine, const char *name, EngineRect *rect);
+bool engine_ui_activate(Engine *engine, const char *name);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,45 @@
+#version 450
+
+layout(location = 0) in vec2 frag_uv;
+layout(location = 1) in vec4 frag_color;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform texture2D imageTexture;
+layout(set = 0, binding = 1) uniform sampler imageSampler;
+
+layout(push_constant) uniform PushConstant {
+    mat4 projection;
+    mat4 transform;
+    vec3 colorModulation;
+    float opacity;
+    // One texel along the blur direction, in uv units
+    vec2 texelStep;
+    // Gaussian standard deviation in texels, 0 samples once
+    float sigma;
+    float dim;
+    float saturation;
+    float padding0;
+    vec2 padding1;
+} pc;
+
+// Samples on each side of the center, bounds the cost of wide blurs
+const int MAX_TAPS = 32;
+
+void main() {
+    int taps = min(int(ceil(pc.sigma * 3.0)), MAX_TAPS);
+    vec4 sum = texture(sampler2D(imageTexture, imageSampler), frag_uv);
+    float total = 1.0;
+    for (int i = 1; i <= taps; i++) {
+        float weight = exp(-float(i * i) / (2.0 * pc.sigma * pc.sigma));
+        vec2 offset = pc.texelStep * float(i);
+        sum += weight * texture(sampler2D(imageTexture, imageSampler), frag_uv + offset);
+        sum += weight * texture(sampler2D(imageTexture, imageSampler), frag_uv - offset);
+        total += 2.0 * weight;
+    }
+    vec4 color = sum / total;
+
+    float luminance = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    vec3 rgb = mix(vec3(luminance), color.rgb, pc.saturation) * (1.0 - pc.dim);
+    out_color = vec4(rgb * frag_color.rgb, color.a * frag_color.a);
+}
@@ -0,0 +1,63 @@
+#version 450
+
+layout(location = 0) in vec2 frag_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform texture2D imageTexture;
+layout(set = 0, binding = 1) uniform sampler imageSampler;
+
+layout(push_constant) uniform PushConstant {
+    mat4 projection;
+    mat4 transform;
+    // One source texel in uv units, along the blur direction for the blur pass
+    vec2 texelStep;
+    float threshold;
+    float knee;
+    float intensity;
+    // BloomPass: 0 prefilter, 1 downsample, 2 blur, 3 composite
+    uint pass;
+    vec2 padding;
+} pc;
+
+// Standard deviation of the blur in texels of the level, the chain widens it
+const float SIGMA = 2.0;
+const int TAPS = 6;
+
+vec3 sample_color(vec2 uv) {
+    return texture(sampler2D(imageTexture, imageSampler), uv).rgb;
+}
+
+// Average of the 4x4 texels around uv, each bilinear tap covers 2x2
+vec3 downsample(vec2 uv) {
+    vec2 d = pc.texelStep;
+    return 0.25 * (sample_color(uv + vec2(-d.x, -d.y)) + sample_color(uv + vec2(d.x, -d.y))
+        + sample_color(uv + vec2(-d.x, d.y)) + sample_color(uv + vec2(d.x, d.y)));
+}
+
+void main() {
+    vec3 color;
+    if (pc.pass == 0u) {
+        color = downsample(frag_uv);
+        // Quadratic curve from threshold - knee to threshold + knee, linear above
+        float brightness = max(color.r, max(color.g, color.b));
+        float soft = clamp(brightness - pc.threshold + pc.knee, 0.0, 2.0 * pc.knee);
+        soft = soft * soft / (4.0 * pc.knee + 0.0001);
+        color *= max(soft, brightness - pc.threshold) / max(brightness, 0.0001);
+    } else if (pc.pass == 1u) {
+        color = downsample(frag_uv);
+    } else if (pc.pass == 2u) {
+        color = sample_color(frag_uv);
+        float total = 1.0;
+        for (int i = 1; i <= TAPS; i++) {
+            float weight = exp(-float(i * i) / (2.0 * SIGMA * SIGMA));
+            vec2 offset = pc.texelStep * float(i);
+            color += weight * (sample_color(frag_uv + offset) + sample_color(frag_uv - offset));
+            total += 2.0 * weight;
+        }
+        color /= total;
+    } else {
+        color = sample_color(frag_uv) * pc.intensity;
+    }
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,56 @@
+#version 450
+
+layout(location = 0) in vec2 frag_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform texture2D imageTexture;
+layout(set = 0, binding = 1) uniform sampler imageSampler;
+
+layout(push_constant) uniform PushConstant {
+    mat4 projection;
+    mat4 transform;
+    // One texel of the source in uv units
+    vec2 texelSize;
+    vec2 padding;
+} pc;
+
+// Longest blur along an edge in texels
+const float SPAN_MAX = 8.0;
+// Keep the direction from growing on dark and flat areas
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+vec3 sample_color(vec2 uv) {
+    return texture(sampler2D(imageTexture, imageSampler), uv).rgb;
+}
+
+// Perceptual brightness, edges are found where it changes (the source is linear)
+float luma(vec3 color) {
+    return sqrt(dot(color, vec3(0.299, 0.587, 0.114)));
+}
+
+void main() {
+    vec2 texel = pc.texelSize;
+    vec4 center = texture(sampler2D(imageTexture, imageSampler), frag_uv);
+    float lumaNW = luma(sample_color(frag_uv + vec2(-1.0, -1.0) * texel));
+    float lumaNE = luma(sample_color(frag_uv + vec2(1.0, -1.0) * texel));
+    float lumaSW = luma(sample_color(frag_uv + vec2(-1.0, 1.0) * texel));
+    float lumaSE = luma(sample_color(frag_uv + vec2(1.0, 1.0) * texel));
+    float lumaM = luma(center.rgb);
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    // Perpendicular to the luma gradient, along the edge
+    vec2 dir = vec2(-((lumaNW + lumaNE) - (lumaSW + lumaSE)), (lumaNW + lumaSW) - (lumaNE + lumaSE));
+    float reduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+    vec3 inner = 0.5 * (sample_color(frag_uv + dir * (1.0 / 3.0 - 0.5)) + sample_color(frag_uv + dir * (2.0 / 3.0 - 0.5)));
+    vec3 outer = inner * 0.5 + 0.25 * (sample_color(frag_uv - dir * 0.5) + sample_color(frag_uv + dir * 0.5));
+    // The wider blur crossed another edge, fall back to the narrow one
+    float lumaOuter = luma(outer);
+    vec3 color = (lumaOuter < lumaMin || lumaOuter > lumaMax) ? inner : outer;
+    out_color = vec4(color, center.a);
+}
@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) in vec2 frag_uv;
+layout(location = 1) in vec4 frag_color;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform texture2D imageTexture;
+layout(set = 0, binding = 1) uniform sampler imageSampler;
+
+void main() {
+    vec4 color = texture(sampler2D(imageTexture, imageSampler), frag_uv);
+    out_color = vec4(color.rgb * frag_color.rgb, color.a * frag_color.a);
+}
@@ -0,0 +1,36 @@
+#version 450
+
+// One point light added to the light map, drawn as a quad twice its radius across.
+// The shadow mask is the size of the light map, white where the light reaches.
+
+layout(location = 0) in vec2 frag_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform texture2D shadowMask;
+layout(set = 0, binding = 1) uniform sampler maskSampler;
+
+layout(push_constant) uniform PushConstant {
+    mat4 projection;
+    mat4 transform;
+    vec3 color;
+    float intensity;
+    vec2 texelSize;
+    uint shadowed;
+    float padding;
+} pc;
+
+void main() {
+    vec2 offset = frag_uv * 2.0 - 1.0;
+    float d2 = dot(offset, offset);
+    if (d2 >= 1.0) {
+        discard;
+    }
+    // Smooth falloff reaching exactly 0 at the radius
+    float falloff = (1.0 - d2) * (1.0 - d2);
+    float visible = 1.0;
+    if (pc.shadowed != 0u) {
+        visible = texture(sampler2D(shadowMask, maskSampler), gl_FragCoord.xy * pc.texelSize).r;
+    }
+    out_color = vec4(pc.color * pc.intensity * falloff * visible, 1.0);
+}
@@ -1,28 +1,60 @@
 #version 450
 
 layout(location = 0) in vec2 frag_uv;
-layout(location = 1) in vec3 frag_color;
+layout(location = 1) in vec4 frag_color;
 
 layout(location = 0) out vec4 out_color;
 
 layout(set = 0, binding = 0) uniform texture2D fontTexture;
 layout(set = 0, binding = 1) uniform sampler fontSampler;
 
-void main() {
-    // Sample the main text alpha
-    float alpha = texture(sampler2D(fontTexture, fontSampler), frag_uv).r;
-    
-    // Add very subtle shadow for contrast
+layout(push_constant) uniform PushConstant {
+    mat4 projection;
+    mat4 transform;
+    vec3 colorModulation;
+    float opacity;
+    float gamma;
+    uint lcd;
+    vec2 padding;
+} pc;
+
+// Glyph coverage with a very subtle shadow for contrast
+float coverage(vec2 uv) {
+    float alpha = texture(sampler2D(fontTexture, fontSampler), uv).r;
+
     vec2 texelSize = 1.0 / vec2(textureSize(sampler2D(fontTexture, fontSampler), 0));
     float shadow = 0.0;
-    shadow += texture(sampler2D(fontTexture, fontSampler), frag_uv + vec2(-1.0, -1.0) * texelSize).r;
-    shadow += texture(sampler2D(fontTexture, fontSampler), frag_uv + vec2(1.0, -1.0) * texelSize).r;
-    shadow += texture(sampler2D(fontTexture, fontSampler), frag_uv + vec2(-1.0, 1.0) * texelSize).r;
-    shadow += texture(sampler2D(fontTexture, fontSampler), frag_uv + vec2(1.0, 1.0) * texelSize).r;
+    shadow += texture(sampler2D(fontTexture, fontSampler), uv + vec2(-1.0, -1.0) * texelSize).r;
+    shadow += texture(sampler2D(fontTexture, fontSampler), uv + vec2(1.0, -1.0) * texelSize).r;
+    shadow += texture(sampler2D(fontTexture, fontSampler), uv + vec2(-1.0, 1.0) * texelSize).r;
+    shadow += texture(sampler2D(fontTexture, fontSampler), uv + vec2(1.0, 1.0) * texelSize).r;
     shadow *= 0.08; // Very subtle
-    
-    // Combine
-    float finalAlpha = clamp(alpha + shadow * (1.0 - alpha), 0.0, 1.0);
-    
-    out_color = vec4(frag_color, finalAlpha);
-}
\ No newline at end of file
+
+    return clamp(alpha + shadow * (1.0 - alpha), 0.0, 1.0);
+}
+
+// Blending happens on linear colors, light text would look too bold and dark text too thin.
+// Adjust coverage so the result matches blending in gamma space, by the text's luminance.
+vec3 gammaCorrect(vec3 cov) {
+    float luminance = dot(frag_color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    vec3 light = pow(cov, vec3(pc.gamma));
+    vec3 dark = 1.0 - pow(1.0 - cov, vec3(pc.gamma));
+    return mix(dark, light, luminance);
+}
+
+void main() {
+    if (pc.lcd == 0u) {
+        float alpha = gammaCorrect(vec3(coverage(frag_uv))).r;
+        out_color = vec4(frag_color.rgb, alpha * frag_color.a);
+        return;
+    }
+
+    // LCD: one sample per color channel a third of a screen pixel apart (RGB stripe order).
+    // Without dual source blending the channels share one alpha, the strongest, and the
+    // color carries the difference.
+    vec2 third = dFdx(frag_uv) / 3.0;
+    vec3 cov = gammaCorrect(vec3(coverage(frag_uv - third), coverage(frag_uv), coverage(frag_uv + third)));
+    float alpha = max(cov.r, max(cov.g, cov.b));
+    vec3 color = alpha > 0.0 ? frag_color.rgb * cov / alpha : frag_color.rgb;
+    out_color = vec4(color, alpha * frag_color.a);
+}
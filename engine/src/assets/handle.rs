@@ -0,0 +1,57 @@
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+/// Typed reference to an asset kept by the manager that loaded it
+///
+/// Handles stay cheap to copy and never keep the asset alive. Once it is unloaded the
+/// manager rejects the handle, even if the slot has been reused by another asset.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _asset: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub(crate) fn new(index: usize, generation: u32) -> Self {
+        Handle { index: index as u32, generation, _asset: PhantomData }
+    }
+
+    /// Slot of the asset in its manager
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// Times the slot had been reused when the asset was loaded
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
@@ -0,0 +1,143 @@
+//! Background asset loading.
+//! A `LoadBatch` decodes a set of assets on the task pool and reports progress, so a
+//! game can draw a loading bar until `take_completed` fires and then switch over.
+//! Only CPU work runs on the workers; GPU uploads (e.g. `Texture::from_image`) are
+//! done by the caller on the main thread once the batch is complete.
+//! Assets kept by a manager, like fonts in the renderer, are referred to by `Handle`s.
+
+use anyhow::{anyhow, Result};
+use std::any::Any;
+use std::collections::HashMap;
+
+use crate::crash::panic_message;
+use crate::tasks::{self, Task};
+
+mod handle;
+pub use handle::Handle;
+
+type Loaded = Box<dyn Any + Send>;
+
+/// Assets loaded together, each under a name unique within the batch
+pub struct LoadBatch {
+    pending: Vec<(String, Task<Result<Loaded>>)>,
+    loaded: HashMap<String, Loaded>,
+    failed: Vec<(String, anyhow::Error)>,
+    /// Assets added since the batch was created
+    total: usize,
+    completion_reported: bool,
+}
+
+impl LoadBatch {
+    pub fn new() -> Self {
+        LoadBatch {
+            pending: Vec::new(),
+            loaded: HashMap::new(),
+            failed: Vec::new(),
+            total: 0,
+            completion_reported: false,
+        }
+    }
+
+    /// Run `loader` on the task pool, its value is taken with `take::<T>(name)`
+    /// A name that is still loading or not taken yet is rejected, the loader doesn't run and
+    /// the error is reported by `errors`.
+    pub fn load<T: Send + 'static>(&mut self, name: &str, loader: impl FnOnce() -> Result<T> + Send + 'static) {
+        if self.loaded.contains_key(name) || self.pending.iter().any(|(pending, _)| pending == name) {
+            log::error!("'{}' is already in the load batch", name);
+            self.failed.push((name.to_string(), anyhow!("'{}' is already in the load batch", name)));
+            return;
+        }
+        let task = tasks::spawn(move || loader().map(|value| Box::new(value) as Loaded));
+        self.pending.push((name.to_string(), task));
+        self.total += 1;
+        self.completion_reported = false;
+    }
+
+    /// Decode a PNG/JPEG file to an `image::RgbaImage`
+    pub fn load_image(&mut self, path: &str) {
+        let owned = path.to_string();
+        self.load(path, move || {
+            let image = image::open(&owned).map_err(|e| anyhow!("Failed to load image '{}': {}", owned, e))?;
+            Ok(image.to_rgba8())
+        });
+    }
+
+    /// Read a file's bytes (shaders, fonts, level data)
+    pub fn load_bytes(&mut self, path: &str) {
+        let owned = path.to_string();
+        self.load(path, move || std::fs::read(&owned).map_err(|e| anyhow!("Failed to read '{}': {}", owned, e)));
+    }
+
+    /// Collect finished loads, call once per frame
+    pub fn poll(&mut self) {
+        let mut index = 0;
+        while index < self.pending.len() {
+            if !self.pending[index].1.is_finished() {
+                index += 1;
+                continue;
+            }
+            let (name, task) = self.pending.swap_remove(index);
+            // A panicking loader fails its asset instead of the game
+            let result = task
+                .join_caught()
+                .unwrap_or_else(|payload| Err(anyhow!("Loader panicked: {}", panic_message(payload.as_ref()))));
+            match result {
+                Ok(value) => {
+                    self.loaded.insert(name, value);
+                }
+                Err(error) => {
+                    log::error!("Loading '{}' failed: {:#}", name, error);
+                    self.failed.push((name, error));
+                }
+            }
+        }
+    }
+
+    /// Fraction of assets finished loading (failed ones count as finished), 1.0 for an empty batch
+    pub fn progress(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.total - self.pending.len()) as f32 / self.total as f32
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns true once when every asset has finished, call after `poll`
+    pub fn take_completed(&mut self) -> bool {
+        if self.is_complete() && !self.completion_reported {
+            self.completion_reported = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Take a loaded asset out of the batch
+    /// `None` if it isn't loaded (yet), failed, was already taken, or isn't a `T`
+    pub fn take<T: 'static>(&mut self, name: &str) -> Option<T> {
+        let value = self.loaded.remove(name)?;
+        match value.downcast::<T>() {
+            Ok(value) => Some(*value),
+            Err(value) => {
+                log::warn!("Asset '{}' was taken as the wrong type", name);
+                self.loaded.insert(name.to_string(), value);
+                None
+            }
+        }
+    }
+
+    /// Assets that failed to load and why
+    pub fn errors(&self) -> &[(String, anyhow::Error)] {
+        &self.failed
+    }
+}
+
+impl Default for LoadBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,283 @@
+//! C interface for embedding the engine in non-Rust hosts or driving it from tools.
+//! `include/engine.h` declares these functions. Build the crate as a library a C linker
+//! understands with `cargo rustc -p engine --features capi --crate-type cdylib` (or
+//! `staticlib`).
+//!
+//! An `Engine` is created with `engine_create`, which opens a window, and freed with
+//! `engine_destroy`. The host calls `engine_tick` once per frame until it returns false.
+//! Functions that can fail return false, `ENGINE_NONE` or null and leave a message for
+//! `engine_last_error`.
+//!
+//! Pointers must be null or valid: engines from `engine_create` that weren't destroyed,
+//! NUL-terminated UTF-8 strings and writable out parameters. An engine belongs to the
+//! thread that created it, on macOS the main thread.
+
+// The pointer contract above applies to every function
+#![allow(clippy::missing_safety_doc)]
+
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use winit::event::MouseButton;
+
+use crate::crash::panic_message;
+use crate::ecs::{EntityId, SceneId};
+use crate::math::Transform;
+
+pub use crate::host::{parse_key, Engine};
+
+/// Returned for missing entities and widgets
+pub const ENGINE_NONE: u32 = u32::MAX;
+
+/// Settings of `engine_create`
+#[repr(C)]
+pub struct EngineConfig {
+    pub title: *const c_char,
+    pub width: u32,
+    pub height: u32,
+    /// TrueType font for widgets the host adds, null for none
+    pub font_path: *const c_char,
+    pub font_size: f32,
+}
+
+/// A rect in window pixels with the origin at the top left
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EngineRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: String) {
+    log::error!("{}", message);
+    // Messages with NULs are cut there rather than lost
+    let message = CString::new(message).unwrap_or_else(|e| {
+        let end = e.nul_position();
+        CString::new(&e.into_vec()[..end]).unwrap_or_default()
+    });
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run `f`, turning errors and panics into `fallback` and a message for `engine_last_error`
+fn guard<R>(fallback: R, f: impl FnOnce() -> Result<R>) -> R {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(e)) => {
+            set_error(format!("{:#}", e));
+            fallback
+        }
+        Err(panic) => {
+            set_error(format!("Engine panicked: {}", panic_message(panic.as_ref())));
+            fallback
+        }
+    }
+}
+
+unsafe fn engine_mut<'a>(engine: *mut Engine) -> Result<&'a mut Engine> {
+    engine.as_mut().ok_or_else(|| anyhow!("Engine is null"))
+}
+
+unsafe fn engine_ref<'a>(engine: *const Engine) -> Result<&'a Engine> {
+    engine.as_ref().ok_or_else(|| anyhow!("Engine is null"))
+}
+
+unsafe fn string<'a>(text: *const c_char) -> Result<&'a str> {
+    if text.is_null() {
+        return Err(anyhow!("String is null"));
+    }
+    CStr::from_ptr(text).to_str().map_err(|e| anyhow!("String is not UTF-8: {}", e))
+}
+
+/// Message of the last failed call on this thread, null if none failed yet
+/// Valid until the next call fails.
+#[no_mangle]
+pub extern "C" fn engine_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(std::ptr::null(), |message| message.as_ptr()))
+}
+
+/// Open a window with an empty World, null on failure
+#[no_mangle]
+pub unsafe extern "C" fn engine_create(config: *const EngineConfig) -> *mut Engine {
+    guard(std::ptr::null_mut(), || {
+        let config = config.as_ref().ok_or_else(|| anyhow!("Config is null"))?;
+        let title = if config.title.is_null() { "Engine" } else { string(config.title)? };
+        let font = if config.font_path.is_null() { None } else { Some((string(config.font_path)?, config.font_size)) };
+        let engine = Engine::new(title, config.width, config.height, font)?;
+        Ok(Box::into_raw(Box::new(engine)))
+    })
+}
+
+/// Close the window and free the engine, null is ignored
+#[no_mangle]
+pub unsafe extern "C" fn engine_destroy(engine: *mut Engine) {
+    if !engine.is_null() {
+        guard((), || {
+            drop(Box::from_raw(engine));
+            Ok(())
+        });
+    }
+}
+
+/// Replace the World with a scene file, see `ecs::load_scene`
+#[no_mangle]
+pub unsafe extern "C" fn engine_load_scene(engine: *mut Engine, path: *const c_char) -> bool {
+    guard(false, || {
+        engine_mut(engine)?.load_scene(string(path)?)?;
+        Ok(true)
+    })
+}
+
+/// Load a scene file next to the loaded ones, returns its id for `engine_unload_scene`
+/// or `ENGINE_NONE`, see `ecs::load_scene_additive`
+#[no_mangle]
+pub unsafe extern "C" fn engine_load_scene_additive(engine: *mut Engine, path: *const c_char) -> u32 {
+    guard(ENGINE_NONE, || Ok(engine_mut(engine)?.load_scene_additive(string(path)?)?.0))
+}
+
+/// Despawn a scene of `engine_load_scene_additive`, false if it isn't loaded
+#[no_mangle]
+pub unsafe extern "C" fn engine_unload_scene(engine: *mut Engine, scene: u32) -> bool {
+    guard(false, || Ok(engine_mut(engine)?.unload_scene(SceneId(scene))))
+}
+
+/// Handle window events, run the game systems and draw a frame
+/// False once the window was closed or on an error.
+#[no_mangle]
+pub unsafe extern "C" fn engine_tick(engine: *mut Engine) -> bool {
+    guard(false, || engine_mut(engine)?.tick())
+}
+
+/// Move the pointer to a window position in pixels, origin at the top left
+#[no_mangle]
+pub unsafe extern "C" fn engine_mouse_move(engine: *mut Engine, x: f32, y: f32) {
+    guard((), || {
+        engine_mut(engine)?.mouse_move(x, y);
+        Ok(())
+    });
+}
+
+/// Press or release a button at the pointer: 0 left, 1 right, 2 middle
+#[no_mangle]
+pub unsafe extern "C" fn engine_mouse_button(engine: *mut Engine, button: u32, pressed: bool) -> bool {
+    guard(false, || {
+        let button = match button {
+            0 => MouseButton::Left,
+            1 => MouseButton::Right,
+            2 => MouseButton::Middle,
+            _ => return Err(anyhow!("Unknown mouse button {}", button)),
+        };
+        engine_mut(engine)?.mouse_button(button, pressed);
+        Ok(true)
+    })
+}
+
+/// Press a key given as a character or a name like "Enter", see `parse_key`
+/// Returns whether the UI used it.
+#[no_mangle]
+pub unsafe extern "C" fn engine_key(engine: *mut Engine, key: *const c_char) -> bool {
+    guard(false, || {
+        let name = string(key)?;
+        let key = parse_key(name).ok_or_else(|| anyhow!("Unknown key '{}'", name))?;
+        Ok(engine_mut(engine)?.key(&key))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn engine_entity_count(engine: *const Engine) -> u32 {
+    guard(0, || Ok(engine_ref(engine)?.world().entity_count() as u32))
+}
+
+/// Id of the entity called `name`, `ENGINE_NONE` if there is none
+#[no_mangle]
+pub unsafe extern "C" fn engine_find_entity(engine: *const Engine, name: *const c_char) -> u32 {
+    guard(ENGINE_NONE, || {
+        let name = string(name)?;
+        Ok(engine_ref(engine)?.find_entity(name).map_or(ENGINE_NONE, |id| id.0))
+    })
+}
+
+/// Position of an entity's Transform relative to its parent
+#[no_mangle]
+pub unsafe extern "C" fn engine_entity_position(engine: *const Engine, entity: u32, x: *mut f32, y: *mut f32) -> bool {
+    guard(false, || {
+        let transform = engine_ref(engine)?
+            .world()
+            .get::<Transform>(EntityId(entity))
+            .ok_or_else(|| anyhow!("Entity {} has no Transform", entity))?;
+        if let Some(x) = x.as_mut() {
+            *x = transform.position.x;
+        }
+        if let Some(y) = y.as_mut() {
+            *y = transform.position.y;
+        }
+        Ok(true)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn engine_set_entity_position(engine: *mut Engine, entity: u32, x: f32, y: f32) -> bool {
+    guard(false, || {
+        let transform = engine_mut(engine)?
+            .world_mut()
+            .get_mut::<Transform>(EntityId(entity))
+            .ok_or_else(|| anyhow!("Entity {} has no Transform", entity))?;
+        transform.position = glam::Vec2::new(x, y);
+        Ok(true)
+    })
+}
+
+/// Add a button above the scene, needs `EngineConfig::font_path`
+/// Returns the widget's id, `ENGINE_NONE` on failure.
+#[no_mangle]
+pub unsafe extern "C" fn engine_ui_add_button(engine: *mut Engine, label: *const c_char) -> u32 {
+    guard(ENGINE_NONE, || Ok(engine_mut(engine)?.add_button(string(label)?)? as u32))
+}
+
+/// Add a line of text above the scene, needs `EngineConfig::font_path`
+#[no_mangle]
+pub unsafe extern "C" fn engine_ui_add_label(engine: *mut Engine, text: *const c_char) -> u32 {
+    guard(ENGINE_NONE, || Ok(engine_mut(engine)?.add_label(string(text)?)? as u32))
+}
+
+/// Change the text of a button or label
+#[no_mangle]
+pub unsafe extern "C" fn engine_ui_set_text(engine: *mut Engine, widget: u32, text: *const c_char) -> bool {
+    guard(false, || {
+        engine_mut(engine)?.set_text(widget as usize, string(text)?)?;
+        Ok(true)
+    })
+}
+
+/// Whether a button was clicked since the last call
+#[no_mangle]
+pub unsafe extern "C" fn engine_ui_take_clicked(engine: *mut Engine, widget: u32) -> bool {
+    guard(false, || Ok(engine_mut(engine)?.take_clicked(widget as usize)))
+}
+
+/// Rect of the UI element with the accessible name `name` (a button's label, a text),
+/// false if there is none
+#[no_mangle]
+pub unsafe extern "C" fn engine_ui_find(engine: *mut Engine, name: *const c_char, rect: *mut EngineRect) -> bool {
+    guard(false, || {
+        let Some(found) = engine_mut(engine)?.find_ui(string(name)?) else {
+            return Ok(false);
+        };
+        if let Some(rect) = rect.as_mut() {
+            *rect = EngineRect { x: found.x, y: found.y, width: found.width, height: found.height };
+        }
+        Ok(true)
+    })
+}
+
+/// Click the UI element called `name` or perform its default action, false if there is none
+#[no_mangle]
+pub unsafe extern "C" fn engine_ui_activate(engine: *mut Engine, name: *const c_char) -> bool {
+    guard(false, || Ok(engine_mut(engine)?.activate_ui(string(name)?)))
+}
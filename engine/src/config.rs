@@ -0,0 +1,234 @@
+//! How a project starts the engine: window, presentation, validation and where its files
+//! are. `EngineConfig` is read from a TOML file and command-line flags, so projects change
+//! these without editing code:
+//!
+//! ```text
+//! title = "My Game"
+//! app = "my-game"
+//! asset_root = "assets"
+//! scene = "scenes/level1.ron"
+//!
+//! [window]
+//! width = 1280
+//! height = 720
+//! fullscreen = false
+//! vsync = true
+//!
+//! [renderer]
+//! validation = false
+//! ```
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::{Path, PathBuf};
+use winit::dpi::PhysicalSize;
+use winit::window::{Fullscreen, WindowBuilder};
+
+use crate::storage::{read_toml, Settings, Value};
+
+/// Read when no `--config` flag names another file
+pub const DEFAULT_CONFIG_FILE: &str = "engine.toml";
+
+/// Command-line flags `apply_args` understands
+pub const USAGE: &str = "\
+Options:
+  --config FILE      Read settings from FILE instead of engine.toml
+  --width PIXELS     Window width
+  --height PIXELS    Window height
+  --fullscreen       Borderless fullscreen on the current monitor
+  --windowed         Open a window even if the config asks for fullscreen
+  --vsync            Wait for vertical sync
+  --no-vsync         Present as fast as possible
+  --validation       Enable the Vulkan validation layer
+  --no-validation    Disable the Vulkan validation layer
+  --assets DIR       Directory assets are loaded from
+  --scene FILE       Scene to open at startup";
+
+/// Engine settings chosen by the project, as opposed to the user's `Settings`
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineConfig {
+    pub title: String,
+    /// Directory name of the user's `Settings`, see `Engine::run`
+    pub app: String,
+    pub window_width: u32,
+    pub window_height: u32,
+    /// Borderless fullscreen on the monitor the window opens on
+    pub fullscreen: bool,
+    pub vsync: bool,
+    /// Khronos validation layer, used only where it is installed
+    pub validation: bool,
+    /// Directory relative asset paths are resolved against, see `asset`
+    pub asset_root: PathBuf,
+    /// Scene file opened at startup, relative to `asset_root`
+    pub scene: Option<PathBuf>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            title: "Engine".to_string(),
+            app: "engine".to_string(),
+            window_width: 1280,
+            window_height: 720,
+            fullscreen: false,
+            vsync: true,
+            validation: cfg!(debug_assertions),
+            asset_root: PathBuf::from("assets"),
+            scene: None,
+        }
+    }
+}
+
+impl EngineConfig {
+    /// The defaults overridden by a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut config = Self::default();
+        config.apply_file(path)?;
+        Ok(config)
+    }
+
+    /// The defaults overridden by the config file and flags of `args`, see `apply_args`
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut config = Self::default();
+        config.apply_args(args)?;
+        Ok(config)
+    }
+
+    /// Override settings with a TOML file, keys it leaves out keep their values
+    pub fn apply_file(&mut self, path: &Path) -> Result<()> {
+        let value = read_toml(path)?;
+        self.apply_value(&value).with_context(|| format!("In '{}'", path.display()))
+    }
+
+    /// Override settings with a parsed config file
+    /// Unknown keys and values of the wrong type are errors, so typos don't go unnoticed.
+    pub fn apply_value(&mut self, value: &Value) -> Result<()> {
+        for (key, value) in value.entries().unwrap_or_default() {
+            match key.as_str() {
+                "title" => self.title = string(key, value)?.to_string(),
+                "app" => self.app = string(key, value)?.to_string(),
+                "asset_root" => self.asset_root = PathBuf::from(string(key, value)?),
+                "scene" => self.scene = Some(PathBuf::from(string(key, value)?)),
+                "window" => {
+                    for (key, value) in table(key, value)? {
+                        match key.as_str() {
+                            "width" => self.window_width = size(key, value)?,
+                            "height" => self.window_height = size(key, value)?,
+                            "fullscreen" => self.fullscreen = boolean(key, value)?,
+                            "vsync" => self.vsync = boolean(key, value)?,
+                            _ => bail!("Unknown setting window.{}", key),
+                        }
+                    }
+                }
+                "renderer" => {
+                    for (key, value) in table(key, value)? {
+                        match key.as_str() {
+                            "validation" => self.validation = boolean(key, value)?,
+                            _ => bail!("Unknown setting renderer.{}", key),
+                        }
+                    }
+                }
+                _ => bail!("Unknown setting {}", key),
+            }
+        }
+        Ok(())
+    }
+
+    /// Override settings with command-line flags (without the program name), see `USAGE`
+    /// The file named by `--config`, or `engine.toml` in the working directory if there is
+    /// one, is applied first so flags win over it.
+    pub fn apply_args(&mut self, args: impl IntoIterator<Item = String>) -> Result<()> {
+        let args: Vec<String> = args.into_iter().collect();
+        let config_file = match args.iter().position(|arg| arg == "--config") {
+            Some(index) => Some(PathBuf::from(args.get(index + 1).ok_or_else(|| anyhow!("--config needs a file"))?)),
+            None => Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()),
+        };
+        if let Some(path) = config_file {
+            self.apply_file(&path)?;
+            log::info!("Loaded engine config from '{}'", path.display());
+        }
+        self.apply_flags(args)
+    }
+
+    /// Override settings with command-line flags only, `--config` is skipped
+    pub fn apply_flags(&mut self, args: impl IntoIterator<Item = String>) -> Result<()> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut operand = || args.next().ok_or_else(|| anyhow!("{} needs a value\n{}", arg, USAGE));
+            match arg.as_str() {
+                "--config" => {
+                    operand()?;
+                }
+                "--width" => self.window_width = parse_size(&arg, &operand()?)?,
+                "--height" => self.window_height = parse_size(&arg, &operand()?)?,
+                "--fullscreen" => self.fullscreen = true,
+                "--windowed" => self.fullscreen = false,
+                "--vsync" => self.vsync = true,
+                "--no-vsync" => self.vsync = false,
+                "--validation" => self.validation = true,
+                "--no-validation" => self.validation = false,
+                "--assets" => self.asset_root = PathBuf::from(operand()?),
+                "--scene" => self.scene = Some(PathBuf::from(operand()?)),
+                _ => bail!("Unknown option '{}'\n{}", arg, USAGE),
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the window size and vsync from the user's settings
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.window_width = settings.window_width;
+        self.window_height = settings.window_height;
+        self.vsync = settings.vsync;
+    }
+
+    /// A path relative to the asset root, absolute paths stay as they are
+    pub fn asset(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.asset_root.join(path)
+    }
+
+    /// The starting scene resolved against the asset root
+    pub fn scene_path(&self) -> Option<PathBuf> {
+        self.scene.as_ref().map(|scene| self.asset(scene))
+    }
+
+    /// A window with the configured title, size and fullscreen mode
+    pub fn window_builder(&self) -> WindowBuilder {
+        let builder = WindowBuilder::new()
+            .with_title(&self.title)
+            .with_inner_size(PhysicalSize::new(self.window_width, self.window_height));
+        if self.fullscreen {
+            builder.with_fullscreen(Some(Fullscreen::Borderless(None)))
+        } else {
+            builder
+        }
+    }
+}
+
+fn string<'a>(key: &str, value: &'a Value) -> Result<&'a str> {
+    value.as_str().ok_or_else(|| anyhow!("{} must be a string", key))
+}
+
+fn boolean(key: &str, value: &Value) -> Result<bool> {
+    value.as_bool().ok_or_else(|| anyhow!("{} must be true or false", key))
+}
+
+fn table<'a>(key: &str, value: &'a Value) -> Result<&'a [(String, Value)]> {
+    match value {
+        Value::Struct(entries) => Ok(entries),
+        _ => bail!("{} must be a table", key),
+    }
+}
+
+fn size(key: &str, value: &Value) -> Result<u32> {
+    match value.as_f64() {
+        Some(pixels) if pixels >= 1.0 && pixels <= u32::MAX as f64 && pixels.fract() == 0.0 => Ok(pixels as u32),
+        _ => bail!("{} must be a whole number of pixels", key),
+    }
+}
+
+fn parse_size(flag: &str, text: &str) -> Result<u32> {
+    match text.parse() {
+        Ok(pixels) if pixels > 0 => Ok(pixels),
+        _ => bail!("{} takes a whole number of pixels, got '{}'", flag, text),
+    }
+}
@@ -0,0 +1,157 @@
+//! Crash reports for panics.
+//! `install` replaces the panic hook with one that logs the panic, writes a report
+//! (message, backtrace, GPU and driver, recent log lines) to the app's data directory
+//! and can show a message box pointing at it, so a crash in a release build leaves
+//! something a player can send in. The GPU part is filled in by `VulkanContext`.
+
+use anyhow::{anyhow, Result};
+use log::LevelFilter;
+use std::any::Any;
+use std::fmt::Write;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::logging;
+use crate::storage;
+
+/// Log lines included in a report
+const REPORT_LOG_LINES: usize = 100;
+
+struct CrashConfig {
+    app: String,
+    message_box: bool,
+}
+
+static CONFIG: OnceLock<CrashConfig> = OnceLock::new();
+static GPU_INFO: Mutex<Option<String>> = Mutex::new(None);
+/// Set by the first panic, a panic while reporting falls back to the default hook
+static REPORTING: AtomicBool = AtomicBool::new(false);
+
+/// Write a crash report whenever a thread panics, into `crashes` under the app's data
+/// directory. With `message_box` the player is also told where it went, which is what
+/// release builds want. Debug builds keep the default console output too.
+/// Only the first call has an effect.
+pub fn install(app: &str, message_box: bool) {
+    if CONFIG.set(CrashConfig { app: app.to_string(), message_box }).is_err() {
+        return;
+    }
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if REPORTING.swap(true, Ordering::SeqCst) {
+            default_hook(info);
+            return;
+        }
+        if cfg!(debug_assertions) {
+            default_hook(info);
+        }
+        report(info);
+        REPORTING.store(false, Ordering::SeqCst);
+    }));
+}
+
+/// Describe the GPU and driver for crash reports
+pub fn set_gpu_info(info: String) {
+    if let Ok(mut gpu) = GPU_INFO.lock() {
+        *gpu = Some(info);
+    }
+}
+
+fn report(info: &PanicHookInfo) {
+    let thread = std::thread::current();
+    let thread = thread.name().unwrap_or("unnamed");
+    let location = info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())).unwrap_or_default();
+    let message = panic_message(info.payload());
+    log::error!("Thread '{}' panicked at {}: {}", thread, location, message);
+    log::logger().flush();
+
+    let Some(config) = CONFIG.get() else {
+        return;
+    };
+    let text = report_text(thread, &location, &message);
+    match write_report(&config.app, &text) {
+        Ok(path) => {
+            eprintln!("Crash report written to {}", path.display());
+            if config.message_box {
+                show_message_box(
+                    &format!("{} crashed", config.app),
+                    &format!("{}\n\nA crash report was saved to\n{}", message, path.display()),
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!("Could not write crash report: {}\n{}", e, text);
+            if config.message_box {
+                show_message_box(&format!("{} crashed", config.app), &message);
+            }
+        }
+    }
+}
+
+/// Text of a panic payload, which is a `&str` or `String` for `panic!` with a message
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+fn report_text(thread: &str, location: &str, message: &str) -> String {
+    let mut text = String::new();
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let _ = writeln!(text, "Crash report");
+    let _ = writeln!(text, "Time: {} (unix)", time);
+    let _ = writeln!(text, "Engine: {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(text, "Platform: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(text, "Thread: {}", thread);
+    let _ = writeln!(text, "Location: {}", location);
+    let _ = writeln!(text, "Message: {}", message);
+
+    let gpu = GPU_INFO.lock().ok().and_then(|gpu| gpu.clone());
+    let _ = writeln!(text, "\nGPU:\n{}", gpu.as_deref().unwrap_or("not initialized"));
+
+    let _ = writeln!(text, "\nBacktrace:\n{}", std::backtrace::Backtrace::force_capture());
+
+    let _ = writeln!(text, "\nLast {} log lines:", REPORT_LOG_LINES);
+    for line in logging::recent_lines(REPORT_LOG_LINES, LevelFilter::Trace) {
+        let _ = writeln!(text, "{}", line.formatted());
+    }
+    text
+}
+
+fn write_report(app: &str, text: &str) -> Result<PathBuf> {
+    let dir = storage::data_dir(app)?.join("crashes");
+    std::fs::create_dir_all(&dir).map_err(|e| anyhow!("Failed to create '{}': {}", dir.display(), e))?;
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let path = dir.join(format!("crash-{}.txt", time));
+    std::fs::write(&path, text).map_err(|e| anyhow!("Failed to write '{}': {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Best effort native dialog through the tools each platform ships with
+fn show_message_box(title: &str, text: &str) {
+    let status = if cfg!(target_os = "windows") {
+        let quote = |s: &str| s.replace('\'', "''");
+        let script = format!(
+            "Add-Type -AssemblyName PresentationFramework; [System.Windows.MessageBox]::Show('{}', '{}', 'OK', 'Error')",
+            quote(text),
+            quote(title)
+        );
+        std::process::Command::new("powershell").args(["-NoProfile", "-Command", &script]).status()
+    } else if cfg!(target_os = "macos") {
+        let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let script = format!("display alert \"{}\" message \"{}\" as critical", quote(title), quote(text));
+        std::process::Command::new("osascript").args(["-e", &script]).status()
+    } else {
+        std::process::Command::new("zenity")
+            .args(["--error", "--no-markup", "--title", title, "--text", text])
+            .status()
+            .or_else(|_| std::process::Command::new("kdialog").args(["--title", title, "--error", text]).status())
+    };
+    if let Err(e) = status {
+        eprintln!("Could not show crash dialog: {}", e);
+    }
+}
@@ -0,0 +1,351 @@
+//! Named values tuned while the game runs, e.g. "r.exposure" or "player.jump_speed".
+//! A `CVars` registry holds floats, bools and colors with a default and a help line.
+//! Code reads them or subscribes to changes with `on_change`; the debug console edits them
+//! (`ConsoleComponent::register_cvars`) and so does a `PropertyGrid` kept in sync by
+//! `CVars::sync_panel`. Values that differ from their defaults are saved as RON in the
+//! app's config directory and restored when the same names are registered on the next run.
+
+use anyhow::{anyhow, bail, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::math::Color;
+use crate::storage::{config_dir, read_ron, write_atomic, Value};
+
+const FILE_NAME: &str = "cvars.ron";
+
+/// Value of a cvar, its kind is fixed when it is registered
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CVarValue {
+    Float(f32),
+    Bool(bool),
+    /// Written as hex, alpha included
+    Color(Color),
+}
+
+impl CVarValue {
+    /// Parse text as a value of the same kind, as typed in the console
+    /// Bools take true/false, on/off or 1/0 and colors a hex code such as #ff8800.
+    pub fn parse_like(&self, text: &str) -> Option<Self> {
+        match self {
+            CVarValue::Float(_) => text.parse().ok().filter(|v: &f32| v.is_finite()).map(CVarValue::Float),
+            CVarValue::Bool(_) => match text.to_ascii_lowercase().as_str() {
+                "true" | "on" | "1" => Some(CVarValue::Bool(true)),
+                "false" | "off" | "0" => Some(CVarValue::Bool(false)),
+                _ => None,
+            },
+            CVarValue::Color(_) => Color::hex(text).map(CVarValue::Color),
+        }
+    }
+
+    fn same_kind(&self, other: &CVarValue) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    fn to_value(self) -> Value {
+        match self {
+            // Through the shortest text of the f32, so 0.35 isn't written as 0.3499999940395355
+            CVarValue::Float(v) => Value::Number(v.to_string().parse().unwrap_or(v as f64)),
+            CVarValue::Bool(b) => Value::Bool(b),
+            CVarValue::Color(c) => Value::String(c.to_hex(true)),
+        }
+    }
+
+    /// A stored value read as the kind of `self`
+    fn read_like(&self, value: &Value) -> Option<Self> {
+        match self {
+            CVarValue::Float(_) => value.as_f64().map(|v| CVarValue::Float(v as f32)),
+            CVarValue::Bool(_) => value.as_bool().map(CVarValue::Bool),
+            CVarValue::Color(_) => value.as_str().and_then(Color::hex).map(CVarValue::Color),
+        }
+    }
+}
+
+impl std::fmt::Display for CVarValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CVarValue::Float(v) => write!(f, "{}", v),
+            CVarValue::Bool(b) => write!(f, "{}", b),
+            CVarValue::Color(c) => write!(f, "{}", c.to_hex(true)),
+        }
+    }
+}
+
+/// Called with the new value of a cvar
+type ChangeCallback = Box<dyn FnMut(CVarValue)>;
+
+struct CVar {
+    help: String,
+    default: CVarValue,
+    value: CVarValue,
+    /// Clamp range for floats
+    range: Option<(f32, f32)>,
+    callbacks: Vec<ChangeCallback>,
+}
+
+/// Registry of named tunable values, see the module docs
+#[derive(Default)]
+pub struct CVars {
+    vars: BTreeMap<String, CVar>,
+    /// Loaded values of cvars not registered (yet), applied when they are
+    stored: BTreeMap<String, Value>,
+    /// A value changed since the last `take_changed`
+    changed: bool,
+}
+
+impl CVars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a cvar, a loaded value for `name` replaces `default` right away
+    /// Registering a name again replaces the cvar and drops its callbacks.
+    pub fn register(&mut self, name: &str, default: CVarValue, help: &str) {
+        let mut cvar = CVar { help: help.to_string(), default, value: default, range: None, callbacks: Vec::new() };
+        if let Some(stored) = self.stored.remove(name) {
+            match default.read_like(&stored) {
+                Some(value) => cvar.value = value,
+                None => log::warn!("Ignoring the saved value of {}, it isn't a {}", name, kind_name(&default)),
+            }
+        }
+        self.vars.insert(name.to_string(), cvar);
+    }
+
+    pub fn register_float(&mut self, name: &str, default: f32, help: &str) {
+        self.register(name, CVarValue::Float(default), help);
+    }
+
+    /// A float kept between `min` and `max`
+    pub fn register_float_range(&mut self, name: &str, default: f32, min: f32, max: f32, help: &str) {
+        self.register(name, CVarValue::Float(default.clamp(min, max)), help);
+        if let Some(cvar) = self.vars.get_mut(name) {
+            cvar.range = Some((min, max));
+            cvar.value = clamp(cvar.value, cvar.range);
+        }
+    }
+
+    pub fn register_bool(&mut self, name: &str, default: bool, help: &str) {
+        self.register(name, CVarValue::Bool(default), help);
+    }
+
+    pub fn register_color(&mut self, name: &str, default: Color, help: &str) {
+        self.register(name, CVarValue::Color(default), help);
+    }
+
+    /// Names of all cvars in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.vars.keys().map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.vars.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<CVarValue> {
+        self.vars.get(name).map(|cvar| cvar.value)
+    }
+
+    pub fn float(&self, name: &str) -> Option<f32> {
+        match self.get(name)? {
+            CVarValue::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            CVarValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn color(&self, name: &str) -> Option<Color> {
+        match self.get(name)? {
+            CVarValue::Color(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn help(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(|cvar| cvar.help.as_str())
+    }
+
+    pub fn default_value(&self, name: &str) -> Option<CVarValue> {
+        self.vars.get(name).map(|cvar| cvar.default)
+    }
+
+    /// Change a cvar and call its callbacks if the value changed
+    /// Floats are clamped to their range. Fails for unknown names and values of another kind.
+    pub fn set(&mut self, name: &str, value: CVarValue) -> Result<()> {
+        let cvar = self.vars.get_mut(name).ok_or_else(|| anyhow!("Unknown cvar '{}'", name))?;
+        if !cvar.default.same_kind(&value) {
+            bail!("{} is a {}", name, kind_name(&cvar.default));
+        }
+        let value = clamp(value, cvar.range);
+        if cvar.value == value {
+            return Ok(());
+        }
+        cvar.value = value;
+        for callback in &mut cvar.callbacks {
+            callback(value);
+        }
+        self.changed = true;
+        Ok(())
+    }
+
+    /// Change a cvar to a value typed as text, see `CVarValue::parse_like`
+    pub fn set_text(&mut self, name: &str, text: &str) -> Result<()> {
+        let current = self.get(name).ok_or_else(|| anyhow!("Unknown cvar '{}'", name))?;
+        let value = current.parse_like(text).ok_or_else(|| anyhow!("'{}' is not a {}", text, kind_name(&current)))?;
+        self.set(name, value)
+    }
+
+    /// Flip a bool cvar
+    pub fn toggle(&mut self, name: &str) -> Result<()> {
+        match self.get(name).ok_or_else(|| anyhow!("Unknown cvar '{}'", name))? {
+            CVarValue::Bool(b) => self.set(name, CVarValue::Bool(!b)),
+            _ => bail!("{} is not a bool", name),
+        }
+    }
+
+    /// Set a cvar back to its default
+    pub fn reset(&mut self, name: &str) -> Result<()> {
+        let default = self.default_value(name).ok_or_else(|| anyhow!("Unknown cvar '{}'", name))?;
+        self.set(name, default)
+    }
+
+    /// Call `callback` with the cvar's value now and whenever it changes
+    /// Calling it right away applies values loaded from the last run without extra code.
+    pub fn on_change<F>(&mut self, name: &str, mut callback: F) -> Result<()>
+    where
+        F: FnMut(CVarValue) + 'static,
+    {
+        let cvar = self.vars.get_mut(name).ok_or_else(|| anyhow!("Unknown cvar '{}'", name))?;
+        callback(cvar.value);
+        cvar.callbacks.push(Box::new(callback));
+        Ok(())
+    }
+
+    /// Whether a value changed since the last call, e.g. to save them
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Values that differ from their defaults, and loaded ones not registered this run
+    pub fn to_value(&self) -> Value {
+        let mut entries: BTreeMap<String, Value> = self.stored.clone();
+        for (name, cvar) in &self.vars {
+            if cvar.value != cvar.default {
+                entries.insert(name.clone(), cvar.value.to_value());
+            }
+        }
+        Value::Map(entries.into_iter().collect())
+    }
+
+    /// Restore values saved by `to_value`, without calling callbacks
+    /// Values for names not registered yet are kept until they are.
+    pub fn apply_value(&mut self, value: &Value) {
+        for (name, stored) in value.entries().unwrap_or_default() {
+            let Some(cvar) = self.vars.get_mut(name) else {
+                self.stored.insert(name.clone(), stored.clone());
+                continue;
+            };
+            match cvar.default.read_like(stored) {
+                Some(value) => cvar.value = clamp(value, cvar.range),
+                None => log::warn!("Ignoring the saved value of {}, it isn't a {}", name, kind_name(&cvar.default)),
+            }
+        }
+    }
+
+    /// Path of an app's cvar file
+    pub fn path(app: &str) -> Result<PathBuf> {
+        Ok(config_dir(app)?.join(FILE_NAME))
+    }
+
+    pub fn load_from(&mut self, path: &Path) -> Result<()> {
+        self.apply_value(&read_ron(path)?);
+        Ok(())
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        write_atomic(path, &self.to_value().to_text())
+    }
+
+    /// Restore an app's values from the last run, a missing file is not an error
+    pub fn load(&mut self, app: &str) -> Result<()> {
+        let path = Self::path(app)?;
+        if path.exists() {
+            self.load_from(&path)?;
+            log::info!("Loaded cvars from '{}'", path.display());
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, app: &str) -> Result<()> {
+        self.save_to(&Self::path(app)?)
+    }
+}
+
+#[cfg(feature = "editor-widgets")]
+impl CVars {
+    /// A property row per cvar under a header per prefix, "r.exposure" is "exposure" under "r"
+    pub fn properties(&self) -> Vec<crate::gui::Property> {
+        use crate::gui::Property;
+
+        let mut properties = Vec::new();
+        let mut section = None;
+        for (name, cvar) in &self.vars {
+            let (prefix, label) = name.rsplit_once('.').map_or(("", name.as_str()), |(prefix, label)| (prefix, label));
+            if section != Some(prefix) && !prefix.is_empty() {
+                properties.push(Property::header(prefix, prefix));
+            }
+            section = Some(prefix);
+            let property = match cvar.value {
+                CVarValue::Float(v) => Property::float(name, label, v),
+                CVarValue::Bool(b) => Property::boolean(name, label, b),
+                CVarValue::Color(c) => Property::color(name, label, c),
+            };
+            properties.push(match cvar.range {
+                Some((min, max)) => property.with_range(min, max),
+                None => property,
+            });
+        }
+        properties
+    }
+
+    /// Apply the edits made in a tweak panel and show the current values in it
+    /// Call once per frame before rendering, it refreshes the grid.
+    pub fn sync_panel(&mut self, grid: &mut crate::gui::PropertyGrid, context: &std::sync::Arc<crate::renderer::VulkanContext>) -> Result<()> {
+        use crate::gui::PropertyValue;
+
+        for (name, edit) in grid.take_edits() {
+            let value = match edit {
+                PropertyValue::Float(v) => CVarValue::Float(v),
+                PropertyValue::Bool(b) => CVarValue::Bool(b),
+                // The picker edits the color channels only
+                PropertyValue::Color(c) => CVarValue::Color(c.with_alpha(self.color(&name).map_or(1.0, |old| old.a))),
+                PropertyValue::Header => continue,
+            };
+            if let Err(e) = self.set(&name, value) {
+                log::warn!("{}", e);
+            }
+        }
+        grid.set_properties(self.properties());
+        grid.refresh(context)
+    }
+}
+
+fn kind_name(value: &CVarValue) -> &'static str {
+    match value {
+        CVarValue::Float(_) => "number",
+        CVarValue::Bool(_) => "bool",
+        CVarValue::Color(_) => "color",
+    }
+}
+
+fn clamp(value: CVarValue, range: Option<(f32, f32)>) -> CVarValue {
+    match (value, range) {
+        (CVarValue::Float(v), Some((min, max))) => CVarValue::Float(v.clamp(min, max)),
+        _ => value,
+    }
+}
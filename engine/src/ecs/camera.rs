@@ -1,3 +1,7 @@
+use std::any::Any;
+
+use crate::ecs::ECSComponent;
+
 #[derive(Debug, Clone)]
 pub struct Camera {
     pub fov: f32,
@@ -6,7 +10,19 @@ pub struct Camera {
     pub orthographic: bool,
 }
 
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            fov: 60.0,
+            near: 0.1,
+            far: 1000.0,
+            orthographic: true,
+        }
+    }
+}
+
 impl ECSComponent for Camera {
     fn as_any(&self) -> &dyn Any { self }
     fn as_any_mut(&mut self) -> &mut dyn Any { self }
-}
\ No newline at end of file
+    fn clone_box(&self) -> Box<dyn ECSComponent> { Box::new(self.clone()) }
+}
@@ -0,0 +1,153 @@
+use crate::ecs::{ECSComponent, EntityId, World};
+
+/// An entity a command refers to, one that exists or one spawned earlier in the same buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandEntity {
+    Existing(EntityId),
+    /// Index among the spawns of the buffer, see `Commands::spawn`
+    Spawned(usize),
+}
+
+impl From<EntityId> for CommandEntity {
+    fn from(id: EntityId) -> Self {
+        CommandEntity::Existing(id)
+    }
+}
+
+type Edit = Box<dyn FnOnce(&mut World, EntityId)>;
+
+enum Command {
+    Spawn { name: String, parent: Option<CommandEntity> },
+    Despawn(CommandEntity),
+    SetParent(CommandEntity, Option<CommandEntity>),
+    /// Insert or remove a component
+    Edit(CommandEntity, Edit),
+    Run(Box<dyn FnOnce(&mut World)>),
+}
+
+/// Structural changes recorded while the World is borrowed, e.g. while iterating its
+/// entities, and applied in order later
+///
+/// Systems added with `Schedule::add_command_fn` get a read-only World and a buffer that is
+/// applied as soon as the system returns, before the next system runs. Entities spawned by
+/// the buffer can be used by the commands after their spawn, ids are only known once it is
+/// applied. Commands on entities that are gone by then do nothing.
+#[derive(Default)]
+pub struct Commands {
+    commands: Vec<Command>,
+    spawns: usize,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn a root entity
+    pub fn spawn(&mut self, name: &str) -> CommandEntity {
+        self.push_spawn(name, None)
+    }
+
+    /// Spawn an entity parented to `parent`
+    pub fn spawn_child(&mut self, parent: impl Into<CommandEntity>, name: &str) -> CommandEntity {
+        self.push_spawn(name, Some(parent.into()))
+    }
+
+    fn push_spawn(&mut self, name: &str, parent: Option<CommandEntity>) -> CommandEntity {
+        self.commands.push(Command::Spawn { name: name.to_string(), parent });
+        self.spawns += 1;
+        CommandEntity::Spawned(self.spawns - 1)
+    }
+
+    /// Despawn an entity and all of its descendants
+    pub fn despawn(&mut self, entity: impl Into<CommandEntity>) {
+        self.commands.push(Command::Despawn(entity.into()));
+    }
+
+    /// Move an entity under `parent`, or to the roots with `None`
+    pub fn set_parent(&mut self, entity: impl Into<CommandEntity>, parent: Option<CommandEntity>) {
+        self.commands.push(Command::SetParent(entity.into(), parent));
+    }
+
+    /// Add or replace a component
+    pub fn insert<T: ECSComponent>(&mut self, entity: impl Into<CommandEntity>, component: T) {
+        self.commands.push(Command::Edit(entity.into(), Box::new(move |world, id| world.insert(id, component))));
+    }
+
+    /// Remove a component, if the entity has one
+    pub fn remove<T: ECSComponent>(&mut self, entity: impl Into<CommandEntity>) {
+        self.commands.push(Command::Edit(entity.into(), Box::new(|world, id| {
+            world.remove::<T>(id);
+        })));
+    }
+
+    /// Run any other change with the whole World, in order with the other commands
+    pub fn add(&mut self, command: impl FnOnce(&mut World) + 'static) {
+        self.commands.push(Command::Run(Box::new(command)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Drop every recorded command
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.spawns = 0;
+    }
+
+    /// Apply the commands in the order they were recorded and empty the buffer
+    /// Returns the ids of the spawned entities, by spawn index.
+    pub fn apply(&mut self, world: &mut World) -> Vec<EntityId> {
+        crate::profile_scope!("commands");
+        let mut spawned = Vec::with_capacity(self.spawns);
+        self.spawns = 0;
+        let resolve = |spawned: &[EntityId], entity: CommandEntity| match entity {
+            CommandEntity::Existing(id) => Some(id),
+            CommandEntity::Spawned(index) => spawned.get(index).copied(),
+        };
+        for command in self.commands.drain(..) {
+            match command {
+                Command::Spawn { name, parent } => {
+                    let id = world.spawn(&name);
+                    if let Some(parent) = parent.and_then(|parent| resolve(&spawned, parent)) {
+                        world.set_parent(id, Some(parent));
+                    }
+                    spawned.push(id);
+                }
+                Command::Despawn(entity) => {
+                    if let Some(id) = resolve(&spawned, entity) {
+                        world.despawn(id);
+                    }
+                }
+                Command::SetParent(entity, parent) => {
+                    let Some(id) = resolve(&spawned, entity) else {
+                        continue;
+                    };
+                    // A parent that is gone leaves the entity where it is
+                    match parent {
+                        Some(parent) => {
+                            if let Some(parent) = resolve(&spawned, parent) {
+                                world.set_parent(id, Some(parent));
+                            }
+                        }
+                        None => {
+                            world.set_parent(id, None);
+                        }
+                    }
+                }
+                Command::Edit(entity, edit) => {
+                    if let Some(id) = resolve(&spawned, entity).filter(|&id| world.contains(id)) {
+                        edit(world, id);
+                    }
+                }
+                Command::Run(command) => command(world),
+            }
+        }
+        spawned
+    }
+}
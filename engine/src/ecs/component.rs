@@ -4,11 +4,14 @@ use std::any::Any;
 pub trait ECSComponent: Any {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Copy of the component, used to snapshot the World
+    fn clone_box(&self) -> Box<dyn ECSComponent>;
 }
 
 impl ECSComponent for Transform {
     fn as_any(&self) -> &dyn Any { self }
     fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn clone_box(&self) -> Box<dyn ECSComponent> { Box::new(*self) }
 }
 
 
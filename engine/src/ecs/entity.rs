@@ -1,26 +1,62 @@
+use std::any::TypeId;
 use std::collections::HashMap;
-use ecs::{EntityId, Component};
 
-struct EntityId {
-    id: u32,
-}
+use crate::ecs::ECSComponent;
+
+/// Stable identifier of an entity inside a World
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId(pub u32);
 
-struct Entity {
-    id: EntityId,
-    name: String,
-    children: Vec<EntityId>,
-    components: HashMap<TypeId, HashMap<String, Box<dyn Component>>>,   
+/// A named node in the scene hierarchy holding at most one component per type
+pub struct Entity {
+    pub(crate) id: EntityId,
+    pub(crate) name: String,
+    pub(crate) parent: Option<EntityId>,
+    pub(crate) children: Vec<EntityId>,
+    pub(crate) components: HashMap<TypeId, Box<dyn ECSComponent>>,
 }
 
 impl Entity {
     pub fn new(id: EntityId, name: &str) -> Self {
-        
         Entity {
             id,
             name: name.to_string(),
+            parent: None,
             children: Vec::new(),
             components: HashMap::new(),
         }
     }
+
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parent(&self) -> Option<EntityId> {
+        self.parent
+    }
+
+    /// Children in insertion order
+    pub fn children(&self) -> &[EntityId] {
+        &self.children
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.components.len()
+    }
 }
 
+impl Clone for Entity {
+    fn clone(&self) -> Self {
+        Entity {
+            id: self.id,
+            name: self.name.clone(),
+            parent: self.parent,
+            children: self.children.clone(),
+            components: self.components.iter().map(|(&type_id, c)| (type_id, c.clone_box())).collect(),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::any::Any;
+
+use crate::ecs::ECSComponent;
+use crate::math::Color;
+
+/// Point light at the entity's position, lighting the 2D scene within `radius`
+/// Only drawn while the World has a `Lighting2D` resource.
+#[derive(Debug, Clone)]
+pub struct Light2D {
+    pub color: Color,
+    /// Brightness at the center, the light fades out smoothly towards `radius`
+    pub intensity: f32,
+    /// World units the light reaches, ignoring the entity's scale
+    pub radius: f32,
+    /// Size of the light's source, 0 casts hard shadows and larger sources softer penumbras
+    pub source_radius: f32,
+    /// Blocked by entities with a `ShadowCaster`
+    pub casts_shadows: bool,
+}
+
+impl Default for Light2D {
+    fn default() -> Self {
+        Light2D { color: Color::WHITE, intensity: 1.0, radius: 5.0, source_radius: 0.0, casts_shadows: true }
+    }
+}
+
+impl ECSComponent for Light2D {
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn clone_box(&self) -> Box<dyn ECSComponent> { Box::new(self.clone()) }
+}
+
+/// Marks an entity whose quad blocks `Light2D`s, the silhouette shadows are cast from
+#[derive(Debug, Clone, Default)]
+pub struct ShadowCaster;
+
+impl ECSComponent for ShadowCaster {
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn clone_box(&self) -> Box<dyn ECSComponent> { Box::new(self.clone()) }
+}
+
+/// Turns on 2D lighting of the scene when stored as a World resource
+///
+/// Everything is darkened to `ambient` and lit back up by the `Light2D` entities, before the
+/// UI is drawn over it (see `ViewportComponent::render_post`).
+#[derive(Debug, Clone, Copy)]
+pub struct Lighting2D {
+    /// Light reaching every point, black leaves unlit parts fully dark
+    pub ambient: Color,
+}
+
+impl Default for Lighting2D {
+    fn default() -> Self {
+        Lighting2D { ambient: Color::srgb(0.15, 0.15, 0.2) }
+    }
+}
+
+impl Lighting2D {
+    pub fn new(ambient: Color) -> Self {
+        Lighting2D { ambient }
+    }
+}
@@ -1,3 +1,47 @@
-
 mod component;
-pub use component::{ECSComponent};
\ No newline at end of file
+pub use component::{ECSComponent};
+
+mod camera;
+pub use camera::Camera;
+
+mod sprite;
+pub use sprite::Sprite;
+
+mod entity;
+pub use entity::{Entity, EntityId};
+
+mod world;
+pub use world::{ComponentSnapshot, DetachedSubtree, World, WorldSnapshot};
+
+mod system;
+pub use system::{CommandSystem, FnSystem, Schedule, System};
+
+mod commands;
+pub use commands::{CommandEntity, Commands};
+
+mod particles;
+pub use particles::{update_particles, EmissionShape, EmitterSettings, ParticleEmitter};
+
+mod trails;
+pub use trails::{update_trails, TrailRenderer};
+
+mod light2d;
+pub use light2d::{Light2D, Lighting2D, ShadowCaster};
+
+mod state_machine;
+pub use state_machine::{run_state_machines, Condition, StateHook, StateMachine, UpdateHook};
+
+mod timers;
+pub use timers::{update_timers, TimerId, Timers};
+
+mod reflect;
+pub use reflect::{ComponentInfo, ComponentRegistry, FieldValue, Reflect, ReflectedComponent};
+
+mod rewind;
+pub use rewind::Rewind;
+
+mod spatial;
+pub use spatial::{entity_bounds, update_spatial_index, SpatialIndex};
+
+mod scene;
+pub use scene::{change_level, load_scene, load_scene_additive, spawn_scene, unload_scene, update_scenes, SceneId, Scenes};
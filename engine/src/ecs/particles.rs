@@ -0,0 +1,242 @@
+use anyhow::{anyhow, Result};
+use glam::Vec2;
+use std::any::Any;
+
+use crate::ecs::{ECSComponent, EntityId, World};
+use crate::math::rng::Rng;
+use crate::math::{Color, Curve, Gradient, Interpolation};
+
+/// Area new particles appear in, around the emitter's position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmissionShape {
+    Point,
+    /// Disc of radius `shape_size.x`
+    Circle,
+    /// Rectangle of `shape_size`
+    Box,
+}
+
+impl EmissionShape {
+    pub const ALL: [EmissionShape; 3] = [EmissionShape::Point, EmissionShape::Circle, EmissionShape::Box];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EmissionShape::Point => "point",
+            EmissionShape::Circle => "circle",
+            EmissionShape::Box => "box",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|shape| shape.name() == name)
+    }
+}
+
+/// Everything that describes an emitter, the part saved as an asset
+///
+/// Size and color are sampled over a particle's life, 0 at birth and 1 at death.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmitterSettings {
+    /// Particles per second
+    pub spawn_rate: f32,
+    /// Seconds a particle lives
+    pub lifetime: f32,
+    /// Initial speed in world units per second
+    pub speed: f32,
+    /// Angle in radians of the cone particles leave in, centered on +y
+    pub spread: f32,
+    /// Acceleration in world units per second squared
+    pub gravity: Vec2,
+    pub shape: EmissionShape,
+    pub shape_size: Vec2,
+    pub max_particles: usize,
+    /// Size in world units over the lifetime
+    pub size: Curve,
+    pub color: Gradient,
+}
+
+impl Default for EmitterSettings {
+    fn default() -> Self {
+        let mut size = Curve::new();
+        size.add_key(0.0, 0.1, Interpolation::Smooth);
+        size.add_key(0.3, 0.25, Interpolation::Smooth);
+        size.add_key(1.0, 0.0, Interpolation::Linear);
+        EmitterSettings {
+            spawn_rate: 20.0,
+            lifetime: 1.5,
+            speed: 2.0,
+            spread: 0.6,
+            gravity: Vec2::new(0.0, -1.0),
+            shape: EmissionShape::Point,
+            shape_size: Vec2::splat(0.5),
+            max_particles: 256,
+            size,
+            color: Gradient::two(Color::srgb(1.0, 0.85, 0.4), Color::srgba(0.9, 0.2, 0.1, 0.0)),
+        }
+    }
+}
+
+impl EmitterSettings {
+    /// Parse settings written by `to_text`, one "key value" per line
+    /// Missing keys keep their default, unknown keys or bad values fail
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut settings = EmitterSettings::default();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            let vec2 = |value: &str| -> Option<Vec2> {
+                let mut parts = value.split_whitespace().map(str::parse::<f32>);
+                let v = Vec2::new(parts.next()?.ok()?, parts.next()?.ok()?);
+                parts.next().is_none().then_some(v)
+            };
+            match key {
+                "spawn_rate" => settings.spawn_rate = value.parse().ok()?,
+                "lifetime" => settings.lifetime = value.parse().ok()?,
+                "speed" => settings.speed = value.parse().ok()?,
+                "spread" => settings.spread = value.parse().ok()?,
+                "gravity" => settings.gravity = vec2(value)?,
+                "shape" => settings.shape = EmissionShape::from_name(value)?,
+                "shape_size" => settings.shape_size = vec2(value)?,
+                "max_particles" => settings.max_particles = value.parse().ok()?,
+                "size" => settings.size = Curve::parse(value)?,
+                "color" => settings.color = Gradient::parse(value)?,
+                _ => return None,
+            }
+        }
+        Some(settings)
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "spawn_rate {}\nlifetime {}\nspeed {}\nspread {}\ngravity {} {}\nshape {}\nshape_size {} {}\nmax_particles {}\nsize {}\ncolor {}\n",
+            self.spawn_rate,
+            self.lifetime,
+            self.speed,
+            self.spread,
+            self.gravity.x,
+            self.gravity.y,
+            self.shape.name(),
+            self.shape_size.x,
+            self.shape_size.y,
+            self.max_particles,
+            self.size.to_text(),
+            self.color.to_text(),
+        )
+    }
+
+    /// Read an emitter asset written by `save`
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read emitter '{}': {}", path, e))?;
+        Self::parse(&text).ok_or_else(|| anyhow!("Invalid emitter file '{}'", path))
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        std::fs::write(path, self.to_text()).map_err(|e| anyhow!("Failed to write emitter '{}': {}", path, e))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    /// World space, particles don't follow the emitter once spawned
+    position: Vec2,
+    velocity: Vec2,
+    age: f32,
+}
+
+/// Spawns and simulates particles at the entity's position, see `update_particles`
+#[derive(Clone, Debug)]
+pub struct ParticleEmitter {
+    pub settings: EmitterSettings,
+    /// Stops spawning when false, live particles finish their life
+    pub emitting: bool,
+    particles: Vec<Particle>,
+    /// Fraction of a particle owed from previous updates
+    spawn_debt: f32,
+    rng: Rng,
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self::new(EmitterSettings::default())
+    }
+}
+
+impl ParticleEmitter {
+    pub fn new(settings: EmitterSettings) -> Self {
+        ParticleEmitter {
+            settings,
+            emitting: true,
+            particles: Vec::new(),
+            spawn_debt: 0.0,
+            rng: Rng::from_time(),
+        }
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Remove every live particle
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.spawn_debt = 0.0;
+    }
+
+    /// Age and move particles, then spawn new ones at `origin`
+    pub fn update(&mut self, dt: f32, origin: Vec2) {
+        let settings = &self.settings;
+        let lifetime = settings.lifetime.max(f32::EPSILON);
+        self.particles.retain_mut(|particle| {
+            particle.age += dt;
+            particle.velocity += settings.gravity * dt;
+            particle.position += particle.velocity * dt;
+            particle.age < lifetime
+        });
+
+        if !self.emitting {
+            self.spawn_debt = 0.0;
+            return;
+        }
+        self.spawn_debt += settings.spawn_rate.max(0.0) * dt;
+        while self.spawn_debt >= 1.0 && self.particles.len() < settings.max_particles {
+            self.spawn_debt -= 1.0;
+            let offset = match settings.shape {
+                EmissionShape::Point => Vec2::ZERO,
+                EmissionShape::Circle => self.rng.in_unit_circle() * settings.shape_size.x,
+                EmissionShape::Box => Vec2::new(self.rng.range_f32(-0.5, 0.5), self.rng.range_f32(-0.5, 0.5)) * settings.shape_size,
+            };
+            let angle = self.rng.range_f32(-0.5, 0.5) * settings.spread;
+            let direction = Vec2::from_angle(angle).rotate(Vec2::Y);
+            self.particles.push(Particle { position: origin + offset, velocity: direction * settings.speed, age: 0.0 });
+        }
+        // Don't save up a burst while at the particle limit
+        self.spawn_debt = self.spawn_debt.min(1.0);
+    }
+
+    /// World position, size and color of every live particle, oldest first
+    pub fn particles(&self) -> impl Iterator<Item = (Vec2, f32, Color)> + '_ {
+        let lifetime = self.settings.lifetime.max(f32::EPSILON);
+        self.particles.iter().map(move |particle| {
+            let t = particle.age / lifetime;
+            (particle.position, self.settings.size.sample(t), self.settings.color.sample(t))
+        })
+    }
+}
+
+impl ECSComponent for ParticleEmitter {
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn clone_box(&self) -> Box<dyn ECSComponent> { Box::new(self.clone()) }
+}
+
+/// Update every emitter with the translation of its entity's world transform
+pub fn update_particles(world: &mut World, dt: f32) {
+    crate::profile_scope!("particles");
+    let emitters: Vec<EntityId> = world.entity_ids().filter(|&id| world.has::<ParticleEmitter>(id)).collect();
+    for id in emitters {
+        let origin = world.world_matrix(id).w_axis.truncate().truncate();
+        if let Some(emitter) = world.get_mut::<ParticleEmitter>(id) {
+            emitter.update(dt, origin);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::any::TypeId;
+use glam::Vec2;
+
+use crate::ecs::{Camera, ECSComponent, EntityId, Light2D, ParticleEmitter, ShadowCaster, Sprite, TrailRenderer, World};
+use crate::math::{Color, Transform};
+use crate::nav::PathFollower;
+
+/// A reflected field value that editors know how to display
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldValue {
+    Float(f32),
+    Vec2(Vec2),
+    Bool(bool),
+    Color(Color),
+}
+
+/// Runtime access to a component's fields by name
+pub trait Reflect {
+    const TYPE_NAME: &'static str;
+
+    /// All editable fields in display order
+    fn fields(&self) -> Vec<(&'static str, FieldValue)>;
+
+    /// Write a field, returns false if the name or value type doesn't match
+    fn set_field(&mut self, name: &str, value: FieldValue) -> bool;
+}
+
+/// The fields of one component of an entity
+#[derive(Clone, Debug)]
+pub struct ReflectedComponent {
+    pub name: &'static str,
+    pub fields: Vec<(&'static str, FieldValue)>,
+}
+
+/// Type-erased reflection functions for a registered component type
+pub struct ComponentInfo {
+    pub type_id: TypeId,
+    pub name: &'static str,
+    fields: fn(&dyn ECSComponent) -> Vec<(&'static str, FieldValue)>,
+    set_field: fn(&mut dyn ECSComponent, &str, FieldValue) -> bool,
+    insert_default: fn(&mut World, EntityId),
+    remove: fn(&mut World, EntityId),
+}
+
+fn fields_of<T: ECSComponent + Reflect>(component: &dyn ECSComponent) -> Vec<(&'static str, FieldValue)> {
+    component.as_any().downcast_ref::<T>().map(Reflect::fields).unwrap_or_default()
+}
+
+fn set_field_of<T: ECSComponent + Reflect>(component: &mut dyn ECSComponent, name: &str, value: FieldValue) -> bool {
+    component
+        .as_any_mut()
+        .downcast_mut::<T>()
+        .is_some_and(|c| c.set_field(name, value))
+}
+
+fn insert_default_of<T: ECSComponent + Default>(world: &mut World, entity: EntityId) {
+    world.insert(entity, T::default());
+}
+
+fn remove_of<T: ECSComponent>(world: &mut World, entity: EntityId) {
+    world.remove::<T>(entity);
+}
+
+/// Registry of reflectable component types, usually stored as a World resource
+pub struct ComponentRegistry {
+    /// Registration order is the display order in editors
+    entries: Vec<ComponentInfo>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        ComponentRegistry { entries: Vec::new() }
+    }
+
+    /// Registry with all built-in engine components
+    pub fn with_engine_components() -> Self {
+        let mut registry = Self::new();
+        registry.register::<Transform>();
+        registry.register::<Camera>();
+        registry.register::<Sprite>();
+        registry.register::<ParticleEmitter>();
+        registry.register::<TrailRenderer>();
+        registry.register::<PathFollower>();
+        registry.register::<Light2D>();
+        registry.register::<ShadowCaster>();
+        registry
+    }
+
+    pub fn register<T: ECSComponent + Reflect + Default>(&mut self) {
+        if self.info(TypeId::of::<T>()).is_some() {
+            return;
+        }
+        self.entries.push(ComponentInfo {
+            type_id: TypeId::of::<T>(),
+            name: T::TYPE_NAME,
+            fields: fields_of::<T>,
+            set_field: set_field_of::<T>,
+            insert_default: insert_default_of::<T>,
+            remove: remove_of::<T>,
+        });
+    }
+
+    pub fn info(&self, type_id: TypeId) -> Option<&ComponentInfo> {
+        self.entries.iter().find(|info| info.type_id == type_id)
+    }
+
+    pub fn info_by_name(&self, name: &str) -> Option<&ComponentInfo> {
+        self.entries.iter().find(|info| info.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ComponentInfo> {
+        self.entries.iter()
+    }
+
+    /// Reflect every registered component on an entity
+    pub fn reflect(&self, world: &World, entity: EntityId) -> Vec<ReflectedComponent> {
+        self.entries
+            .iter()
+            .filter_map(|info| {
+                let component = world.get_dyn(entity, info.type_id)?;
+                Some(ReflectedComponent {
+                    name: info.name,
+                    fields: (info.fields)(component),
+                })
+            })
+            .collect()
+    }
+
+    /// Add a component with its default values to an entity by type name, e.g. when loading
+    /// a scene. Returns false for unregistered names.
+    pub fn insert_default(&self, world: &mut World, entity: EntityId, component: &str) -> bool {
+        let Some(info) = self.info_by_name(component) else {
+            return false;
+        };
+        (info.insert_default)(world, entity);
+        true
+    }
+
+    /// Remove a component from an entity by type name
+    /// Returns false for unregistered names.
+    pub fn remove(&self, world: &mut World, entity: EntityId, component: &str) -> bool {
+        let Some(info) = self.info_by_name(component) else {
+            return false;
+        };
+        (info.remove)(world, entity);
+        true
+    }
+
+    /// Write a single field of a component on an entity
+    pub fn set_field(&self, world: &mut World, entity: EntityId, component: &str, field: &str, value: FieldValue) -> bool {
+        let Some(info) = self.info_by_name(component) else {
+            return false;
+        };
+        match world.get_dyn_mut(entity, info.type_id) {
+            Some(component) => (info.set_field)(component, field, value),
+            None => false,
+        }
+    }
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reflect for Transform {
+    const TYPE_NAME: &'static str = "Transform";
+
+    fn fields(&self) -> Vec<(&'static str, FieldValue)> {
+        vec![
+            ("position", FieldValue::Vec2(self.position)),
+            ("rotation", FieldValue::Float(self.rotation)),
+            ("scale", FieldValue::Vec2(self.scale)),
+        ]
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> bool {
+        match (name, value) {
+            ("position", FieldValue::Vec2(v)) => self.position = v,
+            ("rotation", FieldValue::Float(v)) => self.rotation = v,
+            ("scale", FieldValue::Vec2(v)) => self.scale = v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Reflect for Sprite {
+    const TYPE_NAME: &'static str = "Sprite";
+
+    fn fields(&self) -> Vec<(&'static str, FieldValue)> {
+        vec![("color", FieldValue::Color(self.color))]
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> bool {
+        match (name, value) {
+            ("color", FieldValue::Color(v)) => self.color = v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Reflect for Camera {
+    const TYPE_NAME: &'static str = "Camera";
+
+    fn fields(&self) -> Vec<(&'static str, FieldValue)> {
+        vec![
+            ("fov", FieldValue::Float(self.fov)),
+            ("near", FieldValue::Float(self.near)),
+            ("far", FieldValue::Float(self.far)),
+            ("orthographic", FieldValue::Bool(self.orthographic)),
+        ]
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> bool {
+        match (name, value) {
+            ("fov", FieldValue::Float(v)) => self.fov = v,
+            ("near", FieldValue::Float(v)) => self.near = v,
+            ("far", FieldValue::Float(v)) => self.far = v,
+            ("orthographic", FieldValue::Bool(v)) => self.orthographic = v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Scalar settings only, the curves, gradient and shape have their own editor panel
+impl Reflect for ParticleEmitter {
+    const TYPE_NAME: &'static str = "ParticleEmitter";
+
+    fn fields(&self) -> Vec<(&'static str, FieldValue)> {
+        vec![
+            ("emitting", FieldValue::Bool(self.emitting)),
+            ("spawn_rate", FieldValue::Float(self.settings.spawn_rate)),
+            ("lifetime", FieldValue::Float(self.settings.lifetime)),
+            ("speed", FieldValue::Float(self.settings.speed)),
+            ("spread", FieldValue::Float(self.settings.spread)),
+            ("gravity", FieldValue::Vec2(self.settings.gravity)),
+            ("shape_size", FieldValue::Vec2(self.settings.shape_size)),
+        ]
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> bool {
+        match (name, value) {
+            ("emitting", FieldValue::Bool(v)) => self.emitting = v,
+            ("spawn_rate", FieldValue::Float(v)) => self.settings.spawn_rate = v,
+            ("lifetime", FieldValue::Float(v)) => self.settings.lifetime = v,
+            ("speed", FieldValue::Float(v)) => self.settings.speed = v,
+            ("spread", FieldValue::Float(v)) => self.settings.spread = v,
+            ("gravity", FieldValue::Vec2(v)) => self.settings.gravity = v,
+            ("shape_size", FieldValue::Vec2(v)) => self.settings.shape_size = v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Scalar settings only, like `ParticleEmitter` the width curve and gradient aren't reflected
+impl Reflect for TrailRenderer {
+    const TYPE_NAME: &'static str = "TrailRenderer";
+
+    fn fields(&self) -> Vec<(&'static str, FieldValue)> {
+        vec![
+            ("emitting", FieldValue::Bool(self.emitting)),
+            ("lifetime", FieldValue::Float(self.lifetime)),
+            ("min_distance", FieldValue::Float(self.min_distance)),
+        ]
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> bool {
+        match (name, value) {
+            ("emitting", FieldValue::Bool(v)) => self.emitting = v,
+            ("lifetime", FieldValue::Float(v)) => self.lifetime = v,
+            ("min_distance", FieldValue::Float(v)) => self.min_distance = v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Reflect for PathFollower {
+    const TYPE_NAME: &'static str = "PathFollower";
+
+    fn fields(&self) -> Vec<(&'static str, FieldValue)> {
+        vec![
+            ("speed", FieldValue::Float(self.speed)),
+            ("arrive_distance", FieldValue::Float(self.arrive_distance)),
+        ]
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> bool {
+        match (name, value) {
+            ("speed", FieldValue::Float(v)) => self.speed = v,
+            ("arrive_distance", FieldValue::Float(v)) => self.arrive_distance = v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Reflect for Light2D {
+    const TYPE_NAME: &'static str = "Light2D";
+
+    fn fields(&self) -> Vec<(&'static str, FieldValue)> {
+        vec![
+            ("color", FieldValue::Color(self.color)),
+            ("intensity", FieldValue::Float(self.intensity)),
+            ("radius", FieldValue::Float(self.radius)),
+            ("source_radius", FieldValue::Float(self.source_radius)),
+            ("casts_shadows", FieldValue::Bool(self.casts_shadows)),
+        ]
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> bool {
+        match (name, value) {
+            ("color", FieldValue::Color(v)) => self.color = v,
+            ("intensity", FieldValue::Float(v)) => self.intensity = v,
+            ("radius", FieldValue::Float(v)) => self.radius = v,
+            ("source_radius", FieldValue::Float(v)) => self.source_radius = v,
+            ("casts_shadows", FieldValue::Bool(v)) => self.casts_shadows = v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// A marker, adding and removing it is all there is to edit
+impl Reflect for ShadowCaster {
+    const TYPE_NAME: &'static str = "ShadowCaster";
+
+    fn fields(&self) -> Vec<(&'static str, FieldValue)> {
+        Vec::new()
+    }
+
+    fn set_field(&mut self, _name: &str, _value: FieldValue) -> bool {
+        false
+    }
+}
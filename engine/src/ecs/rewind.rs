@@ -0,0 +1,78 @@
+use std::collections::VecDeque;
+
+use crate::ecs::{ComponentRegistry, ComponentSnapshot, World};
+
+/// Component snapshots of recent ticks, oldest first, to go back to
+///
+/// Games record one per frame for a rewind mechanic, networked games one per simulation tick
+/// so a late server update can roll back to its tick and simulate forward again. Only the
+/// newest `capacity` ticks are kept.
+pub struct Rewind {
+    snapshots: VecDeque<(u32, ComponentSnapshot)>,
+    capacity: usize,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize) -> Self {
+        Rewind { snapshots: VecDeque::with_capacity(capacity), capacity: capacity.max(1) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    pub fn oldest_tick(&self) -> Option<u32> {
+        self.snapshots.front().map(|(tick, _)| *tick)
+    }
+
+    pub fn newest_tick(&self) -> Option<u32> {
+        self.snapshots.back().map(|(tick, _)| *tick)
+    }
+
+    /// Capture the World as the state of `tick`
+    /// Snapshots of `tick` and later ticks are replaced, they belong to a timeline that
+    /// was rolled back.
+    pub fn record(&mut self, tick: u32, world: &World, registry: &ComponentRegistry) {
+        self.snapshots.retain(|(recorded, _)| *recorded < tick);
+        self.snapshots.push_back((tick, world.snapshot_components(registry)));
+        while self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+    }
+
+    pub fn get(&self, tick: u32) -> Option<&ComponentSnapshot> {
+        self.snapshots.iter().find(|(recorded, _)| *recorded == tick).map(|(_, snapshot)| snapshot)
+    }
+
+    /// Restore the state recorded for `tick` and forget the ticks after it
+    /// Returns false, leaving the World alone, if `tick` isn't recorded.
+    pub fn rollback(&mut self, tick: u32, world: &mut World, registry: &ComponentRegistry) -> bool {
+        let Some(index) = self.snapshots.iter().position(|(recorded, _)| *recorded == tick) else {
+            return false;
+        };
+        self.snapshots.truncate(index + 1);
+        world.restore_components(registry, &self.snapshots[index].1);
+        true
+    }
+
+    /// Go back `steps` recorded ticks from the newest, stopping at the oldest
+    /// Returns the tick restored, None when nothing is recorded.
+    pub fn rewind(&mut self, steps: usize, world: &mut World, registry: &ComponentRegistry) -> Option<u32> {
+        let index = self.snapshots.len().checked_sub(1)?.saturating_sub(steps);
+        let tick = self.snapshots[index].0;
+        self.rollback(tick, world, registry);
+        Some(tick)
+    }
+}
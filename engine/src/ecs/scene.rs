@@ -0,0 +1,353 @@
+use anyhow::{anyhow, bail, Context, Result};
+use glam::Vec2;
+use std::path::{Path, PathBuf};
+
+use crate::ecs::{ComponentRegistry, EntityId, FieldValue, World};
+use crate::math::Color;
+use crate::storage::{read_ron, Value};
+use crate::tasks::{self, Task};
+
+/// Field of an entity holding its children instead of a component
+const CHILDREN: &str = "children";
+
+/// Spawn the entities of a scene file into `world`, returns the new root entities
+///
+/// A scene is a RON map from entity names to their components, which are created with
+/// their defaults and then get the fields written in the file:
+///
+/// ```text
+/// {
+///     "Player": (
+///         Transform: (position: (x: -3, y: 0)),
+///         Sprite: (color: "#3373e6"),
+///         children: {
+///             "Camera": (Transform: (position: (x: 0, y: 1.5)), Camera: ()),
+///         },
+///     ),
+/// }
+/// ```
+///
+/// Components are looked up by name in `registry`, vectors are written as `(x: .., y: ..)`
+/// and colors as sRGB `"#RRGGBB[AA]"` or `(r: .., g: .., b: .., a: ..)`.
+pub fn load_scene(world: &mut World, registry: &ComponentRegistry, path: impl AsRef<Path>) -> Result<Vec<EntityId>> {
+    let path = path.as_ref();
+    let scene = read_ron(path)?;
+    spawn_scene(world, registry, &scene).with_context(|| format!("Loading scene '{}'", path.display()))
+}
+
+/// Spawn the entities of an already parsed scene, see `load_scene`
+pub fn spawn_scene(world: &mut World, registry: &ComponentRegistry, scene: &Value) -> Result<Vec<EntityId>> {
+    let Value::Map(entities) = scene else {
+        bail!("A scene is a map from entity names to their components");
+    };
+    let mut roots = Vec::with_capacity(entities.len());
+    for (name, entity) in entities {
+        // Spawned first so a broken scene doesn't leave orphaned children behind
+        let id = world.spawn(name);
+        roots.push(id);
+        if let Err(e) = spawn_entity(world, registry, id, entity) {
+            for root in roots {
+                world.despawn(root);
+            }
+            return Err(e.context(format!("In entity '{}'", name)));
+        }
+    }
+    Ok(roots)
+}
+
+/// Identifies a scene loaded into a World next to others, see `Scenes`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SceneId(pub u32);
+
+struct LoadedScene {
+    id: SceneId,
+    name: String,
+    /// Root entities the scene spawned, or that were moved into it
+    roots: Vec<EntityId>,
+    persistent: bool,
+}
+
+/// A scene file read on a worker thread, spawned by `update_scenes` once it is parsed
+struct PendingScene {
+    id: SceneId,
+    path: PathBuf,
+    persistent: bool,
+    task: Task<Result<Value>>,
+}
+
+/// The scenes loaded into a World side by side, kept as a World resource
+///
+/// Every scene owns the root entities it spawned and `unload_scene` despawns them with their
+/// descendants, so levels can be streamed in chunks and dropped again. Persistent scenes,
+/// e.g. one holding the player, survive `change_level`. Entities spawned from code belong to
+/// no scene until `transfer` moves them into one.
+pub struct Scenes {
+    /// Load order
+    loaded: Vec<LoadedScene>,
+    pending: Vec<PendingScene>,
+    next_id: u32,
+}
+
+impl Scenes {
+    pub fn new() -> Self {
+        Scenes { loaded: Vec::new(), pending: Vec::new(), next_id: 0 }
+    }
+
+    /// Track entities spawned elsewhere (e.g. from code) as a scene called `name`
+    pub fn track(&mut self, name: &str, roots: Vec<EntityId>) -> SceneId {
+        let id = self.allocate();
+        self.loaded.push(LoadedScene { id, name: name.to_string(), roots, persistent: false });
+        id
+    }
+
+    /// Start reading a scene file in the background, `update_scenes` spawns it once it is read
+    pub fn stream(&mut self, path: impl AsRef<Path>) -> SceneId {
+        let id = self.allocate();
+        let path = path.as_ref().to_path_buf();
+        let task = tasks::spawn({
+            let path = path.clone();
+            move || read_ron(&path)
+        });
+        self.pending.push(PendingScene { id, path, persistent: false, task });
+        id
+    }
+
+    fn allocate(&mut self) -> SceneId {
+        let id = SceneId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// Loaded scenes in load order
+    pub fn ids(&self) -> impl Iterator<Item = SceneId> + '_ {
+        self.loaded.iter().map(|scene| scene.id)
+    }
+
+    pub fn is_loaded(&self, id: SceneId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Whether a streamed scene is still being read
+    pub fn is_pending(&self, id: SceneId) -> bool {
+        self.pending.iter().any(|scene| scene.id == id)
+    }
+
+    /// Name of a loaded scene, the file stem for scenes loaded from files
+    pub fn name(&self, id: SceneId) -> Option<&str> {
+        self.get(id).map(|scene| scene.name.as_str())
+    }
+
+    /// Root entities of a loaded scene, despawned ones included until it is unloaded
+    pub fn roots(&self, id: SceneId) -> Option<&[EntityId]> {
+        self.get(id).map(|scene| scene.roots.as_slice())
+    }
+
+    pub fn is_persistent(&self, id: SceneId) -> bool {
+        self.get(id).is_some_and(|scene| scene.persistent)
+            || self.pending.iter().any(|scene| scene.id == id && scene.persistent)
+    }
+
+    /// Keep a scene loaded through `change_level`, returns false if it isn't loaded or streaming
+    pub fn set_persistent(&mut self, id: SceneId, persistent: bool) -> bool {
+        if let Some(scene) = self.loaded.iter_mut().find(|scene| scene.id == id) {
+            scene.persistent = persistent;
+            return true;
+        }
+        match self.pending.iter_mut().find(|scene| scene.id == id) {
+            Some(scene) => {
+                scene.persistent = persistent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Scene the root of `entity` belongs to
+    pub fn scene_of(&self, world: &World, entity: EntityId) -> Option<SceneId> {
+        let mut root = entity;
+        while let Some(parent) = world.parent(root) {
+            root = parent;
+        }
+        self.loaded.iter().find(|scene| scene.roots.contains(&root)).map(|scene| scene.id)
+    }
+
+    /// Move a root entity into the scene `to`, out of the scene it was in
+    /// e.g. an item the player carries into the next level. Returns false if `to` isn't loaded.
+    pub fn transfer(&mut self, entity: EntityId, to: SceneId) -> bool {
+        if !self.is_loaded(to) {
+            return false;
+        }
+        for scene in &mut self.loaded {
+            scene.roots.retain(|&root| root != entity);
+            if scene.id == to {
+                scene.roots.push(entity);
+            }
+        }
+        true
+    }
+
+    fn get(&self, id: SceneId) -> Option<&LoadedScene> {
+        self.loaded.iter().find(|scene| scene.id == id)
+    }
+}
+
+impl Default for Scenes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The World's `Scenes`, inserted on first use
+fn scenes_mut(world: &mut World) -> &mut Scenes {
+    if world.resource::<Scenes>().is_none() {
+        world.insert_resource(Scenes::new());
+    }
+    world.resource_mut::<Scenes>().expect("Inserted above")
+}
+
+fn scene_name(path: &Path) -> String {
+    path.file_stem().map_or_else(|| path.display().to_string(), |stem| stem.to_string_lossy().into_owned())
+}
+
+/// Spawn a scene file next to what is already in `world` and track it in the World's
+/// `Scenes` to unload it later, see `load_scene` for the format
+pub fn load_scene_additive(world: &mut World, registry: &ComponentRegistry, path: impl AsRef<Path>) -> Result<SceneId> {
+    let path = path.as_ref();
+    let roots = load_scene(world, registry, path)?;
+    Ok(scenes_mut(world).track(&scene_name(path), roots))
+}
+
+/// Despawn the entities of a scene and forget it, or stop a streamed scene that is still
+/// being read. Returns false if there is no such scene.
+pub fn unload_scene(world: &mut World, id: SceneId) -> bool {
+    let Some(scenes) = world.resource_mut::<Scenes>() else {
+        return false;
+    };
+    // Dropping the task detaches it, the result is discarded
+    let pending = scenes.pending.len();
+    scenes.pending.retain(|scene| scene.id != id);
+    if scenes.pending.len() != pending {
+        return true;
+    }
+    let Some(index) = scenes.loaded.iter().position(|scene| scene.id == id) else {
+        return false;
+    };
+    let scene = scenes.loaded.remove(index);
+    for root in scene.roots {
+        world.despawn(root);
+    }
+    true
+}
+
+/// Load `path` as the new level: once it spawned, every other scene that isn't persistent
+/// is unloaded, streamed ones included. The old level stays when the new one fails to load.
+pub fn change_level(world: &mut World, registry: &ComponentRegistry, path: impl AsRef<Path>) -> Result<SceneId> {
+    let level = load_scene_additive(world, registry, path)?;
+    let scenes = scenes_mut(world);
+    let stale: Vec<SceneId> = scenes
+        .loaded
+        .iter()
+        .map(|scene| (scene.id, scene.persistent))
+        .chain(scenes.pending.iter().map(|scene| (scene.id, scene.persistent)))
+        .filter(|&(id, persistent)| id != level && !persistent)
+        .map(|(id, _)| id)
+        .collect();
+    for id in stale {
+        unload_scene(world, id);
+    }
+    Ok(level)
+}
+
+/// Spawn the streamed scenes that finished reading, call once per frame
+/// Returns every scene finished since the last call with whether it loaded, failed ones
+/// are forgotten.
+pub fn update_scenes(world: &mut World, registry: &ComponentRegistry) -> Vec<(SceneId, Result<()>)> {
+    let Some(scenes) = world.resource_mut::<Scenes>() else {
+        return Vec::new();
+    };
+    let mut finished = Vec::new();
+    for scene in std::mem::take(&mut scenes.pending) {
+        match scene.task.try_join() {
+            Ok(value) => finished.push((scene.id, scene.path, scene.persistent, value)),
+            Err(task) => scenes.pending.push(PendingScene { task, ..scene }),
+        }
+    }
+
+    let mut results = Vec::with_capacity(finished.len());
+    for (id, path, persistent, value) in finished {
+        let spawned = value.and_then(|value| {
+            spawn_scene(world, registry, &value).with_context(|| format!("Loading scene '{}'", path.display()))
+        });
+        let result = spawned.map(|roots| {
+            let scenes = scenes_mut(world);
+            scenes.loaded.push(LoadedScene { id, name: scene_name(&path), roots, persistent });
+        });
+        results.push((id, result));
+    }
+    results
+}
+
+fn spawn_entity(world: &mut World, registry: &ComponentRegistry, id: EntityId, entity: &Value) -> Result<()> {
+    let Value::Struct(components) = entity else {
+        bail!("Expected the components of the entity as (Component: (..), ..)");
+    };
+    for (name, fields) in components {
+        if name == CHILDREN {
+            let Value::Map(children) = fields else {
+                bail!("children is a map from entity names to their components");
+            };
+            for (child_name, child) in children {
+                let child_id = world.spawn_child(id, child_name);
+                spawn_entity(world, registry, child_id, child).with_context(|| format!("In entity '{}'", child_name))?;
+            }
+            continue;
+        }
+        if !registry.insert_default(world, id, name) {
+            bail!("Unknown component {}", name);
+        }
+        let Some(fields) = fields.entries() else {
+            bail!("Expected the fields of {} as (field: value, ..)", name);
+        };
+        let info = registry.info_by_name(name).expect("Inserted by name above");
+        let defaults = registry
+            .reflect(world, id)
+            .into_iter()
+            .find(|component| component.name == info.name)
+            .map(|component| component.fields)
+            .unwrap_or_default();
+        for (field, value) in fields {
+            let Some(&(_, default)) = defaults.iter().find(|(name, _)| name == field) else {
+                bail!("{} has no field {}", name, field);
+            };
+            let value = field_value(value, default).ok_or_else(|| anyhow!("Invalid value for {}.{}: {:?}", name, field, value))?;
+            registry.set_field(world, id, name, field, value);
+        }
+    }
+    Ok(())
+}
+
+/// A value of the same kind as the field's `default`, missing vector and color channels
+/// keep their defaults
+fn field_value(value: &Value, default: FieldValue) -> Option<FieldValue> {
+    let channel = |name: &str, fallback: f32| match value.get(name) {
+        Some(channel) => channel.as_f64().map(|v| v as f32),
+        None => Some(fallback),
+    };
+    match default {
+        FieldValue::Float(_) => value.as_f64().map(|v| FieldValue::Float(v as f32)),
+        FieldValue::Bool(_) => value.as_bool().map(FieldValue::Bool),
+        FieldValue::Vec2(v) => {
+            value.entries()?;
+            Some(FieldValue::Vec2(Vec2::new(channel("x", v.x)?, channel("y", v.y)?)))
+        }
+        FieldValue::Color(c) => match value {
+            Value::String(hex) => Color::hex(hex).map(FieldValue::Color),
+            _ => {
+                value.entries()?;
+                let [r, g, b, a] = c.to_srgb();
+                let color = Color::srgba(channel("r", r)?, channel("g", g)?, channel("b", b)?, channel("a", a)?);
+                Some(FieldValue::Color(color))
+            }
+        },
+    }
+}
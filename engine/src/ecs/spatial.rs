@@ -0,0 +1,88 @@
+use glam::{Vec2, Vec3};
+use std::collections::HashMap;
+
+use crate::ecs::{EntityId, World};
+use crate::math::{Quadtree, Rect, Transform};
+
+/// World space bounds of entities with a Transform, stored as a World resource and kept up
+/// to date by `update_spatial_index`
+///
+/// Answers "which entities are in this rect" without visiting the whole World, the 2D
+/// viewport uses it to draw only what the camera sees. Entities spawned, moved or despawned
+/// since the last update are found where they were, queries check ids are still alive.
+#[derive(Default)]
+pub struct SpatialIndex {
+    tree: Quadtree<EntityId>,
+    /// Bounds each entity was indexed with, to move only the ones that changed
+    bounds: HashMap<EntityId, Rect>,
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    /// Bounds of an entity as of the last update
+    pub fn bounds(&self, id: EntityId) -> Option<Rect> {
+        self.bounds.get(&id).copied()
+    }
+
+    /// Entities whose bounds overlap `area`, in no particular order
+    pub fn query(&self, area: Rect) -> Vec<EntityId> {
+        self.tree.query(area)
+    }
+
+    /// Entities whose bounds contain `point`
+    pub fn query_point(&self, point: Vec2) -> Vec<EntityId> {
+        self.tree.query_point(point)
+    }
+
+    /// Index an entity with new bounds, untouched if they didn't change
+    pub fn update(&mut self, id: EntityId, bounds: Rect) {
+        if self.bounds.get(&id) != Some(&bounds) {
+            self.tree.insert(id, bounds);
+            self.bounds.insert(id, bounds);
+        }
+    }
+
+    pub fn remove(&mut self, id: EntityId) {
+        if self.bounds.remove(&id).is_some() {
+            self.tree.remove(id);
+        }
+    }
+}
+
+/// Box around an entity's unit quad after its world transform, on the XY plane
+pub fn entity_bounds(world: &World, id: EntityId) -> Rect {
+    let matrix = world.world_matrix(id);
+    let corners = [Vec2::new(-0.5, -0.5), Vec2::new(0.5, -0.5), Vec2::new(-0.5, 0.5), Vec2::new(0.5, 0.5)]
+        .map(|corner| matrix.transform_point3(Vec3::new(corner.x, corner.y, 0.0)).truncate());
+    let min = corners.iter().fold(Vec2::INFINITY, |min, corner| min.min(*corner));
+    let max = corners.iter().fold(Vec2::NEG_INFINITY, |max, corner| max.max(*corner));
+    Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+}
+
+/// Bring the World's `SpatialIndex` up to date, inserting one on the first run
+/// Every entity's bounds are recomputed but only those that changed move in the tree.
+pub fn update_spatial_index(world: &mut World, _dt: f32) {
+    crate::profile_scope!("spatial_index");
+    let mut index = world.remove_resource::<SpatialIndex>().unwrap_or_default();
+    for id in world.entity_ids() {
+        if world.has::<Transform>(id) {
+            index.update(id, entity_bounds(world, id));
+        }
+    }
+    let gone: Vec<EntityId> = index.bounds.keys().copied().filter(|&id| !world.has::<Transform>(id)).collect();
+    for id in gone {
+        index.remove(id);
+    }
+    world.insert_resource(index);
+}
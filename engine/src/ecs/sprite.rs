@@ -0,0 +1,22 @@
+use std::any::Any;
+
+use crate::ecs::ECSComponent;
+use crate::math::Color;
+
+/// Flat colored quad drawn at the entity's Transform
+#[derive(Debug, Clone)]
+pub struct Sprite {
+    pub color: Color,
+}
+
+impl Default for Sprite {
+    fn default() -> Self {
+        Sprite { color: Color::WHITE }
+    }
+}
+
+impl ECSComponent for Sprite {
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn clone_box(&self) -> Box<dyn ECSComponent> { Box::new(self.clone()) }
+}
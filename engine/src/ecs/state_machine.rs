@@ -0,0 +1,183 @@
+use std::any::Any;
+use std::rc::Rc;
+
+use crate::ecs::{ECSComponent, EntityId, World};
+
+/// Called when a state is entered or left
+pub type StateHook = Rc<dyn Fn(&mut World, EntityId)>;
+/// Called every update while a state is active, with the time step in seconds
+pub type UpdateHook = Rc<dyn Fn(&mut World, EntityId, f32)>;
+/// Decides whether a transition is taken
+pub type Condition = Rc<dyn Fn(&World, EntityId) -> bool>;
+
+#[derive(Clone)]
+struct State {
+    name: String,
+    on_enter: Option<StateHook>,
+    on_exit: Option<StateHook>,
+    on_update: Option<UpdateHook>,
+}
+
+#[derive(Clone)]
+struct Transition {
+    /// `None` for transitions taken from any state
+    from: Option<usize>,
+    to: usize,
+    condition: Condition,
+}
+
+/// Named states with hooks and conditional transitions between them, e.g. an enemy
+/// that wanders until the player comes close and then chases them
+///
+/// Built once by chaining calls, states are referred to by name and created on first
+/// mention. Each update, the transitions from the current state (then the ones from
+/// any state) are checked in the order they were added and the first whose condition
+/// holds is taken: the old state's exit hook runs, then the new state's enter hook,
+/// then the update hook of whichever state is current. The first state added is the
+/// initial one, entered on the first update.
+#[derive(Clone)]
+pub struct StateMachine {
+    states: Vec<State>,
+    transitions: Vec<Transition>,
+    current: usize,
+    entered: bool,
+    /// State requested with `force`, taken on the next update
+    forced: Option<usize>,
+    /// Seconds since the current state was entered
+    time_in_state: f32,
+}
+
+impl StateMachine {
+    pub fn new(initial: &str) -> Self {
+        let mut machine = StateMachine {
+            states: Vec::new(),
+            transitions: Vec::new(),
+            current: 0,
+            entered: false,
+            forced: None,
+            time_in_state: 0.0,
+        };
+        machine.state_index(initial);
+        machine
+    }
+
+    /// Index of a state, adding it if it doesn't exist yet
+    fn state_index(&mut self, name: &str) -> usize {
+        if let Some(index) = self.states.iter().position(|state| state.name == name) {
+            return index;
+        }
+        self.states.push(State { name: name.to_string(), on_enter: None, on_exit: None, on_update: None });
+        self.states.len() - 1
+    }
+
+    pub fn state(mut self, name: &str) -> Self {
+        self.state_index(name);
+        self
+    }
+
+    pub fn on_enter(mut self, state: &str, hook: impl Fn(&mut World, EntityId) + 'static) -> Self {
+        let index = self.state_index(state);
+        self.states[index].on_enter = Some(Rc::new(hook));
+        self
+    }
+
+    pub fn on_exit(mut self, state: &str, hook: impl Fn(&mut World, EntityId) + 'static) -> Self {
+        let index = self.state_index(state);
+        self.states[index].on_exit = Some(Rc::new(hook));
+        self
+    }
+
+    pub fn on_update(mut self, state: &str, hook: impl Fn(&mut World, EntityId, f32) + 'static) -> Self {
+        let index = self.state_index(state);
+        self.states[index].on_update = Some(Rc::new(hook));
+        self
+    }
+
+    /// Go from `from` to `to` when `condition` holds
+    pub fn transition(mut self, from: &str, to: &str, condition: impl Fn(&World, EntityId) -> bool + 'static) -> Self {
+        let (from, to) = (self.state_index(from), self.state_index(to));
+        self.transitions.push(Transition { from: Some(from), to, condition: Rc::new(condition) });
+        self
+    }
+
+    /// Go to `to` from any other state when `condition` holds
+    pub fn transition_from_any(mut self, to: &str, condition: impl Fn(&World, EntityId) -> bool + 'static) -> Self {
+        let to = self.state_index(to);
+        self.transitions.push(Transition { from: None, to, condition: Rc::new(condition) });
+        self
+    }
+
+    pub fn current(&self) -> &str {
+        &self.states[self.current].name
+    }
+
+    pub fn time_in_state(&self) -> f32 {
+        self.time_in_state
+    }
+
+    /// Switch state on the next update regardless of conditions, running the usual hooks
+    /// Returns false if there is no state of that name
+    pub fn force(&mut self, state: &str) -> bool {
+        self.forced = self.states.iter().position(|s| s.name == state);
+        self.forced.is_some()
+    }
+
+    /// Target of the first transition whose condition holds
+    fn next_state(&self, world: &World, entity: EntityId) -> Option<usize> {
+        let current = Some(self.current);
+        let from_current = self.transitions.iter().filter(|t| t.from == current);
+        let from_any = self.transitions.iter().filter(|t| t.from.is_none() && t.to != self.current);
+        from_current.chain(from_any).find(|t| (t.condition)(world, entity)).map(|t| t.to)
+    }
+
+    /// Take transitions and run hooks, the machine must not be in the World while this runs
+    fn update(&mut self, world: &mut World, entity: EntityId, dt: f32) {
+        if !self.entered {
+            self.entered = true;
+            if let Some(hook) = &self.states[self.current].on_enter {
+                hook(world, entity);
+            }
+        }
+
+        let next = self.forced.take().or_else(|| self.next_state(world, entity));
+        if let Some(next) = next {
+            if let Some(hook) = &self.states[self.current].on_exit {
+                hook(world, entity);
+            }
+            self.current = next;
+            self.time_in_state = 0.0;
+            if let Some(hook) = &self.states[self.current].on_enter {
+                hook(world, entity);
+            }
+        }
+
+        self.time_in_state += dt;
+        if let Some(hook) = &self.states[self.current].on_update {
+            hook(world, entity, dt);
+        }
+    }
+}
+
+impl ECSComponent for StateMachine {
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn clone_box(&self) -> Box<dyn ECSComponent> { Box::new(self.clone()) }
+}
+
+/// Update every StateMachine in the World
+///
+/// Each machine is taken out of its entity while its hooks run, so hooks get the
+/// whole World mutably. A hook may despawn its entity or replace the machine.
+pub fn run_state_machines(world: &mut World, dt: f32) {
+    crate::profile_scope!("state_machines");
+    let entities: Vec<EntityId> = world.entity_ids().filter(|&id| world.has::<StateMachine>(id)).collect();
+    for id in entities {
+        let Some(mut machine) = world.remove::<StateMachine>(id) else {
+            continue;
+        };
+        machine.update(world, id, dt);
+        if world.contains(id) && !world.has::<StateMachine>(id) {
+            world.insert(id, machine);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::ecs::{Commands, World};
+
+/// Game logic run every frame while the game is playing
+pub trait System {
+    /// Name shown in the profiler
+    fn name(&self) -> &'static str;
+    fn run(&mut self, world: &mut World, dt: f32);
+}
+
+/// System backed by a closure, the way gameplay scripts are written for now
+pub struct FnSystem<F> {
+    name: &'static str,
+    run: F,
+}
+
+impl<F: FnMut(&mut World, f32)> FnSystem<F> {
+    pub fn new(name: &'static str, run: F) -> Self {
+        FnSystem { name, run }
+    }
+}
+
+impl<F: FnMut(&mut World, f32)> System for FnSystem<F> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn run(&mut self, world: &mut World, dt: f32) {
+        (self.run)(world, dt)
+    }
+}
+
+/// System reading a shared World and recording its structural changes as `Commands`,
+/// which are applied once it returns, so it can spawn and despawn while iterating entities
+pub struct CommandSystem<F> {
+    name: &'static str,
+    run: F,
+    /// Reused between runs, empty outside of `run`
+    commands: Commands,
+}
+
+impl<F: FnMut(&World, &mut Commands, f32)> CommandSystem<F> {
+    pub fn new(name: &'static str, run: F) -> Self {
+        CommandSystem { name, run, commands: Commands::new() }
+    }
+}
+
+impl<F: FnMut(&World, &mut Commands, f32)> System for CommandSystem<F> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn run(&mut self, world: &mut World, dt: f32) {
+        (self.run)(world, &mut self.commands, dt);
+        self.commands.apply(world);
+    }
+}
+
+/// Ordered list of systems, run one after another
+pub struct Schedule {
+    systems: Vec<Box<dyn System>>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Schedule { systems: Vec::new() }
+    }
+
+    pub fn add<S: System + 'static>(&mut self, system: S) {
+        self.systems.push(Box::new(system));
+    }
+
+    /// Add a closure system
+    pub fn add_fn(&mut self, name: &'static str, run: impl FnMut(&mut World, f32) + 'static) {
+        self.add(FnSystem::new(name, run));
+    }
+
+    /// Add a closure system that changes the World through `Commands`, see `CommandSystem`
+    pub fn add_command_fn(&mut self, name: &'static str, run: impl FnMut(&World, &mut Commands, f32) + 'static) {
+        self.add(CommandSystem::new(name, run));
+    }
+
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Run every system once with a time step of `dt` seconds
+    /// Each system sees the changes of the ones before it, commands included.
+    pub fn run(&mut self, world: &mut World, dt: f32) {
+        for system in &mut self.systems {
+            crate::profile_scope!(system.name());
+            system.run(world, dt);
+        }
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
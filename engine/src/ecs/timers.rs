@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+
+use crate::ecs::{EntityId, World};
+
+/// Handle of a timer scheduled on `Timers`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+type Callback = Box<dyn FnMut(&mut World)>;
+
+struct Timer {
+    id: TimerId,
+    /// Seconds until the next firing
+    remaining: f32,
+    /// Period of a repeating timer
+    interval: Option<f32>,
+    /// Cancelled once this entity is despawned
+    owner: Option<EntityId>,
+    callback: Callback,
+}
+
+/// Delayed and repeating callbacks, stored as a World resource and driven by `update_timers`
+///
+/// Timers owned by an entity are dropped without firing once it is despawned, so a
+/// cooldown or delayed effect never runs against an entity that no longer exists.
+/// Callbacks may schedule and cancel timers through the resource.
+#[derive(Default)]
+pub struct Timers {
+    timers: Vec<Timer>,
+    /// Cancelled while their timers were out of the list being fired
+    cancelled: HashSet<TimerId>,
+    cancelled_owners: HashSet<EntityId>,
+    next_id: u64,
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&mut self, delay: f32, interval: Option<f32>, owner: Option<EntityId>, callback: Callback) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.push(Timer { id, remaining: delay.max(0.0), interval, owner, callback });
+        id
+    }
+
+    /// Run `callback` once after `seconds`
+    pub fn after(&mut self, seconds: f32, callback: impl FnMut(&mut World) + 'static) -> TimerId {
+        self.add(seconds, None, None, Box::new(callback))
+    }
+
+    /// Run `callback` every `seconds`, first after one interval
+    pub fn every(&mut self, seconds: f32, callback: impl FnMut(&mut World) + 'static) -> TimerId {
+        self.add(seconds, Some(seconds.max(f32::EPSILON)), None, Box::new(callback))
+    }
+
+    /// `after`, cancelled if `owner` is despawned first
+    pub fn after_for(&mut self, owner: EntityId, seconds: f32, callback: impl FnMut(&mut World) + 'static) -> TimerId {
+        self.add(seconds, None, Some(owner), Box::new(callback))
+    }
+
+    /// `every`, cancelled once `owner` is despawned
+    pub fn every_for(&mut self, owner: EntityId, seconds: f32, callback: impl FnMut(&mut World) + 'static) -> TimerId {
+        self.add(seconds, Some(seconds.max(f32::EPSILON)), Some(owner), Box::new(callback))
+    }
+
+    pub fn cancel(&mut self, id: TimerId) {
+        let count = self.timers.len();
+        self.timers.retain(|timer| timer.id != id);
+        if self.timers.len() == count {
+            self.cancelled.insert(id);
+        }
+    }
+
+    /// Cancel every timer owned by an entity
+    pub fn cancel_owned(&mut self, owner: EntityId) {
+        self.timers.retain(|timer| timer.owner != Some(owner));
+        self.cancelled_owners.insert(owner);
+    }
+
+    fn was_cancelled(&self, timer: &Timer) -> bool {
+        self.cancelled.contains(&timer.id) || timer.owner.is_some_and(|owner| self.cancelled_owners.contains(&owner))
+    }
+
+    /// Seconds until the timer fires next, `None` once it is done or cancelled
+    pub fn remaining(&self, id: TimerId) -> Option<f32> {
+        self.timers.iter().find(|timer| timer.id == id).map(|timer| timer.remaining)
+    }
+
+    pub fn is_active(&self, id: TimerId) -> bool {
+        self.remaining(id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+}
+
+/// Advance the World's `Timers` by `dt` seconds and fire the ones that are due
+/// A repeating timer fires once per elapsed interval, in order of scheduling.
+pub fn update_timers(world: &mut World, dt: f32) {
+    crate::profile_scope!("timers");
+    // Taken out of the resource so callbacks can still reach it to schedule and cancel
+    let Some(mut timers) = world.resource_mut::<Timers>().map(|timers| std::mem::take(&mut timers.timers)) else {
+        return;
+    };
+
+    let mut index = 0;
+    while index < timers.len() {
+        let timer = &mut timers[index];
+        let cancelled = |world: &World, timer: &Timer| {
+            timer.owner.is_some_and(|owner| !world.contains(owner))
+                || world.resource::<Timers>().is_some_and(|timers| timers.was_cancelled(timer))
+        };
+        if cancelled(world, timer) {
+            timers.remove(index);
+            continue;
+        }
+
+        timer.remaining -= dt;
+        let mut done = false;
+        while timer.remaining <= 0.0 {
+            (timer.callback)(world);
+            match timer.interval {
+                Some(interval) if !cancelled(world, timer) => timer.remaining += interval,
+                _ => {
+                    done = true;
+                    break;
+                }
+            }
+        }
+        if done {
+            timers.remove(index);
+        } else {
+            index += 1;
+        }
+    }
+
+    match world.resource_mut::<Timers>() {
+        Some(resource) => {
+            // Timers scheduled by callbacks were added to the emptied list
+            timers.append(&mut resource.timers);
+            timers.retain(|timer| !resource.was_cancelled(timer));
+            resource.timers = timers;
+            resource.cancelled.clear();
+            resource.cancelled_owners.clear();
+        }
+        None => log::warn!("Timers resource removed by a timer callback"),
+    }
+}
@@ -0,0 +1,111 @@
+use glam::Vec2;
+use std::any::Any;
+use std::collections::VecDeque;
+
+use crate::ecs::{ECSComponent, EntityId, World};
+use crate::math::{Color, Curve, Gradient};
+
+#[derive(Clone, Copy, Debug)]
+struct TrailPoint {
+    /// World space, points stay where the entity was
+    position: Vec2,
+    age: f32,
+}
+
+/// Ribbon following the entity's past positions, see `update_trails`
+///
+/// Width and color are sampled over a point's life, 0 at the entity and 1 where the
+/// trail ends. The newest point moves with the entity and is left behind once it is
+/// `min_distance` from the one before it.
+#[derive(Clone, Debug)]
+pub struct TrailRenderer {
+    /// Seconds a point stays in the trail
+    pub lifetime: f32,
+    /// World units between points, shorter makes smoother curves out of more triangles
+    pub min_distance: f32,
+    /// Width in world units over the lifetime
+    pub width: Curve,
+    pub color: Gradient,
+    /// Stops adding points when false, the trail shrinks away behind the entity
+    pub emitting: bool,
+    /// Oldest first
+    points: VecDeque<TrailPoint>,
+}
+
+impl Default for TrailRenderer {
+    fn default() -> Self {
+        TrailRenderer {
+            lifetime: 0.5,
+            min_distance: 0.1,
+            width: Curve::linear(0.2, 0.0),
+            color: Gradient::two(Color::WHITE, Color::srgb(0.4, 0.6, 1.0)),
+            emitting: true,
+            points: VecDeque::new(),
+        }
+    }
+}
+
+impl TrailRenderer {
+    pub fn new(lifetime: f32, width: Curve, color: Gradient) -> Self {
+        TrailRenderer { lifetime, width, color, ..Self::default() }
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Remove the whole trail, e.g. after teleporting the entity
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Age the points and drop expired ones, then follow the entity to `position`
+    pub fn update(&mut self, dt: f32, position: Vec2) {
+        let lifetime = self.lifetime.max(f32::EPSILON);
+        for point in &mut self.points {
+            point.age += dt;
+        }
+        while self.points.front().is_some_and(|point| point.age >= lifetime) {
+            self.points.pop_front();
+        }
+        if !self.emitting {
+            return;
+        }
+
+        let head = TrailPoint { position, age: 0.0 };
+        let len = self.points.len();
+        // The head is left behind once far enough from the point before it
+        let anchored = len < 2 || self.points[len - 2].position.distance(self.points[len - 1].position) >= self.min_distance;
+        match self.points.back_mut() {
+            Some(back) if !anchored => *back = head,
+            _ => self.points.push_back(head),
+        }
+    }
+
+    /// World position, width and color of every point, oldest first
+    pub fn points(&self) -> impl Iterator<Item = (Vec2, f32, Color)> + '_ {
+        let lifetime = self.lifetime.max(f32::EPSILON);
+        self.points.iter().map(move |point| {
+            let t = point.age / lifetime;
+            (point.position, self.width.sample(t), self.color.sample(t))
+        })
+    }
+}
+
+impl ECSComponent for TrailRenderer {
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn clone_box(&self) -> Box<dyn ECSComponent> { Box::new(self.clone()) }
+}
+
+/// Update every trail with the translation of its entity's world transform
+pub fn update_trails(world: &mut World, dt: f32) {
+    crate::profile_scope!("trails");
+    let trails: Vec<EntityId> = world.entity_ids().filter(|&id| world.has::<TrailRenderer>(id)).collect();
+    for id in trails {
+        let position = world.world_matrix(id).w_axis.truncate().truncate();
+        if let Some(trail) = world.get_mut::<TrailRenderer>(id) {
+            trail.update(dt, position);
+        }
+    }
+}
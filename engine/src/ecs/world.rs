@@ -1,20 +1,438 @@
-use std::collections::HashMap;
- 
-use crate::ecs::{Entity, EntityId, Component};
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
 
-struct World {
+use glam::Mat4;
+
+use crate::ecs::{ComponentRegistry, ECSComponent, Entity, EntityId, ReflectedComponent};
+use crate::math::Transform;
+
+/// An entity and its descendants removed from a World by `World::detach_subtree`
+/// Restoring it brings back the same ids, components and hierarchy position
+pub struct DetachedSubtree {
+    root: EntityId,
+    parent: Option<EntityId>,
+    /// Position among the parent's children (or the roots)
+    index: usize,
+    /// The root followed by its descendants
+    entities: Vec<Entity>,
+}
+
+impl DetachedSubtree {
+    pub fn root(&self) -> EntityId {
+        self.root
+    }
+
+    pub fn parent(&self) -> Option<EntityId> {
+        self.parent
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// Copy of every entity in a World, see `World::snapshot`
+#[derive(Clone)]
+pub struct WorldSnapshot {
     entities: HashMap<EntityId, Entity>,
+    roots: Vec<EntityId>,
+    next_id: u32,
+}
+
+/// Reflected fields of the registered components of every entity, see `World::snapshot_components`
+///
+/// Unlike a `WorldSnapshot` only what the `ComponentRegistry` can reflect is kept, so it is
+/// cheap to keep many of them, e.g. one per frame for rollback. State outside the reflected
+/// fields (live particles, trail points) and unregistered components are not captured.
+#[derive(Clone, Debug)]
+pub struct ComponentSnapshot {
+    /// Parents before their children, in hierarchy order
+    entities: Vec<SnapshotEntity>,
+    next_id: u32,
+}
+
+#[derive(Clone, Debug)]
+struct SnapshotEntity {
+    id: EntityId,
+    name: String,
+    parent: Option<EntityId>,
+    components: Vec<ReflectedComponent>,
+}
+
+impl ComponentSnapshot {
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.entities.iter().any(|entity| entity.id == id)
+    }
+
+    /// The captured components of an entity
+    pub fn components(&self, id: EntityId) -> Option<&[ReflectedComponent]> {
+        self.entities.iter().find(|entity| entity.id == id).map(|entity| entity.components.as_slice())
+    }
+}
+
+/// Owns all entities, their components and global resources
+pub struct World {
+    entities: HashMap<EntityId, Entity>,
+    /// Root entities in spawn order (children are ordered by their parent)
+    roots: Vec<EntityId>,
+    resources: HashMap<TypeId, Box<dyn Any>>,
+    next_id: u32,
 }
 
 impl World {
     pub fn new() -> Self {
-        
         World {
             entities: HashMap::new(),
+            roots: Vec::new(),
+            resources: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Create a new root entity
+    pub fn spawn(&mut self, name: &str) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.entities.insert(id, Entity::new(id, name));
+        self.roots.push(id);
+        id
+    }
+
+    /// Create a new entity parented to `parent`
+    pub fn spawn_child(&mut self, parent: EntityId, name: &str) -> EntityId {
+        let id = self.spawn(name);
+        self.set_parent(id, Some(parent));
+        id
+    }
+
+    /// Remove an entity and all of its descendants
+    pub fn despawn(&mut self, id: EntityId) {
+        let Some(entity) = self.entities.get(&id) else {
+            return;
+        };
+        let children = entity.children.clone();
+        let parent = entity.parent;
+
+        for child in children {
+            self.despawn(child);
+        }
+
+        self.detach(id, parent);
+        self.entities.remove(&id);
+    }
+
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.entities.contains_key(&id)
+    }
+
+    pub fn entity(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Root entities in spawn order
+    pub fn roots(&self) -> &[EntityId] {
+        &self.roots
+    }
+
+    /// All entity ids (unordered)
+    pub fn entity_ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.entities.keys().copied()
+    }
+
+    pub fn name(&self, id: EntityId) -> Option<&str> {
+        self.entities.get(&id).map(|e| e.name.as_str())
+    }
+
+    pub fn set_name(&mut self, id: EntityId, name: &str) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.name = name.to_string();
+        }
+    }
+
+    pub fn parent(&self, id: EntityId) -> Option<EntityId> {
+        self.entities.get(&id).and_then(|e| e.parent)
+    }
+
+    pub fn children(&self, id: EntityId) -> &[EntityId] {
+        self.entities.get(&id).map(|e| e.children.as_slice()).unwrap_or(&[])
+    }
+
+    /// Returns true if `ancestor` is `id` or one of its parents
+    pub fn is_ancestor(&self, ancestor: EntityId, id: EntityId) -> bool {
+        let mut current = Some(id);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.parent(node);
+        }
+        false
+    }
+
+    /// Position of an entity among its parent's children (or the roots)
+    pub fn sibling_index(&self, id: EntityId) -> Option<usize> {
+        let siblings = match self.parent(id) {
+            Some(parent) => self.children(parent),
+            None => &self.roots,
+        };
+        siblings.iter().position(|&sibling| sibling == id)
+    }
+
+    /// Move an entity under a new parent (or to the root with `None`)
+    /// Returns false if the entity is missing or the move would create a cycle
+    pub fn set_parent(&mut self, id: EntityId, parent: Option<EntityId>) -> bool {
+        self.set_parent_at(id, parent, None)
+    }
+
+    /// Like `set_parent`, but inserts at `index` among the new siblings (appends with `None`)
+    pub fn set_parent_at(&mut self, id: EntityId, parent: Option<EntityId>, index: Option<usize>) -> bool {
+        let Some(old_parent) = self.entities.get(&id).map(|e| e.parent) else {
+            return false;
+        };
+        if let Some(new_parent) = parent {
+            if !self.entities.contains_key(&new_parent) || self.is_ancestor(id, new_parent) {
+                return false;
+            }
+        }
+
+        self.detach(id, old_parent);
+        self.attach(id, parent, index);
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.parent = parent;
+        }
+        true
+    }
+
+    /// Insert `id` into its new parent's child list (or the roots)
+    fn attach(&mut self, id: EntityId, parent: Option<EntityId>, index: Option<usize>) {
+        let siblings = match parent {
+            Some(parent) => match self.entities.get_mut(&parent) {
+                Some(p) => &mut p.children,
+                None => return,
+            },
+            None => &mut self.roots,
+        };
+        let index = index.unwrap_or(siblings.len()).min(siblings.len());
+        siblings.insert(index, id);
+    }
+
+    /// Remove an entity and its descendants, keeping everything needed to restore them
+    pub fn detach_subtree(&mut self, id: EntityId) -> Option<DetachedSubtree> {
+        let index = self.sibling_index(id)?;
+        let parent = self.parent(id);
+
+        let mut ids = vec![id];
+        let mut next = 0;
+        while next < ids.len() {
+            ids.extend_from_slice(self.children(ids[next]));
+            next += 1;
+        }
+
+        self.detach(id, parent);
+        let entities = ids.iter().filter_map(|id| self.entities.remove(id)).collect();
+        Some(DetachedSubtree { root: id, parent, index, entities })
+    }
+
+    /// Put a detached subtree back where it was
+    /// Returns the subtree unchanged if its parent is gone or one of its ids is in use
+    pub fn restore_subtree(&mut self, subtree: DetachedSubtree) -> Result<(), DetachedSubtree> {
+        let parent_missing = subtree.parent.is_some_and(|parent| !self.entities.contains_key(&parent));
+        let id_taken = subtree.entities.iter().any(|e| self.entities.contains_key(&e.id));
+        if parent_missing || id_taken {
+            return Err(subtree);
+        }
+
+        for entity in subtree.entities {
+            self.next_id = self.next_id.max(entity.id.0 + 1);
+            self.entities.insert(entity.id, entity);
+        }
+        self.attach(subtree.root, subtree.parent, Some(subtree.index));
+        Ok(())
+    }
+
+    /// Remove `id` from its parent's child list (or from the roots)
+    fn detach(&mut self, id: EntityId, parent: Option<EntityId>) {
+        match parent {
+            Some(parent) => {
+                if let Some(p) = self.entities.get_mut(&parent) {
+                    p.children.retain(|&child| child != id);
+                }
+            }
+            None => self.roots.retain(|&root| root != id),
+        }
+    }
+
+    /// Copy all entities and components (resources are not included)
+    pub fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot {
+            entities: self.entities.clone(),
+            roots: self.roots.clone(),
+            next_id: self.next_id,
+        }
+    }
+
+    /// Replace all entities with a snapshot, keeping the current resources
+    pub fn restore_snapshot(&mut self, snapshot: WorldSnapshot) {
+        self.entities = snapshot.entities;
+        self.roots = snapshot.roots;
+        self.next_id = snapshot.next_id;
+    }
+
+    /// Capture the registered components of every entity along with names and hierarchy
+    pub fn snapshot_components(&self, registry: &ComponentRegistry) -> ComponentSnapshot {
+        let mut entities = Vec::with_capacity(self.entities.len());
+        let mut stack: Vec<EntityId> = self.roots.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            let Some(entity) = self.entities.get(&id) else {
+                continue;
+            };
+            entities.push(SnapshotEntity {
+                id,
+                name: entity.name.clone(),
+                parent: entity.parent,
+                components: registry.reflect(self, id),
+            });
+            stack.extend(entity.children.iter().rev());
+        }
+        ComponentSnapshot { entities, next_id: self.next_id }
+    }
+
+    /// Put the registered components, names and hierarchy back as they were captured
+    ///
+    /// Entities spawned since are removed and despawned ones come back with the same ids,
+    /// holding only their registered components. Unregistered components of entities that
+    /// still exist are left alone. Ids handed out after the capture are handed out again.
+    pub fn restore_components(&mut self, registry: &ComponentRegistry, snapshot: &ComponentSnapshot) {
+        crate::profile_scope!("restore_components");
+        let captured: HashSet<EntityId> = snapshot.entities.iter().map(|entity| entity.id).collect();
+        self.entities.retain(|id, _| captured.contains(id));
+        for captured in &snapshot.entities {
+            let entity = self.entities.entry(captured.id).or_insert_with(|| Entity::new(captured.id, &captured.name));
+            entity.name.clone_from(&captured.name);
+            entity.parent = captured.parent;
+            entity.children.clear();
         }
+        // Captured in hierarchy order, so pushing rebuilds the child lists in order
+        self.roots.clear();
+        for captured in &snapshot.entities {
+            match captured.parent.and_then(|parent| self.entities.get_mut(&parent)) {
+                Some(parent) => parent.children.push(captured.id),
+                None => self.roots.push(captured.id),
+            }
+        }
+        self.next_id = snapshot.next_id;
+
+        for captured in &snapshot.entities {
+            for info in registry.iter() {
+                let component = captured.components.iter().find(|component| component.name == info.name);
+                let present = self.get_dyn(captured.id, info.type_id).is_some();
+                match component {
+                    Some(component) => {
+                        if !present {
+                            registry.insert_default(self, captured.id, info.name);
+                        }
+                        for &(field, value) in &component.fields {
+                            registry.set_field(self, captured.id, info.name, field, value);
+                        }
+                    }
+                    None if present => {
+                        registry.remove(self, captured.id, info.name);
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+
+    /// Transform of an entity in world space, composed from the Transforms of its parents
+    /// Entities without a Transform inherit their parent's
+    pub fn world_matrix(&self, id: EntityId) -> Mat4 {
+        let local = self.get::<Transform>(id).map(Transform::to_matrix).unwrap_or(Mat4::IDENTITY);
+        match self.parent(id) {
+            Some(parent) => self.world_matrix(parent) * local,
+            None => local,
+        }
+    }
+
+    /// Add or replace a component on an entity
+    pub fn insert<T: ECSComponent>(&mut self, id: EntityId, component: T) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.components.insert(TypeId::of::<T>(), Box::new(component));
+        }
+    }
+
+    /// Remove a component and return it
+    pub fn remove<T: ECSComponent>(&mut self, id: EntityId) -> Option<T> {
+        let boxed = self.entities.get_mut(&id)?.components.remove(&TypeId::of::<T>())?;
+        let any: Box<dyn Any> = boxed;
+        any.downcast::<T>().ok().map(|b| *b)
+    }
+
+    pub fn get<T: ECSComponent>(&self, id: EntityId) -> Option<&T> {
+        self.entities
+            .get(&id)?
+            .components
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref::<T>()
+    }
+
+    pub fn get_mut<T: ECSComponent>(&mut self, id: EntityId) -> Option<&mut T> {
+        self.entities
+            .get_mut(&id)?
+            .components
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<T>()
     }
 
-    pub fn render() {
-        // needs a camera entity
+    pub fn has<T: ECSComponent>(&self, id: EntityId) -> bool {
+        self.entities
+            .get(&id)
+            .is_some_and(|e| e.components.contains_key(&TypeId::of::<T>()))
     }
-}
\ No newline at end of file
+
+    /// Type-erased component access, used by reflection
+    pub fn get_dyn(&self, id: EntityId, type_id: TypeId) -> Option<&dyn ECSComponent> {
+        self.entities.get(&id)?.components.get(&type_id).map(|c| c.as_ref())
+    }
+
+    pub fn get_dyn_mut(&mut self, id: EntityId, type_id: TypeId) -> Option<&mut dyn ECSComponent> {
+        self.entities.get_mut(&id)?.components.get_mut(&type_id).map(|c| c.as_mut())
+    }
+
+    /// Insert or replace a global resource (one per type)
+    pub fn insert_resource<R: Any>(&mut self, resource: R) {
+        self.resources.insert(TypeId::of::<R>(), Box::new(resource));
+    }
+
+    pub fn resource<R: Any>(&self) -> Option<&R> {
+        self.resources.get(&TypeId::of::<R>())?.downcast_ref::<R>()
+    }
+
+    pub fn resource_mut<R: Any>(&mut self) -> Option<&mut R> {
+        self.resources.get_mut(&TypeId::of::<R>())?.downcast_mut::<R>()
+    }
+
+    pub fn remove_resource<R: Any>(&mut self) -> Option<R> {
+        self.resources.remove(&TypeId::of::<R>())?.downcast::<R>().ok().map(|b| *b)
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
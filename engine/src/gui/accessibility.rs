@@ -0,0 +1,119 @@
+use accesskit::{Action, ActionData, NodeBuilder, NodeClassSet, NodeId, Role, Tree, TreeUpdate};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::gui::{Rect, UIScale, Vec2};
+
+/// Node of the window, the root every component node is added under
+const ROOT_ID: NodeId = NodeId(1);
+
+/// Accessibility tree of the UI for assistive technologies (screen readers), see
+/// `UISystem::accessibility_tree`
+///
+/// Components describe themselves in `GUIComponent::accessibility` by adding AccessKit
+/// nodes with the UI rect they cover. Nodes added between `push` and `pop` are children
+/// of the pushed node, everything else goes under the window.
+///
+/// Ids come from the position in the tree, so a component keeps its node as long as
+/// the components before it don't change.
+pub struct AccessTree {
+    nodes: Vec<(NodeId, NodeBuilder)>,
+    /// Indices of the nodes new nodes are added under, the window first
+    parents: Vec<usize>,
+    /// UI rect of every node, to route actions to the component under it
+    bounds: HashMap<NodeId, Rect>,
+    app_name: String,
+    ui_scale: UIScale,
+    window_height: f32,
+}
+
+impl AccessTree {
+    pub(super) fn new(app_name: &str, ui_scale: UIScale, window_size: Vec2) -> Self {
+        let mut window = NodeBuilder::new(Role::Window);
+        window.set_name(app_name);
+        AccessTree {
+            nodes: vec![(ROOT_ID, window)],
+            parents: vec![0],
+            bounds: HashMap::new(),
+            app_name: app_name.to_string(),
+            ui_scale,
+            window_height: window_size.y,
+        }
+    }
+
+    /// Tree with only the window, for platform adapters created before the UI is built
+    pub fn initial(app_name: &str) -> TreeUpdate {
+        Self::new(app_name, UIScale::pixels(Vec2::ZERO), Vec2::ZERO).finish(None).0
+    }
+
+    /// Add a node covering `rect` (UI space) under the current parent
+    pub fn add(&mut self, mut node: NodeBuilder, rect: Rect) -> NodeId {
+        let parent = *self.parents.last().unwrap_or(&0);
+        let id = self.child_id(parent);
+        node.set_bounds(self.window_rect(rect));
+        self.nodes[parent].1.push_child(id);
+        self.nodes.push((id, node));
+        self.bounds.insert(id, rect);
+        id
+    }
+
+    /// Add a node and put the nodes added until `pop` under it
+    pub fn push(&mut self, node: NodeBuilder, rect: Rect) -> NodeId {
+        let id = self.add(node, rect);
+        self.parents.push(self.nodes.len() - 1);
+        id
+    }
+
+    pub fn pop(&mut self) {
+        if self.parents.len() > 1 {
+            self.parents.pop();
+        }
+    }
+
+    /// Id of the next child of `parent`, from the parent's id and the child's index
+    fn child_id(&self, parent: usize) -> NodeId {
+        let (parent_id, node) = &self.nodes[parent];
+        let mut hasher = DefaultHasher::new();
+        (parent_id.0, node.children().len()).hash(&mut hasher);
+        // 0 and the window's id are taken
+        NodeId(hasher.finish().max(ROOT_ID.0 + 1))
+    }
+
+    /// UI rect to the physical window pixels (y down) AccessKit bounds are given in
+    fn window_rect(&self, rect: Rect) -> accesskit::Rect {
+        let rect = self.ui_scale.rect_to_window(rect);
+        let (min, max) = (rect.min(), rect.max());
+        accesskit::Rect {
+            x0: min.x as f64,
+            y0: (self.window_height - max.y) as f64,
+            x1: max.x as f64,
+            y1: (self.window_height - min.y) as f64,
+        }
+    }
+
+    /// Full update for the adapter and the rect of every node, `focus` falls back to the
+    /// window when it isn't in the tree anymore
+    pub(super) fn finish(self, focus: Option<NodeId>) -> (TreeUpdate, HashMap<NodeId, Rect>) {
+        let focus = focus.filter(|id| self.bounds.contains_key(id)).unwrap_or(ROOT_ID);
+        let mut classes = NodeClassSet::new();
+        let mut tree = Tree::new(ROOT_ID);
+        tree.app_name = Some(self.app_name);
+        tree.toolkit_name = Some("engine".to_string());
+        let update = TreeUpdate {
+            nodes: self.nodes.into_iter().map(|(id, node)| (id, node.build(&mut classes))).collect(),
+            tree: Some(tree),
+            focus,
+        };
+        (update, self.bounds)
+    }
+}
+
+/// An action an assistive technology requested on a node, see `GUIComponent::accessibility_action`
+#[derive(Clone, Debug)]
+pub struct AccessAction {
+    pub action: Action,
+    /// Center of the node in UI space, components find the part it targets like a click
+    pub point: Vec2,
+    pub data: Option<ActionData>,
+}
@@ -0,0 +1,210 @@
+use anyhow::Result;
+use ash::vk;
+use glam::Mat4;
+use std::sync::Arc;
+use crate::gui::viewport::image_quad;
+use crate::gui::OffscreenPass;
+use crate::math::{Color, Rect, Transform};
+use crate::renderer::{
+    BackdropPushConstants, Mesh, PipelineId, RenderContext, Renderer, SampledTexture, SamplerConfig, TexturedVertex2D, Texture, VulkanContext,
+};
+
+/// How what's behind a panel shows through it, see `ContainerPanel::set_backdrop`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Backdrop {
+    /// Standard deviation of the gaussian blur in UI units, 0 doesn't blur
+    pub blur: f32,
+    /// 0 keeps the brightness, 1 is black
+    pub dim: f32,
+    /// 0 keeps the colors, 1 is grayscale
+    pub desaturate: f32,
+}
+
+impl Backdrop {
+    /// Shows the background unchanged
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Blurred, darker and muted, e.g. for pause menus and modal dialogs
+    pub fn modal() -> Self {
+        Backdrop { blur: 6.0, dim: 0.35, desaturate: 0.5 }
+    }
+
+    pub fn with_blur(mut self, blur: f32) -> Self {
+        self.blur = blur;
+        self
+    }
+
+    pub fn with_dim(mut self, dim: f32) -> Self {
+        self.dim = dim;
+        self
+    }
+
+    pub fn with_desaturate(mut self, desaturate: f32) -> Self {
+        self.desaturate = desaturate;
+        self
+    }
+}
+
+/// Texture a backdrop samples and the UI area it was drawn over
+#[derive(Clone, Copy, PartialEq)]
+struct BackdropSource {
+    view: vk::ImageView,
+    width: u32,
+    height: u32,
+    rect: Rect,
+}
+
+/// A `Backdrop` drawn under a panel from a texture of what's behind it
+///
+/// The engine can't read back the frame it is drawing, so what's behind is a texture the
+/// application renders first, e.g. the game drawn into a `Texture::render_target` that also
+/// fills the window. Pass it to `set_source` every frame. The blur runs in two passes: the
+/// horizontal one in `render_offscreen` into a texture the size of the panel, the vertical
+/// one together with the color changes when the panel is drawn.
+pub struct BackdropEffect {
+    pub backdrop: Backdrop,
+    /// Unit quad sampling a whole texture
+    image_quad: Mesh<TexturedVertex2D>,
+    source: Option<BackdropSource>,
+    /// Descriptor set of the source it was created for
+    sampled_source: Option<(BackdropSource, SampledTexture)>,
+    /// The source under the panel blurred horizontally
+    target: Option<(Texture, SampledTexture)>,
+    /// Vertical blur in texels of `target`
+    vertical_sigma: f32,
+}
+
+impl BackdropEffect {
+    pub fn new(context: &Arc<VulkanContext>, backdrop: Backdrop) -> Result<Self> {
+        Ok(BackdropEffect {
+            backdrop,
+            image_quad: image_quad(context)?,
+            source: None,
+            sampled_source: None,
+            target: None,
+            vertical_sigma: 0.0,
+        })
+    }
+
+    /// Texture behind the panel, covering `rect` of the UI (in UI units)
+    /// It must be in SHADER_READ_ONLY_OPTIMAL layout when `render_offscreen` runs, as
+    /// `RenderFrame::render_to_texture` leaves it.
+    pub fn set_source(&mut self, texture: &Texture, rect: Rect) {
+        self.source = Some(BackdropSource { view: texture.image_view, width: texture.width, height: texture.height, rect });
+    }
+
+    /// Stop drawing the backdrop until the next `set_source`
+    pub fn clear_source(&mut self) {
+        self.source = None;
+    }
+
+    /// Blur the source under `rect` horizontally, see `GUIComponent::render_offscreen`
+    pub fn render_offscreen(&mut self, pass: &OffscreenPass, renderer: &mut Renderer, rect: Rect) -> Result<()> {
+        let Some(source) = self.source else {
+            return Ok(());
+        };
+        let width = (rect.width * pass.scale).round().max(0.0) as u32;
+        let height = (rect.height * pass.scale).round().max(0.0) as u32;
+        if width == 0 || height == 0 || source.rect.width <= 0.0 {
+            return Ok(());
+        }
+        let pipeline = renderer.get_pipeline(PipelineId::Backdrop)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::Backdrop)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for Backdrop pipeline"))?;
+        let descriptor_set_layout = renderer.get_descriptor_set_layout(PipelineId::Backdrop)
+            .ok_or_else(|| anyhow::anyhow!("Backdrop pipeline should have a descriptor set layout"))?;
+        self.prepare(pass.context, source, width, height, renderer.color_format(), descriptor_set_layout)?;
+        let (Some((_, sampled)), Some((target, _))) = (&self.sampled_source, &self.target) else {
+            return Ok(());
+        };
+
+        // The source quad is drawn where it covers the UI, the projection crops it to the panel
+        let texels_per_unit = source.width as f32 / source.rect.width;
+        let push = BackdropPushConstants::new(
+            Mat4::orthographic_rh(rect.min().x, rect.max().x, rect.min().y, rect.max().y, -1.0, 1.0),
+            Transform::from_rect(source.rect).to_matrix(),
+        )
+        .with_blur([1.0 / source.width as f32, 0.0], self.backdrop.blur * texels_per_unit);
+        let image_quad = &self.image_quad;
+        pass.frame.render_to_texture(target, Color::TRANSPARENT, |ctx| {
+            ctx.bind_pipeline(pipeline);
+            ctx.bind_descriptor_set_at(pipeline_layout, 0, sampled.descriptor_set);
+            ctx.push(pipeline_layout, &push);
+            image_quad.draw(ctx)
+        })?;
+        self.vertical_sigma = self.backdrop.blur * pass.scale;
+        Ok(())
+    }
+
+    /// Recreate the source's descriptor set and the target when they changed
+    fn prepare(
+        &mut self,
+        context: &Arc<VulkanContext>,
+        source: BackdropSource,
+        width: u32,
+        height: u32,
+        color_format: vk::Format,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<()> {
+        let source_changed = self.sampled_source.as_ref().is_none_or(|(sampled, _)| sampled.view != source.view
+            || sampled.width != source.width
+            || sampled.height != source.height);
+        let resized = self.target.as_ref().is_none_or(|(texture, _)| texture.width != width || texture.height != height);
+        if !source_changed && !resized {
+            return Ok(());
+        }
+        // Old descriptor sets and targets may still be used by frames in flight
+        unsafe {
+            let _ = context.device.device_wait_idle();
+        }
+        if source_changed {
+            if let Some((_, sampled)) = self.sampled_source.take() {
+                sampled.destroy(&context.device);
+            }
+            let sampled = SampledTexture::from_view(source.view, SamplerConfig::linear(), descriptor_set_layout, context)?;
+            self.sampled_source = Some((source, sampled));
+        }
+        if resized {
+            if let Some((texture, sampled)) = self.target.take() {
+                sampled.destroy(&context.device);
+                texture.destroy(&context.device);
+            }
+            let texture = Texture::render_target(width, height, color_format, &context.device, &context.instance, context.physical_device)?;
+            let sampled = SampledTexture::new(&texture, SamplerConfig::linear(), descriptor_set_layout, context)?;
+            self.target = Some((texture, sampled));
+        }
+        Ok(())
+    }
+
+    /// Blur the horizontal pass vertically over `rect` and apply the color changes
+    /// Draws nothing until `render_offscreen` ran with a source.
+    pub fn render(&self, ctx: &RenderContext, renderer: &mut Renderer, rect: Rect) -> Result<()> {
+        let Some((texture, sampled)) = self.target.as_ref().filter(|(texture, _)| texture.is_rendered() && self.source.is_some()) else {
+            return Ok(());
+        };
+        let pipeline = renderer.get_pipeline(PipelineId::Backdrop)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::Backdrop)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for Backdrop pipeline"))?;
+        ctx.bind_pipeline(pipeline);
+        ctx.bind_descriptor_set_at(pipeline_layout, 0, sampled.descriptor_set);
+        let push = BackdropPushConstants::new(renderer.projection, Transform::from_rect(rect).to_matrix())
+            .with_opacity(renderer.opacity)
+            .with_blur([0.0, 1.0 / texture.height as f32], self.vertical_sigma)
+            .with_color(self.backdrop.dim.clamp(0.0, 1.0), 1.0 - self.backdrop.desaturate.clamp(0.0, 1.0));
+        ctx.push(pipeline_layout, &push);
+        self.image_quad.draw(ctx)
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        self.image_quad.destroy(device);
+        if let Some((_, sampled)) = &self.sampled_source {
+            sampled.destroy(device);
+        }
+        if let Some((texture, sampled)) = &self.target {
+            sampled.destroy(device);
+            texture.destroy(device);
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use anyhow::Result;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use crate::ecs::{ComponentRegistry, EntityId, FieldValue, World};
+use crate::gui::{ButtonComponent, Checkbox, Color, ColorSwatch, DragFloat, GUIComponent, TextComponent};
+#[cfg(feature = "editor-widgets")]
+use crate::gui::{CurveEditor, GradientEditor};
+#[cfg(feature = "editor-widgets")]
+use crate::math::{Curve, Gradient};
+use crate::renderer::VulkanContext;
+
+/// A widget showing one value that can be bound to application state, see `Bindings`
+pub trait Bindable: GUIComponent {
+    type Value: Clone + PartialEq;
+
+    /// The value the user entered since the last call, display-only widgets never have one
+    fn take_edit(&mut self) -> Option<Self::Value> {
+        None
+    }
+
+    /// Show a value read from the bound state
+    fn show(&mut self, value: Self::Value, context: &Arc<VulkanContext>) -> Result<()>;
+}
+
+impl Bindable for DragFloat {
+    type Value = f32;
+
+    fn take_edit(&mut self) -> Option<f32> {
+        self.take_changed()
+    }
+
+    fn show(&mut self, value: f32, context: &Arc<VulkanContext>) -> Result<()> {
+        // Don't fight the user while the value is being dragged
+        if !self.is_dragging() {
+            self.set_value(value);
+        }
+        self.refresh(context)
+    }
+}
+
+impl Bindable for Checkbox {
+    type Value = bool;
+
+    fn take_edit(&mut self) -> Option<bool> {
+        self.take_changed()
+    }
+
+    fn show(&mut self, value: bool, _context: &Arc<VulkanContext>) -> Result<()> {
+        self.set_checked(value);
+        Ok(())
+    }
+}
+
+impl Bindable for ColorSwatch {
+    type Value = Color;
+
+    fn show(&mut self, value: Color, _context: &Arc<VulkanContext>) -> Result<()> {
+        self.set_color(value);
+        Ok(())
+    }
+}
+
+impl Bindable for TextComponent {
+    type Value = String;
+
+    fn show(&mut self, value: String, context: &Arc<VulkanContext>) -> Result<()> {
+        self.update_text(&value, context)
+    }
+}
+
+/// Binds the button label
+impl Bindable for ButtonComponent {
+    type Value = String;
+
+    fn show(&mut self, value: String, context: &Arc<VulkanContext>) -> Result<()> {
+        self.update_text(&value, context)
+    }
+}
+
+#[cfg(feature = "editor-widgets")]
+impl Bindable for CurveEditor {
+    type Value = Curve;
+
+    fn take_edit(&mut self) -> Option<Curve> {
+        self.take_changed()
+    }
+
+    fn show(&mut self, value: Curve, context: &Arc<VulkanContext>) -> Result<()> {
+        self.set_curve(value);
+        self.refresh(context)
+    }
+}
+
+#[cfg(feature = "editor-widgets")]
+impl Bindable for GradientEditor {
+    type Value = Gradient;
+
+    fn take_edit(&mut self) -> Option<Gradient> {
+        self.take_changed()
+    }
+
+    fn show(&mut self, value: Gradient, context: &Arc<VulkanContext>) -> Result<()> {
+        self.set_gradient(value);
+        self.refresh(context)
+    }
+}
+
+/// Values a widget can edit in a reflected component field
+pub trait FieldData: Sized {
+    fn from_field(value: FieldValue) -> Option<Self>;
+    fn to_field(self) -> FieldValue;
+}
+
+impl FieldData for f32 {
+    fn from_field(value: FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn to_field(self) -> FieldValue {
+        FieldValue::Float(self)
+    }
+}
+
+impl FieldData for bool {
+    fn from_field(value: FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn to_field(self) -> FieldValue {
+        FieldValue::Bool(self)
+    }
+}
+
+impl FieldData for Color {
+    fn from_field(value: FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Color(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn to_field(self) -> FieldValue {
+        FieldValue::Color(self)
+    }
+}
+
+/// A field of a component on an entity, addressed like the inspector keys:
+/// "Component.field", plus ".x"/".y" for one axis of a vector field
+#[derive(Clone, Debug, PartialEq)]
+struct FieldPath {
+    entity: EntityId,
+    component: String,
+    field: String,
+    axis: Option<usize>,
+}
+
+impl FieldPath {
+    fn parse(entity: EntityId, key: &str) -> Option<Self> {
+        let mut parts = key.split('.');
+        let (component, field) = (parts.next()?, parts.next()?);
+        let axis = match parts.next() {
+            None => None,
+            Some("x") => Some(0),
+            Some("y") => Some(1),
+            Some(_) => return None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(FieldPath { entity, component: component.to_string(), field: field.to_string(), axis })
+    }
+
+    fn read(&self, world: &World) -> Option<FieldValue> {
+        let value = world
+            .resource::<ComponentRegistry>()?
+            .reflect(world, self.entity)
+            .into_iter()
+            .find(|c| c.name == self.component)?
+            .fields
+            .into_iter()
+            .find(|(name, _)| *name == self.field)
+            .map(|(_, value)| value)?;
+        match (value, self.axis) {
+            (FieldValue::Vec2(v), Some(axis)) => Some(FieldValue::Float(v[axis])),
+            (value, None) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn write(&self, world: &mut World, value: FieldValue) -> bool {
+        let value = match (self.axis, value) {
+            (None, value) => value,
+            (Some(axis), FieldValue::Float(v)) => {
+                let Some(FieldValue::Vec2(mut vec)) = self.axis_parent(world) else {
+                    return false;
+                };
+                vec[axis] = v;
+                FieldValue::Vec2(vec)
+            }
+            (Some(_), _) => return false,
+        };
+        // The registry is a resource, take it out while it writes into the World
+        let Some(registry) = world.remove_resource::<ComponentRegistry>() else {
+            return false;
+        };
+        let written = registry.set_field(world, self.entity, &self.component, &self.field, value);
+        world.insert_resource(registry);
+        written
+    }
+
+    /// The whole vector an axis path points into
+    fn axis_parent(&self, world: &World) -> Option<FieldValue> {
+        FieldPath { axis: None, ..self.clone() }.read(world)
+    }
+}
+
+/// One widget kept in sync with a piece of state of type `S`
+trait Binding<S> {
+    /// Write user edits into the state, then show the state if it changed
+    /// Returns true if the widget was updated
+    fn sync(&mut self, state: &mut S, context: &Arc<VulkanContext>) -> Result<bool>;
+}
+
+type Getter<S, V> = Box<dyn Fn(&S) -> Option<V>>;
+type Setter<S, V> = Box<dyn FnMut(&mut S, V)>;
+
+struct WidgetBinding<W: Bindable, S> {
+    widget: Arc<RefCell<W>>,
+    get: Getter<S, W::Value>,
+    /// None for display-only bindings, edits are then dropped
+    set: Option<Setter<S, W::Value>>,
+    /// Last value pushed to the widget
+    shown: Option<W::Value>,
+}
+
+impl<W: Bindable, S> Binding<S> for WidgetBinding<W, S> {
+    fn sync(&mut self, state: &mut S, context: &Arc<VulkanContext>) -> Result<bool> {
+        let mut widget = self.widget.borrow_mut();
+        if let Some(value) = widget.take_edit() {
+            if let Some(set) = &mut self.set {
+                set(state, value);
+            }
+            // The widget shows the edit now, put the state back even if the setter rejected it
+            self.shown = None;
+        }
+        // Values the getter can't produce (e.g. a despawned entity) leave the widget as it is
+        let Some(value) = (self.get)(state) else {
+            return Ok(false);
+        };
+        if self.shown.as_ref() == Some(&value) {
+            return Ok(false);
+        }
+        widget.show(value.clone(), context)?;
+        self.shown = Some(value);
+        Ok(true)
+    }
+}
+
+/// Widget properties bound to getter and setter closures over application state
+///
+/// `sync` is called once per frame with the state: edits the user made in a bound widget
+/// go through its setter, then every getter is read and widgets are only updated when
+/// their value changed. Widgets are bound through their `ComponentRef` handles, so they
+/// can live anywhere in the grid.
+///
+/// With a `World` as the state, `bind_field` binds reflected component fields directly.
+pub struct Bindings<S> {
+    bindings: Vec<Box<dyn Binding<S>>>,
+}
+
+impl<S: 'static> Bindings<S> {
+    pub fn new() -> Self {
+        Bindings { bindings: Vec::new() }
+    }
+
+    /// Show `get` in the widget and write user edits back with `set`
+    pub fn bind<W, G, F>(&mut self, widget: Arc<RefCell<W>>, get: G, set: F)
+    where
+        W: Bindable + 'static,
+        G: Fn(&S) -> W::Value + 'static,
+        F: FnMut(&mut S, W::Value) + 'static,
+    {
+        self.bindings.push(Box::new(WidgetBinding {
+            widget,
+            get: Box::new(move |state| Some(get(state))),
+            set: Some(Box::new(set)),
+            shown: None,
+        }));
+    }
+
+    /// Show `get` in the widget, user edits are ignored
+    pub fn bind_read_only<W, G>(&mut self, widget: Arc<RefCell<W>>, get: G)
+    where
+        W: Bindable + 'static,
+        G: Fn(&S) -> W::Value + 'static,
+    {
+        self.bindings.push(Box::new(WidgetBinding {
+            widget,
+            get: Box::new(move |state| Some(get(state))),
+            set: None,
+            shown: None,
+        }));
+    }
+
+    /// Push user edits into `state` and refresh widgets whose value changed
+    /// Returns true if any widget was updated, so the caller can redraw
+    pub fn sync(&mut self, state: &mut S, context: &Arc<VulkanContext>) -> Result<bool> {
+        let mut updated = false;
+        for binding in &mut self.bindings {
+            updated |= binding.sync(state, context)?;
+        }
+        Ok(updated)
+    }
+
+    pub fn clear(&mut self) {
+        self.bindings.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+}
+
+impl Bindings<World> {
+    /// Bind a reflected component field, `key` is "Component.field" or "Component.field.x"
+    /// for one axis of a vector field. Uses the World's `ComponentRegistry` resource.
+    ///
+    /// Edits are written directly, go through a command instead where they must be undoable.
+    pub fn bind_field<W>(&mut self, widget: Arc<RefCell<W>>, entity: EntityId, key: &str) -> Result<()>
+    where
+        W: Bindable + 'static,
+        W::Value: FieldData,
+    {
+        let path = FieldPath::parse(entity, key)
+            .ok_or_else(|| anyhow::anyhow!("Invalid field key '{}'", key))?;
+        let read = path.clone();
+        self.bindings.push(Box::new(WidgetBinding {
+            widget,
+            get: Box::new(move |world: &World| read.read(world).and_then(W::Value::from_field)),
+            set: Some(Box::new(move |world: &mut World, value: W::Value| {
+                if !path.write(world, value.to_field()) {
+                    log::warn!("Could not write {}.{}", path.component, path.field);
+                }
+            })),
+            shown: None,
+        }));
+        Ok(())
+    }
+}
+
+impl<S: 'static> Default for Bindings<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::Result;
+use ash::vk;
+use glam::Mat4;
+use std::sync::Arc;
+use crate::gui::viewport::image_quad;
+use crate::math::Color;
+use crate::renderer::{
+    BloomPass, BloomPushConstants, Mesh, PipelineId, RenderFrame, Renderer, SampledTexture, SamplerConfig, TexturedVertex2D, Texture, VulkanContext,
+};
+
+/// Glow around the bright parts of an image, see `ViewportComponent::bloom_mut`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    /// Brightness (largest channel, 0 to 1) above which colors glow
+    pub threshold: f32,
+    /// Width of the soft transition below the threshold, 0 is a hard cut
+    pub knee: f32,
+    /// Strength of the glow added back, 0 turns bloom off
+    pub intensity: f32,
+    /// Half size steps of the blur chain, more levels spread the glow wider
+    pub levels: u32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom { threshold: 0.8, knee: 0.2, intensity: 0.0, levels: 5 }
+    }
+}
+
+impl Bloom {
+    /// Bloom turned off, set an intensity to use it
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_knee(mut self, knee: f32) -> Self {
+        self.knee = knee;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_levels(mut self, levels: u32) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.intensity > 0.0 && self.levels > 0
+    }
+}
+
+/// One half size step of the chain, blurred horizontally into `scratch` and back
+struct BloomLevel {
+    texture: Texture,
+    sampled: SampledTexture,
+    scratch: Texture,
+    scratch_sampled: SampledTexture,
+}
+
+impl BloomLevel {
+    fn destroy(&self, device: &ash::Device) {
+        self.sampled.destroy(device);
+        self.texture.destroy(device);
+        self.scratch_sampled.destroy(device);
+        self.scratch.destroy(device);
+    }
+}
+
+/// A `Bloom` applied to a render target after it was drawn
+///
+/// The bright parts of the target are downsampled into a chain of half size textures, each
+/// blurred in two passes, and the levels are added back onto the target, so the glow spreads
+/// further than one wide blur at full size could afford. The chain is created the first
+/// time `apply` runs and recreated when the target's size or the level count changes.
+pub struct BloomEffect {
+    pub bloom: Bloom,
+    /// Unit quad sampling a whole texture
+    image_quad: Mesh<TexturedVertex2D>,
+    /// Descriptor set of the target it was created for
+    sampled_source: Option<(vk::ImageView, SampledTexture)>,
+    levels: Vec<BloomLevel>,
+    /// Size of the target the levels were made for
+    size: (u32, u32),
+}
+
+impl BloomEffect {
+    pub fn new(context: &Arc<VulkanContext>, bloom: Bloom) -> Result<Self> {
+        Ok(BloomEffect { bloom, image_quad: image_quad(context)?, sampled_source: None, levels: Vec::new(), size: (0, 0) })
+    }
+
+    /// Add the glow of `target` onto it, does nothing while the bloom is off
+    /// `target` must have been rendered this frame (see `RenderFrame::render_to_targets`),
+    /// with the renderer's color format.
+    pub fn apply(&mut self, frame: &RenderFrame, renderer: &mut Renderer, context: &Arc<VulkanContext>, target: &Texture) -> Result<()> {
+        if !self.bloom.is_enabled() || !target.is_rendered() {
+            return Ok(());
+        }
+        let pipeline = renderer.get_pipeline(PipelineId::Bloom)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::Bloom)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for Bloom pipeline"))?;
+        let composite_pipeline = renderer.get_pipeline(PipelineId::BloomComposite)?;
+        let composite_layout = renderer.get_pipeline_layout(PipelineId::BloomComposite)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for BloomComposite pipeline"))?;
+        let descriptor_set_layout = renderer.get_descriptor_set_layout(PipelineId::Bloom)
+            .ok_or_else(|| anyhow::anyhow!("Bloom pipeline should have a descriptor set layout"))?;
+        self.prepare(context, target, renderer.color_format(), descriptor_set_layout)?;
+        let Some((_, source)) = &self.sampled_source else {
+            return Ok(());
+        };
+        if self.levels.is_empty() {
+            return Ok(());
+        }
+
+        // Every pass fills its whole target with the unit quad
+        let projection = Mat4::orthographic_rh(-0.5, 0.5, -0.5, 0.5, -1.0, 1.0);
+        let image_quad = &self.image_quad;
+        let pass = |output: &Texture, input: &SampledTexture, push: BloomPushConstants| {
+            frame.render_to_texture(output, Color::TRANSPARENT, |ctx| {
+                ctx.bind_pipeline(pipeline);
+                ctx.bind_descriptor_set_at(pipeline_layout, 0, input.descriptor_set);
+                ctx.push(pipeline_layout, &push);
+                image_quad.draw(ctx)
+            })
+        };
+        let texel = |texture: &Texture| [1.0 / texture.width as f32, 1.0 / texture.height as f32];
+
+        let first = &self.levels[0];
+        pass(&first.texture, source, BloomPushConstants::new(projection, Mat4::IDENTITY, BloomPass::Prefilter, texel(target))
+            .with_threshold(self.bloom.threshold, self.bloom.knee.max(0.0)))?;
+        for pair in self.levels.windows(2) {
+            let (larger, level) = (&pair[0], &pair[1]);
+            pass(&level.texture, &larger.sampled, BloomPushConstants::new(projection, Mat4::IDENTITY, BloomPass::Downsample, texel(&larger.texture)))?;
+        }
+        for level in &self.levels {
+            let [x, y] = texel(&level.texture);
+            pass(&level.scratch, &level.sampled, BloomPushConstants::new(projection, Mat4::IDENTITY, BloomPass::Blur, [x, 0.0]))?;
+            pass(&level.texture, &level.scratch_sampled, BloomPushConstants::new(projection, Mat4::IDENTITY, BloomPass::Blur, [0.0, y]))?;
+        }
+
+        // Levels share the intensity so more levels spread the glow without brightening it
+        let intensity = self.bloom.intensity / self.levels.len() as f32;
+        let levels = &self.levels;
+        frame.render_to_targets(&[(target, None)], |ctx| {
+            ctx.bind_pipeline(composite_pipeline);
+            for level in levels {
+                ctx.bind_descriptor_set_at(composite_layout, 0, level.sampled.descriptor_set);
+                ctx.push(composite_layout, &BloomPushConstants::new(projection, Mat4::IDENTITY, BloomPass::Composite, texel(&level.texture))
+                    .with_intensity(intensity));
+                image_quad.draw(ctx)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Recreate the target's descriptor set and the chain when they changed
+    fn prepare(&mut self, context: &Arc<VulkanContext>, target: &Texture, color_format: vk::Format, descriptor_set_layout: vk::DescriptorSetLayout) -> Result<()> {
+        let size = (target.width, target.height);
+        // Halve until the smaller side would drop below a texel
+        let levels = (1..=self.bloom.levels).take_while(|&level| (size.0.min(size.1) >> level) > 0).count();
+        let source_changed = self.sampled_source.as_ref().is_none_or(|(view, _)| *view != target.image_view);
+        let resized = self.size != size || self.levels.len() != levels;
+        if !source_changed && !resized {
+            return Ok(());
+        }
+        // Old descriptor sets and levels may still be used by frames in flight
+        unsafe {
+            let _ = context.device.device_wait_idle();
+        }
+        if source_changed {
+            if let Some((_, sampled)) = self.sampled_source.take() {
+                sampled.destroy(&context.device);
+            }
+            let sampled = SampledTexture::from_view(target.image_view, SamplerConfig::linear(), descriptor_set_layout, context)?;
+            self.sampled_source = Some((target.image_view, sampled));
+        }
+        if resized {
+            for level in self.levels.drain(..) {
+                level.destroy(&context.device);
+            }
+            for level in 1..=levels as u32 {
+                let (width, height) = ((size.0 >> level).max(1), (size.1 >> level).max(1));
+                let create = || -> Result<(Texture, SampledTexture)> {
+                    let texture = Texture::render_target(width, height, color_format, &context.device, &context.instance, context.physical_device)?;
+                    let sampled = SampledTexture::new(&texture, SamplerConfig::linear(), descriptor_set_layout, context)?;
+                    Ok((texture, sampled))
+                };
+                let (texture, sampled) = create()?;
+                let (scratch, scratch_sampled) = create()?;
+                self.levels.push(BloomLevel { texture, sampled, scratch, scratch_sampled });
+            }
+            self.size = size;
+        }
+        Ok(())
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        self.image_quad.destroy(device);
+        if let Some((_, sampled)) = &self.sampled_source {
+            sampled.destroy(device);
+        }
+        for level in &self.levels {
+            level.destroy(device);
+        }
+    }
+}
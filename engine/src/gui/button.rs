@@ -2,9 +2,18 @@ use anyhow::Result;
 use std::sync::Arc;
 use std::cell::RefCell;
 use crate::renderer::{ColorVertex2D, Mesh, PipelineId, RenderContext, VertexBuffer};
-use crate::gui::{GUIComponent, Transform, TextComponent};
+use crate::gui::{AccessAction, AccessTree, Color, GUIComponent, Rect, Transform, TextComponent};
+use accesskit::{Action, DefaultActionVerb, NodeBuilder, Role};
 
 use crate::renderer::PushConstants2D;
+use glam::Vec2;
+
+/// Space between the text and the edges of the button
+const TEXT_PADDING: Vec2 = Vec2::new(8.0, 4.0);
+/// Seconds the hover highlight takes to fade in or out
+const HOVER_FADE: f32 = 0.08;
+/// Brightness of a fully hovered button
+const HOVER_BRIGHTNESS: f32 = 0.7;
 
 /// Button component with optional text
 pub struct ButtonComponent {
@@ -12,35 +21,36 @@ pub struct ButtonComponent {
     transform: Transform,
     text: Option<RefCell<TextComponent>>,
     is_hovered: bool,
-    color: [f32; 3],  // Base color for the button
+    /// Fades from 0 to 1 while hovered and back when the mouse leaves
+    hover_amount: f32,
+    /// Hover changed since the last `take_damage`
+    damaged: bool,
+    clicked: bool,
+    color: Color,  // Base color for the button
 }
 
 impl GUIComponent for ButtonComponent {
     /// Render the button and optional text
     fn render(&self, ctx: &RenderContext, renderer: &mut crate::renderer::Renderer) -> Result<()> {
-        let pipeline = renderer.get_pipeline(PipelineId::BasicGeometry)?;
-        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::BasicGeometry)
+        let pipeline = renderer.get_pipeline(PipelineId::UI)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::UI)
             .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found"))?;
         ctx.bind_pipeline(pipeline);
 
         // Set push constants (projection + transform + color modulation)
-        let color_mod = if self.is_hovered {
-            [0.7, 0.7, 0.7]  // 30% darker on hover
-        } else {
-            [1.0, 1.0, 1.0]  // Normal color
-        };
+        let brightness = 1.0 + (HOVER_BRIGHTNESS - 1.0) * self.hover_amount;
+        let color_mod = [brightness; 3];
         
-        let push = PushConstants2D {
-            projection: renderer.projection,  // Use ortho for 2D
-            transform: 
+        let transform =
             glam::Mat4::from_translation(glam::Vec3::new(self.transform.position.x, self.transform.position.y, 0.0)) *
             glam::Mat4::from_rotation_z(self.transform.rotation) * 
-            glam::Mat4::from_scale(glam::Vec3::new(self.transform.scale.x, self.transform.scale.y, 1.0)),
-            color_modulation: color_mod,
-            _padding: 0.0,
-        };
+            glam::Mat4::from_scale(glam::Vec3::new(self.transform.scale.x, self.transform.scale.y, 1.0));
+        // Use ortho for 2D
+        let push = PushConstants2D::new(renderer.projection, transform)
+            .with_modulation(color_mod)
+            .with_opacity(renderer.opacity);
 
-        ctx.push_constants(pipeline_layout, &push);
+        ctx.push(pipeline_layout, &push);
         
         self.mesh.draw(ctx)?;
         
@@ -61,16 +71,60 @@ impl GUIComponent for ButtonComponent {
 
     fn handle_mouse_down(&mut self, x: f32, y: f32) {
         if self.transform.contains_point(glam::Vec2::new(x, y)) {
-            println!("Button clicked at ({}, {})", x, y);
+            log::debug!("Button clicked at ({}, {})", x, y);
+            self.clicked = true;
         }
     }
-    
-    fn handle_mouse_up(&mut self, _x: f32, _y: f32) {
-        
-    }
 
     fn handle_mouse_move(&mut self, x: f32, y: f32) {
-        self.is_hovered = self.transform.contains_point(glam::Vec2::new(x, y));
+        // Damaged right away so applications rendering on demand start the fade
+        let hovered = self.transform.contains_point(glam::Vec2::new(x, y));
+        self.damaged |= hovered != self.is_hovered;
+        self.is_hovered = hovered;
+    }
+
+    fn update(&mut self, dt: f32) {
+        let target = if self.is_hovered { 1.0 } else { 0.0 };
+        if self.hover_amount != target {
+            let step = dt / HOVER_FADE;
+            self.hover_amount = if target > self.hover_amount {
+                (self.hover_amount + step).min(target)
+            } else {
+                (self.hover_amount - step).max(target)
+            };
+            self.damaged = true;
+        }
+    }
+
+    fn next_update(&self) -> Option<f32> {
+        let target = if self.is_hovered { 1.0 } else { 0.0 };
+        (self.hover_amount != target).then_some(0.0)
+    }
+
+    /// Room for the text with some padding around it, everything offered without text
+    fn measure(&self, constraints: Vec2) -> Vec2 {
+        match &self.text {
+            Some(text) => (text.borrow().measure(constraints) + TEXT_PADDING * 2.0).min(constraints),
+            None => constraints,
+        }
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.transform.rect())
+    }
+
+    fn accessibility(&self, tree: &mut AccessTree) {
+        let mut node = NodeBuilder::new(Role::Button);
+        if let Some(text) = &self.text {
+            node.set_name(text.borrow().text());
+        }
+        node.set_default_action_verb(DefaultActionVerb::Click);
+        tree.add(node, self.transform.rect());
+    }
+
+    fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+        self.clicked |= action.action == Action::Default;
+        action.action == Action::Default
     }
 
     fn transform(&self) -> &Transform {
@@ -90,32 +144,33 @@ impl GUIComponent for ButtonComponent {
 }
 
 impl ButtonComponent {
-    pub fn new(context: &Arc<crate::renderer::VulkanContext>, color: [f32; 3]) -> Result<Self> {
+    pub fn new(context: &Arc<crate::renderer::VulkanContext>, color: Color) -> Result<Self> {
+        let rgb = color.rgb();
         // Define quad vertices with solid color
         let vertices = [
             ColorVertex2D {
                 position: [-0.5, 0.5],
-                color,
+                color: rgb,
             },
             ColorVertex2D {
                 position: [-0.5, -0.5],
-                color,
+                color: rgb,
             },
             ColorVertex2D {
                 position: [0.5, -0.5],
-                color,
+                color: rgb,
             },
             ColorVertex2D {
                 position: [0.5, -0.5],
-                color,
+                color: rgb,
             },
             ColorVertex2D {
                 position: [0.5, 0.5],
-                color,
+                color: rgb,
             },
             ColorVertex2D {
                 position: [-0.5, 0.5],
-                color,
+                color: rgb,
             },
         ];
 
@@ -132,6 +187,9 @@ impl ButtonComponent {
             transform: Transform::new(),
             text: None,
             is_hovered: false,
+            hover_amount: 0.0,
+            damaged: false,
+            clicked: false,
             color,
         })
     }
@@ -141,6 +199,11 @@ impl ButtonComponent {
         self.text = Some(RefCell::new(text));
     }
     
+    /// Returns true if the button was clicked since the last call
+    pub fn take_clicked(&mut self) -> bool {
+        std::mem::take(&mut self.clicked)
+    }
+
     /// Update the button text content
     pub fn update_text(&mut self, new_text: &str, context: &Arc<crate::renderer::VulkanContext>) -> Result<()> {
         if let Some(text_cell) = &self.text {
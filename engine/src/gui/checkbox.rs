@@ -0,0 +1,132 @@
+use anyhow::Result;
+use std::sync::Arc;
+use crate::gui::{AccessAction, AccessTree, Color, GUIComponent, PanelComponent, Rect, Transform};
+use accesskit::{Action, Checked, DefaultActionVerb, NodeBuilder, Role};
+use crate::renderer::{RenderContext, Renderer, VulkanContext};
+use glam::Vec2;
+
+/// Square toggle box, clicking it flips the checked state
+pub struct Checkbox {
+    frame: PanelComponent,
+    mark: PanelComponent,
+    checked: bool,
+    /// Name read by screen readers, the box has no text of its own
+    label: String,
+    changed: bool,
+    /// Toggled by input since the last `take_damage`
+    damaged: bool,
+    transform: Transform,
+}
+
+impl Checkbox {
+    pub fn new(context: &Arc<VulkanContext>, checked: bool) -> Result<Self> {
+        Ok(Checkbox {
+            frame: PanelComponent::new(context, Color::srgb(0.18, 0.18, 0.22))?,
+            mark: PanelComponent::new(context, Color::srgb(0.35, 0.6, 0.95))?,
+            checked,
+            label: String::new(),
+            changed: false,
+            damaged: false,
+            transform: Transform::new(),
+        })
+    }
+
+    pub fn is_checked(&self) -> bool {
+        self.checked
+    }
+
+    /// Set the state programmatically (does not report a change)
+    pub fn set_checked(&mut self, checked: bool) {
+        self.checked = checked;
+    }
+
+    /// Name read by screen readers, usually the text of the row the box is in
+    pub fn set_label(&mut self, label: &str) {
+        self.label = label.to_string();
+    }
+
+    fn toggle(&mut self) {
+        self.checked = !self.checked;
+        self.changed = true;
+        self.damaged = true;
+    }
+
+    /// Returns the new state if the user toggled it since the last call
+    pub fn take_changed(&mut self) -> Option<bool> {
+        if self.changed {
+            self.changed = false;
+            Some(self.checked)
+        } else {
+            None
+        }
+    }
+
+    /// The box is a square on the left side of the transform
+    fn box_transform(&self) -> Transform {
+        let size = self.transform.scale.x.min(self.transform.scale.y);
+        let left = self.transform.position.x - self.transform.scale.x / 2.0;
+        let mut transform = Transform::new();
+        transform.position = Vec2::new(left + size / 2.0, self.transform.position.y);
+        transform.scale = Vec2::splat(size);
+        transform
+    }
+
+    /// Position the box and check mark from the transform
+    /// Call once per frame before rendering
+    pub fn update_layout(&mut self) {
+        let frame = self.box_transform();
+        *self.frame.transform_mut() = frame;
+        let mark = self.mark.transform_mut();
+        *mark = frame;
+        mark.scale *= 0.6;
+    }
+}
+
+impl GUIComponent for Checkbox {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        self.frame.render(ctx, renderer)?;
+        if self.checked {
+            self.mark.render(ctx, renderer)?;
+        }
+        Ok(())
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        if self.box_transform().contains_point(Vec2::new(x, y)) {
+            self.toggle();
+        }
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.transform.rect())
+    }
+
+    fn accessibility(&self, tree: &mut AccessTree) {
+        let mut node = NodeBuilder::new(Role::CheckBox);
+        node.set_name(self.label.as_str());
+        node.set_checked(if self.checked { Checked::True } else { Checked::False });
+        node.set_default_action_verb(if self.checked { DefaultActionVerb::Uncheck } else { DefaultActionVerb::Check });
+        tree.add(node, self.box_transform().rect());
+    }
+
+    fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+        if action.action == Action::Default {
+            self.toggle();
+            return true;
+        }
+        false
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.frame.destroy(device);
+        self.mark.destroy(device);
+    }
+}
@@ -0,0 +1,412 @@
+use anyhow::Result;
+use ash::vk;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use crate::gui::{Color, GUIComponent, Rect, TextComponent, Transform};
+use crate::renderer::{
+    ColorVertex2D, FontAtlas, Mesh, PipelineId, PushConstants2D, RenderContext, Renderer, VertexBuffer, VulkanContext,
+};
+use glam::{Mat4, Vec2, Vec3};
+
+/// Cells per side of the saturation/value square mesh
+const SQUARE_CELLS: usize = 8;
+/// Width of the hue and alpha bars in pixels
+const BAR_WIDTH: f32 = 14.0;
+const GAP: f32 = 4.0;
+/// Old square meshes are kept alive for this many rebuilds, longer than any frame stays in flight
+const MESHES_KEPT: usize = 3;
+
+fn hsv_to_linear(h: f32, s: f32, v: f32) -> [f32; 3] {
+    Color::from_hsv(h, s, v).rgb()
+}
+
+/// Quad covering the unit square with one color per corner (top left, bottom left, bottom right, top right)
+pub(super) fn quad(left: f32, bottom: f32, right: f32, top: f32, colors: [[f32; 3]; 4]) -> [ColorVertex2D; 6] {
+    let [tl, bl, br, tr] = colors;
+    [
+        ColorVertex2D { position: [left, top], color: tl },
+        ColorVertex2D { position: [left, bottom], color: bl },
+        ColorVertex2D { position: [right, bottom], color: br },
+        ColorVertex2D { position: [right, bottom], color: br },
+        ColorVertex2D { position: [right, top], color: tr },
+        ColorVertex2D { position: [left, top], color: tl },
+    ]
+}
+
+pub(super) fn create_mesh(context: &Arc<VulkanContext>, vertices: &[ColorVertex2D]) -> Result<Mesh<ColorVertex2D>> {
+    Ok(Mesh::new(VertexBuffer::new(&context.device, context.physical_device, &context.instance, vertices)?))
+}
+
+/// Saturation (x) / value (y) square for a hue, in linear colors
+fn square_vertices(hue: f32) -> Vec<ColorVertex2D> {
+    let step = 1.0 / SQUARE_CELLS as f32;
+    let mut vertices = Vec::with_capacity(SQUARE_CELLS * SQUARE_CELLS * 6);
+    for row in 0..SQUARE_CELLS {
+        for column in 0..SQUARE_CELLS {
+            let (s0, s1) = (column as f32 * step, (column + 1) as f32 * step);
+            let (v0, v1) = (row as f32 * step, (row + 1) as f32 * step);
+            vertices.extend(quad(
+                s0 - 0.5,
+                v0 - 0.5,
+                s1 - 0.5,
+                v1 - 0.5,
+                [hsv_to_linear(hue, s0, v1), hsv_to_linear(hue, s0, v0), hsv_to_linear(hue, s1, v0), hsv_to_linear(hue, s1, v1)],
+            ));
+        }
+    }
+    vertices
+}
+
+/// Vertical hue gradient, red at the bottom
+fn hue_vertices() -> Vec<ColorVertex2D> {
+    (0..6)
+        .flat_map(|i| {
+            let (h0, h1) = (i as f32 / 6.0, (i + 1) as f32 / 6.0);
+            let (bottom, top) = (hsv_to_linear(h0, 1.0, 1.0), hsv_to_linear(h1, 1.0, 1.0));
+            quad(-0.5, h0 - 0.5, 0.5, h1 - 0.5, [top, bottom, bottom, top])
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Part {
+    Square,
+    Hue,
+    Alpha,
+}
+
+/// HSV color editor: saturation/value square, hue bar, optional alpha bar and a hex field
+///
+/// The hex field and the HSV controls work in sRGB (what the eye sees), `color` and
+/// `set_color` take a linear `Color` like the rest of the GUI.
+pub struct ColorPicker {
+    hue: f32,
+    saturation: f32,
+    value: f32,
+    alpha: f32,
+    show_alpha: bool,
+    square: Mesh<ColorVertex2D>,
+    /// Hue the square mesh was built for
+    square_hue: f32,
+    retired: VecDeque<Mesh<ColorVertex2D>>,
+    hue_bar: Mesh<ColorVertex2D>,
+    /// Grey ramp, tinted with the current color when drawn
+    alpha_bar: Mesh<ColorVertex2D>,
+    /// White quad tinted for the preview swatch and markers
+    white: Mesh<ColorVertex2D>,
+    hex: TextComponent,
+    drag: Option<Part>,
+    changed: bool,
+    /// Color changed by input since the last `take_damage`
+    damaged: bool,
+    transform: Transform,
+}
+
+impl ColorPicker {
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        color: Color,
+    ) -> Result<Self> {
+        let dark = [0.05, 0.05, 0.05];
+        let white = [1.0, 1.0, 1.0];
+        let mut picker = ColorPicker {
+            hue: 0.0,
+            saturation: 0.0,
+            value: 0.0,
+            alpha: 1.0,
+            show_alpha: true,
+            square: create_mesh(context, &square_vertices(0.0))?,
+            square_hue: 0.0,
+            retired: VecDeque::with_capacity(MESHES_KEPT + 1),
+            hue_bar: create_mesh(context, &hue_vertices())?,
+            alpha_bar: create_mesh(context, &quad(-0.5, -0.5, 0.5, 0.5, [white, dark, dark, white]))?,
+            white: create_mesh(context, &quad(-0.5, -0.5, 0.5, 0.5, [white; 4]))?,
+            hex: TextComponent::new("-", font_atlas, font_size, descriptor_set_layout, context)?,
+            drag: None,
+            changed: false,
+            damaged: false,
+            transform: Transform::new(),
+        };
+        picker.set_color(color);
+        Ok(picker)
+    }
+
+    pub fn color(&self) -> Color {
+        Color::from_hsv(self.hue, self.saturation, self.value).with_alpha(self.alpha)
+    }
+
+    /// Set the color programmatically (does not report a change)
+    pub fn set_color(&mut self, color: Color) {
+        let (hue, saturation, value) = color.to_hsv();
+        // Greys have no hue, keep the current one so the square doesn't jump
+        if let Some(hue) = hue {
+            self.hue = hue;
+        }
+        self.saturation = saturation;
+        self.value = value;
+        self.alpha = color.a.clamp(0.0, 1.0);
+    }
+
+    /// Hide the alpha bar for opaque colors (alpha stays at its current value)
+    pub fn set_show_alpha(&mut self, show_alpha: bool) {
+        self.show_alpha = show_alpha;
+    }
+
+    /// "#RRGGBB" or "#RRGGBBAA" in sRGB
+    pub fn hex(&self) -> String {
+        self.color().to_hex(self.show_alpha)
+    }
+
+    /// Parse "#RRGGBB" / "#RRGGBBAA" (sRGB, '#' optional) and report it as a user change
+    /// Returns false if the text isn't a valid color
+    pub fn set_hex(&mut self, text: &str) -> bool {
+        let Some(color) = Color::hex(text) else {
+            return false;
+        };
+        self.set_color(color);
+        self.changed = true;
+        true
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Returns the new color if the user changed it since the last call
+    pub fn take_changed(&mut self) -> Option<Color> {
+        if self.changed {
+            self.changed = false;
+            Some(self.color())
+        } else {
+            None
+        }
+    }
+
+    /// Areas of the controls: square, hue bar, alpha bar, swatch and the hex text row
+    fn layout(&self) -> [Transform; 4] {
+        let size = self.transform.scale;
+        let left = self.transform.position.x - size.x / 2.0;
+        let top = self.transform.position.y + size.y / 2.0;
+        let row_height = self.hex.get_height().max(12.0) + 6.0;
+
+        let controls_height = (size.y - row_height - GAP).max(0.0);
+        let bars = if self.show_alpha { 2.0 } else { 1.0 };
+        let square_width = (size.x - bars * (BAR_WIDTH + GAP)).max(0.0);
+
+        let rect = |x: f32, y_top: f32, width: f32, height: f32| {
+            let mut transform = Transform::new();
+            transform.position = Vec2::new(x + width / 2.0, y_top - height / 2.0);
+            transform.scale = Vec2::new(width, height);
+            transform
+        };
+        let square = rect(left, top, square_width, controls_height);
+        let hue = rect(left + square_width + GAP, top, BAR_WIDTH, controls_height);
+        let alpha = rect(left + square_width + 2.0 * GAP + BAR_WIDTH, top, BAR_WIDTH, controls_height);
+        let swatch = rect(left, top - controls_height - GAP, row_height * 2.0, row_height);
+        [square, hue, alpha, swatch]
+    }
+
+    fn part_at(&self, point: Vec2) -> Option<Part> {
+        let [square, hue, alpha, _] = self.layout();
+        if square.contains_point(point) {
+            Some(Part::Square)
+        } else if hue.contains_point(point) {
+            Some(Part::Hue)
+        } else if self.show_alpha && alpha.contains_point(point) {
+            Some(Part::Alpha)
+        } else {
+            None
+        }
+    }
+
+    /// Update the dragged control from a mouse position
+    fn drag_to(&mut self, part: Part, point: Vec2) {
+        let [square, hue, alpha, _] = self.layout();
+        let fraction = |rect: &Transform| {
+            let min = rect.position - rect.scale / 2.0;
+            ((point - min) / rect.scale.max(Vec2::ONE)).clamp(Vec2::ZERO, Vec2::ONE)
+        };
+        match part {
+            Part::Square => {
+                let f = fraction(&square);
+                self.saturation = f.x;
+                self.value = f.y;
+            }
+            // Keep the hue just below 1.0 so it stays red instead of wrapping
+            Part::Hue => self.hue = fraction(&hue).y.min(0.9999),
+            Part::Alpha => self.alpha = fraction(&alpha).y,
+        }
+        self.changed = true;
+        self.damaged = true;
+    }
+
+    /// Rebuild the square for a new hue, the hex text and the layout
+    /// Call once per frame before rendering
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        if self.square_hue != self.hue {
+            let square = create_mesh(context, &square_vertices(self.hue))?;
+            self.retired.push_back(std::mem::replace(&mut self.square, square));
+            self.square_hue = self.hue;
+            while self.retired.len() > MESHES_KEPT {
+                if let Some(old) = self.retired.pop_front() {
+                    old.destroy(&context.device);
+                }
+            }
+        }
+
+        self.hex.update_text(&self.hex(), context)?;
+        let [_, _, _, swatch] = self.layout();
+        let left = swatch.position.x + swatch.scale.x / 2.0 + GAP;
+        self.hex.set_position(Vec2::new(left + self.hex.get_width() / 2.0, swatch.position.y));
+        Ok(())
+    }
+}
+
+impl GUIComponent for ColorPicker {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        let pipeline = renderer.get_pipeline(PipelineId::BasicGeometry)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::BasicGeometry)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for BasicGeometry pipeline"))?;
+        ctx.bind_pipeline(pipeline);
+
+        let projection = renderer.projection;
+        let draw = |mesh: &Mesh<ColorVertex2D>, position: Vec2, size: Vec2, color: [f32; 3]| -> Result<()> {
+            let transform = Mat4::from_translation(position.extend(0.0)) * Mat4::from_scale(Vec3::new(size.x, size.y, 1.0));
+            let push = PushConstants2D::new(projection, transform).with_modulation(color);
+            ctx.push(pipeline_layout, &push);
+            mesh.draw(ctx)
+        };
+        // Black outlined white marker
+        let marker = |position: Vec2, size: Vec2| -> Result<()> {
+            draw(&self.white, position, size + Vec2::splat(2.0), [0.0, 0.0, 0.0])?;
+            draw(&self.white, position, size, [1.0, 1.0, 1.0])
+        };
+
+        let [square, hue, alpha, swatch] = self.layout();
+        let bottom = |rect: &Transform| rect.position.y - rect.scale.y / 2.0;
+        let rgb = self.color().rgb();
+
+        draw(&self.square, square.position, square.scale, [1.0, 1.0, 1.0])?;
+        let square_min = square.position - square.scale / 2.0;
+        marker(square_min + square.scale * Vec2::new(self.saturation, self.value), Vec2::splat(5.0))?;
+
+        draw(&self.hue_bar, hue.position, hue.scale, [1.0, 1.0, 1.0])?;
+        marker(Vec2::new(hue.position.x, bottom(&hue) + hue.scale.y * self.hue), Vec2::new(BAR_WIDTH, 2.0))?;
+
+        if self.show_alpha {
+            draw(&self.alpha_bar, alpha.position, alpha.scale, rgb)?;
+            marker(Vec2::new(alpha.position.x, bottom(&alpha) + alpha.scale.y * self.alpha), Vec2::new(BAR_WIDTH, 2.0))?;
+        }
+
+        draw(&self.white, swatch.position, swatch.scale, rgb)?;
+        self.hex.render(ctx, renderer)
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        let point = Vec2::new(x, y);
+        self.drag = self.part_at(point);
+        if let Some(part) = self.drag {
+            self.drag_to(part, point);
+        }
+    }
+
+    fn handle_mouse_up(&mut self, _x: f32, _y: f32) {
+        self.drag = None;
+    }
+
+    fn handle_mouse_move(&mut self, x: f32, y: f32) {
+        if let Some(part) = self.drag {
+            self.drag_to(part, Vec2::new(x, y));
+        }
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.transform.rect())
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.square.destroy(device);
+        for mesh in &self.retired {
+            mesh.destroy(device);
+        }
+        self.hue_bar.destroy(device);
+        self.alpha_bar.destroy(device);
+        self.white.destroy(device);
+        self.hex.destroy(device);
+    }
+}
+
+/// Flat color preview, reports clicks (e.g. to open a ColorPicker)
+pub struct ColorSwatch {
+    quad: Mesh<ColorVertex2D>,
+    color: Color,
+    clicked: bool,
+    transform: Transform,
+}
+
+impl ColorSwatch {
+    pub fn new(context: &Arc<VulkanContext>, color: Color) -> Result<Self> {
+        Ok(ColorSwatch {
+            quad: create_mesh(context, &quad(-0.5, -0.5, 0.5, 0.5, [[1.0, 1.0, 1.0]; 4]))?,
+            color,
+            clicked: false,
+            transform: Transform::new(),
+        })
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    /// Returns true if the swatch was clicked since the last call
+    pub fn take_clicked(&mut self) -> bool {
+        std::mem::take(&mut self.clicked)
+    }
+}
+
+impl GUIComponent for ColorSwatch {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        let pipeline = renderer.get_pipeline(PipelineId::BasicGeometry)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::BasicGeometry)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for BasicGeometry pipeline"))?;
+        ctx.bind_pipeline(pipeline);
+
+        let push = PushConstants2D::new(renderer.projection, self.transform.to_matrix()).with_modulation(self.color.rgb());
+        ctx.push(pipeline_layout, &push);
+        self.quad.draw(ctx)
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        if self.transform.contains_point(Vec2::new(x, y)) {
+            self.clicked = true;
+        }
+    }
+
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.quad.destroy(device);
+    }
+}
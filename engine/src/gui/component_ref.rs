@@ -2,15 +2,24 @@ use std::sync::Arc;
 use std::cell::RefCell;
 use anyhow::Result;
 
-use super::{GUIComponent, Transform, ButtonComponent, ContainerPanel};
-use crate::renderer::{RenderContext, Renderer};
+use super::{AccessAction, AccessTree, OffscreenPass, Gesture, GUIComponent, MenuItem, Transform, Vec2, ButtonComponent, Checkbox, ColorSwatch, ConsoleComponent, ColorPicker, DragFloat, TextComponent, ContainerPanel, MinimapComponent, ProfilerOverlay, PlotComponent, StatsOverlay, TableComponent, TreeView, ViewportComponent};
+#[cfg(feature = "editor-widgets")]
+use super::{CurveEditor, GradientEditor, PropertyGrid};
+use crate::math::Rect;
+use crate::renderer::{RenderContext, Renderer, VulkanContext};
+use winit::keyboard::Key;
 
 /// A reference-counted, interior-mutable wrapper for GUI components
 /// This allows external code to hold references and mutate components
 /// while they're also owned by the UI grid
+/// `children` stays empty, the wrapped component can't be lent out past its borrow.
+///
+/// A context menu can be attached to any wrapped component with `set_context_menu`,
+/// it is used where the component doesn't bring its own.
 pub struct ComponentRef<T> {
     inner: Arc<RefCell<T>>,
     cached_transform: Transform,
+    context_menu: Option<Vec<MenuItem>>,
 }
 
 impl<T> ComponentRef<T> {
@@ -19,6 +28,7 @@ impl<T> ComponentRef<T> {
         let wrapper = ComponentRef {
             inner: arc.clone(),
             cached_transform: Transform::new(),
+            context_menu: None,
         };
         (wrapper, arc)
     }
@@ -27,6 +37,11 @@ impl<T> ComponentRef<T> {
     pub fn handle(&self) -> Arc<RefCell<T>> {
         self.inner.clone()
     }
+
+    /// Items shown when the component is right-clicked, see `UISystem::open_context_menu`
+    pub fn set_context_menu(&mut self, items: Vec<MenuItem>) {
+        self.context_menu = Some(items);
+    }
 }
 
 // Macro to reduce boilerplate for implementing GUIComponent
@@ -40,6 +55,14 @@ macro_rules! impl_component_ref {
                 component.render(ctx, renderer)
             }
             
+            fn render_overlay(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+                self.inner.borrow().render_overlay(ctx, renderer)
+            }
+
+            fn overlay_rect(&self) -> Option<Rect> {
+                self.inner.borrow().overlay_rect()
+            }
+
             fn transform(&self) -> &Transform {
                 &self.cached_transform
             }
@@ -47,6 +70,25 @@ macro_rules! impl_component_ref {
             fn transform_mut(&mut self) -> &mut Transform {
                 &mut self.cached_transform
             }
+
+            fn set_layout(&mut self, rect: Rect) {
+                let mut component = self.inner.borrow_mut();
+                component.set_layout(rect);
+                self.cached_transform = *component.transform();
+            }
+
+
+            fn measure(&self, constraints: Vec2) -> Vec2 {
+                self.inner.borrow().measure(constraints)
+            }
+
+            fn update(&mut self, dt: f32) {
+                self.inner.borrow_mut().update(dt);
+            }
+
+            fn next_update(&self) -> Option<f32> {
+                self.inner.borrow().next_update()
+            }
             
             fn handle_mouse_down(&mut self, x: f32, y: f32) {
                 self.inner.borrow_mut().handle_mouse_down(x, y);
@@ -59,6 +101,38 @@ macro_rules! impl_component_ref {
             fn handle_mouse_move(&mut self, x: f32, y: f32) {
                 self.inner.borrow_mut().handle_mouse_move(x, y);
             }
+
+            fn handle_key(&mut self, key: &Key) -> bool {
+                self.inner.borrow_mut().handle_key(key)
+            }
+
+            fn handle_gesture(&mut self, gesture: &Gesture) -> bool {
+                self.inner.borrow_mut().handle_gesture(gesture)
+            }
+
+            fn context_menu(&self, point: Vec2) -> Option<Vec<MenuItem>> {
+                self.inner.borrow().context_menu(point).or_else(|| self.context_menu.clone())
+            }
+
+            fn take_damage(&mut self) -> Option<Rect> {
+                self.inner.borrow_mut().take_damage()
+            }
+
+            fn accessibility(&self, tree: &mut AccessTree) {
+                self.inner.borrow().accessibility(tree);
+            }
+
+            fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+                self.inner.borrow_mut().accessibility_action(action)
+            }
+
+            fn update_geometry(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+                self.inner.borrow_mut().update_geometry(context)
+            }
+
+            fn render_offscreen(&mut self, pass: &OffscreenPass, renderer: &mut Renderer) -> Result<()> {
+                self.inner.borrow_mut().render_offscreen(pass, renderer)
+            }
             
             fn destroy(&self, device: &ash::Device) {
                 self.inner.borrow().destroy(device);
@@ -70,7 +144,56 @@ macro_rules! impl_component_ref {
 // ButtonComponent - no pre-render needed
 impl_component_ref!(ButtonComponent, |_: &mut ButtonComponent| {});
 
-// ContainerPanel - needs grid layout update
-impl_component_ref!(ContainerPanel, |c: &mut ContainerPanel| {
-    c.update_grid_layout();
-});
+// ContainerPanel - children are laid out through set_layout, nothing to do here
+impl_component_ref!(ContainerPanel, |_: &mut ContainerPanel| {});
+
+// ConsoleComponent - lines are refreshed by the owner, nothing to do here
+impl_component_ref!(ConsoleComponent, |_: &mut ConsoleComponent| {});
+
+// ProfilerOverlay - bars are refreshed by the owner, nothing to do here
+impl_component_ref!(ProfilerOverlay, |_: &mut ProfilerOverlay| {});
+
+// StatsOverlay - text is refreshed by the owner, nothing to do here
+impl_component_ref!(StatsOverlay, |_: &mut StatsOverlay| {});
+
+// TreeView - rows are refreshed by the owner, nothing to do here
+impl_component_ref!(TreeView, |_: &mut TreeView| {});
+
+// PlotComponent - geometry is rebuilt by the owner's refresh, nothing to do here
+impl_component_ref!(PlotComponent, |_: &mut PlotComponent| {});
+
+// TableComponent - rows are refreshed by the owner from its source, nothing to do here
+impl_component_ref!(TableComponent, |_: &mut TableComponent| {});
+
+// PropertyGrid - rows are refreshed by the owner, nothing to do here
+#[cfg(feature = "editor-widgets")]
+impl_component_ref!(PropertyGrid, |_: &mut PropertyGrid| {});
+
+// ColorPicker - square mesh is rebuilt by the owner's refresh, nothing to do here
+impl_component_ref!(ColorPicker, |_: &mut ColorPicker| {});
+
+// CurveEditor - line mesh is rebuilt by the owner's refresh, nothing to do here
+#[cfg(feature = "editor-widgets")]
+impl_component_ref!(CurveEditor, |_: &mut CurveEditor| {});
+
+// GradientEditor - bar mesh is rebuilt by the owner's refresh, nothing to do here
+#[cfg(feature = "editor-widgets")]
+impl_component_ref!(GradientEditor, |_: &mut GradientEditor| {});
+
+// ViewportComponent - target is resized by the owner, nothing to do here
+impl_component_ref!(ViewportComponent, |_: &mut ViewportComponent| {});
+
+// MinimapComponent - target is resized by the owner, nothing to do here
+impl_component_ref!(MinimapComponent, |_: &mut MinimapComponent| {});
+
+// DragFloat - text is rebuilt by the owner's refresh (or a binding), only follow the transform here
+impl_component_ref!(DragFloat, |drag: &mut DragFloat| drag.update_layout());
+
+// Checkbox - box and mark follow the transform set just before rendering
+impl_component_ref!(Checkbox, |checkbox: &mut Checkbox| checkbox.update_layout());
+
+// ColorSwatch - a single quad, nothing to do here
+impl_component_ref!(ColorSwatch, |_: &mut ColorSwatch| {});
+
+// TextComponent - text is updated by the owner (or a binding), nothing to do here
+impl_component_ref!(TextComponent, |_: &mut TextComponent| {});
@@ -0,0 +1,315 @@
+use anyhow::Result;
+use ash::vk;
+use log::LevelFilter;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use crate::cvars::CVars;
+use crate::gui::{Color, GUIComponent, PanelComponent, Rect, TextComponent, Transform};
+use crate::logging;
+use crate::renderer::{FontAtlas, RenderContext, Renderer, VulkanContext};
+use glam::Vec2;
+
+/// Handler for a console command: receives the arguments, returns output text
+type CommandHandler = Box<dyn FnMut(&[&str]) -> String>;
+
+/// A debug command that can be run from the console
+struct ConsoleCommand {
+    help: String,
+    handler: CommandHandler,
+}
+
+/// On-screen console that shows recent log lines and runs registered debug commands
+pub struct ConsoleComponent {
+    background: PanelComponent,
+    lines: Vec<TextComponent>,
+    /// Number of line slots holding real log text (the rest are hidden)
+    shown_lines: usize,
+    input_text: TextComponent,
+    input: String,
+    filter: LevelFilter,
+    commands: BTreeMap<String, ConsoleCommand>,
+    transform: Transform,
+    line_height: f32,
+    visible: bool,
+    /// Seconds into the current caret blink cycle, restarted by typing
+    caret_time: f32,
+    /// Caret shown or blink phase changed since the last `take_damage`
+    damaged: bool,
+}
+
+/// Seconds the input caret stays on, then off
+const CARET_BLINK: f32 = 0.5;
+
+impl ConsoleComponent {
+    /// Create a console with `visible_lines` rows of log output plus an input row
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        visible_lines: usize,
+    ) -> Result<Self> {
+        let mut lines = Vec::with_capacity(visible_lines);
+        for _ in 0..visible_lines {
+            // Placeholder text - an empty string would create a zero-sized vertex buffer
+            lines.push(TextComponent::new("-", font_atlas.clone(), font_size, descriptor_set_layout, context)?);
+        }
+
+        Ok(ConsoleComponent {
+            background: PanelComponent::new(context, Color::srgb(0.05, 0.05, 0.07))?,
+            lines,
+            shown_lines: 0,
+            input_text: TextComponent::new(">", font_atlas, font_size, descriptor_set_layout, context)?,
+            input: String::new(),
+            filter: LevelFilter::Info,
+            commands: BTreeMap::new(),
+            transform: Transform::new(),
+            line_height: font_size + 2.0,
+            visible: true,
+            caret_time: 0.0,
+            damaged: false,
+        })
+    }
+
+    /// Register a debug command. The handler receives the whitespace-separated
+    /// arguments and returns text that is written to the log.
+    pub fn register_command<F>(&mut self, name: &str, help: &str, handler: F)
+    where
+        F: FnMut(&[&str]) -> String + 'static,
+    {
+        self.commands.insert(name.to_string(), ConsoleCommand {
+            help: help.to_string(),
+            handler: Box::new(handler),
+        });
+    }
+
+    /// Register `cvars`, `get`, `set` and `reset` to list, read and change the registry's values
+    pub fn register_cvars(&mut self, cvars: Rc<RefCell<CVars>>) {
+        self.register_command("cvars", "cvars [prefix] - list tunable values", {
+            let cvars = cvars.clone();
+            move |args| {
+                let cvars = cvars.borrow();
+                let prefix = args.first().copied().unwrap_or("");
+                let mut found = false;
+                // One log line each, the console shows a line per record
+                for name in cvars.names().filter(|name| name.starts_with(prefix)) {
+                    let value = cvars.get(name).expect("Listed by names");
+                    log::info!(target: "console", "{} = {} - {}", name, value, cvars.help(name).unwrap_or(""));
+                    found = true;
+                }
+                if found { String::new() } else { format!("no cvars start with '{}'", prefix) }
+            }
+        });
+        self.register_command("get", "get <cvar> - print a tunable value and its default", {
+            let cvars = cvars.clone();
+            move |args| {
+                let cvars = cvars.borrow();
+                let Some(name) = args.first() else {
+                    return "usage: get <cvar>".to_string();
+                };
+                match (cvars.get(name), cvars.default_value(name)) {
+                    (Some(value), Some(default)) => format!("{} = {} (default {})", name, value, default),
+                    _ => format!("unknown cvar '{}'", name),
+                }
+            }
+        });
+        self.register_command("set", "set <cvar> <value> - change a tunable value", {
+            let cvars = cvars.clone();
+            move |args| {
+                let [name, value] = args else {
+                    return "usage: set <cvar> <value>".to_string();
+                };
+                let mut cvars = cvars.borrow_mut();
+                match cvars.set_text(name, value) {
+                    Ok(()) => format!("{} = {}", name, cvars.get(name).expect("Just set")),
+                    Err(e) => e.to_string(),
+                }
+            }
+        });
+        self.register_command("reset", "reset <cvar> - restore a tunable value's default", move |args| {
+            let Some(name) = args.first() else {
+                return "usage: reset <cvar>".to_string();
+            };
+            let mut cvars = cvars.borrow_mut();
+            match cvars.reset(name) {
+                Ok(()) => format!("{} = {}", name, cvars.get(name).expect("Just reset")),
+                Err(e) => e.to_string(),
+            }
+        });
+    }
+
+    /// Only show log lines at or above this level
+    pub fn set_filter(&mut self, filter: LevelFilter) {
+        self.filter = filter;
+    }
+
+    pub fn filter(&self) -> LevelFilter {
+        self.filter
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn toggle_visible(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Append typed text to the input line
+    pub fn push_input(&mut self, text: &str) {
+        self.input.extend(text.chars().filter(|c| !c.is_control()));
+        self.caret_time = 0.0;
+    }
+
+    /// Remove the last character from the input line
+    pub fn pop_input(&mut self) {
+        self.input.pop();
+        self.caret_time = 0.0;
+    }
+
+    fn caret_visible(&self) -> bool {
+        self.caret_time < CARET_BLINK
+    }
+
+    /// Execute the current input line and clear it
+    pub fn submit_input(&mut self) {
+        let line = std::mem::take(&mut self.input);
+        self.execute(&line);
+    }
+
+    /// Execute a command line such as `filter debug`
+    pub fn execute(&mut self, line: &str) {
+        let mut parts = line.split_whitespace();
+        let Some(name) = parts.next() else {
+            return;
+        };
+        let args: Vec<&str> = parts.collect();
+        log::info!(target: "console", "> {}", line.trim());
+
+        match name {
+            "help" => {
+                log::info!(target: "console", "help, clear, filter <error|warn|info|debug|trace>");
+                for (name, command) in &self.commands {
+                    log::info!(target: "console", "{} - {}", name, command.help);
+                }
+            }
+            "clear" => logging::clear_history(),
+            "filter" => match args.first().and_then(|level| level.parse::<LevelFilter>().ok()) {
+                Some(level) => self.filter = level,
+                None => log::warn!(target: "console", "usage: filter <error|warn|info|debug|trace>"),
+            },
+            _ => match self.commands.get_mut(name) {
+                Some(command) => {
+                    let output = (command.handler)(&args);
+                    if !output.is_empty() {
+                        log::info!(target: "console", "{}", output);
+                    }
+                }
+                None => log::warn!(target: "console", "unknown command '{}'", name),
+            },
+        }
+    }
+
+    /// Pull the latest log lines into the text meshes
+    /// Call once per frame before rendering (text is only rebuilt when it changed)
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        // Blank lines are dropped first so the shown slots stay contiguous
+        let recent: Vec<_> = logging::recent_lines(self.lines.len(), self.filter)
+            .into_iter()
+            .filter(|line| !line.message.trim().is_empty())
+            .map(|line| (line.formatted(), line.level))
+            .collect();
+        self.shown_lines = 0;
+        for (slot, (text, level)) in self.lines.iter_mut().zip(recent) {
+            slot.update_text(&text, context)?;
+            slot.set_color(match level {
+                log::Level::Error => Color::srgb(1.0, 0.35, 0.35),
+                log::Level::Warn => Color::srgb(1.0, 0.8, 0.3),
+                log::Level::Info => Color::srgb(0.9, 0.9, 0.9),
+                log::Level::Debug | log::Level::Trace => Color::srgb(0.6, 0.6, 0.65),
+            });
+            self.shown_lines += 1;
+        }
+
+        let caret = if self.caret_visible() { "_" } else { "" };
+        self.input_text.update_text(&format!("> {}{}", self.input, caret), context)?;
+        self.update_layout();
+        Ok(())
+    }
+
+    /// Stack the shown lines upwards from the input row, newest at the bottom
+    fn update_layout(&mut self) {
+        *self.background.transform_mut() = self.transform;
+
+        let left = self.transform.position.x - self.transform.scale.x / 2.0 + 6.0;
+        let bottom = self.transform.position.y - self.transform.scale.y / 2.0;
+
+        for (i, line) in self.lines.iter_mut().take(self.shown_lines).enumerate() {
+            let row = (self.shown_lines - i) as f32;
+            let y = bottom + self.line_height * (row + 0.5);
+            line.set_position(Vec2::new(left + line.get_width() / 2.0, y));
+        }
+
+        let input_x = left + self.input_text.get_width() / 2.0;
+        self.input_text.set_position(Vec2::new(input_x, bottom + self.line_height / 2.0));
+    }
+}
+
+impl GUIComponent for ConsoleComponent {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        self.background.render(ctx, renderer)?;
+
+        // Leave room for the input row at the bottom
+        let max_rows = ((self.transform.scale.y / self.line_height) as usize).saturating_sub(1);
+        let shown = self.shown_lines.min(max_rows);
+        for line in &self.lines[self.shown_lines - shown..self.shown_lines] {
+            line.render(ctx, renderer)?;
+        }
+
+        self.input_text.render(ctx, renderer)
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn update(&mut self, dt: f32) {
+        if !self.visible {
+            return;
+        }
+        let was_visible = self.caret_visible();
+        self.caret_time = (self.caret_time + dt) % (CARET_BLINK * 2.0);
+        self.damaged |= self.caret_visible() != was_visible;
+    }
+
+    fn next_update(&self) -> Option<f32> {
+        let phase_end = if self.caret_visible() { CARET_BLINK } else { CARET_BLINK * 2.0 };
+        self.visible.then_some(phase_end - self.caret_time)
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.transform.rect())
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.background.destroy(device);
+        for line in &self.lines {
+            line.destroy(device);
+        }
+        self.input_text.destroy(device);
+    }
+}
@@ -1,26 +1,165 @@
 use anyhow::Result;
+use ash::vk;
 use std::sync::Arc;
-use crate::gui::{GUIComponent, Transform, Grid, PanelComponent};
-use crate::renderer::RenderContext;
+use crate::gui::viewport::image_quad;
+use crate::gui::{AccessAction, AccessTree, Backdrop, BackdropEffect, Color, Gesture, GUIComponent, MenuItem, Transform, Grid, PanelComponent, Vec2};
+use winit::keyboard::Key;
+use crate::math::Rect;
+use crate::renderer::{Mesh, PipelineId, PushConstants2D, RenderContext, RenderFrame, Renderer, SampledTexture, SamplerConfig, TexturedVertex2D, Texture, VulkanContext};
+
+/// Where and at what resolution `GUIComponent::render_offscreen` draws, see `UISystem::render_offscreen`
+pub struct OffscreenPass<'a> {
+    pub frame: &'a RenderFrame,
+    pub context: &'a Arc<VulkanContext>,
+    /// Window pixels per UI unit
+    pub scale: f32,
+    /// UI area changed since the last pass, including changes made from outside the UI
+    pub damage: Option<Rect>,
+}
+
+/// Offscreen copy of a container's contents, see `ContainerPanel::enable_cache`
+struct PanelCache {
+    /// Unit quad sampling the whole target
+    image_quad: Mesh<TexturedVertex2D>,
+    /// Layout of `PipelineId::Image`
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    target: Option<(Texture, SampledTexture)>,
+    /// The contents changed since they were last drawn into the target
+    dirty: bool,
+}
+
+impl PanelCache {
+    /// Recreate the target when the container's size in pixels changed
+    fn resize(&mut self, context: &Arc<VulkanContext>, width: u32, height: u32, color_format: vk::Format) -> Result<()> {
+        if let Some((texture, _)) = &self.target {
+            if texture.width == width && texture.height == height {
+                return Ok(());
+            }
+            // The old target may still be sampled by frames in flight
+            unsafe {
+                let _ = context.device.device_wait_idle();
+            }
+            self.destroy_target(&context.device);
+        }
+        let texture = Texture::render_target(width, height, color_format, &context.device, &context.instance, context.physical_device)?;
+        // Rasterized at the size it is drawn, so texels map to pixels one to one
+        let sampled = SampledTexture::new(&texture, SamplerConfig::nearest(), self.descriptor_set_layout, context)?;
+        self.target = Some((texture, sampled));
+        Ok(())
+    }
+
+    fn destroy_target(&mut self, device: &ash::Device) {
+        if let Some((texture, sampled)) = self.target.take() {
+            sampled.destroy(device);
+            texture.destroy(device);
+        }
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.image_quad.destroy(device);
+        if let Some((texture, sampled)) = &self.target {
+            sampled.destroy(device);
+            texture.destroy(device);
+        }
+    }
+}
 
 /// A panel that can contain other components in a grid layout
+///
+/// Mostly static panels can keep their contents in an offscreen texture with `enable_cache`,
+/// they are then drawn as a single quad until something in them changes. A `Backdrop`
+/// blurs or dims what shows through a translucent background, see `set_backdrop`.
 pub struct ContainerPanel {
     background: PanelComponent,
     grid: Grid,
     transform: Transform,
+    cache: Option<PanelCache>,
+    backdrop: Option<BackdropEffect>,
 }
 
 impl GUIComponent for ContainerPanel {
     fn render(&self, ctx: &RenderContext, renderer: &mut crate::renderer::Renderer) -> Result<()> {
-        // First render the background panel
-        self.background.render(ctx, renderer)?;
-        
-        // Then render the grid contents
-        self.grid.render(ctx, renderer)?;
-        
+        // Changes every frame, so it's never part of the cache
+        if let Some(backdrop) = &self.backdrop {
+            backdrop.render(ctx, renderer, self.transform.rect())?;
+        }
+        let cached = self.cache.as_ref().filter(|cache| !cache.dirty);
+        if let Some((cache, (_, sampled))) = cached.and_then(|cache| Some((cache, cache.target.as_ref()?))) {
+            let pipeline = renderer.get_pipeline(PipelineId::Image)?;
+            let pipeline_layout = renderer.get_pipeline_layout(PipelineId::Image)
+                .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for Image pipeline"))?;
+            ctx.bind_pipeline(pipeline);
+            ctx.bind_descriptor_set_at(pipeline_layout, 0, sampled.descriptor_set);
+            ctx.push(pipeline_layout, &PushConstants2D::new(renderer.projection, self.transform.to_matrix()).with_opacity(renderer.opacity));
+            return cache.image_quad.draw(ctx);
+        }
+        self.render_contents(ctx, renderer)
+    }
+
+    fn render_offscreen(&mut self, pass: &OffscreenPass, renderer: &mut Renderer) -> Result<()> {
+        // Nested caches first, they are drawn into this one
+        self.grid.render_offscreen(pass, renderer)?;
+        let rect = self.transform.rect();
+        if let Some(backdrop) = &mut self.backdrop {
+            backdrop.render_offscreen(pass, renderer, rect)?;
+        }
+        let Some(cache) = self.cache.as_mut() else {
+            return Ok(());
+        };
+        let overlap = pass.damage.map(|damage| damage.intersect(&rect)).unwrap_or_default();
+        cache.dirty |= overlap.width > 0.0 && overlap.height > 0.0;
+        let width = (rect.width * pass.scale).round().max(0.0) as u32;
+        let height = (rect.height * pass.scale).round().max(0.0) as u32;
+        if !cache.dirty || width == 0 || height == 0 {
+            return Ok(());
+        }
+        cache.resize(pass.context, width, height, renderer.color_format())?;
+
+        let Some((texture, _)) = self.cache.as_ref().and_then(|cache| cache.target.as_ref()) else {
+            return Ok(());
+        };
+        // The container's rect fills the target, drawn at full opacity and faded as a whole
+        let (projection, opacity) = (renderer.projection, renderer.opacity);
+        renderer.projection = glam::Mat4::orthographic_rh(rect.min().x, rect.max().x, rect.min().y, rect.max().y, -1.0, 1.0);
+        renderer.opacity = 1.0;
+        let result = pass.frame.render_to_texture(texture, Color::TRANSPARENT, |ctx| self.render_contents(ctx, renderer));
+        renderer.projection = projection;
+        renderer.opacity = opacity;
+        result?;
+        if let Some(cache) = &mut self.cache {
+            cache.dirty = false;
+        }
         Ok(())
     }
 
+    fn update(&mut self, dt: f32) {
+        self.grid.update(dt);
+    }
+
+    fn next_update(&self) -> Option<f32> {
+        self.grid.next_update()
+    }
+
+    fn render_overlay(&self, ctx: &RenderContext, renderer: &mut crate::renderer::Renderer) -> Result<()> {
+        self.grid.render_overlay(ctx, renderer)
+    }
+
+    fn overlay_rect(&self) -> Option<Rect> {
+        self.grid.overlay_rect()
+    }
+
+    fn handle_key(&mut self, key: &Key) -> bool {
+        self.grid.handle_key(key)
+    }
+
+    fn handle_gesture(&mut self, gesture: &Gesture) -> bool {
+        self.grid.handle_gesture(gesture)
+    }
+
+    fn context_menu(&self, point: Vec2) -> Option<Vec<MenuItem>> {
+        self.grid.context_menu_at(point)
+    }
+
     fn handle_mouse_down(&mut self, x: f32, y: f32) {
         self.grid.handle_mouse_down(x, y);
     }
@@ -33,6 +172,27 @@ impl GUIComponent for ContainerPanel {
         self.grid.handle_mouse_move(x, y);
     }
 
+    fn take_damage(&mut self) -> Option<Rect> {
+        let damage = self.grid.take_damage();
+        if damage.is_some() {
+            self.invalidate_cache();
+        }
+        damage
+    }
+
+    fn update_geometry(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        self.grid.update_geometry(context)
+    }
+
+    fn set_layout(&mut self, rect: Rect) {
+        if rect != self.transform.rect() {
+            self.invalidate_cache();
+        }
+        self.transform.position = rect.center();
+        self.transform.scale = rect.size();
+        self.update_grid_layout();
+    }
+
     fn transform(&self) -> &Transform {
         &self.transform
     }
@@ -41,25 +201,111 @@ impl GUIComponent for ContainerPanel {
         &mut self.transform
     }
 
+    fn children(&self) -> Vec<&dyn GUIComponent> {
+        self.grid.components().collect()
+    }
+
+    fn nested_grid(&self) -> Option<&Grid> {
+        Some(&self.grid)
+    }
+
+    fn accessibility(&self, tree: &mut AccessTree) {
+        self.grid.accessibility(tree);
+    }
+
+    fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+        self.grid.accessibility_action(action)
+    }
+
     fn destroy(&self, device: &ash::Device) {
         self.background.destroy(device);
-        for row in &self.grid.rows {
-            for component in &row.components {
-                component.destroy(device);
-            }
+        self.grid.destroy(device);
+        if let Some(cache) = &self.cache {
+            cache.destroy(device);
+        }
+        if let Some(backdrop) = &self.backdrop {
+            backdrop.destroy(device);
         }
     }
 }
 
 impl ContainerPanel {
-    pub fn new(context: &Arc<crate::renderer::VulkanContext>, color: [f32; 3]) -> Result<Self> {
+    pub fn new(context: &Arc<VulkanContext>, color: Color) -> Result<Self> {
         Ok(ContainerPanel {
             background: PanelComponent::new(context, color)?,
             grid: Grid::new(),
             transform: Transform::new(),
+            cache: None,
+            backdrop: None,
         })
     }
 
+    /// Blur, dim or desaturate what's behind the panel, `None` removes the backdrop
+    /// It shows through where the background is translucent and needs a source, see
+    /// `BackdropEffect::set_source` on `backdrop_mut`.
+    pub fn set_backdrop(&mut self, context: &Arc<VulkanContext>, backdrop: Option<Backdrop>) -> Result<()> {
+        match (backdrop, &mut self.backdrop) {
+            (Some(backdrop), Some(effect)) => effect.backdrop = backdrop,
+            (Some(backdrop), None) => self.backdrop = Some(BackdropEffect::new(context, backdrop)?),
+            (None, _) => {
+                if let Some(effect) = self.backdrop.take() {
+                    // Its textures may still be sampled by frames in flight
+                    unsafe {
+                        let _ = context.device.device_wait_idle();
+                    }
+                    effect.destroy(&context.device);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn backdrop_mut(&mut self) -> Option<&mut BackdropEffect> {
+        self.backdrop.as_mut()
+    }
+
+    /// Draw the contents into an offscreen texture and show that until they change, for
+    /// panels with many widgets that rarely change. Needs `UISystem::render_offscreen` every frame.
+    ///
+    /// The cache is redrawn when a child reports damage, the panel is laid out at another rect
+    /// or `UISystem::invalidate` covers it, like every other change made from outside has to.
+    /// Popups of children are still drawn directly.
+    /// `descriptor_set_layout` is the layout of `PipelineId::Image`.
+    pub fn enable_cache(&mut self, context: &Arc<VulkanContext>, descriptor_set_layout: vk::DescriptorSetLayout) -> Result<()> {
+        if self.cache.is_none() {
+            self.cache = Some(PanelCache { image_quad: image_quad(context)?, descriptor_set_layout, target: None, dirty: true });
+        }
+        Ok(())
+    }
+
+    /// Draw the contents directly again and free the offscreen texture
+    pub fn disable_cache(&mut self, context: &Arc<VulkanContext>) {
+        if let Some(cache) = self.cache.take() {
+            // The texture may still be sampled by frames in flight
+            unsafe {
+                let _ = context.device.device_wait_idle();
+            }
+            cache.destroy(&context.device);
+        }
+    }
+
+    pub fn is_cached(&self) -> bool {
+        self.cache.is_some()
+    }
+
+    /// Redraw the offscreen texture in the next `UISystem::render_offscreen`
+    pub fn invalidate_cache(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            cache.dirty = true;
+        }
+    }
+
+    /// Background and grid, what the cache holds
+    fn render_contents(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        self.background.render(ctx, renderer)?;
+        self.grid.render(ctx, renderer)
+    }
+
     pub fn grid_mut(&mut self) -> &mut Grid {
         &mut self.grid
     }
@@ -68,20 +314,20 @@ impl ContainerPanel {
         &self.grid
     }
 
+    /// The background panel, e.g. to give it a gradient or texture
+    pub fn background_mut(&mut self) -> &mut PanelComponent {
+        &mut self.background
+    }
+
     /// Update the grid layout based on this container's current bounds
-    /// Call this after the container's transform has been set by the parent layout
+    /// Call this after the container's transform has been set by the parent layout,
+    /// the grid is only laid out again if the bounds changed.
     pub fn update_grid_layout(&mut self) {
         // Sync background panel transform with container transform
         *self.background.transform_mut() = self.transform;
         
-        // Get the container bounds from its transform
-        let x = self.transform.position.x - (self.transform.scale.x / 2.0);
-        let y = self.transform.position.y - (self.transform.scale.y / 2.0);
-        let width = self.transform.scale.x;
-        let height = self.transform.scale.y;
-        
-        // Apply internal grid layout within these bounds
-        self.grid.set_bounds(x, y, width, height);
+        // Apply internal grid layout within the container bounds
+        self.grid.set_bounds(self.transform.rect());
     }
 }
 
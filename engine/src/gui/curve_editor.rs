@@ -0,0 +1,294 @@
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use crate::gui::color_picker::{create_mesh, quad};
+use crate::gui::{GUIComponent, Rect, Transform};
+use crate::math::{Curve, Interpolation};
+use crate::renderer::{ColorVertex2D, Mesh, PipelineId, PushConstants2D, RenderContext, Renderer, VulkanContext};
+use glam::{Mat4, Vec2, Vec3};
+
+/// Line segments the curve is drawn with
+const SAMPLES: usize = 96;
+const LINE_WIDTH: f32 = 2.0;
+/// Size of a key marker, also how close a click has to be to grab one
+const KEY_SIZE: f32 = 7.0;
+/// Old line meshes are kept alive for this many rebuilds, longer than any frame stays in flight
+const MESHES_KEPT: usize = 3;
+
+/// Quad of `width` along the segment from `a` to `b`
+fn segment(a: Vec2, b: Vec2, width: f32, color: [f32; 3]) -> [ColorVertex2D; 6] {
+    let normal = (b - a).normalize_or_zero().perp() * (width / 2.0);
+    let corner = |p: Vec2| ColorVertex2D { position: p.into(), color };
+    [
+        corner(a + normal),
+        corner(a - normal),
+        corner(b - normal),
+        corner(b - normal),
+        corner(b + normal),
+        corner(a + normal),
+    ]
+}
+
+/// Graph editor for a `Curve`
+///
+/// Click empty space to add a key, drag keys to move them. The last clicked key is
+/// selected and can be removed or given another interpolation. Time runs left to
+/// right over `time_range`, values bottom to top over `value_range`.
+pub struct CurveEditor {
+    curve: Curve,
+    time_range: (f32, f32),
+    value_range: (f32, f32),
+    /// Curve line in pixels around the widget center
+    line: Mesh<ColorVertex2D>,
+    /// Size the line mesh was built for, `None` once the curve changed
+    line_size: Option<Vec2>,
+    retired: VecDeque<Mesh<ColorVertex2D>>,
+    /// White quad tinted for the background and key markers
+    white: Mesh<ColorVertex2D>,
+    /// Hidden editors don't draw or take input
+    visible: bool,
+    selected: Option<usize>,
+    dragging: bool,
+    changed: bool,
+    /// Selection or curve changed by input since the last `take_damage`
+    damaged: bool,
+    transform: Transform,
+}
+
+impl CurveEditor {
+    pub fn new(context: &Arc<VulkanContext>, curve: Curve) -> Result<Self> {
+        let (min, max) = curve.value_range();
+        let value_range = if max - min > f32::EPSILON { (min.min(0.0), max) } else { (min.min(0.0), min.max(0.0) + 1.0) };
+        Ok(CurveEditor {
+            curve,
+            time_range: (0.0, 1.0),
+            value_range,
+            line: create_mesh(context, &segment(Vec2::ZERO, Vec2::X, LINE_WIDTH, [1.0; 3]))?,
+            line_size: None,
+            retired: VecDeque::with_capacity(MESHES_KEPT + 1),
+            white: create_mesh(context, &quad(-0.5, -0.5, 0.5, 0.5, [[1.0, 1.0, 1.0]; 4]))?,
+            visible: true,
+            selected: None,
+            dragging: false,
+            changed: false,
+            damaged: false,
+            transform: Transform::new(),
+        })
+    }
+
+    pub fn curve(&self) -> &Curve {
+        &self.curve
+    }
+
+    /// Replace the curve programmatically (does not report a change)
+    pub fn set_curve(&mut self, curve: Curve) {
+        self.curve = curve;
+        self.selected = None;
+        self.dragging = false;
+        self.line_size = None;
+    }
+
+    /// Times shown from left to right, keys can't be dragged outside it
+    pub fn set_time_range(&mut self, start: f32, end: f32) {
+        self.time_range = (start, end);
+        self.line_size = None;
+    }
+
+    /// Values shown from bottom to top, keys can't be dragged outside it
+    pub fn set_value_range(&mut self, min: f32, max: f32) {
+        self.value_range = (min, max);
+        self.line_size = None;
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.dragging &= visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Remove the selected key, reported as a user change
+    pub fn remove_selected(&mut self) {
+        if let Some(index) = self.selected.take() {
+            self.curve.remove_key(index);
+            self.curve_edited();
+        }
+    }
+
+    /// Change the interpolation after the selected key, reported as a user change
+    pub fn set_selected_interpolation(&mut self, interpolation: Interpolation) {
+        if let Some(index) = self.selected {
+            self.curve.set_interpolation(index, interpolation);
+            self.curve_edited();
+        }
+    }
+
+    /// Returns the new curve if the user changed it since the last call
+    pub fn take_changed(&mut self) -> Option<Curve> {
+        if self.changed {
+            self.changed = false;
+            Some(self.curve.clone())
+        } else {
+            None
+        }
+    }
+
+    fn curve_edited(&mut self) {
+        self.changed = true;
+        self.line_size = None;
+    }
+
+    /// Position of a (time, value) point relative to the widget center
+    fn to_local(&self, time: f32, value: f32) -> Vec2 {
+        let (start, end) = self.time_range;
+        let (min, max) = self.value_range;
+        let fraction = Vec2::new(
+            (time - start) / (end - start).max(f32::EPSILON),
+            (value - min) / (max - min).max(f32::EPSILON),
+        );
+        (fraction - Vec2::splat(0.5)) * self.transform.scale
+    }
+
+    /// (time, value) under a UI space point, clamped to the shown ranges
+    fn point_value(&self, point: Vec2) -> (f32, f32) {
+        let (start, end) = self.time_range;
+        let (min, max) = self.value_range;
+        let fraction = ((point - self.transform.position) / self.transform.scale.max(Vec2::ONE) + Vec2::splat(0.5))
+            .clamp(Vec2::ZERO, Vec2::ONE);
+        (start + (end - start) * fraction.x, min + (max - min) * fraction.y)
+    }
+
+    fn key_at(&self, point: Vec2) -> Option<usize> {
+        let local = point - self.transform.position;
+        self.curve
+            .keys()
+            .iter()
+            .position(|key| (self.to_local(key.time, key.value) - local).abs().max_element() <= KEY_SIZE)
+    }
+
+    fn line_vertices(&self) -> Vec<ColorVertex2D> {
+        let (start, end) = self.time_range;
+        let half = self.transform.scale / 2.0;
+        let points: Vec<Vec2> = (0..=SAMPLES)
+            .map(|i| {
+                let time = start + (end - start) * i as f32 / SAMPLES as f32;
+                self.to_local(time, self.curve.sample(time)).clamp(-half, half)
+            })
+            .collect();
+        points.windows(2).flat_map(|pair| segment(pair[0], pair[1], LINE_WIDTH, [1.0, 1.0, 1.0])).collect()
+    }
+
+    /// Rebuild the curve line after an edit or resize
+    /// Call once per frame before rendering
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        if self.line_size == Some(self.transform.scale) {
+            return Ok(());
+        }
+        let line = create_mesh(context, &self.line_vertices())?;
+        self.retired.push_back(std::mem::replace(&mut self.line, line));
+        self.line_size = Some(self.transform.scale);
+        while self.retired.len() > MESHES_KEPT {
+            if let Some(old) = self.retired.pop_front() {
+                old.destroy(&context.device);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GUIComponent for CurveEditor {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        let pipeline = renderer.get_pipeline(PipelineId::BasicGeometry)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::BasicGeometry)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for BasicGeometry pipeline"))?;
+        ctx.bind_pipeline(pipeline);
+
+        let projection = renderer.projection;
+        let draw = |mesh: &Mesh<ColorVertex2D>, position: Vec2, size: Vec2, color: [f32; 3]| -> Result<()> {
+            let transform = Mat4::from_translation(position.extend(0.0)) * Mat4::from_scale(Vec3::new(size.x, size.y, 1.0));
+            let push = PushConstants2D::new(projection, transform).with_modulation(color);
+            ctx.push(pipeline_layout, &push);
+            mesh.draw(ctx)
+        };
+
+        let center = self.transform.position;
+        draw(&self.white, center, self.transform.scale, [0.02, 0.02, 0.02])?;
+        // Zero line when it is in view
+        let (min, max) = self.value_range;
+        if min < 0.0 && max > 0.0 {
+            let zero = center + self.to_local(self.time_range.0, 0.0);
+            draw(&self.white, Vec2::new(center.x, zero.y), Vec2::new(self.transform.scale.x, 1.0), [0.1, 0.1, 0.1])?;
+        }
+        draw(&self.line, center, Vec2::ONE, [0.9, 0.6, 0.2])?;
+
+        for (index, key) in self.curve.keys().iter().enumerate() {
+            let position = center + self.to_local(key.time, key.value);
+            let color = if self.selected == Some(index) { [1.0, 1.0, 1.0] } else { [0.5, 0.5, 0.5] };
+            draw(&self.white, position, Vec2::splat(KEY_SIZE + 2.0), [0.0, 0.0, 0.0])?;
+            draw(&self.white, position, Vec2::splat(KEY_SIZE), color)?;
+        }
+        Ok(())
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        if !self.visible {
+            return;
+        }
+        let point = Vec2::new(x, y);
+        if let Some(index) = self.key_at(point) {
+            self.selected = Some(index);
+            self.dragging = true;
+            self.damaged = true;
+        } else if self.transform.contains_point(point) {
+            let (time, value) = self.point_value(point);
+            self.selected = Some(self.curve.add_key(time, value, Interpolation::Linear));
+            self.dragging = true;
+            self.damaged = true;
+            self.curve_edited();
+        }
+    }
+
+    fn handle_mouse_up(&mut self, _x: f32, _y: f32) {
+        self.dragging = false;
+    }
+
+    fn handle_mouse_move(&mut self, x: f32, y: f32) {
+        let Some(index) = self.selected.filter(|_| self.dragging) else {
+            return;
+        };
+        let (time, value) = self.point_value(Vec2::new(x, y));
+        self.selected = Some(self.curve.set_key(index, time, value));
+        self.damaged = true;
+        self.curve_edited();
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.transform.rect())
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.line.destroy(device);
+        for mesh in &self.retired {
+            mesh.destroy(device);
+        }
+        self.white.destroy(device);
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::Result;
+use ash::vk;
+use std::sync::Arc;
+use crate::gui::{AccessAction, AccessTree, Color, GUIComponent, PanelComponent, Rect, TextComponent, Transform};
+use accesskit::{Action, ActionData, NodeBuilder, Role};
+use crate::renderer::{FontAtlas, RenderContext, Renderer, VulkanContext};
+use glam::Vec2;
+
+/// Numeric field edited by dragging horizontally
+pub struct DragFloat {
+    background: PanelComponent,
+    active: PanelComponent,
+    text: TextComponent,
+    value: f32,
+    /// Value change per pixel dragged
+    speed: f32,
+    range: Option<(f32, f32)>,
+    precision: usize,
+    /// Name read by screen readers
+    label: String,
+    /// Mouse x and value when the drag started
+    drag_start: Option<(f32, f32)>,
+    changed: bool,
+    /// Drag state or value changed by input since the last `take_damage`
+    damaged: bool,
+    transform: Transform,
+}
+
+impl DragFloat {
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        value: f32,
+    ) -> Result<Self> {
+        Ok(DragFloat {
+            background: PanelComponent::new(context, Color::srgb(0.18, 0.18, 0.22))?,
+            active: PanelComponent::new(context, Color::srgb(0.25, 0.3, 0.45))?,
+            text: TextComponent::new("-", font_atlas, font_size, descriptor_set_layout, context)?,
+            value,
+            speed: 0.1,
+            range: None,
+            precision: 3,
+            label: String::new(),
+            drag_start: None,
+            changed: false,
+            damaged: false,
+            transform: Transform::new(),
+        })
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Set the value programmatically (does not report a change)
+    pub fn set_value(&mut self, value: f32) {
+        self.value = self.clamp(value);
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// Limit the value to `min..=max`
+    pub fn set_range(&mut self, range: Option<(f32, f32)>) {
+        self.range = range;
+        self.value = self.clamp(self.value);
+    }
+
+    /// Number of decimals shown
+    pub fn set_precision(&mut self, precision: usize) {
+        self.precision = precision;
+    }
+
+    /// Name read by screen readers, usually the text of the row the field is in
+    pub fn set_label(&mut self, label: &str) {
+        self.label = label.to_string();
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag_start.is_some()
+    }
+
+    /// Returns the new value if the user changed it since the last call
+    pub fn take_changed(&mut self) -> Option<f32> {
+        if self.changed {
+            self.changed = false;
+            Some(self.value)
+        } else {
+            None
+        }
+    }
+
+    fn clamp(&self, value: f32) -> f32 {
+        match self.range {
+            Some((min, max)) => value.clamp(min, max),
+            None => value,
+        }
+    }
+
+    /// Rebuild the value text and layout
+    /// Call once per frame before rendering
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        self.text.update_text(&format!("{:.*}", self.precision, self.value), context)?;
+        self.update_layout();
+        Ok(())
+    }
+
+    /// Position the background and text from the transform
+    pub fn update_layout(&mut self) {
+        *self.background.transform_mut() = self.transform;
+        *self.active.transform_mut() = self.transform;
+        let left = self.transform.position.x - self.transform.scale.x / 2.0;
+        self.text.set_position(Vec2::new(left + 4.0 + self.text.get_width() / 2.0, self.transform.position.y));
+    }
+}
+
+impl GUIComponent for DragFloat {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if self.is_dragging() {
+            self.active.render(ctx, renderer)?;
+        } else {
+            self.background.render(ctx, renderer)?;
+        }
+        self.text.render(ctx, renderer)
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        if self.transform.contains_point(Vec2::new(x, y)) {
+            self.drag_start = Some((x, self.value));
+            self.damaged = true;
+        }
+    }
+
+    fn handle_mouse_up(&mut self, _x: f32, _y: f32) {
+        self.damaged |= self.drag_start.take().is_some();
+    }
+
+    fn handle_mouse_move(&mut self, x: f32, _y: f32) {
+        let Some((start_x, start_value)) = self.drag_start else {
+            return;
+        };
+        let value = self.clamp(start_value + (x - start_x) * self.speed);
+        if value != self.value {
+            self.value = value;
+            self.changed = true;
+            self.damaged = true;
+        }
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.transform.rect())
+    }
+
+    fn accessibility(&self, tree: &mut AccessTree) {
+        let mut node = NodeBuilder::new(Role::SpinButton);
+        node.set_name(self.label.as_str());
+        node.set_numeric_value(self.value as f64);
+        // Steps of the last shown decimal
+        node.set_numeric_value_step(10f64.powi(-(self.precision as i32)));
+        if let Some((min, max)) = self.range {
+            node.set_min_numeric_value(min as f64);
+            node.set_max_numeric_value(max as f64);
+        }
+        node.add_action(Action::SetValue);
+        node.add_action(Action::Increment);
+        node.add_action(Action::Decrement);
+        tree.add(node, self.transform.rect());
+    }
+
+    fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+        let step = 10f32.powi(-(self.precision as i32));
+        let value = match (action.action, &action.data) {
+            (Action::SetValue, Some(ActionData::NumericValue(value))) => *value as f32,
+            (Action::SetValue, Some(ActionData::Value(text))) => match text.trim().parse() {
+                Ok(value) => value,
+                Err(_) => return false,
+            },
+            (Action::Increment, _) => self.value + step,
+            (Action::Decrement, _) => self.value - step,
+            _ => return false,
+        };
+        self.value = self.clamp(value);
+        self.changed = true;
+        self.damaged = true;
+        true
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.background.destroy(device);
+        self.active.destroy(device);
+        self.text.destroy(device);
+    }
+}
@@ -0,0 +1,130 @@
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::math::{Rect, Transform3D};
+
+/// Projection used by the editor camera
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Top-down orthographic view of the XY plane
+    Ortho2D,
+    /// Perspective view orbiting around the focus point
+    Perspective3D,
+}
+
+/// Scene camera used by editor viewports, independent from any game Camera component
+///
+/// The world is the XY plane with +Z pointing towards the viewer. `zoom` is in
+/// pixels per world unit, the 3D distance is derived from it so switching modes
+/// keeps the focus area roughly the same size.
+#[derive(Clone, Copy, Debug)]
+pub struct EditorCamera {
+    pub mode: CameraMode,
+    pub focus: Vec2,
+    pub zoom: f32,
+    /// Rotation around Z in radians
+    pub yaw: f32,
+    /// Elevation above the plane in radians (3D only)
+    pub pitch: f32,
+    /// Vertical field of view in radians (3D only)
+    pub fov: f32,
+}
+
+const MIN_ZOOM: f32 = 0.01;
+const MAX_ZOOM: f32 = 1000.0;
+const MIN_PITCH: f32 = 0.05;
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+impl EditorCamera {
+    pub fn new() -> Self {
+        EditorCamera {
+            mode: CameraMode::Ortho2D,
+            focus: Vec2::ZERO,
+            zoom: 32.0,
+            yaw: 0.0,
+            pitch: 1.0,
+            fov: 60f32.to_radians(),
+        }
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::Ortho2D => CameraMode::Perspective3D,
+            CameraMode::Perspective3D => CameraMode::Ortho2D,
+        };
+    }
+
+    /// Move the focus by a drag of `delta` pixels, so the scene follows the mouse
+    pub fn pan(&mut self, delta: Vec2) {
+        let world_delta = Vec2::from_angle(self.yaw).rotate(delta) / self.zoom;
+        self.focus -= world_delta;
+    }
+
+    /// Zoom by `steps` (mouse wheel notches), positive zooms in
+    pub fn zoom_by(&mut self, steps: f32) {
+        self.scale_zoom(1.1f32.powf(steps));
+    }
+
+    /// Multiply the zoom by `factor`, e.g. the scale of a pinch
+    pub fn scale_zoom(&mut self, factor: f32) {
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// Rotate around the focus by a drag of `delta` pixels
+    /// In 2D only the horizontal component is used (rotation around Z)
+    pub fn orbit(&mut self, delta: Vec2) {
+        self.yaw -= delta.x * 0.01;
+        if self.mode == CameraMode::Perspective3D {
+            self.pitch = (self.pitch - delta.y * 0.01).clamp(MIN_PITCH, MAX_PITCH);
+        }
+    }
+
+    /// Distance from the focus in 3D mode
+    pub fn distance(&self, viewport_height: f32) -> f32 {
+        viewport_height / (2.0 * self.zoom * (self.fov / 2.0).tan())
+    }
+
+    /// World area seen by a viewport of `size` pixels in 2D mode, widened to an axis-aligned
+    /// box when the view is rotated; `None` in 3D mode
+    pub fn visible_rect(&self, size: Vec2) -> Option<Rect> {
+        if self.mode != CameraMode::Ortho2D {
+            return None;
+        }
+        let half = size.max(Vec2::ONE) / (2.0 * self.zoom);
+        let (sin, cos) = self.yaw.sin_cos();
+        let extent = Vec2::new(half.x * cos.abs() + half.y * sin.abs(), half.x * sin.abs() + half.y * cos.abs());
+        Some(Rect::from_center_size(self.focus, extent * 2.0))
+    }
+
+    /// Combined projection * view matrix for a viewport of `size` pixels
+    pub fn view_projection(&self, size: Vec2) -> Mat4 {
+        let size = size.max(Vec2::ONE);
+        match self.mode {
+            CameraMode::Ortho2D => {
+                let half = size / (2.0 * self.zoom);
+                let projection = Mat4::orthographic_rh(-half.x, half.x, -half.y, half.y, -1000.0, 1000.0);
+                let view = Mat4::from_rotation_z(-self.yaw)
+                    * Mat4::from_translation(Vec3::new(-self.focus.x, -self.focus.y, 0.0));
+                projection * view
+            }
+            CameraMode::Perspective3D => {
+                let distance = self.distance(size.y);
+                let target = self.focus.extend(0.0);
+                // Looking from the -Y side at yaw 0, so +Y is "up" on screen like in 2D
+                let direction = Vec3::new(
+                    self.pitch.cos() * self.yaw.sin(),
+                    -self.pitch.cos() * self.yaw.cos(),
+                    self.pitch.sin(),
+                );
+                let eye = target + direction * distance;
+                let projection = Mat4::perspective_rh(self.fov, size.x / size.y, 0.1, distance * 10.0 + 100.0);
+                projection * Transform3D::looking_at(eye, target, Vec3::Z).view_matrix()
+            }
+        }
+    }
+}
+
+impl Default for EditorCamera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
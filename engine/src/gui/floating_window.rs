@@ -0,0 +1,974 @@
+use anyhow::Result;
+use ash::vk;
+use std::f32::consts::FRAC_PI_4;
+use std::sync::Arc;
+use accesskit::{Action, DefaultActionVerb, NodeBuilder, Role};
+use winit::keyboard::Key;
+use crate::gui::{AccessAction, AccessTree, Color, Gesture, GUIComponent, Grid, MenuItem, OffscreenPass, PanelComponent, Rect, TextComponent, Transform, Vec2, TOUCH_OUTSIDE};
+use crate::renderer::{FontAtlas, RenderContext, Renderer, VulkanContext};
+
+/// Removed windows are destroyed after this many `update_geometry` calls, longer than any frame stays in flight
+const FRAMES_KEPT: usize = 3;
+/// Pixels along the window's edges that resize it instead of reaching the contents
+const RESIZE_BORDER: f32 = 6.0;
+/// Pixels from a corner where grabbing an edge resizes along both sides of the corner
+const RESIZE_CORNER: f32 = 14.0;
+/// Size of the minimize and close buttons
+const BUTTON_SIZE: f32 = 16.0;
+/// Space around the title and between the title bar buttons
+const TITLE_PADDING: f32 = 5.0;
+/// Space between the window frame and its contents
+const CONTENT_PADDING: f32 = 4.0;
+/// Pixels of the title bar kept inside the UI while moving a window, so it can always be grabbed again
+const KEEP_VISIBLE: f32 = 40.0;
+
+fn border_color(active: bool) -> Color {
+    if active { Color::srgb(0.38, 0.48, 0.72) } else { Color::srgb(0.3, 0.3, 0.36) }
+}
+
+fn title_color(active: bool) -> Color {
+    if active { Color::srgb(0.23, 0.25, 0.34) } else { Color::srgb(0.19, 0.19, 0.24) }
+}
+
+fn text_color(active: bool) -> Color {
+    if active { Color::srgb(0.92, 0.92, 0.95) } else { Color::srgb(0.6, 0.6, 0.66) }
+}
+
+/// Color of a title bar button, lit up while hovered
+fn button_color(button: TitleButton, hovered: bool, active: bool) -> Color {
+    match (button, hovered) {
+        (TitleButton::Minimize, true) => Color::srgb(0.34, 0.35, 0.44),
+        (TitleButton::Close, true) => Color::srgb(0.72, 0.24, 0.24),
+        (_, false) => title_color(active),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TitleButton {
+    Minimize,
+    Close,
+}
+
+/// Sides of the window a resize drag moves, two of them at corners
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Edges {
+    left: bool,
+    right: bool,
+    bottom: bool,
+    top: bool,
+}
+
+impl Edges {
+    fn any(&self) -> bool {
+        self.left || self.right || self.bottom || self.top
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DragKind {
+    Move,
+    Resize(Edges),
+}
+
+/// A title bar or edge drag, moving or resizing from where the window was when it started
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    kind: DragKind,
+    start: Vec2,
+    start_rect: Rect,
+}
+
+/// Tool window inside the application window, with a title bar to drag it by, edges and
+/// corners to resize it from and buttons to minimize and close it
+///
+/// The contents are laid out with the window's own `Grid` below the title bar. A minimized
+/// window shrinks to its title bar and keeps its size for when it is restored. Closing only
+/// hides the window, `take_closed` reports it and `set_open` brings it back.
+///
+/// Windows are usually kept in `UISystem::windows`, which draws them over the grid, raises
+/// the one clicked to the front and keeps their title bars inside the UI.
+pub struct FloatingWindow {
+    border: PanelComponent,
+    background: PanelComponent,
+    title_bar: PanelComponent,
+    title: TextComponent,
+    minimize_button: PanelComponent,
+    minimize_glyph: PanelComponent,
+    close_button: PanelComponent,
+    /// The two strokes of the cross
+    close_glyph: [PanelComponent; 2],
+    grid: Grid,
+    /// Rect shown, only the title bar while minimized
+    transform: Transform,
+    /// Rect of the restored window
+    rect: Rect,
+    title_height: f32,
+    min_size: Vec2,
+    resizable: bool,
+    open: bool,
+    minimized: bool,
+    /// In front of the other windows, drawn highlighted
+    active: bool,
+    /// UI area the title bar is kept in
+    area: Option<Rect>,
+    hovered: Option<TitleButton>,
+    /// Button under the last press, it acts when the release is over it too
+    pressed: Option<TitleButton>,
+    drag: Option<Drag>,
+    /// Closed with the close button since the last `take_closed`
+    closed: bool,
+    damage: Option<Rect>,
+}
+
+impl FloatingWindow {
+    /// Window covering `rect` (UI space) with `title` in its title bar
+    ///
+    /// Requires the descriptor_set_layout of `PipelineId::Text` for the title.
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        title: &str,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        rect: Rect,
+    ) -> Result<Self> {
+        let mut title = TextComponent::new(title, font_atlas, font_size, descriptor_set_layout, context)?;
+        title.set_color(text_color(true));
+        let glyph = Color::srgb(0.85, 0.85, 0.9);
+        let mut window = FloatingWindow {
+            border: PanelComponent::new(context, border_color(true))?,
+            background: PanelComponent::new(context, Color::srgb(0.13, 0.13, 0.17))?,
+            title_bar: PanelComponent::new(context, title_color(true))?,
+            title,
+            minimize_button: PanelComponent::new(context, title_color(true))?,
+            minimize_glyph: PanelComponent::new(context, glyph)?,
+            close_button: PanelComponent::new(context, title_color(true))?,
+            close_glyph: [PanelComponent::new(context, glyph)?, PanelComponent::new(context, glyph)?],
+            grid: Grid::new(),
+            transform: Transform::new(),
+            rect,
+            title_height: font_size.max(BUTTON_SIZE) + TITLE_PADDING * 2.0,
+            min_size: Vec2::new(120.0, 60.0),
+            resizable: true,
+            open: true,
+            minimized: false,
+            active: true,
+            area: None,
+            hovered: None,
+            pressed: None,
+            drag: None,
+            closed: false,
+            damage: None,
+        };
+        window.set_rect(rect);
+        Ok(window)
+    }
+
+    pub fn grid_mut(&mut self) -> &mut Grid {
+        &mut self.grid
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn title(&self) -> &str {
+        self.title.text()
+    }
+
+    pub fn set_title(&mut self, title: &str, context: &Arc<VulkanContext>) -> Result<()> {
+        self.title.update_text(title, context)?;
+        // A longer title may not fit anymore
+        self.set_rect(self.rect);
+        Ok(())
+    }
+
+    /// Rect of the restored window in UI space, title bar included
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Move and resize the window, kept at least `min_size` and with its title bar inside its area
+    pub fn set_rect(&mut self, rect: Rect) {
+        // Grown downwards, the title bar stays where it was asked to be
+        let size = rect.size().max(self.min_size());
+        let rect = self.confine(Rect::new(rect.x, rect.max().y - size.y, size.x, size.y));
+        // Whole pixels keep the title sharp
+        self.rect = Rect::new(rect.x.round(), rect.y.round(), rect.width.round(), rect.height.round());
+        self.layout();
+    }
+
+    /// Area the contents are laid out in, below the title bar
+    pub fn content_rect(&self) -> Rect {
+        let rect = self.rect;
+        Rect::new(rect.x, rect.y, rect.width, (rect.height - self.title_height).max(0.0)).inflate(-CONTENT_PADDING)
+    }
+
+    /// Smallest size resizing leaves the window at, never narrower than its title and buttons
+    pub fn min_size(&self) -> Vec2 {
+        let title_width = self.title.get_width() + BUTTON_SIZE * 2.0 + TITLE_PADDING * 4.0;
+        Vec2::new(self.min_size.x.max(title_width), self.min_size.y.max(self.title_height * 2.0))
+    }
+
+    pub fn set_min_size(&mut self, size: Vec2) {
+        self.min_size = size;
+        self.set_rect(self.rect);
+    }
+
+    pub fn is_resizable(&self) -> bool {
+        self.resizable
+    }
+
+    /// Whether the edges resize the window, true by default
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.resizable = resizable;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Show or hide the window, hidden windows are neither drawn nor get input
+    pub fn set_open(&mut self, open: bool) {
+        if self.open != open {
+            self.open = open;
+            self.drag = None;
+            self.invalidate(self.transform.rect());
+        }
+    }
+
+    /// Whether the close button was clicked since the last call, the window is hidden by then
+    pub fn take_closed(&mut self) -> bool {
+        std::mem::take(&mut self.closed)
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// Shrink the window to its title bar or restore it
+    pub fn set_minimized(&mut self, minimized: bool) {
+        if self.minimized != minimized {
+            self.minimized = minimized;
+            self.drag = None;
+            self.layout();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Highlight the window as the one in front, set by `FloatingWindows`
+    pub fn set_active(&mut self, active: bool) {
+        if self.active != active {
+            self.active = active;
+            self.update_colors();
+            self.invalidate(self.transform.rect());
+        }
+    }
+
+    /// Keep the title bar inside `area` (UI space) when moving, resizing or on `set_rect`
+    pub fn set_area(&mut self, area: Option<Rect>) {
+        self.area = area;
+        self.set_rect(self.rect);
+    }
+
+    /// Whether a title bar or edge drag is in progress
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// `rect` moved so its title bar is inside the area, at least `KEEP_VISIBLE` of it sideways
+    fn confine(&self, rect: Rect) -> Rect {
+        let Some(area) = self.area else {
+            return rect;
+        };
+        let x = rect.x.min(area.max().x - KEEP_VISIBLE).max(area.x - rect.width + KEEP_VISIBLE);
+        let top = rect.max().y.min(area.max().y).max(area.y + self.title_height);
+        Rect::new(x, top - rect.height, rect.width, rect.height)
+    }
+
+    /// Rect of the title bar at the top of the window
+    fn title_bar_rect(&self) -> Rect {
+        let rect = self.rect;
+        Rect::new(rect.x, rect.max().y - self.title_height, rect.width, self.title_height)
+    }
+
+    fn button_rect(&self, button: TitleButton) -> Rect {
+        let bar = self.title_bar_rect();
+        let close = Vec2::new(bar.max().x - TITLE_PADDING - BUTTON_SIZE / 2.0, bar.center().y);
+        let center = match button {
+            TitleButton::Close => close,
+            TitleButton::Minimize => close - Vec2::new(BUTTON_SIZE + TITLE_PADDING, 0.0),
+        };
+        Rect::from_center_size(center, Vec2::splat(BUTTON_SIZE))
+    }
+
+    fn button_at(&self, point: Vec2) -> Option<TitleButton> {
+        [TitleButton::Minimize, TitleButton::Close]
+            .into_iter()
+            .find(|&button| self.button_rect(button).contains_point(point))
+    }
+
+    /// Edges a press at `point` resizes, `None` away from the edges or while minimized
+    fn edges_at(&self, point: Vec2) -> Option<Edges> {
+        let rect = self.transform.rect();
+        if !self.resizable || self.minimized || !rect.contains_point(point) {
+            return None;
+        }
+        let (min, max) = (rect.min(), rect.max());
+        let mut edges = Edges {
+            left: point.x - min.x <= RESIZE_BORDER,
+            right: max.x - point.x <= RESIZE_BORDER,
+            bottom: point.y - min.y <= RESIZE_BORDER,
+            top: max.y - point.y <= RESIZE_BORDER,
+        };
+        // Corners are easier to grab, the edge next to them counts from further away
+        if edges.left || edges.right {
+            edges.bottom |= point.y - min.y <= RESIZE_CORNER;
+            edges.top |= max.y - point.y <= RESIZE_CORNER;
+        }
+        if edges.bottom || edges.top {
+            edges.left |= point.x - min.x <= RESIZE_CORNER;
+            edges.right |= max.x - point.x <= RESIZE_CORNER;
+        }
+        edges.any().then_some(edges)
+    }
+
+    /// Rect a drag leaves the window at with the pointer at `point`
+    fn dragged_rect(&self, drag: &Drag, point: Vec2) -> Rect {
+        let delta = point - drag.start;
+        let start = drag.start_rect;
+        let edges = match drag.kind {
+            DragKind::Move => return Rect::new(start.x + delta.x, start.y + delta.y, start.width, start.height),
+            DragKind::Resize(edges) => edges,
+        };
+        let min_size = self.min_size();
+        let (mut min, mut max) = (start.min(), start.max());
+        if edges.left {
+            min.x = (min.x + delta.x).min(max.x - min_size.x);
+        }
+        if edges.right {
+            max.x = (max.x + delta.x).max(min.x + min_size.x);
+        }
+        if edges.bottom {
+            min.y = (min.y + delta.y).min(max.y - min_size.y);
+        }
+        if edges.top {
+            max.y = (max.y + delta.y).max(min.y + min_size.y);
+            // The title bar can't be dragged out of the area by its edge either
+            if let Some(area) = self.area {
+                max.y = max.y.min(area.max().y).max(min.y + min_size.y);
+            }
+        }
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    /// Place the frame, title bar and buttons on the window's rect and lay the contents out
+    fn layout(&mut self) {
+        let previous = self.transform.rect();
+        let shown = if self.minimized { self.title_bar_rect() } else { self.rect };
+        self.transform = Transform::from_rect(shown);
+        self.border.set_layout(shown);
+        self.background.set_layout(shown.inflate(-1.0));
+        let bar = self.title_bar_rect();
+        self.title_bar.set_layout(bar.inflate(-1.0));
+        let left = bar.x + TITLE_PADDING * 2.0;
+        self.title.set_position(Vec2::new(left + self.title.get_width() / 2.0, bar.center().y).round());
+
+        let minimize = self.button_rect(TitleButton::Minimize);
+        self.minimize_button.set_layout(minimize);
+        let bar_size = Vec2::new(BUTTON_SIZE * 0.6, 2.0);
+        self.minimize_glyph.set_layout(Rect::from_center_size(minimize.center() - Vec2::new(0.0, BUTTON_SIZE * 0.2), bar_size));
+        let close = self.button_rect(TitleButton::Close);
+        self.close_button.set_layout(close);
+        for (stroke, angle) in self.close_glyph.iter_mut().zip([FRAC_PI_4, -FRAC_PI_4]) {
+            stroke.set_layout(Rect::from_center_size(close.center(), Vec2::new(BUTTON_SIZE * 0.7, 2.0)));
+            stroke.transform_mut().rotation = angle;
+        }
+
+        if !self.minimized {
+            self.grid.set_bounds(self.content_rect());
+        }
+        self.invalidate(previous);
+        self.invalidate(shown);
+    }
+
+    fn update_colors(&mut self) {
+        self.border.set_color(border_color(self.active));
+        self.title_bar.set_color(title_color(self.active));
+        self.title.set_color(text_color(self.active));
+        let hovered = |button| self.hovered == Some(button);
+        self.minimize_button.set_color(button_color(TitleButton::Minimize, hovered(TitleButton::Minimize), self.active));
+        self.close_button.set_color(button_color(TitleButton::Close, hovered(TitleButton::Close), self.active));
+    }
+
+    fn click(&mut self, button: TitleButton) {
+        match button {
+            TitleButton::Minimize => self.set_minimized(!self.minimized),
+            TitleButton::Close => {
+                self.set_open(false);
+                self.closed = true;
+            }
+        }
+    }
+
+    fn invalidate(&mut self, rect: Rect) {
+        self.damage = Some(self.damage.map_or(rect, |damage| damage.union(&rect)));
+    }
+}
+
+impl GUIComponent for FloatingWindow {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if !self.open {
+            return Ok(());
+        }
+        self.border.render(ctx, renderer)?;
+        self.background.render(ctx, renderer)?;
+        self.title_bar.render(ctx, renderer)?;
+        self.title.render(ctx, renderer)?;
+        self.minimize_button.render(ctx, renderer)?;
+        self.minimize_glyph.render(ctx, renderer)?;
+        self.close_button.render(ctx, renderer)?;
+        for stroke in &self.close_glyph {
+            stroke.render(ctx, renderer)?;
+        }
+        if self.minimized {
+            return Ok(());
+        }
+        self.grid.render(ctx, renderer)
+    }
+
+    fn render_overlay(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if !self.open || self.minimized {
+            return Ok(());
+        }
+        self.grid.render_overlay(ctx, renderer)
+    }
+
+    fn overlay_rect(&self) -> Option<Rect> {
+        self.grid.overlay_rect().filter(|_| self.open && !self.minimized)
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn set_layout(&mut self, rect: Rect) {
+        self.set_rect(rect);
+    }
+
+    fn measure(&self, constraints: Vec2) -> Vec2 {
+        self.rect.size().min(constraints)
+    }
+
+    /// Lays out rows added to the contents since the last frame
+    fn update(&mut self, dt: f32) {
+        if self.grid.update_layout() {
+            self.invalidate(self.content_rect());
+        }
+        self.grid.update(dt);
+    }
+
+    fn next_update(&self) -> Option<f32> {
+        self.grid.next_update().filter(|_| self.open && !self.minimized)
+    }
+
+    /// Buttons act on release, the title bar moves the window and the edges resize it,
+    /// everything else goes to the contents
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        let point = Vec2::new(x, y);
+        if !self.open {
+            return;
+        }
+        // Open popups of the contents reach outside the window
+        if self.grid.overlay_rect().is_some() && !self.minimized {
+            self.grid.handle_mouse_down(x, y);
+            return;
+        }
+        if let Some(button) = self.button_at(point) {
+            self.pressed = Some(button);
+            return;
+        }
+        let kind = match self.edges_at(point) {
+            Some(edges) => Some(DragKind::Resize(edges)),
+            None if self.title_bar_rect().contains_point(point) => Some(DragKind::Move),
+            None => None,
+        };
+        match kind {
+            Some(kind) => self.drag = Some(Drag { kind, start: point, start_rect: self.rect }),
+            None if !self.minimized => self.grid.handle_mouse_down(x, y),
+            None => {}
+        }
+    }
+
+    fn handle_mouse_up(&mut self, x: f32, y: f32) {
+        let point = Vec2::new(x, y);
+        self.drag = None;
+        if let Some(button) = self.pressed.take() {
+            if self.button_at(point) == Some(button) {
+                self.click(button);
+            }
+            return;
+        }
+        if !self.minimized {
+            self.grid.handle_mouse_up(x, y);
+        }
+    }
+
+    fn handle_mouse_move(&mut self, x: f32, y: f32) {
+        let point = Vec2::new(x, y);
+        if let Some(drag) = self.drag {
+            let rect = self.dragged_rect(&drag, point);
+            if rect != self.rect {
+                self.set_rect(rect);
+            }
+            return;
+        }
+        let hovered = self.button_at(point).filter(|_| self.open);
+        if hovered != self.hovered {
+            self.hovered = hovered;
+            self.update_colors();
+            self.invalidate(self.title_bar_rect());
+        }
+        if !self.minimized {
+            self.grid.handle_mouse_move(x, y);
+        }
+    }
+
+    fn handle_key(&mut self, key: &Key) -> bool {
+        self.open && !self.minimized && self.grid.handle_key(key)
+    }
+
+    fn handle_gesture(&mut self, gesture: &Gesture) -> bool {
+        self.open && !self.minimized && self.grid.handle_gesture(gesture)
+    }
+
+    fn context_menu(&self, point: Vec2) -> Option<Vec<MenuItem>> {
+        if !self.open || self.minimized {
+            return None;
+        }
+        self.grid.context_menu_at(point)
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        if let Some(rect) = self.grid.take_damage() {
+            self.invalidate(rect);
+        }
+        self.damage.take()
+    }
+
+    fn update_geometry(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        self.grid.update_geometry(context)
+    }
+
+    fn render_offscreen(&mut self, pass: &OffscreenPass, renderer: &mut Renderer) -> Result<()> {
+        if !self.open || self.minimized {
+            return Ok(());
+        }
+        self.grid.render_offscreen(pass, renderer)
+    }
+
+    fn children(&self) -> Vec<&dyn GUIComponent> {
+        self.grid.components().collect()
+    }
+
+    fn nested_grid(&self) -> Option<&Grid> {
+        Some(&self.grid)
+    }
+
+    fn accessibility(&self, tree: &mut AccessTree) {
+        if !self.open {
+            return;
+        }
+        let mut node = NodeBuilder::new(Role::Window);
+        node.set_name(self.title.text());
+        tree.push(node, self.transform.rect());
+        for (button, name) in [(TitleButton::Minimize, if self.minimized { "Restore" } else { "Minimize" }), (TitleButton::Close, "Close")] {
+            let mut node = NodeBuilder::new(Role::Button);
+            node.set_name(name);
+            node.set_default_action_verb(DefaultActionVerb::Click);
+            tree.add(node, self.button_rect(button));
+        }
+        if !self.minimized {
+            self.grid.accessibility(tree);
+        }
+        tree.pop();
+    }
+
+    fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+        if !self.open {
+            return false;
+        }
+        if let Some(button) = self.button_at(action.point) {
+            if action.action == Action::Default {
+                self.click(button);
+            }
+            return action.action == Action::Default;
+        }
+        !self.minimized && self.grid.accessibility_action(action)
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.border.destroy(device);
+        self.background.destroy(device);
+        self.title_bar.destroy(device);
+        self.title.destroy(device);
+        self.minimize_button.destroy(device);
+        self.minimize_glyph.destroy(device);
+        self.close_button.destroy(device);
+        for stroke in &self.close_glyph {
+            stroke.destroy(device);
+        }
+        self.grid.destroy(device);
+    }
+}
+
+/// Reference to a window added with `FloatingWindows::add`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowHandle {
+    id: u64,
+}
+
+/// Floating tool windows over the rest of the UI, back to front
+///
+/// A press on a window raises it to the front and makes it the active window, which also
+/// gets the keys. The window under a press gets the moves and the release until the button
+/// is released, wherever the pointer goes. Mouse handlers return whether a window took the
+/// event, everything beneath should then not see the pointer.
+///
+/// `UISystem` owns one as `windows`, drawn over the grid and world-space widgets and under
+/// popups and the context menu.
+pub struct FloatingWindows {
+    /// Back to front
+    windows: Vec<(u64, FloatingWindow)>,
+    /// Removed windows waiting to be destroyed, with the frames they waited
+    retired: Vec<(FloatingWindow, usize)>,
+    next_id: u64,
+    /// UI area the title bars are kept in
+    area: Option<Rect>,
+    /// Window under the last press, it gets the moves and the release
+    captured: Option<u64>,
+    /// Window under the pointer on the last move
+    hovered: Option<u64>,
+    damage: Option<Rect>,
+}
+
+impl FloatingWindows {
+    pub fn new() -> Self {
+        FloatingWindows {
+            windows: Vec::new(),
+            retired: Vec::new(),
+            next_id: 0,
+            area: None,
+            captured: None,
+            hovered: None,
+            damage: None,
+        }
+    }
+
+    /// Show `window` in front of the others
+    pub fn add(&mut self, mut window: FloatingWindow) -> WindowHandle {
+        let id = self.next_id;
+        self.next_id += 1;
+        window.set_area(self.area);
+        self.invalidate(window.transform().rect());
+        self.windows.push((id, window));
+        self.update_active();
+        WindowHandle { id }
+    }
+
+    /// Remove a window, returns false if it was already removed
+    pub fn remove(&mut self, handle: WindowHandle) -> bool {
+        let Some(index) = self.index(handle.id) else {
+            return false;
+        };
+        let (_, window) = self.windows.remove(index);
+        self.invalidate(window.transform().rect());
+        self.retired.push((window, 0));
+        self.update_active();
+        true
+    }
+
+    pub fn contains(&self, handle: WindowHandle) -> bool {
+        self.index(handle.id).is_some()
+    }
+
+    pub fn get(&self, handle: WindowHandle) -> Option<&FloatingWindow> {
+        self.windows.iter().find(|(id, _)| *id == handle.id).map(|(_, window)| window)
+    }
+
+    pub fn get_mut(&mut self, handle: WindowHandle) -> Option<&mut FloatingWindow> {
+        self.windows.iter_mut().find(|(id, _)| *id == handle.id).map(|(_, window)| window)
+    }
+
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Bring a window to the front, returns false if it was removed
+    pub fn raise(&mut self, handle: WindowHandle) -> bool {
+        match self.index(handle.id) {
+            Some(index) => {
+                self.raise_index(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Open a window (after it was closed) and bring it to the front
+    pub fn show(&mut self, handle: WindowHandle) -> bool {
+        let Some(window) = self.get_mut(handle) else {
+            return false;
+        };
+        window.set_open(true);
+        self.raise(handle)
+    }
+
+    /// Open a closed window in front or close an open one
+    pub fn toggle(&mut self, handle: WindowHandle) -> bool {
+        match self.get(handle).map(FloatingWindow::is_open) {
+            Some(true) => {
+                if let Some(window) = self.get_mut(handle) {
+                    window.set_open(false);
+                }
+                self.update_active();
+                true
+            }
+            Some(false) => self.show(handle),
+            None => false,
+        }
+    }
+
+    /// The open window in front, it gets the keys
+    pub fn active(&self) -> Option<WindowHandle> {
+        self.windows.iter().rev().find(|(_, window)| window.is_open()).map(|&(id, _)| WindowHandle { id })
+    }
+
+    /// Keep every title bar inside `area` (UI space), e.g. the UI bounds after a resize
+    pub fn set_area(&mut self, area: Rect) {
+        self.area = Some(area);
+        for (_, window) in &mut self.windows {
+            window.set_area(Some(area));
+        }
+    }
+
+    /// Whether an open window covers `point` (UI space)
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        self.top_at(point).is_some()
+    }
+
+    /// Whether a window took the last press and still waits for its release
+    pub fn is_capturing(&self) -> bool {
+        self.captured.is_some()
+    }
+
+    fn index(&self, id: u64) -> Option<usize> {
+        self.windows.iter().position(|(window_id, _)| *window_id == id)
+    }
+
+    /// Frontmost open window covering `point`
+    fn top_at(&self, point: Vec2) -> Option<usize> {
+        self.windows
+            .iter()
+            .rposition(|(_, window)| window.is_open() && window.transform().contains_point(point))
+    }
+
+    /// Window with an open popup, it gets all mouse input while the popup is open
+    fn modal(&self) -> Option<usize> {
+        self.windows.iter().position(|(_, window)| window.overlay_rect().is_some())
+    }
+
+    /// Move the window at `index` to the front, returns its new index
+    fn raise_index(&mut self, index: usize) -> usize {
+        let last = self.windows.len() - 1;
+        if index != last {
+            let entry = self.windows.remove(index);
+            self.invalidate(entry.1.transform().rect());
+            self.windows.push(entry);
+        }
+        self.update_active();
+        last
+    }
+
+    /// Highlight only the open window in front
+    fn update_active(&mut self) {
+        let active = self.active().map(|handle| handle.id);
+        for (id, window) in &mut self.windows {
+            window.set_active(Some(*id) == active);
+        }
+    }
+
+    fn invalidate(&mut self, rect: Rect) {
+        self.damage = Some(self.damage.map_or(rect, |damage| damage.union(&rect)));
+    }
+
+    /// Raise the window under a press and hand it the press, returns whether there was one
+    pub fn handle_mouse_down(&mut self, x: f32, y: f32) -> bool {
+        let Some(index) = self.modal().or_else(|| self.top_at(Vec2::new(x, y))) else {
+            return false;
+        };
+        let index = self.raise_index(index);
+        let (id, window) = &mut self.windows[index];
+        self.captured = Some(*id);
+        window.handle_mouse_down(x, y);
+        // Closing or minimizing may change which window is in front
+        self.update_active();
+        true
+    }
+
+    /// Release the press of the window that took it, returns whether a window had it
+    pub fn handle_mouse_up(&mut self, x: f32, y: f32) -> bool {
+        let Some(id) = self.captured.take() else {
+            return false;
+        };
+        if let Some(index) = self.index(id) {
+            self.windows[index].1.handle_mouse_up(x, y);
+        }
+        self.update_active();
+        true
+    }
+
+    /// Hover the frontmost window under the pointer, or move the window being dragged,
+    /// returns whether the pointer is taken by a window
+    pub fn handle_mouse_move(&mut self, x: f32, y: f32) -> bool {
+        let target = self.captured.or_else(|| {
+            let index = self.modal().or_else(|| self.top_at(Vec2::new(x, y)))?;
+            Some(self.windows[index].0)
+        });
+        if self.hovered != target {
+            // The window left sees the pointer go, so it drops its hover
+            if let Some(index) = self.hovered.and_then(|id| self.index(id)) {
+                self.windows[index].1.handle_mouse_move(TOUCH_OUTSIDE, TOUCH_OUTSIDE);
+            }
+            self.hovered = target;
+        }
+        let Some(index) = target.and_then(|id| self.index(id)) else {
+            return false;
+        };
+        self.windows[index].1.handle_mouse_move(x, y);
+        true
+    }
+
+    /// Send a key to the window with an open popup, or else to the active window
+    pub fn handle_key(&mut self, key: &Key) -> bool {
+        let index = self.modal().or_else(|| self.active().and_then(|handle| self.index(handle.id)));
+        index.is_some_and(|index| self.windows[index].1.handle_key(key))
+    }
+
+    /// Send a gesture to the window with an open popup, or else to the frontmost window under it
+    pub fn handle_gesture(&mut self, gesture: &Gesture) -> bool {
+        let index = self.modal().or_else(|| self.top_at(gesture.point()));
+        index.is_some_and(|index| self.windows[index].1.handle_gesture(gesture))
+    }
+
+    /// Context menu of the frontmost window under `point`
+    pub fn context_menu_at(&self, point: Vec2) -> Option<Vec<MenuItem>> {
+        self.windows[self.top_at(point)?].1.context_menu(point)
+    }
+
+    /// Offer an action to the frontmost window under its point
+    pub fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+        let index = self.modal().or_else(|| self.top_at(action.point));
+        index.is_some_and(|index| self.windows[index].1.accessibility_action(action))
+    }
+
+    /// Area of the open popups of all windows
+    pub fn overlay_rect(&self) -> Option<Rect> {
+        self.windows.iter().filter_map(|(_, window)| window.overlay_rect()).reduce(|a, b| a.union(&b))
+    }
+
+    /// Draw the open windows back to front
+    pub fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        for (_, window) in &self.windows {
+            window.render(ctx, renderer)?;
+        }
+        Ok(())
+    }
+
+    /// Draw the popups of all windows, over every window
+    pub fn render_overlay(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        for (_, window) in &self.windows {
+            window.render_overlay(ctx, renderer)?;
+        }
+        Ok(())
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for (_, window) in &mut self.windows {
+            window.update(dt);
+        }
+    }
+
+    pub fn next_update(&self) -> Option<f32> {
+        self.windows.iter().filter_map(|(_, window)| window.next_update()).reduce(f32::min)
+    }
+
+    pub fn take_damage(&mut self) -> Option<Rect> {
+        for (_, window) in &mut self.windows {
+            if let Some(rect) = window.take_damage() {
+                self.damage = Some(self.damage.map_or(rect, |damage| damage.union(&rect)));
+            }
+        }
+        self.damage.take()
+    }
+
+    /// Also destroys removed windows once no frame in flight can use them
+    pub fn update_geometry(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        for (_, window) in &mut self.windows {
+            window.update_geometry(context)?;
+        }
+        for (window, frames) in &mut self.retired {
+            *frames += 1;
+            if *frames > FRAMES_KEPT {
+                window.destroy(&context.device);
+            }
+        }
+        self.retired.retain(|(_, frames)| *frames <= FRAMES_KEPT);
+        Ok(())
+    }
+
+    pub fn render_offscreen(&mut self, pass: &OffscreenPass, renderer: &mut Renderer) -> Result<()> {
+        for (_, window) in &mut self.windows {
+            window.render_offscreen(pass, renderer)?;
+        }
+        Ok(())
+    }
+
+    /// Windows back to front
+    pub fn windows(&self) -> impl Iterator<Item = &FloatingWindow> {
+        self.windows.iter().map(|(_, window)| window)
+    }
+
+    /// Describe the open windows back to front
+    pub fn accessibility(&self, tree: &mut AccessTree) {
+        for (_, window) in &self.windows {
+            window.accessibility(tree);
+        }
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        for (_, window) in &self.windows {
+            window.destroy(device);
+        }
+        for (window, _) in &self.retired {
+            window.destroy(device);
+        }
+    }
+}
+
+impl Default for FloatingWindows {
+    fn default() -> Self {
+        Self::new()
+    }
+}
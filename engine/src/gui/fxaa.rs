@@ -0,0 +1,119 @@
+use anyhow::Result;
+use ash::vk;
+use glam::Mat4;
+use std::sync::Arc;
+use crate::gui::viewport::image_quad;
+use crate::math::Color;
+use crate::renderer::{
+    FxaaPushConstants, Mesh, PipelineId, RenderFrame, Renderer, SampledTexture, SamplerConfig, TexturedVertex2D, Texture, VulkanContext,
+};
+
+/// Post-process anti-aliasing of a rendered image, see `ViewportComponent::set_anti_aliasing`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AntiAliasing {
+    /// Draw the image as rendered
+    #[default]
+    None,
+    /// Fast approximate anti-aliasing: one pass blurring along edges found from luma
+    /// contrast, far cheaper than MSAA but softens fine detail a little
+    Fxaa,
+}
+
+/// FXAA applied to a render target after it was drawn, into a copy drawn in its place
+///
+/// The copy is made the first time `apply` runs and recreated when the source's size
+/// changes. It is sampled through a descriptor set of the layout given to `new`, so it can be
+/// drawn with the same pipeline as the source (e.g. `PipelineId::Image`).
+pub struct FxaaEffect {
+    /// Unit quad sampling a whole texture
+    image_quad: Mesh<TexturedVertex2D>,
+    /// Layout of the descriptor set `output` is drawn with
+    output_layout: vk::DescriptorSetLayout,
+    /// Descriptor set of the source it was created for
+    sampled_source: Option<(vk::ImageView, SampledTexture)>,
+    /// The smoothed image
+    output: Option<(Texture, SampledTexture)>,
+}
+
+impl FxaaEffect {
+    pub fn new(context: &Arc<VulkanContext>, output_layout: vk::DescriptorSetLayout) -> Result<Self> {
+        Ok(FxaaEffect { image_quad: image_quad(context)?, output_layout, sampled_source: None, output: None })
+    }
+
+    /// Smooth the edges of `source` into the output
+    /// `source` must have been rendered this frame (see `RenderFrame::render_to_targets`),
+    /// with the renderer's color format.
+    pub fn apply(&mut self, frame: &RenderFrame, renderer: &mut Renderer, context: &Arc<VulkanContext>, source: &Texture) -> Result<()> {
+        if !source.is_rendered() {
+            return Ok(());
+        }
+        let pipeline = renderer.get_pipeline(PipelineId::Fxaa)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::Fxaa)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for Fxaa pipeline"))?;
+        let descriptor_set_layout = renderer.get_descriptor_set_layout(PipelineId::Fxaa)
+            .ok_or_else(|| anyhow::anyhow!("Fxaa pipeline should have a descriptor set layout"))?;
+        self.prepare(context, source, renderer.color_format(), descriptor_set_layout)?;
+        let (Some((_, sampled)), Some((output, _))) = (&self.sampled_source, &self.output) else {
+            return Ok(());
+        };
+
+        let push = FxaaPushConstants::new(
+            Mat4::orthographic_rh(-0.5, 0.5, -0.5, 0.5, -1.0, 1.0),
+            Mat4::IDENTITY,
+            [1.0 / source.width as f32, 1.0 / source.height as f32],
+        );
+        let image_quad = &self.image_quad;
+        frame.render_to_texture(output, Color::TRANSPARENT, |ctx| {
+            ctx.bind_pipeline(pipeline);
+            ctx.bind_descriptor_set_at(pipeline_layout, 0, sampled.descriptor_set);
+            ctx.push(pipeline_layout, &push);
+            image_quad.draw(ctx)
+        })
+    }
+
+    /// The smoothed image of the last `apply`, `None` before the first one
+    pub fn output(&self) -> Option<&SampledTexture> {
+        self.output.as_ref().filter(|(texture, _)| texture.is_rendered()).map(|(_, sampled)| sampled)
+    }
+
+    /// Recreate the source's descriptor set and the output when they changed
+    fn prepare(&mut self, context: &Arc<VulkanContext>, source: &Texture, color_format: vk::Format, descriptor_set_layout: vk::DescriptorSetLayout) -> Result<()> {
+        let source_changed = self.sampled_source.as_ref().is_none_or(|(view, _)| *view != source.image_view);
+        let resized = self.output.as_ref().is_none_or(|(texture, _)| texture.width != source.width || texture.height != source.height);
+        if !source_changed && !resized {
+            return Ok(());
+        }
+        // Old descriptor sets and outputs may still be used by frames in flight
+        unsafe {
+            let _ = context.device.device_wait_idle();
+        }
+        if source_changed {
+            if let Some((_, sampled)) = self.sampled_source.take() {
+                sampled.destroy(&context.device);
+            }
+            let sampled = SampledTexture::from_view(source.image_view, SamplerConfig::linear(), descriptor_set_layout, context)?;
+            self.sampled_source = Some((source.image_view, sampled));
+        }
+        if resized {
+            if let Some((texture, sampled)) = self.output.take() {
+                sampled.destroy(&context.device);
+                texture.destroy(&context.device);
+            }
+            let texture = Texture::render_target(source.width, source.height, color_format, &context.device, &context.instance, context.physical_device)?;
+            let sampled = SampledTexture::new(&texture, SamplerConfig::linear(), self.output_layout, context)?;
+            self.output = Some((texture, sampled));
+        }
+        Ok(())
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        self.image_quad.destroy(device);
+        if let Some((_, sampled)) = &self.sampled_source {
+            sampled.destroy(device);
+        }
+        if let Some((texture, sampled)) = &self.output {
+            sampled.destroy(device);
+            texture.destroy(device);
+        }
+    }
+}
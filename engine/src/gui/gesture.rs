@@ -0,0 +1,163 @@
+use glam::Vec2;
+use std::time::{Duration, Instant};
+use winit::event::TouchPhase;
+
+/// A finger held still this long is a long press
+const LONG_PRESS: Duration = Duration::from_millis(500);
+/// Pixels a finger may move and still tap or long press
+const TAP_SLOP: f32 = 10.0;
+
+/// A gesture made with one or two fingers, positions in UI orientation (y up)
+///
+/// `GestureRecognizer` reports them in window pixels, components get them in UI units
+/// through `GUIComponent::handle_gesture`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    /// One finger pressed and lifted quickly without moving
+    Tap { point: Vec2 },
+    /// One finger held still, fired once while it is still down
+    LongPress { point: Vec2 },
+    /// Two fingers moved apart (`scale` above 1) or together since the last pinch
+    Pinch { center: Vec2, scale: f32 },
+    /// Two fingers moved together by `delta` since the last scroll, content follows them
+    Scroll { center: Vec2, delta: Vec2 },
+}
+
+impl Gesture {
+    /// Where the gesture happened, components under it get it first
+    pub fn point(&self) -> Vec2 {
+        match *self {
+            Gesture::Tap { point } | Gesture::LongPress { point } => point,
+            Gesture::Pinch { center, .. } | Gesture::Scroll { center, .. } => center,
+        }
+    }
+}
+
+struct Finger {
+    id: u64,
+    start: Vec2,
+    position: Vec2,
+    down: Instant,
+}
+
+/// Turns the touches of every finger into taps, long presses, pinches and two-finger scrolls
+///
+/// Feed it each `WindowEvent::Touch` with `touch`, call `update` every frame so long presses
+/// fire while the finger is held and `take` the gestures recognized since the last call.
+/// Taps and long presses only count while a single finger was down, the first two fingers
+/// make pinches and scrolls.
+pub struct GestureRecognizer {
+    fingers: Vec<Finger>,
+    /// More than one finger touched since the first went down
+    multi: bool,
+    /// The single finger moved beyond `TAP_SLOP` or already long pressed
+    consumed: bool,
+    gestures: Vec<Gesture>,
+}
+
+impl GestureRecognizer {
+    pub fn new() -> Self {
+        GestureRecognizer { fingers: Vec::new(), multi: false, consumed: false, gestures: Vec::new() }
+    }
+
+    /// Fingers currently down
+    pub fn finger_count(&self) -> usize {
+        self.fingers.len()
+    }
+
+    /// A touch of finger `id` at `point` in window pixels (y up)
+    pub fn touch(&mut self, id: u64, phase: TouchPhase, point: Vec2) {
+        match phase {
+            TouchPhase::Started => {
+                if self.fingers.is_empty() {
+                    self.multi = false;
+                    self.consumed = false;
+                }
+                self.fingers.retain(|finger| finger.id != id);
+                self.fingers.push(Finger { id, start: point, position: point, down: Instant::now() });
+                self.multi |= self.fingers.len() > 1;
+            }
+            TouchPhase::Moved => {
+                let Some(index) = self.fingers.iter().position(|finger| finger.id == id) else {
+                    return;
+                };
+                let before = self.pair();
+                let finger = &mut self.fingers[index];
+                finger.position = point;
+                if finger.start.distance(point) > TAP_SLOP {
+                    self.consumed = true;
+                }
+                if let (Some((a, b)), Some((c, d))) = (before, self.pair()) {
+                    self.two_finger_move(a, b, c, d);
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let Some(index) = self.fingers.iter().position(|finger| finger.id == id) else {
+                    return;
+                };
+                let finger = self.fingers.remove(index);
+                let tapped = phase == TouchPhase::Ended
+                    && !self.multi
+                    && !self.consumed
+                    && finger.down.elapsed() < LONG_PRESS
+                    && finger.start.distance(point) <= TAP_SLOP;
+                if tapped {
+                    self.gestures.push(Gesture::Tap { point });
+                }
+            }
+        }
+    }
+
+    /// Fire a long press once the single finger was held long enough
+    pub fn update(&mut self) {
+        if self.multi || self.consumed {
+            return;
+        }
+        if let [finger] = self.fingers.as_slice() {
+            if finger.down.elapsed() >= LONG_PRESS {
+                self.consumed = true;
+                self.gestures.push(Gesture::LongPress { point: finger.position });
+            }
+        }
+    }
+
+    /// Seconds until a held finger becomes a long press, `None` if none can
+    pub fn next_update(&self) -> Option<f32> {
+        match self.fingers.as_slice() {
+            [finger] if !self.multi && !self.consumed => Some(LONG_PRESS.saturating_sub(finger.down.elapsed()).as_secs_f32()),
+            _ => None,
+        }
+    }
+
+    /// Gestures recognized since the last call, oldest first
+    pub fn take(&mut self) -> Vec<Gesture> {
+        std::mem::take(&mut self.gestures)
+    }
+
+    fn pair(&self) -> Option<(Vec2, Vec2)> {
+        match self.fingers.as_slice() {
+            [a, b, ..] => Some((a.position, b.position)),
+            _ => None,
+        }
+    }
+
+    /// Pinch and scroll from the first two fingers moving from `a`, `b` to `c`, `d`
+    fn two_finger_move(&mut self, a: Vec2, b: Vec2, c: Vec2, d: Vec2) {
+        let center = (c + d) / 2.0;
+        let before = a.distance(b);
+        let after = c.distance(d);
+        if before > 0.0 && after > 0.0 && before != after {
+            self.gestures.push(Gesture::Pinch { center, scale: after / before });
+        }
+        let delta = center - (a + b) / 2.0;
+        if delta != Vec2::ZERO {
+            self.gestures.push(Gesture::Scroll { center, delta });
+        }
+    }
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
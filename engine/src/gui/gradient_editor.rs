@@ -0,0 +1,269 @@
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use crate::gui::color_picker::{create_mesh, quad};
+use crate::gui::{Color, GUIComponent, Rect, Transform};
+use crate::math::Gradient;
+use crate::renderer::{ColorVertex2D, Mesh, PipelineId, PushConstants2D, RenderContext, Renderer, VulkanContext};
+use glam::{Mat4, Vec2, Vec3};
+
+/// Height of the row of stop markers under the bar in pixels
+const MARKER_ROW: f32 = 12.0;
+const MARKER_WIDTH: f32 = 8.0;
+/// Old bar meshes are kept alive for this many rebuilds, longer than any frame stays in flight
+const MESHES_KEPT: usize = 3;
+
+/// Bar covering the unit square, one quad between each pair of neighbouring stops
+fn bar_vertices(gradient: &Gradient) -> Vec<ColorVertex2D> {
+    let mut edges = vec![0.0];
+    edges.extend(gradient.stops().iter().map(|stop| stop.t));
+    edges.push(1.0);
+    edges
+        .windows(2)
+        .flat_map(|pair| {
+            let (left, right) = (gradient.sample(pair[0]).rgb(), gradient.sample(pair[1]).rgb());
+            quad(pair[0] - 0.5, -0.5, pair[1] - 0.5, 0.5, [left, left, right, right])
+        })
+        .collect()
+}
+
+/// Editor for a `Gradient`: a preview bar with draggable stop markers under it
+///
+/// Clicking the bar adds a stop with the color already there, dragging a marker moves
+/// its stop. The last clicked stop is selected; pair the editor with a `ColorPicker`
+/// and `set_selected_color` to recolor it. The bar shows color only, not alpha.
+pub struct GradientEditor {
+    gradient: Gradient,
+    bar: Mesh<ColorVertex2D>,
+    /// The bar no longer matches the gradient
+    bar_dirty: bool,
+    retired: VecDeque<Mesh<ColorVertex2D>>,
+    /// White quad tinted for the markers
+    white: Mesh<ColorVertex2D>,
+    /// Hidden editors don't draw or take input
+    visible: bool,
+    selected: Option<usize>,
+    dragging: bool,
+    changed: bool,
+    /// Selection or stops changed by input since the last `take_damage`
+    damaged: bool,
+    transform: Transform,
+}
+
+impl GradientEditor {
+    pub fn new(context: &Arc<VulkanContext>, gradient: Gradient) -> Result<Self> {
+        Ok(GradientEditor {
+            bar: create_mesh(context, &bar_vertices(&gradient))?,
+            gradient,
+            bar_dirty: false,
+            retired: VecDeque::with_capacity(MESHES_KEPT + 1),
+            white: create_mesh(context, &quad(-0.5, -0.5, 0.5, 0.5, [[1.0, 1.0, 1.0]; 4]))?,
+            visible: true,
+            selected: None,
+            dragging: false,
+            changed: false,
+            damaged: false,
+            transform: Transform::new(),
+        })
+    }
+
+    pub fn gradient(&self) -> &Gradient {
+        &self.gradient
+    }
+
+    /// Replace the gradient programmatically (does not report a change)
+    pub fn set_gradient(&mut self, gradient: Gradient) {
+        self.gradient = gradient;
+        self.selected = None;
+        self.dragging = false;
+        self.bar_dirty = true;
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.dragging &= visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn selected_color(&self) -> Option<Color> {
+        self.selected.map(|index| self.gradient.stops()[index].color)
+    }
+
+    /// Recolor the selected stop, reported as a user change
+    pub fn set_selected_color(&mut self, color: Color) {
+        if let Some(index) = self.selected {
+            self.gradient.set_color(index, color);
+            self.gradient_edited();
+        }
+    }
+
+    /// Remove the selected stop, reported as a user change
+    pub fn remove_selected(&mut self) {
+        if let Some(index) = self.selected.take() {
+            self.gradient.remove_stop(index);
+            self.gradient_edited();
+        }
+    }
+
+    /// Returns the new gradient if the user changed it since the last call
+    pub fn take_changed(&mut self) -> Option<Gradient> {
+        if self.changed {
+            self.changed = false;
+            Some(self.gradient.clone())
+        } else {
+            None
+        }
+    }
+
+    fn gradient_edited(&mut self) {
+        self.changed = true;
+        self.bar_dirty = true;
+    }
+
+    /// Bar and marker row areas
+    fn layout(&self) -> [Transform; 2] {
+        let size = self.transform.scale;
+        let bar_height = (size.y - MARKER_ROW).max(0.0);
+        let mut bar = self.transform;
+        bar.position.y += MARKER_ROW / 2.0;
+        bar.scale.y = bar_height;
+        let mut markers = self.transform;
+        markers.position.y -= bar_height / 2.0;
+        markers.scale.y = MARKER_ROW;
+        [bar, markers]
+    }
+
+    /// Position along the bar (0..1) under a UI space x
+    fn t_at(&self, x: f32) -> f32 {
+        let left = self.transform.position.x - self.transform.scale.x / 2.0;
+        ((x - left) / self.transform.scale.x.max(1.0)).clamp(0.0, 1.0)
+    }
+
+    fn marker_x(&self, t: f32) -> f32 {
+        self.transform.position.x + (t - 0.5) * self.transform.scale.x
+    }
+
+    /// Stop whose marker is under the point, the nearest one if markers overlap
+    fn stop_at(&self, point: Vec2) -> Option<usize> {
+        let [_, markers] = self.layout();
+        if !markers.rect().inflate(2.0).contains_point(point) {
+            return None;
+        }
+        self.gradient
+            .stops()
+            .iter()
+            .enumerate()
+            .map(|(index, stop)| (index, (self.marker_x(stop.t) - point.x).abs()))
+            .filter(|&(_, distance)| distance <= MARKER_WIDTH / 2.0 + 1.0)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+
+    /// Rebuild the bar after an edit
+    /// Call once per frame before rendering
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        if !self.bar_dirty {
+            return Ok(());
+        }
+        let bar = create_mesh(context, &bar_vertices(&self.gradient))?;
+        self.retired.push_back(std::mem::replace(&mut self.bar, bar));
+        self.bar_dirty = false;
+        while self.retired.len() > MESHES_KEPT {
+            if let Some(old) = self.retired.pop_front() {
+                old.destroy(&context.device);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GUIComponent for GradientEditor {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        let pipeline = renderer.get_pipeline(PipelineId::BasicGeometry)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::BasicGeometry)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for BasicGeometry pipeline"))?;
+        ctx.bind_pipeline(pipeline);
+
+        let projection = renderer.projection;
+        let draw = |mesh: &Mesh<ColorVertex2D>, position: Vec2, size: Vec2, color: [f32; 3]| -> Result<()> {
+            let transform = Mat4::from_translation(position.extend(0.0)) * Mat4::from_scale(Vec3::new(size.x, size.y, 1.0));
+            let push = PushConstants2D::new(projection, transform).with_modulation(color);
+            ctx.push(pipeline_layout, &push);
+            mesh.draw(ctx)
+        };
+
+        let [bar, markers] = self.layout();
+        draw(&self.bar, bar.position, bar.scale, [1.0, 1.0, 1.0])?;
+        for (index, stop) in self.gradient.stops().iter().enumerate() {
+            let position = Vec2::new(self.marker_x(stop.t), markers.position.y);
+            let outline = if self.selected == Some(index) { [1.0, 1.0, 1.0] } else { [0.0, 0.0, 0.0] };
+            draw(&self.white, position, Vec2::new(MARKER_WIDTH + 2.0, MARKER_ROW), outline)?;
+            draw(&self.white, position, Vec2::new(MARKER_WIDTH, MARKER_ROW - 2.0), stop.color.rgb())?;
+        }
+        Ok(())
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        if !self.visible {
+            return;
+        }
+        let point = Vec2::new(x, y);
+        let [bar, _] = self.layout();
+        if let Some(index) = self.stop_at(point) {
+            self.selected = Some(index);
+            self.dragging = true;
+            self.damaged = true;
+        } else if bar.contains_point(point) {
+            let t = self.t_at(x);
+            let color = self.gradient.sample(t);
+            self.selected = Some(self.gradient.add_stop(t, color));
+            self.dragging = true;
+            self.damaged = true;
+            self.gradient_edited();
+        }
+    }
+
+    fn handle_mouse_up(&mut self, _x: f32, _y: f32) {
+        self.dragging = false;
+    }
+
+    fn handle_mouse_move(&mut self, x: f32, _y: f32) {
+        let Some(index) = self.selected.filter(|_| self.dragging) else {
+            return;
+        };
+        self.selected = Some(self.gradient.move_stop(index, self.t_at(x)));
+        self.damaged = true;
+        self.gradient_edited();
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.transform.rect())
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.bar.destroy(device);
+        for mesh in &self.retired {
+            mesh.destroy(device);
+        }
+        self.white.destroy(device);
+    }
+}
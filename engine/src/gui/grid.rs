@@ -1,11 +1,24 @@
-use anyhow::Result;
-use crate::gui::{GUIComponent, LayoutSpec, ComputedLayout};
-use crate::renderer::RenderContext;
+use anyhow::{anyhow, Result};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use crate::gui::{AccessAction, AccessTree, OffscreenPass, Gesture, GUIComponent, LayoutSpec, ComputedLayout, RowSpec, SizeSpec, MenuItem, QuadBatch, SpatialHash};
+use crate::math::ease::{Ease, Lerp};
+use crate::math::{Color, Rect};
+use crate::renderer::{RenderContext, Renderer, VulkanContext};
+use glam::Vec2;
 
 /// A grid row containing multiple components
 pub struct GridRow {
     pub components: Vec<Box<dyn GUIComponent>>,
     pub layout_specs: Vec<LayoutSpec>,
+    /// Layout of the row itself, `None` to derive it from the first component's spec
+    spec: Option<RowSpec>,
+    /// Area of the last layout, before padding
+    rect: Rect,
+    /// Components or specs changed since the last layout
+    dirty: bool,
+    /// Area the background was or will be drawn at since the last `take_damage`
+    damage: Option<Rect>,
 }
 
 impl GridRow {
@@ -13,34 +26,88 @@ impl GridRow {
         GridRow {
             components: Vec::new(),
             layout_specs: Vec::new(),
+            spec: None,
+            rect: Rect::default(),
+            dirty: false,
+            damage: None,
+        }
+    }
+
+    pub fn with_spec(spec: RowSpec) -> Self {
+        GridRow { spec: Some(spec), ..Self::new() }
+    }
+
+    /// Change the height, padding, alignment or background of the row, it is laid out again
+    /// on the next `Grid::set_bounds`
+    pub fn set_row_spec(&mut self, spec: RowSpec) {
+        if self.spec != Some(spec) {
+            self.damage_background(self.rect);
+            self.spec = Some(spec);
+            self.dirty = true;
+        }
+    }
+
+    /// Spec the row is laid out by, its own or the one derived from its first component
+    pub fn row_spec(&self) -> RowSpec {
+        match (self.spec, self.layout_specs.first()) {
+            (Some(spec), _) => spec,
+            (None, Some(first)) => RowSpec::from_component(first),
+            (None, None) => RowSpec::new(SizeSpec::Fixed(0.0)),
+        }
+    }
+
+    /// Background rect and color, for the grid's batch of row backgrounds
+    pub fn background(&self) -> Option<(Rect, Color)> {
+        self.spec.and_then(|spec| spec.background).map(|color| (self.rect, color))
+    }
+
+    fn damage_background(&mut self, rect: Rect) {
+        if self.background().is_some() {
+            self.damage = Some(self.damage.map_or(rect, |damage| damage.union(&rect)));
         }
     }
 
     pub fn add_component(&mut self, component: Box<dyn GUIComponent>, spec: LayoutSpec) {
         self.components.push(component);
         self.layout_specs.push(spec);
+        self.dirty = true;
+    }
+
+    /// Change how a component is sized, the row is laid out again on the next `Grid::set_bounds`
+    pub fn set_spec(&mut self, index: usize, spec: LayoutSpec) {
+        if let Some(current) = self.layout_specs.get_mut(index) {
+            *current = spec;
+            self.dirty = true;
+        }
     }
 
     /// Apply layout constraints to all components in this row
-    pub fn set_layout(&mut self, parent_x: f32, parent_y: f32, parent_width: f32, parent_height: f32) {
+    pub fn set_layout(&mut self, parent: Rect) {
+        self.dirty = false;
+        if self.rect != parent {
+            self.damage_background(self.rect);
+            self.damage_background(parent);
+        }
+        self.rect = parent;
         if self.components.is_empty() {
             return;
         }
 
-        let layouts = ComputedLayout::compute_row(
-            &self.layout_specs,
-            parent_x,
-            parent_y,
-            parent_width,
-            parent_height,
-        );
+        let row = self.row_spec();
+        let offered = parent.inflate(-row.padding).size();
+        let content: Vec<Vec2> = self.components.iter().map(|component| component.measure(offered)).collect();
+        let layouts = ComputedLayout::compute_row(row, &self.layout_specs, &content, parent);
 
         for (component, layout) in self.components.iter_mut().zip(layouts.iter()) {
-            component.transform_mut().position = layout.position;
-            component.transform_mut().scale = layout.scale;
+            component.set_layout(layout.rect());
         }
     }
 
+    /// Area the row was last laid out in, the components are placed inside its padding
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
     pub fn get_component(&self, index: usize) -> Option<&dyn GUIComponent> {
         self.components.get(index).map(|c| c.as_ref())
     }
@@ -56,6 +123,12 @@ impl GridRow {
         Ok(())
     }
 
+    pub fn update(&mut self, dt: f32) {
+        for component in &mut self.components {
+            component.update(dt);
+        }
+    }
+
     pub fn handle_mouse_down(&mut self, x: f32, y: f32) {
         for component in &mut self.components {
             component.handle_mouse_down(x, y);
@@ -73,6 +146,15 @@ impl GridRow {
             component.handle_mouse_move(x, y);
         }
     }
+
+    /// Damage of the background and all components, see `GUIComponent::take_damage`
+    pub fn take_damage(&mut self) -> Option<Rect> {
+        self.damage
+            .take()
+            .into_iter()
+            .chain(self.components.iter_mut().filter_map(|component| component.take_damage()))
+            .reduce(|a, b| a.union(&b))
+    }
 }
 
 impl Default for GridRow {
@@ -81,23 +163,138 @@ impl Default for GridRow {
     }
 }
 
+/// Typed reference to a component added with `Grid::add`, for changing it afterwards
+/// through `Grid::get_mut` (or the grid of the container holding it)
+pub struct WidgetHandle<T> {
+    row: usize,
+    index: usize,
+    _component: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for WidgetHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WidgetHandle<T> {}
+
+impl<T> std::fmt::Debug for WidgetHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WidgetHandle({}, {})", self.row, self.index)
+    }
+}
+
+/// Space between rows of a new grid
+const DEFAULT_SPACING: f32 = 3.0;
+
 /// A grid layout system for organizing components in rows
+///
+/// Rows are stacked from the top with `spacing` between them. Each is laid out by its
+/// `RowSpec`, see `add_row_with_spec`, or else by the spec of its first component.
+///
+/// Layout is only recomputed when the bounds or a row changed. Components that are a
+/// single flat rect (see `GUIComponent::batch_quad`) are drawn together from a retained
+/// buffer once `update_geometry` has run.
+///
+/// Mouse events only reach the components under the pointer, found through a spatial
+/// hash rebuilt with the layout, plus the ones that need to see the pointer leave
+/// (hovered on the last move) or finish a drag (pressed until the button is released).
 pub struct Grid {
     pub rows: Vec<GridRow>,
+    /// Space between neighbouring rows
+    spacing: f32,
+    /// Bounds of the last layout
+    bounds: Option<Rect>,
+    /// Rows were added since the last layout
+    layout_dirty: bool,
+    batch: QuadBatch,
+    /// Row backgrounds, drawn before everything else
+    backgrounds: QuadBatch,
+    /// Row and component index of each quad in `batch`
+    batched: Vec<(usize, usize)>,
+    /// Component bounds of the last layout, by row and component index
+    hits: SpatialHash<(usize, usize)>,
+    /// Components under the pointer on the last move
+    hovered: Vec<(usize, usize)>,
+    /// Components under the pointer on the last press, they get the release wherever it is
+    captured: Vec<(usize, usize)>,
+    /// Rows and components moving to the rects of the last layout, see `set_bounds_animated`
+    transition: Option<LayoutTransition>,
+}
+
+/// Start and end rects of an animated layout change
+struct LayoutTransition {
+    elapsed: f32,
+    duration: f32,
+    /// Rect of every row, then of each of its components, by row
+    from: Vec<(Rect, Vec<Rect>)>,
+    to: Vec<(Rect, Vec<Rect>)>,
 }
 
 impl Grid {
     pub fn new() -> Self {
         Grid {
             rows: Vec::new(),
+            spacing: DEFAULT_SPACING,
+            bounds: None,
+            layout_dirty: false,
+            batch: QuadBatch::new(),
+            backgrounds: QuadBatch::new(),
+            batched: Vec::new(),
+            hits: SpatialHash::new(),
+            hovered: Vec::new(),
+            captured: Vec::new(),
+            transition: None,
         }
     }
 
     pub fn add_row(&mut self) -> usize {
         self.rows.push(GridRow::new());
+        self.layout_dirty = true;
         self.rows.len() - 1
     }
 
+    /// Add a row laid out by `spec` instead of its first component's spec
+    pub fn add_row_with_spec(&mut self, spec: RowSpec) -> usize {
+        self.rows.push(GridRow::with_spec(spec));
+        self.layout_dirty = true;
+        self.rows.len() - 1
+    }
+
+    /// Change the space between rows, laid out again on the next `set_bounds`
+    pub fn set_spacing(&mut self, spacing: f32) {
+        if self.spacing != spacing {
+            self.spacing = spacing;
+            self.layout_dirty = true;
+        }
+    }
+
+    pub fn spacing(&self) -> f32 {
+        self.spacing
+    }
+
+    /// Add a component to the end of a row and get a typed handle to it
+    pub fn add<T: GUIComponent + 'static>(&mut self, row: usize, component: T, spec: LayoutSpec) -> Result<WidgetHandle<T>> {
+        let grid_row = self.rows.get_mut(row).ok_or_else(|| anyhow!("Grid has no row {}", row))?;
+        grid_row.add_component(Box::new(component), spec);
+        Ok(WidgetHandle { row, index: grid_row.components.len() - 1, _component: PhantomData })
+    }
+
+    /// The component behind a handle, `None` if it was removed or replaced by another type
+    pub fn get<T: GUIComponent + 'static>(&self, handle: WidgetHandle<T>) -> Option<&T> {
+        self.rows.get(handle.row)?.components.get(handle.index)?.as_any().downcast_ref()
+    }
+
+    pub fn get_mut<T: GUIComponent + 'static>(&mut self, handle: WidgetHandle<T>) -> Option<&mut T> {
+        self.rows.get_mut(handle.row)?.components.get_mut(handle.index)?.as_any_mut().downcast_mut()
+    }
+
+    /// Lay out again on the next `set_bounds`, for changes made through the public fields
+    pub fn invalidate_layout(&mut self) {
+        self.layout_dirty = true;
+    }
+
     pub fn get_row(&self, index: usize) -> Option<&GridRow> {
         self.rows.get(index)
     }
@@ -111,82 +308,376 @@ impl Grid {
     }
 
     /// Update layout for all rows based on bounds
-    pub fn set_bounds(&mut self, x: f32, y: f32, width: f32, height: f32) {
-        if self.rows.is_empty() {
+    /// Nothing is recomputed if neither the bounds nor the rows changed since the last call.
+    pub fn set_bounds(&mut self, bounds: Rect) {
+        if self.bounds != Some(bounds) {
+            self.bounds = Some(bounds);
+            self.layout_dirty = true;
+        }
+        self.update_layout();
+    }
+
+    /// `set_bounds`, with rows and components moving from where they are to their new
+    /// rects over `duration` seconds instead of jumping there, e.g. on window resizes
+    /// The move is advanced by `animate_layout`, any other layout ends it.
+    pub fn set_bounds_animated(&mut self, bounds: Rect, duration: f32) {
+        if self.bounds.is_none() || duration <= 0.0 {
+            self.set_bounds(bounds);
+            return;
+        }
+        let from = self.layout_rects();
+        self.set_bounds(bounds);
+        let to = self.layout_rects();
+        if from == to || from.len() != to.len() || from.iter().zip(&to).any(|(a, b)| a.1.len() != b.1.len()) {
             return;
         }
+        self.transition = Some(LayoutTransition { elapsed: 0.0, duration, from, to });
+        self.apply_transition(0.0);
+    }
 
-        let row_spacing = 3.0; // Space between rows
-        let num_rows = self.rows.len();
-        let total_spacing = row_spacing * (num_rows - 1) as f32;
+    /// Advance a `set_bounds_animated` move by `dt` seconds, returns whether anything moved
+    pub fn animate_layout(&mut self, dt: f32) -> bool {
+        let Some(transition) = &mut self.transition else {
+            return false;
+        };
+        transition.elapsed += dt;
+        let t = transition.elapsed / transition.duration;
+        self.apply_transition(t);
+        if t >= 1.0 {
+            self.transition = None;
+        }
+        true
+    }
 
-        // Calculate each row's actual height based on its component specs
-        let mut row_heights = Vec::new();
-        let mut total_fixed_height = 0.0;
-        let mut percent_rows = 0;
+    /// Whether rows and components are still moving to their new rects
+    pub fn is_animating_layout(&self) -> bool {
+        self.transition.is_some()
+    }
 
-        for row in &self.rows {
-            if !row.layout_specs.is_empty() {
-                // Use first component's height spec (assuming homogeneous row heights)
-                match row.layout_specs[0].height {
-                    crate::gui::SizeSpec::Fixed(h) => {
-                        row_heights.push(h);
-                        total_fixed_height += h;
-                    }
-                    crate::gui::SizeSpec::Percent(_) => {
-                        row_heights.push(0.0); // Placeholder, will compute after
-                        percent_rows += 1;
-                    }
-                }
+    /// Rect of every row and component, by row
+    fn layout_rects(&self) -> Vec<(Rect, Vec<Rect>)> {
+        self.rows
+            .iter()
+            .map(|row| (row.rect, row.components.iter().map(|component| component.transform().rect()).collect()))
+            .collect()
+    }
+
+    /// Place rows and components `t` (0 to 1) of the way through the transition
+    fn apply_transition(&mut self, t: f32) {
+        let Some(transition) = &self.transition else {
+            return;
+        };
+        let t = Ease::CubicOut.apply(t);
+        for (row, (from, to)) in self.rows.iter_mut().zip(transition.from.iter().zip(&transition.to)) {
+            let rect = from.0.lerp(to.0, t);
+            row.damage_background(row.rect);
+            row.damage_background(rect);
+            row.rect = rect;
+            for (component, (from, to)) in row.components.iter_mut().zip(from.1.iter().zip(&to.1)) {
+                component.set_layout(from.lerp(*to, t));
             }
         }
+        self.rebuild_hits();
+    }
 
-        // Distribute remaining height among percent-based rows
-        if percent_rows > 0 {
-            let remaining = (height - total_fixed_height - total_spacing).max(0.0);
-            let per_row = remaining / percent_rows as f32;
-            for h in &mut row_heights {
-                if *h == 0.0 {
-                    *h = per_row;
-                }
-            }
+    /// Bounds of the last layout, `None` before the first `set_bounds`
+    pub fn bounds(&self) -> Option<Rect> {
+        self.bounds
+    }
+
+    /// Lay out again within the current bounds if rows changed since the last layout,
+    /// returns whether it did
+    pub fn update_layout(&mut self) -> bool {
+        let Some(bounds) = self.bounds else {
+            return false;
+        };
+        if !self.layout_dirty && !self.rows.iter().any(|row| row.dirty) {
+            return false;
+        }
+        self.layout_dirty = false;
+        self.transition = None;
+        if self.rows.is_empty() {
+            self.hits.clear();
+            return true;
         }
+        crate::profile_scope!("layout");
+
+        let row_spacing = self.spacing;
+        let num_rows = self.rows.len();
+        let total_spacing = row_spacing * (num_rows - 1) as f32;
+
+        // Each row is as high as its spec says, `Auto` rows as high as their tallest component
+        // wants to be plus padding
+        let heights: Vec<(SizeSpec, f32)> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let spec = row.row_spec();
+                let offered = bounds.inflate(-spec.padding).size();
+                let content = row.components.iter().map(|c| c.measure(offered).y).fold(0.0, f32::max);
+                (spec.height, content + spec.padding * 2.0)
+            })
+            .collect();
+        let row_heights = SizeSpec::distribute(&heights, bounds.height, total_spacing);
 
         // Apply layout with calculated row heights and spacing
         // Position rows starting from the top (high Y) going downward
-        let mut current_y = y + height; // Start at top
+        let mut current_y = bounds.y + bounds.height; // Start at top
         for (i, (row, &row_height)) in self.rows.iter_mut().zip(row_heights.iter()).enumerate() {
             current_y -= row_height; // Move down
-            row.set_layout(x, current_y, width, row_height);
+            row.set_layout(Rect::new(bounds.x, current_y, bounds.width, row_height));
             if i < num_rows - 1 {
                 current_y -= row_spacing; // Add spacing between rows
             }
         }
+        self.rebuild_hits();
+        true
+    }
+
+    fn rebuild_hits(&mut self) {
+        self.hits.clear();
+        for (row_index, row) in self.rows.iter().enumerate() {
+            for (index, component) in row.components.iter().enumerate() {
+                self.hits.insert(component.transform().rect(), (row_index, index));
+            }
+        }
+    }
+
+    /// Components under a point, `None` while the spatial hash is out of date (before the
+    /// first layout or with components added since)
+    fn hits_at(&self, x: f32, y: f32) -> Option<Vec<(usize, usize)>> {
+        let current = self.bounds.is_some() && !self.layout_dirty && !self.rows.iter().any(|row| row.dirty);
+        current.then(|| self.hits.query_point(glam::Vec2::new(x, y)).collect())
     }
 
+    /// First component with an open popup, it gets all mouse input while the popup is open
+    fn modal(&self) -> Option<(usize, usize)> {
+        self.rows.iter().enumerate().find_map(|(row_index, row)| {
+            row.components.iter().position(|component| component.overlay_rect().is_some()).map(|index| (row_index, index))
+        })
+    }
+
+    /// Area of the open popups of all components
+    pub fn overlay_rect(&self) -> Option<Rect> {
+        self.components().filter_map(|component| component.overlay_rect()).reduce(|a, b| a.union(&b))
+    }
+
+    /// Draw the popups of all components, after `render`
+    pub fn render_overlay(&self, ctx: &RenderContext, renderer: &mut crate::renderer::Renderer) -> Result<()> {
+        for component in self.components() {
+            component.render_overlay(ctx, renderer)?;
+        }
+        Ok(())
+    }
+
+    /// Send a key to the component with an open popup, or else to every component until
+    /// one uses it
+    pub fn handle_key(&mut self, key: &winit::keyboard::Key) -> bool {
+        if let Some((row, index)) = self.modal() {
+            return self.rows[row].components[index].handle_key(key);
+        }
+        self.rows.iter_mut().flat_map(|row| row.components.iter_mut()).any(|component| component.handle_key(key))
+    }
+
+    /// Send a gesture to the component with an open popup, or else to the topmost components
+    /// under it until one uses it
+    pub fn handle_gesture(&mut self, gesture: &Gesture) -> bool {
+        if let Some((row, index)) = self.modal() {
+            return self.rows[row].components[index].handle_gesture(gesture);
+        }
+        let point = gesture.point();
+        self.rows
+            .iter_mut()
+            .flat_map(|row| row.components.iter_mut())
+            .rev()
+            .filter(|component| component.transform().contains_point(point))
+            .any(|component| component.handle_gesture(gesture))
+    }
+
+    /// Context menu of the topmost component containing `point`
+    pub fn context_menu_at(&self, point: glam::Vec2) -> Option<Vec<MenuItem>> {
+        let components: Vec<&dyn GUIComponent> = self.components().collect();
+        components
+            .into_iter()
+            .rev()
+            .filter(|component| component.transform().contains_point(point))
+            .find_map(|component| component.context_menu(point))
+    }
+
+    /// Call `event` on each of `targets` once, in row order
+    fn dispatch(&mut self, mut targets: Vec<(usize, usize)>, mut event: impl FnMut(&mut dyn GUIComponent)) {
+        targets.sort_unstable();
+        targets.dedup();
+        for (row, index) in targets {
+            if let Some(component) = self.rows.get_mut(row).and_then(|row| row.components.get_mut(index)) {
+                event(component.as_mut());
+            }
+        }
+    }
+
+    /// Rebuild the batch of flat rects if they changed, and the geometry of nested components
+    /// Call once per frame before rendering.
+    pub fn update_geometry(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        let mut quads = Vec::new();
+        self.batched.clear();
+        for (row_index, row) in self.rows.iter_mut().enumerate() {
+            for (index, component) in row.components.iter_mut().enumerate() {
+                component.update_geometry(context)?;
+                if let Some(quad) = component.batch_quad() {
+                    quads.push(quad);
+                    self.batched.push((row_index, index));
+                }
+            }
+        }
+        self.backgrounds.update(context, self.rows.iter().filter_map(GridRow::background).collect())?;
+        self.batch.update(context, quads)
+    }
+
+    /// Record the offscreen passes of the components, see `GUIComponent::render_offscreen`
+    pub fn render_offscreen(&mut self, pass: &OffscreenPass, renderer: &mut Renderer) -> Result<()> {
+        for row in &mut self.rows {
+            for component in &mut row.components {
+                component.render_offscreen(pass, renderer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the batch still matches its components, a moved or recolored rect since
+    /// `update_geometry` would otherwise be drawn at its old place
+    fn batch_is_current(&self) -> bool {
+        self.batched.len() == self.batch.quads().len()
+            && self.batched.iter().zip(self.batch.quads()).all(|(&(row, index), quad)| {
+                self.rows
+                    .get(row)
+                    .and_then(|row| row.get_component(index))
+                    .and_then(|component| component.batch_quad())
+                    .is_some_and(|current| current == *quad)
+            })
+    }
+
+    /// Row backgrounds and batched rects are drawn first, then every other component in row order
     pub fn render(&self, ctx: &RenderContext, renderer: &mut crate::renderer::Renderer) -> Result<()> {
-        for row in &self.rows {
-            row.render(ctx, renderer)?;
+        self.backgrounds.render(ctx, renderer)?;
+        if self.batched.is_empty() || !self.batch_is_current() {
+            for row in &self.rows {
+                row.render(ctx, renderer)?;
+            }
+            return Ok(());
+        }
+        self.batch.render(ctx, renderer)?;
+        let mut batched = self.batched.iter().peekable();
+        for (row_index, row) in self.rows.iter().enumerate() {
+            for (index, component) in row.components.iter().enumerate() {
+                if batched.next_if_eq(&&(row_index, index)).is_none() {
+                    component.render(ctx, renderer)?;
+                }
+            }
         }
         Ok(())
     }
 
-    pub fn handle_mouse_down(&mut self, x: f32, y: f32) {
+    /// Destroy the components and the batch buffers
+    pub fn destroy(&self, device: &ash::Device) {
+        for row in &self.rows {
+            for component in &row.components {
+                component.destroy(device);
+            }
+        }
+        self.batch.destroy(device);
+        self.backgrounds.destroy(device);
+    }
+
+    /// Components of all rows in row order
+    pub fn components(&self) -> impl Iterator<Item = &dyn GUIComponent> {
+        self.rows.iter().flat_map(|row| row.components.iter().map(|c| c.as_ref()))
+    }
+
+    /// Describe every component in row order, see `GUIComponent::accessibility`
+    pub fn accessibility(&self, tree: &mut AccessTree) {
+        for component in self.components() {
+            component.accessibility(tree);
+        }
+    }
+
+    /// Offer an action to the components under its point until one performs it
+    pub fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+        let open = self.overlay_rect().is_some_and(|rect| rect.contains_point(action.point));
+        self.rows
+            .iter_mut()
+            .flat_map(|row| row.components.iter_mut())
+            // Popups reach outside their component
+            .filter(|component| open || component.transform().contains_point(action.point))
+            .any(|component| component.accessibility_action(action))
+    }
+
+    /// Advance every component by `dt` seconds, see `GUIComponent::update`
+    pub fn update(&mut self, dt: f32) {
         for row in &mut self.rows {
-            row.handle_mouse_down(x, y);
+            row.update(dt);
+        }
+    }
+
+    /// Soonest `GUIComponent::next_update` of all components, right away during a layout move
+    pub fn next_update(&self) -> Option<f32> {
+        if self.transition.is_some() {
+            return Some(0.0);
         }
+        self.components().filter_map(|component| component.next_update()).reduce(f32::min)
+    }
+
+    pub fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        if let Some(owner) = self.modal() {
+            self.captured = vec![owner];
+            self.dispatch(vec![owner], |component| component.handle_mouse_down(x, y));
+            return;
+        }
+        let Some(hits) = self.hits_at(x, y) else {
+            for row in &mut self.rows {
+                row.handle_mouse_down(x, y);
+            }
+            return;
+        };
+        self.captured = hits.clone();
+        self.dispatch(hits, |component| component.handle_mouse_down(x, y));
     }
 
     pub fn handle_mouse_up(&mut self, x: f32, y: f32) {
-        for row in &mut self.rows {
-            row.handle_mouse_up(x, y);
+        let captured = std::mem::take(&mut self.captured);
+        if let Some(owner) = self.modal() {
+            self.dispatch(vec![owner], |component| component.handle_mouse_up(x, y));
+            return;
         }
+        let Some(mut targets) = self.hits_at(x, y) else {
+            for row in &mut self.rows {
+                row.handle_mouse_up(x, y);
+            }
+            return;
+        };
+        targets.extend(captured);
+        self.dispatch(targets, |component| component.handle_mouse_up(x, y));
     }
 
     pub fn handle_mouse_move(&mut self, x: f32, y: f32) {
-        for row in &mut self.rows {
-            row.handle_mouse_move(x, y);
+        if let Some(owner) = self.modal() {
+            self.dispatch(vec![owner], |component| component.handle_mouse_move(x, y));
+            return;
         }
+        let Some(hits) = self.hits_at(x, y) else {
+            for row in &mut self.rows {
+                row.handle_mouse_move(x, y);
+            }
+            return;
+        };
+        let mut targets = std::mem::replace(&mut self.hovered, hits.clone());
+        targets.extend(hits);
+        targets.extend_from_slice(&self.captured);
+        self.dispatch(targets, |component| component.handle_mouse_move(x, y));
+    }
+
+    /// Damage of all rows, see `GUIComponent::take_damage`
+    pub fn take_damage(&mut self) -> Option<Rect> {
+        self.rows.iter_mut().filter_map(GridRow::take_damage).reduce(|a, b| a.union(&b))
     }
 }
 
@@ -229,3 +720,75 @@ impl LayoutConstraints {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Transform;
+
+    /// A component asking for a fixed size
+    struct Probe {
+        transform: Transform,
+        size: Vec2,
+    }
+
+    impl GUIComponent for Probe {
+        fn render(&self, _ctx: &RenderContext, _renderer: &mut Renderer) -> Result<()> { Ok(()) }
+        fn transform(&self) -> &Transform { &self.transform }
+        fn transform_mut(&mut self) -> &mut Transform { &mut self.transform }
+        fn measure(&self, constraints: Vec2) -> Vec2 { self.size.min(constraints) }
+        fn destroy(&self, _device: &ash::Device) {}
+    }
+
+    fn row_rects(grid: &Grid) -> Vec<Rect> {
+        grid.rows.iter().map(|row| row.rect).collect()
+    }
+
+    #[test]
+    fn row_heights_sum_to_the_parent() {
+        let mut grid = Grid::new();
+        grid.set_spacing(0.0);
+        grid.add_row_with_spec(RowSpec::new(SizeSpec::Fixed(50.0)));
+        grid.add_row_with_spec(RowSpec::new(SizeSpec::Fraction(1.0)));
+        grid.add_row_with_spec(RowSpec::new(SizeSpec::Fraction(1.0)));
+        grid.add_row_with_spec(RowSpec::new(SizeSpec::Percent(0.25)));
+        grid.set_bounds(Rect::new(0.0, 0.0, 200.0, 400.0));
+
+        // Stacked from the top, fractions split what the others leave
+        assert_eq!(row_rects(&grid), vec![
+            Rect::new(0.0, 350.0, 200.0, 50.0),
+            Rect::new(0.0, 225.0, 200.0, 125.0),
+            Rect::new(0.0, 100.0, 200.0, 125.0),
+            Rect::new(0.0, 0.0, 200.0, 100.0),
+        ]);
+    }
+
+    #[test]
+    fn spacing_is_taken_before_fractions() {
+        let mut grid = Grid::new();
+        grid.set_spacing(10.0);
+        for _ in 0..3 {
+            grid.add_row_with_spec(RowSpec::new(SizeSpec::Fraction(1.0)));
+        }
+        grid.set_bounds(Rect::new(0.0, 0.0, 100.0, 320.0));
+        let heights: Vec<f32> = row_rects(&grid).iter().map(|rect| rect.height).collect();
+        assert_eq!(heights, vec![100.0, 100.0, 100.0]);
+        assert_eq!(row_rects(&grid)[2].y, 0.0);
+    }
+
+    #[test]
+    fn auto_rows_fit_their_tallest_component() {
+        let mut grid = Grid::new();
+        grid.set_spacing(0.0);
+        let auto = grid.add_row_with_spec(RowSpec::new(SizeSpec::Auto).with_padding(2.0));
+        let fill = grid.add_row_with_spec(RowSpec::new(SizeSpec::Fraction(1.0)));
+        let spec = LayoutSpec::new(SizeSpec::Auto, SizeSpec::Auto);
+        grid.add(auto, Probe { transform: Transform::new(), size: Vec2::new(40.0, 20.0) }, spec).unwrap();
+        let tall = grid.add(auto, Probe { transform: Transform::new(), size: Vec2::new(40.0, 30.0) }, spec).unwrap();
+        grid.add(fill, Probe { transform: Transform::new(), size: Vec2::ZERO }, LayoutSpec::new(SizeSpec::Fraction(1.0), SizeSpec::Fraction(1.0))).unwrap();
+        grid.set_bounds(Rect::new(0.0, 0.0, 200.0, 300.0));
+
+        assert_eq!(row_rects(&grid), vec![Rect::new(0.0, 266.0, 200.0, 34.0), Rect::new(0.0, 0.0, 200.0, 266.0)]);
+        assert_eq!(grid.get(tall).unwrap().transform().rect(), Rect::new(42.0, 268.0, 40.0, 30.0));
+    }
+}
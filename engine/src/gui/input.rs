@@ -0,0 +1,44 @@
+use glam::Vec2;
+use winit::event::MouseButton;
+
+/// Pointer state the application passes to `UISystem::update` every frame
+///
+/// Kept up to date from window events by the application, so the UI can notice what its
+/// mouse handlers missed, like a button released outside the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputState {
+    /// Mouse position in UI space
+    pub mouse: Vec2,
+    pub left: bool,
+    pub middle: bool,
+    pub right: bool,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a press or release, other buttons are ignored
+    pub fn set_button(&mut self, button: MouseButton, pressed: bool) {
+        match button {
+            MouseButton::Left => self.left = pressed,
+            MouseButton::Middle => self.middle = pressed,
+            MouseButton::Right => self.right = pressed,
+            _ => {}
+        }
+    }
+
+    pub fn is_pressed(&self, button: MouseButton) -> bool {
+        match button {
+            MouseButton::Left => self.left,
+            MouseButton::Middle => self.middle,
+            MouseButton::Right => self.right,
+            _ => false,
+        }
+    }
+
+    pub fn any_pressed(&self) -> bool {
+        self.left || self.middle || self.right
+    }
+}
@@ -3,26 +3,69 @@
 
 use glam::Vec2;
 
+use crate::math::{Color, Rect};
+
 /// How a component should size itself relative to its parent
-#[derive(Clone, Copy, Debug)]
+///
+/// Along a row (widths) and down a grid (row heights) `Fixed`, `Percent` and `Auto` sizes
+/// are taken first, then `Fraction`s split what is left. Across them (a component's height
+/// in its row) a `Fraction` fills the parent.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SizeSpec {
     /// Fixed size in pixels
     Fixed(f32),
-    /// Percentage of parent size (0.0 to 1.0)
+    /// Share of the parent size inside its padding (0.0 to 1.0), whatever else is in the row:
+    /// `Percent(1.0)` is as wide as the parent, two of them overflow it
     Percent(f32),
+    /// Flexible share of the space left by the other sizes, weighted by the value:
+    /// `Fraction(2.0)` gets twice as much as `Fraction(1.0)`
+    Fraction(f32),
+    /// The size the content asks for, see `GUIComponent::measure`
+    Auto,
 }
 
 impl SizeSpec {
-    pub fn compute(&self, parent_size: f32) -> f32 {
+    /// Size in a parent of `parent_size` for content measuring `content`, a `Fraction` on its
+    /// own takes the whole parent
+    pub fn compute(&self, parent_size: f32, content: f32) -> f32 {
         match self {
             SizeSpec::Fixed(px) => *px,
             SizeSpec::Percent(pct) => parent_size * pct.clamp(0.0, 1.0),
+            SizeSpec::Fraction(_) => parent_size,
+            SizeSpec::Auto => content.min(parent_size),
         }
     }
+
+    /// Sizes along one axis of (spec, content size) pairs laid out one after another in
+    /// `parent_size`, with `gaps` of it taken by spacing between them
+    pub fn distribute(sizes: &[(SizeSpec, f32)], parent_size: f32, gaps: f32) -> Vec<f32> {
+        let mut result: Vec<f32> = sizes
+            .iter()
+            .map(|&(spec, content)| match spec {
+                SizeSpec::Fraction(_) => 0.0,
+                SizeSpec::Auto => content,
+                spec => spec.compute(parent_size, content),
+            })
+            .collect();
+        let weight = |spec: SizeSpec| match spec {
+            SizeSpec::Fraction(share) => share.max(0.0),
+            _ => 0.0,
+        };
+        let total_weight: f32 = sizes.iter().map(|&(spec, _)| weight(spec)).sum();
+        if total_weight > 0.0 {
+            let remaining = (parent_size - gaps - result.iter().sum::<f32>()).max(0.0);
+            for (size, &(spec, _)) in result.iter_mut().zip(sizes) {
+                if let SizeSpec::Fraction(_) = spec {
+                    *size = remaining * weight(spec) / total_weight;
+                }
+            }
+        }
+        result
+    }
 }
 
 /// Horizontal alignment
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum HAlign {
     Left,
     Center,
@@ -30,7 +73,7 @@ pub enum HAlign {
 }
 
 /// Vertical alignment
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum VAlign {
     Top,
     Middle,
@@ -87,6 +130,66 @@ impl LayoutSpec {
     }
 }
 
+/// Layout of a whole grid row
+///
+/// Rows without one take the height, padding and margin of their first component's spec.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RowSpec {
+    pub height: SizeSpec,
+    pub padding: f32,
+    /// Space between neighbouring components
+    pub margin: f32,
+    /// Where the components go when they don't fill the row
+    pub h_align: HAlign,
+    /// Vertical alignment for every component of the row instead of their own
+    pub v_align: Option<VAlign>,
+    /// Flat color drawn under the row's components, padding included
+    pub background: Option<Color>,
+}
+
+impl RowSpec {
+    pub fn new(height: SizeSpec) -> Self {
+        RowSpec {
+            height,
+            padding: 0.0,
+            margin: 0.0,
+            h_align: HAlign::Left,
+            v_align: None,
+            background: None,
+        }
+    }
+
+    /// What a row without its own spec is laid out by
+    pub fn from_component(spec: &LayoutSpec) -> Self {
+        RowSpec::new(spec.height).with_padding(spec.padding).with_margin(spec.margin)
+    }
+
+    pub fn with_padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_h_align(mut self, h_align: HAlign) -> Self {
+        self.h_align = h_align;
+        self
+    }
+
+    pub fn with_v_align(mut self, v_align: VAlign) -> Self {
+        self.v_align = Some(v_align);
+        self
+    }
+
+    pub fn with_background(mut self, color: Color) -> Self {
+        self.background = Some(color);
+        self
+    }
+}
+
 /// Computed layout result - actual position and size
 #[derive(Clone, Copy, Debug)]
 pub struct ComputedLayout {
@@ -95,22 +198,18 @@ pub struct ComputedLayout {
 }
 
 impl ComputedLayout {
-    /// Compute layout for a single component within a parent bounds
-    pub fn compute(
-        spec: LayoutSpec,
-        parent_x: f32,
-        parent_y: f32,
-        parent_width: f32,
-        parent_height: f32,
-    ) -> Self {
-        let padded_width = (parent_width - (spec.padding * 2.0)).max(0.0);
-        let padded_height = (parent_height - (spec.padding * 2.0)).max(0.0);
-
-        let width = spec.width.compute(padded_width);
-        let height = spec.height.compute(padded_height);
-
-        let padded_x = parent_x + spec.padding;
-        let padded_y = parent_y + spec.padding;
+    /// Bounds of the component
+    pub fn rect(&self) -> Rect {
+        Rect::from_center_size(self.position, self.scale)
+    }
+
+    /// Compute layout for a single component measuring `content` within a parent bounds
+    pub fn compute(spec: LayoutSpec, content: Vec2, parent: Rect) -> Self {
+        let padded = parent.inflate(-spec.padding);
+        let (padded_x, padded_y, padded_width, padded_height) = (padded.x, padded.y, padded.width, padded.height);
+
+        let width = spec.width.compute(padded_width, content.x);
+        let height = spec.height.compute(padded_height, content.y);
 
         // Compute X position based on horizontal alignment
         let x = match spec.h_align {
@@ -133,13 +232,8 @@ impl ComputedLayout {
     }
 
     /// Compute layout for multiple components in a row with margins between them
-    pub fn compute_row(
-        specs: &[LayoutSpec],
-        parent_x: f32,
-        parent_y: f32,
-        parent_width: f32,
-        parent_height: f32,
-    ) -> Vec<ComputedLayout> {
+    /// `content` holds what each component measures, for `SizeSpec::Auto`.
+    pub fn compute_row(row: RowSpec, specs: &[LayoutSpec], content: &[Vec2], parent: Rect) -> Vec<ComputedLayout> {
         if specs.is_empty() {
             return Vec::new();
         }
@@ -147,40 +241,34 @@ impl ComputedLayout {
         let num_components = specs.len() as f32;
         let mut result = Vec::with_capacity(specs.len());
 
-        // Use the first spec's padding/margin as row-level values
-        let first_spec = specs[0];
-        let padded_x = parent_x + first_spec.padding;
-        let padded_width = (parent_width - (first_spec.padding * 2.0)).max(0.0);
-        let padded_height = (parent_height - (first_spec.padding * 2.0)).max(0.0);
-        let padded_y = parent_y + first_spec.padding;
-
-        // Account for margins between components
-        let total_margin_space = first_spec.margin * (num_components - 1.0);
-        let available_width = (padded_width - total_margin_space).max(0.0);
-
-        // Pre-calculate all component widths
-        let mut component_widths = Vec::new();
-        for spec in specs.iter() {
-            // For percentage specs, compute relative to the full available width
-            let width = match spec.width {
-                SizeSpec::Percent(pct) => available_width * pct.clamp(0.0, 1.0),
-                SizeSpec::Fixed(px) => px,
-            };
-            component_widths.push(width);
-        }
+        let padded = parent.inflate(-row.padding);
+        let (padded_x, padded_y, padded_width, padded_height) = (padded.x, padded.y, padded.width, padded.height);
+        let content_size = |i: usize| content.get(i).copied().unwrap_or(Vec2::ZERO);
 
-        // Position components with proper centering and spacing
+        // Percentages are of the padded width, fractions share what the margins and other widths leave
+        let total_margin_space = row.margin * (num_components - 1.0);
+        let widths: Vec<(SizeSpec, f32)> = specs.iter().enumerate().map(|(i, spec)| (spec.width, content_size(i).x)).collect();
+        let component_widths = SizeSpec::distribute(&widths, padded_width, total_margin_space);
+
+        // Components stay together, the row's alignment places the group
+        let used = component_widths.iter().sum::<f32>() + total_margin_space;
+        let free = (padded_width - used).max(0.0);
+        let mut left = padded_x + match row.h_align {
+            HAlign::Left => 0.0,
+            HAlign::Center => free / 2.0,
+            HAlign::Right => free,
+        };
+
+        // Position components left to right with margins between them
         for (i, spec) in specs.iter().enumerate() {
             let width = component_widths[i];
-            let height = spec.height.compute(padded_height);
+            let height = spec.height.compute(padded_height, content_size(i).y);
 
-            // Position each component with margins between them
-            let x = padded_x
-                + (i as f32 * (width + first_spec.margin))
-                + (width / 2.0);
+            let x = left + width / 2.0;
+            left += width + row.margin;
 
             // Vertical alignment (Y=0 at bottom, increases upward)
-            let y = match spec.v_align {
+            let y = match row.v_align.unwrap_or(spec.v_align) {
                 VAlign::Top => padded_y + padded_height - height / 2.0,
                 VAlign::Middle => padded_y + padded_height / 2.0,
                 VAlign::Bottom => padded_y + height / 2.0,
@@ -195,3 +283,63 @@ impl ComputedLayout {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row_widths(row: RowSpec, specs: &[LayoutSpec], content: &[Vec2], width: f32) -> Vec<f32> {
+        ComputedLayout::compute_row(row, specs, content, Rect::new(0.0, 0.0, width, 20.0))
+            .iter()
+            .map(|layout| layout.scale.x)
+            .collect()
+    }
+
+    #[test]
+    fn mixed_widths_in_a_row() {
+        let specs = [
+            LayoutSpec::new(SizeSpec::Fixed(100.0), SizeSpec::Percent(1.0)),
+            LayoutSpec::new(SizeSpec::Percent(0.25), SizeSpec::Percent(1.0)),
+            LayoutSpec::new(SizeSpec::Fraction(1.0), SizeSpec::Percent(1.0)),
+        ];
+        // 400 wide: 100 fixed, 25% of 400, the fraction gets the 200 left
+        assert_eq!(row_widths(RowSpec::new(SizeSpec::Fixed(20.0)), &specs, &[], 400.0), vec![100.0, 100.0, 200.0]);
+
+        // Margins are taken before fractions, percentages still use the padded width
+        let row = RowSpec::new(SizeSpec::Fixed(20.0)).with_padding(10.0).with_margin(10.0);
+        assert_eq!(row_widths(row, &specs, &[], 420.0), vec![100.0, 100.0, 180.0]);
+    }
+
+    #[test]
+    fn fractions_share_the_leftover_by_weight() {
+        let sizes = [(SizeSpec::Fixed(100.0), 0.0), (SizeSpec::Fraction(1.0), 0.0), (SizeSpec::Fraction(3.0), 0.0)];
+        assert_eq!(SizeSpec::distribute(&sizes, 500.0, 0.0), vec![100.0, 100.0, 300.0]);
+        assert_eq!(SizeSpec::distribute(&sizes, 500.0, 100.0), vec![100.0, 75.0, 225.0]);
+        // Nothing left is not negative
+        assert_eq!(SizeSpec::distribute(&sizes, 50.0, 0.0), vec![100.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn auto_uses_content_size() {
+        let sizes = [(SizeSpec::Auto, 120.0), (SizeSpec::Fraction(1.0), 0.0)];
+        assert_eq!(SizeSpec::distribute(&sizes, 300.0, 0.0), vec![120.0, 180.0]);
+
+        let specs = [
+            LayoutSpec::new(SizeSpec::Auto, SizeSpec::Auto),
+            LayoutSpec::new(SizeSpec::Auto, SizeSpec::Auto),
+        ];
+        let content = [Vec2::new(30.0, 12.0), Vec2::new(50.0, 40.0)];
+        let layouts = ComputedLayout::compute_row(RowSpec::new(SizeSpec::Fixed(20.0)), &specs, &content, Rect::new(0.0, 0.0, 200.0, 20.0));
+        assert_eq!(layouts[0].scale, Vec2::new(30.0, 12.0));
+        // Heights are clamped to the row
+        assert_eq!(layouts[1].scale, Vec2::new(50.0, 20.0));
+        assert_eq!(layouts[1].rect().x, 30.0);
+    }
+
+    #[test]
+    fn single_component_in_parent() {
+        let spec = LayoutSpec::new(SizeSpec::Percent(0.5), SizeSpec::Fixed(10.0)).with_alignment(HAlign::Left, VAlign::Top);
+        let layout = ComputedLayout::compute(spec, Vec2::ZERO, Rect::new(0.0, 0.0, 200.0, 100.0));
+        assert_eq!(layout.rect(), Rect::new(0.0, 90.0, 100.0, 10.0));
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::Result;
+use ash::vk;
+use std::sync::Arc;
+use crate::gui::{Color, GUIComponent, Grid, LayoutSpec, PanelComponent, Rect, TextComponent, VAlign};
+use crate::renderer::{DebugLines, FontAtlas, RenderContext, Renderer, VulkanContext};
+use glam::Vec2;
+
+const ROW_COLOR: [f32; 3] = [0.35, 0.35, 0.4];
+const PADDING_COLOR: [f32; 3] = [0.3, 0.8, 0.4];
+const MARGIN_COLOR: [f32; 3] = [0.95, 0.6, 0.2];
+const BOUNDS_COLOR: [f32; 3] = [0.3, 0.6, 1.0];
+const ANCHOR_COLOR: [f32; 3] = [0.95, 0.3, 0.9];
+const HOVER_COLOR: [f32; 3] = [1.0, 0.9, 0.2];
+/// Half the size of the cross marking an alignment anchor
+const ANCHOR_SIZE: f32 = 3.0;
+/// Space between the pointer and the info box, and around the info text
+const INFO_OFFSET: f32 = 16.0;
+const INFO_PADDING: f32 = 6.0;
+const INFO_LINES: usize = 4;
+
+/// A component found in the grid tree with the spec it was laid out by
+struct InspectedWidget {
+    /// Row and component index through nested grids, e.g. "1.0/0.2"
+    path: String,
+    spec: LayoutSpec,
+    rect: Rect,
+    /// Alignment the component was placed by, its row's when that overrides its own
+    v_align: VAlign,
+    /// Index into `LayoutInspector::rows`
+    row: usize,
+}
+
+/// A laid out grid row with the space its spec leaves around the components
+struct InspectedRow {
+    rect: Rect,
+    padding: f32,
+    /// Gaps between neighbouring components
+    margins: Vec<Rect>,
+}
+
+/// Debug overlay outlining how the UI was laid out, like the element inspector of a browser
+///
+/// Shows every grid row with its padding, the margins between components, component bounds
+/// and the anchor each one is vertically aligned to. The component under the pointer is
+/// highlighted with its `LayoutSpec` and computed rect next to it. Components in nested
+/// grids are included, see `GUIComponent::nested_grid`.
+///
+/// Enable with `UISystem::set_layout_inspector`, then toggle it with `toggle_visible`.
+pub struct LayoutInspector {
+    visible: bool,
+    lines: DebugLines,
+    background: PanelComponent,
+    info: Vec<TextComponent>,
+    widgets: Vec<InspectedWidget>,
+    rows: Vec<InspectedRow>,
+    /// Index into `widgets` under the pointer
+    hovered: Option<usize>,
+    pointer: Option<Vec2>,
+    /// Rects and hover the lines were built for, `None` after a visibility change
+    built: Option<(Vec<Rect>, Option<Vec2>)>,
+    /// Area covered by the overlay the last time it was built
+    bounds: Rect,
+    damaged: bool,
+}
+
+impl LayoutInspector {
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<Self> {
+        let info = (0..INFO_LINES)
+            .map(|_| TextComponent::new("-", font_atlas.clone(), font_size, descriptor_set_layout, context))
+            .collect::<Result<_>>()?;
+        Ok(LayoutInspector {
+            visible: false,
+            lines: DebugLines::for_ui(context.clone()),
+            background: PanelComponent::new(context, Color::srgb(0.05, 0.05, 0.07))?,
+            info,
+            widgets: Vec::new(),
+            rows: Vec::new(),
+            hovered: None,
+            pointer: None,
+            built: None,
+            bounds: Rect::default(),
+            damaged: false,
+        })
+    }
+
+    pub fn toggle_visible(&mut self) {
+        self.set_visible(!self.visible);
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        if visible != self.visible {
+            self.visible = visible;
+            self.built = None;
+            self.damaged = true;
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Pointer position in UI units, picks the component to describe
+    /// Damages the overlay while visible so on-demand applications redraw and `refresh` runs.
+    pub fn set_pointer(&mut self, point: Vec2) {
+        self.damaged |= self.visible && self.pointer != Some(point);
+        self.pointer = Some(point);
+    }
+
+    /// Walk the grid again and rebuild the outlines and info when the layout or hover changed
+    /// Called by `UISystem::update_geometry`
+    pub fn refresh(&mut self, grid: &Grid, context: &Arc<VulkanContext>) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.widgets.clear();
+        self.rows.clear();
+        self.collect(grid, "");
+
+        // The last match is the innermost, nested grids come after their container
+        let pointer = self.pointer;
+        self.hovered = pointer.and_then(|point| self.widgets.iter().rposition(|w| w.rect.contains_point(point)));
+        let rects: Vec<Rect> = self.widgets.iter().map(|w| w.rect).collect();
+        // Rebuilt on every move over a component, the info box follows the pointer
+        let key = (rects, self.hovered.and(pointer));
+        if self.built.as_ref() == Some(&key) {
+            return Ok(());
+        }
+        self.build_lines()?;
+        self.build_info(context, grid.bounds().unwrap_or_default())?;
+        self.built = Some(key);
+        self.damaged = true;
+        Ok(())
+    }
+
+    fn collect(&mut self, grid: &Grid, prefix: &str) {
+        for (row_index, row) in grid.rows.iter().enumerate() {
+            if row.components.is_empty() {
+                continue;
+            }
+            let row_spec = row.row_spec();
+            let (padding, margin) = (row_spec.padding, row_spec.margin);
+            let padded = row.rect().inflate(-padding);
+            let rects: Vec<Rect> = row.components.iter().map(|c| c.transform().rect()).collect();
+            let margins = if margin > 0.0 {
+                rects.windows(2).map(|pair| Rect::new(pair[0].max().x, padded.y, margin, padded.height)).collect()
+            } else {
+                Vec::new()
+            };
+            self.rows.push(InspectedRow { rect: row.rect(), padding, margins });
+            let inspected_row = self.rows.len() - 1;
+
+            for (index, (component, spec)) in row.components.iter().zip(&row.layout_specs).enumerate() {
+                let path = format!("{}{}.{}", prefix, row_index, index);
+                self.widgets.push(InspectedWidget {
+                    path: path.clone(),
+                    spec: *spec,
+                    rect: component.transform().rect(),
+                    v_align: row_spec.v_align.unwrap_or(spec.v_align),
+                    row: inspected_row,
+                });
+                if let Some(nested) = component.nested_grid() {
+                    self.collect(nested, &format!("{}/", path));
+                }
+            }
+        }
+    }
+
+    fn build_lines(&mut self) -> Result<()> {
+        self.lines.clear();
+        for row in &self.rows {
+            self.lines.rect(row.rect.center(), row.rect.size(), ROW_COLOR);
+            if row.padding > 0.0 {
+                let padded = row.rect.inflate(-row.padding);
+                self.lines.rect(padded.center(), padded.size(), PADDING_COLOR);
+            }
+            for gap in &row.margins {
+                self.lines.rect(gap.center(), gap.size(), MARGIN_COLOR);
+            }
+        }
+        for widget in &self.widgets {
+            self.lines.rect(widget.rect.center(), widget.rect.size(), BOUNDS_COLOR);
+
+            // Components of a row are placed left to right, only the vertical alignment picks a point
+            let padded = self.rows[widget.row].rect.inflate(-self.rows[widget.row].padding);
+            let anchor = Vec2::new(widget.rect.center().x, match widget.v_align {
+                VAlign::Top => padded.max().y,
+                VAlign::Middle => padded.center().y,
+                VAlign::Bottom => padded.min().y,
+            });
+            self.lines.line(anchor - Vec2::X * ANCHOR_SIZE, anchor + Vec2::X * ANCHOR_SIZE, ANCHOR_COLOR);
+            self.lines.line(anchor - Vec2::Y * ANCHOR_SIZE, anchor + Vec2::Y * ANCHOR_SIZE, ANCHOR_COLOR);
+        }
+        // Last so no other outline covers it
+        if let Some(widget) = self.hovered.map(|i| &self.widgets[i]) {
+            self.lines.rect(widget.rect.center(), widget.rect.size(), HOVER_COLOR);
+        }
+        self.lines.upload()
+    }
+
+    /// Describe the hovered widget in a box next to the pointer, kept inside `screen`
+    fn build_info(&mut self, context: &Arc<VulkanContext>, screen: Rect) -> Result<()> {
+        self.bounds = screen;
+        let (Some(widget), Some(pointer)) = (self.hovered.map(|i| &self.widgets[i]), self.pointer) else {
+            return Ok(());
+        };
+        let spec = widget.spec;
+        let rect = widget.rect;
+        let text = [
+            format!("Component {}", widget.path),
+            format!("Size {:?} x {:?}", spec.width, spec.height),
+            format!("Align {:?} {:?}  padding {}  margin {}", spec.h_align, spec.v_align, spec.padding, spec.margin),
+            format!("Rect {:.1}, {:.1}  {:.1} x {:.1}", rect.x, rect.y, rect.width, rect.height),
+        ];
+        for (line, text) in self.info.iter_mut().zip(&text) {
+            line.update_text(text, context)?;
+        }
+
+        let line_height = self.info.iter().map(TextComponent::get_height).fold(0.0, f32::max) + 2.0;
+        let width = self.info.iter().map(TextComponent::get_width).fold(0.0, f32::max) + INFO_PADDING * 2.0;
+        let height = line_height * INFO_LINES as f32 + INFO_PADDING * 2.0;
+        // Below right of the pointer, flipped where it would leave the screen
+        let mut min = Vec2::new(pointer.x + INFO_OFFSET, pointer.y - INFO_OFFSET - height);
+        if min.x + width > screen.max().x {
+            min.x = pointer.x - INFO_OFFSET - width;
+        }
+        if min.y < screen.min().y {
+            min.y = pointer.y + INFO_OFFSET;
+        }
+        let info_rect = Rect::new(min.x, min.y, width, height);
+        self.background.set_layout(info_rect);
+
+        let mut y = info_rect.max().y - INFO_PADDING - line_height / 2.0;
+        for line in &mut self.info {
+            line.set_position(Vec2::new(min.x + INFO_PADDING + line.get_width() / 2.0, y));
+            y -= line_height;
+        }
+        self.bounds = screen.union(&info_rect);
+        Ok(())
+    }
+
+    /// Draw the outlines and info box, over everything else
+    pub fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        let projection = renderer.projection;
+        self.lines.draw(ctx, renderer, projection)?;
+        if self.hovered.is_some() {
+            self.background.render(ctx, renderer)?;
+            for line in &self.info {
+                line.render(ctx, renderer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The whole overlay area when it was rebuilt or toggled since the last call
+    pub fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then_some(self.bounds)
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        self.lines.destroy(device);
+        self.background.destroy(device);
+        for line in &self.info {
+            line.destroy(device);
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use anyhow::Result;
+use ash::vk;
+use glam::{Mat4, Vec2, Vec3};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use crate::ecs::{entity_bounds, Light2D, Lighting2D, ShadowCaster, World};
+use crate::gui::viewport::image_quad;
+use crate::math::{Color, Rect, Transform};
+use crate::renderer::{
+    ColorVertex2D, Light2DPushConstants, Mesh, PipelineId, PushConstants2D, RenderFrame, Renderer, SampledTexture, SamplerConfig,
+    TexturedVertex2D, Texture, VertexBuffer, VulkanContext,
+};
+
+/// Shadow geometry buffers are kept alive for this many frames, longer than any frame stays in flight
+const BUFFERS_KEPT: usize = 3;
+
+/// A light of the frame and its shadow geometry in the frame's vertex buffer
+struct LightDraw {
+    position: Vec2,
+    light: Light2D,
+    /// First vertex and vertex count of its shadows, no shadows when the count is 0
+    shadows: (u32, u32),
+}
+
+/// Color and descriptor set of one of the effect's targets
+struct LightTarget {
+    texture: Texture,
+    sampled: SampledTexture,
+}
+
+impl LightTarget {
+    fn destroy(&self, device: &ash::Device) {
+        self.sampled.destroy(device);
+        self.texture.destroy(device);
+    }
+}
+
+/// 2D lighting applied to a render target after the scene was drawn into it
+///
+/// Lights are added one by one into a light map cleared to the `Lighting2D` ambient, which
+/// is then multiplied onto the target. Before each shadowed light, the shadows of the
+/// `ShadowCaster`s around it are drawn into a mask the light is multiplied by: the edges of
+/// each caster's quad facing away from the light are stretched out past its radius, and
+/// lights with a `source_radius` add a penumbra fin along both sides of the shadow that fades
+/// from dark to lit. The maps are created the first time `apply` runs and recreated when the
+/// target's size changes.
+pub struct Lighting2DEffect {
+    context: Arc<VulkanContext>,
+    /// Unit quad sampling a whole texture, also the quad each light is drawn with
+    image_quad: Mesh<TexturedVertex2D>,
+    light_map: Option<LightTarget>,
+    shadow_mask: Option<LightTarget>,
+    /// Shadow geometry of recent frames that may still be read by the GPU
+    buffers: VecDeque<VertexBuffer<ColorVertex2D>>,
+    vertices: Vec<ColorVertex2D>,
+}
+
+impl Lighting2DEffect {
+    pub fn new(context: &Arc<VulkanContext>) -> Result<Self> {
+        Ok(Lighting2DEffect {
+            context: Arc::clone(context),
+            image_quad: image_quad(context)?,
+            light_map: None,
+            shadow_mask: None,
+            buffers: VecDeque::with_capacity(BUFFERS_KEPT + 1),
+            vertices: Vec::new(),
+        })
+    }
+
+    /// Light `target` with the World's `Light2D`s, does nothing without a `Lighting2D` resource
+    /// `target` must have been rendered this frame through `view_projection`, with the
+    /// renderer's color format. Lights reaching outside a `visible` world rect are skipped.
+    pub fn apply(
+        &mut self,
+        frame: &RenderFrame,
+        renderer: &mut Renderer,
+        world: &World,
+        target: &Texture,
+        view_projection: Mat4,
+        visible: Option<Rect>,
+    ) -> Result<()> {
+        let Some(lighting) = world.resource::<Lighting2D>() else {
+            return Ok(());
+        };
+        if !target.is_rendered() {
+            return Ok(());
+        }
+        let shadow_pipeline = renderer.get_pipeline(PipelineId::Shadow2D)?;
+        let shadow_layout = renderer.get_pipeline_layout(PipelineId::Shadow2D)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for Shadow2D pipeline"))?;
+        let light_pipeline = renderer.get_pipeline(PipelineId::Light2D)?;
+        let light_layout = renderer.get_pipeline_layout(PipelineId::Light2D)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for Light2D pipeline"))?;
+        let composite_pipeline = renderer.get_pipeline(PipelineId::LightComposite)?;
+        let composite_layout = renderer.get_pipeline_layout(PipelineId::LightComposite)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for LightComposite pipeline"))?;
+        let descriptor_set_layout = renderer.get_descriptor_set_layout(PipelineId::Light2D)
+            .ok_or_else(|| anyhow::anyhow!("Light2D pipeline should have a descriptor set layout"))?;
+        self.prepare(target, renderer.color_format(), descriptor_set_layout)?;
+        let lights = self.build_shadows(world, visible);
+        self.upload()?;
+        let buffer = self.buffers.back().filter(|_| !self.vertices.is_empty());
+        let (Some(light_map), Some(shadow_mask)) = (&self.light_map, &self.shadow_mask) else {
+            return Ok(());
+        };
+
+        let image_quad = &self.image_quad;
+        frame.render_to_texture(&light_map.texture, lighting.ambient.with_alpha(1.0), |_| Ok(()))?;
+        let texel_size = [1.0 / target.width as f32, 1.0 / target.height as f32];
+        for draw in &lights {
+            let (first, count) = draw.shadows;
+            let shadow_buffer = buffer.filter(|_| count > 0);
+            if let Some(buffer) = shadow_buffer {
+                frame.render_to_texture(&shadow_mask.texture, Color::WHITE, |ctx| {
+                    ctx.bind_pipeline(shadow_pipeline);
+                    ctx.push(shadow_layout, &PushConstants2D::new(view_projection, Mat4::IDENTITY));
+                    ctx.bind_vertex_buffer(buffer.buffer);
+                    ctx.draw(count, 1, first, 0);
+                    Ok(())
+                })?;
+            }
+
+            let transform = Mat4::from_translation(draw.position.extend(0.0)) * Mat4::from_scale(Vec3::new(draw.light.radius * 2.0, draw.light.radius * 2.0, 1.0));
+            let mut push = Light2DPushConstants::new(view_projection, transform, draw.light.color.rgb(), draw.light.intensity);
+            if shadow_buffer.is_some() {
+                push = push.with_shadows(texel_size);
+            }
+            frame.render_to_targets(&[(&light_map.texture, None)], |ctx| {
+                ctx.bind_pipeline(light_pipeline);
+                // Bound even when unshadowed, the layout has the mask's set
+                ctx.bind_descriptor_set_at(light_layout, 0, shadow_mask.sampled.descriptor_set);
+                ctx.push(light_layout, &push);
+                image_quad.draw(ctx)
+            })?;
+        }
+
+        frame.render_to_targets(&[(target, None)], |ctx| {
+            ctx.bind_pipeline(composite_pipeline);
+            ctx.bind_descriptor_set_at(composite_layout, 0, light_map.sampled.descriptor_set);
+            ctx.push(composite_layout, &PushConstants2D::new(Mat4::orthographic_rh(-0.5, 0.5, -0.5, 0.5, -1.0, 1.0), Mat4::IDENTITY));
+            image_quad.draw(ctx)
+        })
+    }
+
+    /// Lights to draw this frame, with the shadow geometry of each queued into `vertices`
+    fn build_shadows(&mut self, world: &World, visible: Option<Rect>) -> Vec<LightDraw> {
+        let casters: Vec<([Vec2; 4], Rect)> = world
+            .entity_ids()
+            .filter(|&id| world.has::<ShadowCaster>(id) && world.has::<Transform>(id))
+            .map(|id| (caster_corners(world.world_matrix(id)), entity_bounds(world, id)))
+            .collect();
+
+        self.vertices.clear();
+        let mut lights = Vec::new();
+        for id in world.entity_ids() {
+            let Some(light) = world.get::<Light2D>(id) else {
+                continue;
+            };
+            if !world.has::<Transform>(id) || light.radius <= 0.0 || light.intensity <= 0.0 {
+                continue;
+            }
+            let position = world.world_matrix(id).w_axis.truncate().truncate();
+            let reach = Rect::from_center_size(position, Vec2::splat(light.radius * 2.0));
+            if visible.is_some_and(|visible| !overlaps(&visible, &reach)) {
+                continue;
+            }
+            let first = self.vertices.len() as u32;
+            if light.casts_shadows {
+                for (corners, bounds) in &casters {
+                    if overlaps(bounds, &reach) {
+                        shadow_geometry(position, light.radius, light.source_radius, corners, &mut self.vertices);
+                    }
+                }
+            }
+            let count = self.vertices.len() as u32 - first;
+            lights.push(LightDraw { position, light: light.clone(), shadows: (first, count) });
+        }
+        lights
+    }
+
+    /// Copy the queued shadow geometry into a new buffer, if there is any
+    fn upload(&mut self) -> Result<()> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+        let buffer = VertexBuffer::new(&self.context.device, self.context.physical_device, &self.context.instance, &self.vertices)?;
+        self.buffers.push_back(buffer);
+        while self.buffers.len() > BUFFERS_KEPT {
+            if let Some(old) = self.buffers.pop_front() {
+                old.destroy(&self.context.device);
+            }
+        }
+        Ok(())
+    }
+
+    /// Recreate the light map and shadow mask when the target's size changed
+    fn prepare(&mut self, target: &Texture, color_format: vk::Format, descriptor_set_layout: vk::DescriptorSetLayout) -> Result<()> {
+        let resized = self.light_map.as_ref().is_none_or(|map| map.texture.width != target.width || map.texture.height != target.height);
+        if !resized {
+            return Ok(());
+        }
+        let context = &self.context;
+        // The old maps may still be used by frames in flight
+        unsafe {
+            let _ = context.device.device_wait_idle();
+        }
+        for map in self.light_map.take().into_iter().chain(self.shadow_mask.take()) {
+            map.destroy(&context.device);
+        }
+        let create = || -> Result<LightTarget> {
+            let texture = Texture::render_target(target.width, target.height, color_format, &context.device, &context.instance, context.physical_device)?;
+            let sampled = SampledTexture::new(&texture, SamplerConfig::linear(), descriptor_set_layout, context)?;
+            Ok(LightTarget { texture, sampled })
+        };
+        self.light_map = Some(create()?);
+        self.shadow_mask = Some(create()?);
+        Ok(())
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        self.image_quad.destroy(device);
+        for map in self.light_map.iter().chain(&self.shadow_mask) {
+            map.destroy(device);
+        }
+        for buffer in &self.buffers {
+            buffer.destroy(device);
+        }
+    }
+}
+
+/// Corners of an entity's unit quad in world space, counter-clockwise
+fn caster_corners(matrix: Mat4) -> [Vec2; 4] {
+    let mut corners = [Vec2::new(-0.5, -0.5), Vec2::new(0.5, -0.5), Vec2::new(0.5, 0.5), Vec2::new(-0.5, 0.5)]
+        .map(|corner| matrix.transform_point3(corner.extend(0.0)).truncate());
+    // A negative scale mirrors the quad and turns it clockwise
+    if (corners[1] - corners[0]).perp_dot(corners[2] - corners[0]) < 0.0 {
+        corners.reverse();
+    }
+    corners
+}
+
+/// Append the triangles of the shadow a convex `occluder` (corners counter-clockwise) casts
+/// from a light at `light`, black where the light is blocked
+///
+/// Only the edges facing away from the light are stretched out so the caster's lit side
+/// stays lit. With a `source_radius` a fin is added at both silhouette corners, widening the
+/// shadow by the angle the source covers from there and fading from dark to lit across it.
+fn shadow_geometry(light: Vec2, radius: f32, source_radius: f32, occluder: &[Vec2], vertices: &mut Vec<ColorVertex2D>) {
+    const DARK: [f32; 3] = [0.0; 3];
+    const LIT: [f32; 3] = [1.0; 3];
+    let n = occluder.len();
+    let faces_away = |i: usize| (occluder[(i + 1) % n] - occluder[i]).perp_dot(light - occluder[i]) > 0.0;
+    // The light is inside the occluder
+    if (0..n).all(faces_away) {
+        return;
+    }
+
+    // Well past the light's reach from any point within it
+    let reach = radius * 2.0;
+    let far = |p: Vec2| p + (p - light).normalize_or_zero() * reach;
+    let mut push = |positions: [Vec2; 3], colors: [[f32; 3]; 3]| {
+        for (position, color) in positions.into_iter().zip(colors) {
+            vertices.push(ColorVertex2D { position: position.into(), color });
+        }
+    };
+    for i in (0..n).filter(|&i| faces_away(i)) {
+        let (a, b) = (occluder[i], occluder[(i + 1) % n]);
+        push([a, b, far(b)], [DARK; 3]);
+        push([a, far(b), far(a)], [DARK; 3]);
+    }
+
+    if source_radius <= 0.0 {
+        return;
+    }
+    let center = occluder.iter().copied().sum::<Vec2>() / n as f32;
+    for (i, &p) in occluder.iter().enumerate() {
+        // Silhouette corners are between an edge facing the light and one facing away
+        if faces_away((i + n - 1) % n) == faces_away(i) {
+            continue;
+        }
+        let Some(direction) = (p - light).try_normalize() else {
+            continue;
+        };
+        // Turn away from the occluder's side of the shadow edge
+        let away = -(p - light).perp_dot(center - light).signum();
+        let angle = (source_radius / p.distance(light)).atan() * away;
+        let outer = Vec2::from_angle(angle).rotate(direction);
+        push([p, p + direction * reach, p + outer * reach], [DARK, DARK, LIT]);
+    }
+}
+
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.min().cmple(b.max()).all() && b.min().cmple(a.max()).all()
+}
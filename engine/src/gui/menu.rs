@@ -0,0 +1,925 @@
+use anyhow::Result;
+use ash::vk;
+use glam::Vec2;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use winit::keyboard::{Key, NamedKey};
+
+use crate::gui::{AccessTree, Color, GUIComponent, PanelComponent, Rect, TextComponent, Transform};
+use accesskit::{DefaultActionVerb, HasPopup, NodeBuilder, Role};
+use crate::renderer::{FontAtlas, RenderContext, Renderer, VulkanContext};
+
+/// Space left and right of item text, and around menu bar titles
+const PADDING: f32 = 8.0;
+/// Space between the longest label and the shortcut column
+const SHORTCUT_GAP: f32 = 24.0;
+/// Column for the submenu arrow
+const ARROW_WIDTH: f32 = 14.0;
+const SEPARATOR_HEIGHT: f32 = 7.0;
+/// Popups replaced by a new context menu are kept alive this many opens, longer than any frame stays in flight
+const POPUPS_KEPT: usize = 3;
+
+fn background_color() -> Color {
+    Color::srgb(0.12, 0.12, 0.15)
+}
+
+fn highlight_color() -> Color {
+    Color::srgb(0.25, 0.35, 0.6)
+}
+
+fn text_color(enabled: bool) -> Color {
+    if enabled { Color::srgb(0.9, 0.9, 0.9) } else { Color::srgb(0.45, 0.45, 0.5) }
+}
+
+/// Entry of a menu bar menu or context menu
+#[derive(Clone, Debug, PartialEq)]
+pub struct MenuItem {
+    pub label: String,
+    /// Reported by `take_activated` when the item is chosen
+    pub action: Option<String>,
+    /// Key combination shown next to the label, e.g. "Ctrl+Z". Only a hint, the
+    /// application handles the shortcut itself.
+    pub shortcut: Option<String>,
+    pub enabled: bool,
+    /// Items of the nested menu opened by this one
+    pub submenu: Vec<MenuItem>,
+    pub separator: bool,
+}
+
+impl MenuItem {
+    /// Item reporting `action` when chosen
+    pub fn action(label: &str, action: &str) -> Self {
+        MenuItem {
+            label: label.to_string(),
+            action: Some(action.to_string()),
+            shortcut: None,
+            enabled: true,
+            submenu: Vec::new(),
+            separator: false,
+        }
+    }
+
+    /// Item opening a nested menu
+    pub fn submenu(label: &str, items: Vec<MenuItem>) -> Self {
+        MenuItem {
+            label: label.to_string(),
+            action: None,
+            shortcut: None,
+            enabled: true,
+            submenu: items,
+            separator: false,
+        }
+    }
+
+    /// Line between groups of items
+    pub fn separator() -> Self {
+        MenuItem {
+            label: String::new(),
+            action: None,
+            shortcut: None,
+            enabled: false,
+            submenu: Vec::new(),
+            separator: true,
+        }
+    }
+
+    pub fn with_shortcut(mut self, shortcut: &str) -> Self {
+        self.shortcut = Some(shortcut.to_string());
+        self
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+}
+
+/// What an open popup did with a click or key
+#[derive(Clone, Debug, PartialEq)]
+enum MenuEvent {
+    Handled,
+    /// An item with an action was chosen, the menu should close
+    Activated(String),
+    /// Escape, the innermost open menu should close
+    Close,
+    /// Not for this popup: a click outside or a key the parent may use (left/right)
+    Unhandled,
+}
+
+struct PopupEntry {
+    item: MenuItem,
+    label: Option<TextComponent>,
+    shortcut: Option<TextComponent>,
+    arrow: Option<TextComponent>,
+    separator: Option<PanelComponent>,
+    submenu: Option<Box<MenuPopup>>,
+    rect: Rect,
+}
+
+/// List of items shown over the rest of the UI, with nested submenus
+///
+/// Text meshes for the whole tree are built up front, opening only moves them.
+struct MenuPopup {
+    entries: Vec<PopupEntry>,
+    background: PanelComponent,
+    highlight: PanelComponent,
+    size: Vec2,
+    item_height: f32,
+    /// Width of the arrow column, zero without submenus
+    arrow_space: f32,
+    rect: Rect,
+    /// Area the popup is kept inside of, passed on to submenus
+    screen: Option<Rect>,
+    is_open: bool,
+    highlighted: Option<usize>,
+    open_submenu: Option<usize>,
+}
+
+impl MenuPopup {
+    fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: &Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        items: &[MenuItem],
+    ) -> Result<Self> {
+        let text = |text: &str, color: Color| -> Result<TextComponent> {
+            let mut text = TextComponent::new(text, font_atlas.clone(), font_size, descriptor_set_layout, context)?;
+            text.set_color(color);
+            Ok(text)
+        };
+
+        let mut entries = Vec::with_capacity(items.len());
+        let (mut label_width, mut shortcut_width, mut has_submenu) = (0.0f32, 0.0f32, false);
+        for item in items {
+            let mut entry = PopupEntry {
+                item: item.clone(),
+                label: None,
+                shortcut: None,
+                arrow: None,
+                separator: None,
+                submenu: None,
+                rect: Rect::default(),
+            };
+            if item.separator {
+                entry.separator = Some(PanelComponent::new(context, Color::srgb(0.3, 0.3, 0.35))?);
+                entries.push(entry);
+                continue;
+            }
+            let label = text(&item.label, text_color(item.enabled))?;
+            label_width = label_width.max(label.get_width());
+            entry.label = Some(label);
+            if let Some(shortcut) = &item.shortcut {
+                let shortcut = text(shortcut, Color::srgb(0.6, 0.6, 0.65))?;
+                shortcut_width = shortcut_width.max(shortcut.get_width());
+                entry.shortcut = Some(shortcut);
+            }
+            if !item.submenu.is_empty() {
+                has_submenu = true;
+                entry.arrow = Some(text(">", text_color(item.enabled))?);
+                entry.submenu = Some(Box::new(MenuPopup::new(context, font_atlas, font_size, descriptor_set_layout, &item.submenu)?));
+            }
+            entries.push(entry);
+        }
+
+        let item_height = font_size + 8.0;
+        let arrow_space = if has_submenu { ARROW_WIDTH } else { 0.0 };
+        let shortcut_space = if shortcut_width > 0.0 { SHORTCUT_GAP + shortcut_width } else { 0.0 };
+        let height: f32 = entries.iter().map(|entry| if entry.item.separator { SEPARATOR_HEIGHT } else { item_height }).sum();
+        Ok(MenuPopup {
+            entries,
+            background: PanelComponent::new(context, background_color())?,
+            highlight: PanelComponent::new(context, highlight_color())?,
+            size: Vec2::new(PADDING * 2.0 + label_width + shortcut_space + arrow_space, height),
+            item_height,
+            arrow_space,
+            rect: Rect::default(),
+            screen: None,
+            is_open: false,
+            highlighted: None,
+            open_submenu: None,
+        })
+    }
+
+    /// Open with the top left corner at `top_left`, moved inside `screen` where it would stick out
+    fn open(&mut self, top_left: Vec2, screen: Option<Rect>) {
+        let mut min = Vec2::new(top_left.x, top_left.y - self.size.y);
+        if let Some(screen) = screen {
+            min.x = min.x.min(screen.max().x - self.size.x).max(screen.min().x);
+            min.y = min.y.max(screen.min().y).min(screen.max().y - self.size.y);
+        }
+        self.rect = Rect::new(min.x, min.y, self.size.x, self.size.y);
+        self.screen = screen;
+        self.is_open = true;
+        self.highlighted = None;
+        self.close_submenu();
+        self.layout();
+    }
+
+    fn close(&mut self) {
+        self.is_open = false;
+        self.highlighted = None;
+        self.close_submenu();
+    }
+
+    fn layout(&mut self) {
+        *self.background.transform_mut() = Transform::from_rect(self.rect);
+        let (left, right) = (self.rect.min().x, self.rect.max().x);
+        let mut top = self.rect.max().y;
+        for entry in &mut self.entries {
+            let height = if entry.item.separator { SEPARATOR_HEIGHT } else { self.item_height };
+            top -= height;
+            entry.rect = Rect::new(left, top, self.rect.width, height);
+            let center_y = top + height / 2.0;
+            if let Some(separator) = &mut entry.separator {
+                *separator.transform_mut() = Transform::from_rect(Rect::new(left + PADDING / 2.0, center_y - 0.5, self.rect.width - PADDING, 1.0));
+            }
+            if let Some(label) = &mut entry.label {
+                label.set_position(Vec2::new(left + PADDING + label.get_width() / 2.0, center_y));
+            }
+            if let Some(shortcut) = &mut entry.shortcut {
+                shortcut.set_position(Vec2::new(right - PADDING - self.arrow_space - shortcut.get_width() / 2.0, center_y));
+            }
+            if let Some(arrow) = &mut entry.arrow {
+                arrow.set_position(Vec2::new(right - PADDING - arrow.get_width() / 2.0, center_y));
+            }
+        }
+        self.layout_highlight();
+    }
+
+    fn layout_highlight(&mut self) {
+        if let Some(entry) = self.highlighted.and_then(|i| self.entries.get(i)) {
+            *self.highlight.transform_mut() = Transform::from_rect(entry.rect);
+        }
+    }
+
+    /// Area of this popup and its open submenus, `None` while closed
+    fn bounds(&self) -> Option<Rect> {
+        if !self.is_open {
+            return None;
+        }
+        let submenu = self.submenu().and_then(MenuPopup::bounds);
+        Some(submenu.map_or(self.rect, |rect| rect.union(&self.rect)))
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        self.is_open && (self.rect.contains_point(point) || self.submenu().is_some_and(|submenu| submenu.contains(point)))
+    }
+
+    fn submenu(&self) -> Option<&MenuPopup> {
+        self.entries.get(self.open_submenu?)?.submenu.as_deref()
+    }
+
+    fn submenu_mut(&mut self) -> Option<&mut MenuPopup> {
+        self.entries.get_mut(self.open_submenu?)?.submenu.as_deref_mut()
+    }
+
+    fn item_at(&self, point: Vec2) -> Option<usize> {
+        self.entries.iter().position(|entry| !entry.item.separator && entry.rect.contains_point(point))
+    }
+
+    fn is_selectable(&self, index: usize) -> bool {
+        self.entries.get(index).is_some_and(|entry| entry.item.enabled && !entry.item.separator)
+    }
+
+    fn open_submenu(&mut self, index: usize) {
+        if self.open_submenu == Some(index) {
+            return;
+        }
+        self.close_submenu();
+        let screen = self.screen;
+        let Some(entry) = self.entries.get_mut(index) else {
+            return;
+        };
+        let corner = Vec2::new(entry.rect.max().x, entry.rect.max().y);
+        if let Some(submenu) = entry.submenu.as_deref_mut() {
+            submenu.open(corner, screen);
+            self.open_submenu = Some(index);
+        }
+    }
+
+    fn close_submenu(&mut self) {
+        if let Some(submenu) = self.submenu_mut() {
+            submenu.close();
+        }
+        self.open_submenu = None;
+    }
+
+    fn highlight(&mut self, index: Option<usize>) {
+        self.highlighted = index;
+        self.layout_highlight();
+    }
+
+    fn highlight_first(&mut self) {
+        let first = (0..self.entries.len()).find(|&i| self.is_selectable(i));
+        self.highlight(first);
+    }
+
+    /// Move the highlight by `step` selectable items, wrapping around
+    fn move_highlight(&mut self, step: isize) {
+        let selectable: Vec<usize> = (0..self.entries.len()).filter(|&i| self.is_selectable(i)).collect();
+        if selectable.is_empty() {
+            return;
+        }
+        let next = match self.highlighted.and_then(|current| selectable.iter().position(|&i| i == current)) {
+            Some(position) => selectable[(position as isize + step).rem_euclid(selectable.len() as isize) as usize],
+            None if step < 0 => selectable[selectable.len() - 1],
+            None => selectable[0],
+        };
+        self.close_submenu();
+        self.highlight(Some(next));
+    }
+
+    /// Choose an item: report its action or open its submenu
+    fn activate(&mut self, index: usize) -> MenuEvent {
+        if !self.is_selectable(index) {
+            return MenuEvent::Handled;
+        }
+        self.highlight(Some(index));
+        if self.entries[index].submenu.is_some() {
+            self.open_submenu(index);
+            return MenuEvent::Handled;
+        }
+        match &self.entries[index].item.action {
+            Some(action) => MenuEvent::Activated(action.clone()),
+            None => MenuEvent::Handled,
+        }
+    }
+
+    /// Hover highlights an item and opens its submenu, returns whether anything changed
+    fn handle_move(&mut self, point: Vec2) -> bool {
+        if let Some(submenu) = self.submenu_mut() {
+            if submenu.contains(point) {
+                return submenu.handle_move(point);
+            }
+        }
+        if !self.rect.contains_point(point) {
+            return false;
+        }
+        let item = self.item_at(point).filter(|&i| self.is_selectable(i));
+        if item == self.highlighted {
+            return false;
+        }
+        self.highlight(item);
+        match item {
+            Some(i) if self.entries[i].submenu.is_some() => self.open_submenu(i),
+            _ => self.close_submenu(),
+        }
+        true
+    }
+
+    fn handle_click(&mut self, point: Vec2) -> MenuEvent {
+        if let Some(submenu) = self.submenu_mut() {
+            if submenu.contains(point) {
+                return submenu.handle_click(point);
+            }
+        }
+        if !self.rect.contains_point(point) {
+            return MenuEvent::Unhandled;
+        }
+        match self.item_at(point) {
+            Some(index) => self.activate(index),
+            None => MenuEvent::Handled,
+        }
+    }
+
+    /// Arrows move through the items and in and out of submenus, Enter chooses, Escape closes
+    fn handle_key(&mut self, key: &Key) -> MenuEvent {
+        // Keys go to a submenu once it has a highlight, one opened by hovering doesn't take them
+        if let Some(submenu) = self.submenu_mut().filter(|submenu| submenu.highlighted.is_some()) {
+            let event = submenu.handle_key(key);
+            return match event {
+                MenuEvent::Close => {
+                    self.close_submenu();
+                    MenuEvent::Handled
+                }
+                MenuEvent::Unhandled if *key == Key::Named(NamedKey::ArrowLeft) => {
+                    self.close_submenu();
+                    MenuEvent::Handled
+                }
+                event => event,
+            };
+        }
+        match key {
+            Key::Named(NamedKey::ArrowDown) => {
+                self.move_highlight(1);
+                MenuEvent::Handled
+            }
+            Key::Named(NamedKey::ArrowUp) => {
+                self.move_highlight(-1);
+                MenuEvent::Handled
+            }
+            Key::Named(NamedKey::ArrowRight | NamedKey::Enter | NamedKey::Space) => {
+                let is_right = *key == Key::Named(NamedKey::ArrowRight);
+                match self.highlighted {
+                    Some(index) if self.entries[index].submenu.is_some() => {
+                        self.open_submenu(index);
+                        if let Some(submenu) = self.submenu_mut() {
+                            submenu.highlight_first();
+                        }
+                        MenuEvent::Handled
+                    }
+                    _ if is_right => MenuEvent::Unhandled,
+                    Some(index) => self.activate(index),
+                    None => MenuEvent::Handled,
+                }
+            }
+            Key::Named(NamedKey::Escape) => MenuEvent::Close,
+            _ => MenuEvent::Unhandled,
+        }
+    }
+
+    /// Describe the open popup and its open submenu, items are chosen by a click on them
+    fn accessibility(&self, tree: &mut AccessTree) {
+        if !self.is_open {
+            return;
+        }
+        tree.push(NodeBuilder::new(Role::Menu), self.rect);
+        for (i, entry) in self.entries.iter().enumerate() {
+            if entry.item.separator {
+                continue;
+            }
+            let mut node = NodeBuilder::new(Role::MenuItem);
+            node.set_name(entry.item.label.as_str());
+            node.set_default_action_verb(DefaultActionVerb::Click);
+            if let Some(shortcut) = &entry.item.shortcut {
+                node.set_keyboard_shortcut(shortcut.as_str());
+            }
+            if !entry.item.enabled {
+                node.set_disabled();
+            }
+            if Some(i) == self.highlighted {
+                node.set_selected(true);
+            }
+            if entry.submenu.is_none() {
+                tree.add(node, entry.rect);
+                continue;
+            }
+            node.set_has_popup(HasPopup::Menu);
+            node.set_expanded(Some(i) == self.open_submenu);
+            tree.push(node, entry.rect);
+            if let Some(submenu) = entry.submenu.as_deref().filter(|_| Some(i) == self.open_submenu) {
+                submenu.accessibility(tree);
+            }
+            tree.pop();
+        }
+        tree.pop();
+    }
+
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if !self.is_open {
+            return Ok(());
+        }
+        self.background.render(ctx, renderer)?;
+        if self.highlighted.is_some() {
+            self.highlight.render(ctx, renderer)?;
+        }
+        for entry in &self.entries {
+            if let Some(separator) = &entry.separator {
+                separator.render(ctx, renderer)?;
+            }
+            for text in [&entry.label, &entry.shortcut, &entry.arrow].into_iter().flatten() {
+                text.render(ctx, renderer)?;
+            }
+        }
+        match self.submenu() {
+            Some(submenu) => submenu.render(ctx, renderer),
+            None => Ok(()),
+        }
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.background.destroy(device);
+        self.highlight.destroy(device);
+        for entry in &self.entries {
+            if let Some(separator) = &entry.separator {
+                separator.destroy(device);
+            }
+            for text in [&entry.label, &entry.shortcut, &entry.arrow].into_iter().flatten() {
+                text.destroy(device);
+            }
+            if let Some(submenu) = &entry.submenu {
+                submenu.destroy(device);
+            }
+        }
+    }
+}
+
+/// Row of menu titles (File, Edit, View...) opening dropdowns with nested submenus
+///
+/// Open menus are drawn in the overlay pass and take all mouse input until they close.
+/// With a menu open the arrow keys move through items and across menus, Enter chooses
+/// and Escape closes. Chosen actions are read with `take_activated`.
+pub struct MenuBar {
+    titles: Vec<TextComponent>,
+    menus: Vec<MenuPopup>,
+    title_rects: Vec<Rect>,
+    highlight: PanelComponent,
+    transform: Transform,
+    font_size: f32,
+    hovered: Option<usize>,
+    open: Option<usize>,
+    activated: Option<String>,
+    damage: Option<Rect>,
+}
+
+impl MenuBar {
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        menus: Vec<(&str, Vec<MenuItem>)>,
+    ) -> Result<Self> {
+        let mut titles = Vec::with_capacity(menus.len());
+        let mut popups = Vec::with_capacity(menus.len());
+        for (title, items) in &menus {
+            let mut text = TextComponent::new(title, font_atlas.clone(), font_size, descriptor_set_layout, context)?;
+            text.set_color(text_color(true));
+            titles.push(text);
+            popups.push(MenuPopup::new(context, &font_atlas, font_size, descriptor_set_layout, items)?);
+        }
+        Ok(MenuBar {
+            title_rects: vec![Rect::default(); titles.len()],
+            titles,
+            menus: popups,
+            highlight: PanelComponent::new(context, highlight_color())?,
+            transform: Transform::new(),
+            font_size,
+            hovered: None,
+            open: None,
+            activated: None,
+            damage: None,
+        })
+    }
+
+    /// Action of the item chosen since the last call
+    pub fn take_activated(&mut self) -> Option<String> {
+        self.activated.take()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// Close the open menu, if any
+    pub fn close(&mut self) {
+        self.open_menu(None);
+    }
+
+    fn layout_titles(&mut self) {
+        let rect = self.transform.rect();
+        let mut x = rect.min().x;
+        for (title, title_rect) in self.titles.iter_mut().zip(&mut self.title_rects) {
+            let width = title.get_width() + PADDING * 2.0;
+            *title_rect = Rect::new(x, rect.y, width, rect.height);
+            title.set_position(title_rect.center());
+            x += width;
+        }
+        self.layout_highlight();
+    }
+
+    fn layout_highlight(&mut self) {
+        if let Some(rect) = self.open.or(self.hovered).and_then(|i| self.title_rects.get(i)) {
+            *self.highlight.transform_mut() = Transform::from_rect(*rect);
+        }
+    }
+
+    fn title_at(&self, point: Vec2) -> Option<usize> {
+        self.title_rects.iter().position(|rect| rect.contains_point(point))
+    }
+
+    /// Open the menu of a title (closing any other) or close them all with `None`
+    fn open_menu(&mut self, index: Option<usize>) {
+        if index == self.open {
+            return;
+        }
+        let before = self.overlay_rect();
+        if let Some(menu) = self.open.and_then(|i| self.menus.get_mut(i)) {
+            menu.close();
+        }
+        self.open = index;
+        if let Some(i) = index {
+            let corner = self.title_rects[i].min();
+            self.menus[i].open(corner, None);
+        }
+        self.layout_highlight();
+        self.changed(before);
+    }
+
+    /// Damage the bar and the popups that were open (`before`) and are open now
+    fn changed(&mut self, before: Option<Rect>) {
+        let rect = [before, self.overlay_rect()].into_iter().flatten().fold(self.transform.rect(), |rect, other| rect.union(&other));
+        self.damage = Some(self.damage.map_or(rect, |damage| damage.union(&rect)));
+    }
+
+    fn finish(&mut self, event: MenuEvent) {
+        match event {
+            MenuEvent::Activated(action) => {
+                self.activated = Some(action);
+                self.open_menu(None);
+            }
+            MenuEvent::Close => self.open_menu(None),
+            MenuEvent::Handled | MenuEvent::Unhandled => {}
+        }
+    }
+}
+
+impl GUIComponent for MenuBar {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if self.open.or(self.hovered).is_some() {
+            self.highlight.render(ctx, renderer)?;
+        }
+        for title in &self.titles {
+            title.render(ctx, renderer)?;
+        }
+        Ok(())
+    }
+
+    fn render_overlay(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        match self.open.and_then(|i| self.menus.get(i)) {
+            Some(menu) => menu.render(ctx, renderer),
+            None => Ok(()),
+        }
+    }
+
+    fn overlay_rect(&self) -> Option<Rect> {
+        self.menus.get(self.open?)?.bounds()
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn set_layout(&mut self, rect: Rect) {
+        self.transform = Transform::from_rect(rect);
+        self.layout_titles();
+    }
+
+    fn measure(&self, constraints: Vec2) -> Vec2 {
+        let width: f32 = self.titles.iter().map(|title| title.get_width() + PADDING * 2.0).sum();
+        Vec2::new(width, self.font_size + 8.0).min(constraints)
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        let point = Vec2::new(x, y);
+        if let Some(title) = self.title_at(point) {
+            // Clicking the open title closes its menu
+            let index = if self.open == Some(title) { None } else { Some(title) };
+            self.open_menu(index);
+            return;
+        }
+        let Some(open) = self.open else {
+            return;
+        };
+        let before = self.overlay_rect();
+        match self.menus[open].handle_click(point) {
+            // A click outside the menus closes them
+            MenuEvent::Unhandled => self.open_menu(None),
+            event => {
+                self.changed(before);
+                self.finish(event);
+            }
+        }
+    }
+
+    fn handle_mouse_move(&mut self, x: f32, y: f32) {
+        let point = Vec2::new(x, y);
+        let title = self.title_at(point);
+        if title != self.hovered {
+            self.hovered = title;
+            self.layout_highlight();
+            self.changed(None);
+        }
+        let Some(open) = self.open else {
+            return;
+        };
+        // With a menu open, moving over another title switches to its menu
+        match title {
+            Some(title) => self.open_menu(Some(title)),
+            None => {
+                let before = self.overlay_rect();
+                if self.menus[open].handle_move(point) {
+                    self.changed(before);
+                }
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: &Key) -> bool {
+        let Some(open) = self.open else {
+            return false;
+        };
+        let before = self.overlay_rect();
+        match self.menus[open].handle_key(key) {
+            // Left and right past the outermost menu move to the neighbouring title
+            MenuEvent::Unhandled => {
+                let count = self.menus.len();
+                let next = match key {
+                    Key::Named(NamedKey::ArrowLeft) => Some((open + count - 1) % count),
+                    Key::Named(NamedKey::ArrowRight) => Some((open + 1) % count),
+                    _ => None,
+                };
+                if let Some(next) = next {
+                    self.open_menu(Some(next));
+                    self.menus[next].highlight_first();
+                }
+            }
+            event => self.finish(event),
+        }
+        self.changed(before);
+        true
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        self.damage.take()
+    }
+
+    /// Titles open their menu on a click, so the synthesized click of a default action does
+    fn accessibility(&self, tree: &mut AccessTree) {
+        tree.push(NodeBuilder::new(Role::MenuBar), self.transform.rect());
+        for (i, (title, rect)) in self.titles.iter().zip(&self.title_rects).enumerate() {
+            let mut node = NodeBuilder::new(Role::MenuItem);
+            node.set_name(title.text());
+            node.set_has_popup(HasPopup::Menu);
+            node.set_expanded(Some(i) == self.open);
+            node.set_default_action_verb(DefaultActionVerb::Open);
+            tree.push(node, *rect);
+            if Some(i) == self.open {
+                self.menus[i].accessibility(tree);
+            }
+            tree.pop();
+        }
+        tree.pop();
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        for title in &self.titles {
+            title.destroy(device);
+        }
+        for menu in &self.menus {
+            menu.destroy(device);
+        }
+        self.highlight.destroy(device);
+    }
+}
+
+/// Right-click menu layer of the UI, see `UISystem::open_context_menu`
+///
+/// Items come from the component under the pointer (`GUIComponent::context_menu`), the
+/// popup is rebuilt for every open since each component brings its own items.
+pub struct ContextMenu {
+    context: Arc<VulkanContext>,
+    font_atlas: Arc<FontAtlas>,
+    font_size: f32,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    popup: Option<MenuPopup>,
+    retired: VecDeque<MenuPopup>,
+    activated: Option<String>,
+    damage: Option<Rect>,
+}
+
+impl ContextMenu {
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Self {
+        ContextMenu {
+            context: context.clone(),
+            font_atlas,
+            font_size,
+            descriptor_set_layout,
+            popup: None,
+            retired: VecDeque::with_capacity(POPUPS_KEPT + 1),
+            activated: None,
+            damage: None,
+        }
+    }
+
+    /// Show `items` with the top left corner at `point`, kept inside `screen`
+    pub fn open(&mut self, items: &[MenuItem], point: Vec2, screen: Option<Rect>) -> Result<()> {
+        let mut popup = MenuPopup::new(&self.context, &self.font_atlas, self.font_size, self.descriptor_set_layout, items)?;
+        popup.open(point, screen);
+        let before = self.bounds();
+        if let Some(old) = self.popup.replace(popup) {
+            self.retired.push_back(old);
+        }
+        while self.retired.len() > POPUPS_KEPT {
+            if let Some(old) = self.retired.pop_front() {
+                old.destroy(&self.context.device);
+            }
+        }
+        self.changed(before);
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        let before = self.bounds();
+        if let Some(popup) = &mut self.popup {
+            popup.close();
+        }
+        self.changed(before);
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.as_ref().is_some_and(|popup| popup.is_open)
+    }
+
+    /// Area of the open menu and its submenus
+    pub fn bounds(&self) -> Option<Rect> {
+        self.popup.as_ref()?.bounds()
+    }
+
+    /// Action of the item chosen since the last call
+    pub fn take_activated(&mut self) -> Option<String> {
+        self.activated.take()
+    }
+
+    /// Area that changed since the last call
+    pub fn take_damage(&mut self) -> Option<Rect> {
+        self.damage.take()
+    }
+
+    fn changed(&mut self, before: Option<Rect>) {
+        if let Some(rect) = [before, self.bounds()].into_iter().flatten().reduce(|a, b| a.union(&b)) {
+            self.damage = Some(self.damage.map_or(rect, |damage| damage.union(&rect)));
+        }
+    }
+
+    fn finish(&mut self, event: MenuEvent) {
+        match event {
+            MenuEvent::Activated(action) => {
+                self.activated = Some(action);
+                self.close();
+            }
+            MenuEvent::Close => self.close(),
+            MenuEvent::Handled | MenuEvent::Unhandled => {}
+        }
+    }
+
+    /// A press anywhere while open: chooses an item, or closes the menu outside of it
+    pub fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        let Some(popup) = self.popup.as_mut().filter(|popup| popup.is_open) else {
+            return;
+        };
+        let before = popup.bounds();
+        match popup.handle_click(Vec2::new(x, y)) {
+            MenuEvent::Unhandled => self.close(),
+            event => {
+                self.changed(before);
+                self.finish(event);
+            }
+        }
+    }
+
+    pub fn handle_mouse_move(&mut self, x: f32, y: f32) {
+        let Some(popup) = self.popup.as_mut().filter(|popup| popup.is_open) else {
+            return;
+        };
+        let before = popup.bounds();
+        if popup.handle_move(Vec2::new(x, y)) {
+            self.changed(before);
+        }
+    }
+
+    /// Keyboard navigation, returns whether the menu was open and took the key
+    pub fn handle_key(&mut self, key: &Key) -> bool {
+        let Some(popup) = self.popup.as_mut().filter(|popup| popup.is_open) else {
+            return false;
+        };
+        let before = popup.bounds();
+        let event = popup.handle_key(key);
+        self.changed(before);
+        self.finish(event);
+        true
+    }
+
+    /// Describe the open menu, see `GUIComponent::accessibility`
+    pub fn accessibility(&self, tree: &mut AccessTree) {
+        if let Some(popup) = &self.popup {
+            popup.accessibility(tree);
+        }
+    }
+
+    pub fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        match &self.popup {
+            Some(popup) => popup.render(ctx, renderer),
+            None => Ok(()),
+        }
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        if let Some(popup) = &self.popup {
+            popup.destroy(device);
+        }
+        for popup in &self.retired {
+            popup.destroy(device);
+        }
+    }
+}
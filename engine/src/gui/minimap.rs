@@ -0,0 +1,197 @@
+use anyhow::Result;
+use ash::vk;
+use std::sync::Arc;
+use crate::ecs::World;
+use crate::gui::viewport::{draw_world, entity_quad, image_quad, ViewportTarget};
+use crate::gui::{Color, EditorCamera, GUIComponent, Transform};
+use crate::math::{coords, Rect};
+use crate::renderer::{ColorVertex2D, Mesh, RenderContext, Renderer, SamplerConfig, Texture, TexturedVertex2D, VulkanContext};
+use glam::{Mat4, UVec2, Vec2};
+
+/// A second view of the World in its own offscreen target, e.g. a top-down map
+///
+/// Works like `ViewportComponent`: call `refresh`, then `RenderFrame::render_to_targets`
+/// with `scene_targets()` and `render_scene`, then render the widget with the UI. The
+/// target can have a fixed resolution and its own sampling (nearest for a pixelated map).
+///
+/// Clicking or dragging over the map reports the world position under the mouse through
+/// `take_clicked`, e.g. to move the main camera there.
+pub struct MinimapComponent {
+    target: Option<ViewportTarget>,
+    /// Target size in pixels, `None` follows the widget size
+    resolution: Option<UVec2>,
+    sampler: SamplerConfig,
+    /// The sampler changed since the target was created
+    sampler_changed: bool,
+    image_quad: Mesh<TexturedVertex2D>,
+    entity_quad: Mesh<ColorVertex2D>,
+    camera: EditorCamera,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    /// Must match the color format the pipelines are built with
+    color_format: vk::Format,
+    clear_color: Color,
+    /// World position of the last click or drag not taken yet
+    clicked: Option<Vec2>,
+    dragging: bool,
+    transform: Transform,
+}
+
+impl MinimapComponent {
+    /// `descriptor_set_layout` is the layout of `PipelineId::Image`, `color_format` is `Renderer::color_format`
+    pub fn new(context: &Arc<VulkanContext>, descriptor_set_layout: vk::DescriptorSetLayout, color_format: vk::Format) -> Result<Self> {
+        Ok(MinimapComponent {
+            target: None,
+            resolution: None,
+            sampler: SamplerConfig::linear(),
+            sampler_changed: false,
+            image_quad: image_quad(context)?,
+            entity_quad: entity_quad(context)?,
+            camera: EditorCamera::new(),
+            descriptor_set_layout,
+            color_format,
+            clear_color: Color::srgb(0.08, 0.08, 0.1),
+            clicked: None,
+            dragging: false,
+            transform: Transform::new(),
+        })
+    }
+
+    pub fn camera(&self) -> &EditorCamera {
+        &self.camera
+    }
+
+    /// Camera the map is rendered with, `zoom` is in pixels of the widget per world unit
+    pub fn camera_mut(&mut self) -> &mut EditorCamera {
+        &mut self.camera
+    }
+
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
+
+    /// Render at a fixed size instead of the widget size, the image is stretched over the widget
+    pub fn set_resolution(&mut self, resolution: Option<UVec2>) {
+        self.resolution = resolution;
+    }
+
+    /// How the target is sampled when drawn, applied on the next `refresh`
+    pub fn set_sampler(&mut self, sampler: SamplerConfig) {
+        self.sampler = sampler;
+        self.sampler_changed = true;
+    }
+
+    /// Offscreen texture the map is rendered into, if it has a size yet
+    pub fn target(&self) -> Option<&Texture> {
+        self.target.as_ref().map(|t| &t.texture)
+    }
+
+    /// Color and entity id targets of the map pass with their clear values, for `RenderFrame::render_to_targets`
+    pub fn scene_targets(&self) -> Option<[(&Texture, Option<vk::ClearColorValue>); 2]> {
+        self.target.as_ref().map(|t| t.scene_targets(self.clear_color))
+    }
+
+    /// Projection * view of the map camera for the widget size
+    pub fn view_projection(&self) -> Mat4 {
+        self.camera.view_projection(self.transform.scale)
+    }
+
+    /// World position on the XY plane under a UI point of the map
+    pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
+        coords::ui_to_world(point, self.transform.rect(), self.view_projection()).unwrap_or(self.camera.focus)
+    }
+
+    /// UI rect of the map showing the world rect `area`, e.g. to outline what the main view sees
+    pub fn world_to_screen(&self, area: Rect) -> Rect {
+        let (min, max) = (area.min(), area.max());
+        let view_projection = self.view_projection();
+        let rect = self.transform.rect();
+        let min = coords::world_to_ui(min.extend(0.0), rect, view_projection);
+        let max = coords::world_to_ui(max.extend(0.0), rect, view_projection);
+        Rect::new(min.x.min(max.x), min.y.min(max.y), (max.x - min.x).abs(), (max.y - min.y).abs())
+    }
+
+    /// Returns the world position clicked or dragged to since the last call
+    pub fn take_clicked(&mut self) -> Option<Vec2> {
+        self.clicked.take()
+    }
+
+    /// Recreate the offscreen target when its size or sampler changed
+    /// Returns true if the target was (re)created, the caller should redraw
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<bool> {
+        let size = self.resolution.unwrap_or_else(|| self.transform.scale.round().max(Vec2::ZERO).as_uvec2());
+        if size.x == 0 || size.y == 0 {
+            return Ok(false);
+        }
+        if let Some(target) = &self.target {
+            if target.has_size(size.x, size.y) && !self.sampler_changed {
+                return Ok(false);
+            }
+            // The old target may still be sampled by frames in flight
+            unsafe {
+                let _ = context.device.device_wait_idle();
+            }
+            target.destroy(&context.device);
+            self.target = None;
+        }
+
+        self.target = Some(ViewportTarget::new(context, size.x, size.y, self.color_format, self.descriptor_set_layout, self.sampler)?);
+        self.sampler_changed = false;
+        Ok(true)
+    }
+
+    /// Draw the World through the map camera
+    /// Must be called inside `RenderFrame::render_to_targets` for `scene_targets()`
+    pub fn render_scene(&self, ctx: &RenderContext, renderer: &mut Renderer, world: &World) -> Result<()> {
+        // Axis width is given in target pixels, which differ from widget pixels at a fixed resolution
+        let target_height = self.target.as_ref().map_or(1.0, |t| t.texture.height as f32);
+        let zoom = self.camera.zoom * target_height / self.transform.scale.y.max(1.0);
+        let visible = self.camera.visible_rect(self.transform.scale);
+        draw_world(ctx, renderer, world, &self.entity_quad, self.view_projection(), visible, zoom)
+    }
+}
+
+impl GUIComponent for MinimapComponent {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        match &self.target {
+            Some(target) => target.draw(ctx, renderer, &self.image_quad, &self.transform),
+            None => Ok(()),
+        }
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        let point = Vec2::new(x, y);
+        if self.transform.contains_point(point) {
+            self.clicked = Some(self.screen_to_world(point));
+            self.dragging = true;
+        }
+    }
+
+    fn handle_mouse_up(&mut self, _x: f32, _y: f32) {
+        self.dragging = false;
+    }
+
+    fn handle_mouse_move(&mut self, x: f32, y: f32) {
+        if self.dragging {
+            // Clamped to the map so dragging past its edge stops there
+            let rect = self.transform.rect();
+            let point = Vec2::new(x, y).clamp(rect.min(), rect.max());
+            self.clicked = Some(self.screen_to_world(point));
+        }
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        if let Some(target) = &self.target {
+            target.destroy(device);
+        }
+        self.image_quad.destroy(device);
+        self.entity_quad.destroy(device);
+    }
+}
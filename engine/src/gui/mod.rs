@@ -1,85 +1,830 @@
-use crate::renderer::RenderContext;
+use crate::localization::{Arg, Localization};
+use crate::math::coords;
+use crate::renderer::{RenderContext, RenderFrame, VulkanContext};
 use anyhow::Result;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+use winit::event::{Touch, TouchPhase};
+use winit::keyboard::Key;
 
 mod button;
 pub use button::ButtonComponent;
 
 mod panel;
-pub use panel::PanelComponent;
+pub use panel::{GradientDirection, PanelBackground, PanelComponent};
 
 mod container;
-pub use container::ContainerPanel;
+pub use container::{OffscreenPass, ContainerPanel};
+
+mod backdrop;
+pub use backdrop::{Backdrop, BackdropEffect};
+
+mod bloom;
+pub use bloom::{Bloom, BloomEffect};
+
+mod fxaa;
+pub use fxaa::{AntiAliasing, FxaaEffect};
+
+mod lighting2d;
+pub use lighting2d::Lighting2DEffect;
 
 mod grid;
-pub use grid::{Grid, GridRow, LayoutConstraints};
+pub use grid::{Grid, GridRow, LayoutConstraints, WidgetHandle};
+
+mod quad_batch;
+pub use quad_batch::QuadBatch;
+
+mod spatial_hash;
+pub use spatial_hash::SpatialHash;
+
+mod input;
+pub use input::InputState;
+
+mod gesture;
+pub use gesture::{Gesture, GestureRecognizer};
+
+mod scaling;
+pub use scaling::{SafeArea, ScalePolicy, UIScale};
 
 mod layout;
-pub use layout::{ComputedLayout, HAlign, LayoutSpec, SizeSpec, VAlign};
+pub use layout::{ComputedLayout, HAlign, LayoutSpec, RowSpec, SizeSpec, VAlign};
 
 mod text;
-pub use text::TextComponent;
+pub use text::{GlyphSnap, TextComponent, TextRendering};
+
+mod text_effects;
+pub use text_effects::{GlyphAnimation, GlyphStyle, TextEffects, TextSpan};
+
+mod console;
+pub use console::ConsoleComponent;
+
+mod profiler_overlay;
+pub use profiler_overlay::ProfilerOverlay;
+
+mod stats_overlay;
+pub use stats_overlay::StatsOverlay;
+
+mod tree_view;
+pub use tree_view::{TreeItem, TreeView};
+
+mod plot;
+pub use plot::{PlotComponent, PlotStyle};
+
+mod table;
+pub use table::{SortOrder, TableColumn, TableComponent, TableRow, TableSource};
+
+mod drag_float;
+pub use drag_float::DragFloat;
+
+mod checkbox;
+pub use checkbox::Checkbox;
+
+mod color_picker;
+pub use color_picker::{ColorPicker, ColorSwatch};
+
+#[cfg(feature = "editor-widgets")]
+mod curve_editor;
+#[cfg(feature = "editor-widgets")]
+pub use curve_editor::CurveEditor;
+
+#[cfg(feature = "editor-widgets")]
+mod gradient_editor;
+#[cfg(feature = "editor-widgets")]
+pub use gradient_editor::GradientEditor;
+
+#[cfg(feature = "editor-widgets")]
+mod property_grid;
+#[cfg(feature = "editor-widgets")]
+pub use property_grid::{Property, PropertyGrid, PropertyValue};
+
+mod editor_camera;
+pub use editor_camera::{CameraMode, EditorCamera};
+
+mod viewport;
+pub use viewport::ViewportComponent;
+
+mod trails;
+
+mod video;
+pub use video::VideoComponent;
+
+mod minimap;
+pub use minimap::MinimapComponent;
+
+mod world_space;
+pub use world_space::{WorldAnchor, WorldSpaceUI, WorldTarget, WorldWidgetHandle};
+
+mod floating_window;
+pub use floating_window::{FloatingWindow, FloatingWindows, WindowHandle};
+
+#[cfg(feature = "editor-widgets")]
+mod layout_inspector;
+#[cfg(feature = "editor-widgets")]
+pub use layout_inspector::LayoutInspector;
+
+mod snapshot;
+pub use snapshot::{assert_image_golden, render_snapshot, ImageDiff, ImageTolerance, LayoutSnapshot};
+
+mod menu;
+pub use menu::{ContextMenu, MenuBar, MenuItem};
+
+mod binding;
+pub use binding::{Bindable, Bindings, FieldData};
 
 mod component_ref;
 pub use component_ref::ComponentRef;
 
+mod accessibility;
+pub use accessibility::{AccessAction, AccessTree};
+/// The AccessKit version the accessibility tree is built with, for platform adapters
+pub use accesskit;
+
 pub use glam::Vec2;
 
-pub use crate::math::Transform;
+pub use crate::math::{Color, Rect, Transform};
+
+/// Downcasting of boxed components, implemented for every component type
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
 
-pub trait GUIComponent {
+pub trait GUIComponent: AsAny {
     fn render(&self, ctx: &RenderContext, renderer: &mut crate::renderer::Renderer) -> Result<()>;
+    /// Draw popups (open menus, dropdowns) over the rest of the UI, called once every
+    /// component rendered
+    fn render_overlay(&self, _ctx: &RenderContext, _renderer: &mut crate::renderer::Renderer) -> Result<()> {
+        Ok(())
+    }
+    /// Area of the component's open popups, while there is one the component gets all
+    /// mouse input of its grid (a click outside usually closes it)
+    fn overlay_rect(&self) -> Option<Rect> {
+        None
+    }
     fn transform(&self) -> &Transform;
     fn transform_mut(&mut self) -> &mut Transform;
-    fn handle_mouse_down(&mut self, x: f32, y: f32);
-    fn handle_mouse_up(&mut self, x: f32, y: f32);
-    fn handle_mouse_move(&mut self, x: f32, y: f32);
+    /// Place the component in `rect`, called by its grid's layout pass
+    /// Containers lay out their own children here.
+    fn set_layout(&mut self, rect: Rect) {
+        let transform = self.transform_mut();
+        transform.position = rect.center();
+        transform.scale = rect.size();
+    }
+    /// Preferred size within `constraints`, the largest size the parent can offer
+    /// By default a component takes everything it is offered.
+    fn measure(&self, constraints: Vec2) -> Vec2 {
+        constraints
+    }
+    /// Advance animations and other time based state by `dt` seconds
+    fn update(&mut self, _dt: f32) {}
+    /// Seconds until the component changes by itself, e.g. the next step of an animation,
+    /// `None` while it only changes on input
+    fn next_update(&self) -> Option<f32> {
+        None
+    }
+    /// Mouse handlers take UI space positions and do nothing unless the component is interactive
+    fn handle_mouse_down(&mut self, _x: f32, _y: f32) {}
+    fn handle_mouse_up(&mut self, _x: f32, _y: f32) {}
+    fn handle_mouse_move(&mut self, _x: f32, _y: f32) {}
+    /// A key press, returns whether the component used it
+    /// Goes to the component with an open popup, or else to every component until one uses it.
+    fn handle_key(&mut self, _key: &Key) -> bool {
+        false
+    }
+    /// A touch gesture in UI space, returns whether the component used it
+    /// Goes to the components under `Gesture::point` until one uses it. Taps also arrive
+    /// as clicks through the mouse handlers.
+    fn handle_gesture(&mut self, _gesture: &Gesture) -> bool {
+        false
+    }
+    /// Items to show when the component is right-clicked at `point`, `None` for no menu
+    fn context_menu(&self, _point: Vec2) -> Option<Vec<MenuItem>> {
+        None
+    }
+    /// Area that looks different since the last call because of input the component
+    /// handled (hover, clicks, drags) or its own `update`, `None` if nothing changed
+    /// Changes made through the component's own methods are redrawn by whoever made them.
+    fn take_damage(&mut self) -> Option<Rect> {
+        None
+    }
+    /// The rect and color if the component draws nothing but one flat unrotated rect,
+    /// its grid then draws it from a shared buffer instead of calling `render`
+    fn batch_quad(&self) -> Option<(Rect, Color)> {
+        None
+    }
+    /// Rebuild cached geometry of nested components, see `Grid::update_geometry`
+    fn update_geometry(&mut self, _context: &Arc<VulkanContext>) -> Result<()> {
+        Ok(())
+    }
+    /// Record offscreen passes the subtree draws from, e.g. out of date panel caches and
+    /// blurred backdrops (see `ContainerPanel`)
+    fn render_offscreen(&mut self, _pass: &OffscreenPass, _renderer: &mut crate::renderer::Renderer) -> Result<()> {
+        Ok(())
+    }
+    /// Directly nested components for walking the tree, empty for leaf widgets
+    fn children(&self) -> Vec<&dyn GUIComponent> {
+        Vec::new()
+    }
+    /// Grid the component lays its own children out with, for tools inspecting the layout
+    fn nested_grid(&self) -> Option<&Grid> {
+        None
+    }
+    /// Describe the component to assistive technologies by adding nodes to `tree`
+    /// Decoration adds nothing, containers describe their children.
+    fn accessibility(&self, _tree: &mut AccessTree) {}
+    /// An action an assistive technology requested on a node under `action.point`,
+    /// returns whether the component performed it. Default actions nobody performs
+    /// are clicks on the node.
+    fn accessibility_action(&mut self, _action: &AccessAction) -> bool {
+        false
+    }
     /// Manually destroy Vulkan resources
     fn destroy(&self, device: &ash::Device);
 }
 
+/// Longest step passed to `GUIComponent::update`, the first frame after an on-demand
+/// application idled would otherwise finish every animation at once
+const MAX_UPDATE_STEP: f32 = 1.0 / 30.0;
+
+/// Window position far from every component, where a lifted finger leaves the pointer
+const TOUCH_OUTSIDE: f32 = -1.0e6;
+
 /// Simple triangle GUI component
 
 /// GUI system that manages renderable components via a grid layout
+///
+/// Input that changes how a component looks is collected as damage, so applications that
+/// only render on demand can skip frames where nothing changed (see `take_damage`).
+/// `update` is the per-frame tick, run before rendering.
+///
+/// The layout is in UI units, which are window pixels unless a `ScalePolicy` scales the UI
+/// to a design resolution. Mouse positions passed in and damage handed out are in window
+/// pixels, `resize` with the window size keeps the mapping current.
 pub struct UISystem {
     pub grid: Grid,
+    /// Widgets following entities, place them with `WorldSpaceUI::place` every frame
+    pub world_ui: WorldSpaceUI,
+    /// Tool windows over the grid, they get mouse input before it
+    pub windows: FloatingWindows,
+    scale_policy: ScalePolicy,
+    safe_area: SafeArea,
+    /// Mapping resolved by the last `resize`
+    ui_scale: UIScale,
+    /// Window size of the last `resize`
+    window_size: Vec2,
+    /// Popup layer for right-click menus, drawn over everything and given input first
+    context_menu: Option<ContextMenu>,
+    /// Debug overlay drawn over everything, including the context menu
+    #[cfg(feature = "editor-widgets")]
+    layout_inspector: Option<LayoutInspector>,
+    damage: Option<Rect>,
+    /// Damage since the last `render_offscreen`, kept apart as the app takes `damage` whenever it likes
+    cache_damage: Option<Rect>,
+    /// A mouse button went down through `handle_mouse_down` and wasn't released yet
+    pressed: bool,
+    /// Finger driving the pointer, see `handle_touch`
+    touch: Option<u64>,
+    /// Every finger of `handle_touch`, for the gestures components get
+    gestures: GestureRecognizer,
+    /// UI rect of every node of the last `accessibility_tree`, actions are routed by it
+    access_bounds: HashMap<accesskit::NodeId, Rect>,
+    /// Node assistive technologies last moved focus to
+    access_focus: Option<accesskit::NodeId>,
+    /// Messages shown by `text_key` widgets
+    localization: Localization,
+    localized: Bindings<Localization>,
+    /// Localization revision the `text_key` widgets were last refreshed for
+    localized_revision: Option<u64>,
+    /// Seconds components take to move to their new rects after a window resize, `None` to jump
+    resize_animation: Option<f32>,
 }
 
 impl UISystem {
     pub fn new() -> Self {
         UISystem {
             grid: Grid::new(),
+            world_ui: WorldSpaceUI::new(),
+            windows: FloatingWindows::new(),
+            scale_policy: ScalePolicy::Pixels,
+            safe_area: SafeArea::default(),
+            ui_scale: UIScale::pixels(Vec2::ZERO),
+            window_size: Vec2::ZERO,
+            context_menu: None,
+            #[cfg(feature = "editor-widgets")]
+            layout_inspector: None,
+            damage: None,
+            cache_damage: None,
+            pressed: false,
+            touch: None,
+            gestures: GestureRecognizer::new(),
+            access_bounds: HashMap::new(),
+            access_focus: None,
+            localization: Localization::default(),
+            localized: Bindings::new(),
+            localized_revision: None,
+            resize_animation: None,
         }
     }
 
+    /// Fit the UI to a window of `width` x `height` pixels, call at startup and on every resize
+    /// Lays the grid out at the size the scale policy gives.
+    pub fn resize(&mut self, width: f32, height: f32) {
+        let resized = self.window_size != Vec2::new(width, height);
+        self.window_size = Vec2::new(width, height);
+        self.ui_scale = UIScale::resolve(self.scale_policy, self.safe_area, self.window_size);
+        let bounds = Rect::new(0.0, 0.0, self.ui_scale.size.x, self.ui_scale.size.y);
+        match self.resize_animation {
+            Some(duration) if resized => self.grid.set_bounds_animated(bounds, duration),
+            _ => self.grid.set_bounds(bounds),
+        }
+        self.windows.set_area(bounds);
+        // The mapping may have changed even where the layout size didn't
+        self.invalidate(Rect::new(0.0, 0.0, self.ui_scale.size.x, self.ui_scale.size.y));
+    }
+
+    pub fn resize_animation(&self) -> Option<f32> {
+        self.resize_animation
+    }
+
+    /// Move components to their new rects over `seconds` when the window is resized instead
+    /// of jumping there, `None` (the default) to jump
+    pub fn set_resize_animation(&mut self, seconds: Option<f32>) {
+        self.resize_animation = seconds.filter(|seconds| *seconds > 0.0);
+    }
+
+    /// Whether components are still moving after a resize, see `set_resize_animation`
+    pub fn is_animating_layout(&self) -> bool {
+        self.grid.is_animating_layout()
+    }
+
+    pub fn scale_policy(&self) -> ScalePolicy {
+        self.scale_policy
+    }
+
+    /// Lay the UI out at a design resolution instead of window pixels, applied right away
+    pub fn set_scale_policy(&mut self, policy: ScalePolicy) {
+        self.scale_policy = policy;
+        self.resize(self.window_size.x, self.window_size.y);
+    }
+
+    pub fn safe_area(&self) -> SafeArea {
+        self.safe_area
+    }
+
+    /// Window edges the UI stays clear of, applied right away
+    pub fn set_safe_area(&mut self, safe_area: SafeArea) {
+        self.safe_area = safe_area;
+        self.resize(self.window_size.x, self.window_size.y);
+    }
+
+    /// Current mapping between UI units and window pixels
+    pub fn ui_scale(&self) -> UIScale {
+        self.ui_scale
+    }
+
+    /// Advance the UI by `dt` seconds, call once per frame before `update_geometry`
+    ///
+    /// Lays out rows that changed or moves components on after a resize, ends drags whose release happened outside the window,
+    /// refreshes hover after the layout moved components under the mouse and updates
+    /// every component (animations, caret blinking).
+    pub fn update(&mut self, dt: f32, input: &InputState) {
+        crate::profile_scope!("ui_update");
+        // Fingers aren't mouse buttons, a press made by touch ends with its finger
+        if self.pressed && self.touch.is_none() && !input.any_pressed() {
+            self.handle_mouse_up(input.mouse.x, input.mouse.y);
+        }
+        let laid_out = self.grid.update_layout();
+        let moved = self.grid.animate_layout(dt.min(MAX_UPDATE_STEP));
+        if laid_out || moved {
+            self.invalidate(self.grid.bounds().unwrap_or_default());
+            self.handle_mouse_move(input.mouse.x, input.mouse.y);
+        }
+        self.gestures.update();
+        self.dispatch_gestures();
+        self.grid.update(dt.min(MAX_UPDATE_STEP));
+        self.world_ui.update(dt.min(MAX_UPDATE_STEP));
+        self.windows.update(dt.min(MAX_UPDATE_STEP));
+        self.collect_damage();
+    }
+
+    /// Seconds until a component changes by itself, applications rendering on demand
+    /// should render again by then. `None` if the UI only changes on input.
+    pub fn next_update(&self) -> Option<f32> {
+        [self.grid.next_update(), self.world_ui.next_update(), self.windows.next_update(), self.gestures.next_update()]
+            .into_iter()
+            .flatten()
+            .reduce(f32::min)
+    }
+
+    /// Components first, then world-space widgets and floating windows, then popups and the context menu over them
     pub fn render(&self, ctx: &RenderContext, renderer: &mut crate::renderer::Renderer) -> anyhow::Result<()> {
-        self.grid.render(ctx, renderer)
+        crate::profile_scope!("ui_render");
+        // Components draw with the window projection, scaling goes in front of it
+        let window_projection = renderer.projection;
+        renderer.projection = window_projection * self.ui_scale.matrix();
+        let result = self.render_layers(ctx, renderer);
+        renderer.projection = window_projection;
+        result
+    }
+
+    fn render_layers(&self, ctx: &RenderContext, renderer: &mut crate::renderer::Renderer) -> anyhow::Result<()> {
+        self.grid.render(ctx, renderer)?;
+        self.world_ui.render(ctx, renderer)?;
+        self.windows.render(ctx, renderer)?;
+        self.windows.render_overlay(ctx, renderer)?;
+        self.grid.render_overlay(ctx, renderer)?;
+        if let Some(menu) = &self.context_menu {
+            menu.render(ctx, renderer)?;
+        }
+        #[cfg(feature = "editor-widgets")]
+        if let Some(inspector) = &self.layout_inspector {
+            inspector.render(ctx, renderer)?;
+        }
+        Ok(())
+    }
+
+    /// Enable right-click menus, `menu` holds the font they are drawn with
+    /// Returns the menu it replaces, which the caller destroys.
+    pub fn set_context_menu(&mut self, menu: ContextMenu) -> Option<ContextMenu> {
+        self.context_menu.replace(menu)
+    }
+
+    /// Enable the layout inspector, `inspector` holds the font its info is drawn with
+    /// Returns the inspector it replaces, which the caller destroys.
+    #[cfg(feature = "editor-widgets")]
+    pub fn set_layout_inspector(&mut self, inspector: LayoutInspector) -> Option<LayoutInspector> {
+        self.layout_inspector.replace(inspector)
+    }
+
+    /// The layout inspector, e.g. to toggle it, if one was set
+    #[cfg(feature = "editor-widgets")]
+    pub fn layout_inspector_mut(&mut self) -> Option<&mut LayoutInspector> {
+        self.layout_inspector.as_mut()
     }
 
+    /// Mouse handlers take window positions in UI orientation (pixels, y up), convert
+    /// window events with `math::coords::window_to_ui`. The scale policy maps them to UI units.
+    /// An open context menu takes all mouse input until it closes, then popups of the grid,
+    /// then the floating windows under the pointer.
     pub fn handle_mouse_down(&mut self, x: f32, y: f32) {
-        self.grid.handle_mouse_down(x, y);
+        let Vec2 { x, y } = self.ui_scale.to_ui(Vec2::new(x, y));
+        self.pressed = true;
+        match self.context_menu.as_mut().filter(|menu| menu.is_open()) {
+            Some(menu) => menu.handle_mouse_down(x, y),
+            None if self.grid.overlay_rect().is_some() => self.grid.handle_mouse_down(x, y),
+            None => {
+                if !self.windows.handle_mouse_down(x, y) {
+                    self.grid.handle_mouse_down(x, y);
+                }
+            }
+        }
+        self.collect_damage();
     }
 
     pub fn handle_mouse_up(&mut self, x: f32, y: f32) {
-        self.grid.handle_mouse_up(x, y);
+        let Vec2 { x, y } = self.ui_scale.to_ui(Vec2::new(x, y));
+        self.pressed = false;
+        if !self.context_menu.as_ref().is_some_and(ContextMenu::is_open) {
+            // A window that took the press keeps its release from the components beneath
+            if self.windows.handle_mouse_up(x, y) {
+                self.grid.handle_mouse_up(TOUCH_OUTSIDE, TOUCH_OUTSIDE);
+            } else {
+                self.grid.handle_mouse_up(x, y);
+            }
+        }
+        self.collect_damage();
     }
 
     pub fn handle_mouse_move(&mut self, x: f32, y: f32) {
-        self.grid.handle_mouse_move(x, y);
+        let Vec2 { x, y } = self.ui_scale.to_ui(Vec2::new(x, y));
+        #[cfg(feature = "editor-widgets")]
+        if let Some(inspector) = &mut self.layout_inspector {
+            inspector.set_pointer(Vec2::new(x, y));
+        }
+        // Popups and presses of the grid keep the pointer from the windows, the windows
+        // under the pointer or dragged keep it from the grid
+        let grid_owns = self.grid.overlay_rect().is_some() || (self.pressed && !self.windows.is_capturing());
+        match self.context_menu.as_mut().filter(|menu| menu.is_open()) {
+            Some(menu) => menu.handle_mouse_move(x, y),
+            None if grid_owns => {
+                self.windows.handle_mouse_move(TOUCH_OUTSIDE, TOUCH_OUTSIDE);
+                self.grid.handle_mouse_move(x, y);
+            }
+            None if self.windows.handle_mouse_move(x, y) => self.grid.handle_mouse_move(TOUCH_OUTSIDE, TOUCH_OUTSIDE),
+            None => self.grid.handle_mouse_move(x, y),
+        }
+        self.collect_damage();
     }
 
-    /// Update layout for nested containers after main grid layout has been set
-    pub fn update_nested_layouts(&mut self) {
-        // This is a placeholder - the real implementation would require
-        // the ability to downcast components to ContainerPanel
-        // For now, containers will need to be updated manually
+    /// A `WindowEvent::Touch`, `window_height` in pixels turns its position to UI orientation
+    /// A single finger acts as the mouse with its left button held. A second finger calls
+    /// the press off and the two make pinches and scrolls, which go to the components under
+    /// them as `Gesture`s, as do taps. Holding a finger still opens the context menu where
+    /// no component uses the long press. Returns whether the touch drove the pointer or a
+    /// component used a gesture.
+    pub fn handle_touch(&mut self, touch: &Touch, window_height: f32) -> bool {
+        let point = coords::window_to_ui(Vec2::new(touch.location.x as f32, touch.location.y as f32), window_height);
+        self.gestures.touch(touch.id, touch.phase, point);
+        let pointer = match touch.phase {
+            TouchPhase::Started if self.touch.is_none() && self.gestures.finger_count() == 1 => {
+                self.touch = Some(touch.id);
+                // Hover first, components take presses where the pointer last moved
+                self.handle_mouse_move(point.x, point.y);
+                self.handle_mouse_down(point.x, point.y);
+                true
+            }
+            TouchPhase::Started if self.touch.is_some() => {
+                self.cancel_touch();
+                true
+            }
+            _ if self.touch != Some(touch.id) => false,
+            TouchPhase::Started | TouchPhase::Moved => {
+                self.handle_mouse_move(point.x, point.y);
+                true
+            }
+            TouchPhase::Ended => {
+                // Touch screens have no hover, the pointer leaves the UI when the finger lifts
+                let outside = Vec2::splat(TOUCH_OUTSIDE);
+                self.touch = None;
+                self.handle_mouse_move(point.x, point.y);
+                self.handle_mouse_up(point.x, point.y);
+                self.handle_mouse_move(outside.x, outside.y);
+                true
+            }
+            TouchPhase::Cancelled => {
+                self.cancel_touch();
+                true
+            }
+        };
+        let used = self.dispatch_gestures();
+        pointer || used
+    }
+
+    /// Release the finger driving the pointer away from every component, so it isn't a click
+    fn cancel_touch(&mut self) {
+        let outside = Vec2::splat(TOUCH_OUTSIDE);
+        self.touch = None;
+        self.handle_mouse_move(outside.x, outside.y);
+        self.handle_mouse_up(outside.x, outside.y);
+    }
+
+    /// Hand recognized gestures to the components under them, returns whether any was used
+    fn dispatch_gestures(&mut self) -> bool {
+        let mut used = false;
+        for gesture in self.gestures.take() {
+            let scale = self.ui_scale;
+            let ui_gesture = match gesture {
+                Gesture::Tap { point } => Gesture::Tap { point: scale.to_ui(point) },
+                Gesture::LongPress { point } => Gesture::LongPress { point: scale.to_ui(point) },
+                Gesture::Pinch { center, scale: factor } => Gesture::Pinch { center: scale.to_ui(center), scale: factor },
+                Gesture::Scroll { center, delta } => Gesture::Scroll { center: scale.to_ui(center), delta: delta / scale.scale },
+            };
+            let on_window = self.windows.contains_point(ui_gesture.point()) && self.grid.overlay_rect().is_none();
+            let mut handled = if on_window {
+                self.windows.handle_gesture(&ui_gesture)
+            } else {
+                self.grid.handle_gesture(&ui_gesture)
+            };
+            if let (false, Gesture::LongPress { point }) = (handled, gesture) {
+                // The menu takes the following taps, not the press still held
+                self.cancel_touch();
+                handled = self.open_context_menu(point.x, point.y).unwrap_or_else(|e| {
+                    log::error!("Failed to open context menu: {}", e);
+                    false
+                });
+            }
+            used |= handled;
+        }
+        self.collect_damage();
+        used
+    }
+
+    /// A key press, returns whether the UI used it (e.g. to move through an open menu)
+    pub fn handle_key(&mut self, key: &Key) -> bool {
+        let used = match self.context_menu.as_mut().filter(|menu| menu.is_open()) {
+            Some(menu) => menu.handle_key(key),
+            None if self.grid.overlay_rect().is_some() => self.grid.handle_key(key),
+            None => self.windows.handle_key(key) || self.grid.handle_key(key),
+        };
+        self.collect_damage();
+        used
+    }
+
+    /// Show the context menu of the component under a right click, returns whether it had one
+    /// Needs `set_context_menu`. The press shouldn't also go to `handle_mouse_down` when this opened a menu.
+    pub fn open_context_menu(&mut self, x: f32, y: f32) -> Result<bool> {
+        let point = self.ui_scale.to_ui(Vec2::new(x, y));
+        let Some(menu) = self.context_menu.as_mut() else {
+            return Ok(false);
+        };
+        let items = if self.windows.contains_point(point) {
+            self.windows.context_menu_at(point)
+        } else {
+            self.grid.context_menu_at(point)
+        };
+        let Some(items) = items.filter(|items| !items.is_empty()) else {
+            return Ok(false);
+        };
+        menu.open(&items, point, self.grid.bounds())?;
+        self.collect_damage();
+        Ok(true)
+    }
+
+    /// Whether a menu or other popup is open, presses then only go to the UI
+    pub fn has_popup(&self) -> bool {
+        self.grid.overlay_rect().is_some()
+            || self.windows.overlay_rect().is_some()
+            || self.context_menu.as_ref().is_some_and(ContextMenu::is_open)
+    }
+
+    /// Action of the context menu item chosen since the last call
+    pub fn take_menu_action(&mut self) -> Option<String> {
+        self.context_menu.as_mut()?.take_activated()
+    }
+
+    /// Full AccessKit tree of the UI for a platform adapter, e.g. once per frame through
+    /// `accesskit_winit::Adapter::update_if_active`
+    pub fn accessibility_tree(&mut self, app_name: &str) -> accesskit::TreeUpdate {
+        let mut tree = AccessTree::new(app_name, self.ui_scale, self.window_size);
+        self.grid.accessibility(&mut tree);
+        self.world_ui.accessibility(&mut tree);
+        self.windows.accessibility(&mut tree);
+        if let Some(menu) = &self.context_menu {
+            menu.accessibility(&mut tree);
+        }
+        let (update, bounds) = tree.finish(self.access_focus);
+        self.access_bounds = bounds;
+        update
+    }
+
+    /// Perform an action requested by an assistive technology on a node of the last
+    /// `accessibility_tree`, returns whether anything handled it
+    /// Default actions no component performs itself are clicks on the center of the node.
+    pub fn handle_access_action(&mut self, request: &accesskit::ActionRequest) -> bool {
+        let Some(&rect) = self.access_bounds.get(&request.target) else {
+            return false;
+        };
+        match request.action {
+            accesskit::Action::Focus => {
+                self.access_focus = Some(request.target);
+                return true;
+            }
+            accesskit::Action::Blur => {
+                self.access_focus = None;
+                return true;
+            }
+            _ => {}
+        }
+        let action = AccessAction { action: request.action, point: rect.center(), data: request.data.clone() };
+        let popup_open = self.context_menu.as_ref().is_some_and(ContextMenu::is_open);
+        let handled = !popup_open && (self.windows.accessibility_action(&action)
+            || self.grid.accessibility_action(&action)
+            || self.world_ui.accessibility_action(&action));
+        if handled {
+            self.invalidate(rect);
+            self.collect_damage();
+            return true;
+        }
+        if request.action != accesskit::Action::Default {
+            return false;
+        }
+        let point = self.ui_scale.to_window(action.point);
+        self.handle_mouse_down(point.x, point.y);
+        self.handle_mouse_up(point.x, point.y);
+        true
+    }
+
+    /// Mark an area in UI units as needing a redraw, e.g. after changing a component from outside
+    pub fn invalidate(&mut self, rect: Rect) {
+        self.damage = Some(self.damage.map_or(rect, |damage| damage.union(&rect)));
+        self.cache_damage = Some(self.cache_damage.map_or(rect, |damage| damage.union(&rect)));
+    }
+
+    fn collect_damage(&mut self) {
+        if let Some(rect) = self.grid.take_damage() {
+            self.invalidate(rect);
+        }
+        if let Some(rect) = self.world_ui.take_damage() {
+            self.invalidate(rect);
+        }
+        if let Some(rect) = self.windows.take_damage() {
+            self.invalidate(rect);
+        }
+        if let Some(rect) = self.context_menu.as_mut().and_then(ContextMenu::take_damage) {
+            self.invalidate(rect);
+        }
+        #[cfg(feature = "editor-widgets")]
+        if let Some(rect) = self.layout_inspector.as_mut().and_then(LayoutInspector::take_damage) {
+            self.invalidate(rect);
+        }
+    }
+
+    /// Whether anything was damaged since the last `take_damage`
+    pub fn is_damaged(&self) -> bool {
+        self.damage.is_some()
+    }
+
+    /// Window area damaged since the last call, the frame should be redrawn if there is one
+    pub fn take_damage(&mut self) -> Option<Rect> {
+        self.collect_damage();
+        self.damage.take().map(|rect| self.ui_scale.rect_to_window(rect))
+    }
+
+    pub fn localization(&self) -> &Localization {
+        &self.localization
+    }
+
+    /// Load messages or change the language, `text_key` widgets follow on the next `update_geometry`
+    pub fn localization_mut(&mut self) -> &mut Localization {
+        &mut self.localization
+    }
+
+    /// Show `language`, returns whether any messages were loaded for it
+    pub fn set_language(&mut self, language: &str) -> bool {
+        self.localization.set_language(language)
+    }
+
+    /// Show the message `key` in a text widget (a label or button), e.g. "menu.start"
+    /// The text is updated whenever the language changes or messages are loaded.
+    pub fn text_key<W>(&mut self, widget: Arc<RefCell<W>>, key: &str)
+    where
+        W: Bindable<Value = String> + 'static,
+    {
+        self.text_key_with(widget, key, Vec::new());
+    }
+
+    /// `text_key` for a message with arguments, e.g. `[("count", 3.into())]`
+    pub fn text_key_with<W>(&mut self, widget: Arc<RefCell<W>>, key: &str, args: Vec<(&str, Arg)>)
+    where
+        W: Bindable<Value = String> + 'static,
+    {
+        let key = key.to_string();
+        let args: Vec<(String, Arg)> = args.into_iter().map(|(name, value)| (name.to_string(), value)).collect();
+        self.localized.bind_read_only(widget, move |localization: &Localization| {
+            let args: Vec<(&str, Arg)> = args.iter().map(|(name, value)| (name.as_str(), value.clone())).collect();
+            localization.format(&key, &args)
+        });
+        self.localized_revision = None;
+    }
+
+    /// Refresh `text_key` widgets after a language change, their text sizes change with it
+    fn update_localized(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        let revision = self.localization.revision();
+        if self.localized_revision == Some(revision) {
+            return Ok(());
+        }
+        self.localized_revision = Some(revision);
+        if self.localized.sync(&mut self.localization, context)? {
+            self.resize(self.window_size.x, self.window_size.y);
+        }
+        Ok(())
+    }
+
+    /// Rebuild retained geometry that changed, call once per frame before `render`
+    pub fn update_geometry(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        self.update_localized(context)?;
+        self.grid.update_geometry(context)?;
+        #[cfg(feature = "editor-widgets")]
+        if let Some(inspector) = &mut self.layout_inspector {
+            inspector.refresh(&self.grid, context)?;
+        }
+        self.world_ui.update_geometry(context)?;
+        self.windows.update_geometry(context)
+    }
+
+    /// Record the offscreen passes of the UI: caches of containers whose contents changed
+    /// and panel backdrops. Call every frame after `update_geometry` and the passes backdrops
+    /// sample from, before `render`.
+    pub fn render_offscreen(&mut self, frame: &RenderFrame, renderer: &mut crate::renderer::Renderer, context: &Arc<VulkanContext>) -> Result<()> {
+        self.collect_damage();
+        let pass = OffscreenPass { frame, context, scale: self.ui_scale.scale, damage: self.cache_damage.take() };
+        self.grid.render_offscreen(&pass, renderer)?;
+        self.windows.render_offscreen(&pass, renderer)
     }
 
     /// Manually destroy all GUI resources
     pub fn destroy(&self, device: &ash::Device) {
-        for row in &self.grid.rows {
-            for component in &row.components {
-                component.destroy(device);
-            }
+        self.grid.destroy(device);
+        self.world_ui.destroy(device);
+        self.windows.destroy(device);
+        if let Some(menu) = &self.context_menu {
+            menu.destroy(device);
+        }
+        #[cfg(feature = "editor-widgets")]
+        if let Some(inspector) = &self.layout_inspector {
+            inspector.destroy(device);
         }
     }
 }
@@ -1,108 +1,220 @@
 use anyhow::Result;
+use std::collections::VecDeque;
 use std::sync::Arc;
-use crate::renderer::{ColorVertex2D, Mesh, PipelineId, RenderContext, VertexBuffer};
-use crate::gui::{GUIComponent, Transform};
+use crate::renderer::{ColorVertex2D, Mesh, PipelineId, RenderContext, SampledTexture, TexturedVertex2D, VertexBuffer, VulkanContext};
+use crate::gui::color_picker::{create_mesh, quad};
+use crate::gui::{Color, GUIComponent, Rect, Transform};
+use crate::math::Gradient;
 use crate::renderer::PushConstants2D;
 
+/// Old meshes are kept alive for this many background changes, longer than any frame stays in flight
+const MESHES_KEPT: usize = 3;
+/// Rings and spokes of the grid a radial gradient is interpolated over
+const RADIAL_CELLS: usize = 16;
+
+/// Axis a panel gradient runs along
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientDirection {
+    /// Left (0) to right (1)
+    Horizontal,
+    /// Top (0) to bottom (1)
+    Vertical,
+    /// Center (0) to the corners (1), stretched with the panel
+    Radial,
+}
+
+/// What fills a panel, tinted by the panel color
+#[derive(Clone)]
+pub enum PanelBackground {
+    /// The panel color alone
+    Flat,
+    Gradient(Gradient, GradientDirection),
+    /// Stretched over the panel, the owner keeps the texture alive and destroys it
+    /// (like the font atlas of text components)
+    Texture(Arc<SampledTexture>),
+}
+
+enum PanelMesh {
+    Color(Mesh<ColorVertex2D>),
+    Textured(Mesh<TexturedVertex2D>, Arc<SampledTexture>),
+}
+
+impl PanelMesh {
+    fn destroy(&self, device: &ash::Device) {
+        match self {
+            PanelMesh::Color(mesh) => mesh.destroy(device),
+            PanelMesh::Textured(mesh, _) => mesh.destroy(device),
+        }
+    }
+}
+
 /// A panel is a rectangular container that can render a background and hold other components
 pub struct PanelComponent {
-    mesh: Mesh<ColorVertex2D>,
+    mesh: PanelMesh,
+    retired: VecDeque<PanelMesh>,
     transform: Transform,
-    color: [f32; 3],
+    color: Color,
+    /// Whether the background is `Flat`, only those can be batched
+    flat: bool,
 }
 
 impl GUIComponent for PanelComponent {
     fn render(&self, ctx: &RenderContext, renderer: &mut crate::renderer::Renderer) -> Result<()> {
-        let pipeline = renderer.get_pipeline(PipelineId::BasicGeometry)?;
-        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::BasicGeometry).unwrap();
+        let pipeline_id = match self.mesh {
+            PanelMesh::Color(_) => PipelineId::UI,
+            PanelMesh::Textured(..) => PipelineId::Image,
+        };
+        let pipeline = renderer.get_pipeline(pipeline_id)?;
+        let pipeline_layout = renderer.get_pipeline_layout(pipeline_id)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for {:?} pipeline", pipeline_id))?;
         ctx.bind_pipeline(pipeline);
 
-        let push = PushConstants2D {
-            projection: renderer.projection,
-            transform: 
+        let transform =
             glam::Mat4::from_translation(glam::Vec3::new(self.transform.position.x, self.transform.position.y, 0.0)) *
-            glam::Mat4::from_rotation_z(self.transform.rotation) * 
-            glam::Mat4::from_scale(glam::Vec3::new(self.transform.scale.x, self.transform.scale.y, 1.0)),
-            color_modulation: [1.0, 1.0, 1.0],  // No modulation for panels
-            _padding: 0.0,
-        };
+            glam::Mat4::from_rotation_z(self.transform.rotation) *
+            glam::Mat4::from_scale(glam::Vec3::new(self.transform.scale.x, self.transform.scale.y, 1.0));
+        // Backgrounds are built white (or from their own colors), so `set_color` tints them
+        // without rebuilding anything
+        let push = PushConstants2D::new(renderer.projection, transform)
+            .with_modulation(self.color.rgb())
+            .with_opacity(renderer.opacity);
 
-        ctx.push_constants(pipeline_layout, &push);
-        
-        self.mesh.draw(ctx)?;
-        
-        Ok(())
+        match &self.mesh {
+            PanelMesh::Color(mesh) => {
+                ctx.push(pipeline_layout, &push);
+                mesh.draw(ctx)
+            }
+            PanelMesh::Textured(mesh, texture) => {
+                ctx.bind_descriptor_set_at(pipeline_layout, 0, texture.descriptor_set);
+                ctx.push(pipeline_layout, &push);
+                mesh.draw(ctx)
+            }
+        }
     }
 
-    fn handle_mouse_down(&mut self, _x: f32, _y: f32) {}
-    fn handle_mouse_up(&mut self, _x: f32, _y: f32) {}
-    fn handle_mouse_move(&mut self, _x: f32, _y: f32) {}
+    fn batch_quad(&self) -> Option<(Rect, Color)> {
+        (self.flat && self.transform.rotation == 0.0).then(|| (self.transform.rect(), self.color))
+    }
 
     fn transform(&self) -> &Transform {
         &self.transform
     }
-    
+
     fn transform_mut(&mut self) -> &mut Transform {
         &mut self.transform
     }
 
     fn destroy(&self, device: &ash::Device) {
         self.mesh.destroy(device);
+        for mesh in &self.retired {
+            mesh.destroy(device);
+        }
     }
 }
 
 impl PanelComponent {
     pub fn new(
         context: &Arc<crate::renderer::VulkanContext>,
-        color: [f32; 3],
+        color: Color,
     ) -> Result<Self> {
-        // Define quad vertices (0.5 units = 50% of width/height from center)
-        let vertices = [
-            ColorVertex2D {
-                position: [-0.5, 0.5],
-                color,
-            },
-            ColorVertex2D {
-                position: [-0.5, -0.5],
-                color,
-            },
-            ColorVertex2D {
-                position: [0.5, -0.5],
-                color,
-            },
-            ColorVertex2D {
-                position: [0.5, -0.5],
-                color,
-            },
-            ColorVertex2D {
-                position: [0.5, 0.5],
-                color,
-            },
-            ColorVertex2D {
-                position: [-0.5, 0.5],
-                color,
-            },
-        ];
-
-        let vertex_buffer = VertexBuffer::new(
-            &context.device,
-            context.physical_device,
-            &context.instance,
-            &vertices,
-        )?;
-
         Ok(PanelComponent {
-            mesh: Mesh::new(vertex_buffer),
+            mesh: Self::build_mesh(context, &PanelBackground::Flat)?,
+            retired: VecDeque::with_capacity(MESHES_KEPT + 1),
             transform: Transform::new(),
             color,
+            flat: true,
         })
     }
 
-    pub fn set_color(&mut self, color: [f32; 3]) {
+    /// Panel filled with `background`, tinted by `color` (white keeps its colors)
+    pub fn with_background(context: &Arc<VulkanContext>, color: Color, background: PanelBackground) -> Result<Self> {
+        let mut panel = Self::new(context, color)?;
+        panel.set_background(context, background)?;
+        Ok(panel)
+    }
+
+    pub fn set_color(&mut self, color: Color) {
         self.color = color;
     }
 
-    pub fn color(&self) -> [f32; 3] {
+    pub fn color(&self) -> Color {
         self.color
     }
+
+    /// Replace what fills the panel, the panel color stays as a tint
+    pub fn set_background(&mut self, context: &Arc<VulkanContext>, background: PanelBackground) -> Result<()> {
+        let mesh = Self::build_mesh(context, &background)?;
+        self.retired.push_back(std::mem::replace(&mut self.mesh, mesh));
+        while self.retired.len() > MESHES_KEPT {
+            if let Some(old) = self.retired.pop_front() {
+                old.destroy(&context.device);
+            }
+        }
+        self.flat = matches!(background, PanelBackground::Flat);
+        Ok(())
+    }
+
+    fn build_mesh(context: &Arc<VulkanContext>, background: &PanelBackground) -> Result<PanelMesh> {
+        Ok(match background {
+            PanelBackground::Flat => PanelMesh::Color(create_mesh(context, &quad(-0.5, -0.5, 0.5, 0.5, [[1.0; 3]; 4]))?),
+            PanelBackground::Gradient(gradient, direction) => {
+                PanelMesh::Color(create_mesh(context, &gradient_vertices(gradient, *direction))?)
+            }
+            PanelBackground::Texture(texture) => {
+                // Image rows go down while UI y goes up, so the top of the quad samples v = 0
+                let vertices = [
+                    TexturedVertex2D { position: [-0.5, 0.5], uv: [0.0, 0.0] },
+                    TexturedVertex2D { position: [-0.5, -0.5], uv: [0.0, 1.0] },
+                    TexturedVertex2D { position: [0.5, -0.5], uv: [1.0, 1.0] },
+                    TexturedVertex2D { position: [0.5, -0.5], uv: [1.0, 1.0] },
+                    TexturedVertex2D { position: [0.5, 0.5], uv: [1.0, 0.0] },
+                    TexturedVertex2D { position: [-0.5, 0.5], uv: [0.0, 0.0] },
+                ];
+                let buffer = VertexBuffer::new(&context.device, context.physical_device, &context.instance, &vertices)?;
+                PanelMesh::Textured(Mesh::new(buffer), texture.clone())
+            }
+        })
+    }
+}
+
+/// Unit quad split into cells whose corners sample the gradient
+///
+/// Linear gradients get a cell edge at every stop, so they are exact. Radial ones are
+/// interpolated over a `RADIAL_CELLS` grid.
+fn gradient_vertices(gradient: &Gradient, direction: GradientDirection) -> Vec<ColorVertex2D> {
+    let mut stops: Vec<f32> = [0.0, 1.0].into_iter().chain(gradient.stops().iter().map(|stop| stop.t)).collect();
+    stops.sort_by(f32::total_cmp);
+    stops.dedup();
+    let even: Vec<f32> = (0..=RADIAL_CELLS).map(|i| i as f32 / RADIAL_CELLS as f32).collect();
+    let ends = vec![0.0, 1.0];
+
+    // Cell edges across (u, left to right) and up (v, bottom to top) the quad
+    let (us, vs) = match direction {
+        GradientDirection::Horizontal => (stops, ends),
+        GradientDirection::Vertical => (ends, stops.iter().map(|t| 1.0 - t).rev().collect()),
+        GradientDirection::Radial => (even.clone(), even),
+    };
+    let color = |u: f32, v: f32| {
+        let t = match direction {
+            GradientDirection::Horizontal => u,
+            GradientDirection::Vertical => 1.0 - v,
+            GradientDirection::Radial => glam::Vec2::new(u - 0.5, v - 0.5).length() / std::f32::consts::FRAC_1_SQRT_2,
+        };
+        gradient.sample(t).rgb()
+    };
+
+    let mut vertices = Vec::with_capacity((us.len() - 1) * (vs.len() - 1) * 6);
+    for v in vs.windows(2) {
+        for u in us.windows(2) {
+            let (left, right, bottom, top) = (u[0], u[1], v[0], v[1]);
+            vertices.extend(quad(
+                left - 0.5,
+                bottom - 0.5,
+                right - 0.5,
+                top - 0.5,
+                [color(left, top), color(left, bottom), color(right, bottom), color(right, top)],
+            ));
+        }
+    }
+    vertices
 }
@@ -0,0 +1,257 @@
+use anyhow::Result;
+use ash::vk;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use crate::gui::{Color, GUIComponent, QuadBatch, Rect, TextComponent, Transform};
+use crate::renderer::{DebugLines, FontAtlas, RenderContext, Renderer, VulkanContext};
+use glam::Vec2;
+
+/// Headroom added above and below auto-scaled samples, as a fraction of their spread
+const AUTO_MARGIN: f32 = 0.1;
+const BACKGROUND: Color = Color::linear(0.02, 0.02, 0.025);
+
+/// How a `PlotComponent` draws its samples
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlotStyle {
+    /// Line through the samples
+    Line,
+    /// A bar per sample, rising from the bottom of the range
+    Bars,
+}
+
+/// Graph of the last samples of a metric, e.g. frame times or memory usage
+///
+/// Samples are kept in a ring buffer, the newest on the right. Each end of the value
+/// axis is either fixed or scaled to the samples. Hovering shows the value under the
+/// mouse in the readout, which otherwise shows the newest sample.
+///
+/// Lines are drawn with `DebugLines` and bars with a `QuadBatch`, both rebuilt by
+/// `refresh` only when the samples, hover or size changed.
+pub struct PlotComponent {
+    samples: VecDeque<f32>,
+    capacity: usize,
+    style: PlotStyle,
+    /// Fixed ends of the value axis, `None` follows the samples
+    min: Option<f32>,
+    max: Option<f32>,
+    color: Color,
+    label: String,
+    unit: String,
+    precision: usize,
+    lines: DebugLines,
+    quads: QuadBatch,
+    readout: TextComponent,
+    /// Sample index under the mouse
+    hovered: Option<usize>,
+    /// Rect the geometry was built for, `None` once the samples or style changed
+    built: Option<Rect>,
+    /// Hover changed by input since the last `take_damage`
+    damaged: bool,
+    transform: Transform,
+}
+
+impl PlotComponent {
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        capacity: usize,
+    ) -> Result<Self> {
+        Ok(PlotComponent {
+            samples: VecDeque::with_capacity(capacity.max(2)),
+            capacity: capacity.max(2),
+            style: PlotStyle::Line,
+            min: None,
+            max: None,
+            color: Color::srgb(0.3, 0.8, 0.4),
+            label: String::new(),
+            unit: String::new(),
+            precision: 2,
+            lines: DebugLines::for_ui(context.clone()),
+            quads: QuadBatch::new(),
+            readout: TextComponent::new("-", font_atlas, font_size, descriptor_set_layout, context)?,
+            hovered: None,
+            built: None,
+            damaged: false,
+            transform: Transform::new(),
+        })
+    }
+
+    /// Add a sample, dropping the oldest once the buffer is full
+    pub fn push(&mut self, value: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+        self.built = None;
+    }
+
+    pub fn samples(&self) -> &VecDeque<f32> {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.hovered = None;
+        self.built = None;
+    }
+
+    /// Number of samples kept, older ones are dropped
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(2);
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+        self.built = None;
+    }
+
+    pub fn set_style(&mut self, style: PlotStyle) {
+        self.style = style;
+        self.built = None;
+    }
+
+    /// Fix either end of the value axis, `None` scales that end to the samples
+    pub fn set_range(&mut self, min: Option<f32>, max: Option<f32>) {
+        self.min = min;
+        self.max = max;
+        self.built = None;
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+        self.built = None;
+    }
+
+    /// Readout text, e.g. ("Frame", "ms") shows "Frame 16.67 ms"
+    pub fn set_label(&mut self, label: &str, unit: &str) {
+        self.label = label.to_string();
+        self.unit = unit.to_string();
+    }
+
+    /// Number of decimals in the readout
+    pub fn set_precision(&mut self, precision: usize) {
+        self.precision = precision;
+    }
+
+    /// Value axis after auto-scaling, bottom to top
+    pub fn range(&self) -> (f32, f32) {
+        let lowest = self.samples.iter().copied().fold(f32::INFINITY, f32::min);
+        let highest = self.samples.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let (lowest, highest) = if self.samples.is_empty() { (0.0, 1.0) } else { (lowest, highest) };
+        let margin = ((highest - lowest) * AUTO_MARGIN).max(f32::EPSILON);
+        let min = self.min.unwrap_or(lowest - margin);
+        let max = self.max.unwrap_or(highest + margin);
+        // A flat line in the middle rather than a division by zero
+        if max - min > f32::EPSILON { (min, max) } else { (min - 1.0, max + 1.0) }
+    }
+
+    /// Horizontal slot of the sample at `index`, slots fill from the right
+    fn slot(&self, index: usize) -> usize {
+        self.capacity - self.samples.len() + index
+    }
+
+    /// Left edge and width of a sample slot in pixels
+    fn slot_span(&self) -> (f32, f32) {
+        let rect = self.transform.rect();
+        (rect.min().x, rect.size().x / self.capacity as f32)
+    }
+
+    /// Sample index under `x`, if a sample is there
+    fn sample_at(&self, x: f32) -> Option<usize> {
+        let (left, width) = self.slot_span();
+        let slot = ((x - left) / width).floor();
+        if slot < 0.0 {
+            return None;
+        }
+        let empty = self.capacity - self.samples.len();
+        (slot as usize).checked_sub(empty).filter(|&index| index < self.samples.len())
+    }
+
+    /// Rebuild lines, bars and readout after new samples, hover changes or a resize
+    /// Call once per frame before rendering
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        let value = self.hovered.or(self.samples.len().checked_sub(1)).and_then(|i| self.samples.get(i));
+        let text = match value {
+            Some(value) => format!("{} {:.*} {}", self.label, self.precision, value, self.unit),
+            None => format!("{} -", self.label),
+        };
+        self.readout.update_text(text.trim(), context)?;
+        let rect = self.transform.rect();
+        let (min, max) = (rect.min(), rect.max());
+        self.readout.set_position(Vec2::new(
+            min.x + 4.0 + self.readout.get_width() / 2.0,
+            max.y - 2.0 - self.readout.get_height() / 2.0,
+        ));
+
+        if self.built == Some(rect) {
+            return Ok(());
+        }
+        let (bottom, top) = self.range();
+        let (left, width) = self.slot_span();
+        let y_of = |value: f32| min.y + (value.clamp(bottom, top) - bottom) / (top - bottom) * rect.size().y;
+
+        let mut quads = vec![(rect, BACKGROUND)];
+        match self.style {
+            PlotStyle::Line => {
+                let points: Vec<Vec2> = self.samples.iter().enumerate()
+                    .map(|(i, &value)| Vec2::new(left + (self.slot(i) as f32 + 0.5) * width, y_of(value)))
+                    .collect();
+                self.lines.polyline(&points, false, self.color.rgb());
+            }
+            PlotStyle::Bars => {
+                // Leave a gap between bars once they are wide enough to see it
+                let gap = if width >= 4.0 { 1.0 } else { 0.0 };
+                for (i, &value) in self.samples.iter().enumerate() {
+                    let height = y_of(value) - min.y;
+                    let x = left + self.slot(i) as f32 * width;
+                    quads.push((Rect::new(x, min.y, width - gap, height), self.color));
+                }
+            }
+        }
+        if let Some(index) = self.hovered.filter(|&i| i < self.samples.len()) {
+            let x = left + (self.slot(index) as f32 + 0.5) * width;
+            self.lines.line(Vec2::new(x, min.y), Vec2::new(x, max.y), [0.8, 0.8, 0.8]);
+        }
+        self.quads.update(context, quads)?;
+        self.lines.upload()?;
+        self.built = Some(rect);
+        Ok(())
+    }
+}
+
+impl GUIComponent for PlotComponent {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        self.quads.render(ctx, renderer)?;
+        let projection = renderer.projection;
+        self.lines.draw(ctx, renderer, projection)?;
+        self.readout.render(ctx, renderer)
+    }
+
+    fn handle_mouse_move(&mut self, x: f32, y: f32) {
+        let hovered = if self.transform.contains_point(Vec2::new(x, y)) { self.sample_at(x) } else { None };
+        if hovered != self.hovered {
+            self.hovered = hovered;
+            self.built = None;
+            self.damaged = true;
+        }
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.transform.rect())
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.quads.destroy(device);
+        self.lines.destroy(device);
+        self.readout.destroy(device);
+    }
+}
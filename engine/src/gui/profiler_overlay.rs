@@ -0,0 +1,174 @@
+use anyhow::Result;
+use ash::vk;
+use std::sync::Arc;
+use crate::gui::{Color, GUIComponent, PanelComponent, TextComponent, Transform};
+use crate::profiler;
+use crate::renderer::{FontAtlas, RenderContext, Renderer, VulkanContext};
+use glam::Vec2;
+
+/// Colors cycled through for zone bars
+/// Zone bar colors (sRGB)
+const BAR_COLORS: [[f32; 3]; 6] = [
+    [0.85, 0.35, 0.35],
+    [0.35, 0.75, 0.4],
+    [0.35, 0.55, 0.9],
+    [0.9, 0.75, 0.3],
+    [0.7, 0.4, 0.85],
+    [0.3, 0.8, 0.8],
+];
+
+/// One bar + label per profiled zone
+struct ZoneRow {
+    name: &'static str,
+    bar: PanelComponent,
+    label: TextComponent,
+    /// Fraction of the frame time spent in this zone (0.0 to 1.0)
+    fraction: f32,
+}
+
+/// Overlay panel showing per-zone CPU time of the last profiled frame as bars
+pub struct ProfilerOverlay {
+    background: PanelComponent,
+    title: TextComponent,
+    rows: Vec<ZoneRow>,
+    font_atlas: Arc<FontAtlas>,
+    font_size: f32,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    transform: Transform,
+    row_height: f32,
+    visible: bool,
+}
+
+impl ProfilerOverlay {
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<Self> {
+        Ok(ProfilerOverlay {
+            background: PanelComponent::new(context, Color::srgb(0.06, 0.06, 0.08))?,
+            title: TextComponent::new("CPU frame", font_atlas.clone(), font_size, descriptor_set_layout, context)?,
+            rows: Vec::new(),
+            font_atlas,
+            font_size,
+            descriptor_set_layout,
+            transform: Transform::new(),
+            row_height: font_size + 4.0,
+            visible: true,
+        })
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn toggle_visible(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Pull the last frame from the profiler and rebuild labels
+    /// Call once per frame before rendering
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        let frame = profiler::last_frame();
+        let frame_ms = frame.frame_time.as_secs_f32() * 1000.0;
+        self.title.update_text(&format!("CPU frame {:.2} ms", frame_ms), context)?;
+
+        for row in &mut self.rows {
+            row.fraction = 0.0;
+        }
+
+        for zone in &frame.zones {
+            let index = match self.rows.iter().position(|row| row.name == zone.name) {
+                Some(index) => index,
+                None => {
+                    let [r, g, b] = BAR_COLORS[self.rows.len() % BAR_COLORS.len()];
+                    self.rows.push(ZoneRow {
+                        name: zone.name,
+                        bar: PanelComponent::new(context, Color::srgb(r, g, b))?,
+                        label: TextComponent::new(zone.name, self.font_atlas.clone(), self.font_size, self.descriptor_set_layout, context)?,
+                        fraction: 0.0,
+                    });
+                    self.rows.len() - 1
+                }
+            };
+
+            let zone_ms = zone.total.as_secs_f32() * 1000.0;
+            let row = &mut self.rows[index];
+            row.fraction = if frame_ms > 0.0 { (zone_ms / frame_ms).clamp(0.0, 1.0) } else { 0.0 };
+            row.label.update_text(&format!("{} {:.2} ms", zone.name, zone_ms), context)?;
+        }
+
+        self.update_layout();
+        Ok(())
+    }
+
+    /// Lay out the title and one row per zone from the top of the overlay
+    fn update_layout(&mut self) {
+        *self.background.transform_mut() = self.transform;
+
+        let left = self.transform.position.x - self.transform.scale.x / 2.0 + 6.0;
+        let top = self.transform.position.y + self.transform.scale.y / 2.0;
+        // Bars use the right half of the panel, labels the left half
+        let bar_left = self.transform.position.x;
+        let bar_max_width = (self.transform.scale.x / 2.0 - 6.0).max(0.0);
+
+        self.title.set_position(Vec2::new(left + self.title.get_width() / 2.0, top - self.row_height / 2.0));
+
+        for (i, row) in self.rows.iter_mut().enumerate() {
+            let y = top - self.row_height * (i as f32 + 1.5);
+            row.label.set_position(Vec2::new(left + row.label.get_width() / 2.0, y));
+
+            let width = bar_max_width * row.fraction;
+            let bar = row.bar.transform_mut();
+            bar.position = Vec2::new(bar_left + width / 2.0, y);
+            bar.scale = Vec2::new(width, self.row_height - 4.0);
+        }
+    }
+}
+
+impl GUIComponent for ProfilerOverlay {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        self.background.render(ctx, renderer)?;
+        self.title.render(ctx, renderer)?;
+
+        let max_rows = ((self.transform.scale.y / self.row_height) as usize).saturating_sub(1);
+        for row in self.rows.iter().take(max_rows) {
+            row.label.render(ctx, renderer)?;
+            if row.fraction > 0.0 {
+                row.bar.render(ctx, renderer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+
+    fn destroy(&self, device: &ash::Device) {
+        self.background.destroy(device);
+        self.title.destroy(device);
+        for row in &self.rows {
+            row.bar.destroy(device);
+            row.label.destroy(device);
+        }
+    }
+}
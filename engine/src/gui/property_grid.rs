@@ -0,0 +1,453 @@
+use anyhow::Result;
+use ash::vk;
+use std::sync::Arc;
+use crate::gui::{AccessAction, AccessTree, Checkbox, Color, ColorPicker, ColorSwatch, DragFloat, GUIComponent, PanelComponent, Rect, TextComponent, Transform};
+use crate::renderer::{FontAtlas, RenderContext, Renderer, VulkanContext};
+use glam::Vec2;
+use accesskit::{NodeBuilder, Role};
+
+/// Height of the color picker opened below a color row
+const PICKER_HEIGHT: f32 = 150.0;
+
+/// Value shown by a property row
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PropertyValue {
+    /// Section title without an editor
+    Header,
+    Float(f32),
+    Bool(bool),
+    /// Edited with a ColorPicker opened by clicking the swatch, alpha is ignored
+    Color(Color),
+}
+
+/// A labelled, editable value
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    /// Identifies the property in `take_edits`, e.g. "Transform.position.x"
+    pub key: String,
+    pub label: String,
+    pub value: PropertyValue,
+    /// Clamp range for floats
+    pub range: Option<(f32, f32)>,
+}
+
+impl Property {
+    pub fn header(key: &str, label: &str) -> Self {
+        Property { key: key.to_string(), label: label.to_string(), value: PropertyValue::Header, range: None }
+    }
+
+    pub fn float(key: &str, label: &str, value: f32) -> Self {
+        Property { key: key.to_string(), label: label.to_string(), value: PropertyValue::Float(value), range: None }
+    }
+
+    pub fn boolean(key: &str, label: &str, value: bool) -> Self {
+        Property { key: key.to_string(), label: label.to_string(), value: PropertyValue::Bool(value), range: None }
+    }
+
+    pub fn color(key: &str, label: &str, value: Color) -> Self {
+        Property { key: key.to_string(), label: label.to_string(), value: PropertyValue::Color(value), range: None }
+    }
+
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+}
+
+enum PropertyEditor {
+    Header(PanelComponent),
+    Float(Box<DragFloat>),
+    Bool(Checkbox),
+    Color(ColorSwatch),
+}
+
+impl PropertyEditor {
+    fn destroy(&self, device: &ash::Device) {
+        match self {
+            PropertyEditor::Header(panel) => panel.destroy(device),
+            PropertyEditor::Float(drag) => drag.destroy(device),
+            PropertyEditor::Bool(checkbox) => checkbox.destroy(device),
+            PropertyEditor::Color(swatch) => swatch.destroy(device),
+        }
+    }
+}
+
+struct PropertyRow {
+    key: String,
+    label: TextComponent,
+    editor: PropertyEditor,
+}
+
+impl PropertyRow {
+    fn matches(&self, property: &Property) -> bool {
+        self.key == property.key
+            && matches!(
+                (&self.editor, property.value),
+                (PropertyEditor::Header(_), PropertyValue::Header)
+                    | (PropertyEditor::Float(_), PropertyValue::Float(_))
+                    | (PropertyEditor::Bool(_), PropertyValue::Bool(_))
+                    | (PropertyEditor::Color(_), PropertyValue::Color(_))
+            )
+    }
+}
+
+/// Two column list of labels and value editors
+pub struct PropertyGrid {
+    properties: Vec<Property>,
+    rows: Vec<PropertyRow>,
+    edits: Vec<(String, PropertyValue)>,
+    /// Shared picker for the open color row, created on first use
+    picker: Option<Box<ColorPicker>>,
+    /// Key of the color row whose picker is open
+    open_color: Option<String>,
+    /// A picker was opened or closed since the last `take_damage`
+    damaged: bool,
+    /// Rows that fit in the grid after the last layout
+    visible_rows: usize,
+    font_atlas: Arc<FontAtlas>,
+    font_size: f32,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    transform: Transform,
+    row_height: f32,
+}
+
+impl PropertyGrid {
+    pub fn new(
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Self {
+        PropertyGrid {
+            properties: Vec::new(),
+            rows: Vec::new(),
+            edits: Vec::new(),
+            picker: None,
+            open_color: None,
+            damaged: false,
+            visible_rows: 0,
+            font_atlas,
+            font_size,
+            descriptor_set_layout,
+            transform: Transform::new(),
+            row_height: font_size + 6.0,
+        }
+    }
+
+    /// Replace the shown properties, rows are rebuilt on the next refresh if the keys changed
+    pub fn set_properties(&mut self, properties: Vec<Property>) {
+        self.properties = properties;
+    }
+
+    pub fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+
+    /// The picker of the open color row, e.g. to type a hex value into it
+    pub fn open_color_picker(&mut self) -> Option<&mut ColorPicker> {
+        if self.open_color.is_some() { self.picker.as_deref_mut() } else { None }
+    }
+
+    /// Returns all values changed by the user since the last call, in edit order
+    pub fn take_edits(&mut self) -> Vec<(String, PropertyValue)> {
+        self.collect_edits();
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Collect pending edits from the editors
+    fn collect_edits(&mut self) {
+        for row in &mut self.rows {
+            let value = match &mut row.editor {
+                PropertyEditor::Header(_) => None,
+                PropertyEditor::Float(drag) => drag.take_changed().map(PropertyValue::Float),
+                PropertyEditor::Bool(checkbox) => checkbox.take_changed().map(PropertyValue::Bool),
+                PropertyEditor::Color(_) => None,
+            };
+            if let Some(value) = value {
+                self.edits.push((row.key.clone(), value));
+            }
+        }
+        if let (Some(key), Some(picker)) = (&self.open_color, &mut self.picker) {
+            if let Some(color) = picker.take_changed() {
+                self.edits.push((key.clone(), PropertyValue::Color(color.with_alpha(1.0))));
+            }
+        }
+    }
+
+    /// Whether a picker is open and the layout reserved space for it
+    fn picker_open(&self) -> bool {
+        self.open_color.is_some() && self.picker.is_some()
+    }
+
+    fn create_row(&self, context: &Arc<VulkanContext>, property: &Property) -> Result<PropertyRow> {
+        let editor = match property.value {
+            PropertyValue::Header => PropertyEditor::Header(PanelComponent::new(context, Color::srgb(0.2, 0.2, 0.26))?),
+            PropertyValue::Float(value) => {
+                let mut drag = DragFloat::new(context, self.font_atlas.clone(), self.font_size, self.descriptor_set_layout, value)?;
+                if let Some((min, max)) = property.range {
+                    drag.set_range(Some((min, max)));
+                    drag.set_speed((max - min) / 200.0);
+                }
+                drag.set_label(&property.label);
+                PropertyEditor::Float(Box::new(drag))
+            }
+            PropertyValue::Bool(checked) => {
+                let mut checkbox = Checkbox::new(context, checked)?;
+                checkbox.set_label(&property.label);
+                PropertyEditor::Bool(checkbox)
+            }
+            PropertyValue::Color(color) => PropertyEditor::Color(ColorSwatch::new(context, color)?),
+        };
+        let mut label = TextComponent::new(&property.label, self.font_atlas.clone(), self.font_size, self.descriptor_set_layout, context)?;
+        if matches!(property.value, PropertyValue::Header) {
+            label.set_color(Color::srgb(0.9, 0.8, 0.5));
+        }
+        Ok(PropertyRow { key: property.key.clone(), label, editor })
+    }
+
+    /// Sync rows with the properties, then rebuild text and layout
+    /// Call once per frame before rendering
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        // Keep edits made before the rows are rebuilt
+        self.collect_edits();
+
+        let unchanged = self.rows.len() == self.properties.len()
+            && self.rows.iter().zip(&self.properties).all(|(row, property)| row.matches(property));
+        if !unchanged {
+            // Wait for the GPU before destroying meshes that may still be in flight
+            unsafe {
+                let _ = context.device.device_wait_idle();
+            }
+            for row in self.rows.drain(..) {
+                row.label.destroy(&context.device);
+                row.editor.destroy(&context.device);
+            }
+            for property in &self.properties {
+                let row = self.create_row(context, property)?;
+                self.rows.push(row);
+            }
+        }
+
+        for (row, property) in self.rows.iter_mut().zip(&self.properties) {
+            row.label.update_text(&property.label, context)?;
+            match (&mut row.editor, property.value) {
+                // Don't fight the user while a value is being dragged
+                (PropertyEditor::Float(drag), PropertyValue::Float(value)) if !drag.is_dragging() => drag.set_value(value),
+                (PropertyEditor::Bool(checkbox), PropertyValue::Bool(checked)) => checkbox.set_checked(checked),
+                (PropertyEditor::Color(swatch), PropertyValue::Color(color)) => swatch.set_color(color),
+                _ => {}
+            }
+        }
+
+        // Close the picker when its property went away, otherwise follow outside changes
+        let open_value = self.open_color.as_ref().and_then(|key| {
+            self.properties.iter().find(|p| &p.key == key).and_then(|p| match p.value {
+                PropertyValue::Color(color) => Some(color),
+                _ => None,
+            })
+        });
+        match open_value {
+            Some(color) => {
+                if self.picker.is_none() {
+                    let mut picker = ColorPicker::new(context, self.font_atlas.clone(), self.font_size, self.descriptor_set_layout, color)?;
+                    picker.set_show_alpha(false);
+                    self.picker = Some(Box::new(picker));
+                }
+                if let Some(picker) = self.picker.as_mut().filter(|p| !p.is_dragging()) {
+                    picker.set_color(color);
+                }
+            }
+            None => self.open_color = None,
+        }
+
+        self.update_layout(context)
+    }
+
+    fn update_layout(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        let left = self.transform.position.x - self.transform.scale.x / 2.0;
+        let top = self.transform.position.y + self.transform.scale.y / 2.0;
+        let width = self.transform.scale.x;
+        // Labels use the left 40%, editors the rest
+        let editor_left = left + width * 0.4;
+        let editor_width = (width * 0.6 - 4.0).max(0.0);
+        let bottom = top - self.transform.scale.y;
+        let picker_open = self.picker_open();
+
+        let mut row_top = top;
+        self.visible_rows = 0;
+        for row in self.rows.iter_mut() {
+            let y = row_top - self.row_height / 2.0;
+            row_top -= self.row_height;
+            if row_top >= bottom {
+                self.visible_rows += 1;
+            }
+            row.label.set_position(Vec2::new(left + 4.0 + row.label.get_width() / 2.0, y));
+
+            let mut editor_transform = Transform::new();
+            editor_transform.position = Vec2::new(editor_left + editor_width / 2.0, y);
+            editor_transform.scale = Vec2::new(editor_width, self.row_height - 2.0);
+
+            match &mut row.editor {
+                PropertyEditor::Header(panel) => {
+                    let transform = panel.transform_mut();
+                    transform.position = Vec2::new(left + width / 2.0, y);
+                    transform.scale = Vec2::new(width, self.row_height - 2.0);
+                }
+                PropertyEditor::Float(drag) => {
+                    *drag.transform_mut() = editor_transform;
+                    drag.refresh(context)?;
+                }
+                PropertyEditor::Bool(checkbox) => {
+                    *checkbox.transform_mut() = editor_transform;
+                    checkbox.update_layout();
+                }
+                PropertyEditor::Color(swatch) => {
+                    *swatch.transform_mut() = editor_transform;
+                }
+            }
+
+            // The open picker takes the space below its row
+            if picker_open && self.open_color.as_deref() == Some(row.key.as_str()) {
+                if let Some(picker) = &mut self.picker {
+                    let transform = picker.transform_mut();
+                    transform.position = Vec2::new(left + width / 2.0, row_top - 2.0 - PICKER_HEIGHT / 2.0);
+                    transform.scale = Vec2::new((width - 8.0).max(0.0), PICKER_HEIGHT);
+                    picker.refresh(context)?;
+                }
+                row_top -= PICKER_HEIGHT + 4.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// The open picker if it fits in the grid
+    fn visible_picker(&self) -> Option<&ColorPicker> {
+        let picker = self.picker.as_deref().filter(|_| self.picker_open())?;
+        let bottom = self.transform.position.y - self.transform.scale.y / 2.0;
+        let picker_bottom = picker.transform().position.y - picker.transform().scale.y / 2.0;
+        (picker_bottom >= bottom).then_some(picker)
+    }
+}
+
+impl GUIComponent for PropertyGrid {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        for row in self.rows.iter().take(self.visible_rows) {
+            match &row.editor {
+                PropertyEditor::Header(panel) => panel.render(ctx, renderer)?,
+                PropertyEditor::Float(drag) => drag.render(ctx, renderer)?,
+                PropertyEditor::Bool(checkbox) => checkbox.render(ctx, renderer)?,
+                PropertyEditor::Color(swatch) => swatch.render(ctx, renderer)?,
+            }
+            row.label.render(ctx, renderer)?;
+        }
+        if let Some(picker) = self.visible_picker() {
+            picker.render(ctx, renderer)?;
+        }
+        Ok(())
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        if self.visible_picker().is_some() {
+            if let Some(picker) = &mut self.picker {
+                picker.handle_mouse_down(x, y);
+            }
+        }
+        let visible_rows = self.visible_rows;
+        for row in self.rows.iter_mut().take(visible_rows) {
+            match &mut row.editor {
+                PropertyEditor::Header(_) => {}
+                PropertyEditor::Float(drag) => drag.handle_mouse_down(x, y),
+                PropertyEditor::Bool(checkbox) => checkbox.handle_mouse_down(x, y),
+                PropertyEditor::Color(swatch) => {
+                    swatch.handle_mouse_down(x, y);
+                    // Clicking a swatch toggles its picker
+                    if swatch.take_clicked() {
+                        let open = self.open_color.as_deref() == Some(row.key.as_str());
+                        self.open_color = if open { None } else { Some(row.key.clone()) };
+                        self.damaged = true;
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_mouse_up(&mut self, x: f32, y: f32) {
+        for row in &mut self.rows {
+            if let PropertyEditor::Float(drag) = &mut row.editor {
+                drag.handle_mouse_up(x, y);
+            }
+        }
+        if let Some(picker) = &mut self.picker {
+            picker.handle_mouse_up(x, y);
+        }
+    }
+
+    fn handle_mouse_move(&mut self, x: f32, y: f32) {
+        for row in &mut self.rows {
+            if let PropertyEditor::Float(drag) = &mut row.editor {
+                drag.handle_mouse_move(x, y);
+            }
+        }
+        if let Some(picker) = &mut self.picker {
+            picker.handle_mouse_move(x, y);
+        }
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        let own = std::mem::take(&mut self.damaged).then(|| self.transform.rect());
+        let editors = self.rows.iter_mut().filter_map(|row| match &mut row.editor {
+            PropertyEditor::Float(drag) => drag.take_damage(),
+            PropertyEditor::Bool(checkbox) => checkbox.take_damage(),
+            PropertyEditor::Header(_) | PropertyEditor::Color(_) => None,
+        });
+        let picker = self.picker.as_mut().and_then(|picker| picker.take_damage());
+        own.into_iter().chain(editors).chain(picker).reduce(|a, b| a.union(&b))
+    }
+
+    /// Headers become headings, editors carry their row label as name
+    fn accessibility(&self, tree: &mut AccessTree) {
+        for row in self.rows.iter().take(self.visible_rows) {
+            match &row.editor {
+                PropertyEditor::Header(panel) => {
+                    let mut node = NodeBuilder::new(Role::Heading);
+                    node.set_name(row.label.text());
+                    tree.add(node, panel.transform().rect());
+                }
+                PropertyEditor::Float(drag) => drag.accessibility(tree),
+                PropertyEditor::Bool(checkbox) => checkbox.accessibility(tree),
+                PropertyEditor::Color(swatch) => {
+                    let mut node = NodeBuilder::new(Role::ColorWell);
+                    node.set_name(row.label.text());
+                    node.set_default_action_verb(accesskit::DefaultActionVerb::Open);
+                    tree.add(node, swatch.transform().rect());
+                }
+            }
+        }
+    }
+
+    fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+        let visible_rows = self.visible_rows;
+        self.rows.iter_mut().take(visible_rows).any(|row| match &mut row.editor {
+            PropertyEditor::Float(drag) => drag.transform().contains_point(action.point) && drag.accessibility_action(action),
+            PropertyEditor::Bool(checkbox) => checkbox.transform().contains_point(action.point) && checkbox.accessibility_action(action),
+            PropertyEditor::Header(_) | PropertyEditor::Color(_) => false,
+        })
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        for row in &self.rows {
+            row.label.destroy(device);
+            row.editor.destroy(device);
+        }
+        if let Some(picker) = &self.picker {
+            picker.destroy(device);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::gui::color_picker::{create_mesh, quad};
+use crate::math::{Color, Rect};
+use crate::renderer::{ColorVertex2D, Mesh, PipelineId, PushConstants2D, RenderContext, Renderer, VulkanContext};
+
+/// Old meshes are kept alive for this many rebuilds, longer than any frame stays in flight
+const MESHES_KEPT: usize = 3;
+
+/// Flat-colored rects drawn with a single draw call
+///
+/// The vertices are kept in a persistent buffer in UI pixel space and only rebuilt when
+/// the rects change, so static panels cost one push and one draw however many there are.
+pub struct QuadBatch {
+    quads: Vec<(Rect, Color)>,
+    mesh: Option<Mesh<ColorVertex2D>>,
+    retired: VecDeque<Mesh<ColorVertex2D>>,
+}
+
+impl QuadBatch {
+    pub fn new() -> Self {
+        QuadBatch {
+            quads: Vec::new(),
+            mesh: None,
+            retired: VecDeque::with_capacity(MESHES_KEPT + 1),
+        }
+    }
+
+    /// Rects in the current buffer, in draw order
+    pub fn quads(&self) -> &[(Rect, Color)] {
+        &self.quads
+    }
+
+    /// Replace the rects, the buffer is only rebuilt if they differ from the current ones
+    pub fn update(&mut self, context: &Arc<VulkanContext>, quads: Vec<(Rect, Color)>) -> Result<()> {
+        if quads == self.quads {
+            return Ok(());
+        }
+        let mesh = if quads.is_empty() {
+            None
+        } else {
+            let vertices: Vec<ColorVertex2D> = quads
+                .iter()
+                .flat_map(|(rect, color)| {
+                    let (min, max) = (rect.min(), rect.max());
+                    quad(min.x, min.y, max.x, max.y, [color.rgb(); 4])
+                })
+                .collect();
+            Some(create_mesh(context, &vertices)?)
+        };
+        if let Some(old) = std::mem::replace(&mut self.mesh, mesh) {
+            self.retired.push_back(old);
+        }
+        while self.retired.len() > MESHES_KEPT {
+            if let Some(old) = self.retired.pop_front() {
+                old.destroy(&context.device);
+            }
+        }
+        self.quads = quads;
+        Ok(())
+    }
+
+    pub fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        let Some(mesh) = &self.mesh else {
+            return Ok(());
+        };
+        let pipeline = renderer.get_pipeline(PipelineId::UI)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::UI)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for UI pipeline"))?;
+        ctx.bind_pipeline(pipeline);
+        ctx.push(pipeline_layout, &PushConstants2D::new(renderer.projection, glam::Mat4::IDENTITY).with_opacity(renderer.opacity));
+        mesh.draw(ctx)
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        if let Some(mesh) = &self.mesh {
+            mesh.destroy(device);
+        }
+        for mesh in &self.retired {
+            mesh.destroy(device);
+        }
+    }
+}
+
+impl Default for QuadBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,111 @@
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::math::Rect;
+
+/// How the UI is laid out and scaled to the window, see `UISystem::set_scale_policy`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScalePolicy {
+    /// One UI unit is one window pixel and the layout fills the window (editor UIs)
+    Pixels,
+    /// Laid out at `design` size and scaled uniformly until it fits, centered with empty
+    /// bars on the sides that don't match the aspect ratio
+    Letterbox { design: Vec2 },
+    /// Scaled uniformly like `Letterbox`, but the layout grows along the longer axis to fill
+    /// the window, so edge-anchored rows reach the window edges
+    ScaleToFit { design: Vec2 },
+}
+
+/// Window pixels kept free on each edge, e.g. for notches, rounded corners or TV overscan
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SafeArea {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl SafeArea {
+    pub fn new(left: f32, right: f32, top: f32, bottom: f32) -> Self {
+        SafeArea { left, right, top, bottom }
+    }
+
+    /// The same inset on every edge
+    pub fn uniform(inset: f32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+
+    /// Part of a `width` x `height` window inside the insets (y up)
+    pub fn apply(&self, window: Vec2) -> Rect {
+        Rect::new(
+            self.left,
+            self.bottom,
+            (window.x - self.left - self.right).max(0.0),
+            (window.y - self.bottom - self.top).max(0.0),
+        )
+    }
+}
+
+/// Mapping between UI units and window pixels resolved from a policy and the window size
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UIScale {
+    /// Window pixels per UI unit
+    pub scale: f32,
+    /// Window position of the UI origin
+    pub offset: Vec2,
+    /// Size the UI is laid out at, in UI units
+    pub size: Vec2,
+}
+
+impl UIScale {
+    /// Identity mapping for a window of `size` pixels
+    pub fn pixels(size: Vec2) -> Self {
+        UIScale { scale: 1.0, offset: Vec2::ZERO, size }
+    }
+
+    pub fn resolve(policy: ScalePolicy, safe_area: SafeArea, window: Vec2) -> Self {
+        let area = safe_area.apply(window);
+        match policy {
+            ScalePolicy::Pixels => UIScale { scale: 1.0, offset: area.min(), size: area.size() },
+            ScalePolicy::Letterbox { design } => {
+                let scale = Self::fit(area.size(), design);
+                let offset = area.min() + (area.size() - design * scale) / 2.0;
+                UIScale { scale, offset, size: design }
+            }
+            ScalePolicy::ScaleToFit { design } => {
+                let scale = Self::fit(area.size(), design);
+                UIScale { scale, offset: area.min(), size: area.size() / scale }
+            }
+        }
+    }
+
+    /// Largest uniform scale that fits `design` into `area`
+    fn fit(area: Vec2, design: Vec2) -> f32 {
+        let scale = (area / design.max(Vec2::ONE)).min_element();
+        if scale > 0.0 { scale } else { 1.0 }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.scale == 1.0 && self.offset == Vec2::ZERO
+    }
+
+    /// Window UI point (pixels, y up) to UI units
+    pub fn to_ui(&self, point: Vec2) -> Vec2 {
+        (point - self.offset) / self.scale
+    }
+
+    /// UI units to a window UI point (pixels, y up)
+    pub fn to_window(&self, point: Vec2) -> Vec2 {
+        point * self.scale + self.offset
+    }
+
+    /// UI rect to the window pixels it covers
+    pub fn rect_to_window(&self, rect: Rect) -> Rect {
+        let min = self.to_window(rect.min());
+        Rect::new(min.x, min.y, rect.width * self.scale, rect.height * self.scale)
+    }
+
+    /// UI units to window pixels, applied after the UI transforms and before the window projection
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_translation(self.offset.extend(0.0)) * Mat4::from_scale(Vec3::new(self.scale, self.scale, 1.0))
+    }
+}
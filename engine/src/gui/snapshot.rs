@@ -0,0 +1,256 @@
+//! Golden file checks of UI layout and rendering, for tests of applications built on the GUI
+//!
+//! Layout snapshots and image comparisons run anywhere. Rendering a snapshot is not
+//! headless: the Vulkan context and renderer are created for a window surface, so
+//! `render_snapshot` needs a GPU and a window that can present, which CI machines
+//! usually don't have.
+
+use anyhow::{bail, Context, Result};
+use ash::vk;
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::gui::{GUIComponent, Rect, UISystem};
+use crate::math::Color;
+use crate::renderer::{Renderer, Texture, VulkanContext};
+
+/// Environment variable, set to rewrite golden files from the current output instead of comparing against them
+const UPDATE_ENV: &str = "UPDATE_SNAPSHOTS";
+
+fn update_requested() -> bool {
+    std::env::var_os(UPDATE_ENV).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Computed rects of every component of a UI, for layout assertions without a GPU
+///
+/// Components are named by their index path through the tree, e.g. "2/0/1" is the second
+/// child of the first child of the third component of the grid (row order).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LayoutSnapshot {
+    pub entries: Vec<(String, Rect)>,
+}
+
+impl LayoutSnapshot {
+    /// Lay `ui` out for a `width` x `height` window and record every component
+    pub fn capture(ui: &mut UISystem, width: f32, height: f32) -> Self {
+        ui.resize(width, height);
+        let mut snapshot = LayoutSnapshot::default();
+        for (i, component) in ui.grid.components().enumerate() {
+            snapshot.record(i.to_string(), component);
+        }
+        snapshot
+    }
+
+    fn record(&mut self, path: String, component: &dyn GUIComponent) {
+        self.entries.push((path.clone(), component.transform().rect()));
+        for (i, child) in component.children().into_iter().enumerate() {
+            self.record(format!("{}/{}", path, i), child);
+        }
+    }
+
+    pub fn rect(&self, path: &str) -> Option<Rect> {
+        self.entries.iter().find(|(p, _)| p == path).map(|&(_, rect)| rect)
+    }
+
+    /// Fails unless the component at `path` covers `expected` within `tolerance` pixels
+    pub fn assert_rect(&self, path: &str, expected: Rect, tolerance: f32) -> Result<()> {
+        let Some(actual) = self.rect(path) else {
+            bail!("No component at {}", path);
+        };
+        if !rects_match(actual, expected, tolerance) {
+            bail!("{}: expected {:?}, laid out at {:?}", path, expected, actual);
+        }
+        Ok(())
+    }
+
+    /// One line per component: path, x, y, width and height
+    pub fn to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(path, rect)| format!("{} {:.1} {:.1} {:.1} {:.1}\n", path, rect.x, rect.y, rect.width, rect.height))
+            .collect()
+    }
+
+    pub fn from_text(text: &str) -> Result<Self> {
+        let mut entries = Vec::new();
+        for (number, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let mut fields = line.split_whitespace();
+            let path = fields.next().unwrap_or_default().to_string();
+            let values: Vec<f32> = fields.map(str::parse).collect::<Result<_, _>>()
+                .with_context(|| format!("Line {}: bad number in {:?}", number + 1, line))?;
+            let [x, y, width, height] = values[..] else {
+                bail!("Line {}: expected a path and 4 numbers, got {:?}", number + 1, line);
+            };
+            entries.push((path, Rect::new(x, y, width, height)));
+        }
+        Ok(LayoutSnapshot { entries })
+    }
+
+    /// Compare against the golden file at `path`, rects may differ by `tolerance` pixels
+    /// The golden file is written when it doesn't exist yet or `UPDATE_SNAPSHOTS` is set.
+    pub fn assert_golden(&self, path: impl AsRef<Path>, tolerance: f32) -> Result<()> {
+        let path = path.as_ref();
+        if update_requested() || !path.exists() {
+            return write_golden(path, self.to_text().as_bytes());
+        }
+        let text = std::fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        let golden = Self::from_text(&text).with_context(|| format!("Parsing {}", path.display()))?;
+
+        let mut differences = Vec::new();
+        for (component, expected) in &golden.entries {
+            match self.rect(component) {
+                Some(actual) if rects_match(actual, *expected, tolerance) => {}
+                Some(actual) => differences.push(format!("{}: expected {:?}, laid out at {:?}", component, expected, actual)),
+                None => differences.push(format!("{}: missing", component)),
+            }
+        }
+        for (component, _) in self.entries.iter().filter(|(p, _)| golden.rect(p).is_none()) {
+            differences.push(format!("{}: not in the golden file", component));
+        }
+        if !differences.is_empty() {
+            bail!("Layout differs from {} ({} to update):\n{}", path.display(), UPDATE_ENV, differences.join("\n"));
+        }
+        Ok(())
+    }
+}
+
+fn rects_match(a: Rect, b: Rect, tolerance: f32) -> bool {
+    (a.x - b.x).abs() <= tolerance
+        && (a.y - b.y).abs() <= tolerance
+        && (a.width - b.width).abs() <= tolerance
+        && (a.height - b.height).abs() <= tolerance
+}
+
+fn write_golden(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, data).with_context(|| format!("Writing {}", path.display()))?;
+    log::info!("Wrote snapshot {}", path.display());
+    Ok(())
+}
+
+/// How far a rendered snapshot may be from its golden image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageTolerance {
+    /// Largest difference of any channel for a pixel to still match
+    pub channel: u8,
+    /// Fraction of pixels allowed to differ by more, for antialiased edges
+    pub pixels: f32,
+}
+
+impl Default for ImageTolerance {
+    fn default() -> Self {
+        ImageTolerance { channel: 2, pixels: 0.0 }
+    }
+}
+
+/// Pixels of two images that differ by more than the tolerance
+pub struct ImageDiff {
+    pub mismatched: usize,
+    pub max_delta: u8,
+    /// Mismatched pixels in red over a dimmed copy of the actual image
+    pub image: RgbaImage,
+}
+
+impl ImageDiff {
+    pub fn compare(actual: &RgbaImage, expected: &RgbaImage, channel: u8) -> Result<Self> {
+        if actual.dimensions() != expected.dimensions() {
+            bail!("Snapshot is {:?}, golden image is {:?}", actual.dimensions(), expected.dimensions());
+        }
+        let mut diff = ImageDiff { mismatched: 0, max_delta: 0, image: RgbaImage::new(actual.width(), actual.height()) };
+        for ((a, e), d) in actual.pixels().zip(expected.pixels()).zip(diff.image.pixels_mut()) {
+            let delta = a.0.iter().zip(e.0).map(|(&a, e)| a.abs_diff(e)).max().unwrap_or(0);
+            diff.max_delta = diff.max_delta.max(delta);
+            *d = if delta > channel {
+                diff.mismatched += 1;
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([a[0] / 4, a[1] / 4, a[2] / 4, 255])
+            };
+        }
+        Ok(diff)
+    }
+}
+
+/// Compare a rendered UI against the golden PNG at `path`
+/// The golden image is written when it doesn't exist yet or `UPDATE_SNAPSHOTS` is set. On a
+/// mismatch the actual and diff images are written next to it as `<name>.actual.png` and
+/// `<name>.diff.png`.
+pub fn assert_image_golden(actual: &RgbaImage, path: impl AsRef<Path>, tolerance: ImageTolerance) -> Result<()> {
+    let path = path.as_ref();
+    if update_requested() || !path.exists() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        actual.save(path).with_context(|| format!("Writing {}", path.display()))?;
+        log::info!("Wrote snapshot {}", path.display());
+        return Ok(());
+    }
+    let expected = image::open(path).with_context(|| format!("Reading {}", path.display()))?.to_rgba8();
+    let diff = ImageDiff::compare(actual, &expected, tolerance.channel)?;
+    let allowed = (tolerance.pixels * (actual.width() * actual.height()) as f32) as usize;
+    if diff.mismatched <= allowed {
+        return Ok(());
+    }
+    let actual_path = sibling(path, "actual");
+    let diff_path = sibling(path, "diff");
+    actual.save(&actual_path)?;
+    diff.image.save(&diff_path)?;
+    bail!(
+        "{} pixels differ from {} by up to {} (allowed {}), see {} ({} to update)",
+        diff.mismatched,
+        path.display(),
+        diff.max_delta,
+        allowed,
+        diff_path.display(),
+        UPDATE_ENV,
+    )
+}
+
+/// `dir/name.png` -> `dir/name.<suffix>.png`
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+/// Render `ui` laid out for `width` x `height` into an offscreen target and read it back
+///
+/// Not headless: runs a whole frame of `renderer` (the window shows nothing of it), so it
+/// needs a GPU and a renderer whose window can present, it fails while the window is
+/// minimized. Text and other retained geometry is rebuilt first.
+pub fn render_snapshot(
+    renderer: &mut Renderer,
+    context: &Arc<VulkanContext>,
+    ui: &mut UISystem,
+    width: u32,
+    height: u32,
+    background: Color,
+) -> Result<RgbaImage> {
+    ui.resize(width as f32, height as f32);
+    ui.update_geometry(context)?;
+    ui.take_damage();
+
+    let target = Texture::render_target(width, height, renderer.color_format(), &context.device, &context.instance, context.physical_device)?;
+    let result = render_into(renderer, ui, &target, background).and_then(|()| renderer.read_pixels(&target));
+    unsafe {
+        let _ = context.device.device_wait_idle();
+    }
+    target.destroy(&context.device);
+    result
+}
+
+fn render_into(renderer: &mut Renderer, ui: &UISystem, target: &Texture, background: Color) -> Result<()> {
+    let Some(frame) = renderer.begin_frame()? else {
+        bail!("The renderer skipped the frame, is the window minimized?");
+    };
+    let window_projection = renderer.projection;
+    renderer.projection = glam::Mat4::orthographic_rh(0.0, target.width as f32, 0.0, target.height as f32, -1.0, 1.0);
+    let clear = vk::ClearColorValue { float32: background.to_array() };
+    let result = frame.render_to_targets(&[(target, Some(clear))], |ctx| ui.render(ctx, renderer));
+    renderer.projection = window_projection;
+    // Submits the frame, the read back waits for it
+    drop(frame);
+    result
+}
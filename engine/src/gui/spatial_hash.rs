@@ -0,0 +1,82 @@
+use glam::{IVec2, Vec2};
+use std::collections::HashMap;
+
+use crate::math::Rect;
+
+/// Side of a hash cell in UI pixels, about the size of a small widget
+const CELL_SIZE: f32 = 64.0;
+/// Rects spanning more cells are kept in a list checked by every query instead
+const MAX_CELLS: i64 = 4096;
+
+/// Uniform hash over widget bounds for hit testing
+///
+/// Each rect is registered in every cell it overlaps, so a point query only looks at the
+/// few rects sharing its cell instead of every widget. Rebuilt whenever the layout changes.
+pub struct SpatialHash<T> {
+    cells: HashMap<IVec2, Vec<usize>>,
+    /// Rects too large to register cell by cell
+    large: Vec<usize>,
+    items: Vec<(Rect, T)>,
+}
+
+impl<T: Copy> SpatialHash<T> {
+    pub fn new() -> Self {
+        SpatialHash {
+            cells: HashMap::new(),
+            large: Vec::new(),
+            items: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.large.clear();
+        self.items.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    fn cell(point: Vec2) -> IVec2 {
+        (point / CELL_SIZE).floor().as_ivec2()
+    }
+
+    pub fn insert(&mut self, rect: Rect, item: T) {
+        let index = self.items.len();
+        self.items.push((rect, item));
+        let (min, max) = (Self::cell(rect.min()), Self::cell(rect.max()));
+        let cells = (max.x as i64 - min.x as i64 + 1) * (max.y as i64 - min.y as i64 + 1);
+        if cells > MAX_CELLS {
+            self.large.push(index);
+            return;
+        }
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(index);
+            }
+        }
+    }
+
+    /// Items whose rect contains `point`, in no particular order
+    pub fn query_point(&self, point: Vec2) -> impl Iterator<Item = T> + '_ {
+        self.cells
+            .get(&Self::cell(point))
+            .into_iter()
+            .flatten()
+            .chain(&self.large)
+            .map(|&index| &self.items[index])
+            .filter(move |(rect, _)| rect.contains_point(point))
+            .map(|&(_, item)| item)
+    }
+}
+
+impl<T: Copy> Default for SpatialHash<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
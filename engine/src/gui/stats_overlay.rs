@@ -0,0 +1,167 @@
+use anyhow::Result;
+use ash::vk;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use crate::gui::{Color, GUIComponent, PanelComponent, TextComponent, Transform};
+use crate::renderer::{FontAtlas, RenderContext, RenderStats, Renderer, VulkanContext};
+use glam::Vec2;
+
+/// Number of frames shown in the frame time graph
+const GRAPH_FRAMES: usize = 60;
+/// Frame time (ms) that fills the full graph height
+const GRAPH_MAX_MS: f32 = 33.3;
+
+/// Debug overlay with FPS, a frame time graph, draw statistics, GPU memory and entity count
+pub struct StatsOverlay {
+    background: PanelComponent,
+    lines: Vec<TextComponent>,
+    graph_bars: Vec<PanelComponent>,
+    frame_times: VecDeque<f32>,
+    entity_count: usize,
+    transform: Transform,
+    line_height: f32,
+    graph_height: f32,
+    visible: bool,
+}
+
+impl StatsOverlay {
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<Self> {
+        let mut lines = Vec::new();
+        for _ in 0..4 {
+            lines.push(TextComponent::new("-", font_atlas.clone(), font_size, descriptor_set_layout, context)?);
+        }
+
+        let mut graph_bars = Vec::with_capacity(GRAPH_FRAMES);
+        for _ in 0..GRAPH_FRAMES {
+            graph_bars.push(PanelComponent::new(context, Color::srgb(0.3, 0.8, 0.4))?);
+        }
+
+        Ok(StatsOverlay {
+            background: PanelComponent::new(context, Color::srgb(0.06, 0.06, 0.08))?,
+            lines,
+            graph_bars,
+            frame_times: VecDeque::with_capacity(GRAPH_FRAMES),
+            entity_count: 0,
+            transform: Transform::new(),
+            line_height: font_size + 2.0,
+            graph_height: 30.0,
+            visible: true,
+        })
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn toggle_visible(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Set the number of entities reported by the overlay
+    pub fn set_entity_count(&mut self, count: usize) {
+        self.entity_count = count;
+    }
+
+    /// Record the last frame and rebuild the text
+    ///
+    /// `dt` is the frame time in seconds, `stats` and `gpu_memory` usually come from
+    /// `Renderer::stats()` and `Renderer::gpu_memory_used()`.
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>, dt: f32, stats: RenderStats, gpu_memory: u64) -> Result<()> {
+        if self.frame_times.len() == GRAPH_FRAMES {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(dt * 1000.0);
+
+        if !self.visible {
+            return Ok(());
+        }
+
+        let average_ms = self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32;
+        let fps = if average_ms > 0.0 { 1000.0 / average_ms } else { 0.0 };
+
+        let texts = [
+            format!("FPS {:.1} ({:.2} ms)", fps, average_ms),
+            format!("Draw calls {} | Tris {}", stats.draw_calls, stats.triangles),
+            format!("GPU memory {:.2} MB", gpu_memory as f64 / (1024.0 * 1024.0)),
+            format!("Entities {}", self.entity_count),
+        ];
+        for (line, text) in self.lines.iter_mut().zip(texts.iter()) {
+            line.update_text(text, context)?;
+        }
+
+        self.update_layout();
+        Ok(())
+    }
+
+    fn update_layout(&mut self) {
+        *self.background.transform_mut() = self.transform;
+
+        let left = self.transform.position.x - self.transform.scale.x / 2.0 + 6.0;
+        let top = self.transform.position.y + self.transform.scale.y / 2.0;
+        let bottom = self.transform.position.y - self.transform.scale.y / 2.0 + 4.0;
+
+        for (i, line) in self.lines.iter_mut().enumerate() {
+            let y = top - self.line_height * (i as f32 + 0.5);
+            line.set_position(Vec2::new(left + line.get_width() / 2.0, y));
+        }
+
+        // Graph: newest frame on the right, bars grow upwards from the bottom edge
+        let graph_width = (self.transform.scale.x - 12.0).max(0.0);
+        let bar_width = graph_width / GRAPH_FRAMES as f32;
+        let offset = GRAPH_FRAMES - self.frame_times.len();
+        for (i, bar) in self.graph_bars.iter_mut().enumerate() {
+            let ms = if i >= offset { self.frame_times[i - offset] } else { 0.0 };
+            let height = self.graph_height * (ms / GRAPH_MAX_MS).clamp(0.0, 1.0);
+            let transform = bar.transform_mut();
+            transform.position = Vec2::new(left + bar_width * (i as f32 + 0.5), bottom + height / 2.0);
+            transform.scale = Vec2::new((bar_width - 1.0).max(1.0), height);
+        }
+    }
+}
+
+impl GUIComponent for StatsOverlay {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        self.background.render(ctx, renderer)?;
+        for line in &self.lines {
+            line.render(ctx, renderer)?;
+        }
+        for bar in &self.graph_bars {
+            if bar.transform().scale.y > 0.0 {
+                bar.render(ctx, renderer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+
+    fn destroy(&self, device: &ash::Device) {
+        self.background.destroy(device);
+        for line in &self.lines {
+            line.destroy(device);
+        }
+        for bar in &self.graph_bars {
+            bar.destroy(device);
+        }
+    }
+}
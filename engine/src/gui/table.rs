@@ -0,0 +1,485 @@
+use anyhow::Result;
+use ash::vk;
+use std::cmp::Ordering;
+use std::sync::Arc;
+use crate::gui::{Color, GUIComponent, PanelComponent, Rect, TextComponent, Transform};
+use crate::renderer::{FontAtlas, RenderContext, Renderer, VulkanContext};
+use glam::Vec2;
+
+/// Distance from a header column edge in pixels where a press starts resizing the column
+const RESIZE_GRAB: f32 = 4.0;
+/// Gap between a cell's text and its column edges
+const CELL_PADDING: f32 = 4.0;
+
+/// A column of a `TableComponent`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableColumn {
+    pub title: String,
+    /// Width in pixels, changed when the user drags the header edge
+    pub width: f32,
+    pub min_width: f32,
+    /// Clicking the header sorts by this column
+    pub sortable: bool,
+}
+
+impl TableColumn {
+    pub fn new(title: &str, width: f32) -> Self {
+        TableColumn { title: title.to_string(), width, min_width: 24.0, sortable: true }
+    }
+
+    pub fn with_min_width(mut self, min_width: f32) -> Self {
+        self.min_width = min_width;
+        self
+    }
+
+    pub fn with_sortable(mut self, sortable: bool) -> Self {
+        self.sortable = sortable;
+        self
+    }
+}
+
+/// Direction rows are sorted in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// Rows shown by a `TableComponent`, read when it refreshes
+///
+/// Only the rows that fit are turned into text, so large lists don't need to be copied
+/// into the table.
+pub trait TableSource {
+    fn row_count(&self) -> usize;
+
+    /// Stable id of a row, the selection follows it when rows are sorted or replaced
+    fn row_id(&self, row: usize) -> u64;
+
+    fn cell(&self, row: usize, column: usize) -> String;
+
+    /// Ordering of two rows by a column, used when the user sorts by it
+    /// Compares the cell text, as numbers when both cells are numbers.
+    fn compare(&self, a: usize, b: usize, column: usize) -> Ordering {
+        let (a, b) = (self.cell(a, column), self.cell(b, column));
+        match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+            (Ok(a), Ok(b)) => a.total_cmp(&b),
+            _ => a.cmp(&b),
+        }
+    }
+}
+
+/// A row of text cells, for tables whose rows are cheap to build every frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableRow {
+    pub id: u64,
+    pub cells: Vec<String>,
+}
+
+impl TableRow {
+    pub fn new(id: u64, cells: Vec<String>) -> Self {
+        TableRow { id, cells }
+    }
+}
+
+impl TableSource for [TableRow] {
+    fn row_count(&self) -> usize {
+        self.len()
+    }
+
+    fn row_id(&self, row: usize) -> u64 {
+        self[row].id
+    }
+
+    fn cell(&self, row: usize, column: usize) -> String {
+        self[row].cells.get(column).cloned().unwrap_or_default()
+    }
+}
+
+impl TableSource for Vec<TableRow> {
+    fn row_count(&self) -> usize {
+        self.len()
+    }
+
+    fn row_id(&self, row: usize) -> u64 {
+        self[row].id
+    }
+
+    fn cell(&self, row: usize, column: usize) -> String {
+        self.as_slice().cell(row, column)
+    }
+}
+
+/// Reusable visual row, cells past the current column count are kept for later
+struct TableRowView {
+    background: PanelComponent,
+    highlight: PanelComponent,
+    cells: Vec<TextComponent>,
+    id: u64,
+}
+
+/// Column drag in progress
+#[derive(Clone, Copy, Debug)]
+struct ColumnResize {
+    column: usize,
+    start_x: f32,
+    start_width: f32,
+}
+
+/// Data grid with a header of sortable, resizable columns and single row selection
+///
+/// Rows come from a `TableSource` passed to `refresh`, sorted by the column the user
+/// picked. Changes are reported through `take_selection_change` and `take_sort_change`.
+pub struct TableComponent {
+    columns: Vec<TableColumn>,
+    header: PanelComponent,
+    titles: Vec<TextComponent>,
+    /// Column edges in the header
+    separators: Vec<PanelComponent>,
+    rows: Vec<TableRowView>,
+    /// Number of rows currently in use
+    visible_rows: usize,
+    sort: Option<(usize, SortOrder)>,
+    sort_changed: bool,
+    selected: Option<u64>,
+    selection_changed: bool,
+    hovered_row: Option<usize>,
+    resizing: Option<ColumnResize>,
+    /// Hover, selection, sorting or column widths changed by input since the last `take_damage`
+    damaged: bool,
+    font_atlas: Arc<FontAtlas>,
+    font_size: f32,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    transform: Transform,
+    row_height: f32,
+}
+
+impl TableComponent {
+    pub fn new(
+        context: &Arc<VulkanContext>,
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        columns: Vec<TableColumn>,
+    ) -> Result<Self> {
+        Ok(TableComponent {
+            columns,
+            header: PanelComponent::new(context, Color::srgb(0.16, 0.16, 0.2))?,
+            titles: Vec::new(),
+            separators: Vec::new(),
+            rows: Vec::new(),
+            visible_rows: 0,
+            sort: None,
+            sort_changed: false,
+            selected: None,
+            selection_changed: false,
+            hovered_row: None,
+            resizing: None,
+            damaged: false,
+            font_atlas,
+            font_size,
+            descriptor_set_layout,
+            transform: Transform::new(),
+            row_height: font_size + 6.0,
+        })
+    }
+
+    pub fn columns(&self) -> &[TableColumn] {
+        &self.columns
+    }
+
+    /// Replace the columns, a sort by a column that no longer exists is dropped
+    pub fn set_columns(&mut self, columns: Vec<TableColumn>) {
+        if self.sort.is_some_and(|(column, _)| column >= columns.len()) {
+            self.sort = None;
+        }
+        self.columns = columns;
+    }
+
+    /// Column and direction rows are sorted by, `None` keeps the source order
+    pub fn sort(&self) -> Option<(usize, SortOrder)> {
+        self.sort
+    }
+
+    /// Sort programmatically (does not report a sort change)
+    pub fn set_sort(&mut self, sort: Option<(usize, SortOrder)>) {
+        self.sort = sort.filter(|&(column, _)| column < self.columns.len());
+    }
+
+    /// Returns the new sort if the user changed it since the last call
+    pub fn take_sort_change(&mut self) -> Option<Option<(usize, SortOrder)>> {
+        std::mem::take(&mut self.sort_changed).then_some(self.sort)
+    }
+
+    pub fn selected(&self) -> Option<u64> {
+        self.selected
+    }
+
+    /// Select a row programmatically (does not report a selection change)
+    pub fn set_selected(&mut self, id: Option<u64>) {
+        self.selected = id;
+    }
+
+    /// Returns the new selection if the user changed it since the last call
+    pub fn take_selection_change(&mut self) -> Option<Option<u64>> {
+        std::mem::take(&mut self.selection_changed).then_some(self.selected)
+    }
+
+    /// Source rows in display order
+    fn sorted_rows(&self, source: &dyn TableSource) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..source.row_count()).collect();
+        if let Some((column, direction)) = self.sort {
+            // Stable, rows that compare equal keep the source order either way
+            order.sort_by(|&a, &b| {
+                let ordering = source.compare(a, b, column);
+                match direction {
+                    SortOrder::Ascending => ordering,
+                    SortOrder::Descending => ordering.reverse(),
+                }
+            });
+        }
+        order
+    }
+
+    /// Longest prefix of `text` that fits in `width` pixels, with ".." when cut
+    fn fit_text(&self, text: &str, width: f32) -> String {
+        // Same metrics as TextComponent::get_width
+        let scale = self.font_atlas.scale_for(self.font_size);
+        let text_width = |text: &str| self.font_atlas.get_text_width(text) * scale;
+        if text_width(text) <= width {
+            return text.to_string();
+        }
+        let budget = width - text_width("..");
+        let mut used = 0.0;
+        let mut fitted = String::new();
+        for c in text.chars() {
+            let advance = self.font_atlas.get_glyph(c).map_or(0.0, |glyph| glyph.advance_width * scale);
+            if used + advance > budget {
+                break;
+            }
+            used += advance;
+            fitted.push(c);
+        }
+        fitted.push_str("..");
+        fitted
+    }
+
+    fn new_text(&self, context: &Arc<VulkanContext>) -> Result<TextComponent> {
+        TextComponent::new("-", self.font_atlas.clone(), self.font_size, self.descriptor_set_layout, context)
+    }
+
+    /// Read the visible rows from `source` in the current sort, then rebuild text and layout
+    /// (text meshes are only rebuilt when they changed)
+    /// Call once per frame before rendering
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>, source: &dyn TableSource) -> Result<()> {
+        let columns = self.columns.len();
+        while self.titles.len() < columns {
+            let title = self.new_text(context)?;
+            self.titles.push(title);
+            self.separators.push(PanelComponent::new(context, Color::srgb(0.3, 0.3, 0.36))?);
+        }
+        for (i, column) in self.columns.iter().enumerate() {
+            let marker = match self.sort {
+                Some((sorted, SortOrder::Ascending)) if sorted == i => " ^",
+                Some((sorted, SortOrder::Descending)) if sorted == i => " v",
+                _ => "",
+            };
+            let title = self.fit_text(&format!("{}{}", column.title, marker), column.width - CELL_PADDING * 2.0);
+            self.titles[i].update_text(&title, context)?;
+        }
+
+        let order = self.sorted_rows(source);
+        let max_rows = ((self.transform.scale.y - self.row_height) / self.row_height).max(0.0) as usize;
+        let count = order.len().min(max_rows);
+        while self.rows.len() < count {
+            self.rows.push(TableRowView {
+                background: PanelComponent::new(context, Color::srgb(0.25, 0.25, 0.3))?,
+                highlight: PanelComponent::new(context, Color::srgb(0.2, 0.4, 0.75))?,
+                cells: Vec::new(),
+                id: 0,
+            });
+        }
+        for (i, &row) in order.iter().enumerate().take(count) {
+            while self.rows[i].cells.len() < columns {
+                let cell = self.new_text(context)?;
+                self.rows[i].cells.push(cell);
+            }
+            for column in 0..columns {
+                let text = self.fit_text(&source.cell(row, column), self.columns[column].width - CELL_PADDING * 2.0);
+                self.rows[i].cells[column].update_text(&text, context)?;
+            }
+            self.rows[i].id = source.row_id(row);
+        }
+        self.visible_rows = count;
+
+        self.update_layout();
+        Ok(())
+    }
+
+    fn update_layout(&mut self) {
+        let left = self.transform.position.x - self.transform.scale.x / 2.0;
+        let top = self.transform.position.y + self.transform.scale.y / 2.0;
+        let width = self.transform.scale.x;
+
+        let header = self.header.transform_mut();
+        header.position = Vec2::new(left + width / 2.0, top - self.row_height / 2.0);
+        header.scale = Vec2::new(width, self.row_height);
+
+        let mut column_left = left;
+        for (i, column) in self.columns.iter().enumerate() {
+            let (Some(title), Some(separator)) = (self.titles.get_mut(i), self.separators.get_mut(i)) else {
+                break;
+            };
+            title.set_position(Vec2::new(column_left + CELL_PADDING + title.get_width() / 2.0, top - self.row_height / 2.0));
+            column_left += column.width;
+            let transform = separator.transform_mut();
+            transform.position = Vec2::new(column_left - 0.5, top - self.row_height / 2.0);
+            transform.scale = Vec2::new(1.0, self.row_height - 4.0);
+        }
+
+        for (i, row) in self.rows.iter_mut().take(self.visible_rows).enumerate() {
+            let y = top - self.row_height * (i as f32 + 1.5);
+            for panel in [&mut row.background, &mut row.highlight] {
+                let transform = panel.transform_mut();
+                transform.position = Vec2::new(left + width / 2.0, y);
+                transform.scale = Vec2::new(width, self.row_height - 2.0);
+            }
+            let mut column_left = left;
+            for (cell, column) in row.cells.iter_mut().zip(&self.columns) {
+                cell.set_position(Vec2::new(column_left + CELL_PADDING + cell.get_width() / 2.0, y));
+                column_left += column.width;
+            }
+        }
+    }
+
+    /// Whether a point is in the header row
+    fn in_header(&self, x: f32, y: f32) -> bool {
+        let top = self.transform.position.y + self.transform.scale.y / 2.0;
+        self.transform.contains_point(Vec2::new(x, y)) && y >= top - self.row_height
+    }
+
+    /// Column whose right edge is within grabbing distance of `x`
+    fn column_edge_at(&self, x: f32) -> Option<usize> {
+        let mut edge = self.transform.position.x - self.transform.scale.x / 2.0;
+        self.columns.iter().position(|column| {
+            edge += column.width;
+            (x - edge).abs() <= RESIZE_GRAB
+        })
+    }
+
+    /// Column under `x`, if any
+    fn column_at(&self, x: f32) -> Option<usize> {
+        let mut right = self.transform.position.x - self.transform.scale.x / 2.0;
+        self.columns.iter().position(|column| {
+            right += column.width;
+            x < right
+        })
+    }
+
+    /// Body row index under a point, if any
+    fn row_at(&self, x: f32, y: f32) -> Option<usize> {
+        if !self.transform.contains_point(Vec2::new(x, y)) || self.in_header(x, y) {
+            return None;
+        }
+        let top = self.transform.position.y + self.transform.scale.y / 2.0 - self.row_height;
+        let row = ((top - y) / self.row_height) as usize;
+        (row < self.visible_rows).then_some(row)
+    }
+}
+
+impl GUIComponent for TableComponent {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        self.header.render(ctx, renderer)?;
+        for (title, separator) in self.titles.iter().zip(&self.separators).take(self.columns.len()) {
+            title.render(ctx, renderer)?;
+            separator.render(ctx, renderer)?;
+        }
+        for (i, row) in self.rows.iter().take(self.visible_rows).enumerate() {
+            if Some(row.id) == self.selected {
+                row.highlight.render(ctx, renderer)?;
+            } else if Some(i) == self.hovered_row {
+                row.background.render(ctx, renderer)?;
+            }
+            for cell in row.cells.iter().take(self.columns.len()) {
+                cell.render(ctx, renderer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        if self.in_header(x, y) {
+            // Edges take priority, a press right next to one resizes instead of sorting
+            if let Some(column) = self.column_edge_at(x) {
+                self.resizing = Some(ColumnResize { column, start_x: x, start_width: self.columns[column].width });
+                return;
+            }
+            let Some(column) = self.column_at(x).filter(|&column| self.columns[column].sortable) else {
+                return;
+            };
+            self.sort = match self.sort {
+                Some((sorted, SortOrder::Ascending)) if sorted == column => Some((column, SortOrder::Descending)),
+                _ => Some((column, SortOrder::Ascending)),
+            };
+            self.sort_changed = true;
+            self.damaged = true;
+            return;
+        }
+
+        let Some(row) = self.row_at(x, y) else {
+            return;
+        };
+        let id = self.rows[row].id;
+        if self.selected != Some(id) {
+            self.selected = Some(id);
+            self.selection_changed = true;
+            self.damaged = true;
+        }
+    }
+
+    fn handle_mouse_up(&mut self, _x: f32, _y: f32) {
+        self.resizing = None;
+    }
+
+    fn handle_mouse_move(&mut self, x: f32, y: f32) {
+        if let Some(resize) = self.resizing {
+            let column = &mut self.columns[resize.column];
+            let width = (resize.start_width + x - resize.start_x).max(column.min_width);
+            if width != column.width {
+                column.width = width;
+                self.update_layout();
+                self.damaged = true;
+            }
+            return;
+        }
+        let hovered_row = self.row_at(x, y);
+        self.damaged |= hovered_row != self.hovered_row;
+        self.hovered_row = hovered_row;
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.transform.rect())
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.header.destroy(device);
+        for (title, separator) in self.titles.iter().zip(&self.separators) {
+            title.destroy(device);
+            separator.destroy(device);
+        }
+        for row in &self.rows {
+            row.background.destroy(device);
+            row.highlight.destroy(device);
+            for cell in &row.cells {
+                cell.destroy(device);
+            }
+        }
+    }
+}
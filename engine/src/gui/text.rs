@@ -1,26 +1,64 @@
 use anyhow::Result;
 use std::sync::Arc;
 use ash::vk;
-use crate::gui::{GUIComponent, Transform};
-use crate::renderer::{RenderContext, Renderer, FontAtlas, TexturedVertex2D, VertexBuffer, Mesh, PipelineId, PushConstants2D, SampledTexture, SamplerConfig};
+use crate::gui::{AccessTree, Color, GUIComponent, TextEffects, Transform};
+use accesskit::{NodeBuilder, Role};
+use crate::renderer::{RenderContext, Renderer, FontAtlas, TexturedVertex2D, VertexBuffer, Mesh, PipelineId, TextPushConstants, SampledTexture, SamplerConfig};
 use glam::Vec2;
 
+/// How glyph quads are placed on the pixel grid
+/// Positions are snapped in UI units, which are only whole pixels at a UI scale of 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GlyphSnap {
+    /// Where the layout puts them, smooth when text moves but blurry at rest
+    None,
+    /// Baseline on whole pixels, glyphs keep their fractional horizontal positions
+    #[default]
+    Baseline,
+    /// Every glyph on whole pixels, sharpest but spacing is off by up to half a pixel
+    Pixel,
+}
+
+/// How a `TextComponent` is rasterized, see `TextComponent::set_rendering`
+/// Coverage is gamma corrected by `Renderer::text_gamma` in every mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextRendering {
+    pub snap: GlyphSnap,
+    /// Sample coverage per color channel for LCD panels with horizontal RGB stripes
+    /// Only for text that is neither rotated nor drawn into a target that gets scaled.
+    pub lcd: bool,
+}
+
 /// A text rendering component that displays text using a font atlas
 pub struct TextComponent {
     text: String,
     font_atlas: Arc<FontAtlas>,
+    /// Page of `font_atlas` rasterized for `font_size`, see `FontAtlas::for_size`
+    page: Arc<FontAtlas>,
+    descriptor_set_layout: vk::DescriptorSetLayout,
     transform: Transform,
-    color: [f32; 3],
+    color: Color,
     font_size: f32,
+    rendering: TextRendering,
     mesh: Mesh<TexturedVertex2D>,
+    /// Character index of each glyph quad in `mesh`
+    glyph_chars: Vec<usize>,
+    effects: Option<TextEffects>,
+    /// An animation changed since the last `take_damage`
+    damaged: bool,
     sampled_texture: SampledTexture,
 }
 
 impl TextComponent {
     /// Helper function to build text vertices
-    fn build_text_vertices(text: &str, font_atlas: &FontAtlas, font_size: f32) -> Vec<TexturedVertex2D> {
+    /// Also returns the character index of each glyph quad, characters without one (spaces)
+    /// are skipped.
+    fn build_text_vertices(text: &str, font_atlas: &FontAtlas, font_size: f32, snap: GlyphSnap) -> (Vec<TexturedVertex2D>, Vec<usize>) {
         let mut vertices = Vec::new();
-        let scale = 0.5;  // Atlas is at 2x font_size
+        let mut glyph_chars = Vec::new();
+        let scale = font_atlas.scale_for(font_size);
+        let snap_x = |x: f32| if snap == GlyphSnap::Pixel { x.round() } else { x };
+        let snap_y = |y: f32| if snap == GlyphSnap::None { y } else { y.round() };
         
         let total_width: f32 = text.chars().filter_map(|ch| {
             font_atlas.get_glyph(ch).map(|g| g.advance_width * scale)
@@ -42,43 +80,46 @@ impl TextComponent {
         
         // Center the text vertically around y=0
         let text_height = max_y - min_y;
-        let baseline_y = -text_height / 2.0 - min_y;
+        let baseline_y = snap_y(-text_height / 2.0 - min_y);
         
-        let start_x = -total_width / 2.0;
+        let start_x = snap_x(-total_width / 2.0);
         let mut x = start_x;
 
-        for ch in text.chars() {
+        for (char_index, ch) in text.chars().enumerate() {
             if let Some(glyph) = font_atlas.get_glyph(ch) {
                 let width = glyph.width * scale;
                 let height = glyph.height * scale;
                 
                 if width > 0.0 && height > 0.0 {
+                    glyph_chars.push(char_index);
                     let bearing_x = glyph.bearing_x * scale;
                     let bearing_y = glyph.bearing_y * scale;
-                    let y = baseline_y - bearing_y;
+                    // Quads start on whole pixels so the 2x atlas texels line up with them
+                    let left = snap_x(x + bearing_x);
+                    let y = snap_y(baseline_y - bearing_y);
 
                     vertices.push(TexturedVertex2D {
-                        position: [x + bearing_x, y],
+                        position: [left, y],
                         uv: [glyph.uv_min.x, glyph.uv_min.y],
                     });
                     vertices.push(TexturedVertex2D {
-                        position: [x + bearing_x + width, y],
+                        position: [left + width, y],
                         uv: [glyph.uv_max.x, glyph.uv_min.y],
                     });
                     vertices.push(TexturedVertex2D {
-                        position: [x + bearing_x, y + height],
+                        position: [left, y + height],
                         uv: [glyph.uv_min.x, glyph.uv_max.y],
                     });
                     vertices.push(TexturedVertex2D {
-                        position: [x + bearing_x + width, y],
+                        position: [left + width, y],
                         uv: [glyph.uv_max.x, glyph.uv_min.y],
                     });
                     vertices.push(TexturedVertex2D {
-                        position: [x + bearing_x + width, y + height],
+                        position: [left + width, y + height],
                         uv: [glyph.uv_max.x, glyph.uv_max.y],
                     });
                     vertices.push(TexturedVertex2D {
-                        position: [x + bearing_x, y + height],
+                        position: [left, y + height],
                         uv: [glyph.uv_min.x, glyph.uv_max.y],
                     });
                 }
@@ -87,7 +128,7 @@ impl TextComponent {
             }
         }
         
-        vertices
+        (vertices, glyph_chars)
     }
     
     /// Create a new text component
@@ -101,31 +142,39 @@ impl TextComponent {
         descriptor_set_layout: vk::DescriptorSetLayout,
         context: &Arc<crate::renderer::VulkanContext>,
     ) -> Result<Self> {
-        let vertices = Self::build_text_vertices(text, &font_atlas, font_size);
+        let rendering = TextRendering::default();
+        let page = font_atlas.for_size(font_size, context)?;
+        let (vertices, glyph_chars) = Self::build_text_vertices(text, &page, font_size, rendering.snap);
 
         let vertex_buffer = VertexBuffer::new(&context.device, context.physical_device, &context.instance, &vertices)?;
 
         // Create sampled texture with linear filtering for smooth text
         let sampled_texture = SampledTexture::new(
-            &font_atlas.texture,
+            &page.texture,
             SamplerConfig::linear(),
             descriptor_set_layout,
-            &context.device,
+            context,
         )?;
 
         Ok(TextComponent {
             text: text.to_string(),
             font_atlas,
+            page,
+            descriptor_set_layout,
             transform: Transform::new(),
-            color: [1.0, 1.0, 1.0],
+            color: Color::WHITE,
             font_size,
+            rendering,
             mesh: Mesh::new(vertex_buffer),
+            glyph_chars,
+            effects: None,
+            damaged: false,
             sampled_texture,
         })
     }
 
-    /// Set the text color (RGB)
-    pub fn set_color(&mut self, color: [f32; 3]) {
+    /// Set the text color
+    pub fn set_color(&mut self, color: Color) {
         self.color = color;
     }
 
@@ -135,7 +184,47 @@ impl TextComponent {
         if self.text == text {
             return Ok(());
         }
-        
+        self.text = text.to_string();
+        self.rebuild_mesh(context)
+    }
+
+    /// Change how glyphs are snapped and sampled, the mesh is rebuilt if the snapping changed
+    pub fn set_rendering(&mut self, rendering: TextRendering, context: &Arc<crate::renderer::VulkanContext>) -> Result<()> {
+        let snap_changed = self.rendering.snap != rendering.snap;
+        self.rendering = rendering;
+        if snap_changed {
+            self.rebuild_mesh(context)?;
+        }
+        Ok(())
+    }
+
+    pub fn rendering(&self) -> TextRendering {
+        self.rendering
+    }
+
+    /// Change the size of the text, drawn from a page of the atlas rasterized for it
+    pub fn set_font_size(&mut self, font_size: f32, context: &Arc<crate::renderer::VulkanContext>) -> Result<()> {
+        if self.font_size == font_size {
+            return Ok(());
+        }
+        let page = self.font_atlas.for_size(font_size, context)?;
+        if !Arc::ptr_eq(&page, &self.page) {
+            let sampled_texture = SampledTexture::new(&page.texture, SamplerConfig::linear(), self.descriptor_set_layout, context)?;
+            unsafe {
+                let _ = context.device.device_wait_idle();
+            }
+            std::mem::replace(&mut self.sampled_texture, sampled_texture).destroy(&context.device);
+            self.page = page;
+        }
+        self.font_size = font_size;
+        self.rebuild_mesh(context)
+    }
+
+    pub fn font_size(&self) -> f32 {
+        self.font_size
+    }
+
+    fn rebuild_mesh(&mut self, context: &Arc<crate::renderer::VulkanContext>) -> Result<()> {
         // Wait for GPU to finish using old mesh before destroying it
         unsafe {
             let _ = context.device.device_wait_idle();
@@ -144,16 +233,20 @@ impl TextComponent {
         // Destroy old mesh before creating new one
         self.mesh.destroy(&context.device);
         
-        self.text = text.to_string();
-        let vertices = Self::build_text_vertices(text, &self.font_atlas, self.font_size);
+        let (vertices, glyph_chars) = Self::build_text_vertices(&self.text, &self.page, self.font_size, self.rendering.snap);
+        self.glyph_chars = glyph_chars;
         let vertex_buffer = VertexBuffer::new(&context.device, context.physical_device, &context.instance, &vertices)?;
         self.mesh = Mesh::new(vertex_buffer);
         Ok(())
     }
 
-    /// Get the width of the current text at the given font size
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the width of the current text in pixels (matches the built mesh)
     pub fn get_width(&self) -> f32 {
-        self.font_atlas.get_text_width(&self.text) * (self.font_size / 128.0)
+        self.page.text_width(&self.text, self.font_size)
     }
 
     /// Get the height (approximate, based on font size)
@@ -165,6 +258,31 @@ impl TextComponent {
     pub fn set_position(&mut self, position: Vec2) {
         self.transform.position = position;
     }
+
+    /// Color, move or reveal single characters, `None` draws the text plainly again
+    /// Character indices of the spans refer to the current text.
+    pub fn set_effects(&mut self, effects: Option<TextEffects>) {
+        self.effects = effects;
+    }
+
+    pub fn effects(&self) -> Option<&TextEffects> {
+        self.effects.as_ref()
+    }
+
+    pub fn effects_mut(&mut self) -> Option<&mut TextEffects> {
+        self.effects.as_mut()
+    }
+
+    fn char_count(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// Area the text covers, animations included
+    fn bounds(&self) -> crate::math::Rect {
+        let margin = self.effects.as_ref().map_or(0.0, TextEffects::max_offset);
+        crate::math::Rect::from_center_size(self.transform.position, Vec2::new(self.get_width(), self.get_height()))
+            .inflate(margin)
+    }
 }
 
 impl GUIComponent for TextComponent {
@@ -175,35 +293,66 @@ impl GUIComponent for TextComponent {
         ctx.bind_pipeline(pipeline);
 
         // Bind descriptor set for font texture
-        ctx.bind_descriptor_sets(
-            vk::PipelineBindPoint::GRAPHICS,
-            pipeline_layout,
-            0,
-            &[self.sampled_texture.descriptor_set],
-            &[],
-        );
-
-        let push = PushConstants2D {
-            projection: renderer.projection,
-            transform: glam::Mat4::from_translation(glam::Vec3::new(
-                self.transform.position.x,
-                self.transform.position.y,
-                0.0,
-            )) * glam::Mat4::from_scale(glam::Vec3::new(
-                self.transform.scale.x,
-                self.transform.scale.y,
-                1.0,
-            )),
-            color_modulation: self.color,  // Use text color
-            _padding: 0.0,
+        ctx.bind_descriptor_set_at(pipeline_layout, 0, self.sampled_texture.descriptor_set);
+
+        // The mesh is snapped relative to the position, which has to land on whole pixels too
+        let position = self.transform.position;
+        let position = match self.rendering.snap {
+            GlyphSnap::None => position,
+            GlyphSnap::Baseline => Vec2::new(position.x, position.y.round()),
+            GlyphSnap::Pixel => position.round(),
         };
+        let transform = glam::Mat4::from_translation(glam::Vec3::new(
+            position.x,
+            position.y,
+            0.0,
+        )) * glam::Mat4::from_scale(glam::Vec3::new(
+            self.transform.scale.x,
+            self.transform.scale.y,
+            1.0,
+        ));
+        let push = TextPushConstants::new(renderer.projection, transform)
+            .with_modulation(self.color.rgb())  // Use text color
+            .with_opacity(renderer.opacity)
+            .with_gamma(renderer.text_gamma)
+            .with_lcd(self.rendering.lcd);
 
-        ctx.push_constants(pipeline_layout, &push);
-        self.mesh.draw(ctx)?;
+        let Some(effects) = &self.effects else {
+            ctx.push(pipeline_layout, &push);
+            return self.mesh.draw(ctx);
+        };
 
+        // One draw per glyph, each with its own offset and color
+        ctx.bind_vertex_buffer(self.mesh.vertex_buffer.buffer);
+        for (quad, &char_index) in self.glyph_chars.iter().enumerate() {
+            let style = effects.glyph(char_index, self.color);
+            if !style.visible {
+                continue;
+            }
+            let offset = glam::Mat4::from_translation(style.offset.extend(0.0));
+            let mut glyph_push = push.with_modulation(style.color.rgb());
+            glyph_push.transform = offset * transform;
+            ctx.push(pipeline_layout, &glyph_push);
+            ctx.draw(6, 1, quad as u32 * 6, 0);
+        }
         Ok(())
     }
 
+    fn update(&mut self, dt: f32) {
+        let len = self.char_count();
+        if let Some(effects) = &mut self.effects {
+            self.damaged |= effects.advance(dt, len);
+        }
+    }
+
+    fn next_update(&self) -> Option<f32> {
+        self.effects.as_ref()?.next_update(self.char_count())
+    }
+
+    fn take_damage(&mut self) -> Option<crate::math::Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.bounds())
+    }
+
     fn transform(&self) -> &Transform {
         &self.transform
     }
@@ -212,16 +361,20 @@ impl GUIComponent for TextComponent {
         &mut self.transform
     }
 
-    fn handle_mouse_down(&mut self, _x: f32, _y: f32) {
-        // Text doesn't handle input yet
+    /// Centered in `rect`, the mesh is already in pixels so the scale stays as it is
+    fn set_layout(&mut self, rect: crate::math::Rect) {
+        self.transform.position = rect.center();
     }
 
-    fn handle_mouse_up(&mut self, _x: f32, _y: f32) {
-        // Text doesn't handle input yet
+    fn measure(&self, constraints: Vec2) -> Vec2 {
+        Vec2::new(self.get_width(), self.get_height()).min(constraints)
     }
 
-    fn handle_mouse_move(&mut self, _x: f32, _y: f32) {
-        // Text doesn't handle input yet
+    fn accessibility(&self, tree: &mut AccessTree) {
+        let mut node = NodeBuilder::new(Role::StaticText);
+        node.set_name(self.text.as_str());
+        let size = Vec2::new(self.get_width(), self.get_height());
+        tree.add(node, crate::math::Rect::from_center_size(self.transform.position, size));
     }
 
     fn destroy(&self, device: &ash::Device) {
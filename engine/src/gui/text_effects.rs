@@ -0,0 +1,178 @@
+use std::ops::Range;
+use glam::Vec2;
+use crate::math::Color;
+
+/// Radians between the wave phases of neighbouring characters
+const WAVE_PHASE_STEP: f32 = 0.6;
+/// New shake offsets per second
+const SHAKE_RATE: f32 = 20.0;
+
+/// Animation moving characters of a `TextSpan`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlyphAnimation {
+    /// Characters bob up and down one after another, `amplitude` in pixels and `speed` in
+    /// waves per second
+    Wave { amplitude: f32, speed: f32 },
+    /// Characters jitter in random directions up to `amplitude` pixels
+    Shake { amplitude: f32 },
+}
+
+impl GlyphAnimation {
+    fn amplitude(&self) -> f32 {
+        match *self {
+            GlyphAnimation::Wave { amplitude, .. } | GlyphAnimation::Shake { amplitude } => amplitude.abs(),
+        }
+    }
+
+    fn offset(&self, index: usize, time: f32) -> Vec2 {
+        match *self {
+            GlyphAnimation::Wave { amplitude, speed } => {
+                let phase = time * speed * std::f32::consts::TAU - index as f32 * WAVE_PHASE_STEP;
+                Vec2::new(0.0, amplitude * phase.sin())
+            }
+            GlyphAnimation::Shake { amplitude } => {
+                let step = (time * SHAKE_RATE) as u32;
+                Vec2::new(noise(index as u32, step, 0), noise(index as u32, step, 1)) * amplitude
+            }
+        }
+    }
+}
+
+/// Repeatable pseudo random value in -1..1
+fn noise(index: u32, step: u32, axis: u32) -> f32 {
+    let mut x = index.wrapping_mul(0x9E37_79B9) ^ step.wrapping_mul(0x85EB_CA6B) ^ axis.wrapping_mul(0xC2B2_AE35);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+/// Color and animation of a range of characters
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextSpan {
+    /// Character indices, not bytes
+    pub range: Range<usize>,
+    pub color: Option<Color>,
+    pub animation: Option<GlyphAnimation>,
+}
+
+impl TextSpan {
+    pub fn new(range: Range<usize>) -> Self {
+        TextSpan { range, color: None, animation: None }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_animation(mut self, animation: GlyphAnimation) -> Self {
+        self.animation = Some(animation);
+        self
+    }
+}
+
+/// Where and how a single character is drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphStyle {
+    pub offset: Vec2,
+    pub color: Color,
+    pub visible: bool,
+}
+
+/// Per-character colors and animations of a `TextComponent`, e.g. for dialogue boxes
+///
+/// Spans later in the list win where they overlap. With a typewriter speed the text is
+/// revealed one character at a time, starting over on `restart`. Animations run on
+/// `GUIComponent::update`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextEffects {
+    pub spans: Vec<TextSpan>,
+    /// Characters revealed per second, `None` shows the whole text at once
+    pub typewriter: Option<f32>,
+    time: f32,
+    /// Characters revealed so far, fractional between two
+    revealed: f32,
+}
+
+impl TextEffects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_span(mut self, span: TextSpan) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    pub fn with_typewriter(mut self, chars_per_second: f32) -> Self {
+        self.typewriter = Some(chars_per_second);
+        self
+    }
+
+    /// Reveal the text again from its first character and restart the animations
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+        self.revealed = 0.0;
+    }
+
+    /// Show every character right away, e.g. when the player skips ahead
+    pub fn finish_reveal(&mut self) {
+        self.revealed = f32::INFINITY;
+    }
+
+    /// Whether characters of a `len` character text are still being revealed
+    pub fn is_revealing(&self, len: usize) -> bool {
+        self.typewriter.is_some() && self.revealed < len as f32
+    }
+
+    /// Characters shown so far
+    pub fn revealed(&self) -> usize {
+        if self.typewriter.is_some() { self.revealed as usize } else { usize::MAX }
+    }
+
+    /// Whether any span moves its characters
+    pub fn is_animated(&self) -> bool {
+        self.spans.iter().any(|span| span.animation.is_some())
+    }
+
+    /// Advance by `dt` seconds, returns whether anything looks different
+    pub(crate) fn advance(&mut self, dt: f32, len: usize) -> bool {
+        self.time += dt;
+        let revealing = self.is_revealing(len);
+        if let Some(speed) = self.typewriter {
+            let before = self.revealed as usize;
+            self.revealed = (self.revealed + speed * dt).min(len as f32);
+            return self.is_animated() || (revealing && self.revealed as usize != before);
+        }
+        self.is_animated()
+    }
+
+    /// Seconds until the next change of a `len` character text, `None` once it's still
+    pub(crate) fn next_update(&self, len: usize) -> Option<f32> {
+        if self.is_animated() {
+            return Some(0.0);
+        }
+        let speed = self.typewriter.filter(|_| self.is_revealing(len))?;
+        Some((1.0 - self.revealed.fract()) / speed.max(f32::EPSILON))
+    }
+
+    /// Largest distance an animation moves a character
+    pub(crate) fn max_offset(&self) -> f32 {
+        self.spans.iter().filter_map(|span| span.animation).map(|animation| animation.amplitude()).fold(0.0, f32::max)
+    }
+
+    /// Style of character `index` of text drawn in `color`
+    pub fn glyph(&self, index: usize, color: Color) -> GlyphStyle {
+        let mut style = GlyphStyle { offset: Vec2::ZERO, color, visible: index < self.revealed() };
+        for span in self.spans.iter().filter(|span| span.range.contains(&index)) {
+            if let Some(color) = span.color {
+                style.color = color;
+            }
+            if let Some(animation) = span.animation {
+                style.offset = animation.offset(index - span.range.start, self.time);
+            }
+        }
+        style
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use glam::{Mat4, Vec2};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use crate::ecs::{TrailRenderer, World};
+use crate::renderer::{ColorVertex2D, PipelineId, PushConstants2D, RenderContext, Renderer, VertexBuffer, VulkanContext};
+
+/// Uploaded buffers are kept alive for this many frames, longer than any frame stays in flight
+const BUFFERS_KEPT: usize = 3;
+
+/// Ribbons of the World's `TrailRenderer`s, rebuilt into a new vertex buffer every frame
+///
+/// Trails change every frame they move, so the triangles of all of them are uploaded
+/// together by `update` and drawn with one call in the scene pass.
+pub(super) struct TrailBatch {
+    vertices: Vec<ColorVertex2D>,
+    /// Buffers of recent updates that may still be read by the GPU
+    buffers: VecDeque<VertexBuffer<ColorVertex2D>>,
+    /// The newest buffer holds this frame's trails, false if there were none
+    uploaded: bool,
+}
+
+impl TrailBatch {
+    pub(super) fn new() -> Self {
+        TrailBatch { vertices: Vec::new(), buffers: VecDeque::with_capacity(BUFFERS_KEPT + 1), uploaded: false }
+    }
+
+    /// Build and upload the ribbons of every trail in `world`
+    pub(super) fn update(&mut self, context: &Arc<VulkanContext>, world: &World) -> Result<()> {
+        self.vertices.clear();
+        for id in world.entity_ids() {
+            if let Some(trail) = world.get::<TrailRenderer>(id) {
+                ribbon(trail, &mut self.vertices);
+            }
+        }
+        self.uploaded = !self.vertices.is_empty();
+        if !self.uploaded {
+            return Ok(());
+        }
+
+        let buffer = VertexBuffer::new(&context.device, context.physical_device, &context.instance, &self.vertices)?;
+        self.buffers.push_back(buffer);
+        while self.buffers.len() > BUFFERS_KEPT {
+            if let Some(old) = self.buffers.pop_front() {
+                old.destroy(&context.device);
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the trails of the last `update` into the scene targets of the current pass
+    pub(super) fn draw(&self, ctx: &RenderContext, renderer: &mut Renderer, view_projection: Mat4) -> Result<()> {
+        let Some(buffer) = self.buffers.back().filter(|_| self.uploaded) else {
+            return Ok(());
+        };
+        let pipeline = renderer.get_pipeline(PipelineId::Trails)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::Trails)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for Trails pipeline"))?;
+        ctx.bind_pipeline(pipeline);
+        ctx.push(pipeline_layout, &PushConstants2D::new(view_projection, Mat4::IDENTITY));
+        ctx.bind_vertex_buffer(buffer.buffer);
+        ctx.draw(buffer.vertex_count, 1, 0, 0);
+        Ok(())
+    }
+
+    pub(super) fn destroy(&self, device: &ash::Device) {
+        for buffer in &self.buffers {
+            buffer.destroy(device);
+        }
+    }
+}
+
+/// Append the triangles of a trail, a strip of quads between its points, each point
+/// widened across the direction the trail runs there (alpha isn't shown)
+fn ribbon(trail: &TrailRenderer, vertices: &mut Vec<ColorVertex2D>) {
+    let points: Vec<_> = trail.points().collect();
+    if points.len() < 2 {
+        return;
+    }
+    // Left and right edge and color of each point
+    let mut side = Vec2::X;
+    let edges: Vec<(Vec2, Vec2, [f32; 3])> = (0..points.len())
+        .map(|i| {
+            let before = points[i.saturating_sub(1)].0;
+            let after = points[(i + 1).min(points.len() - 1)].0;
+            // Points on top of each other keep the previous direction
+            if let Some(direction) = (after - before).try_normalize() {
+                side = direction.perp();
+            }
+            let (position, width, color) = points[i];
+            let half = side * width.max(0.0) * 0.5;
+            (position + half, position - half, color.rgb())
+        })
+        .collect();
+
+    for pair in edges.windows(2) {
+        let ((left_a, right_a, color_a), (left_b, right_b, color_b)) = (pair[0], pair[1]);
+        for (position, color) in [
+            (left_a, color_a), (right_a, color_a), (right_b, color_b),
+            (left_a, color_a), (right_b, color_b), (left_b, color_b),
+        ] {
+            vertices.push(ColorVertex2D { position: position.into(), color });
+        }
+    }
+}
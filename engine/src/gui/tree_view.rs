@@ -0,0 +1,309 @@
+use anyhow::Result;
+use ash::vk;
+use std::collections::HashSet;
+use std::sync::Arc;
+use crate::gui::{AccessAction, AccessTree, Color, GUIComponent, PanelComponent, Rect, TextComponent, Transform};
+use accesskit::{Action, DefaultActionVerb, NodeBuilder, Role};
+use crate::renderer::{FontAtlas, RenderContext, Renderer, VulkanContext};
+use glam::Vec2;
+
+/// Horizontal indent per nesting level in pixels
+const INDENT: f32 = 14.0;
+
+/// A single node of a tree, given in pre-order (parents before their children)
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeItem {
+    pub id: u64,
+    pub label: String,
+    pub depth: usize,
+    pub has_children: bool,
+}
+
+/// Reusable visual row
+struct TreeRow {
+    background: PanelComponent,
+    highlight: PanelComponent,
+    label: TextComponent,
+    /// Index into `items` shown by this row
+    item: usize,
+}
+
+/// Collapsible tree of labelled rows with single selection
+pub struct TreeView {
+    items: Vec<TreeItem>,
+    rows: Vec<TreeRow>,
+    /// Number of rows currently in use
+    visible_rows: usize,
+    collapsed: HashSet<u64>,
+    selected: Option<u64>,
+    selection_changed: bool,
+    hovered_row: Option<usize>,
+    /// Hover, selection or expansion changed by input since the last `take_damage`
+    damaged: bool,
+    font_atlas: Arc<FontAtlas>,
+    font_size: f32,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    transform: Transform,
+    row_height: f32,
+}
+
+impl TreeView {
+    pub fn new(
+        font_atlas: Arc<FontAtlas>,
+        font_size: f32,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Self {
+        TreeView {
+            items: Vec::new(),
+            rows: Vec::new(),
+            visible_rows: 0,
+            collapsed: HashSet::new(),
+            selected: None,
+            selection_changed: false,
+            hovered_row: None,
+            damaged: false,
+            font_atlas,
+            font_size,
+            descriptor_set_layout,
+            transform: Transform::new(),
+            row_height: font_size + 6.0,
+        }
+    }
+
+    /// Replace the tree contents
+    pub fn set_items(&mut self, items: Vec<TreeItem>) {
+        self.items = items;
+    }
+
+    pub fn items(&self) -> &[TreeItem] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> Option<u64> {
+        self.selected
+    }
+
+    /// Select an item programmatically (does not report a selection change)
+    pub fn set_selected(&mut self, id: Option<u64>) {
+        self.selected = id;
+    }
+
+    /// Returns the new selection if the user changed it since the last call
+    pub fn take_selection_change(&mut self) -> Option<Option<u64>> {
+        if self.selection_changed {
+            self.selection_changed = false;
+            Some(self.selected)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_expanded(&mut self, id: u64, expanded: bool) {
+        if expanded {
+            self.collapsed.remove(&id);
+        } else {
+            self.collapsed.insert(id);
+        }
+    }
+
+    pub fn is_expanded(&self, id: u64) -> bool {
+        !self.collapsed.contains(&id)
+    }
+
+    /// Indices of items not hidden by a collapsed ancestor
+    fn visible_items(&self) -> Vec<usize> {
+        let mut visible = Vec::new();
+        let mut hidden_below: Option<usize> = None;
+        for (i, item) in self.items.iter().enumerate() {
+            if let Some(depth) = hidden_below {
+                if item.depth > depth {
+                    continue;
+                }
+                hidden_below = None;
+            }
+            visible.push(i);
+            if item.has_children && self.collapsed.contains(&item.id) {
+                hidden_below = Some(item.depth);
+            }
+        }
+        visible
+    }
+
+    /// Rebuild row text and layout (text meshes are only rebuilt when they changed)
+    /// Call once per frame before rendering
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        let visible = self.visible_items();
+        let max_rows = (self.transform.scale.y / self.row_height).max(0.0) as usize;
+        let count = visible.len().min(max_rows);
+
+        while self.rows.len() < count {
+            self.rows.push(TreeRow {
+                background: PanelComponent::new(context, Color::srgb(0.25, 0.25, 0.3))?,
+                highlight: PanelComponent::new(context, Color::srgb(0.2, 0.4, 0.75))?,
+                label: TextComponent::new("-", self.font_atlas.clone(), self.font_size, self.descriptor_set_layout, context)?,
+                item: 0,
+            });
+        }
+
+        for (row, &item_index) in self.rows.iter_mut().zip(visible.iter()).take(count) {
+            let item = &self.items[item_index];
+            let prefix = match (item.has_children, self.collapsed.contains(&item.id)) {
+                (false, _) => "  ",
+                (true, true) => "+ ",
+                (true, false) => "- ",
+            };
+            row.label.update_text(&format!("{}{}", prefix, item.label), context)?;
+            row.item = item_index;
+        }
+        self.visible_rows = count;
+
+        self.update_layout();
+        Ok(())
+    }
+
+    fn update_layout(&mut self) {
+        let left = self.transform.position.x - self.transform.scale.x / 2.0;
+        let top = self.transform.position.y + self.transform.scale.y / 2.0;
+        let width = self.transform.scale.x;
+
+        for (i, row) in self.rows.iter_mut().take(self.visible_rows).enumerate() {
+            let y = top - self.row_height * (i as f32 + 0.5);
+            let depth = self.items[row.item].depth as f32;
+
+            for panel in [&mut row.background, &mut row.highlight] {
+                let transform = panel.transform_mut();
+                transform.position = Vec2::new(left + width / 2.0, y);
+                transform.scale = Vec2::new(width, self.row_height - 2.0);
+            }
+
+            let text_left = left + 4.0 + depth * INDENT;
+            row.label.set_position(Vec2::new(text_left + row.label.get_width() / 2.0, y));
+        }
+    }
+
+    /// Row index under a point, if any
+    fn row_at(&self, x: f32, y: f32) -> Option<usize> {
+        if !self.transform.contains_point(Vec2::new(x, y)) {
+            return None;
+        }
+        let top = self.transform.position.y + self.transform.scale.y / 2.0;
+        let row = ((top - y) / self.row_height) as usize;
+        (row < self.visible_rows).then_some(row)
+    }
+}
+
+impl GUIComponent for TreeView {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        for (i, row) in self.rows.iter().take(self.visible_rows).enumerate() {
+            // Items may have been replaced since the last refresh
+            let Some(item) = self.items.get(row.item) else {
+                continue;
+            };
+            if Some(item.id) == self.selected {
+                row.highlight.render(ctx, renderer)?;
+            } else if Some(i) == self.hovered_row {
+                row.background.render(ctx, renderer)?;
+            }
+            row.label.render(ctx, renderer)?;
+        }
+        Ok(())
+    }
+
+    fn handle_mouse_down(&mut self, x: f32, y: f32) {
+        let Some(row) = self.row_at(x, y) else {
+            return;
+        };
+        let Some(item) = self.items.get(self.rows[row].item) else {
+            return;
+        };
+        let id = item.id;
+
+        // Clicking the +/- marker toggles the node, anywhere else selects it
+        let left = self.transform.position.x - self.transform.scale.x / 2.0;
+        let marker_right = left + 4.0 + item.depth as f32 * INDENT + INDENT;
+        if item.has_children && x < marker_right {
+            let expanded = self.is_expanded(id);
+            self.set_expanded(id, !expanded);
+            self.damaged = true;
+            return;
+        }
+
+        if self.selected != Some(id) {
+            self.selected = Some(id);
+            self.selection_changed = true;
+            self.damaged = true;
+        }
+    }
+
+
+    fn handle_mouse_move(&mut self, x: f32, y: f32) {
+        let hovered_row = self.row_at(x, y);
+        self.damaged |= hovered_row != self.hovered_row;
+        self.hovered_row = hovered_row;
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.transform.rect())
+    }
+
+    /// Visible rows as a flat list of items with their level, like the rows are drawn
+    fn accessibility(&self, tree: &mut AccessTree) {
+        tree.push(NodeBuilder::new(Role::Tree), self.transform.rect());
+        for row in self.rows.iter().take(self.visible_rows) {
+            let Some(item) = self.items.get(row.item) else {
+                continue;
+            };
+            let mut node = NodeBuilder::new(Role::TreeItem);
+            node.set_name(item.label.as_str());
+            node.set_hierarchical_level(item.depth + 1);
+            node.set_selected(Some(item.id) == self.selected);
+            node.set_default_action_verb(DefaultActionVerb::Click);
+            if item.has_children {
+                node.set_expanded(self.is_expanded(item.id));
+                node.add_action(Action::Expand);
+                node.add_action(Action::Collapse);
+            }
+            tree.add(node, row.background.transform().rect());
+        }
+        tree.pop();
+    }
+
+    fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+        let Some(row) = self.row_at(action.point.x, action.point.y) else {
+            return false;
+        };
+        let Some(item) = self.items.get(self.rows[row].item) else {
+            return false;
+        };
+        let id = item.id;
+        match action.action {
+            Action::Expand | Action::Collapse if item.has_children => {
+                self.set_expanded(id, action.action == Action::Expand);
+            }
+            // The center of a row is past its +/- marker, a click there would select too
+            Action::Default => {
+                self.selection_changed |= self.selected != Some(id);
+                self.selected = Some(id);
+            }
+            _ => return false,
+        }
+        self.damaged = true;
+        true
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        for row in &self.rows {
+            row.background.destroy(device);
+            row.highlight.destroy(device);
+            row.label.destroy(device);
+        }
+    }
+}
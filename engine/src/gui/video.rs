@@ -0,0 +1,155 @@
+use anyhow::Result;
+use ash::vk;
+use std::path::Path;
+use std::sync::Arc;
+use crate::gui::{GUIComponent, Rect, Transform};
+use crate::renderer::{Mesh, PipelineId, PushConstants2D, RenderContext, Renderer, SampledTexture, SamplerConfig, TexturedVertex2D, VertexBuffer, VulkanContext};
+use crate::video::{VideoDecoder, VideoTexture};
+
+/// Plays a video, e.g. behind a main menu or as a cutscene
+///
+/// `update` advances playback and `update_geometry` copies new frames into the texture,
+/// both run by the `UISystem`. Playback starts paused, call `play`.
+pub struct VideoComponent {
+    video: VideoTexture,
+    sampled: SampledTexture,
+    /// Unit quad with image rows going down
+    quad: Mesh<TexturedVertex2D>,
+    transform: Transform,
+    /// Fit the video into the layout rect with its aspect ratio instead of stretching it
+    keep_aspect: bool,
+    damaged: bool,
+}
+
+impl VideoComponent {
+    /// Video from a file with a decoder from `video::open_decoder`
+    pub fn open(context: &Arc<VulkanContext>, descriptor_set_layout: vk::DescriptorSetLayout, path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_video(context, descriptor_set_layout, VideoTexture::open(context, path)?)
+    }
+
+    pub fn new(context: &Arc<VulkanContext>, descriptor_set_layout: vk::DescriptorSetLayout, decoder: Box<dyn VideoDecoder>) -> Result<Self> {
+        Self::with_video(context, descriptor_set_layout, VideoTexture::new(context, decoder)?)
+    }
+
+    fn with_video(context: &Arc<VulkanContext>, descriptor_set_layout: vk::DescriptorSetLayout, video: VideoTexture) -> Result<Self> {
+        let sampled = SampledTexture::new(video.texture(), SamplerConfig::linear(), descriptor_set_layout, context)?;
+        // Image rows go down while UI y goes up, so the top of the quad samples v = 0
+        let vertices = [
+            TexturedVertex2D { position: [-0.5, 0.5], uv: [0.0, 0.0] },
+            TexturedVertex2D { position: [-0.5, -0.5], uv: [0.0, 1.0] },
+            TexturedVertex2D { position: [0.5, -0.5], uv: [1.0, 1.0] },
+            TexturedVertex2D { position: [0.5, -0.5], uv: [1.0, 1.0] },
+            TexturedVertex2D { position: [0.5, 0.5], uv: [1.0, 0.0] },
+            TexturedVertex2D { position: [-0.5, 0.5], uv: [0.0, 0.0] },
+        ];
+        let quad = Mesh::new(VertexBuffer::new(&context.device, context.physical_device, &context.instance, &vertices)?);
+        Ok(VideoComponent {
+            video,
+            sampled,
+            quad,
+            transform: Transform::new(),
+            keep_aspect: true,
+            damaged: false,
+        })
+    }
+
+    pub fn video(&self) -> &VideoTexture {
+        &self.video
+    }
+
+    /// Looping, playback position and the like, see `VideoTexture`
+    pub fn video_mut(&mut self) -> &mut VideoTexture {
+        &mut self.video
+    }
+
+    pub fn play(&mut self) {
+        self.video.play();
+    }
+
+    pub fn pause(&mut self) {
+        self.video.pause();
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.video.toggle_pause();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.video.is_playing()
+    }
+
+    /// Jump to `time` seconds
+    pub fn seek(&mut self, time: f64) {
+        self.video.seek(time);
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.video.set_looping(looping);
+    }
+
+    /// Letterbox the video in its rect (the default) or stretch it over the whole rect
+    pub fn set_keep_aspect(&mut self, keep_aspect: bool) {
+        self.keep_aspect = keep_aspect;
+    }
+
+    /// Area the video is drawn over, `rect` shrunk to the video's aspect ratio if kept
+    fn video_rect(&self, rect: Rect) -> Rect {
+        let info = self.video.info();
+        if !self.keep_aspect || rect.width <= 0.0 || rect.height <= 0.0 {
+            return rect;
+        }
+        let aspect = info.width as f32 / info.height as f32;
+        let (width, height) = if rect.width / rect.height > aspect {
+            (rect.height * aspect, rect.height)
+        } else {
+            (rect.width, rect.width / aspect)
+        };
+        Rect::from_center_size(rect.center(), glam::Vec2::new(width, height))
+    }
+}
+
+impl GUIComponent for VideoComponent {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        let pipeline = renderer.get_pipeline(PipelineId::Image)?;
+        let pipeline_layout = renderer.get_pipeline_layout(PipelineId::Image)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for Image pipeline"))?;
+        ctx.bind_pipeline(pipeline);
+        ctx.bind_descriptor_set_at(pipeline_layout, 0, self.sampled.descriptor_set);
+        let transform = Transform::from_rect(self.video_rect(self.transform.rect()));
+        ctx.push(pipeline_layout, &PushConstants2D::new(renderer.projection, transform.to_matrix()).with_opacity(renderer.opacity));
+        self.quad.draw(ctx)
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    fn update(&mut self, dt: f32) {
+        if self.video.update(dt) {
+            self.damaged = true;
+        }
+    }
+
+    fn next_update(&self) -> Option<f32> {
+        self.video.next_frame_in()
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        std::mem::take(&mut self.damaged).then(|| self.video_rect(self.transform.rect()))
+    }
+
+    fn update_geometry(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        self.video.upload(context)?;
+        Ok(())
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        self.sampled.destroy(device);
+        self.quad.destroy(device);
+        self.video.destroy(device);
+    }
+}
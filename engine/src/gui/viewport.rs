@@ -0,0 +1,431 @@
+use anyhow::Result;
+use ash::vk;
+use std::sync::Arc;
+use crate::ecs::{EntityId, ParticleEmitter, SpatialIndex, Sprite, World};
+use crate::gui::trails::TrailBatch;
+use crate::gui::{AntiAliasing, Bloom, BloomEffect, Color, EditorCamera, FxaaEffect, Gesture, GUIComponent, Lighting2DEffect, Transform};
+use crate::math::{coords, Rect};
+use crate::renderer::{
+    ColorVertex2D, DrawKey, Mesh, PipelineId, PushConstants2D, RenderContext, RenderQueue, Renderable, Renderer, SampledTexture, SamplerConfig,
+    ProjectionSpace, RenderFrame, ScenePushConstants, TexturedVertex2D, Texture, VertexBuffer, VulkanContext, ENTITY_ID_FORMAT,
+};
+use glam::{Mat4, Vec2, Vec3};
+
+/// Colors cycled through for entity quads (linear)
+const ENTITY_COLORS: [[f32; 3]; 5] = [
+    [0.9, 0.55, 0.3],
+    [0.4, 0.75, 0.45],
+    [0.45, 0.6, 0.95],
+    [0.85, 0.8, 0.35],
+    [0.75, 0.45, 0.85],
+];
+
+/// Tinted unit quad in the scene pass, `entity_id` is 0 for quads that can't be picked
+struct SceneQuad<'a> {
+    mesh: &'a Mesh<ColorVertex2D>,
+    transform: Mat4,
+    color: [f32; 3],
+    entity_id: u32,
+    layer: i8,
+}
+
+impl Renderable for SceneQuad<'_> {
+    fn pipeline(&self) -> PipelineId {
+        PipelineId::Scene
+    }
+
+    fn sort_key(&self) -> DrawKey {
+        DrawKey::opaque(self.layer, PipelineId::Scene, 0, 0.0)
+    }
+
+    fn record(&self, ctx: &RenderContext, layout: vk::PipelineLayout, projection: Mat4) -> Result<()> {
+        ctx.push(layout, &ScenePushConstants::new(projection, self.transform, self.color, self.entity_id));
+        self.mesh.draw(ctx)
+    }
+}
+
+/// Offscreen color target, the descriptor set used to sample it and the entity ids drawn with it
+pub(super) struct ViewportTarget {
+    pub(super) texture: Texture,
+    pub(super) sampled: SampledTexture,
+    pub(super) ids: Texture,
+}
+
+impl ViewportTarget {
+    pub(super) fn new(
+        context: &Arc<VulkanContext>,
+        width: u32,
+        height: u32,
+        color_format: vk::Format,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        sampler: SamplerConfig,
+    ) -> Result<Self> {
+        let texture = Texture::render_target(width, height, color_format, &context.device, &context.instance, context.physical_device)?;
+        let sampled = SampledTexture::new(&texture, sampler, descriptor_set_layout, context)?;
+        let ids = Texture::render_target(width, height, ENTITY_ID_FORMAT, &context.device, &context.instance, context.physical_device)?;
+        Ok(ViewportTarget { texture, sampled, ids })
+    }
+
+    pub(super) fn has_size(&self, width: u32, height: u32) -> bool {
+        self.texture.width == width && self.texture.height == height
+    }
+
+    /// Color and entity id targets with their clear values, for `RenderFrame::render_to_targets`
+    pub(super) fn scene_targets(&self, clear_color: Color) -> [(&Texture, Option<vk::ClearColorValue>); 2] {
+        [
+            (&self.texture, Some(vk::ClearColorValue { float32: clear_color.to_array() })),
+            (&self.ids, Some(vk::ClearColorValue { uint32: [0; 4] })),
+        ]
+    }
+
+    /// Draw the color target stretched over `transform` with `image_quad`
+    pub(super) fn draw(&self, ctx: &RenderContext, renderer: &mut Renderer, image_quad: &Mesh<TexturedVertex2D>, transform: &Transform) -> Result<()> {
+        draw_image(ctx, renderer, image_quad, &self.sampled, transform)
+    }
+
+    pub(super) fn destroy(&self, device: &ash::Device) {
+        self.sampled.destroy(device);
+        self.texture.destroy(device);
+        self.ids.destroy(device);
+    }
+}
+
+/// Draw a texture sampled with the layout of `PipelineId::Image` stretched over `transform`
+pub(super) fn draw_image(
+    ctx: &RenderContext,
+    renderer: &mut Renderer,
+    image_quad: &Mesh<TexturedVertex2D>,
+    sampled: &SampledTexture,
+    transform: &Transform,
+) -> Result<()> {
+    let pipeline = renderer.get_pipeline(PipelineId::Image)?;
+    let pipeline_layout = renderer.get_pipeline_layout(PipelineId::Image)
+        .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for Image pipeline"))?;
+    ctx.bind_pipeline(pipeline);
+    ctx.bind_descriptor_set_at(pipeline_layout, 0, sampled.descriptor_set);
+
+    ctx.push(pipeline_layout, &PushConstants2D::new(renderer.projection, transform.to_matrix()).with_opacity(renderer.opacity));
+    image_quad.draw(ctx)
+}
+
+/// Unit quad sampling a whole target
+/// Quad y grows with the target's rows, so the image keeps the orientation of the scene pass
+pub(super) fn image_quad(context: &Arc<VulkanContext>) -> Result<Mesh<TexturedVertex2D>> {
+    let vertices = [
+        TexturedVertex2D { position: [-0.5, 0.5], uv: [0.0, 1.0] },
+        TexturedVertex2D { position: [-0.5, -0.5], uv: [0.0, 0.0] },
+        TexturedVertex2D { position: [0.5, -0.5], uv: [1.0, 0.0] },
+        TexturedVertex2D { position: [0.5, -0.5], uv: [1.0, 0.0] },
+        TexturedVertex2D { position: [0.5, 0.5], uv: [1.0, 1.0] },
+        TexturedVertex2D { position: [-0.5, 0.5], uv: [0.0, 1.0] },
+    ];
+    Ok(Mesh::new(VertexBuffer::new(&context.device, context.physical_device, &context.instance, &vertices)?))
+}
+
+/// White unit quad tinted per entity
+pub(super) fn entity_quad(context: &Arc<VulkanContext>) -> Result<Mesh<ColorVertex2D>> {
+    let white = [1.0, 1.0, 1.0];
+    let vertices = [
+        ColorVertex2D { position: [-0.5, 0.5], color: white },
+        ColorVertex2D { position: [-0.5, -0.5], color: white },
+        ColorVertex2D { position: [0.5, -0.5], color: white },
+        ColorVertex2D { position: [0.5, -0.5], color: white },
+        ColorVertex2D { position: [0.5, 0.5], color: white },
+        ColorVertex2D { position: [-0.5, 0.5], color: white },
+    ];
+    Ok(Mesh::new(VertexBuffer::new(&context.device, context.physical_device, &context.instance, &vertices)?))
+}
+
+/// Shows the World rendered offscreen through an `EditorCamera`
+///
+/// Each frame call `refresh` (resizes the target to the widget), then
+/// `RenderFrame::render_to_targets` with `scene_targets()` and `render_scene`, and
+/// finally render the widget itself as part of the UI.
+pub struct ViewportComponent {
+    target: Option<ViewportTarget>,
+    /// Unit quad sampling the whole target
+    image_quad: Mesh<TexturedVertex2D>,
+    /// White unit quad tinted per entity
+    entity_quad: Mesh<ColorVertex2D>,
+    /// Ribbons of the World's trails as of the last `update_trails`
+    trails: TrailBatch,
+    camera: EditorCamera,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    /// Must match the color format the pipelines are built with
+    color_format: vk::Format,
+    clear_color: Color,
+    /// Post effects applied to the target by `render_post`, off by default
+    /// Lighting is on while the World has a `Lighting2D` resource.
+    lighting: Lighting2DEffect,
+    bloom: BloomEffect,
+    anti_aliasing: AntiAliasing,
+    /// Smoothed copy of the target drawn instead of it with `AntiAliasing::Fxaa`
+    fxaa: FxaaEffect,
+    transform: Transform,
+}
+
+impl ViewportComponent {
+    /// `descriptor_set_layout` is the layout of `PipelineId::Image`, `color_format` is `Renderer::color_format`
+    pub fn new(context: &Arc<VulkanContext>, descriptor_set_layout: vk::DescriptorSetLayout, color_format: vk::Format) -> Result<Self> {
+        Ok(ViewportComponent {
+            target: None,
+            image_quad: image_quad(context)?,
+            entity_quad: entity_quad(context)?,
+            trails: TrailBatch::new(),
+            camera: EditorCamera::new(),
+            descriptor_set_layout,
+            color_format,
+            clear_color: Color::srgb(0.12, 0.12, 0.14),
+            lighting: Lighting2DEffect::new(context)?,
+            bloom: BloomEffect::new(context, Bloom::new())?,
+            anti_aliasing: AntiAliasing::None,
+            fxaa: FxaaEffect::new(context, descriptor_set_layout)?,
+            transform: Transform::new(),
+        })
+    }
+
+    pub fn camera(&self) -> &EditorCamera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut EditorCamera {
+        &mut self.camera
+    }
+
+    pub fn clear_color(&self) -> Color {
+        self.clear_color
+    }
+
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
+
+    pub fn bloom(&self) -> &Bloom {
+        &self.bloom.bloom
+    }
+
+    /// Bloom settings of the scene, give it an intensity to turn it on
+    pub fn bloom_mut(&mut self) -> &mut Bloom {
+        &mut self.bloom.bloom
+    }
+
+    pub fn anti_aliasing(&self) -> AntiAliasing {
+        self.anti_aliasing
+    }
+
+    /// Smooth the scene's edges in `render_post`, can change any frame
+    pub fn set_anti_aliasing(&mut self, anti_aliasing: AntiAliasing) {
+        self.anti_aliasing = anti_aliasing;
+    }
+
+    /// Offscreen texture to render the scene into, if the viewport has a size yet
+    pub fn target(&self) -> Option<&Texture> {
+        self.target.as_ref().map(|t| &t.texture)
+    }
+
+    /// Color and entity id targets of the scene pass with their clear values, for `RenderFrame::render_to_targets`
+    pub fn scene_targets(&self) -> Option<[(&Texture, Option<vk::ClearColorValue>); 2]> {
+        self.target.as_ref().map(|t| t.scene_targets(self.clear_color))
+    }
+
+    /// Size of the viewport in pixels
+    pub fn size(&self) -> Vec2 {
+        self.transform.scale
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        self.transform.contains_point(point)
+    }
+
+    /// Projection * view of the editor camera for the current size
+    pub fn view_projection(&self) -> Mat4 {
+        self.camera.view_projection(self.size())
+    }
+
+    /// UI space rect covered by the viewport
+    pub fn rect(&self) -> Rect {
+        self.transform.rect()
+    }
+
+    /// Convert a UI point to the world position on the XY plane under it
+    /// Returns the focus if the ray misses the plane (3D view looking away from it)
+    pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
+        coords::ui_to_world(point, self.rect(), self.view_projection()).unwrap_or(self.camera.focus)
+    }
+
+    /// Convert a distance in pixels to world units at the focus
+    pub fn pixels_to_world(&self, pixels: f32) -> f32 {
+        pixels / self.camera.zoom
+    }
+
+    /// Entity drawn at a UI point in the last rendered frame, read back from the entity id target
+    pub fn pick(&self, renderer: &Renderer, point: Vec2) -> Result<Option<EntityId>> {
+        let Some(target) = &self.target else {
+            return Ok(None);
+        };
+        // Rows grow upwards like UI y, see the image quad
+        match coords::ui_to_pixel(point, self.rect()) {
+            Some((x, y)) => renderer.pick(&target.ids, x, y),
+            None => Ok(None),
+        }
+    }
+
+    /// Recreate the offscreen target when the widget size changed
+    /// Returns true if the target was (re)created, the caller should redraw
+    pub fn refresh(&mut self, context: &Arc<VulkanContext>) -> Result<bool> {
+        let width = self.transform.scale.x.round().max(0.0) as u32;
+        let height = self.transform.scale.y.round().max(0.0) as u32;
+        if width == 0 || height == 0 {
+            return Ok(false);
+        }
+        if let Some(target) = &self.target {
+            if target.has_size(width, height) {
+                return Ok(false);
+            }
+            // The old target may still be sampled by frames in flight
+            unsafe {
+                let _ = context.device.device_wait_idle();
+            }
+            target.destroy(&context.device);
+            self.target = None;
+        }
+
+        self.target = Some(ViewportTarget::new(context, width, height, self.color_format, self.descriptor_set_layout, SamplerConfig::linear())?);
+        Ok(true)
+    }
+
+    /// Rebuild the ribbons of the World's `TrailRenderer`s, once per frame before `render_scene`
+    pub fn update_trails(&mut self, context: &Arc<VulkanContext>, world: &World) -> Result<()> {
+        self.trails.update(context, world)
+    }
+
+    /// Draw every entity with a Transform as a quad tinted by its Sprite (or a color from its id), particles, trails and the world axes
+    /// Must be called inside `RenderFrame::render_to_targets` for `scene_targets()`, entity quads
+    /// also write their id for `pick`
+    pub fn render_scene(&self, ctx: &RenderContext, renderer: &mut Renderer, world: &World) -> Result<()> {
+        let visible = self.camera.visible_rect(self.size());
+        draw_world(ctx, renderer, world, &self.entity_quad, self.view_projection(), visible, self.camera.zoom)?;
+        self.trails.draw(ctx, renderer, self.view_projection())
+    }
+
+    /// Apply the 2D lighting of `world` and the post effects to the scene target, after the
+    /// scene pass of the frame
+    pub fn render_post(&mut self, frame: &RenderFrame, renderer: &mut Renderer, context: &Arc<VulkanContext>, world: &World) -> Result<()> {
+        let Some(target) = &self.target else {
+            return Ok(());
+        };
+        let visible = self.camera.visible_rect(self.size());
+        self.lighting.apply(frame, renderer, world, &target.texture, self.view_projection(), visible)?;
+        self.bloom.apply(frame, renderer, context, &target.texture)?;
+        // Last, on the final colors
+        match self.anti_aliasing {
+            AntiAliasing::None => Ok(()),
+            AntiAliasing::Fxaa => self.fxaa.apply(frame, renderer, context, &target.texture),
+        }
+    }
+}
+
+/// Draw the World's entities, particles and axes seen through `view_projection` into the
+/// scene targets of the current pass, `zoom` is pixels per world unit (for the axis width)
+/// With a `visible` world rect and a `SpatialIndex` in the World only the entities it finds
+/// there are drawn.
+pub(super) fn draw_world(
+    ctx: &RenderContext,
+    renderer: &mut Renderer,
+    world: &World,
+    entity_quad: &Mesh<ColorVertex2D>,
+    view_projection: Mat4,
+    visible: Option<Rect>,
+    zoom: f32,
+) -> Result<()> {
+    // Id 0 is the cleared background, entity ids are written off by one
+    let quad = |transform: Mat4, color: [f32; 3], layer: i8, id: Option<EntityId>| SceneQuad {
+        mesh: entity_quad,
+        transform,
+        color,
+        entity_id: id.map_or(0, |id| id.0 + 1),
+        layer,
+    };
+
+    // Axes through the origin, two pixels wide at the current zoom, under all entities
+    let thickness = 2.0 / zoom;
+    let extent = 10_000.0;
+    let mut quads = vec![
+        quad(Mat4::from_scale(Vec3::new(extent, thickness, 1.0)), [0.6, 0.25, 0.25], -1, None),
+        quad(Mat4::from_scale(Vec3::new(thickness, extent, 1.0)), [0.25, 0.6, 0.25], -1, None),
+    ];
+
+    let mut ids: Vec<_> = match (visible, world.resource::<SpatialIndex>()) {
+        // Despawned since the index was updated
+        (Some(visible), Some(index)) => index.query(visible).into_iter().filter(|&id| world.has::<Transform>(id)).collect(),
+        _ => world.entity_ids().filter(|&id| world.has::<Transform>(id)).collect(),
+    };
+    ids.sort();
+    for id in ids {
+        let color = world
+            .get::<Sprite>(id)
+            .map(|sprite| sprite.color.rgb())
+            .unwrap_or(ENTITY_COLORS[id.0 as usize % ENTITY_COLORS.len()]);
+        quads.push(quad(world.world_matrix(id), color, 0, Some(id)));
+    }
+
+    // Particles over the entities, picking one selects its emitter (alpha isn't shown)
+    for id in world.entity_ids() {
+        let Some(emitter) = world.get::<ParticleEmitter>(id) else {
+            continue;
+        };
+        for (position, size, color) in emitter.particles() {
+            let transform = Mat4::from_translation(position.extend(0.0)) * Mat4::from_scale(Vec3::new(size, size, 1.0));
+            quads.push(quad(transform, color.rgb(), 1, Some(id)));
+        }
+    }
+
+    let mut queue = RenderQueue::in_space(ProjectionSpace::Custom(view_projection));
+    for quad in &quads {
+        queue.submit(quad);
+    }
+    queue.flush(ctx, renderer)
+}
+
+impl GUIComponent for ViewportComponent {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        let Some(target) = &self.target else {
+            return Ok(());
+        };
+        match self.fxaa.output().filter(|_| self.anti_aliasing == AntiAliasing::Fxaa) {
+            Some(smoothed) => draw_image(ctx, renderer, &self.image_quad, smoothed, &self.transform),
+            None => target.draw(ctx, renderer, &self.image_quad, &self.transform),
+        }
+    }
+
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    /// Pinching zooms the camera, scrolling with two fingers pans it
+    fn handle_gesture(&mut self, gesture: &Gesture) -> bool {
+        match *gesture {
+            Gesture::Pinch { scale, .. } => self.camera.scale_zoom(scale),
+            Gesture::Scroll { delta, .. } => self.camera.pan(delta),
+            Gesture::Tap { .. } | Gesture::LongPress { .. } => return false,
+        }
+        true
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        if let Some(target) = &self.target {
+            target.destroy(device);
+        }
+        self.image_quad.destroy(device);
+        self.entity_quad.destroy(device);
+        self.trails.destroy(device);
+        self.lighting.destroy(device);
+        self.bloom.destroy(device);
+        self.fxaa.destroy(device);
+    }
+}
@@ -0,0 +1,433 @@
+use anyhow::Result;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use crate::ecs::{EntityId, World};
+use crate::gui::{AccessAction, AccessTree, GUIComponent, Rect, Transform};
+use crate::math::coords;
+use crate::renderer::{RenderContext, Renderer, VulkanContext};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+/// Removed widgets are destroyed after this many `update_geometry` calls, longer than any frame stays in flight
+const FRAMES_KEPT: usize = 3;
+/// Seconds a widget with a lifetime takes to fade out at its end
+const LIFETIME_FADE: f32 = 0.3;
+
+/// What a world-space widget follows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorldTarget {
+    /// World position of an entity, the widget is removed once the entity is despawned
+    Entity(EntityId),
+    /// Fixed world position, e.g. where a hit landed
+    Point(Vec3),
+}
+
+/// Where and how a widget of `WorldSpaceUI` is shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldAnchor {
+    pub target: WorldTarget,
+    /// World units added to the target position, e.g. to float above a character
+    pub offset: Vec3,
+    /// Pixels added after projecting, e.g. to stack a health bar under a name
+    pub screen_offset: Vec2,
+    /// Widget size in pixels, `None` asks the widget (`measure`), fine for text
+    pub size: Option<Vec2>,
+    /// Keep the widget this many pixels inside the view instead of hiding it off screen
+    pub clamp: Option<f32>,
+    /// Fully visible up to `.0` world units from the camera, gone from `.1` on
+    pub fade: Option<(f32, f32)>,
+    /// Seconds until the widget is removed, it fades out at the end
+    pub lifetime: Option<f32>,
+    /// Pixels per second the widget drifts up, e.g. for floating damage numbers
+    pub rise: f32,
+}
+
+impl WorldAnchor {
+    pub fn entity(entity: EntityId) -> Self {
+        Self::new(WorldTarget::Entity(entity))
+    }
+
+    pub fn point(position: Vec3) -> Self {
+        Self::new(WorldTarget::Point(position))
+    }
+
+    fn new(target: WorldTarget) -> Self {
+        WorldAnchor {
+            target,
+            offset: Vec3::ZERO,
+            screen_offset: Vec2::ZERO,
+            size: None,
+            clamp: None,
+            fade: None,
+            lifetime: None,
+            rise: 0.0,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: Vec3) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_screen_offset(mut self, offset: Vec2) -> Self {
+        self.screen_offset = offset;
+        self
+    }
+
+    pub fn with_size(mut self, size: Vec2) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_clamp(mut self, margin: f32) -> Self {
+        self.clamp = Some(margin);
+        self
+    }
+
+    pub fn with_fade(mut self, near: f32, far: f32) -> Self {
+        self.fade = Some((near, far.max(near)));
+        self
+    }
+
+    pub fn with_lifetime(mut self, seconds: f32) -> Self {
+        self.lifetime = Some(seconds);
+        self
+    }
+
+    pub fn with_rise(mut self, pixels_per_second: f32) -> Self {
+        self.rise = pixels_per_second;
+        self
+    }
+}
+
+/// Typed reference to a widget added with `WorldSpaceUI::add`
+pub struct WorldWidgetHandle<T> {
+    id: u64,
+    _component: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for WorldWidgetHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WorldWidgetHandle<T> {}
+
+impl<T> PartialEq for WorldWidgetHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> std::fmt::Debug for WorldWidgetHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WorldWidgetHandle({})", self.id)
+    }
+}
+
+struct WorldWidget {
+    id: u64,
+    widget: Box<dyn GUIComponent>,
+    anchor: WorldAnchor,
+    /// Seconds since the widget was added
+    age: f32,
+    /// Rect it was placed at by the last `place`, `None` while hidden
+    placed: Option<Rect>,
+    opacity: f32,
+}
+
+/// Widgets following entities or world positions, e.g. nameplates, health bars and
+/// floating damage numbers
+///
+/// `place` projects every anchor through the camera of a view each frame and lays the
+/// widgets out centered on their projected points. Widgets leaving the view are hidden,
+/// or held at its edge when clamped, and can fade with their distance from the camera.
+/// Distance is the view depth for perspective cameras and the distance from the center
+/// of the view on the XY plane for orthographic ones.
+///
+/// The widgets are display only, they get no mouse input. `UISystem` owns one as
+/// `world_ui`, drawn over the grid and under popups.
+pub struct WorldSpaceUI {
+    widgets: Vec<WorldWidget>,
+    /// Removed widgets waiting to be destroyed, with the frames they waited
+    retired: Vec<(Box<dyn GUIComponent>, usize)>,
+    next_id: u64,
+    damage: Option<Rect>,
+    /// Region of the last `place`
+    transform: Transform,
+}
+
+impl WorldSpaceUI {
+    pub fn new() -> Self {
+        WorldSpaceUI {
+            widgets: Vec::new(),
+            retired: Vec::new(),
+            next_id: 0,
+            damage: None,
+            transform: Transform::new(),
+        }
+    }
+
+    /// Show `widget` at `anchor` from the next `place` on
+    pub fn add<T: GUIComponent + 'static>(&mut self, widget: T, anchor: WorldAnchor) -> WorldWidgetHandle<T> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.widgets.push(WorldWidget {
+            id,
+            widget: Box::new(widget),
+            anchor,
+            age: 0.0,
+            placed: None,
+            opacity: 1.0,
+        });
+        WorldWidgetHandle { id, _component: PhantomData }
+    }
+
+    /// Remove a widget, returns false if it was already gone (removed, expired or its entity despawned)
+    pub fn remove<T>(&mut self, handle: WorldWidgetHandle<T>) -> bool {
+        match self.widgets.iter().position(|w| w.id == handle.id) {
+            Some(index) => {
+                self.retire(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn contains<T>(&self, handle: WorldWidgetHandle<T>) -> bool {
+        self.widgets.iter().any(|w| w.id == handle.id)
+    }
+
+    /// The widget behind a handle, `None` once it is gone
+    pub fn get<T: GUIComponent + 'static>(&self, handle: WorldWidgetHandle<T>) -> Option<&T> {
+        self.widgets.iter().find(|w| w.id == handle.id)?.widget.as_any().downcast_ref()
+    }
+
+    pub fn get_mut<T: GUIComponent + 'static>(&mut self, handle: WorldWidgetHandle<T>) -> Option<&mut T> {
+        self.widgets.iter_mut().find(|w| w.id == handle.id)?.widget.as_any_mut().downcast_mut()
+    }
+
+    /// Anchor of a widget, changes apply on the next `place`
+    pub fn anchor_mut<T>(&mut self, handle: WorldWidgetHandle<T>) -> Option<&mut WorldAnchor> {
+        self.widgets.iter_mut().find(|w| w.id == handle.id).map(|w| &mut w.anchor)
+    }
+
+    pub fn len(&self) -> usize {
+        self.widgets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.widgets.is_empty()
+    }
+
+    /// Remove every widget
+    pub fn clear(&mut self) {
+        self.retire_where(|_| true);
+    }
+
+    fn retire(&mut self, index: usize) {
+        let widget = self.widgets.remove(index);
+        if let Some(rect) = widget.placed {
+            self.invalidate(rect);
+        }
+        self.retired.push((widget.widget, 0));
+    }
+
+    /// Retire every widget `expired` returns true for
+    fn retire_where(&mut self, expired: impl Fn(&WorldWidget) -> bool) {
+        let mut index = 0;
+        while index < self.widgets.len() {
+            if expired(&self.widgets[index]) {
+                self.retire(index);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    fn invalidate(&mut self, rect: Rect) {
+        self.damage = Some(self.damage.map_or(rect, |damage| damage.union(&rect)));
+    }
+
+    /// Lay the widgets out over `region` (UI space) seen through `view_projection`
+    /// Call once per frame after the World and camera moved, e.g. with a viewport's
+    /// `view_projection()` and `rect()`.
+    pub fn place(&mut self, world: &World, view_projection: Mat4, region: Rect) {
+        self.retire_where(|w| match w.anchor.target {
+            WorldTarget::Entity(entity) => !world.contains(entity),
+            WorldTarget::Point(_) => false,
+        });
+        self.transform = Transform::from_rect(region);
+        // Orthographic cameras measure fade distance from the point under the view center
+        let perspective = view_projection.row(3) != Vec4::W;
+        let view_center = coords::ui_to_world(region.center(), region, view_projection).unwrap_or_default();
+
+        let mut damage = Vec::new();
+        for item in &mut self.widgets {
+            let position = match item.anchor.target {
+                WorldTarget::Entity(entity) => world.world_matrix(entity).w_axis.truncate(),
+                WorldTarget::Point(point) => point,
+            } + item.anchor.offset;
+            let distance = if perspective {
+                (view_projection * position.extend(1.0)).w
+            } else {
+                position.truncate().distance(view_center)
+            };
+            let mut opacity = match item.anchor.fade {
+                Some((near, far)) if far > near => ((far - distance) / (far - near)).clamp(0.0, 1.0),
+                Some((near, _)) => if distance <= near { 1.0 } else { 0.0 },
+                None => 1.0,
+            };
+            if let Some(lifetime) = item.anchor.lifetime {
+                opacity *= ((lifetime - item.age) / LIFETIME_FADE).clamp(0.0, 1.0);
+            }
+
+            let size = item.anchor.size.unwrap_or_else(|| item.widget.measure(region.size()));
+            let placed = Self::project(&item.anchor, position, size, item.age, view_projection, region)
+                .filter(|_| opacity > 0.0);
+            if placed != item.placed || opacity != item.opacity {
+                damage.extend(item.placed);
+                damage.extend(placed);
+            }
+            if let Some(rect) = placed {
+                item.widget.set_layout(rect);
+            }
+            item.placed = placed;
+            item.opacity = opacity;
+        }
+        for rect in damage {
+            self.invalidate(rect);
+        }
+    }
+
+    /// Rect of a widget of `size` anchored at `position`, `None` where it is hidden
+    fn project(anchor: &WorldAnchor, position: Vec3, size: Vec2, age: f32, view_projection: Mat4, region: Rect) -> Option<Rect> {
+        let clip = view_projection * position.extend(1.0);
+        let offset = anchor.screen_offset + Vec2::new(0.0, anchor.rise * age);
+        let center = if clip.w > f32::EPSILON {
+            coords::ndc_to_ui(clip.truncate().truncate() / clip.w, region) + offset
+        } else {
+            // Behind the camera, only clamped widgets stay, on the edge towards the point
+            anchor.clamp?;
+            let direction = (clip.truncate().truncate() / -clip.w.min(-f32::EPSILON)).normalize_or_zero();
+            region.center() + direction * region.size().length()
+        };
+
+        let center = match anchor.clamp {
+            Some(margin) => {
+                let inset = size / 2.0 + margin;
+                let min = region.min() + inset;
+                let max = (region.max() - inset).max(min);
+                center.clamp(min, max)
+            }
+            None if region.contains_point(center) => center,
+            None => return None,
+        };
+        // Whole pixels keep text sharp
+        Some(Rect::from_center_size(center.round(), size))
+    }
+}
+
+impl GUIComponent for WorldSpaceUI {
+    fn render(&self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        let opacity = renderer.opacity;
+        let mut result = Ok(());
+        for item in self.widgets.iter().filter(|w| w.placed.is_some()) {
+            renderer.opacity = opacity * item.opacity;
+            result = item.widget.render(ctx, renderer);
+            if result.is_err() {
+                break;
+            }
+        }
+        renderer.opacity = opacity;
+        result
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    /// Only widgets placed on screen this frame
+    fn accessibility(&self, tree: &mut AccessTree) {
+        for item in self.widgets.iter().filter(|w| w.placed.is_some()) {
+            item.widget.accessibility(tree);
+        }
+    }
+
+    fn accessibility_action(&mut self, action: &AccessAction) -> bool {
+        self.widgets
+            .iter_mut()
+            .filter(|w| w.placed.is_some() && w.widget.transform().contains_point(action.point))
+            .any(|w| w.widget.accessibility_action(action))
+    }
+
+    /// Ages widgets with a lifetime and removes the expired ones
+    fn update(&mut self, dt: f32) {
+        for item in &mut self.widgets {
+            item.age += dt;
+            item.widget.update(dt);
+        }
+        self.retire_where(|w| w.anchor.lifetime.is_some_and(|lifetime| w.age >= lifetime));
+    }
+
+    fn next_update(&self) -> Option<f32> {
+        self.widgets
+            .iter()
+            .filter_map(|w| {
+                // Moving or fading widgets need every frame
+                if w.anchor.lifetime.is_some() || w.anchor.rise != 0.0 {
+                    Some(0.0)
+                } else {
+                    w.widget.next_update()
+                }
+            })
+            .reduce(f32::min)
+    }
+
+    fn take_damage(&mut self) -> Option<Rect> {
+        for item in &mut self.widgets {
+            if let Some(rect) = item.widget.take_damage() {
+                self.damage = Some(self.damage.map_or(rect, |damage| damage.union(&rect)));
+            }
+        }
+        self.damage.take()
+    }
+
+    /// Also destroys removed widgets once no frame in flight can use them
+    fn update_geometry(&mut self, context: &Arc<VulkanContext>) -> Result<()> {
+        for item in &mut self.widgets {
+            item.widget.update_geometry(context)?;
+        }
+        for (widget, frames) in &mut self.retired {
+            *frames += 1;
+            if *frames > FRAMES_KEPT {
+                widget.destroy(&context.device);
+            }
+        }
+        self.retired.retain(|(_, frames)| *frames <= FRAMES_KEPT);
+        Ok(())
+    }
+
+    fn children(&self) -> Vec<&dyn GUIComponent> {
+        self.widgets.iter().map(|w| w.widget.as_ref()).collect()
+    }
+
+    fn destroy(&self, device: &ash::Device) {
+        for item in &self.widgets {
+            item.widget.destroy(device);
+        }
+        for (widget, _) in &self.retired {
+            widget.destroy(device);
+        }
+    }
+}
+
+impl Default for WorldSpaceUI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,578 @@
+//! `Engine` wires a window, the renderer, the UI and a World together, for games and hosts
+//! that don't need a loop of their own.
+
+use anyhow::{anyhow, bail, Result};
+use glam::Vec2;
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use winit::event::{ElementState, Event, MouseButton, WindowEvent};
+use winit::event_loop::EventLoop;
+use winit::keyboard::{Key, NamedKey, SmolStr};
+use winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+use winit::window::Window;
+
+use crate::config::EngineConfig;
+use crate::ecs::{
+    change_level, load_scene, load_scene_additive, run_state_machines, unload_scene, update_particles, update_scenes,
+    update_spatial_index, update_timers, ComponentRegistry, EntityId, SceneId, Scenes, Schedule, Timers, World,
+};
+use crate::gui::{ButtonComponent, ComponentRef, HAlign, InputState, LayoutSpec, RowSpec, SizeSpec, TextComponent, UISystem, VAlign, ViewportComponent};
+use crate::math::{coords, Color, Rect};
+use crate::renderer::{FontAtlas, PipelineId, Recovery, Renderer, VulkanContext};
+use crate::replay::{Replay, ReplayPlayer, ReplayRecorder};
+use crate::storage::Settings;
+use crate::window::{Lifecycle, LifecycleChange, ResizeTracker};
+
+/// Name the UI reports to assistive technologies and `find_ui`
+const APP_NAME: &str = "engine";
+/// Height of the host's widget row
+const WIDGET_HEIGHT: f32 = 24.0;
+
+/// A widget the host added
+enum HostWidget {
+    Button(Arc<RefCell<ButtonComponent>>),
+    Label(Arc<RefCell<TextComponent>>),
+}
+
+/// Font widgets are drawn with and the layout of the text pipeline
+struct HostFont {
+    atlas: Arc<FontAtlas>,
+    size: f32,
+    descriptor_set_layout: ash::vk::DescriptorSetLayout,
+}
+
+/// An engine in a window of its own, driven one `tick` at a time by a host
+///
+/// The window shows the World through a viewport under a row of widgets the host adds.
+/// Input from the window is handled during `tick`; hosts and tools can inject more through
+/// `mouse_move`, `mouse_button` and `key`. Games usually hand it their loop with `run`,
+/// the C API wraps it for other languages.
+///
+/// Every tick the pointer's `InputState` is a World resource. While recording or playing
+/// a `Replay` the World also holds an `Rng` seeded from it, so systems that use only
+/// those two and `dt` play the same way again.
+pub struct Engine {
+    ui: UISystem,
+    input: InputState,
+    viewport: Arc<RefCell<ViewportComponent>>,
+    widget_row: usize,
+    widgets: Vec<HostWidget>,
+    font: Option<HostFont>,
+    world: World,
+    schedule: Schedule,
+    settings: Settings,
+    /// Directory name `settings` are saved under
+    app: String,
+    resizes: ResizeTracker,
+    lifecycle: Lifecycle,
+    recorder: Option<ReplayRecorder>,
+    player: Option<ReplayPlayer>,
+    last_tick: Instant,
+    closed: bool,
+    // Dropped in this order, the renderer before the context and the window before its event loop
+    renderer: Renderer,
+    context: Arc<VulkanContext>,
+    window: Arc<Window>,
+    event_loop: EventLoop<()>,
+}
+
+impl Engine {
+    /// Open a window, `font` is a TrueType file and pixel size for host widgets
+    pub fn new(title: &str, width: u32, height: u32, font: Option<(&str, f32)>) -> Result<Self> {
+        let config = EngineConfig {
+            title: title.to_string(),
+            window_width: width.max(1),
+            window_height: height.max(1),
+            ..EngineConfig::default()
+        };
+        Self::with_config(&config, font)
+    }
+
+    /// Open a window as `config` says and load its starting scene
+    /// The user's settings for `config.app` are loaded for `settings`, they don't change the window.
+    pub fn with_config(config: &EngineConfig, font: Option<(&str, f32)>) -> Result<Self> {
+        Self::with_settings(config, load_settings(config), font)
+    }
+
+    fn with_settings(config: &EngineConfig, settings: Settings, font: Option<(&str, f32)>) -> Result<Self> {
+        let event_loop = EventLoop::new()?;
+        let window = Arc::new(config.window_builder().build(&event_loop)?);
+        let size = window.inner_size();
+        // Shared as an Arc throughout the engine even though it stays on this thread
+        #[allow(clippy::arc_with_non_send_sync)]
+        let context = Arc::new(VulkanContext::with_validation(window.clone(), config.validation)?);
+        let mut renderer = Renderer::new(context.clone(), size.width, size.height)?;
+        renderer.set_vsync(config.vsync);
+
+        let font = match font {
+            Some((path, size)) => {
+                let handle = renderer.load_font(path, size)?;
+                Some(HostFont {
+                    atlas: renderer.font(handle).ok_or_else(|| anyhow!("Font {} was not loaded", path))?,
+                    size,
+                    descriptor_set_layout: renderer
+                        .get_descriptor_set_layout(PipelineId::Text)
+                        .ok_or_else(|| anyhow!("Text pipeline has no descriptor set layout"))?,
+                })
+            }
+            None => None,
+        };
+
+        // Host widgets on top, as high as they need, the scene fills the rest
+        let mut ui = UISystem::new();
+        let widget_row = ui.grid.add_row_with_spec(RowSpec::new(SizeSpec::Auto).with_margin(4.0).with_v_align(VAlign::Middle));
+        let image_layout = renderer
+            .get_descriptor_set_layout(PipelineId::Image)
+            .ok_or_else(|| anyhow!("Image pipeline has no descriptor set layout"))?;
+        let (viewport_wrapper, viewport) = ComponentRef::new(ViewportComponent::new(&context, image_layout, renderer.color_format())?);
+        let viewport_row = ui.grid.add_row();
+        let viewport_spec = LayoutSpec::new(SizeSpec::Fraction(1.0), SizeSpec::Fraction(1.0)).with_alignment(HAlign::Center, VAlign::Middle);
+        ui.grid.add(viewport_row, viewport_wrapper, viewport_spec)?;
+        ui.resize(size.width as f32, size.height as f32);
+
+        let mut schedule = Schedule::new();
+        schedule.add_fn("timers", update_timers);
+        schedule.add_fn("state_machines", run_state_machines);
+        schedule.add_fn("particles", update_particles);
+        schedule.add_fn("spatial_index", update_spatial_index);
+
+        let mut engine = Engine {
+            ui,
+            input: InputState::new(),
+            viewport,
+            widget_row,
+            widgets: Vec::new(),
+            font,
+            world: Self::empty_world(),
+            schedule,
+            settings,
+            app: config.app.clone(),
+            resizes: ResizeTracker::new(),
+            lifecycle: Lifecycle::new(),
+            recorder: None,
+            player: None,
+            last_tick: Instant::now(),
+            closed: false,
+            renderer,
+            context,
+            window,
+            event_loop,
+        };
+        if let Some(scene) = config.scene_path() {
+            engine.load_scene(&scene)?;
+        }
+        Ok(engine)
+    }
+
+    /// Open an engine as `config` says and tick it until its window closes, calling `frame`
+    /// with it before every tick
+    /// The window size and vsync the user saved for `config.app` replace the config's, flags on
+    /// the command line still win. The settings are saved with the window's size on exit.
+    pub fn run(config: &EngineConfig, mut frame: impl FnMut(&mut Engine) -> Result<()>) -> Result<()> {
+        let settings = load_settings(config);
+        let mut config = config.clone();
+        config.apply_settings(&settings);
+        config.apply_flags(std::env::args().skip(1))?;
+        let mut engine = Self::with_settings(&config, settings, None)?;
+        loop {
+            frame(&mut engine)?;
+            if !engine.tick()? {
+                let size = engine.window.inner_size();
+                engine.settings.window_width = size.width.max(1);
+                engine.settings.window_height = size.height.max(1);
+                if let Err(e) = engine.save_settings() {
+                    log::warn!("Could not save settings: {}", e);
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    fn empty_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(ComponentRegistry::with_engine_components());
+        world.insert_resource(Timers::new());
+        world
+    }
+
+    /// The user's settings, window size and vsync changes apply on the next start
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    /// Write the settings to the app's settings file
+    pub fn save_settings(&self) -> Result<()> {
+        self.settings.save(&self.app)
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Replace the World with the scene in a file, see `ecs::load_scene`
+    /// The current World is kept when the scene fails to load.
+    pub fn load_scene(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let mut world = Self::empty_world();
+        let registry = world.remove_resource::<ComponentRegistry>().expect("Inserted by empty_world");
+        let result = load_scene(&mut world, &registry, path);
+        world.insert_resource(registry);
+        result?;
+        self.world = world;
+        Ok(())
+    }
+
+    /// Load a scene file next to the loaded ones, see `ecs::load_scene_additive`
+    pub fn load_scene_additive(&mut self, path: impl AsRef<Path>) -> Result<SceneId> {
+        self.with_registry(|world, registry| load_scene_additive(world, registry, path))
+    }
+
+    /// Start reading a scene file in the background, it is spawned by a later `tick`
+    pub fn stream_scene(&mut self, path: impl AsRef<Path>) -> SceneId {
+        if self.world.resource::<Scenes>().is_none() {
+            self.world.insert_resource(Scenes::new());
+        }
+        self.world.resource_mut::<Scenes>().expect("Inserted above").stream(path)
+    }
+
+    /// Despawn a scene loaded with `load_scene_additive`, `stream_scene` or `change_level`
+    pub fn unload_scene(&mut self, id: SceneId) -> bool {
+        unload_scene(&mut self.world, id)
+    }
+
+    /// Load a level, unloading the scenes that aren't persistent, see `ecs::change_level`
+    pub fn change_level(&mut self, path: impl AsRef<Path>) -> Result<SceneId> {
+        self.with_registry(|world, registry| change_level(world, registry, path))
+    }
+
+    /// Run `f` with the World and its component registry taken out of it
+    fn with_registry<R>(&mut self, f: impl FnOnce(&mut World, &ComponentRegistry) -> R) -> R {
+        let registry = self.world.remove_resource::<ComponentRegistry>().unwrap_or_else(ComponentRegistry::with_engine_components);
+        let result = f(&mut self.world, &registry);
+        self.world.insert_resource(registry);
+        result
+    }
+
+    /// Whether the window was closed, `tick` does nothing after that
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Handle the window's events, run the game systems and draw a frame
+    /// Returns false once the window was closed.
+    pub fn tick(&mut self) -> Result<bool> {
+        if self.closed {
+            return Ok(false);
+        }
+        let mut events = Vec::new();
+        let mut lifecycle = Vec::new();
+        let status = self.event_loop.pump_events(Some(Duration::ZERO), |event, _| {
+            if let Some(change) = self.lifecycle.handle(&event) {
+                lifecycle.push(change);
+            }
+            if let Event::WindowEvent { event, .. } = event {
+                events.push(event);
+            }
+        });
+        if let PumpStatus::Exit(_) = status {
+            self.closed = true;
+        }
+        for change in lifecycle {
+            match change {
+                LifecycleChange::Suspended => self.renderer.suspend(),
+                LifecycleChange::Resumed => self.renderer.resume(&self.window)?,
+                // The renderer was created with the window
+                LifecycleChange::Started => {}
+            }
+        }
+        for event in events {
+            self.handle_window_event(event);
+        }
+        if self.closed {
+            return Ok(false);
+        }
+
+        let mut dt = self.last_tick.elapsed().as_secs_f32();
+        self.last_tick = Instant::now();
+        if let Some(player) = &mut self.player {
+            match player.next_frame() {
+                Some((recorded_dt, input)) => {
+                    dt = recorded_dt;
+                    self.apply_input(input);
+                }
+                None => {
+                    log::info!("Replay finished after {} frames", player.position());
+                    self.player = None;
+                }
+            }
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(dt, &self.input);
+        }
+        self.world.insert_resource(self.input);
+        if let Some((width, height)) = self.resizes.take() {
+            self.renderer.handle_resize(width, height, self.window.scale_factor() as f32);
+            self.ui.resize(width as f32, height as f32);
+        }
+        for (id, result) in self.with_registry(update_scenes) {
+            if let Err(e) = result {
+                log::error!("Failed to stream scene {:?}: {:#}", id, e);
+            }
+        }
+        self.schedule.run(&mut self.world, dt);
+        self.ui.update(dt, &self.input);
+        self.viewport.borrow_mut().refresh(&self.context)?;
+        self.ui.update_geometry(&self.context)?;
+        self.draw()?;
+        crate::tasks::wait_frame();
+        Ok(!self.closed)
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        let frame = match self.renderer.begin_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(()),
+            Err(error) => {
+                self.closed = self.renderer.recover(error) == Recovery::Shutdown;
+                return Ok(());
+            }
+        };
+        let renderer = &mut self.renderer;
+        let viewport = self.viewport.borrow();
+        if let Some(targets) = viewport.scene_targets() {
+            frame.render_to_targets(&targets, |ctx| viewport.render_scene(ctx, renderer, &self.world))?;
+        }
+        drop(viewport);
+        self.ui.render_offscreen(&frame, renderer, &self.context)?;
+        // The whole UI is drawn, so pending damage is covered
+        self.ui.take_damage();
+        self.ui.render(&frame.render_ctx, renderer)
+    }
+
+    fn handle_window_event(&mut self, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested | WindowEvent::Destroyed => self.closed = true,
+            WindowEvent::Resized(size) => self.resizes.resized(size.width, size.height),
+            // A replay's input replaces the pointer's
+            WindowEvent::CursorMoved { .. } | WindowEvent::MouseInput { .. } | WindowEvent::Touch(_) if self.is_replaying() => {}
+            WindowEvent::CursorMoved { position, .. } => self.mouse_move(position.x as f32, position.y as f32),
+            WindowEvent::MouseInput { state, button, .. } => self.mouse_button(button, state == ElementState::Pressed),
+            WindowEvent::Touch(touch) => {
+                let window_height = self.window.inner_size().height as f32;
+                self.ui.handle_touch(&touch, window_height);
+            }
+            WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
+                self.key(&event.logical_key);
+            }
+            _ => {}
+        }
+    }
+
+    /// Pointer moved to a window position in pixels, origin at the top left
+    pub fn mouse_move(&mut self, x: f32, y: f32) {
+        let window_height = self.window.inner_size().height as f32;
+        let point = coords::window_to_ui(Vec2::new(x, y), window_height);
+        self.input.mouse = point;
+        self.ui.handle_mouse_move(point.x, point.y);
+    }
+
+    /// Mouse button pressed or released at the last `mouse_move` position
+    pub fn mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        self.input.set_button(button, pressed);
+        if button != MouseButton::Left {
+            return;
+        }
+        let point = self.input.mouse;
+        if pressed {
+            self.ui.handle_mouse_down(point.x, point.y);
+        } else {
+            self.ui.handle_mouse_up(point.x, point.y);
+        }
+    }
+
+    /// Move the pointer and press or release the left button to match a replayed frame
+    fn apply_input(&mut self, input: InputState) {
+        let previous = self.input;
+        self.input = input;
+        if input.mouse != previous.mouse {
+            self.ui.handle_mouse_move(input.mouse.x, input.mouse.y);
+        }
+        match (previous.left, input.left) {
+            (false, true) => self.ui.handle_mouse_down(input.mouse.x, input.mouse.y),
+            (true, false) => self.ui.handle_mouse_up(input.mouse.x, input.mouse.y),
+            _ => {}
+        }
+    }
+
+    /// Record the input and time steps of every tick from now on, `seed` seeds the World's
+    /// `Rng` resource
+    /// Load the scene before starting, the replay begins with the World as it is.
+    pub fn start_recording(&mut self, seed: u64) {
+        let recorder = ReplayRecorder::new(seed);
+        self.world.insert_resource(recorder.rng(0));
+        self.recorder = Some(recorder);
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// The session recorded since `start_recording`, `None` if nothing was recorded
+    pub fn stop_recording(&mut self) -> Option<Replay> {
+        self.recorder.take().map(ReplayRecorder::finish)
+    }
+
+    /// Play a recorded session, replacing the pointer's input and the time steps until it ends
+    /// The World should be in the state it was when the replay was recorded.
+    pub fn play(&mut self, replay: Replay) {
+        let player = ReplayPlayer::new(replay);
+        self.world.insert_resource(player.rng(0));
+        self.player = Some(player);
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.player.is_some()
+    }
+
+    /// A key press, returns whether the UI used it
+    pub fn key(&mut self, key: &Key) -> bool {
+        self.ui.handle_key(key)
+    }
+
+    /// Entity called `name`, the first one if several are
+    pub fn find_entity(&self, name: &str) -> Option<EntityId> {
+        let mut ids: Vec<_> = self.world.entity_ids().filter(|&id| self.world.name(id) == Some(name)).collect();
+        ids.sort();
+        ids.first().copied()
+    }
+
+    /// Add a button to the widget row, needs a font
+    /// Returns its index for `take_clicked` and `set_text`.
+    pub fn add_button(&mut self, label: &str) -> Result<usize> {
+        let text = self.text(label)?;
+        let mut button = ButtonComponent::new(&self.context, Color::srgb(0.2, 0.2, 0.22))?;
+        button.set_text(text);
+        let spec = LayoutSpec::new(SizeSpec::Auto, SizeSpec::Fixed(WIDGET_HEIGHT)).with_alignment(HAlign::Left, VAlign::Middle);
+        let (wrapper, button) = ComponentRef::new(button);
+        self.ui.grid.add(self.widget_row, wrapper, spec)?;
+        self.widgets.push(HostWidget::Button(button));
+        Ok(self.widgets.len() - 1)
+    }
+
+    /// Add a line of text to the widget row, needs a font
+    pub fn add_label(&mut self, text: &str) -> Result<usize> {
+        let spec = LayoutSpec::new(SizeSpec::Auto, SizeSpec::Fixed(WIDGET_HEIGHT)).with_alignment(HAlign::Left, VAlign::Middle);
+        let (wrapper, label) = ComponentRef::new(self.text(text)?);
+        self.ui.grid.add(self.widget_row, wrapper, spec)?;
+        self.widgets.push(HostWidget::Label(label));
+        Ok(self.widgets.len() - 1)
+    }
+
+    fn text(&self, text: &str) -> Result<TextComponent> {
+        let Some(font) = &self.font else {
+            bail!("Widgets need a font, pass one when creating the engine");
+        };
+        TextComponent::new(text, font.atlas.clone(), font.size, font.descriptor_set_layout, &self.context)
+    }
+
+    /// Change the text of a button or label
+    pub fn set_text(&mut self, widget: usize, text: &str) -> Result<()> {
+        match self.widgets.get(widget) {
+            Some(HostWidget::Button(button)) => button.borrow_mut().update_text(text, &self.context),
+            Some(HostWidget::Label(label)) => label.borrow_mut().update_text(text, &self.context),
+            None => bail!("No widget {}", widget),
+        }
+    }
+
+    /// Whether a button was clicked since the last call
+    pub fn take_clicked(&mut self, widget: usize) -> bool {
+        match self.widgets.get(widget) {
+            Some(HostWidget::Button(button)) => button.borrow_mut().take_clicked(),
+            _ => false,
+        }
+    }
+
+    /// Window rect (pixels, y down) of the first UI element whose accessible name is `name`,
+    /// e.g. a button's label
+    pub fn find_ui(&mut self, name: &str) -> Option<Rect> {
+        let (_, bounds) = self.find_ui_node(name)?;
+        Some(bounds)
+    }
+
+    /// Perform the default action (a click for buttons) of the UI element called `name`,
+    /// returns whether there was one
+    pub fn activate_ui(&mut self, name: &str) -> bool {
+        let Some((target, _)) = self.find_ui_node(name) else {
+            return false;
+        };
+        let request = accesskit::ActionRequest { action: accesskit::Action::Default, target, data: None };
+        self.ui.handle_access_action(&request)
+    }
+
+    fn find_ui_node(&mut self, name: &str) -> Option<(accesskit::NodeId, Rect)> {
+        let tree = self.ui.accessibility_tree(APP_NAME);
+        tree.nodes.iter().find_map(|(id, node)| {
+            let bounds = node.bounds().filter(|_| node.name() == Some(name))?;
+            let rect = Rect::new(bounds.x0 as f32, bounds.y0 as f32, bounds.width() as f32, bounds.height() as f32);
+            Some((*id, rect))
+        })
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        unsafe {
+            self.context.device().device_wait_idle().ok();
+        }
+        self.ui.destroy(self.context.device());
+    }
+}
+
+/// The settings saved for `config.app`, or ones matching `config` when there are none yet
+fn load_settings(config: &EngineConfig) -> Settings {
+    if Settings::path(&config.app).is_ok_and(|path| path.exists()) {
+        return Settings::load(&config.app);
+    }
+    Settings {
+        window_width: config.window_width,
+        window_height: config.window_height,
+        vsync: config.vsync,
+        ..Settings::default()
+    }
+}
+
+/// A key by its name: a single character or a named key such as "Enter", "Escape" or
+/// "ArrowUp" (the W3C `KeyboardEvent.key` names winit uses)
+pub fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    if let (Some(_), None) = (chars.next(), chars.next()) {
+        return Some(Key::Character(SmolStr::new(name)));
+    }
+    let named = match name {
+        "Enter" => NamedKey::Enter,
+        "Escape" => NamedKey::Escape,
+        "Tab" => NamedKey::Tab,
+        "Space" => NamedKey::Space,
+        "Backspace" => NamedKey::Backspace,
+        "Delete" => NamedKey::Delete,
+        "Home" => NamedKey::Home,
+        "End" => NamedKey::End,
+        "PageUp" => NamedKey::PageUp,
+        "PageDown" => NamedKey::PageDown,
+        "ArrowUp" => NamedKey::ArrowUp,
+        "ArrowDown" => NamedKey::ArrowDown,
+        "ArrowLeft" => NamedKey::ArrowLeft,
+        "ArrowRight" => NamedKey::ArrowRight,
+        _ => return None,
+    };
+    Some(Key::Named(named))
+}
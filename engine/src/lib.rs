@@ -1,5 +1,34 @@
+//! Vulkan game engine: renderer, retained UI, ECS and the services around them.
+//! `prelude` re-exports the types most games use. The `gui`, `ecs` and `editor-widgets`
+//! features (all on by default) leave out what a game doesn't need.
+
+pub mod prelude;
+
+pub mod config;
+pub mod cvars;
+
 pub mod renderer;
 pub mod window;
+#[cfg(feature = "gui")]
 pub mod gui;
 pub mod math;
-pub mod ecs;
\ No newline at end of file
+#[cfg(feature = "ecs")]
+pub mod ecs;
+pub mod logging;
+pub mod profiler;
+pub mod tasks;
+pub mod assets;
+#[cfg(feature = "ecs")]
+pub mod nav;
+#[cfg(feature = "ecs")]
+pub mod net;
+pub mod storage;
+pub mod localization;
+pub mod video;
+pub mod crash;
+#[cfg(feature = "gui")]
+pub mod host;
+#[cfg(feature = "gui")]
+pub mod replay;
+#[cfg(feature = "capi")]
+pub mod capi;
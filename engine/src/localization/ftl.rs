@@ -0,0 +1,59 @@
+use anyhow::{bail, Context, Result};
+
+use super::Pattern;
+
+/// Messages of a Fluent (.ftl) file
+///
+/// Supports the commonly used subset: `key = value` messages and `-term = value` terms,
+/// `#` comments, values continued on indented lines, attributes (`.tooltip = ...`, stored as
+/// "key.tooltip") and the placeables `Pattern::parse` understands.
+pub fn parse(source: &str) -> Result<Vec<(String, Pattern)>> {
+    // Raw value of every message and the line it starts on, continuation lines joined
+    let mut raw: Vec<(String, String, usize)> = Vec::new();
+    // Message attributes are added to
+    let mut parent: Option<String> = None;
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // The brace closing a select may also start the line
+        let indented = line.starts_with([' ', '\t', '}']);
+        let trimmed = line.trim();
+        if indented && !trimmed.starts_with('.') {
+            let Some((_, value, _)) = raw.last_mut() else {
+                bail!("Line {}: indented text outside of a message", number);
+            };
+            if !value.is_empty() {
+                value.push('\n');
+            }
+            value.push_str(trimmed);
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once('=') else {
+            bail!("Line {}: expected 'key = value', got {:?}", number, line);
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            bail!("Line {}: message without a name", number);
+        }
+        let key = if let Some(attribute) = key.strip_prefix('.') {
+            let Some(parent) = &parent else {
+                bail!("Line {}: attribute .{} outside of a message", number, attribute);
+            };
+            format!("{}.{}", parent, attribute)
+        } else {
+            parent = Some(key.to_string());
+            key.to_string()
+        };
+        raw.push((key, value.trim().to_string(), number));
+    }
+
+    raw.into_iter()
+        .filter(|(_, value, _)| !value.is_empty())
+        .map(|(key, value, number)| {
+            let pattern = Pattern::parse(&value).with_context(|| format!("Line {}: in message {}", number, key))?;
+            Ok((key, pattern))
+        })
+        .collect()
+}
@@ -0,0 +1,290 @@
+//! Translated UI text.
+//! A `Localization` holds a key -> message table per language, loaded from Fluent (.ftl)
+//! or JSON files, and formats messages with arguments and plural forms for the current
+//! language. `UISystem::text_key` binds widgets to keys so they follow language changes.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::storage::Value;
+
+mod ftl;
+mod pattern;
+pub use pattern::{Element, Pattern};
+
+mod plural;
+pub use plural::plural_category;
+
+/// Messages referencing each other deeper than this are treated as a cycle
+const MAX_DEPTH: usize = 8;
+
+/// Value filled into a `{ $name }` placeable
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arg {
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // Whole numbers without a fraction, "3" rather than "3.0"
+            Arg::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Arg::Number(n) => write!(f, "{}", n),
+            Arg::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+macro_rules! number_arg {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Arg {
+            fn from(n: $t) -> Self {
+                Arg::Number(n as f64)
+            }
+        })*
+    };
+}
+
+number_arg!(i32, i64, u32, u64, usize, f32, f64);
+
+impl From<&str> for Arg {
+    fn from(text: &str) -> Self {
+        Arg::Text(text.to_string())
+    }
+}
+
+impl From<String> for Arg {
+    fn from(text: String) -> Self {
+        Arg::Text(text)
+    }
+}
+
+/// Messages of every loaded language and the language they are shown in
+///
+/// Lookups fall back from the current language ("pt-BR") to its primary language ("pt")
+/// and then to the fallback language, a key missing everywhere shows the key itself so
+/// untranslated text is easy to spot.
+pub struct Localization {
+    tables: HashMap<String, HashMap<String, Pattern>>,
+    language: String,
+    fallback: String,
+    /// Bumped on every language change or load, widgets showing messages refresh on it
+    revision: u64,
+}
+
+impl Localization {
+    /// Show `language`, which is also the fallback until `set_fallback`
+    pub fn new(language: &str) -> Self {
+        Localization {
+            tables: HashMap::new(),
+            language: language.to_string(),
+            fallback: language.to_string(),
+            revision: 0,
+        }
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Switch to `language`, returns whether any messages were loaded for it
+    pub fn set_language(&mut self, language: &str) -> bool {
+        if self.language != language {
+            self.language = language.to_string();
+            self.revision += 1;
+        }
+        let primary = language.split(['-', '_']).next().unwrap_or_default();
+        self.tables.contains_key(language) || self.tables.contains_key(primary)
+    }
+
+    pub fn fallback(&self) -> &str {
+        &self.fallback
+    }
+
+    /// Language messages missing from the current one are taken from
+    pub fn set_fallback(&mut self, language: &str) {
+        self.fallback = language.to_string();
+        self.revision += 1;
+    }
+
+    /// Languages with loaded messages, in no particular order
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.tables.keys().map(String::as_str)
+    }
+
+    /// Changes whenever formatted messages may have changed
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Add messages to `language`, replacing ones with the same key
+    pub fn add_messages(&mut self, language: &str, messages: impl IntoIterator<Item = (String, Pattern)>) -> usize {
+        let table = self.tables.entry(language.to_string()).or_default();
+        let before = table.len();
+        let mut count = 0;
+        for (key, pattern) in messages {
+            table.insert(key, pattern);
+            count += 1;
+        }
+        log::debug!("Loaded {} messages for {} ({} new)", count, language, table.len() - before);
+        self.revision += 1;
+        count
+    }
+
+    /// Add the messages of a Fluent file, returns how many there were
+    pub fn add_ftl(&mut self, language: &str, source: &str) -> Result<usize> {
+        let messages = ftl::parse(source)?;
+        Ok(self.add_messages(language, messages))
+    }
+
+    /// Add the messages of a JSON object, returns how many there were
+    ///
+    /// Values are strings in Fluent pattern syntax, e.g. "Hello, { $name }!". Nested objects
+    /// add their keys with the parent key in front ("menu": {"start": ..} is "menu.start"),
+    /// unless all their keys are plural categories or numbers: those select a form by
+    /// `$count`, e.g. {"one": "{ $count } file", "other": "{ $count } files"}.
+    pub fn add_json(&mut self, language: &str, source: &str) -> Result<usize> {
+        let value = Value::parse(source).ok_or_else(|| anyhow!("Invalid JSON"))?;
+        let mut messages = Vec::new();
+        json_messages(&value, "", &mut messages)?;
+        Ok(self.add_messages(language, messages))
+    }
+
+    /// Add the messages of a .ftl or .json file
+    pub fn load_file(&mut self, language: &str, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        let result = match path.extension().and_then(|e| e.to_str()) {
+            Some("ftl") => self.add_ftl(language, &source),
+            Some("json") => self.add_json(language, &source),
+            _ => bail!("Unknown message format {}, expected .ftl or .json", path.display()),
+        };
+        result.with_context(|| format!("Parsing {}", path.display()))
+    }
+
+    /// Load every `<language>.ftl` and `<language>.json` file of a directory, e.g. "en.ftl"
+    /// and "de.json", returns the languages found
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<Vec<String>> {
+        let dir = dir.as_ref();
+        let mut languages = Vec::new();
+        for entry in std::fs::read_dir(dir).with_context(|| format!("Reading {}", dir.display()))? {
+            let path = entry?.path();
+            let format = path.extension().and_then(|e| e.to_str());
+            let Some(language) = path.file_stem().and_then(|s| s.to_str()).filter(|_| matches!(format, Some("ftl" | "json"))) else {
+                continue;
+            };
+            let language = language.to_string();
+            self.load_file(&language, &path)?;
+            languages.push(language);
+        }
+        Ok(languages)
+    }
+
+    /// Whether `key` has a message in the current or a fallback language
+    pub fn has(&self, key: &str) -> bool {
+        self.lookup(key).is_some()
+    }
+
+    /// Message `key` without arguments
+    pub fn get(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    /// Message `key` with its placeables filled from `args`, e.g.
+    /// `format("files", &[("count", 3.into())])`
+    /// Placeables without an argument are left in the text as `{$name}`.
+    pub fn format(&self, key: &str, args: &[(&str, Arg)]) -> String {
+        let Some((language, pattern)) = self.lookup(key) else {
+            return key.to_string();
+        };
+        let mut out = String::new();
+        self.write(&mut out, pattern, language, args, 0);
+        out
+    }
+
+    /// Tables to look a key up in, in order, with their language
+    fn chain(&self) -> impl Iterator<Item = (&str, &HashMap<String, Pattern>)> {
+        let primary = self.language.split(['-', '_']).next().unwrap_or_default();
+        [self.language.as_str(), primary, self.fallback.as_str()]
+            .into_iter()
+            .filter_map(|language| self.tables.get_key_value(language))
+            .map(|(language, table)| (language.as_str(), table))
+    }
+
+    fn lookup(&self, key: &str) -> Option<(&str, &Pattern)> {
+        self.chain().find_map(|(language, table)| table.get(key).map(|pattern| (language, pattern)))
+    }
+
+    fn write(&self, out: &mut String, pattern: &Pattern, language: &str, args: &[(&str, Arg)], depth: usize) {
+        let arg = |name: &str| args.iter().find(|(n, _)| *n == name).map(|(_, value)| value);
+        for element in &pattern.elements {
+            match element {
+                Element::Text(text) => out.push_str(text),
+                Element::Arg(name) => match arg(name) {
+                    Some(value) => out.push_str(&value.to_string()),
+                    None => out.push_str(&format!("{{${}}}", name)),
+                },
+                Element::Message(key) => match self.lookup(key) {
+                    Some((language, pattern)) if depth < MAX_DEPTH => self.write(out, pattern, language, args, depth + 1),
+                    _ => out.push_str(key),
+                },
+                Element::Select { arg: name, variants, default } => {
+                    let chosen = match arg(name) {
+                        // An exact value like [0] wins over the plural category
+                        Some(Arg::Number(n)) => variants
+                            .iter()
+                            .position(|(key, _)| key.parse::<f64>().is_ok_and(|k| k == *n))
+                            .or_else(|| {
+                                let category = plural_category(language, *n);
+                                variants.iter().position(|(key, _)| key == category)
+                            }),
+                        Some(Arg::Text(text)) => variants.iter().position(|(key, _)| key == text),
+                        None => None,
+                    };
+                    self.write(out, &variants[chosen.unwrap_or(*default)].1, language, args, depth);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Self::new("en")
+    }
+}
+
+fn json_messages(value: &Value, prefix: &str, messages: &mut Vec<(String, Pattern)>) -> Result<()> {
+    let Some(entries) = value.entries() else {
+        bail!("Expected an object of messages");
+    };
+    for (key, value) in entries {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            Value::String(text) => messages.push((key.clone(), Pattern::parse(text).with_context(|| format!("In message {}", key))?)),
+            Value::Map(forms) if is_plural(forms) => {
+                let variants = forms
+                    .iter()
+                    .map(|(form, text)| {
+                        let text = text.as_str().ok_or_else(|| anyhow!("{}.{} is not a string", key, form))?;
+                        Ok((form.clone(), Pattern::parse(text).with_context(|| format!("In message {}.{}", key, form))?))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let default = variants.iter().position(|(form, _)| form == "other").unwrap_or(variants.len() - 1);
+                let select = Element::Select { arg: "count".to_string(), variants, default };
+                messages.push((key, Pattern { elements: vec![select] }));
+            }
+            Value::Map(_) => json_messages(value, &key, messages)?,
+            other => messages.push((key, Pattern::text(&other.to_text()))),
+        }
+    }
+    Ok(())
+}
+
+/// A non-empty object keyed only by plural categories and numbers
+fn is_plural(forms: &[(String, Value)]) -> bool {
+    !forms.is_empty() && forms.iter().all(|(form, _)| plural::is_category(form) || form.parse::<f64>().is_ok())
+}
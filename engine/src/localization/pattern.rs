@@ -0,0 +1,156 @@
+use anyhow::{bail, Result};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Parsed text of a message, with placeables filled in when it's formatted
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pattern {
+    pub elements: Vec<Element>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Element {
+    Text(String),
+    /// `{ $name }`
+    Arg(String),
+    /// `{ other-message }`, the text of another message of the same language
+    Message(String),
+    /// `{ $name -> [one] ... *[other] ... }`, picks a variant by a number's plural category
+    /// or exact value, or by a text argument
+    Select { arg: String, variants: Vec<(String, Pattern)>, default: usize },
+}
+
+impl Pattern {
+    pub fn text(text: &str) -> Self {
+        Pattern { elements: vec![Element::Text(text.to_string())] }
+    }
+
+    /// Parse Fluent pattern syntax, e.g. "Delete { $count -> [one] a file *[other] { $count } files }?"
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parser = PatternParser { chars: source.chars().peekable() };
+        let pattern = parser.pattern(false)?;
+        if parser.chars.next().is_some() {
+            bail!("Unmatched '}}' in {:?}", source);
+        }
+        Ok(pattern)
+    }
+}
+
+struct PatternParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl PatternParser<'_> {
+    /// Text and placeables up to an unmatched '}', and a newline when `in_variant`
+    fn pattern(&mut self, in_variant: bool) -> Result<Pattern> {
+        let mut elements = Vec::new();
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c == '}' || (in_variant && c == '\n') {
+                break;
+            }
+            self.chars.next();
+            if c != '{' {
+                text.push(c);
+                continue;
+            }
+            match self.placeable()? {
+                Element::Text(literal) => text.push_str(&literal),
+                element => {
+                    if !text.is_empty() {
+                        elements.push(Element::Text(std::mem::take(&mut text)));
+                    }
+                    elements.push(element);
+                }
+            }
+        }
+        if in_variant {
+            text.truncate(text.trim_end().len());
+        }
+        if !text.is_empty() {
+            elements.push(Element::Text(text));
+        }
+        Ok(Pattern { elements })
+    }
+
+    /// The inside of `{ ... }`, the opening brace already taken
+    fn placeable(&mut self) -> Result<Element> {
+        self.skip_blank();
+        let element = match self.chars.peek() {
+            Some('"') => {
+                self.chars.next();
+                let mut literal = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some('"') => break,
+                        Some('\\') => literal.extend(self.chars.next()),
+                        Some(c) => literal.push(c),
+                        None => bail!("Unterminated string literal"),
+                    }
+                }
+                Element::Text(literal)
+            }
+            Some('$') => {
+                self.chars.next();
+                let arg = self.identifier()?;
+                self.skip_blank();
+                if self.chars.next_if_eq(&'-').is_some() {
+                    if self.chars.next_if_eq(&'>').is_none() {
+                        bail!("Expected '->' after ${}", arg);
+                    }
+                    return self.select(arg);
+                }
+                Element::Arg(arg)
+            }
+            _ => Element::Message(self.identifier()?),
+        };
+        self.skip_blank();
+        if self.chars.next_if_eq(&'}').is_none() {
+            bail!("Expected '}}' to close a placeable");
+        }
+        Ok(element)
+    }
+
+    /// Variants of a select expression up to its closing brace
+    fn select(&mut self, arg: String) -> Result<Element> {
+        let mut variants = Vec::new();
+        let mut default = None;
+        loop {
+            self.skip_blank();
+            match self.chars.next() {
+                Some('}') => break,
+                Some('*') if self.chars.next_if_eq(&'[').is_some() => default = Some(variants.len()),
+                Some('[') => {}
+                _ => bail!("Expected a variant like '[one]' in the select on ${}", arg),
+            }
+            self.skip_blank();
+            let key = self.identifier()?;
+            self.skip_blank();
+            if self.chars.next_if_eq(&']').is_none() {
+                bail!("Expected ']' after variant key {}", key);
+            }
+            while self.chars.next_if(|&c| c == ' ' || c == '\t').is_some() {}
+            variants.push((key, self.pattern(true)?));
+        }
+        let Some(default) = default else {
+            bail!("The select on ${} needs a default variant marked with '*'", arg);
+        };
+        Ok(Element::Select { arg, variants, default })
+    }
+
+    fn skip_blank(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    /// Message, argument or variant name, variant keys may also be numbers
+    fn identifier(&mut self) -> Result<String> {
+        let mut name = String::new();
+        while let Some(c) = self.chars.next_if(|&c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+            name.push(c);
+        }
+        if name.is_empty() {
+            bail!("Expected a name, found {:?}", self.chars.peek());
+        }
+        Ok(name)
+    }
+}
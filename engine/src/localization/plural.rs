@@ -0,0 +1,51 @@
+/// CLDR plural category of the number `n` in `language`, e.g. "one" or "few"
+///
+/// Covers the cardinal rules of the common language families, others fall back to the
+/// English "one"/"other". Only the primary subtag counts, "pt-BR" is treated as "pt".
+pub fn plural_category(language: &str, n: f64) -> &'static str {
+    let primary = language.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+    let n = n.abs();
+    let integer = n.fract() == 0.0;
+    // Last digits only matter for whole numbers
+    let (mod10, mod100) = if integer { ((n % 10.0) as u32, (n % 100.0) as u32) } else { (u32::MAX, u32::MAX) };
+    match primary.as_str() {
+        "ja" | "zh" | "ko" | "vi" | "th" | "id" | "ms" | "tr" => "other",
+        "fr" | "pt" | "hi" | "fa" | "bn" => {
+            if n < 2.0 { "one" } else { "other" }
+        }
+        "ru" | "uk" | "be" | "sr" | "hr" | "bs" => match (mod10, mod100) {
+            _ if !integer => "other",
+            (1, m) if m != 11 => "one",
+            (2..=4, m) if !(12..=14).contains(&m) => "few",
+            _ => "many",
+        },
+        "pl" => match (mod10, mod100) {
+            _ if !integer => "other",
+            _ if n == 1.0 => "one",
+            (2..=4, m) if !(12..=14).contains(&m) => "few",
+            _ => "many",
+        },
+        "cs" | "sk" => match n {
+            _ if !integer => "many",
+            1.0 => "one",
+            2.0..=4.0 => "few",
+            _ => "other",
+        },
+        "ar" => match (n, mod100) {
+            (0.0, _) => "zero",
+            (1.0, _) => "one",
+            (2.0, _) => "two",
+            (_, 3..=10) => "few",
+            (_, 11..=99) => "many",
+            _ => "other",
+        },
+        _ => {
+            if n == 1.0 { "one" } else { "other" }
+        }
+    }
+}
+
+/// Whether `key` names a plural category rather than a message
+pub fn is_category(key: &str) -> bool {
+    matches!(key, "zero" | "one" | "two" | "few" | "many" | "other")
+}
@@ -0,0 +1,98 @@
+//! Engine-wide logging backed by the `log` facade.
+//! Every record is printed to stderr and kept in a bounded history so the
+//! on-screen console (and anything else) can show recent lines.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+
+/// Maximum number of log lines kept in memory
+const MAX_HISTORY: usize = 512;
+
+/// A single captured log record
+#[derive(Clone, Debug)]
+pub struct LogLine {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+impl LogLine {
+    /// Format the line the same way it is printed to stderr
+    pub fn formatted(&self) -> String {
+        format!("[{} {}] {}", self.level, self.target, self.message)
+    }
+}
+
+struct EngineLogger {
+    history: Mutex<VecDeque<LogLine>>,
+}
+
+impl Log for EngineLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = LogLine {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        eprintln!("{}", line.formatted());
+
+        if let Ok(mut history) = self.history.lock() {
+            if history.len() == MAX_HISTORY {
+                history.pop_front();
+            }
+            history.push_back(line);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: OnceLock<EngineLogger> = OnceLock::new();
+
+/// Install the engine logger as the global `log` backend
+/// Safe to call more than once - later calls only change the max level
+pub fn init(max_level: LevelFilter) {
+    let logger = LOGGER.get_or_init(|| EngineLogger {
+        history: Mutex::new(VecDeque::with_capacity(MAX_HISTORY)),
+    });
+    let _ = log::set_logger(logger);
+    log::set_max_level(max_level);
+}
+
+/// Get up to `count` of the most recent lines at or above `filter`, oldest first
+pub fn recent_lines(count: usize, filter: LevelFilter) -> Vec<LogLine> {
+    let Some(logger) = LOGGER.get() else {
+        return Vec::new();
+    };
+    let Ok(history) = logger.history.lock() else {
+        return Vec::new();
+    };
+
+    let mut lines: Vec<LogLine> = history
+        .iter()
+        .rev()
+        .filter(|line| line.level <= filter)
+        .take(count)
+        .cloned()
+        .collect();
+    lines.reverse();
+    lines
+}
+
+/// Drop all captured log history
+pub fn clear_history() {
+    if let Some(logger) = LOGGER.get() {
+        if let Ok(mut history) = logger.history.lock() {
+            history.clear();
+        }
+    }
+}
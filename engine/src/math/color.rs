@@ -0,0 +1,146 @@
+/// Convert one sRGB encoded channel (0..1) to linear
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// Convert one linear channel (0..1) to sRGB encoding
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+/// RGBA color stored in linear space, the space vertex colors and clear values are written in
+///
+/// The swapchain and render targets use sRGB formats, so the hardware encodes linear
+/// values on write. Colors picked by eye (hex codes, design tools, color pickers) are
+/// sRGB encoded and must be created with `srgb`/`hex` to look the same on screen.
+/// Alpha is always linear.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::linear(1.0, 1.0, 1.0);
+    pub const BLACK: Color = Color::linear(0.0, 0.0, 0.0);
+    pub const TRANSPARENT: Color = Color::linear_rgba(0.0, 0.0, 0.0, 0.0);
+
+    /// Opaque color from linear channels
+    pub const fn linear(r: f32, g: f32, b: f32) -> Self {
+        Color { r, g, b, a: 1.0 }
+    }
+
+    pub const fn linear_rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// Opaque color from sRGB encoded channels
+    pub fn srgb(r: f32, g: f32, b: f32) -> Self {
+        Self::srgba(r, g, b, 1.0)
+    }
+
+    pub fn srgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r: srgb_to_linear(r), g: srgb_to_linear(g), b: srgb_to_linear(b), a }
+    }
+
+    /// Parse "#RRGGBB" or "#RRGGBBAA" (sRGB, the '#' is optional)
+    pub fn hex(text: &str) -> Option<Self> {
+        let digits = text.trim().trim_start_matches('#');
+        if !matches!(digits.len(), 6 | 8) || !digits.is_ascii() {
+            return None;
+        }
+        let mut bytes = [255u8; 4];
+        for (i, byte) in bytes.iter_mut().take(digits.len() / 2).enumerate() {
+            *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).ok()?;
+        }
+        let [r, g, b, a] = bytes.map(|b| b as f32 / 255.0);
+        Some(Self::srgba(r, g, b, a))
+    }
+
+    /// "#RRGGBB" in sRGB, with an extra "AA" if `with_alpha`
+    pub fn to_hex(&self, with_alpha: bool) -> String {
+        let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let [r, g, b, a] = self.to_srgb();
+        let mut hex = format!("#{:02X}{:02X}{:02X}", byte(r), byte(g), byte(b));
+        if with_alpha {
+            hex.push_str(&format!("{:02X}", byte(a)));
+        }
+        hex
+    }
+
+    /// Opaque color from HSV (all 0..1) in sRGB space, the way color pickers present it
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let h = h.rem_euclid(1.0) * 6.0;
+        let c = v * s;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let m = v - c;
+        Self::srgb(r + m, g + m, b + m)
+    }
+
+    /// Hue, saturation and value in sRGB space, the hue is `None` for greys
+    pub fn to_hsv(&self) -> (Option<f32>, f32, f32) {
+        let [r, g, b, _] = self.to_srgb().map(|c| c.clamp(0.0, 1.0));
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+        let s = if max > 0.0 { delta / max } else { 0.0 };
+        if delta <= f32::EPSILON {
+            return (None, s, max);
+        }
+        let h = if max == r {
+            ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            (b - r) / delta + 2.0
+        } else {
+            (r - g) / delta + 4.0
+        };
+        (Some(h / 6.0), s, max)
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Color { a, ..self }
+    }
+
+    /// sRGB encoded RGBA
+    pub fn to_srgb(&self) -> [f32; 4] {
+        [linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b), self.a]
+    }
+
+    /// Linear RGB, as written into vertices and push constants
+    pub fn rgb(&self) -> [f32; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    /// Linear RGBA, as written into clear values
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color::WHITE
+    }
+}
+
+impl From<Color> for [f32; 3] {
+    fn from(color: Color) -> Self {
+        color.rgb()
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
@@ -0,0 +1,65 @@
+//! Conversions between the coordinate spaces the engine works in
+//!
+//! - Window: physical pixels as reported by winit, origin top left, y down
+//! - UI: pixels with the origin at the bottom left and y up, what `Renderer::projection`
+//!   maps and every `GUIComponent` transform, hit-test and mouse handler uses
+//! - NDC: -1..1 across a region of the screen (a viewport or view rect), y up
+//! - World: units seen through a camera's view-projection, entities live on the z = 0 plane
+
+use glam::{Mat4, Vec2, Vec3};
+
+use super::Rect;
+
+/// Window pixel position to UI space
+pub fn window_to_ui(point: Vec2, window_height: f32) -> Vec2 {
+    Vec2::new(point.x, window_height - point.y)
+}
+
+/// UI position to window pixels
+pub fn ui_to_window(point: Vec2, window_height: f32) -> Vec2 {
+    Vec2::new(point.x, window_height - point.y)
+}
+
+/// UI position to NDC of `region` (a UI space rect)
+pub fn ui_to_ndc(point: Vec2, region: Rect) -> Vec2 {
+    (point - region.center()) / (region.size().max(Vec2::ONE) / 2.0)
+}
+
+/// NDC of `region` to UI position
+pub fn ndc_to_ui(ndc: Vec2, region: Rect) -> Vec2 {
+    region.center() + ndc * region.size() / 2.0
+}
+
+/// Pixel of a render target drawn over `region` (rows grow with UI y), `None` outside it
+pub fn ui_to_pixel(point: Vec2, region: Rect) -> Option<(u32, u32)> {
+    let pixel = (point - region.min()).floor();
+    let size = region.size().round();
+    if pixel.x < 0.0 || pixel.y < 0.0 || pixel.x >= size.x || pixel.y >= size.y {
+        return None;
+    }
+    Some((pixel.x as u32, pixel.y as u32))
+}
+
+/// World position on the z = 0 plane under a UI point of `region` drawn with `view_projection`
+/// `None` if the ray through the point doesn't hit the plane (a 3D camera looking away from it)
+pub fn ui_to_world(point: Vec2, region: Rect, view_projection: Mat4) -> Option<Vec2> {
+    let ndc = ui_to_ndc(point, region);
+    let inverse = view_projection.inverse();
+    let near = inverse.project_point3(Vec3::new(ndc.x, ndc.y, 0.0));
+    let far = inverse.project_point3(Vec3::new(ndc.x, ndc.y, 1.0));
+    let direction = far - near;
+    if direction.z.abs() < f32::EPSILON {
+        // Ray parallel to the plane (orthographic camera looking straight down)
+        return Some(near.truncate());
+    }
+    let t = -near.z / direction.z;
+    if t < 0.0 {
+        return None;
+    }
+    Some((near + direction * t).truncate())
+}
+
+/// UI position of a world point drawn with `view_projection` into `region`
+pub fn world_to_ui(point: Vec3, region: Rect, view_projection: Mat4) -> Vec2 {
+    ndc_to_ui(view_projection.project_point3(point).truncate(), region)
+}
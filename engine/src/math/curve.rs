@@ -0,0 +1,299 @@
+use super::ease::lerp;
+use super::Color;
+
+/// How a curve gets from a key to the next one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Hold the key's value until the next key
+    Step,
+    Linear,
+    /// Cubic through the neighbouring keys (Catmull-Rom), flat at the first and last key
+    Smooth,
+}
+
+impl Interpolation {
+    fn name(self) -> &'static str {
+        match self {
+            Interpolation::Step => "step",
+            Interpolation::Linear => "linear",
+            Interpolation::Smooth => "smooth",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "step" => Some(Interpolation::Step),
+            "linear" => Some(Interpolation::Linear),
+            "smooth" => Some(Interpolation::Smooth),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CurveKey {
+    pub time: f32,
+    pub value: f32,
+    /// Interpolation of the segment after this key
+    pub interpolation: Interpolation,
+}
+
+/// Float keyed over time, e.g. particle size over lifetime or a UI property over an animation
+///
+/// Keys are kept sorted by time. Sampling before the first key or after the last one
+/// holds the end value, a curve without keys is 0 everywhere.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Curve {
+    keys: Vec<CurveKey>,
+}
+
+impl Curve {
+    pub fn new() -> Self {
+        Curve { keys: Vec::new() }
+    }
+
+    /// Same value at every time
+    pub fn constant(value: f32) -> Self {
+        let mut curve = Curve::new();
+        curve.add_key(0.0, value, Interpolation::Linear);
+        curve
+    }
+
+    /// Straight line from `from` at 0 to `to` at 1
+    pub fn linear(from: f32, to: f32) -> Self {
+        let mut curve = Curve::new();
+        curve.add_key(0.0, from, Interpolation::Linear);
+        curve.add_key(1.0, to, Interpolation::Linear);
+        curve
+    }
+
+    pub fn keys(&self) -> &[CurveKey] {
+        &self.keys
+    }
+
+    /// Insert a key, returns its index
+    /// A key at the same time as an existing one goes after it (the value jumps there)
+    pub fn add_key(&mut self, time: f32, value: f32, interpolation: Interpolation) -> usize {
+        let index = self.keys.partition_point(|key| key.time <= time);
+        self.keys.insert(index, CurveKey { time, value, interpolation });
+        index
+    }
+
+    pub fn remove_key(&mut self, index: usize) -> CurveKey {
+        self.keys.remove(index)
+    }
+
+    /// Move a key, returns its new index since it may pass its neighbours
+    pub fn set_key(&mut self, index: usize, time: f32, value: f32) -> usize {
+        let interpolation = self.keys.remove(index).interpolation;
+        self.add_key(time, value, interpolation)
+    }
+
+    pub fn set_interpolation(&mut self, index: usize, interpolation: Interpolation) {
+        self.keys[index].interpolation = interpolation;
+    }
+
+    /// Time of the first and last key, (0, 0) without keys
+    pub fn time_range(&self) -> (f32, f32) {
+        match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first.time, last.time),
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// Smallest and largest key value, (0, 0) without keys
+    /// Smooth segments can overshoot these slightly
+    pub fn value_range(&self) -> (f32, f32) {
+        if self.keys.is_empty() {
+            return (0.0, 0.0);
+        }
+        self.keys.iter().fold((f32::MAX, f32::MIN), |(min, max), key| (min.min(key.value), max.max(key.value)))
+    }
+
+    pub fn sample(&self, time: f32) -> f32 {
+        let (Some(first), Some(last)) = (self.keys.first(), self.keys.last()) else {
+            return 0.0;
+        };
+        if time <= first.time {
+            return first.value;
+        }
+        if time >= last.time {
+            return last.value;
+        }
+
+        // First key after `time`, the segment starts at the key before it
+        let next = self.keys.partition_point(|key| key.time <= time);
+        let (a, b) = (self.keys[next - 1], self.keys[next]);
+        let span = b.time - a.time;
+        if span <= f32::EPSILON {
+            return b.value;
+        }
+        let t = (time - a.time) / span;
+        match a.interpolation {
+            Interpolation::Step => a.value,
+            Interpolation::Linear => lerp(a.value, b.value, t),
+            Interpolation::Smooth => {
+                let (m0, m1) = (self.slope(next - 1), self.slope(next));
+                let (t2, t3) = (t * t, t * t * t);
+                (2.0 * t3 - 3.0 * t2 + 1.0) * a.value
+                    + (t3 - 2.0 * t2 + t) * span * m0
+                    + (-2.0 * t3 + 3.0 * t2) * b.value
+                    + (t3 - t2) * span * m1
+            }
+        }
+    }
+
+    /// Catmull-Rom tangent at a key, flat at the ends
+    fn slope(&self, index: usize) -> f32 {
+        if index == 0 || index + 1 >= self.keys.len() {
+            return 0.0;
+        }
+        let (before, after) = (self.keys[index - 1], self.keys[index + 1]);
+        let span = after.time - before.time;
+        if span <= f32::EPSILON { 0.0 } else { (after.value - before.value) / span }
+    }
+
+    /// Parse keys written by `to_text`: "time value interpolation" separated by ';'
+    /// The interpolation is optional and defaults to linear
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut curve = Curve::new();
+        for key in text.split(';').map(str::trim).filter(|key| !key.is_empty()) {
+            let mut parts = key.split_whitespace();
+            let time = parts.next()?.parse().ok()?;
+            let value = parts.next()?.parse().ok()?;
+            let interpolation = match parts.next() {
+                Some(name) => Interpolation::from_name(name)?,
+                None => Interpolation::Linear,
+            };
+            if parts.next().is_some() {
+                return None;
+            }
+            curve.add_key(time, value, interpolation);
+        }
+        Some(curve)
+    }
+
+    /// e.g. "0 0 smooth; 0.5 1 linear; 1 0 linear"
+    pub fn to_text(&self) -> String {
+        self.keys
+            .iter()
+            .map(|key| format!("{} {} {}", key.time, key.value, key.interpolation.name()))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    /// Position along the gradient, 0..1
+    pub t: f32,
+    pub color: Color,
+}
+
+/// Colors blended over 0..1, e.g. particle color over lifetime
+///
+/// Stops are kept sorted. Colors blend in linear space, before the first stop and after
+/// the last one the end color holds. A gradient without stops is white.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Gradient {
+    stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    pub fn new() -> Self {
+        Gradient { stops: Vec::new() }
+    }
+
+    pub fn solid(color: Color) -> Self {
+        let mut gradient = Gradient::new();
+        gradient.add_stop(0.0, color);
+        gradient
+    }
+
+    /// `from` at 0 to `to` at 1
+    pub fn two(from: Color, to: Color) -> Self {
+        let mut gradient = Gradient::new();
+        gradient.add_stop(0.0, from);
+        gradient.add_stop(1.0, to);
+        gradient
+    }
+
+    /// Four evenly spaced colors, the first at 0 and the last at 1
+    pub fn four(colors: [Color; 4]) -> Self {
+        let mut gradient = Gradient::new();
+        for (i, color) in colors.into_iter().enumerate() {
+            gradient.add_stop(i as f32 / 3.0, color);
+        }
+        gradient
+    }
+
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
+    }
+
+    /// Insert a stop, returns its index
+    pub fn add_stop(&mut self, t: f32, color: Color) -> usize {
+        let t = t.clamp(0.0, 1.0);
+        let index = self.stops.partition_point(|stop| stop.t <= t);
+        self.stops.insert(index, GradientStop { t, color });
+        index
+    }
+
+    pub fn remove_stop(&mut self, index: usize) -> GradientStop {
+        self.stops.remove(index)
+    }
+
+    /// Move a stop, returns its new index since it may pass its neighbours
+    pub fn move_stop(&mut self, index: usize, t: f32) -> usize {
+        let color = self.stops.remove(index).color;
+        self.add_stop(t, color)
+    }
+
+    pub fn set_color(&mut self, index: usize, color: Color) {
+        self.stops[index].color = color;
+    }
+
+    pub fn sample(&self, t: f32) -> Color {
+        let (Some(first), Some(last)) = (self.stops.first(), self.stops.last()) else {
+            return Color::WHITE;
+        };
+        if t <= first.t {
+            return first.color;
+        }
+        if t >= last.t {
+            return last.color;
+        }
+
+        let next = self.stops.partition_point(|stop| stop.t <= t);
+        let (a, b) = (self.stops[next - 1], self.stops[next]);
+        let span = b.t - a.t;
+        if span <= f32::EPSILON {
+            return b.color;
+        }
+        lerp(a.color, b.color, (t - a.t) / span)
+    }
+
+    /// Parse stops written by `to_text`: "t #RRGGBBAA" separated by ';'
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut gradient = Gradient::new();
+        for stop in text.split(';').map(str::trim).filter(|stop| !stop.is_empty()) {
+            let mut parts = stop.split_whitespace();
+            let t = parts.next()?.parse().ok()?;
+            let color = Color::hex(parts.next()?)?;
+            if parts.next().is_some() {
+                return None;
+            }
+            gradient.add_stop(t, color);
+        }
+        Some(gradient)
+    }
+
+    /// e.g. "0 #FF0000FF; 1 #0000FF00", colors in sRGB hex like `Color::to_hex`
+    pub fn to_text(&self) -> String {
+        self.stops
+            .iter()
+            .map(|stop| format!("{} {}", stop.t, stop.color.to_hex(true)))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
@@ -0,0 +1,180 @@
+//! Easing curves and interpolation helpers
+//!
+//! Easing functions map a normalized time `t` in 0..1 to progress, 0 at the start and
+//! 1 at the end. `back` and `elastic` overshoot outside 0..1 on the way.
+
+use glam::{Vec2, Vec3};
+use std::f32::consts::{PI, TAU};
+
+use super::{Color, Rect};
+
+/// Easing curve, `apply` clamps `t` to 0..1
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Ease {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+}
+
+impl Ease {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear => t,
+            Ease::QuadIn => quad_in(t),
+            Ease::QuadOut => out(quad_in, t),
+            Ease::QuadInOut => in_out(quad_in, t),
+            Ease::CubicIn => cubic_in(t),
+            Ease::CubicOut => out(cubic_in, t),
+            Ease::CubicInOut => in_out(cubic_in, t),
+            Ease::ExpoIn => expo_in(t),
+            Ease::ExpoOut => out(expo_in, t),
+            Ease::ExpoInOut => in_out(expo_in, t),
+            Ease::BackIn => back_in(t),
+            Ease::BackOut => out(back_in, t),
+            Ease::BackInOut => in_out(back_in, t),
+            Ease::ElasticIn => elastic_in(t),
+            Ease::ElasticOut => out(elastic_in, t),
+            Ease::ElasticInOut => in_out(elastic_in, t),
+        }
+    }
+}
+
+pub fn quad_in(t: f32) -> f32 {
+    t * t
+}
+
+pub fn cubic_in(t: f32) -> f32 {
+    t * t * t
+}
+
+pub fn expo_in(t: f32) -> f32 {
+    if t <= 0.0 { 0.0 } else { 2f32.powf(10.0 * t - 10.0) }
+}
+
+/// Pulls back before moving forward
+pub fn back_in(t: f32) -> f32 {
+    const OVERSHOOT: f32 = 1.70158;
+    t * t * ((OVERSHOOT + 1.0) * t - OVERSHOOT)
+}
+
+/// Oscillates with growing amplitude like a stretched spring
+pub fn elastic_in(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+    -(2f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * (TAU / 3.0)).sin()
+}
+
+/// Mirror an ease-in curve into its ease-out
+pub fn out(ease_in: fn(f32) -> f32, t: f32) -> f32 {
+    1.0 - ease_in(1.0 - t)
+}
+
+/// Ease-in for the first half, ease-out for the second
+pub fn in_out(ease_in: fn(f32) -> f32, t: f32) -> f32 {
+    if t < 0.5 {
+        ease_in(t * 2.0) / 2.0
+    } else {
+        1.0 - ease_in((1.0 - t) * 2.0) / 2.0
+    }
+}
+
+/// Values that can be blended linearly, `t` = 0 gives `self` and 1 gives `other`
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vec2 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Vec2::lerp(self, other, t)
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Vec3::lerp(self, other, t)
+    }
+}
+
+/// Blends in linear space, like the GPU does
+impl Lerp for Color {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Color {
+            r: self.r.lerp(other.r, t),
+            g: self.g.lerp(other.g, t),
+            b: self.b.lerp(other.b, t),
+            a: self.a.lerp(other.a, t),
+        }
+    }
+}
+
+/// Blends position and size
+impl Lerp for Rect {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Rect::new(
+            self.x.lerp(other.x, t),
+            self.y.lerp(other.y, t),
+            self.width.lerp(other.width, t),
+            self.height.lerp(other.height, t),
+        )
+    }
+}
+
+pub fn lerp<T: Lerp>(a: T, b: T, t: f32) -> T {
+    a.lerp(b, t)
+}
+
+/// Blend with an easing curve applied to `t`
+pub fn ease<T: Lerp>(a: T, b: T, t: f32, curve: Ease) -> T {
+    a.lerp(b, curve.apply(t))
+}
+
+/// Where `value` lies between `a` and `b`, 0 at `a` and 1 at `b` (not clamped)
+pub fn inverse_lerp(a: f32, b: f32, value: f32) -> f32 {
+    if a == b { 0.0 } else { (value - a) / (b - a) }
+}
+
+/// Smooth 0 to 1 transition between the edges (Hermite, zero slope at both ends)
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = inverse_lerp(edge0, edge1, x).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Blend between angles in radians along the shorter way around
+pub fn lerp_angle(a: f32, b: f32, t: f32) -> f32 {
+    let delta = (b - a + PI).rem_euclid(TAU) - PI;
+    a + delta * t
+}
+
+/// Rotate direction `a` towards `b` at a constant angular speed, lengths are blended linearly
+pub fn slerp(a: Vec2, b: Vec2, t: f32) -> Vec2 {
+    let angle = lerp_angle(a.to_angle(), b.to_angle(), t);
+    Vec2::from_angle(angle) * a.length().lerp(b.length(), t)
+}
+
+/// Frame rate independent exponential smoothing (camera follow, value chasing)
+/// `sharpness` is how fast the gap closes per second, `dt` the frame time in seconds
+pub fn damp<T: Lerp>(current: T, target: T, sharpness: f32, dt: f32) -> T {
+    current.lerp(target, 1.0 - (-sharpness * dt).exp())
+}
@@ -1,2 +1,25 @@
 mod transform;
-pub use transform::Transform;
\ No newline at end of file
+pub use transform::Transform;
+
+mod transform3d;
+pub use transform3d::Transform3D;
+
+mod color;
+pub use color::{linear_to_srgb, srgb_to_linear, Color};
+
+mod rect;
+pub use rect::Rect;
+
+mod curve;
+pub use curve::{Curve, CurveKey, Gradient, GradientStop, Interpolation};
+
+pub mod coords;
+
+pub mod ease;
+
+pub mod rng;
+
+pub mod noise;
+
+mod quadtree;
+pub use quadtree::Quadtree;
@@ -0,0 +1,137 @@
+//! Gradient noise for procedural textures, terrain and organic motion
+//!
+//! Every function returns roughly -1..1 and is 0 at integer lattice points (Perlin).
+//! The lattice is shuffled from a seed, so each `Noise` is a different, repeatable field.
+
+use glam::Vec2;
+
+use super::rng::Rng;
+
+/// Gradient directions of 2D Perlin noise
+const GRADIENTS_2D: [Vec2; 8] = [
+    Vec2::new(1.0, 0.0),
+    Vec2::new(-1.0, 0.0),
+    Vec2::new(0.0, 1.0),
+    Vec2::new(0.0, -1.0),
+    Vec2::new(std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2),
+    Vec2::new(-std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2),
+    Vec2::new(std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2),
+    Vec2::new(-std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2),
+];
+
+/// Skew factors between the square and the simplex (triangle) grid
+const SKEW_2D: f32 = 0.366_025_42; // (sqrt(3) - 1) / 2
+const UNSKEW_2D: f32 = 0.211_324_87; // (3 - sqrt(3)) / 6
+
+/// Seeded noise field
+#[derive(Clone)]
+pub struct Noise {
+    /// Shuffled 0..256, repeated so lookups of hash + 1 don't wrap
+    permutation: [u8; 512],
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut values: Vec<u8> = (0..=255).collect();
+        Rng::new(seed).shuffle(&mut values);
+        let mut permutation = [0; 512];
+        for (i, slot) in permutation.iter_mut().enumerate() {
+            *slot = values[i & 255];
+        }
+        Noise { permutation }
+    }
+
+    fn hash(&self, x: i32) -> usize {
+        self.permutation[(x & 255) as usize] as usize
+    }
+
+    fn hash2(&self, x: i32, y: i32) -> usize {
+        self.permutation[self.hash(x) + (y & 255) as usize] as usize
+    }
+
+    /// 1D Perlin noise
+    pub fn perlin1(&self, x: f32) -> f32 {
+        let cell = x.floor();
+        let i = cell as i32;
+        let t = x - cell;
+        let gradient = |hash: usize, d: f32| {
+            // Slopes -8..8 excluding 0
+            let slope = (hash & 7) as f32 + 1.0;
+            if hash & 8 != 0 { -slope * d } else { slope * d }
+        };
+        let a = gradient(self.hash(i), t);
+        let b = gradient(self.hash(i + 1), t - 1.0);
+        // Largest slope at the middle of a cell gives 8 * 0.5
+        (a + (b - a) * fade(t)) * 0.25
+    }
+
+    /// 2D Perlin noise
+    pub fn perlin2(&self, point: Vec2) -> f32 {
+        let cell = point.floor();
+        let (x, y) = (cell.x as i32, cell.y as i32);
+        let offset = point - cell;
+        let corner = |dx: i32, dy: i32| {
+            let gradient = GRADIENTS_2D[self.hash2(x + dx, y + dy) & 7];
+            gradient.dot(offset - Vec2::new(dx as f32, dy as f32))
+        };
+        let u = fade(offset.x);
+        let v = fade(offset.y);
+        let bottom = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * u;
+        let top = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * u;
+        // Unit gradients peak at sqrt(0.5) in the middle of a cell
+        (bottom + (top - bottom) * v) * std::f32::consts::SQRT_2
+    }
+
+    /// 2D simplex noise, fewer directional artifacts than Perlin and cheaper per octave
+    pub fn simplex2(&self, point: Vec2) -> f32 {
+        // Cell of the skewed grid and the position in its unskewed triangle
+        let skew = (point.x + point.y) * SKEW_2D;
+        let cell = (point + Vec2::splat(skew)).floor();
+        let unskew = (cell.x + cell.y) * UNSKEW_2D;
+        let d0 = point - (cell - Vec2::splat(unskew));
+        let step = if d0.x > d0.y { Vec2::X } else { Vec2::Y };
+        let d1 = d0 - step + Vec2::splat(UNSKEW_2D);
+        let d2 = d0 - Vec2::ONE + Vec2::splat(2.0 * UNSKEW_2D);
+
+        let (i, j) = (cell.x as i32, cell.y as i32);
+        let corner = |d: Vec2, di: i32, dj: i32| {
+            let falloff = 0.5 - d.length_squared();
+            if falloff <= 0.0 {
+                return 0.0;
+            }
+            let gradient = GRADIENTS_2D[self.hash2(i + di, j + dj) & 7];
+            falloff.powi(4) * gradient.dot(d)
+        };
+        let n = corner(d0, 0, 0) + corner(d1, step.x as i32, step.y as i32) + corner(d2, 1, 1);
+        // Scales the peak of the kernel sum to about 1
+        n * 99.2
+    }
+
+    /// Fractal sum of `octaves` layers of simplex noise (fractional Brownian motion)
+    /// Each octave has `lacunarity` times the frequency and `gain` times the amplitude
+    /// of the previous one, the sum is normalized back to about -1..1.
+    pub fn fbm2(&self, point: Vec2, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut total_amplitude = 0.0;
+        for _ in 0..octaves {
+            sum += self.simplex2(point * frequency) * amplitude;
+            total_amplitude += amplitude;
+            amplitude *= gain;
+            frequency *= lacunarity;
+        }
+        if total_amplitude > 0.0 { sum / total_amplitude } else { 0.0 }
+    }
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Perlin's quintic fade, zero first and second derivatives at 0 and 1
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
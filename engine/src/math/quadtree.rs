@@ -0,0 +1,247 @@
+use glam::Vec2;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::math::Rect;
+
+/// Items a node holds before it splits into quadrants
+const MAX_ITEMS: usize = 8;
+/// Nodes this small don't split, so stacked items can't recurse forever
+const MIN_NODE_SIZE: f32 = 1.0;
+/// Side of the root before the first item grows it
+const INITIAL_SIZE: f32 = 64.0;
+/// Offsets of the quadrants in halves of their parent
+const QUADRANTS: [(f32, f32); 4] = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+
+struct Node<T> {
+    bounds: Rect,
+    /// Quadrant node indices, `None` for leaves
+    children: Option<[usize; 4]>,
+    /// Items inside `bounds` that no quadrant holds entirely
+    items: Vec<(T, Rect)>,
+}
+
+impl<T> Node<T> {
+    fn new(bounds: Rect) -> Self {
+        Node { bounds, children: None, items: Vec::new() }
+    }
+}
+
+/// Quadtree over item bounds for culling and area queries in 2D
+///
+/// Items live in the smallest node containing their whole rect, so a query only visits the
+/// nodes overlapping it. The root grows to fit items placed outside it, and inserting an item
+/// again only moves it when it left its leaf, so moving a few items in a large world stays cheap.
+/// Emptied nodes aren't merged back, `clear` starts over.
+pub struct Quadtree<T> {
+    nodes: Vec<Node<T>>,
+    root: usize,
+    /// Node each item is in, for removal without searching
+    locations: HashMap<T, usize>,
+}
+
+impl<T: Copy + Eq + Hash> Quadtree<T> {
+    pub fn new() -> Self {
+        let root = Node::new(Rect::from_center_size(Vec2::ZERO, Vec2::splat(INITIAL_SIZE)));
+        Quadtree { nodes: vec![root], root: 0, locations: HashMap::new() }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    pub fn contains(&self, item: T) -> bool {
+        self.locations.contains_key(&item)
+    }
+
+    /// Area covered by the root, every item is inside it unless too far out to grow to
+    pub fn bounds(&self) -> Rect {
+        self.nodes[self.root].bounds
+    }
+
+    /// Rect an item was inserted with
+    pub fn rect(&self, item: T) -> Option<Rect> {
+        let node = &self.nodes[*self.locations.get(&item)?];
+        node.items.iter().find(|(other, _)| *other == item).map(|(_, rect)| *rect)
+    }
+
+    /// Add an item or move it to a new rect
+    /// Rects that aren't finite (NaN or infinite coordinates) are ignored.
+    pub fn insert(&mut self, item: T, rect: Rect) {
+        if !rect.min().is_finite() || !rect.max().is_finite() {
+            log::warn!("Ignoring a quadtree item with bounds {:?}", rect);
+            return;
+        }
+        if let Some(&index) = self.locations.get(&item) {
+            let node = &mut self.nodes[index];
+            // Still in the same leaf, only the stored rect changes
+            if node.children.is_none() && contains(&node.bounds, &rect) {
+                if let Some(entry) = node.items.iter_mut().find(|(other, _)| *other == item) {
+                    entry.1 = rect;
+                }
+                return;
+            }
+            self.remove(item);
+        }
+        self.grow_to(&rect);
+        let index = self.insert_at(self.root, item, rect);
+        self.locations.insert(item, index);
+    }
+
+    /// Remove an item, returns whether it was in the tree
+    pub fn remove(&mut self, item: T) -> bool {
+        let Some(index) = self.locations.remove(&item) else {
+            return false;
+        };
+        self.nodes[index].items.retain(|(other, _)| *other != item);
+        true
+    }
+
+    /// Items whose rects overlap `area`, touching edges count
+    pub fn query(&self, area: Rect) -> Vec<T> {
+        let mut found = Vec::new();
+        let mut stack = vec![self.root];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            // The root may hold items too far out to grow it to, see `grow_to`
+            if index != self.root && !overlaps(&node.bounds, &area) {
+                continue;
+            }
+            found.extend(node.items.iter().filter(|(_, rect)| overlaps(rect, &area)).map(|(item, _)| *item));
+            if let Some(children) = node.children {
+                stack.extend(children);
+            }
+        }
+        found
+    }
+
+    /// Items whose rects contain `point`
+    pub fn query_point(&self, point: Vec2) -> Vec<T> {
+        self.query(Rect::new(point.x, point.y, 0.0, 0.0))
+    }
+
+    /// Put an item into the subtree of node `index`, returns the node it ended up in
+    fn insert_at(&mut self, mut index: usize, item: T, rect: Rect) -> usize {
+        loop {
+            if let Some(children) = self.nodes[index].children {
+                match children.into_iter().find(|&child| contains(&self.nodes[child].bounds, &rect)) {
+                    Some(child) => index = child,
+                    None => break,
+                }
+                continue;
+            }
+            let node = &self.nodes[index];
+            if node.items.len() < MAX_ITEMS || node.bounds.width / 2.0 < MIN_NODE_SIZE {
+                break;
+            }
+            self.split(index);
+        }
+        self.nodes[index].items.push((item, rect));
+        index
+    }
+
+    /// Give a leaf four quadrants and move down the items that fit in one
+    fn split(&mut self, index: usize) {
+        let bounds = self.nodes[index].bounds;
+        let half = bounds.size() / 2.0;
+        let first = self.nodes.len();
+        for (dx, dy) in QUADRANTS {
+            self.nodes.push(Node::new(Rect::new(bounds.x + dx * half.x, bounds.y + dy * half.y, half.x, half.y)));
+        }
+        let children = [first, first + 1, first + 2, first + 3];
+        self.nodes[index].children = Some(children);
+
+        let items = std::mem::take(&mut self.nodes[index].items);
+        for (item, rect) in items {
+            let target = children.into_iter().find(|&child| contains(&self.nodes[child].bounds, &rect)).unwrap_or(index);
+            self.nodes[target].items.push((item, rect));
+            self.locations.insert(item, target);
+        }
+    }
+
+    /// Double the root towards `rect` until it fits, the old root becomes a quadrant
+    /// Growing stops once rounding would leave the old root outside the new one, items still
+    /// outside then stay in the root.
+    fn grow_to(&mut self, rect: &Rect) {
+        while !contains(&self.nodes[self.root].bounds, rect) {
+            let old = self.nodes[self.root].bounds;
+            // Which quadrant of the new root the old one is
+            let old_dx = if rect.x < old.x { 1.0 } else { 0.0 };
+            let old_dy = if rect.y < old.y { 1.0 } else { 0.0 };
+            let x = old.x - old_dx * old.width;
+            let y = old.y - old_dy * old.height;
+            let grown = Rect::new(x, y, old.width * 2.0, old.height * 2.0);
+            if !grown.max().is_finite() || !contains(&grown, &old) {
+                break;
+            }
+            let mut children = [self.root; 4];
+            for (slot, (dx, dy)) in QUADRANTS.into_iter().enumerate() {
+                if (dx, dy) != (old_dx, old_dy) {
+                    children[slot] = self.nodes.len();
+                    self.nodes.push(Node::new(Rect::new(x + dx * old.width, y + dy * old.height, old.width, old.height)));
+                }
+            }
+            let mut root = Node::new(grown);
+            root.children = Some(children);
+            // Items the old root held from past the edge move up with the edge
+            let (inside, outside) = std::mem::take(&mut self.nodes[self.root].items)
+                .into_iter()
+                .partition(|(_, rect)| contains(&old, rect));
+            self.nodes[self.root].items = inside;
+            root.items = outside;
+            self.root = self.nodes.len();
+            for (item, _) in &root.items {
+                self.locations.insert(*item, self.root);
+            }
+            self.nodes.push(root);
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> Default for Quadtree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn contains(outer: &Rect, inner: &Rect) -> bool {
+    inner.min().cmpge(outer.min()).all() && inner.max().cmple(outer.max()).all()
+}
+
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.min().cmple(b.max()).all() && b.min().cmple(a.max()).all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extreme_rects_stay_in_the_root() {
+        let mut tree = Quadtree::new();
+        tree.insert(1, Rect::new(0.0, 0.0, 1.0, 1.0));
+        // Growing far enough to reach these would overflow or round away the root's bounds
+        tree.insert(2, Rect::new(-2e38, 0.0, 1.0, 1.0));
+        tree.insert(3, Rect::new(0.0, -3.4e38, 1.0, 1.0));
+        tree.insert(4, Rect::new(3e38, 3e38, 1.0, 1.0));
+        assert_eq!(tree.len(), 4);
+
+        assert_eq!(tree.query_point(Vec2::new(0.5, 0.5)), vec![1]);
+        assert_eq!(tree.query(Rect::new(-2e38, 0.0, 1.0, 1.0)), vec![2]);
+        assert_eq!(tree.query(Rect::new(0.0, -3.4e38, 1.0, 1.0)), vec![3]);
+
+        // Moving back in range and removing still work
+        tree.insert(2, Rect::new(2.0, 2.0, 1.0, 1.0));
+        assert_eq!(tree.query_point(Vec2::new(2.5, 2.5)), vec![2]);
+        assert!(tree.remove(3));
+        assert_eq!(tree.len(), 3);
+    }
+}
@@ -0,0 +1,84 @@
+use glam::Vec2;
+
+/// Axis-aligned rectangle in pixels, `x`/`y` is the corner with the smallest coordinates
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Rect { x, y, width, height }
+    }
+
+    /// Rectangle at the origin covering `width` x `height`
+    pub fn from_size(width: f32, height: f32) -> Self {
+        Rect::new(0.0, 0.0, width, height)
+    }
+
+    /// Rectangle centered on `center`, the layout of a `Transform` (position is the center, scale the size)
+    pub fn from_center_size(center: Vec2, size: Vec2) -> Self {
+        let min = center - size / 2.0;
+        Rect::new(min.x, min.y, size.x, size.y)
+    }
+
+    pub fn min(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    pub fn max(&self) -> Vec2 {
+        Vec2::new(self.x + self.width, self.y + self.height)
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.min() + self.size() / 2.0
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        point.cmpge(self.min()).all() && point.cmple(self.max()).all()
+    }
+
+    /// Grow every side by `amount`, negative amounts shrink (e.g. padding) down to zero size
+    pub fn inflate(&self, amount: f32) -> Rect {
+        let width = (self.width + amount * 2.0).max(0.0);
+        let height = (self.height + amount * 2.0).max(0.0);
+        Rect::from_center_size(self.center(), Vec2::new(width, height))
+    }
+
+    /// Smallest rectangle containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let min = self.min().min(other.min());
+        let max = self.max().max(other.max());
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    /// Overlap of two rectangles, empty (zero size) if they don't overlap
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let min = self.min().max(other.min());
+        let max = self.max().min(other.max()).max(min);
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    /// `count` equal rectangles side by side, left to right (e.g. two player split-screen)
+    pub fn split_columns(&self, count: u32) -> Vec<Rect> {
+        let width = self.width / count.max(1) as f32;
+        (0..count)
+            .map(|i| Rect::new(self.x + width * i as f32, self.y, width, self.height))
+            .collect()
+    }
+
+    /// `count` equal rectangles stacked along y, from the smallest y up
+    pub fn split_rows(&self, count: u32) -> Vec<Rect> {
+        let height = self.height / count.max(1) as f32;
+        (0..count)
+            .map(|i| Rect::new(self.x, self.y + height * i as f32, self.width, height))
+            .collect()
+    }
+}
@@ -1,4 +1,7 @@
 pub use glam::Vec2;
+use glam::{Mat4, Vec3};
+
+use super::Rect;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Transform {
@@ -16,16 +19,29 @@ impl Transform {
         }
     }
     
+    /// Scale, then rotate (radians around Z), then translate
+    pub fn to_matrix(&self) -> Mat4 {
+        Mat4::from_translation(Vec3::new(self.position.x, self.position.y, 0.0))
+            * Mat4::from_rotation_z(self.rotation)
+            * Mat4::from_scale(Vec3::new(self.scale.x, self.scale.y, 1.0))
+    }
+
+    /// Axis-aligned bounds, ignoring rotation
+    pub fn rect(&self) -> Rect {
+        Rect::from_center_size(self.position, self.scale)
+    }
+
+    /// Centered on the rect and sized to it, without rotation
+    pub fn from_rect(rect: Rect) -> Self {
+        Self {
+            position: rect.center(),
+            rotation: 0.0,
+            scale: rect.size(),
+        }
+    }
+
     pub fn contains_point(&self, point: Vec2) -> bool {
-        let half_width = self.scale.x * 0.5;
-        let half_height = self.scale.y * 0.5;
-        
-        let min_x = self.position.x - half_width;
-        let max_x = self.position.x + half_width;
-        let min_y = self.position.y - half_height;
-        let max_y = self.position.y + half_height;
-        
-        point.x >= min_x && point.x <= max_x && point.y >= min_y && point.y <= max_y
+        self.rect().contains_point(point)
     }
 }
 
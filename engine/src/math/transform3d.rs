@@ -0,0 +1,98 @@
+use glam::{Mat3, Mat4, Quat, Vec3};
+
+use super::Transform;
+
+/// Position, rotation and scale in 3D
+///
+/// Right handed, an unrotated transform looks down -Z with +Y up like glam's `look_at_rh`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform3D {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform3D {
+    pub fn new() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+
+    pub fn from_position(position: Vec3) -> Self {
+        Self { position, ..Self::new() }
+    }
+
+    /// Transform at `eye` facing `target`, `up` must not be parallel to the view direction
+    pub fn looking_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        let mut transform = Self::from_position(eye);
+        transform.look_at(target, up);
+        transform
+    }
+
+    /// Rotate to face `target` from the current position
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        // look_at_rh is the world to view rotation, the transform is its inverse
+        self.rotation = Quat::from_mat3(&Mat3::look_at_rh(self.position, target, up)).inverse();
+    }
+
+    /// Decompose an affine matrix (no shear or perspective)
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, position) = matrix.to_scale_rotation_translation();
+        Self { position, rotation, scale }
+    }
+
+    /// Scale, then rotate, then translate
+    pub fn to_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+    }
+
+    /// World to view matrix of a camera placed by this transform (scale ignored)
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.rotation, self.position).inverse()
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.position + self.rotation * (self.scale * point)
+    }
+
+    /// `child` expressed relative to this transform, moved into this transform's parent space
+    pub fn mul_transform(&self, child: &Transform3D) -> Transform3D {
+        Transform3D {
+            position: self.transform_point(child.position),
+            rotation: self.rotation * child.rotation,
+            scale: self.scale * child.scale,
+        }
+    }
+}
+
+impl Default for Transform3D {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The 2D transform on the z = 0 plane, rotated around Z
+impl From<Transform> for Transform3D {
+    fn from(transform: Transform) -> Self {
+        Transform3D {
+            position: transform.position.extend(0.0),
+            rotation: Quat::from_rotation_z(transform.rotation),
+            scale: transform.scale.extend(1.0),
+        }
+    }
+}
@@ -0,0 +1,389 @@
+//! Grid navigation for 2D games.
+//! A `NavGrid` covers part of the world with square cells that are either blocked or
+//! walkable at some cost, `find_path` runs A* over it (8 directions, no cutting
+//! corners past blocked cells) and `smooth_path` drops waypoints that can be skipped
+//! in a straight line. Entities with a `PathFollower` walk their path in `follow_paths`.
+
+use glam::{IVec2, Vec2};
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::ecs::{ECSComponent, EntityId, World};
+use crate::math::{Rect, Transform};
+
+const DIAGONAL: f32 = std::f32::consts::SQRT_2;
+
+/// Walkable cells and their movement costs over a rectangle of the world
+#[derive(Clone, Debug)]
+pub struct NavGrid {
+    width: i32,
+    height: i32,
+    cell_size: f32,
+    /// World position of the bottom left corner of cell (0, 0)
+    origin: Vec2,
+    /// Cost multiplier per cell, row by row from the bottom, `None` when blocked
+    costs: Vec<Option<f32>>,
+}
+
+impl NavGrid {
+    /// Grid of `width` by `height` cells, all walkable at cost 1
+    pub fn new(width: u32, height: u32, cell_size: f32, origin: Vec2) -> Self {
+        NavGrid {
+            width: width as i32,
+            height: height as i32,
+            cell_size,
+            origin,
+            costs: vec![Some(1.0); (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height as u32
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// World area covered by the grid
+    pub fn bounds(&self) -> Rect {
+        Rect::new(self.origin.x, self.origin.y, self.width as f32 * self.cell_size, self.height as f32 * self.cell_size)
+    }
+
+    pub fn in_bounds(&self, cell: IVec2) -> bool {
+        cell.x >= 0 && cell.y >= 0 && cell.x < self.width && cell.y < self.height
+    }
+
+    fn index(&self, cell: IVec2) -> usize {
+        (cell.y * self.width + cell.x) as usize
+    }
+
+    fn cell_of(&self, index: usize) -> IVec2 {
+        IVec2::new(index as i32 % self.width, index as i32 / self.width)
+    }
+
+    /// Cost multiplier of entering a cell, `None` if blocked or outside the grid
+    pub fn cost(&self, cell: IVec2) -> Option<f32> {
+        if self.in_bounds(cell) { self.costs[self.index(cell)] } else { None }
+    }
+
+    pub fn is_walkable(&self, cell: IVec2) -> bool {
+        self.cost(cell).is_some()
+    }
+
+    /// Set a cell's cost (clamped to a small positive value), `None` blocks it
+    pub fn set_cost(&mut self, cell: IVec2, cost: Option<f32>) {
+        if self.in_bounds(cell) {
+            let index = self.index(cell);
+            self.costs[index] = cost.map(|cost| cost.max(0.01));
+        }
+    }
+
+    pub fn set_blocked(&mut self, cell: IVec2, blocked: bool) {
+        self.set_cost(cell, if blocked { None } else { Some(1.0) });
+    }
+
+    /// Set the cost of every cell overlapping a world rectangle, e.g. a collider's bounds
+    pub fn set_rect_cost(&mut self, rect: Rect, cost: Option<f32>) {
+        let min = ((rect.min() - self.origin) / self.cell_size).floor().as_ivec2();
+        // Cells merely touching the far edge aren't covered
+        let max = ((rect.max() - self.origin) / self.cell_size).ceil().as_ivec2() - IVec2::ONE;
+        for y in min.y.max(0)..=max.y.min(self.height - 1) {
+            for x in min.x.max(0)..=max.x.min(self.width - 1) {
+                self.set_cost(IVec2::new(x, y), cost);
+            }
+        }
+    }
+
+    /// Block every cell under the world rectangle of each entity with a Transform that passes `filter`
+    pub fn block_entities(&mut self, world: &World, filter: impl Fn(EntityId) -> bool) {
+        for id in world.entity_ids().filter(|&id| filter(id)) {
+            if world.has::<Transform>(id) {
+                let matrix = world.world_matrix(id);
+                // Axis aligned bounds of the transformed unit quad
+                let corners = [Vec2::new(-0.5, -0.5), Vec2::new(0.5, -0.5), Vec2::new(0.5, 0.5), Vec2::new(-0.5, 0.5)]
+                    .map(|corner| matrix.transform_point3(corner.extend(0.0)).truncate());
+                let min = corners.iter().fold(Vec2::MAX, |min, &c| min.min(c));
+                let max = corners.iter().fold(Vec2::MIN, |max, &c| max.max(c));
+                self.set_rect_cost(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y), None);
+            }
+        }
+    }
+
+    /// Cell containing a world position, which may be outside the grid
+    pub fn cell_at(&self, position: Vec2) -> IVec2 {
+        ((position - self.origin) / self.cell_size).floor().as_ivec2()
+    }
+
+    pub fn cell_center(&self, cell: IVec2) -> Vec2 {
+        self.origin + (cell.as_vec2() + Vec2::splat(0.5)) * self.cell_size
+    }
+
+    /// Cheapest path between the cells of two world positions, from start to goal
+    /// The first and last points are `start` and `goal` themselves, the rest are cell centers.
+    /// `None` if either end is blocked or outside the grid, or the goal can't be reached.
+    pub fn find_path(&self, start: Vec2, goal: Vec2) -> Option<Vec<Vec2>> {
+        let cells = self.find_cell_path(self.cell_at(start), self.cell_at(goal))?;
+        let mut path: Vec<Vec2> = cells.iter().map(|&cell| self.cell_center(cell)).collect();
+        path[0] = start;
+        *path.last_mut().unwrap() = goal;
+        Some(path)
+    }
+
+    /// A* from cell to cell, both included in the returned path
+    pub fn find_cell_path(&self, start: IVec2, goal: IVec2) -> Option<Vec<IVec2>> {
+        crate::profile_scope!("find_path");
+        if !self.is_walkable(start) || !self.is_walkable(goal) {
+            return None;
+        }
+        // Scaling by the cheapest cell keeps the heuristic admissible
+        let min_cost = self.costs.iter().flatten().fold(f32::MAX, |min, &cost| min.min(cost));
+        let heuristic = |cell: IVec2| {
+            let delta = (goal - cell).abs();
+            let (long, short) = (delta.max_element() as f32, delta.min_element() as f32);
+            (long - short + short * DIAGONAL) * min_cost
+        };
+
+        let count = self.costs.len();
+        let mut best = vec![f32::INFINITY; count];
+        let mut came_from = vec![usize::MAX; count];
+        let mut closed = vec![false; count];
+        let mut open = BinaryHeap::new();
+        let (start_index, goal_index) = (self.index(start), self.index(goal));
+        best[start_index] = 0.0;
+        open.push(OpenNode { estimate: heuristic(start), index: start_index });
+
+        while let Some(OpenNode { index, .. }) = open.pop() {
+            if index == goal_index {
+                let mut path = vec![goal];
+                let mut current = index;
+                while current != start_index {
+                    current = came_from[current];
+                    path.push(self.cell_of(current));
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if std::mem::replace(&mut closed[index], true) {
+                continue;
+            }
+
+            let cell = self.cell_of(index);
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let step = IVec2::new(dx, dy);
+                    if step == IVec2::ZERO {
+                        continue;
+                    }
+                    let next = cell + step;
+                    let Some(cost) = self.cost(next) else {
+                        continue;
+                    };
+                    let diagonal = dx != 0 && dy != 0;
+                    // Don't squeeze between two blocked cells or clip a blocked corner
+                    if diagonal && (!self.is_walkable(cell + IVec2::new(dx, 0)) || !self.is_walkable(cell + IVec2::new(0, dy))) {
+                        continue;
+                    }
+                    let next_index = self.index(next);
+                    let distance = best[index] + cost * if diagonal { DIAGONAL } else { 1.0 };
+                    if distance < best[next_index] {
+                        best[next_index] = distance;
+                        came_from[next_index] = index;
+                        open.push(OpenNode { estimate: distance + heuristic(next), index: next_index });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether every cell along the straight line between two world positions is walkable
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        // Grid traversal (Amanatides & Woo) over every cell the segment touches
+        let (a, b) = ((from - self.origin) / self.cell_size, (to - self.origin) / self.cell_size);
+        let mut cell = a.floor().as_ivec2();
+        let end = b.floor().as_ivec2();
+        let direction = b - a;
+        let step = IVec2::new(direction.x.signum() as i32, direction.y.signum() as i32);
+        let boundary = |p: f32, d: f32| if d > 0.0 { p.floor() + 1.0 } else { p.floor() };
+        let mut t_max = Vec2::new(
+            if direction.x != 0.0 { (boundary(a.x, direction.x) - a.x) / direction.x } else { f32::INFINITY },
+            if direction.y != 0.0 { (boundary(a.y, direction.y) - a.y) / direction.y } else { f32::INFINITY },
+        );
+        let t_delta = Vec2::new(
+            if direction.x != 0.0 { 1.0 / direction.x.abs() } else { f32::INFINITY },
+            if direction.y != 0.0 { 1.0 / direction.y.abs() } else { f32::INFINITY },
+        );
+
+        loop {
+            if !self.is_walkable(cell) {
+                return false;
+            }
+            if cell == end {
+                return true;
+            }
+            if t_max.x < t_max.y {
+                cell.x += step.x;
+                t_max.x += t_delta.x;
+            } else if t_max.y < t_max.x {
+                cell.y += step.y;
+                t_max.y += t_delta.y;
+            } else {
+                // Exactly through a corner, both side cells must be open
+                if !self.is_walkable(cell + IVec2::new(step.x, 0)) || !self.is_walkable(cell + IVec2::new(0, step.y)) {
+                    return false;
+                }
+                cell += step;
+                t_max += t_delta;
+            }
+            if t_max.min_element() > 1.0 && cell != end {
+                // Rounding left the segment without reaching the end cell
+                return self.is_walkable(end);
+            }
+        }
+    }
+
+    /// Drop waypoints that the previous kept point can see past (string pulling)
+    /// Only blocked cells are considered, so smoothing can cut through costly cells.
+    pub fn smooth_path(&self, path: &[Vec2]) -> Vec<Vec2> {
+        let Some(&first) = path.first() else {
+            return Vec::new();
+        };
+        let mut smoothed = vec![first];
+        let mut anchor = 0;
+        for index in 1..path.len() {
+            let is_last = index + 1 == path.len();
+            if is_last || !self.line_of_sight(path[anchor], path[index + 1]) {
+                smoothed.push(path[index]);
+                anchor = index;
+            }
+        }
+        smoothed
+    }
+}
+
+/// Entry of the A* open list, popped lowest estimate first
+struct OpenNode {
+    estimate: f32,
+    index: usize,
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// Moves the entity's Transform along a path of world positions, see `follow_paths`
+///
+/// The path is followed in the entity's local space, which is world space for roots.
+#[derive(Clone, Debug)]
+pub struct PathFollower {
+    /// World units per second
+    pub speed: f32,
+    /// How close counts as having reached a waypoint
+    pub arrive_distance: f32,
+    path: Vec<Vec2>,
+    next: usize,
+}
+
+impl Default for PathFollower {
+    fn default() -> Self {
+        PathFollower { speed: 2.0, arrive_distance: 0.05, path: Vec::new(), next: 0 }
+    }
+}
+
+impl PathFollower {
+    pub fn new(speed: f32) -> Self {
+        PathFollower { speed, ..Default::default() }
+    }
+
+    /// Start following a new path from its first point
+    pub fn set_path(&mut self, path: Vec<Vec2>) {
+        self.path = path;
+        self.next = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.set_path(Vec::new());
+    }
+
+    pub fn path(&self) -> &[Vec2] {
+        &self.path
+    }
+
+    /// Waypoint currently walked towards
+    pub fn target(&self) -> Option<Vec2> {
+        self.path.get(self.next).copied()
+    }
+
+    /// True once the last waypoint is reached (or there is no path)
+    pub fn is_done(&self) -> bool {
+        self.next >= self.path.len()
+    }
+
+    /// New position after walking `dt` seconds from `position`
+    pub fn step(&mut self, position: Vec2, dt: f32) -> Vec2 {
+        let mut position = position;
+        let mut travel = self.speed.max(0.0) * dt;
+        while let Some(target) = self.target() {
+            let to_target = target - position;
+            let distance = to_target.length();
+            if distance <= self.arrive_distance.max(travel) {
+                // Carry the remaining movement over to the next waypoint
+                travel = (travel - distance).max(0.0);
+                position = target;
+                self.next += 1;
+                continue;
+            }
+            position += to_target / distance * travel;
+            break;
+        }
+        position
+    }
+}
+
+impl ECSComponent for PathFollower {
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn clone_box(&self) -> Box<dyn ECSComponent> { Box::new(self.clone()) }
+}
+
+/// Move every entity with a PathFollower and a Transform along its path
+pub fn follow_paths(world: &mut World, dt: f32) {
+    crate::profile_scope!("follow_paths");
+    let followers: Vec<EntityId> = world
+        .entity_ids()
+        .filter(|&id| world.has::<PathFollower>(id) && world.has::<Transform>(id))
+        .collect();
+    for id in followers {
+        let Some(position) = world.get::<Transform>(id).map(|transform| transform.position) else {
+            continue;
+        };
+        let Some(position) = world.get_mut::<PathFollower>(id).map(|follower| follower.step(position, dt)) else {
+            continue;
+        };
+        if let Some(transform) = world.get_mut::<Transform>(id) {
+            transform.position = position;
+        }
+    }
+}
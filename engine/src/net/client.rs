@@ -0,0 +1,181 @@
+use anyhow::{anyhow, Result};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+use crate::math::rng::Rng;
+use crate::net::connection::Connection;
+use crate::net::{receive_all, Channel, ClientId, DisconnectReason, NetConfig, Packet};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientEvent {
+    Connected(ClientId),
+    Disconnected(DisconnectReason),
+    Message { channel: Channel, data: Vec<u8> },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClientState {
+    /// Asking the server for a slot
+    Connecting,
+    Connected(ClientId),
+    Disconnected(DisconnectReason),
+}
+
+/// Connection to a `Server`, see the module docs
+pub struct Client {
+    socket: UdpSocket,
+    config: NetConfig,
+    server: SocketAddr,
+    state: ClientState,
+    connection: Connection,
+    started: Instant,
+    last_request: Option<Instant>,
+}
+
+impl Client {
+    /// Start connecting to a server, `update` reports `Connected` once it accepts
+    pub fn connect(server: impl ToSocketAddrs, config: NetConfig) -> Result<Self> {
+        let server = server
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("Server address resolved to nothing"))?;
+        let local: SocketAddr = if server.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(local).map_err(|e| anyhow!("Failed to bind client socket: {}", e))?;
+        socket.set_nonblocking(true)?;
+        // Tells this connection's packets apart from an earlier one's on the same port
+        let session = Rng::from_time().next_u64();
+        log::info!("Connecting to {}", server);
+        Ok(Client {
+            socket,
+            config,
+            server,
+            state: ClientState::Connecting,
+            connection: Connection::new(server, session),
+            started: Instant::now(),
+            last_request: None,
+        })
+    }
+
+    pub fn state(&self) -> ClientState {
+        self.state
+    }
+
+    pub fn is_connected(&self) -> bool {
+        matches!(self.state, ClientState::Connected(_))
+    }
+
+    /// Id the server gave this client
+    pub fn id(&self) -> Option<ClientId> {
+        match self.state {
+            ClientState::Connected(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    pub fn server_addr(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// Read incoming packets and check for a timeout
+    pub fn update(&mut self) -> Vec<ClientEvent> {
+        let mut events = Vec::new();
+        if let ClientState::Disconnected(_) = self.state {
+            return events;
+        }
+
+        let mut received = Vec::new();
+        receive_all(&self.socket, |from, bytes| {
+            if from != self.server {
+                return;
+            }
+            match Packet::decode(bytes, self.config.protocol_id) {
+                Some(packet) if packet.session() == self.connection.session => received.push(packet),
+                _ => {}
+            }
+        });
+        for packet in received {
+            match (packet, self.state) {
+                (Packet::ConnectAccept { client, .. }, ClientState::Connecting) => {
+                    self.state = ClientState::Connected(client);
+                    self.connection.touch();
+                    log::info!("Connected to {} as client {}", self.server, client.0);
+                    events.push(ClientEvent::Connected(client));
+                }
+                (Packet::ConnectDeny { .. }, ClientState::Connecting) => {
+                    self.state = ClientState::Disconnected(DisconnectReason::ServerFull);
+                    log::warn!("Server {} is full", self.server);
+                    events.push(ClientEvent::Disconnected(DisconnectReason::ServerFull));
+                }
+                (Packet::Disconnect { .. }, ClientState::Connected(_)) => {
+                    self.state = ClientState::Disconnected(DisconnectReason::Closed);
+                    log::info!("Server closed the connection");
+                    events.push(ClientEvent::Disconnected(DisconnectReason::Closed));
+                }
+                (Packet::Data { acks, messages, .. }, ClientState::Connected(_)) => {
+                    for message in self.connection.receive(&acks, messages) {
+                        events.push(ClientEvent::Message { channel: message.channel, data: message.data });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        let timed_out = match self.state {
+            ClientState::Connecting => now.duration_since(self.started) > self.config.timeout,
+            ClientState::Connected(_) => self.connection.timed_out(now, &self.config),
+            ClientState::Disconnected(_) => false,
+        };
+        if timed_out {
+            self.state = ClientState::Disconnected(DisconnectReason::TimedOut);
+            log::warn!("Connection to {} timed out", self.server);
+            events.push(ClientEvent::Disconnected(DisconnectReason::TimedOut));
+        }
+        events
+    }
+
+    /// Queue a message for the server, messages queued while connecting wait for it
+    pub fn send(&mut self, channel: Channel, data: &[u8]) -> Result<()> {
+        if let ClientState::Disconnected(_) = self.state {
+            return Err(anyhow!("Not connected to {}", self.server));
+        }
+        self.connection.queue(channel, data)
+    }
+
+    /// Send everything queued since the last flush, or the connection request
+    pub fn flush(&mut self) {
+        match self.state {
+            ClientState::Connecting => {
+                let now = Instant::now();
+                if self.last_request.is_none_or(|sent| now.duration_since(sent) >= self.config.resend) {
+                    self.last_request = Some(now);
+                    let request = Packet::ConnectRequest { session: self.connection.session };
+                    if let Err(e) = self.socket.send_to(&request.encode(self.config.protocol_id), self.server) {
+                        log::warn!("Failed to send to {}: {}", self.server, e);
+                    }
+                }
+            }
+            ClientState::Connected(_) => self.connection.flush(&self.socket, &self.config),
+            ClientState::Disconnected(_) => {}
+        }
+    }
+
+    /// Leave the server, telling it so
+    pub fn disconnect(&mut self) {
+        if let ClientState::Connected(_) = self.state {
+            self.connection.flush(&self.socket, &self.config);
+            let packet = Packet::Disconnect { session: self.connection.session };
+            // Never acknowledged, so send it a few times in case one is lost
+            for _ in 0..3 {
+                let _ = self.socket.send_to(&packet.encode(self.config.protocol_id), self.server);
+            }
+        }
+        self.state = ClientState::Disconnected(DisconnectReason::Closed);
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
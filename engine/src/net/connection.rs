@@ -0,0 +1,145 @@
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, VecDeque};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Instant;
+
+use crate::net::{Channel, Message, NetConfig, Packet, HEADER_SIZE, MAX_MESSAGE_SIZE, MAX_PACKET_SIZE, MESSAGE_HEADER_SIZE};
+
+/// Acknowledgements sent in one packet, the rest wait for the next
+const MAX_ACKS_PER_PACKET: usize = 64;
+/// Reliable messages in flight past the oldest unacknowledged one, later ones wait
+/// and the receiver drops anything further ahead than this
+const RELIABLE_WINDOW: u32 = 1024;
+
+struct PendingReliable {
+    sequence: u32,
+    data: Vec<u8>,
+    /// `None` until first sent
+    sent_at: Option<Instant>,
+}
+
+/// Reliability and message queues of one established connection
+pub(crate) struct Connection {
+    pub addr: SocketAddr,
+    pub session: u64,
+    last_received: Instant,
+    last_sent: Instant,
+    /// Sequence number of the next reliable message sent
+    next_sequence: u32,
+    unacked: VecDeque<PendingReliable>,
+    unreliable: Vec<Message>,
+    /// Sequence number of the next reliable message to deliver
+    next_expected: u32,
+    /// Reliable messages received ahead of `next_expected`
+    out_of_order: BTreeMap<u32, Vec<u8>>,
+    acks: Vec<u32>,
+}
+
+impl Connection {
+    pub fn new(addr: SocketAddr, session: u64) -> Self {
+        let now = Instant::now();
+        Connection {
+            addr,
+            session,
+            last_received: now,
+            last_sent: now,
+            next_sequence: 0,
+            unacked: VecDeque::new(),
+            unreliable: Vec::new(),
+            next_expected: 0,
+            out_of_order: BTreeMap::new(),
+            acks: Vec::new(),
+        }
+    }
+
+    pub fn queue(&mut self, channel: Channel, data: &[u8]) -> Result<()> {
+        if data.len() > MAX_MESSAGE_SIZE {
+            return Err(anyhow!("Message of {} bytes is larger than the limit of {}", data.len(), MAX_MESSAGE_SIZE));
+        }
+        match channel {
+            Channel::Reliable => {
+                self.unacked.push_back(PendingReliable { sequence: self.next_sequence, data: data.to_vec(), sent_at: None });
+                self.next_sequence = self.next_sequence.wrapping_add(1);
+            }
+            _ => self.unreliable.push(Message { channel, sequence: None, data: data.to_vec() }),
+        }
+        Ok(())
+    }
+
+    pub fn timed_out(&self, now: Instant, config: &NetConfig) -> bool {
+        now.duration_since(self.last_received) > config.timeout
+    }
+
+    /// Handle a data packet, returning the messages ready for the game in order
+    pub fn receive(&mut self, acks: &[u32], messages: Vec<Message>) -> Vec<Message> {
+        self.last_received = Instant::now();
+        self.unacked.retain(|pending| !acks.contains(&pending.sequence));
+
+        let mut delivered = Vec::new();
+        for message in messages {
+            let Some(sequence) = message.sequence else {
+                delivered.push(message);
+                continue;
+            };
+            // Acknowledge duplicates too, the earlier acknowledgement may have been lost
+            self.acks.push(sequence);
+            // Sequence numbers wrap, anything behind `next_expected` was already delivered
+            if sequence.wrapping_sub(self.next_expected) < RELIABLE_WINDOW {
+                self.out_of_order.entry(sequence).or_insert(message.data);
+            }
+            while let Some(data) = self.out_of_order.remove(&self.next_expected) {
+                delivered.push(Message { channel: Channel::Reliable, sequence: Some(self.next_expected), data });
+                self.next_expected = self.next_expected.wrapping_add(1);
+            }
+        }
+        delivered
+    }
+
+    /// Note that some packet arrived, for packets other than data
+    pub fn touch(&mut self) {
+        self.last_received = Instant::now();
+    }
+
+    /// Send queued messages, due resends and acknowledgements, or a keep-alive if
+    /// nothing was sent for a while
+    pub fn flush(&mut self, socket: &UdpSocket, config: &NetConfig) {
+        let now = Instant::now();
+        let mut messages: Vec<Message> = Vec::new();
+        let oldest = self.unacked.front().map_or(0, |pending| pending.sequence);
+        let in_window = |pending: &&mut PendingReliable| pending.sequence.wrapping_sub(oldest) < RELIABLE_WINDOW;
+        for pending in self.unacked.iter_mut().take_while(in_window) {
+            if pending.sent_at.is_none_or(|sent| now.duration_since(sent) >= config.resend) {
+                pending.sent_at = Some(now);
+                messages.push(Message { channel: Channel::Reliable, sequence: Some(pending.sequence), data: pending.data.clone() });
+            }
+        }
+        messages.append(&mut self.unreliable);
+        self.acks.sort_unstable();
+        self.acks.dedup();
+
+        let keep_alive = now.duration_since(self.last_sent) >= config.keep_alive;
+        if messages.is_empty() && self.acks.is_empty() && !keep_alive {
+            return;
+        }
+
+        // Pack messages in order into as few packets as fit
+        let mut messages = messages.into_iter().peekable();
+        loop {
+            let acks: Vec<u32> = self.acks.drain(..self.acks.len().min(MAX_ACKS_PER_PACKET)).collect();
+            let mut size = HEADER_SIZE + 2 + acks.len() * 4;
+            let mut batch = Vec::new();
+            while let Some(message) = messages.next_if(|m| size + MESSAGE_HEADER_SIZE + m.data.len() <= MAX_PACKET_SIZE) {
+                size += MESSAGE_HEADER_SIZE + message.data.len();
+                batch.push(message);
+            }
+            let packet = Packet::Data { session: self.session, acks, messages: batch };
+            if let Err(e) = socket.send_to(&packet.encode(config.protocol_id), self.addr) {
+                log::warn!("Failed to send to {}: {}", self.addr, e);
+            }
+            if messages.peek().is_none() && self.acks.is_empty() {
+                break;
+            }
+        }
+        self.last_sent = now;
+    }
+}
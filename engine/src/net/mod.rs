@@ -0,0 +1,219 @@
+//! Client/server networking over UDP.
+//! A `Server` accepts up to `max_clients` connections, each `Client` connects to one
+//! server with a small handshake. Messages go on a `Channel`: reliable ones are resent
+//! until acknowledged and delivered in order, unreliable ones are sent once and may be
+//! lost. Both ends are polled once per frame: `update` reads the socket and returns
+//! events, `flush` sends what was queued since (plus resends and keep-alives).
+//! The `replication` half sends snapshots of entities marked `Replicated` from the
+//! server's World to every client's World.
+
+use std::time::Duration;
+
+mod bytes;
+pub use bytes::{ByteReader, ByteWriter};
+
+mod connection;
+
+mod server;
+pub use server::{Server, ServerEvent};
+
+mod client;
+pub use client::{Client, ClientEvent, ClientState};
+
+mod replication;
+pub use replication::{Replicated, ReplicationClient, ReplicationRegistry, ReplicationServer};
+
+/// Largest datagram sent, small enough to avoid IP fragmentation on most links
+const MAX_PACKET_SIZE: usize = 1200;
+/// Packet header: protocol id, kind and session
+const HEADER_SIZE: usize = 4 + 1 + 8;
+/// Header of a message inside a data packet: channel, sequence number and length
+const MESSAGE_HEADER_SIZE: usize = 1 + 4 + 2;
+/// Largest message accepted by `send`, what fits alone in a data packet
+pub const MAX_MESSAGE_SIZE: usize = MAX_PACKET_SIZE - HEADER_SIZE - 2 - MESSAGE_HEADER_SIZE;
+
+/// Identifies a client on the server, assigned during the handshake
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientId(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// Resent until acknowledged, delivered exactly once and in order
+    Reliable,
+    /// Sent once, may be lost
+    Unreliable,
+    /// Unreliable, carries the snapshots of `ReplicationServer`
+    Replication,
+}
+
+impl Channel {
+    fn to_u8(self) -> u8 {
+        match self {
+            Channel::Reliable => 0,
+            Channel::Unreliable => 1,
+            Channel::Replication => 2,
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Channel::Reliable),
+            1 => Some(Channel::Unreliable),
+            2 => Some(Channel::Replication),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Nothing was received for `NetConfig::timeout`
+    TimedOut,
+    /// The server had no free slot
+    ServerFull,
+    /// The other end disconnected
+    Closed,
+}
+
+/// Settings shared by servers and clients, both ends need the same `protocol_id`
+#[derive(Clone, Debug)]
+pub struct NetConfig {
+    /// Packets with another id are ignored, change it when the game's messages change
+    pub protocol_id: u32,
+    pub max_clients: usize,
+    /// Connections are dropped when nothing was received for this long
+    pub timeout: Duration,
+    /// An empty packet is sent when nothing else was for this long
+    pub keep_alive: Duration,
+    /// Unacknowledged reliable messages are sent again after this long
+    pub resend: Duration,
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        NetConfig {
+            protocol_id: 0x454e_4731,
+            max_clients: 16,
+            timeout: Duration::from_secs(5),
+            keep_alive: Duration::from_millis(250),
+            resend: Duration::from_millis(100),
+        }
+    }
+}
+
+/// A message received on a channel
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Message {
+    pub channel: Channel,
+    /// Sequence number of a reliable message
+    pub sequence: Option<u32>,
+    pub data: Vec<u8>,
+}
+
+/// Everything sent over the socket, the session guards against stale packets from an
+/// earlier connection on the same address
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Packet {
+    ConnectRequest { session: u64 },
+    ConnectAccept { session: u64, client: ClientId },
+    ConnectDeny { session: u64 },
+    Disconnect { session: u64 },
+    /// Acknowledged reliable sequence numbers and messages, empty as a keep-alive
+    Data { session: u64, acks: Vec<u32>, messages: Vec<Message> },
+}
+
+impl Packet {
+    fn kind(&self) -> u8 {
+        match self {
+            Packet::ConnectRequest { .. } => 0,
+            Packet::ConnectAccept { .. } => 1,
+            Packet::ConnectDeny { .. } => 2,
+            Packet::Disconnect { .. } => 3,
+            Packet::Data { .. } => 4,
+        }
+    }
+
+    pub fn session(&self) -> u64 {
+        match *self {
+            Packet::ConnectRequest { session }
+            | Packet::ConnectAccept { session, .. }
+            | Packet::ConnectDeny { session }
+            | Packet::Disconnect { session }
+            | Packet::Data { session, .. } => session,
+        }
+    }
+
+    pub fn encode(&self, protocol_id: u32) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+        writer.u32(protocol_id);
+        writer.u8(self.kind());
+        writer.u64(self.session());
+        match self {
+            Packet::ConnectAccept { client, .. } => writer.u32(client.0),
+            Packet::Data { acks, messages, .. } => {
+                writer.u16(acks.len() as u16);
+                for &ack in acks {
+                    writer.u32(ack);
+                }
+                for message in messages {
+                    writer.u8(message.channel.to_u8());
+                    if let Some(sequence) = message.sequence {
+                        writer.u32(sequence);
+                    }
+                    writer.bytes(&message.data);
+                }
+            }
+            _ => {}
+        }
+        writer.into_bytes()
+    }
+
+    /// Read a packet, `None` if it is malformed or from another protocol
+    pub fn decode(bytes: &[u8], protocol_id: u32) -> Option<Self> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.u32()? != protocol_id {
+            return None;
+        }
+        let kind = reader.u8()?;
+        let session = reader.u64()?;
+        let packet = match kind {
+            0 => Packet::ConnectRequest { session },
+            1 => Packet::ConnectAccept { session, client: ClientId(reader.u32()?) },
+            2 => Packet::ConnectDeny { session },
+            3 => Packet::Disconnect { session },
+            4 => {
+                let ack_count = reader.u16()?;
+                let acks = (0..ack_count).map(|_| reader.u32()).collect::<Option<Vec<_>>>()?;
+                let mut messages = Vec::new();
+                while !reader.is_empty() {
+                    let channel = Channel::from_u8(reader.u8()?)?;
+                    let sequence = match channel {
+                        Channel::Reliable => Some(reader.u32()?),
+                        _ => None,
+                    };
+                    messages.push(Message { channel, sequence, data: reader.bytes()?.to_vec() });
+                }
+                Packet::Data { session, acks, messages }
+            }
+            _ => return None,
+        };
+        reader.is_empty().then_some(packet)
+    }
+}
+
+/// Read every datagram waiting on a non-blocking socket
+fn receive_all(socket: &std::net::UdpSocket, mut on_packet: impl FnMut(std::net::SocketAddr, &[u8])) {
+    let mut buffer = [0u8; MAX_PACKET_SIZE];
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((len, from)) => on_packet(from, &buffer[..len]),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+            // Windows reports an earlier send to a closed port on the next receive
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => continue,
+            Err(e) => {
+                log::warn!("Failed to receive packet: {}", e);
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use anyhow::Result;
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+
+use crate::ecs::{ECSComponent, EntityId, FieldValue, Reflect, Sprite, World};
+use crate::math::{Color, Transform};
+use crate::net::{ByteReader, ByteWriter, Channel, Server, MAX_MESSAGE_SIZE};
+
+/// Snapshot part header: tick, part index and part count
+const PART_HEADER_SIZE: usize = 4 + 2 + 2;
+
+/// Marks an entity whose replicated components are sent to clients
+#[derive(Clone, Debug, Default)]
+pub struct Replicated;
+
+impl ECSComponent for Replicated {
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn clone_box(&self) -> Box<dyn ECSComponent> { Box::new(self.clone()) }
+}
+
+fn write_field(writer: &mut ByteWriter, value: FieldValue) {
+    match value {
+        FieldValue::Float(v) => {
+            writer.u8(0);
+            writer.f32(v);
+        }
+        FieldValue::Vec2(v) => {
+            writer.u8(1);
+            writer.vec2(v);
+        }
+        FieldValue::Bool(v) => {
+            writer.u8(2);
+            writer.bool(v);
+        }
+        FieldValue::Color(c) => {
+            writer.u8(3);
+            for channel in c.to_array() {
+                writer.f32(channel);
+            }
+        }
+    }
+}
+
+fn read_field(reader: &mut ByteReader) -> Option<FieldValue> {
+    Some(match reader.u8()? {
+        0 => FieldValue::Float(reader.f32()?),
+        1 => FieldValue::Vec2(reader.vec2()?),
+        2 => FieldValue::Bool(reader.bool()?),
+        3 => FieldValue::Color(Color::linear_rgba(reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?)),
+        _ => return None,
+    })
+}
+
+fn encode_fields<T: ECSComponent + Reflect>(component: &dyn ECSComponent, writer: &mut ByteWriter) {
+    let fields = component.as_any().downcast_ref::<T>().map(Reflect::fields).unwrap_or_default();
+    writer.u8(fields.len() as u8);
+    for (_, value) in fields {
+        write_field(writer, value);
+    }
+}
+
+/// Read fields written by `encode_fields` onto the entity's component, adding it if missing
+fn apply_fields<T: ECSComponent + Reflect + Default>(world: &mut World, entity: EntityId, reader: &mut ByteReader) -> Option<()> {
+    let count = reader.u8()?;
+    let values = (0..count).map(|_| read_field(reader)).collect::<Option<Vec<_>>>()?;
+    if !world.has::<T>(entity) {
+        world.insert(entity, T::default());
+    }
+    let component = world.get_mut::<T>(entity)?;
+    // Fields are sent in order without their names
+    let names: Vec<&'static str> = component.fields().into_iter().map(|(name, _)| name).collect();
+    for (name, value) in names.into_iter().zip(values) {
+        component.set_field(name, value);
+    }
+    Some(())
+}
+
+fn remove_component<T: ECSComponent>(world: &mut World, entity: EntityId) {
+    world.remove::<T>(entity);
+}
+
+struct ReplicatedType {
+    type_id: TypeId,
+    name: &'static str,
+    encode: fn(&dyn ECSComponent, &mut ByteWriter),
+    apply: fn(&mut World, EntityId, &mut ByteReader) -> Option<()>,
+    remove: fn(&mut World, EntityId),
+}
+
+/// Component types sent in snapshots, through their `Reflect` fields
+///
+/// Server and clients must register the same types. A client adds a missing component
+/// with `Default` before writing the fields, so only reflected fields are replicated.
+pub struct ReplicationRegistry {
+    types: Vec<ReplicatedType>,
+}
+
+impl ReplicationRegistry {
+    pub fn new() -> Self {
+        ReplicationRegistry { types: Vec::new() }
+    }
+
+    /// Registry with the built-in components that make sense on every machine
+    pub fn with_engine_components() -> Self {
+        let mut registry = Self::new();
+        registry.register::<Transform>();
+        registry.register::<Sprite>();
+        registry
+    }
+
+    pub fn register<T: ECSComponent + Reflect + Default>(&mut self) {
+        if self.types.iter().any(|t| t.type_id == TypeId::of::<T>()) {
+            return;
+        }
+        self.types.push(ReplicatedType {
+            type_id: TypeId::of::<T>(),
+            name: T::TYPE_NAME,
+            encode: encode_fields::<T>,
+            apply: apply_fields::<T>,
+            remove: remove_component::<T>,
+        });
+    }
+
+    /// Entity id, name, parent and replicated components
+    fn encode_entity(&self, world: &World, id: EntityId, writer: &mut ByteWriter) {
+        writer.u32(id.0);
+        writer.str(world.name(id).unwrap_or_default());
+        // Only kept when the parent is replicated too
+        match world.parent(id).filter(|&parent| world.has::<Replicated>(parent)) {
+            Some(parent) => {
+                writer.bool(true);
+                writer.u32(parent.0);
+            }
+            None => writer.bool(false),
+        }
+        let components: Vec<_> = self.types.iter().filter_map(|t| Some((t, world.get_dyn(id, t.type_id)?))).collect();
+        writer.u8(components.len() as u8);
+        for (t, component) in components {
+            writer.str(t.name);
+            (t.encode)(component, writer);
+        }
+    }
+}
+
+impl Default for ReplicationRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sends snapshots of every `Replicated` entity, once per call to `send`
+///
+/// Each snapshot holds the full state and goes on the unreliable `Replication`
+/// channel, split into parts that fit a message. A lost part only drops that tick,
+/// the next snapshot replaces it.
+pub struct ReplicationServer {
+    registry: ReplicationRegistry,
+    tick: u32,
+}
+
+impl ReplicationServer {
+    pub fn new(registry: ReplicationRegistry) -> Self {
+        ReplicationServer { registry, tick: 0 }
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Encode the next snapshot into messages
+    pub fn snapshot(&mut self, world: &World) -> Vec<Vec<u8>> {
+        self.tick = self.tick.wrapping_add(1);
+        let mut entities: Vec<EntityId> = world.entity_ids().filter(|&id| world.has::<Replicated>(id)).collect();
+        entities.sort();
+
+        let mut bodies: Vec<ByteWriter> = Vec::new();
+        for id in entities {
+            let mut entity = ByteWriter::new();
+            self.registry.encode_entity(world, id, &mut entity);
+            let entity = entity.into_bytes();
+            if PART_HEADER_SIZE + entity.len() > MAX_MESSAGE_SIZE {
+                log::warn!("Entity {} is too large to replicate ({} bytes)", id.0, entity.len());
+                continue;
+            }
+            match bodies.last_mut() {
+                Some(body) if PART_HEADER_SIZE + body.len() + entity.len() <= MAX_MESSAGE_SIZE => body.raw(&entity),
+                _ => {
+                    let mut body = ByteWriter::new();
+                    body.raw(&entity);
+                    bodies.push(body);
+                }
+            }
+        }
+
+        // An empty World still sends one empty part so clients despawn everything
+        if bodies.is_empty() {
+            bodies.push(ByteWriter::new());
+        }
+        let count = bodies.len() as u16;
+        bodies
+            .into_iter()
+            .enumerate()
+            .map(|(index, body)| {
+                let mut part = ByteWriter::new();
+                part.u32(self.tick);
+                part.u16(index as u16);
+                part.u16(count);
+                part.raw(&body.into_bytes());
+                part.into_bytes()
+            })
+            .collect()
+    }
+
+    /// Queue the next snapshot for every client of `server`
+    pub fn send(&mut self, server: &mut Server, world: &World) -> Result<()> {
+        for part in self.snapshot(world) {
+            server.broadcast(Channel::Replication, &part)?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies snapshots from a `ReplicationServer` to a client's World
+///
+/// Replicated entities are spawned locally (marked `Replicated`) and despawned when
+/// they leave the snapshot. Snapshots older than the last one applied are ignored.
+pub struct ReplicationClient {
+    registry: ReplicationRegistry,
+    /// Tick of the last snapshot applied
+    applied: Option<u32>,
+    /// Tick and parts of the newest snapshot still being received
+    pending: Option<(u32, Vec<Option<Vec<u8>>>)>,
+    /// Server entity id to local entity
+    entities: HashMap<u32, EntityId>,
+}
+
+impl ReplicationClient {
+    pub fn new(registry: ReplicationRegistry) -> Self {
+        ReplicationClient { registry, applied: None, pending: None, entities: HashMap::new() }
+    }
+
+    /// Tick of the last snapshot applied
+    pub fn tick(&self) -> Option<u32> {
+        self.applied
+    }
+
+    /// Local entity replicating the server's `entity`
+    pub fn local_entity(&self, server_entity: EntityId) -> Option<EntityId> {
+        self.entities.get(&server_entity.0).copied()
+    }
+
+    fn is_newer(&self, tick: u32) -> bool {
+        self.applied.is_none_or(|applied| tick != applied && tick.wrapping_sub(applied) < u32::MAX / 2)
+    }
+
+    /// Handle a message received on the `Replication` channel
+    /// Returns true when it completed a snapshot and the World was updated.
+    pub fn receive(&mut self, world: &mut World, data: &[u8]) -> bool {
+        let mut reader = ByteReader::new(data);
+        let (Some(tick), Some(index), Some(count)) = (reader.u32(), reader.u16(), reader.u16()) else {
+            return false;
+        };
+        if index >= count || !self.is_newer(tick) {
+            return false;
+        }
+        let parts = match &mut self.pending {
+            Some((pending, parts)) if *pending == tick && parts.len() == count as usize => parts,
+            // Part of an older snapshot than the one being received
+            Some((pending, _)) if pending.wrapping_sub(tick) < u32::MAX / 2 => return false,
+            pending => &mut pending.insert((tick, vec![None; count as usize])).1,
+        };
+        parts[index as usize] = Some(reader.raw(reader.remaining()).unwrap_or_default().to_vec());
+        if parts.iter().any(Option::is_none) {
+            return false;
+        }
+
+        let Some((_, parts)) = self.pending.take() else {
+            return false;
+        };
+        let snapshot: Vec<u8> = parts.into_iter().flatten().flatten().collect();
+        if self.apply(world, &snapshot).is_none() {
+            log::warn!("Dropped malformed snapshot {}", tick);
+            return false;
+        }
+        self.applied = Some(tick);
+        true
+    }
+
+    fn apply(&mut self, world: &mut World, snapshot: &[u8]) -> Option<()> {
+        let mut reader = ByteReader::new(snapshot);
+        let mut seen = HashSet::new();
+        let mut parents = Vec::new();
+        while !reader.is_empty() {
+            let server_id = reader.u32()?;
+            let name = reader.str()?;
+            let parent = if reader.bool()? { Some(reader.u32()?) } else { None };
+
+            let local = match self.entities.get(&server_id) {
+                Some(&local) if world.contains(local) => {
+                    if world.name(local) != Some(name) {
+                        world.set_name(local, name);
+                    }
+                    local
+                }
+                _ => {
+                    let local = world.spawn(name);
+                    world.insert(local, Replicated);
+                    self.entities.insert(server_id, local);
+                    local
+                }
+            };
+            seen.insert(server_id);
+            parents.push((local, parent));
+
+            let component_count = reader.u8()?;
+            let mut present = HashSet::new();
+            for _ in 0..component_count {
+                let type_name = reader.str()?;
+                let Some(t) = self.registry.types.iter().find(|t| t.name == type_name) else {
+                    log::warn!("Snapshot has unregistered component '{}'", type_name);
+                    return None;
+                };
+                (t.apply)(world, local, &mut reader)?;
+                present.insert(t.type_id);
+            }
+            for t in self.registry.types.iter().filter(|t| !present.contains(&t.type_id)) {
+                (t.remove)(world, local);
+            }
+        }
+
+        // Parents may come after their children in the snapshot
+        for (local, parent) in parents {
+            let parent = parent.and_then(|server_id| self.entities.get(&server_id).copied());
+            if world.parent(local) != parent {
+                world.set_parent(local, parent);
+            }
+        }
+
+        let gone: Vec<u32> = self.entities.keys().copied().filter(|id| !seen.contains(id)).collect();
+        for server_id in gone {
+            if let Some(local) = self.entities.remove(&server_id) {
+                world.despawn(local);
+            }
+        }
+        Some(())
+    }
+
+    /// Despawn every replicated entity, e.g. after disconnecting
+    pub fn clear(&mut self, world: &mut World) {
+        for (_, local) in self.entities.drain() {
+            world.despawn(local);
+        }
+        self.applied = None;
+        self.pending = None;
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+use crate::net::connection::Connection;
+use crate::net::{receive_all, Channel, ClientId, DisconnectReason, NetConfig, Packet};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerEvent {
+    Connected(ClientId),
+    Disconnected(ClientId, DisconnectReason),
+    Message { client: ClientId, channel: Channel, data: Vec<u8> },
+}
+
+/// Accepts client connections on a UDP port, see the module docs
+pub struct Server {
+    socket: UdpSocket,
+    config: NetConfig,
+    clients: HashMap<ClientId, Connection>,
+    next_client: u32,
+}
+
+impl Server {
+    /// Listen on `addr`, e.g. "0.0.0.0:7777"
+    pub fn bind(addr: impl ToSocketAddrs, config: NetConfig) -> Result<Self> {
+        let socket = UdpSocket::bind(addr).map_err(|e| anyhow!("Failed to bind server socket: {}", e))?;
+        socket.set_nonblocking(true)?;
+        log::info!("Server listening on {}", socket.local_addr()?);
+        Ok(Server { socket, config, clients: HashMap::new(), next_client: 1 })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    pub fn config(&self) -> &NetConfig {
+        &self.config
+    }
+
+    /// Connected clients in no particular order
+    pub fn clients(&self) -> impl Iterator<Item = ClientId> + '_ {
+        self.clients.keys().copied()
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn client_addr(&self, client: ClientId) -> Option<SocketAddr> {
+        self.clients.get(&client).map(|connection| connection.addr)
+    }
+
+    fn client_at(&self, addr: SocketAddr) -> Option<ClientId> {
+        self.clients.iter().find(|(_, connection)| connection.addr == addr).map(|(&id, _)| id)
+    }
+
+    fn send_packet(&self, packet: &Packet, addr: SocketAddr) {
+        if let Err(e) = self.socket.send_to(&packet.encode(self.config.protocol_id), addr) {
+            log::warn!("Failed to send to {}: {}", addr, e);
+        }
+    }
+
+    /// Read incoming packets and drop timed out clients
+    pub fn update(&mut self) -> Vec<ServerEvent> {
+        let mut events = Vec::new();
+        let mut received = Vec::new();
+        receive_all(&self.socket, |from, bytes| {
+            if let Some(packet) = Packet::decode(bytes, self.config.protocol_id) {
+                received.push((from, packet));
+            }
+        });
+        for (from, packet) in received {
+            self.handle_packet(from, packet, &mut events);
+        }
+
+        let now = Instant::now();
+        let timed_out: Vec<ClientId> =
+            self.clients.iter().filter(|(_, connection)| connection.timed_out(now, &self.config)).map(|(&id, _)| id).collect();
+        for client in timed_out {
+            self.clients.remove(&client);
+            log::info!("Client {} timed out", client.0);
+            events.push(ServerEvent::Disconnected(client, DisconnectReason::TimedOut));
+        }
+        events
+    }
+
+    fn handle_packet(&mut self, from: SocketAddr, packet: Packet, events: &mut Vec<ServerEvent>) {
+        let session = packet.session();
+        let existing = self.client_at(from);
+        // The client at this address in the session the packet belongs to
+        let current = existing.filter(|client| self.clients[client].session == session);
+
+        match packet {
+            Packet::ConnectRequest { .. } => {
+                if let Some(client) = current {
+                    // Our accept was lost, the client is still asking
+                    self.send_packet(&Packet::ConnectAccept { session, client }, from);
+                    return;
+                }
+                if let Some(client) = existing {
+                    // The client restarted on the same address
+                    self.clients.remove(&client);
+                    events.push(ServerEvent::Disconnected(client, DisconnectReason::Closed));
+                }
+                if self.clients.len() >= self.config.max_clients {
+                    self.send_packet(&Packet::ConnectDeny { session }, from);
+                    return;
+                }
+                let client = ClientId(self.next_client);
+                self.next_client += 1;
+                self.clients.insert(client, Connection::new(from, session));
+                self.send_packet(&Packet::ConnectAccept { session, client }, from);
+                log::info!("Client {} connected from {}", client.0, from);
+                events.push(ServerEvent::Connected(client));
+            }
+            Packet::Disconnect { .. } => {
+                if let Some(client) = current {
+                    self.clients.remove(&client);
+                    log::info!("Client {} disconnected", client.0);
+                    events.push(ServerEvent::Disconnected(client, DisconnectReason::Closed));
+                }
+            }
+            Packet::Data { acks, messages, .. } => {
+                let Some((client, connection)) = current.and_then(|client| Some((client, self.clients.get_mut(&client)?))) else {
+                    return;
+                };
+                for message in connection.receive(&acks, messages) {
+                    events.push(ServerEvent::Message { client, channel: message.channel, data: message.data });
+                }
+            }
+            // Only a server sends these
+            Packet::ConnectAccept { .. } | Packet::ConnectDeny { .. } => {}
+        }
+    }
+
+    /// Queue a message for a client, sent on the next `flush`
+    pub fn send(&mut self, client: ClientId, channel: Channel, data: &[u8]) -> Result<()> {
+        let connection = self.clients.get_mut(&client).ok_or_else(|| anyhow!("No client {}", client.0))?;
+        connection.queue(channel, data)
+    }
+
+    /// Queue a message for every connected client
+    pub fn broadcast(&mut self, channel: Channel, data: &[u8]) -> Result<()> {
+        for connection in self.clients.values_mut() {
+            connection.queue(channel, data)?;
+        }
+        Ok(())
+    }
+
+    /// Send everything queued since the last flush
+    pub fn flush(&mut self) {
+        for connection in self.clients.values_mut() {
+            connection.flush(&self.socket, &self.config);
+        }
+    }
+
+    /// Drop a client, telling it so. No `Disconnected` event is reported for it.
+    pub fn disconnect(&mut self, client: ClientId) {
+        if let Some(mut connection) = self.clients.remove(&client) {
+            connection.flush(&self.socket, &self.config);
+            // Never acknowledged, so send it a few times in case one is lost
+            for _ in 0..3 {
+                self.send_packet(&Packet::Disconnect { session: connection.session }, connection.addr);
+            }
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let clients: Vec<ClientId> = self.clients().collect();
+        for client in clients {
+            self.disconnect(client);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+//! Types most games use, `use engine::prelude::*;` instead of importing them one by one.
+
+pub use glam::{Mat4, Vec2, Vec3};
+
+pub use crate::assets::Handle;
+pub use crate::config::EngineConfig;
+pub use crate::cvars::{CVarValue, CVars};
+pub use crate::localization::Localization;
+pub use crate::math::{Color, Rect, Transform};
+pub use crate::renderer::{GraphicsBackend, RenderContext, Renderer, Texture, VulkanContext};
+pub use crate::window::{EventLoop, Lifecycle, ResizeTracker};
+
+#[cfg(feature = "gui")]
+pub use crate::gui::{
+    ButtonComponent, ComponentRef, ContainerPanel, Gesture, GUIComponent, HAlign, InputState, LayoutSpec, PanelComponent, SizeSpec,
+    TextComponent, UISystem, VAlign,
+};
+#[cfg(feature = "gui")]
+pub use crate::host::Engine;
+#[cfg(feature = "gui")]
+pub use crate::replay::Replay;
+
+#[cfg(feature = "ecs")]
+pub use crate::ecs::{Camera, EntityId, Schedule, Sprite, World};
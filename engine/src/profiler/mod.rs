@@ -0,0 +1,222 @@
+//! Lightweight CPU profiler.
+//! Code is instrumented with `profile_scope!("name")`, zones are aggregated per
+//! frame between `begin_frame`/`end_frame`, and raw zones can optionally be
+//! exported in the chrome://tracing JSON format.
+
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Maximum number of raw zones kept for a chrome trace export
+const MAX_TRACE_EVENTS: usize = 100_000;
+
+/// Total time spent in one named zone during a frame
+#[derive(Clone, Debug)]
+pub struct ZoneStats {
+    pub name: &'static str,
+    pub total: Duration,
+    pub calls: u32,
+}
+
+/// Aggregated results for a single frame
+#[derive(Clone, Debug, Default)]
+pub struct FrameProfile {
+    pub frame_time: Duration,
+    /// Zones in order of first appearance during the frame
+    pub zones: Vec<ZoneStats>,
+}
+
+impl FrameProfile {
+    pub fn zone(&self, name: &str) -> Option<&ZoneStats> {
+        self.zones.iter().find(|zone| zone.name == name)
+    }
+}
+
+/// A raw zone recorded for tracing export
+#[derive(Clone, Debug)]
+struct TraceEvent {
+    name: &'static str,
+    start: Duration,
+    duration: Duration,
+    thread: String,
+}
+
+struct ProfilerState {
+    enabled: bool,
+    epoch: Instant,
+    frame_start: Option<Instant>,
+    current: Vec<ZoneStats>,
+    last_frame: FrameProfile,
+    capture_trace: bool,
+    trace: Vec<TraceEvent>,
+}
+
+fn state() -> &'static Mutex<ProfilerState> {
+    static STATE: OnceLock<Mutex<ProfilerState>> = OnceLock::new();
+    STATE.get_or_init(|| {
+        Mutex::new(ProfilerState {
+            enabled: true,
+            epoch: Instant::now(),
+            frame_start: None,
+            current: Vec::new(),
+            last_frame: FrameProfile::default(),
+            capture_trace: false,
+            trace: Vec::new(),
+        })
+    })
+}
+
+/// Enable or disable zone recording globally
+pub fn set_enabled(enabled: bool) {
+    if let Ok(mut state) = state().lock() {
+        state.enabled = enabled;
+    }
+}
+
+pub fn is_enabled() -> bool {
+    state().lock().map(|state| state.enabled).unwrap_or(false)
+}
+
+/// Mark the start of a frame
+/// Zones recorded since the previous `end_frame` (e.g. input handling) count towards this frame
+pub fn begin_frame() {
+    if let Ok(mut state) = state().lock() {
+        state.frame_start = Some(Instant::now());
+    }
+}
+
+/// Close the current frame and publish its aggregated zones
+pub fn end_frame() {
+    if let Ok(mut state) = state().lock() {
+        let frame_time = state.frame_start.take().map(|start| start.elapsed()).unwrap_or_default();
+        let zones = std::mem::take(&mut state.current);
+        state.last_frame = FrameProfile { frame_time, zones };
+    }
+}
+
+/// Get the aggregated zones of the last completed frame
+pub fn last_frame() -> FrameProfile {
+    state().lock().map(|state| state.last_frame.clone()).unwrap_or_default()
+}
+
+/// Start or stop collecting raw zones for `write_chrome_trace`
+pub fn set_trace_capture(capture: bool) {
+    if let Ok(mut state) = state().lock() {
+        state.capture_trace = capture;
+        if capture {
+            state.trace.clear();
+        }
+    }
+}
+
+/// Write the captured zones as a chrome://tracing (or Perfetto) compatible JSON file
+pub fn write_chrome_trace(path: &str) -> anyhow::Result<()> {
+    let json = {
+        let state = state().lock().map_err(|_| anyhow::anyhow!("Profiler state poisoned"))?;
+        let mut json = String::from("{\"traceEvents\":[");
+        for (i, event) in state.trace.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "{{\"name\":\"{}\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":0,\"tid\":\"{}\"}}",
+                json_escape(event.name),
+                event.start.as_micros(),
+                event.duration.as_micros(),
+                json_escape(&event.thread),
+            );
+        }
+        json.push_str("]}");
+        json
+    };
+
+    std::fs::write(path, json)
+        .map_err(|e| anyhow::anyhow!("Failed to write trace '{}': {}", path, e))
+}
+
+/// Escape a string for use inside a JSON string literal
+fn json_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn record(name: &'static str, start: Instant, duration: Duration) {
+    let Ok(mut state) = state().lock() else {
+        return;
+    };
+
+    match state.current.iter_mut().find(|zone| zone.name == name) {
+        Some(zone) => {
+            zone.total += duration;
+            zone.calls += 1;
+        }
+        None => state.current.push(ZoneStats { name, total: duration, calls: 1 }),
+    }
+
+    if state.capture_trace && state.trace.len() < MAX_TRACE_EVENTS {
+        let thread = std::thread::current();
+        let thread = thread.name().map(str::to_string).unwrap_or_else(|| format!("{:?}", thread.id()));
+        let start = start.saturating_duration_since(state.epoch);
+        state.trace.push(TraceEvent { name, start, duration, thread });
+    }
+}
+
+/// Guard that records the time between its creation and drop as a named zone
+/// Usually created through the `profile_scope!` macro
+pub struct ProfileScope {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl ProfileScope {
+    pub fn new(name: &'static str) -> Self {
+        let start = if is_enabled() { Some(Instant::now()) } else { None };
+        ProfileScope { name, start }
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            record(self.name, start, start.elapsed());
+        }
+    }
+}
+
+/// Profile the rest of the enclosing scope under the given name
+///
+/// ```ignore
+/// profile_scope!("ui_layout");
+/// ```
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::profiler::ProfileScope::new($name);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_names_for_json() {
+        assert_eq!(json_escape("plain"), "plain");
+        assert_eq!(json_escape("a \"b\" c:\\d"), "a \\\"b\\\" c:\\\\d");
+        assert_eq!(json_escape("line\nnext\ttab\u{1}"), "line\\nnext\\ttab\\u0001");
+    }
+}
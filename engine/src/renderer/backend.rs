@@ -0,0 +1,316 @@
+//! Graphics API neutral interface to the renderer.
+//! `GraphicsBackend` creates buffers and textures, looks up the built-in pipelines and
+//! records draws using plain handles instead of Vulkan objects, so code written against it
+//! doesn't depend on ash and another backend (wgpu, Metal, GL) can implement it. `Renderer`
+//! implements it for Vulkan, its resources live in a `BackendResources` table.
+
+use anyhow::{anyhow, bail, Result};
+use ash::vk;
+use std::sync::Arc;
+
+use super::buffer_utils::{create_buffer_with_data, track_free, MemoryCategory};
+use super::{PipelineId, PipelinePush, SampledTexture, SamplerConfig, Texture, VulkanContext};
+
+/// Frames a destroyed resource is kept for, longer than any frame stays in flight
+const RETIRE_FRAMES: u64 = 3;
+
+/// A buffer of a `GraphicsBackend`, invalid once destroyed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BufferHandle(Slot);
+
+/// A sampled texture of a `GraphicsBackend`, invalid once destroyed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(Slot);
+
+/// A built-in pipeline, ready to draw with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineHandle {
+    pub id: PipelineId,
+}
+
+/// Index into a resource table and the generation of the resource there, so handles of
+/// destroyed resources don't reach their slot's next occupant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Slot {
+    index: u32,
+    generation: u32,
+}
+
+/// What a buffer is bound as
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferUsage {
+    /// Vertices in the pipeline's vertex format
+    Vertex,
+    /// u32 indices
+    Index,
+}
+
+/// Texel format of a texture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// 8-bit RGBA, sRGB encoded color
+    Rgba8Srgb,
+    /// 8-bit RGBA, linear data
+    Rgba8Unorm,
+    /// One 8-bit channel, e.g. glyph coverage
+    R8Unorm,
+}
+
+impl TextureFormat {
+    pub fn bytes_per_texel(self) -> usize {
+        match self {
+            TextureFormat::Rgba8Srgb | TextureFormat::Rgba8Unorm => 4,
+            TextureFormat::R8Unorm => 1,
+        }
+    }
+
+    pub(crate) fn to_vk(self) -> vk::Format {
+        match self {
+            TextureFormat::Rgba8Srgb => vk::Format::R8G8B8A8_SRGB,
+            TextureFormat::Rgba8Unorm => vk::Format::R8G8B8A8_UNORM,
+            TextureFormat::R8Unorm => vk::Format::R8_UNORM,
+        }
+    }
+}
+
+/// Area of a texture in texels, row 0 at the top
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One draw of `count` vertices (or indices, with an index buffer)
+#[derive(Clone, Copy, Debug)]
+pub struct DrawCall<'a> {
+    pub pipeline: PipelineHandle,
+    pub vertices: BufferHandle,
+    pub indices: Option<BufferHandle>,
+    pub count: u32,
+    /// Texture of pipelines that sample one (Image, Text, Backdrop)
+    pub texture: Option<TextureHandle>,
+    /// The pipeline's push constant block, see `push_bytes`
+    pub push: &'a [u8],
+}
+
+/// Bytes of a push constant block for `DrawCall::push`
+pub fn push_bytes<T: PipelinePush>(data: &T) -> &[u8] {
+    // PipelinePush blocks are plain `#[repr(C)]` data without padding
+    unsafe { std::slice::from_raw_parts(data as *const T as *const u8, std::mem::size_of::<T>()) }
+}
+
+/// What the engine needs from a graphics API
+///
+/// Resources are referred to by handles and destroyed explicitly; backends keep them alive
+/// until frames using them are done. Draws are recorded into the backend's `Commands`, which
+/// it hands out while a frame is being drawn (`RenderContext` for Vulkan).
+pub trait GraphicsBackend {
+    type Commands;
+
+    /// Name of the graphics API, for logs and diagnostics
+    fn name(&self) -> &'static str;
+    /// Size of the surface drawn to, in pixels
+    fn surface_size(&self) -> (u32, u32);
+
+    fn create_buffer(&mut self, usage: BufferUsage, data: &[u8]) -> Result<BufferHandle>;
+    fn destroy_buffer(&mut self, buffer: BufferHandle);
+
+    /// A texture filled with `data`, tightly packed rows from the top
+    fn create_texture(&mut self, width: u32, height: u32, format: TextureFormat, data: &[u8]) -> Result<TextureHandle>;
+    /// Replace the texels of `region` with `data`
+    fn update_texture(&mut self, texture: TextureHandle, region: TextureRegion, data: &[u8]) -> Result<()>;
+    /// Width and height, `None` for destroyed textures
+    fn texture_size(&self, texture: TextureHandle) -> Option<(u32, u32)>;
+    fn destroy_texture(&mut self, texture: TextureHandle);
+
+    /// A built-in pipeline, created on first use
+    fn pipeline(&mut self, id: PipelineId) -> Result<PipelineHandle>;
+    fn draw(&mut self, commands: &Self::Commands, call: &DrawCall) -> Result<()>;
+}
+
+/// Resources stored by slot, with generations to catch stale handles
+struct Slots<T> {
+    entries: Vec<(u32, Option<T>)>,
+    free: Vec<u32>,
+}
+
+impl<T> Slots<T> {
+    fn new() -> Self {
+        Slots { entries: Vec::new(), free: Vec::new() }
+    }
+
+    fn insert(&mut self, value: T) -> Slot {
+        match self.free.pop() {
+            Some(index) => {
+                let entry = &mut self.entries[index as usize];
+                entry.1 = Some(value);
+                Slot { index, generation: entry.0 }
+            }
+            None => {
+                self.entries.push((0, Some(value)));
+                Slot { index: self.entries.len() as u32 - 1, generation: 0 }
+            }
+        }
+    }
+
+    fn get(&self, slot: Slot) -> Option<&T> {
+        self.entries.get(slot.index as usize).filter(|(generation, _)| *generation == slot.generation)?.1.as_ref()
+    }
+
+    fn remove(&mut self, slot: Slot) -> Option<T> {
+        let entry = self.entries.get_mut(slot.index as usize).filter(|(generation, _)| *generation == slot.generation)?;
+        let value = entry.1.take()?;
+        entry.0 = entry.0.wrapping_add(1);
+        self.free.push(slot.index);
+        Some(value)
+    }
+
+    fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.free.clear();
+        self.entries.drain(..).filter_map(|(_, value)| value)
+    }
+}
+
+/// A Vulkan buffer created through `GraphicsBackend`
+pub(crate) struct BackendBuffer {
+    pub(crate) buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    allocation_size: vk::DeviceSize,
+    pub(crate) usage: BufferUsage,
+}
+
+impl BackendBuffer {
+    fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_buffer(self.buffer, None);
+            device.free_memory(self.memory, None);
+        }
+        track_free(MemoryCategory::Buffers, self.allocation_size);
+    }
+}
+
+/// Destroyed resources waiting for the frames using them
+enum Retired {
+    Buffer(BackendBuffer),
+    Texture(Texture, SampledTexture),
+}
+
+/// Buffers and textures the `Renderer` created as a `GraphicsBackend`
+pub(crate) struct BackendResources {
+    buffers: Slots<BackendBuffer>,
+    textures: Slots<(Texture, SampledTexture)>,
+    retired: Vec<(u64, Retired)>,
+    frame: u64,
+}
+
+impl BackendResources {
+    pub(crate) fn new() -> Self {
+        BackendResources { buffers: Slots::new(), textures: Slots::new(), retired: Vec::new(), frame: 0 }
+    }
+
+    /// Start a new frame, destroying retired resources the GPU is done with
+    pub(crate) fn next_frame(&mut self, device: &ash::Device) {
+        self.frame += 1;
+        let frame = self.frame;
+        self.retired.retain(|(destroyed, resource)| {
+            let done = frame - destroyed > RETIRE_FRAMES;
+            if done {
+                Self::destroy_retired(resource, device);
+            }
+            !done
+        });
+    }
+
+    pub(crate) fn create_buffer(&mut self, context: &Arc<VulkanContext>, usage: BufferUsage, data: &[u8]) -> Result<BufferHandle> {
+        if data.is_empty() {
+            bail!("Buffers need at least one byte");
+        }
+        let flags = match usage {
+            BufferUsage::Vertex => vk::BufferUsageFlags::VERTEX_BUFFER,
+            BufferUsage::Index => vk::BufferUsageFlags::INDEX_BUFFER,
+        };
+        let (buffer, memory, allocation_size) =
+            create_buffer_with_data(&context.device, context.physical_device, &context.instance, data, flags)?;
+        Ok(BufferHandle(self.buffers.insert(BackendBuffer { buffer, memory, allocation_size, usage })))
+    }
+
+    pub(crate) fn buffer(&self, buffer: BufferHandle) -> Result<&BackendBuffer> {
+        self.buffers.get(buffer.0).ok_or_else(|| anyhow!("Buffer {:?} was destroyed", buffer))
+    }
+
+    pub(crate) fn destroy_buffer(&mut self, buffer: BufferHandle) {
+        if let Some(buffer) = self.buffers.remove(buffer.0) {
+            self.retired.push((self.frame, Retired::Buffer(buffer)));
+        }
+    }
+
+    /// A texture sampled through `descriptor_set_layout`, the layout of pipelines with one image
+    pub(crate) fn create_texture(
+        &mut self,
+        context: &Arc<VulkanContext>,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[u8],
+    ) -> Result<TextureHandle> {
+        let expected = width as usize * height as usize * format.bytes_per_texel();
+        if data.len() != expected || expected == 0 {
+            bail!("{}x{} {:?} texture needs {} bytes, got {}", width, height, format, expected, data.len());
+        }
+        let texture = Texture::from_bytes(
+            data,
+            width,
+            height,
+            format.to_vk(),
+            &context.device,
+            &context.instance,
+            context.physical_device,
+            context.queue_family_indices[0],
+        )?;
+        let sampled = match SampledTexture::new(&texture, SamplerConfig::linear(), descriptor_set_layout, context) {
+            Ok(sampled) => sampled,
+            Err(e) => {
+                texture.destroy(&context.device);
+                return Err(e);
+            }
+        };
+        Ok(TextureHandle(self.textures.insert((texture, sampled))))
+    }
+
+    pub(crate) fn texture(&self, texture: TextureHandle) -> Result<&(Texture, SampledTexture)> {
+        self.textures.get(texture.0).ok_or_else(|| anyhow!("Texture {:?} was destroyed", texture))
+    }
+
+    pub(crate) fn destroy_texture(&mut self, texture: TextureHandle) {
+        if let Some((texture, sampled)) = self.textures.remove(texture.0) {
+            self.retired.push((self.frame, Retired::Texture(texture, sampled)));
+        }
+    }
+
+    fn destroy_retired(resource: &Retired, device: &ash::Device) {
+        match resource {
+            Retired::Buffer(buffer) => buffer.destroy(device),
+            Retired::Texture(texture, sampled) => {
+                sampled.destroy(device);
+                texture.destroy(device);
+            }
+        }
+    }
+
+    /// Destroy everything, the device must be idle
+    pub(crate) fn destroy(&mut self, device: &ash::Device) {
+        for buffer in self.buffers.drain() {
+            buffer.destroy(device);
+        }
+        for (texture, sampled) in self.textures.drain() {
+            sampled.destroy(device);
+            texture.destroy(device);
+        }
+        for (_, resource) in self.retired.drain(..) {
+            Self::destroy_retired(&resource, device);
+        }
+    }
+}
@@ -1,7 +1,56 @@
 use anyhow::Result;
 use ash::vk;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+/// What a device memory allocation is used for, reported separately by `Renderer::memory_stats`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MemoryCategory {
+    /// Vertex, index and uniform buffers
+    Buffers,
+    /// Sampled textures uploaded from the CPU
+    Textures,
+    /// Textures rendered into (viewports, offscreen passes)
+    RenderTargets,
+}
+
+/// Bytes of device memory currently allocated through the engine, indexed by `MemoryCategory`
+static ALLOCATED_BYTES: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Record a device memory allocation
+pub fn track_allocation(category: MemoryCategory, size: vk::DeviceSize) {
+    ALLOCATED_BYTES[category as usize].fetch_add(size, Ordering::Relaxed);
+}
+
+/// Record that a device memory allocation was freed
+pub fn track_free(category: MemoryCategory, size: vk::DeviceSize) {
+    ALLOCATED_BYTES[category as usize].fetch_sub(size, Ordering::Relaxed);
+}
+
+/// Device memory allocated through the engine, per category
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub buffers: u64,
+    pub textures: u64,
+    pub render_targets: u64,
+}
+
+impl MemoryStats {
+    pub fn total(&self) -> u64 {
+        self.buffers + self.textures + self.render_targets
+    }
+}
+
+/// Snapshot of the allocation counters
+pub fn memory_stats() -> MemoryStats {
+    let load = |category: MemoryCategory| ALLOCATED_BYTES[category as usize].load(Ordering::Relaxed);
+    MemoryStats {
+        buffers: load(MemoryCategory::Buffers),
+        textures: load(MemoryCategory::Textures),
+        render_targets: load(MemoryCategory::RenderTargets),
+    }
+}
+
 /// Find suitable memory type for allocation
 /// Used by buffers, images, and any Vulkan memory allocation
 pub fn find_memory_type(
@@ -33,13 +82,15 @@ pub fn find_memory_type(
 /// # Arguments
 /// * `data` - The data to copy into the buffer
 /// * `usage` - Buffer usage flags (e.g., VERTEX_BUFFER, INDEX_BUFFER)
+///
+/// Returns the buffer, its memory and the allocation size (needed for `track_free`)
 pub fn create_buffer_with_data<T>(
     device: &Arc<ash::Device>,
     physical_device: vk::PhysicalDevice,
     instance: &ash::Instance,
     data: &[T],
     usage: vk::BufferUsageFlags,
-) -> Result<(vk::Buffer, vk::DeviceMemory)> {
+) -> Result<(vk::Buffer, vk::DeviceMemory, vk::DeviceSize)> {
     let buffer_size = std::mem::size_of_val(data) as vk::DeviceSize;
     
     let buffer_info = vk::BufferCreateInfo::default()
@@ -62,6 +113,7 @@ pub fn create_buffer_with_data<T>(
         .memory_type_index(mem_type_index);
 
     let memory = unsafe { device.allocate_memory(&alloc_info, None)? };
+    track_allocation(MemoryCategory::Buffers, mem_requirements.size);
 
     unsafe {
         device.bind_buffer_memory(buffer, memory, 0)?;
@@ -81,5 +133,5 @@ pub fn create_buffer_with_data<T>(
         device.unmap_memory(memory);
     }
 
-    Ok((buffer, memory))
+    Ok((buffer, memory, mem_requirements.size))
 }
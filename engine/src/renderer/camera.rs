@@ -0,0 +1,64 @@
+use glam::{Mat4, Vec2, Vec3};
+
+const MIN_ZOOM: f32 = 0.01;
+const MAX_ZOOM: f32 = 1000.0;
+
+/// Orthographic camera over the 2D game world
+///
+/// `zoom` is in pixels per world unit, so the visible area grows with the window
+/// instead of stretching. World y points up on screen.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera2D {
+    /// World position at the center of the screen
+    pub center: Vec2,
+    pub zoom: f32,
+    /// Rotation around Z in radians, the world turns the opposite way on screen
+    pub rotation: f32,
+}
+
+impl Camera2D {
+    pub fn new() -> Self {
+        Camera2D {
+            center: Vec2::ZERO,
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+
+    /// Move by a drag of `delta` pixels, so the world follows the mouse
+    pub fn pan(&mut self, delta: Vec2) {
+        self.center -= Vec2::from_angle(self.rotation).rotate(delta) / self.zoom;
+    }
+
+    /// Zoom by `steps` (mouse wheel notches), positive zooms in
+    pub fn zoom_by(&mut self, steps: f32) {
+        self.zoom = (self.zoom * 1.1f32.powf(steps)).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// Projection * view for a viewport of `size` pixels
+    pub fn view_projection(&self, size: Vec2) -> Mat4 {
+        let half = size.max(Vec2::ONE) / (2.0 * self.zoom);
+        let projection = Mat4::orthographic_rh(-half.x, half.x, -half.y, half.y, -1.0, 1.0);
+        let view = Mat4::from_rotation_z(-self.rotation)
+            * Mat4::from_translation(Vec3::new(-self.center.x, -self.center.y, 0.0));
+        projection * view
+    }
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Coordinate space a draw is positioned in, resolved with `Renderer::projection_for`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ProjectionSpace {
+    /// Window pixels, fixed on screen (HUD, editor panels)
+    #[default]
+    Ui,
+    /// World units seen through `Renderer::camera`
+    World,
+    /// Any other camera, e.g. an offscreen viewport's
+    Custom(Mat4),
+}
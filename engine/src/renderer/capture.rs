@@ -0,0 +1,76 @@
+#[cfg(feature = "renderdoc")]
+use renderdoc::{RenderDoc, V141};
+
+/// Programmatic frame captures through the RenderDoc in-application API
+///
+/// Only available with the `renderdoc` feature, and only does anything when the
+/// application was launched from RenderDoc (or had it injected), since the API is
+/// served by the library RenderDoc loads into the process.
+pub(crate) struct FrameCapture {
+    #[cfg(feature = "renderdoc")]
+    api: Option<RenderDoc<V141>>,
+    /// Captures already reported, new ones are logged by `poll`
+    #[cfg(feature = "renderdoc")]
+    captures: u32,
+}
+
+impl FrameCapture {
+    #[cfg(feature = "renderdoc")]
+    pub fn new() -> Self {
+        let api = match RenderDoc::<V141>::new() {
+            Ok(api) => {
+                let (major, minor, patch) = api.get_api_version();
+                log::info!("RenderDoc {}.{}.{} attached, frame captures available", major, minor, patch);
+                Some(api)
+            }
+            Err(e) => {
+                log::debug!("RenderDoc is not attached: {}", e);
+                None
+            }
+        };
+        let captures = api.as_ref().map_or(0, |api| api.get_num_captures());
+        FrameCapture { api, captures }
+    }
+
+    #[cfg(not(feature = "renderdoc"))]
+    pub fn new() -> Self {
+        FrameCapture {}
+    }
+
+    pub fn is_available(&self) -> bool {
+        #[cfg(feature = "renderdoc")]
+        return self.api.is_some();
+        #[cfg(not(feature = "renderdoc"))]
+        return false;
+    }
+
+    /// Capture the next presented frame, false if RenderDoc isn't available
+    pub fn trigger(&mut self) -> bool {
+        #[cfg(feature = "renderdoc")]
+        if let Some(api) = self.api.as_mut() {
+            api.trigger_capture();
+            log::info!("Capturing the next frame with RenderDoc");
+            return true;
+        }
+        if cfg!(feature = "renderdoc") {
+            log::warn!("Frame capture requested, but the application wasn't started from RenderDoc");
+        } else {
+            log::warn!("Frame capture requested, but the engine was built without the 'renderdoc' feature");
+        }
+        false
+    }
+
+    /// Log captures written since the last call
+    pub fn poll(&mut self) {
+        #[cfg(feature = "renderdoc")]
+        if let Some(api) = &self.api {
+            let count = api.get_num_captures();
+            for index in self.captures..count {
+                if let Some((path, _)) = api.get_capture(index) {
+                    log::info!("RenderDoc capture written to '{}'", path.display());
+                }
+            }
+            self.captures = count;
+        }
+    }
+}
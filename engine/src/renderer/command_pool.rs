@@ -1,81 +1,68 @@
 use ash::{vk, Device};
 use std::sync::Arc;
 
+/// One command pool per frame in flight, each owning that frame's primary command buffer
+///
+/// Resetting a whole pool once per frame is cheaper than resetting individual buffers
+/// (pools created without RESET_COMMAND_BUFFER let the driver use a simple linear allocator).
 pub struct CommandPool {
-    pool: vk::CommandPool,
+    pools: Vec<vk::CommandPool>,
+    /// Command buffer of each frame, `buffers[i]` is allocated from `pools[i]`
     pub buffers: Vec<vk::CommandBuffer>,
     device: Arc<Device>,
 }
 
 impl CommandPool {
-    /// Create a command pool and allocate command buffers for the given queue family.
+    /// Create a command pool and allocate its command buffer for each frame in flight.
     ///
     /// # Arguments
     /// * `device` - The Vulkan logical device
     /// * `queue_family_index` - The queue family index (usually graphics family)
-    /// * `buffer_count` - Number of command buffers to allocate
+    /// * `frame_count` - Number of frames in flight
     pub fn new(
         device: &Arc<Device>,
         queue_family_index: u32,
-        buffer_count: u32,
+        frame_count: u32,
     ) -> Self {
         let pool_create_info = vk::CommandPoolCreateInfo::default()
-            .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
             .queue_family_index(queue_family_index);
 
-        let pool = unsafe {
-            device
-                .create_command_pool(&pool_create_info, None)
-                .expect("Failed to create command pool!")
-        };
+        let mut pools = Vec::with_capacity(frame_count as usize);
+        let mut buffers = Vec::with_capacity(frame_count as usize);
+        for _ in 0..frame_count {
+            let pool = unsafe {
+                device
+                    .create_command_pool(&pool_create_info, None)
+                    .expect("Failed to create command pool!")
+            };
 
-        let allocate_info = vk::CommandBufferAllocateInfo::default()
-            .command_pool(pool)
-            .level(vk::CommandBufferLevel::PRIMARY)
-            .command_buffer_count(buffer_count);
+            let allocate_info = vk::CommandBufferAllocateInfo::default()
+                .command_pool(pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1);
 
-        let buffers = unsafe {
-            device
-                .allocate_command_buffers(&allocate_info)
-                .expect("Failed to allocate command buffers!")
-        };
+            let buffer = unsafe {
+                device
+                    .allocate_command_buffers(&allocate_info)
+                    .expect("Failed to allocate command buffers!")[0]
+            };
+            pools.push(pool);
+            buffers.push(buffer);
+        }
 
         CommandPool { 
-            pool, 
+            pools, 
             buffers,
             device: device.clone(),
         }
     }
 
-    pub fn allocate_buffers(
-        &mut self,
-        device: &Arc<Device>,
-        count: u32,
-    ) -> Vec<vk::CommandBuffer> {
-        let allocate_info = vk::CommandBufferAllocateInfo::default()
-            .command_pool(self.pool)
-            .level(vk::CommandBufferLevel::PRIMARY)
-            .command_buffer_count(count);
-
-        let buffers = unsafe {
-            device
-                .allocate_command_buffers(&allocate_info)
-                .expect("Failed to allocate command buffers!")
-        };
-
-        self.buffers.extend_from_slice(&buffers);
-        buffers
-    }
-
-    /// Reset the command pool, freeing all allocated command buffers.
-    pub fn reset(&self, device: &Arc<Device>) {
+    /// Reset the pool of `frame`, returning its command buffer to the initial state.
+    /// The frame's previous submission must have completed (its fence waited on).
+    pub fn reset_frame(&self, frame: usize) -> Result<(), vk::Result> {
         unsafe {
-            device
-                .reset_command_pool(
-                    self.pool,
-                    vk::CommandPoolResetFlags::RELEASE_RESOURCES,
-                )
-                .expect("Failed to reset command pool!");
+            self.device.reset_command_pool(self.pools[frame], vk::CommandPoolResetFlags::empty())
         }
     }
 
@@ -104,7 +91,10 @@ impl Drop for CommandPool {
         unsafe {
             // Wait for all GPU work to complete before destroying the command pool
             let _ = self.device.device_wait_idle();
-            self.device.destroy_command_pool(self.pool, None);
+            // Destroying a pool frees its command buffers
+            for &pool in &self.pools {
+                self.device.destroy_command_pool(pool, None);
+            }
         }
     }
 }
@@ -1,33 +1,49 @@
 use anyhow::{anyhow, Result};
 use std::ffi::CStr;
-use std::{mem::ManuallyDrop, os::raw::c_char, sync::Arc};
+use std::{cell::Cell, mem::ManuallyDrop, os::raw::c_char, sync::Arc};
 use winit::{
     raw_window_handle::{HasDisplayHandle, RawDisplayHandle},
     raw_window_handle::{HasWindowHandle, RawWindowHandle},
     window::{Window, WindowId},
 };
 
+use super::DeviceFeatures;
 use ash::{
-    // ext::debug_utils,
+    ext::debug_utils,
     khr::swapchain,
     vk,
     Entry,
     Instance,
 };
+
+/// Instance, device and surface of the window the engine renders to
+///
+/// The Vulkan objects are reachable through accessors for code that talks to Vulkan
+/// directly, everything else should go through the renderer. The surface goes away while
+/// a mobile app is in the background, see `Renderer::suspend` and `Renderer::resume`.
 pub struct VulkanContext {
-    pub entry: Entry,
-    pub instance: Instance,
-    pub physical_device: vk::PhysicalDevice,
-    pub surface_loader: ash::khr::surface::Instance,
-    pub raw_display_handle: RawDisplayHandle,
-    pub raw_window_handle: RawWindowHandle,
-    pub device: ManuallyDrop<Arc<ash::Device>>,
-    pub surface: ash::vk::SurfaceKHR,
-    pub queue_family_indices: Vec<u32>,
+    pub(crate) entry: Entry,
+    pub(crate) instance: Instance,
+    pub(crate) physical_device: vk::PhysicalDevice,
+    pub(crate) surface_loader: ash::khr::surface::Instance,
+    pub(crate) raw_display_handle: RawDisplayHandle,
+    pub(crate) raw_window_handle: Cell<RawWindowHandle>,
+    pub(crate) device: ManuallyDrop<Arc<ash::Device>>,
+    /// Null while suspended
+    pub(crate) surface: Cell<vk::SurfaceKHR>,
+    pub(crate) queue_family_indices: Vec<u32>,
+    features: DeviceFeatures,
+    /// Command buffer labels, `None` when the loader doesn't offer VK_EXT_debug_utils
+    debug_utils: Option<debug_utils::Device>,
 }
 
 impl VulkanContext {
     pub fn new(window: Arc<Window>) -> Result<Self> {
+        Self::with_validation(window, true)
+    }
+
+    /// `validation` turns the Khronos validation layer on where it is installed
+    pub fn with_validation(window: Arc<Window>, validation: bool) -> Result<Self> {
         unsafe {
             let entry = Entry::linked();
             let app_name = c"VulkanTriangle";
@@ -35,21 +51,41 @@ impl VulkanContext {
             let raw_display_handle = window.display_handle()?.as_raw();
             let raw_window_handle = window.window_handle()?.as_raw();
 
-            let layer_names = [c"VK_LAYER_KHRONOS_validation"];
-            let layers_names_raw: Vec<*const c_char> = layer_names
+            // Validation where the layer is installed, phones rarely have it
+            let validation_layer = c"VK_LAYER_KHRONOS_validation";
+            let has_validation = entry
+                .enumerate_instance_layer_properties()?
                 .iter()
-                .map(|raw_name| raw_name.as_ptr())
-                .collect();
+                .any(|layer| layer.layer_name_as_c_str() == Ok(validation_layer));
+            let layers_names_raw: Vec<*const c_char> = if !validation {
+                Vec::new()
+            } else if has_validation {
+                vec![validation_layer.as_ptr()]
+            } else {
+                log::info!("Vulkan validation layer not available");
+                Vec::new()
+            };
 
-            let extension_names =
+            let mut extension_names =
                 ash_window::enumerate_required_extensions(raw_display_handle)?.to_vec();
 
-            // extension_names.push(debug_utils::NAME.as_ptr());
+            let instance_extensions = entry.enumerate_instance_extension_properties(None)?;
+            let has_instance_extension =
+                |name: &CStr| instance_extensions.iter().any(|ext| ext.extension_name_as_c_str() == Ok(name));
+            // Labels make passes show up by name in RenderDoc and other debuggers
+            let has_debug_utils = has_instance_extension(debug_utils::NAME);
+            if has_debug_utils {
+                extension_names.push(debug_utils::NAME.as_ptr());
+            }
 
-            #[cfg(any(target_os = "macos", target_os = "ios"))]
-            {
+            // Layered implementations (MoltenVK on macOS and iOS) only show up with portability
+            // enumeration, wherever the loader offers it
+            let portability = has_instance_extension(ash::khr::portability_enumeration::NAME);
+            if portability {
                 extension_names.push(ash::khr::portability_enumeration::NAME.as_ptr());
-                extension_names.push(ash::khr::get_physical_device_properties2::NAME.as_ptr());
+                if has_instance_extension(ash::khr::get_physical_device_properties2::NAME) {
+                    extension_names.push(ash::khr::get_physical_device_properties2::NAME.as_ptr());
+                }
             }
 
             let appinfo = vk::ApplicationInfo::default()
@@ -59,7 +95,7 @@ impl VulkanContext {
                 .engine_version(0)
                 .api_version(vk::API_VERSION_1_3);
 
-            let create_flags = if cfg!(any(target_os = "macos", target_os = "ios")) {
+            let create_flags = if portability {
                 vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR
             } else {
                 vk::InstanceCreateFlags::default()
@@ -97,6 +133,10 @@ impl VulkanContext {
 
                     if graphics_families.is_empty() {
                         None
+                    } else if !DeviceFeatures::query(&instance, pdevice).is_supported() {
+                        let name = Self::vk_to_string(&instance.get_physical_device_properties(pdevice).device_name);
+                        log::warn!("Skipping {}: dynamic rendering is not supported", name);
+                        None
                     } else {
                         Some((pdevice, graphics_families))
                     }
@@ -104,7 +144,7 @@ impl VulkanContext {
                 .collect();
 
             if graphics_devices.is_empty() {
-                return Err(anyhow!("No graphics-capable devices found!"));
+                return Err(anyhow!("No graphics-capable devices with dynamic rendering found!"));
             }
 
             // window should outlive this
@@ -200,23 +240,28 @@ impl VulkanContext {
                 queue_create_infos.push(queue_info);
             }
 
-            let device_extension_names_raw = [
-                swapchain::NAME.as_ptr(),
-                #[cfg(any(target_os = "macos", target_os = "ios"))]
-                ash::khr::portability_subset::NAME.as_ptr(),
-            ];
+            // Devices that only implement a subset of Vulkan must have it enabled
+            let has_portability_subset = instance
+                .enumerate_device_extension_properties(*physical_device)?
+                .iter()
+                .any(|ext| ext.extension_name_as_c_str() == Ok(ash::khr::portability_subset::NAME));
+            let mut device_extension_names_raw = vec![swapchain::NAME.as_ptr()];
+            if has_portability_subset {
+                log::info!("Device implements the Vulkan portability subset");
+                device_extension_names_raw.push(ash::khr::portability_subset::NAME.as_ptr());
+            }
 
-            let features = vk::PhysicalDeviceFeatures {
-                shader_clip_distance: 1,
-                ..Default::default()
-            };
+            // Enable only what the device has, so optional features degrade instead of failing creation
+            let device_features = DeviceFeatures::query(&instance, *physical_device);
+            device_features.log_report();
+            let features = device_features.enabled_core();
 
             let mut dynamic_rendering_features =
                 vk::PhysicalDeviceDynamicRenderingFeatures::default().dynamic_rendering(true);
 
             let mut buffer_device_features =
                 vk::PhysicalDeviceBufferDeviceAddressFeatures::default()
-                    .buffer_device_address(true);
+                    .buffer_device_address(device_features.buffer_device_address);
             // Create logical device
             let device_create_info = vk::DeviceCreateInfo::default()
                 .queue_create_infos(&queue_create_infos)
@@ -234,10 +279,25 @@ impl VulkanContext {
             let compute_queue = device.get_device_queue(compute_family, 0);
             let transfer_queue = device.get_device_queue(transfer_family, 0);
 
-            println!("Graphics queue:  {:?}", graphics_queue);
-            println!("Present queue:   {:?}", present_queue);
-            println!("Compute queue:   {:?}", compute_queue);
-            println!("Transfer queue:  {:?}", transfer_queue);
+            log::debug!("Graphics queue:  {:?}", graphics_queue);
+            log::debug!("Present queue:   {:?}", present_queue);
+            log::debug!("Compute queue:   {:?}", compute_queue);
+            log::debug!("Transfer queue:  {:?}", transfer_queue);
+
+            let properties = instance.get_physical_device_properties(*physical_device);
+            crate::crash::set_gpu_info(format!(
+                "{} ({:?}, vendor {:#06x}, device {:#06x})\nDriver {:#x}, Vulkan {}.{}.{}",
+                Self::vk_to_string(&properties.device_name),
+                properties.device_type,
+                properties.vendor_id,
+                properties.device_id,
+                properties.driver_version,
+                vk::api_version_major(properties.api_version),
+                vk::api_version_minor(properties.api_version),
+                vk::api_version_patch(properties.api_version),
+            ));
+
+            let debug_utils = has_debug_utils.then(|| debug_utils::Device::new(&instance, &device));
 
             let device_arc = Arc::new(device);
             Ok(Self {
@@ -246,14 +306,94 @@ impl VulkanContext {
                 physical_device: *physical_device,
                 surface_loader,
                 raw_display_handle,
-                raw_window_handle,
+                raw_window_handle: Cell::new(raw_window_handle),
                 device: ManuallyDrop::new(device_arc),
-                surface,
+                surface: Cell::new(surface),
                 queue_family_indices: unique_families.iter().copied().collect(),
+                features: device_features,
+                debug_utils,
             })
         }
     }
 
+    pub fn entry(&self) -> &Entry {
+        &self.entry
+    }
+
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
+
+    pub fn physical_device(&self) -> vk::PhysicalDevice {
+        self.physical_device
+    }
+
+    pub fn device(&self) -> &Arc<ash::Device> {
+        &self.device
+    }
+
+    /// Surface of the window, null while the renderer is suspended
+    pub fn surface(&self) -> vk::SurfaceKHR {
+        self.surface.get()
+    }
+
+    pub fn surface_loader(&self) -> &ash::khr::surface::Instance {
+        &self.surface_loader
+    }
+
+    pub fn raw_display_handle(&self) -> RawDisplayHandle {
+        self.raw_display_handle
+    }
+
+    pub fn raw_window_handle(&self) -> RawWindowHandle {
+        self.raw_window_handle.get()
+    }
+
+    pub fn queue_family_indices(&self) -> &[u32] {
+        &self.queue_family_indices
+    }
+
+    /// Destroy the surface, e.g. when a mobile app goes to the background and its native
+    /// window goes away. Swapchains of the surface must be destroyed first.
+    pub(crate) fn destroy_surface(&self) {
+        let surface = self.surface.replace(vk::SurfaceKHR::null());
+        if surface != vk::SurfaceKHR::null() {
+            unsafe { self.surface_loader.destroy_surface(surface, None) };
+        }
+    }
+
+    /// Create a surface for `window`'s current native window, replacing the old one, which
+    /// must not have swapchains anymore
+    pub(crate) fn recreate_surface(&self, window: &Window) -> Result<()> {
+        self.destroy_surface();
+        let raw_window_handle = window.window_handle()?.as_raw();
+        let surface = unsafe {
+            ash_window::create_surface(&self.entry, &self.instance, self.raw_display_handle, raw_window_handle, None)?
+        };
+        let supported = self.queue_family_indices.iter().any(|&family| unsafe {
+            self.surface_loader
+                .get_physical_device_surface_support(self.physical_device, family, surface)
+                .unwrap_or(false)
+        });
+        if !supported {
+            unsafe { self.surface_loader.destroy_surface(surface, None) };
+            return Err(anyhow!("The device can't present to the new window surface"));
+        }
+        self.surface.set(surface);
+        self.raw_window_handle.set(raw_window_handle);
+        Ok(())
+    }
+
+    /// Optional features available (and enabled) on the device
+    pub fn features(&self) -> &DeviceFeatures {
+        &self.features
+    }
+
+    /// Loader for command buffer labels, if the instance has VK_EXT_debug_utils
+    pub fn debug_utils(&self) -> Option<&debug_utils::Device> {
+        self.debug_utils.as_ref()
+    }
+
     // source for this fn:
     // https://github.com/unknownue/vulkan-tutorial-rust/blob/master/src/utility/tools.rs
     pub fn vk_to_string(raw_string_array: &[c_char]) -> String {
@@ -295,7 +435,7 @@ impl VulkanContext {
         score += (total_mem / (1024 * 1024)) as i32;
 
         let device_name = Self::vk_to_string(&props.device_name);
-        println!(
+        log::info!(
             "Device for {:?}:\n\t {}, id: {}, type: {}",
             id, device_name, props.device_id, device_type
         );
@@ -310,7 +450,7 @@ impl Drop for VulkanContext {
             let _ = self.device.device_wait_idle();
             
             // Destroy surface before instance
-            self.surface_loader.destroy_surface(self.surface, None);
+            self.destroy_surface();
             
             // Take ownership of the device Arc from ManuallyDrop
             let device_arc = ManuallyDrop::take(&mut self.device);
@@ -323,7 +463,7 @@ impl Drop for VulkanContext {
                 }
                 Err(_arc) => {
                     // Someone else still has a reference - this shouldn't happen
-                    eprintln!("Warning: VulkanContext dropped but device still has references");
+                    log::warn!("VulkanContext dropped but device still has references");
                 }
             }
             
@@ -0,0 +1,152 @@
+use anyhow::Result;
+use glam::{Mat4, Vec2};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use super::{ColorVertex2D, PipelineId, PushConstants2D, RenderContext, Renderer, VertexBuffer, VulkanContext};
+
+/// Uploaded buffers are kept alive for this many flushes, longer than any frame stays in flight
+const BUFFERS_KEPT: usize = 3;
+
+/// Immediate mode line renderer for debug drawing and editor gizmos
+///
+/// Queue lines during the frame, then `flush` them once per frame inside a rendering pass.
+/// Lines that stay the same for many frames can be uploaded once and drawn again instead.
+pub struct DebugLines {
+    context: Arc<VulkanContext>,
+    vertices: Vec<ColorVertex2D>,
+    /// Buffers of recent flushes that may still be read by the GPU
+    buffers: VecDeque<VertexBuffer<ColorVertex2D>>,
+    /// `DebugLines` in the scene pass, `UILines` when drawn with the UI
+    pipeline: PipelineId,
+    /// The newest buffer holds the lines of the last upload, false if that upload was empty
+    uploaded: bool,
+}
+
+impl DebugLines {
+    /// Lines drawn in the scene pass, over the entities
+    pub fn new(context: Arc<VulkanContext>) -> Self {
+        DebugLines {
+            context,
+            pipeline: PipelineId::DebugLines,
+            vertices: Vec::new(),
+            buffers: VecDeque::with_capacity(BUFFERS_KEPT + 1),
+            uploaded: false,
+        }
+    }
+
+    /// Lines drawn with the UI, e.g. by widgets in UI pixel space
+    pub fn for_ui(context: Arc<VulkanContext>) -> Self {
+        DebugLines {
+            pipeline: PipelineId::UILines,
+            ..Self::new(context)
+        }
+    }
+
+    pub fn line(&mut self, from: Vec2, to: Vec2, color: [f32; 3]) {
+        self.vertices.push(ColorVertex2D { position: from.into(), color });
+        self.vertices.push(ColorVertex2D { position: to.into(), color });
+    }
+
+    /// Line strip through `points`, closed back to the first point if `closed`
+    pub fn polyline(&mut self, points: &[Vec2], closed: bool, color: [f32; 3]) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], color);
+        }
+        if let (true, Some(&first), Some(&last)) = (closed && points.len() > 2, points.first(), points.last()) {
+            self.line(last, first, color);
+        }
+    }
+
+    /// Axis-aligned rectangle outline
+    pub fn rect(&mut self, center: Vec2, size: Vec2, color: [f32; 3]) {
+        let half = size / 2.0;
+        let corners = [
+            center + Vec2::new(-half.x, -half.y),
+            center + Vec2::new(half.x, -half.y),
+            center + Vec2::new(half.x, half.y),
+            center + Vec2::new(-half.x, half.y),
+        ];
+        self.polyline(&corners, true, color);
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f32, segments: usize, color: [f32; 3]) {
+        let segments = segments.max(3);
+        let points: Vec<Vec2> = (0..segments)
+            .map(|i| center + Vec2::from_angle(i as f32 / segments as f32 * std::f32::consts::TAU) * radius)
+            .collect();
+        self.polyline(&points, true, color);
+    }
+
+    /// Line with a small arrow head at `to`
+    pub fn arrow(&mut self, from: Vec2, to: Vec2, head_size: f32, color: [f32; 3]) {
+        self.line(from, to, color);
+        let direction = (to - from).normalize_or_zero();
+        let side = direction.perp();
+        self.line(to, to - direction * head_size + side * head_size * 0.5, color);
+        self.line(to, to - direction * head_size - side * head_size * 0.5, color);
+    }
+
+    /// Number of queued line segments
+    pub fn len(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// Drop queued lines without drawing them
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Draw and clear all queued lines with the given projection * view matrix
+    pub fn flush(&mut self, ctx: &RenderContext, renderer: &mut Renderer, view_projection: Mat4) -> Result<()> {
+        self.upload()?;
+        self.draw(ctx, renderer, view_projection)
+    }
+
+    /// Move the queued lines into a GPU buffer that `draw` keeps drawing until the next upload
+    ///
+    /// For lines that don't change every frame, e.g. widgets rebuilding them on refresh and
+    /// drawing them from a `render` that can't mutate.
+    pub fn upload(&mut self) -> Result<()> {
+        self.uploaded = !self.vertices.is_empty();
+        if !self.uploaded {
+            return Ok(());
+        }
+
+        let buffer = VertexBuffer::new(&self.context.device, self.context.physical_device, &self.context.instance, &self.vertices)?;
+        self.vertices.clear();
+        self.buffers.push_back(buffer);
+        while self.buffers.len() > BUFFERS_KEPT {
+            if let Some(old) = self.buffers.pop_front() {
+                old.destroy(&self.context.device);
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the lines of the last `upload`
+    pub fn draw(&self, ctx: &RenderContext, renderer: &mut Renderer, view_projection: Mat4) -> Result<()> {
+        let Some(buffer) = self.buffers.back().filter(|_| self.uploaded) else {
+            return Ok(());
+        };
+        let pipeline = renderer.get_pipeline(self.pipeline)?;
+        let pipeline_layout = renderer.get_pipeline_layout(self.pipeline)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for {:?} pipeline", self.pipeline))?;
+        ctx.bind_pipeline(pipeline);
+        ctx.push(pipeline_layout, &PushConstants2D::new(view_projection, Mat4::IDENTITY));
+        ctx.bind_vertex_buffer(buffer.buffer);
+        ctx.draw(buffer.vertex_count, 1, 0, 0);
+        Ok(())
+    }
+
+    /// Manually destroy Vulkan resources
+    pub fn destroy(&self, device: &ash::Device) {
+        for buffer in &self.buffers {
+            buffer.destroy(device);
+        }
+    }
+}
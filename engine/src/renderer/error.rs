@@ -0,0 +1,50 @@
+use ash::vk;
+use std::fmt;
+
+/// Failure while recording or submitting a frame, returned by `Renderer::begin_frame`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RendererError {
+    /// The GPU was reset or removed, every object created from the device is invalid
+    DeviceLost,
+    /// Host or device memory ran out
+    OutOfMemory { device: bool },
+    /// The window surface is gone (e.g. the display was disconnected)
+    SurfaceLost,
+    /// Any other Vulkan error
+    Vulkan(vk::Result),
+}
+
+impl From<vk::Result> for RendererError {
+    fn from(result: vk::Result) -> Self {
+        match result {
+            vk::Result::ERROR_DEVICE_LOST => RendererError::DeviceLost,
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY => RendererError::OutOfMemory { device: false },
+            vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => RendererError::OutOfMemory { device: true },
+            vk::Result::ERROR_SURFACE_LOST_KHR => RendererError::SurfaceLost,
+            other => RendererError::Vulkan(other),
+        }
+    }
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendererError::DeviceLost => write!(f, "GPU device lost"),
+            RendererError::OutOfMemory { device: true } => write!(f, "out of GPU memory"),
+            RendererError::OutOfMemory { device: false } => write!(f, "out of host memory"),
+            RendererError::SurfaceLost => write!(f, "window surface lost"),
+            RendererError::Vulkan(result) => write!(f, "Vulkan error {:?}", result),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {}
+
+/// What the application should do after `Renderer::recover`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    /// The renderer was reset, keep rendering
+    Retry,
+    /// The renderer can't continue, release GPU resources and exit
+    Shutdown,
+}
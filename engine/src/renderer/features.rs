@@ -0,0 +1,94 @@
+use ash::{vk, Instance};
+
+/// Optional device capabilities, queried before device creation
+///
+/// Only `dynamic_rendering` is required (the renderer has no render pass path);
+/// everything else is enabled when present and code using it must check first.
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceFeatures {
+    pub dynamic_rendering: bool,
+    pub buffer_device_address: bool,
+    pub shader_clip_distance: bool,
+    /// Required for `SamplerConfig::anisotropy`
+    pub sampler_anisotropy: bool,
+    /// Highest `SamplerConfig::anisotropy` the device filters with
+    pub max_sampler_anisotropy: f32,
+    /// Wireframe polygon modes
+    pub fill_mode_non_solid: bool,
+    /// Runtime sized, partially bound, non-uniformly indexed texture arrays
+    pub bindless: bool,
+    /// Highest sample count usable for color attachments (TYPE_1 means no MSAA)
+    pub max_msaa_samples: vk::SampleCountFlags,
+    pub max_push_constants_size: u32,
+}
+
+impl DeviceFeatures {
+    pub fn query(instance: &Instance, device: vk::PhysicalDevice) -> Self {
+        let mut dynamic_rendering = vk::PhysicalDeviceDynamicRenderingFeatures::default();
+        let mut buffer_device_address = vk::PhysicalDeviceBufferDeviceAddressFeatures::default();
+        let mut descriptor_indexing = vk::PhysicalDeviceDescriptorIndexingFeatures::default();
+        let mut features2 = vk::PhysicalDeviceFeatures2::default()
+            .push_next(&mut dynamic_rendering)
+            .push_next(&mut buffer_device_address)
+            .push_next(&mut descriptor_indexing);
+        unsafe { instance.get_physical_device_features2(device, &mut features2) };
+        let core = features2.features;
+        let limits = unsafe { instance.get_physical_device_properties(device) }.limits;
+
+        // Highest bit of the supported counts
+        let sample_counts = limits.framebuffer_color_sample_counts;
+        let max_msaa_samples = [
+            vk::SampleCountFlags::TYPE_64,
+            vk::SampleCountFlags::TYPE_32,
+            vk::SampleCountFlags::TYPE_16,
+            vk::SampleCountFlags::TYPE_8,
+            vk::SampleCountFlags::TYPE_4,
+            vk::SampleCountFlags::TYPE_2,
+        ]
+        .into_iter()
+        .find(|&count| sample_counts.contains(count))
+        .unwrap_or(vk::SampleCountFlags::TYPE_1);
+
+        DeviceFeatures {
+            dynamic_rendering: dynamic_rendering.dynamic_rendering == vk::TRUE,
+            buffer_device_address: buffer_device_address.buffer_device_address == vk::TRUE,
+            shader_clip_distance: core.shader_clip_distance == vk::TRUE,
+            sampler_anisotropy: core.sampler_anisotropy == vk::TRUE,
+            max_sampler_anisotropy: limits.max_sampler_anisotropy,
+            fill_mode_non_solid: core.fill_mode_non_solid == vk::TRUE,
+            bindless: descriptor_indexing.runtime_descriptor_array == vk::TRUE
+                && descriptor_indexing.descriptor_binding_partially_bound == vk::TRUE
+                && descriptor_indexing.shader_sampled_image_array_non_uniform_indexing == vk::TRUE,
+            max_msaa_samples,
+            max_push_constants_size: limits.max_push_constants_size,
+        }
+    }
+
+    /// Whether the engine can run on the device at all
+    pub fn is_supported(&self) -> bool {
+        self.dynamic_rendering
+    }
+
+    /// Core features to enable, the supported subset of what the engine uses
+    pub fn enabled_core(&self) -> vk::PhysicalDeviceFeatures {
+        vk::PhysicalDeviceFeatures {
+            shader_clip_distance: self.shader_clip_distance as vk::Bool32,
+            sampler_anisotropy: self.sampler_anisotropy as vk::Bool32,
+            fill_mode_non_solid: self.fill_mode_non_solid as vk::Bool32,
+            ..Default::default()
+        }
+    }
+
+    pub fn log_report(&self) {
+        log::info!(
+            "Device features: buffer device address: {}, clip distance: {}, anisotropy: {}, wireframe: {}, bindless: {}, max MSAA: {:?}, push constants: {} bytes",
+            self.buffer_device_address,
+            self.shader_clip_distance,
+            self.sampler_anisotropy,
+            self.fill_mode_non_solid,
+            self.bindless,
+            self.max_msaa_samples,
+            self.max_push_constants_size,
+        );
+    }
+}
@@ -2,14 +2,24 @@
 use anyhow::Result;
 use ash::vk::Format;
 use glam::Vec2;
-use rusttype::{point, Font, Scale};
-use std::{collections::HashMap, sync::Arc};
+use rusttype::{point, Font as TrueTypeFont, Scale};
+use std::{collections::HashMap, sync::{Arc, Mutex}};
 
-use super::Texture;
+use super::{Texture, VulkanContext};
+use crate::assets::Handle;
 
+/// Glyphs of a font rasterized at twice `size` pixels, drawn at half scale
+///
+/// Text of other sizes asks for a page of its own with `for_size` instead of scaling these
+/// glyphs, the pages are kept and destroyed with this atlas.
 pub struct FontAtlas {
     pub texture: Texture,
     pub glyph_map: HashMap<char, GlyphMetrics>,
+    /// Font size in pixels the glyphs were rasterized for
+    pub size: f32,
+    font: Arc<TrueTypeFont<'static>>,
+    /// Atlases of the same font at other sizes
+    pages: Mutex<Vec<Arc<FontAtlas>>>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -37,9 +47,19 @@ impl FontAtlas {
     ) -> Result<Self> {
         let font_data = std::fs::read(path)
             .map_err(|e| anyhow::anyhow!("Failed to load font file '{}': {}", path, e))?;
-        let font = Font::try_from_vec(font_data)
+        let font = TrueTypeFont::try_from_vec(font_data)
             .ok_or_else(|| anyhow::anyhow!("Invalid font file format"))?;
+        Self::rasterize(Arc::new(font), font_size, device, instance, physical_device, queue_family_index)
+    }
 
+    fn rasterize(
+        font: Arc<TrueTypeFont<'static>>,
+        font_size: f32,
+        device: &Arc<ash::Device>,
+        instance: &ash::Instance,
+        physical_device: ash::vk::PhysicalDevice,
+        queue_family_index: u32,
+    ) -> Result<Self> {
         // Rasterize at 2x target size for good antialiasing, then scale down 2x for crisp rendering
         let height: f32 = font_size * 2.0;
         let scale = Scale { x: height, y: height };
@@ -137,7 +157,41 @@ impl FontAtlas {
             queue_family_index,
         )?;
 
-        Ok(FontAtlas { texture, glyph_map })
+        Ok(FontAtlas { texture, glyph_map, size: font_size, font, pages: Mutex::new(Vec::new()) })
+    }
+
+    /// Atlas to draw text of `font_size` pixels with, this one or a page rasterized for the
+    /// size rounded to whole pixels
+    pub fn for_size(self: &Arc<Self>, font_size: f32, context: &VulkanContext) -> Result<Arc<FontAtlas>> {
+        let size = font_size.round().max(1.0);
+        if size == self.size.round() {
+            return Ok(self.clone());
+        }
+        let mut pages = self.pages.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(page) = pages.iter().find(|page| page.size == size) {
+            return Ok(page.clone());
+        }
+        let page = Arc::new(Self::rasterize(
+            self.font.clone(),
+            size,
+            &context.device,
+            &context.instance,
+            context.physical_device,
+            context.queue_family_indices[0],
+        )?);
+        log::debug!("Rasterized a {}px font page", size);
+        pages.push(page.clone());
+        Ok(page)
+    }
+
+    /// Scale glyph metrics are multiplied by for text of `font_size` pixels
+    pub fn scale_for(&self, font_size: f32) -> f32 {
+        font_size / (self.size * 2.0)
+    }
+
+    /// Width of `text` at `font_size` pixels, as laid out by `TextComponent`
+    pub fn text_width(&self, text: &str, font_size: f32) -> f32 {
+        self.get_text_width(text) * self.scale_for(font_size)
     }
 
     pub fn get_text_width(&self, text: &str) -> f32 {
@@ -152,7 +206,148 @@ impl FontAtlas {
     }
 
     /// Manually destroy Vulkan resources
+    /// Atlases loaded through a `FontManager` are destroyed by it instead.
     pub fn destroy(&self, device: &ash::Device) {
         self.texture.destroy(device);
+        for page in self.pages.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).drain(..) {
+            page.destroy(device);
+        }
+    }
+}
+
+/// Frames an unloaded atlas is kept alive, the GPU may still be sampling it
+const RETIRE_FRAMES: u64 = 3;
+
+/// A font rasterized at one size, loaded through a `FontManager`
+pub struct Font {
+    pub path: String,
+    pub size: f32,
+    pub atlas: Arc<FontAtlas>,
+}
+
+struct FontSlot {
+    font: Option<Font>,
+    generation: u32,
+}
+
+/// Font atlases owned by the renderer, see `Renderer::load_font`
+///
+/// Loading the same file at the same size again returns the existing handle. Unloaded
+/// atlases are only destroyed once the GPU is done with them and no text holds on to
+/// them anymore, everything left is destroyed with the renderer.
+pub struct FontManager {
+    slots: Vec<FontSlot>,
+    /// Unloaded atlases and the frame they were unloaded in
+    retired: Vec<(u64, Arc<FontAtlas>)>,
+    frame: u64,
+}
+
+impl FontManager {
+    pub fn new() -> Self {
+        FontManager {
+            slots: Vec::new(),
+            retired: Vec::new(),
+            frame: 0,
+        }
+    }
+
+    /// Rasterize the font at `path` for text of `size` pixels
+    pub fn load(&mut self, context: &VulkanContext, path: &str, size: f32) -> Result<Handle<Font>> {
+        if let Some(handle) = self.find(path, size) {
+            return Ok(handle);
+        }
+        let atlas = FontAtlas::load(
+            path,
+            size,
+            &context.device,
+            &context.instance,
+            context.physical_device,
+            context.queue_family_indices[0],
+        )?;
+        let font = Font { path: path.to_string(), size, atlas: Arc::new(atlas) };
+        let index = match self.slots.iter().position(|slot| slot.font.is_none()) {
+            Some(index) => {
+                self.slots[index].font = Some(font);
+                index
+            }
+            None => {
+                self.slots.push(FontSlot { font: Some(font), generation: 0 });
+                self.slots.len() - 1
+            }
+        };
+        Ok(Handle::new(index, self.slots[index].generation))
+    }
+
+    /// Handle of a loaded font, `None` if that file isn't loaded at that size
+    pub fn find(&self, path: &str, size: f32) -> Option<Handle<Font>> {
+        self.slots.iter().enumerate().find_map(|(index, slot)| {
+            let font = slot.font.as_ref()?;
+            (font.path == path && font.size == size).then(|| Handle::new(index, slot.generation))
+        })
+    }
+
+    /// `None` once the font was unloaded
+    pub fn get(&self, handle: Handle<Font>) -> Option<&Font> {
+        let slot = self.slots.get(handle.index())?;
+        slot.font.as_ref().filter(|_| slot.generation == handle.generation())
+    }
+
+    /// Shared atlas of a font, for `TextComponent`s
+    pub fn atlas(&self, handle: Handle<Font>) -> Option<Arc<FontAtlas>> {
+        self.get(handle).map(|font| font.atlas.clone())
+    }
+
+    /// Release a font, its atlas is destroyed once nothing uses it anymore
+    /// Returns false for a handle that was already unloaded.
+    pub fn unload(&mut self, handle: Handle<Font>) -> bool {
+        if self.get(handle).is_none() {
+            return false;
+        }
+        let slot = &mut self.slots[handle.index()];
+        slot.generation = slot.generation.wrapping_add(1);
+        if let Some(font) = slot.font.take() {
+            self.retired.push((self.frame, font.atlas));
+        }
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.font.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Start a new frame, destroying unloaded atlases the GPU and all text are done with
+    pub fn next_frame(&mut self, device: &ash::Device) {
+        self.frame += 1;
+        let frame = self.frame;
+        self.retired.retain(|(unloaded, atlas)| {
+            let done = frame - unloaded > RETIRE_FRAMES && Arc::strong_count(atlas) == 1;
+            if done {
+                atlas.destroy(device);
+            }
+            !done
+        });
+    }
+
+    /// Destroy every atlas, loaded or not, after the device is idle
+    pub fn destroy(&mut self, device: &ash::Device) {
+        for (_, atlas) in self.retired.drain(..) {
+            atlas.destroy(device);
+        }
+        for slot in &mut self.slots {
+            if let Some(font) = slot.font.take() {
+                font.atlas.destroy(device);
+            }
+            slot.generation = slot.generation.wrapping_add(1);
+        }
+    }
+}
+
+impl Default for FontManager {
+    fn default() -> Self {
+        Self::new()
     }
 }
\ No newline at end of file
@@ -2,13 +2,14 @@ use anyhow::Result;
 use ash::vk;
 use std::sync::Arc;
 
-use super::buffer_utils::create_buffer_with_data;
+use super::buffer_utils::{create_buffer_with_data, track_free, MemoryCategory};
 
 /// Generic vertex buffer that can hold any vertex type
 pub struct VertexBuffer<V> {
     pub buffer: vk::Buffer,
     pub memory: vk::DeviceMemory,
     pub vertex_count: u32,
+    allocation_size: vk::DeviceSize,
     _phantom: std::marker::PhantomData<V>,
 }
 
@@ -20,7 +21,7 @@ impl<V> VertexBuffer<V> {
         instance: &ash::Instance,
         vertices: &[V],
     ) -> Result<Self> {
-        let (buffer, memory) = create_buffer_with_data(
+        let (buffer, memory, allocation_size) = create_buffer_with_data(
             device,
             physical_device,
             instance,
@@ -32,6 +33,7 @@ impl<V> VertexBuffer<V> {
             buffer,
             memory,
             vertex_count: vertices.len() as u32,
+            allocation_size,
             _phantom: std::marker::PhantomData,
         })
     }
@@ -42,6 +44,7 @@ impl<V> VertexBuffer<V> {
             device.destroy_buffer(self.buffer, None);
             device.free_memory(self.memory, None);
         }
+        track_free(MemoryCategory::Buffers, self.allocation_size);
     }
 }
 
@@ -50,16 +53,35 @@ pub struct IndexBuffer {
     pub buffer: vk::Buffer,
     pub memory: vk::DeviceMemory,
     pub index_count: u32,
+    /// UINT16 or UINT32, chosen from the largest index
+    pub index_type: vk::IndexType,
+    allocation_size: vk::DeviceSize,
 }
 
 impl IndexBuffer {
+    /// Create an index buffer, stored as 16-bit indices if they all fit
     pub fn new(
         device: &Arc<ash::Device>,
         physical_device: vk::PhysicalDevice,
         instance: &ash::Instance,
         indices: &[u32],
     ) -> Result<Self> {
-        let (buffer, memory) = create_buffer_with_data(
+        if indices.iter().all(|&i| i <= u16::MAX as u32) {
+            let narrow: Vec<u16> = indices.iter().map(|&i| i as u16).collect();
+            Self::with_type(device, physical_device, instance, &narrow, vk::IndexType::UINT16)
+        } else {
+            Self::with_type(device, physical_device, instance, indices, vk::IndexType::UINT32)
+        }
+    }
+
+    fn with_type<I>(
+        device: &Arc<ash::Device>,
+        physical_device: vk::PhysicalDevice,
+        instance: &ash::Instance,
+        indices: &[I],
+        index_type: vk::IndexType,
+    ) -> Result<Self> {
+        let (buffer, memory, allocation_size) = create_buffer_with_data(
             device,
             physical_device,
             instance,
@@ -71,6 +93,8 @@ impl IndexBuffer {
             buffer,
             memory,
             index_count: indices.len() as u32,
+            index_type,
+            allocation_size,
         })
     }
 
@@ -80,6 +104,7 @@ impl IndexBuffer {
             device.destroy_buffer(self.buffer, None);
             device.free_memory(self.memory, None);
         }
+        track_free(MemoryCategory::Buffers, self.allocation_size);
     }
 }
 
@@ -95,7 +120,13 @@ pub struct PipelineBuilder {
     front_face: vk::FrontFace,
     color_format: vk::Format,
     enable_blending: bool,
+    /// Color blend factors (src, dst) of the primary attachment instead of blending by alpha,
+    /// the attachment's alpha is kept
+    color_factors: Option<(vk::BlendFactor, vk::BlendFactor)>,
+    /// Attachments after the primary one (format, blending, write mask)
+    extra_color_attachments: Vec<(vk::Format, bool, vk::ColorComponentFlags)>,
     descriptor_set_layouts: Vec<vk::DescriptorSetLayout>,
+    push_constant_ranges: Vec<vk::PushConstantRange>,
 }
 
 impl PipelineBuilder {
@@ -111,7 +142,10 @@ impl PipelineBuilder {
             front_face: vk::FrontFace::COUNTER_CLOCKWISE,
             color_format: vk::Format::B8G8R8A8_SRGB,
             enable_blending: false,
+            color_factors: None,
+            extra_color_attachments: Vec::new(),
             descriptor_set_layouts: Vec::new(),
+            push_constant_ranges: Vec::new(),
         }
     }
 
@@ -151,11 +185,49 @@ impl PipelineBuilder {
         self
     }
 
+    /// Add the fragment color to the primary attachment instead of blending by alpha
+    /// The attachment's alpha is kept, for glows composited onto an image
+    pub fn additive_blending(mut self) -> Self {
+        self.enable_blending = true;
+        self.color_factors = Some((vk::BlendFactor::ONE, vk::BlendFactor::ONE));
+        self
+    }
+
+    /// Multiply the primary attachment by the fragment color, keeping its alpha
+    /// For light and shadow maps darkening what was drawn before
+    pub fn multiply_blending(mut self) -> Self {
+        self.enable_blending = true;
+        self.color_factors = Some((vk::BlendFactor::ZERO, vk::BlendFactor::SRC_COLOR));
+        self
+    }
+
+    /// Add a color attachment after the primary one (`color_format`/`blending`)
+    /// Attachments are numbered in the order they're added, matching the fragment shader
+    /// outputs and the attachments passed to `RenderContext::begin_rendering_attachments`.
+    /// Integer formats (e.g. R32_UINT) can't be blended.
+    pub fn color_attachment(mut self, format: vk::Format, blending: bool) -> Self {
+        self.extra_color_attachments.push((format, blending, vk::ColorComponentFlags::RGBA));
+        self
+    }
+
+    /// Add a color attachment the pipeline leaves untouched (empty write mask)
+    /// For passes whose extra attachments only some of the pipelines output to
+    pub fn unwritten_color_attachment(mut self, format: vk::Format) -> Self {
+        self.extra_color_attachments.push((format, false, vk::ColorComponentFlags::empty()));
+        self
+    }
+
     pub fn descriptor_set_layouts(mut self, layouts: Vec<vk::DescriptorSetLayout>) -> Self {
         self.descriptor_set_layouts = layouts;
         self
     }
 
+    /// Declare a push constant range in the pipeline layout (see `PipelinePush::range`)
+    pub fn push_constant_range(mut self, range: vk::PushConstantRange) -> Self {
+        self.push_constant_ranges.push(range);
+        self
+    }
+
     pub fn build(
         self,
         device: &Arc<ash::Device>,
@@ -176,14 +248,8 @@ impl PipelineBuilder {
         };
 
         // Create pipeline layout with push constants and descriptor sets
-        let push_constant_range = vk::PushConstantRange::default()
-            .stage_flags(vk::ShaderStageFlags::VERTEX)
-            .offset(0)
-            .size(std::mem::size_of::<crate::renderer::PushConstants2D>() as u32);
-
-        let push_constant_ranges = [push_constant_range];
         let mut layout_info = vk::PipelineLayoutCreateInfo::default()
-            .push_constant_ranges(&push_constant_ranges);
+            .push_constant_ranges(&self.push_constant_ranges);
         
         if !self.descriptor_set_layouts.is_empty() {
             layout_info = layout_info.set_layouts(&self.descriptor_set_layouts);
@@ -214,17 +280,32 @@ impl PipelineBuilder {
         let multisample_state = vk::PipelineMultisampleStateCreateInfo::default()
             .rasterization_samples(vk::SampleCountFlags::TYPE_1);
 
-        let color_blend_attachment = vk::PipelineColorBlendAttachmentState::default()
-            .color_write_mask(vk::ColorComponentFlags::RGBA)
-            .blend_enable(self.enable_blending)
-            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
-            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
-            .color_blend_op(vk::BlendOp::ADD)
-            .src_alpha_blend_factor(vk::BlendFactor::ONE)
-            .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
-            .alpha_blend_op(vk::BlendOp::ADD);
-
-        let attachments = [color_blend_attachment];
+        let blend_state = |enable: bool, write_mask: vk::ColorComponentFlags| {
+            vk::PipelineColorBlendAttachmentState::default()
+                .color_write_mask(write_mask)
+                .blend_enable(enable)
+                .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .color_blend_op(vk::BlendOp::ADD)
+                .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+                .alpha_blend_op(vk::BlendOp::ADD)
+        };
+
+        let mut primary = blend_state(self.enable_blending, vk::ColorComponentFlags::RGBA);
+        if let Some((src, dst)) = self.color_factors {
+            primary = primary
+                .src_color_blend_factor(src)
+                .dst_color_blend_factor(dst)
+                .src_alpha_blend_factor(vk::BlendFactor::ZERO)
+                .dst_alpha_blend_factor(vk::BlendFactor::ONE);
+        }
+        let mut attachments = vec![primary];
+        let mut color_formats = vec![self.color_format];
+        for &(format, blending, write_mask) in &self.extra_color_attachments {
+            attachments.push(blend_state(blending, write_mask));
+            color_formats.push(format);
+        }
         let color_blend_state = vk::PipelineColorBlendStateCreateInfo::default()
             .logic_op_enable(false)
             .attachments(&attachments);
@@ -244,7 +325,6 @@ impl PipelineBuilder {
                 .name(c"main"),
         ];
 
-        let color_formats = [self.color_format];
         let mut rendering_info = vk::PipelineRenderingCreateInfo::default()
             .color_attachment_formats(&color_formats);
 
@@ -302,7 +382,7 @@ impl<V> Mesh<V> {
         ctx.bind_vertex_buffer(self.vertex_buffer.buffer);
         
         if let Some(ref indices) = self.index_buffer {
-            ctx.bind_index_buffer(indices.buffer);
+            ctx.bind_index_buffer(indices.buffer, indices.index_type);
             ctx.draw_indexed(indices.index_count, 1, 0, 0, 0);
         } else {
             ctx.draw(self.vertex_buffer.vertex_count, 1, 0, 0);
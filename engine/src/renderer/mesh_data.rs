@@ -0,0 +1,167 @@
+use anyhow::Result;
+use ash::vk;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{IndexBuffer, Mesh, VertexBuffer};
+
+/// Vertices the post-transform cache is assumed to hold
+const CACHE_SIZE: usize = 32;
+
+/// CPU-side indexed geometry, the step between a loader and a `Mesh`
+///
+/// Loaders produce one vertex per triangle corner; `from_triangles` merges the
+/// duplicates and `optimize` reorders triangles for the vertex cache before upload.
+pub struct MeshData<V> {
+    pub vertices: Vec<V>,
+    pub indices: Vec<u32>,
+}
+
+impl<V: bytemuck::Pod> MeshData<V> {
+    pub fn new(vertices: Vec<V>, indices: Vec<u32>) -> Self {
+        MeshData { vertices, indices }
+    }
+
+    /// Indexed geometry from an unindexed triangle list, sharing identical vertices
+    pub fn from_triangles(vertices: Vec<V>) -> Self {
+        let indices = (0..vertices.len() as u32).collect();
+        let mut data = MeshData { vertices, indices };
+        data.deduplicate();
+        data
+    }
+
+    /// Merge bitwise identical vertices and drop the unused ones
+    pub fn deduplicate(&mut self) {
+        let mut remap = vec![u32::MAX; self.vertices.len()];
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        let mut seen: HashMap<&[u8], u32> = HashMap::new();
+        for index in &mut self.indices {
+            let old = *index as usize;
+            if remap[old] == u32::MAX {
+                let vertex = &self.vertices[old];
+                remap[old] = *seen.entry(bytemuck::bytes_of(vertex)).or_insert_with(|| {
+                    vertices.push(*vertex);
+                    vertices.len() as u32 - 1
+                });
+            }
+            *index = remap[old];
+        }
+        self.vertices = vertices;
+    }
+
+    /// Deduplicate and reorder triangles for the vertex cache
+    pub fn optimize(&mut self) {
+        self.deduplicate();
+        optimize_vertex_cache(&mut self.indices, self.vertices.len());
+    }
+
+    /// Upload to the GPU, indices are 16-bit when the vertex count allows it
+    pub fn upload(
+        &self,
+        device: &Arc<ash::Device>,
+        physical_device: vk::PhysicalDevice,
+        instance: &ash::Instance,
+    ) -> Result<Mesh<V>> {
+        let vertex_buffer = VertexBuffer::new(device, physical_device, instance, &self.vertices)?;
+        let index_buffer = IndexBuffer::new(device, physical_device, instance, &self.indices)?;
+        Ok(Mesh::with_indices(vertex_buffer, index_buffer))
+    }
+}
+
+/// Score of a vertex for the next triangle (Forsyth's linear-speed heuristic)
+/// Recently used vertices and vertices with few remaining triangles score higher
+fn vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        // The last triangle's vertices, deliberately below the next few so strips don't
+        // always continue from the same edge
+        Some(position) if position < 3 => 0.75,
+        Some(position) => (1.0 - (position - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(1.5),
+        None => 0.0,
+    };
+    cache_score + 2.0 / (remaining_triangles as f32).sqrt()
+}
+
+/// Reorder the triangles of an indexed triangle list so shared vertices are reused
+/// while still in the post-transform cache
+pub fn optimize_vertex_cache(indices: &mut [u32], vertex_count: usize) {
+    let triangle_count = indices.len() / 3;
+    if triangle_count < 2 {
+        return;
+    }
+
+    // Triangles not yet emitted, per vertex
+    let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        for &vertex in corners {
+            adjacency[vertex as usize].push(triangle);
+        }
+    }
+    let mut cache_position: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_scores: Vec<f32> = adjacency.iter().map(|triangles| vertex_score(None, triangles.len())).collect();
+    let triangle_score = |scores: &[f32], triangle: usize| -> f32 {
+        indices[triangle * 3..triangle * 3 + 3].iter().map(|&v| scores[v as usize]).sum()
+    };
+    let mut triangle_scores: Vec<f32> = (0..triangle_count).map(|t| triangle_score(&vertex_scores, t)).collect();
+
+    let mut emitted = vec![false; triangle_count];
+    let mut output = Vec::with_capacity(triangle_count * 3);
+    // Most recently used first
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    // Triangles before this one were all emitted
+    let mut scan_start = 0;
+    let mut next = (0..triangle_count).max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]));
+
+    while let Some(triangle) = next {
+        emitted[triangle] = true;
+        let corners = [indices[triangle * 3], indices[triangle * 3 + 1], indices[triangle * 3 + 2]];
+        output.extend_from_slice(&corners);
+
+        for &vertex in &corners {
+            adjacency[vertex as usize].retain(|&t| t != triangle);
+            cache.retain(|&cached| cached != vertex);
+        }
+        for &vertex in corners.iter().rev() {
+            cache.insert(0, vertex);
+        }
+        let evicted = if cache.len() > CACHE_SIZE { cache.split_off(CACHE_SIZE) } else { Vec::new() };
+        for &vertex in &evicted {
+            cache_position[vertex as usize] = None;
+        }
+        for (position, &vertex) in cache.iter().enumerate() {
+            cache_position[vertex as usize] = Some(position);
+        }
+
+        // Only vertices that moved in the cache (or lost a triangle) change score
+        for &vertex in cache.iter().chain(&evicted) {
+            let vertex = vertex as usize;
+            vertex_scores[vertex] = vertex_score(cache_position[vertex], adjacency[vertex].len());
+        }
+        next = None;
+        let mut best_score = f32::MIN;
+        for &vertex in cache.iter().chain(&evicted) {
+            for &other in &adjacency[vertex as usize] {
+                let score = triangle_score(&vertex_scores, other);
+                triangle_scores[other] = score;
+                if score > best_score {
+                    best_score = score;
+                    next = Some(other);
+                }
+            }
+        }
+
+        // Nothing left around the cache, start over from the best remaining triangle
+        if next.is_none() {
+            while scan_start < triangle_count && emitted[scan_start] {
+                scan_start += 1;
+            }
+            next = (scan_start..triangle_count)
+                .filter(|&t| !emitted[t])
+                .max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]));
+        }
+    }
+
+    indices[..output.len()].copy_from_slice(&output);
+}
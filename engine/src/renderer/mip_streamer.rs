@@ -0,0 +1,338 @@
+use anyhow::{anyhow, Result};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use std::collections::HashMap;
+
+use super::{Texture, VulkanContext};
+use crate::tasks::{self, Task};
+
+/// Frames a replaced texture is kept alive, the GPU may still be sampling it
+const RETIRE_FRAMES: u64 = 3;
+/// Levels whose larger side is at most this many texels are resident once a texture was used
+const LOW_MIP_SIZE: u32 = 64;
+/// Finer levels loading at once, more requests wait for a later frame
+const MAX_LOADS: usize = 2;
+
+/// Levels `first..` of an image's mip chain, decoded on a worker
+struct Decoded {
+    width: u32,
+    height: u32,
+    first: u32,
+    levels: Vec<RgbaImage>,
+}
+
+struct StreamedMips {
+    width: u32,
+    height: u32,
+    level_count: u32,
+    /// Levels `resident..level_count`, `None` until the low mips are loaded
+    texture: Option<Texture>,
+    resident: u32,
+    /// Finest level requested in the frame of `last_used`
+    wanted: u32,
+    last_used: u64,
+    /// The low mips, uploaded again when the finer levels are evicted
+    low_mips: Vec<RgbaImage>,
+    loading: Option<Task<Result<Decoded>>>,
+    /// Decoding failed, finer levels aren't tried again
+    failed: bool,
+}
+
+impl StreamedMips {
+    fn resident_bytes(&self) -> u64 {
+        self.texture.as_ref().map_or(0, Texture::allocation_size)
+    }
+
+    /// First level of `low_mips`
+    fn low_level(&self) -> u32 {
+        self.level_count - self.low_mips.len() as u32
+    }
+
+    /// Bytes of RGBA8 texels in levels `first..`, an estimate of the device memory they need
+    fn bytes_from(&self, first: u32) -> u64 {
+        (first..self.level_count).map(|level| level_size(self.width, level) as u64 * level_size(self.height, level) as u64 * 4).sum()
+    }
+}
+
+/// Mip-mapped textures of which only the levels the camera needs are resident
+///
+/// The first `request` for a file decodes it on the task pool and uploads its small mips
+/// (at most `LOW_MIP_SIZE` texels), until then the texture isn't available. Each frame
+/// the finest level asked for, from the size the texture covers on screen (see
+/// `projected_size`), is compared with what is resident and the missing levels are decoded
+/// in the background and swapped in a few textures at a time. Finer levels are only
+/// loaded while they fit the budget; when the resident textures exceed it, those not
+/// used last frame, then those holding finer levels than wanted, drop back to their low
+/// mips.
+///
+/// Call `update` once per frame before the `request`s. A texture returned by `request`
+/// (and views or descriptor sets made from it) is only valid for that frame.
+pub struct MipStreamer {
+    budget: u64,
+    textures: HashMap<String, StreamedMips>,
+    resident_bytes: u64,
+    /// Replaced textures and the frame they were replaced in
+    retired: Vec<(u64, Texture)>,
+    frame: u64,
+}
+
+impl MipStreamer {
+    /// `budget` is in bytes of device memory
+    pub fn new(budget: u64) -> Self {
+        MipStreamer {
+            budget,
+            textures: HashMap::new(),
+            resident_bytes: 0,
+            retired: Vec::new(),
+            frame: 0,
+        }
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    /// Change the budget, dropping fine levels on the next `update` if they no longer fit
+    pub fn set_budget(&mut self, budget: u64) {
+        self.budget = budget;
+    }
+
+    /// Bytes of device memory held by resident levels (replaced ones waiting for the GPU excluded)
+    pub fn resident_bytes(&self) -> u64 {
+        self.resident_bytes
+    }
+
+    /// Finest resident level of a texture, 0 is full size
+    pub fn resident_level(&self, path: &str) -> Option<u32> {
+        self.textures.get(path).filter(|entry| entry.texture.is_some()).map(|entry| entry.resident)
+    }
+
+    /// Textures with levels still decoding
+    pub fn loading_count(&self) -> usize {
+        self.textures.values().filter(|entry| entry.loading.is_some()).count()
+    }
+
+    /// The texture at `path` as far as it is loaded, drawn `screen_size` pixels large
+    /// (its larger side); `None` until its low mips are resident
+    pub fn request(&mut self, path: &str, screen_size: f32) -> Option<&Texture> {
+        let frame = self.frame;
+        let entry = self.textures.entry(path.to_string()).or_insert_with(|| {
+            let owned = path.to_string();
+            StreamedMips {
+                width: 0,
+                height: 0,
+                level_count: 0,
+                texture: None,
+                resident: 0,
+                wanted: 0,
+                last_used: frame,
+                low_mips: Vec::new(),
+                loading: Some(tasks::spawn(move || decode(&owned, None))),
+                failed: false,
+            }
+        });
+        if entry.level_count > 0 {
+            let level = level_for(entry.width.max(entry.height), entry.level_count, screen_size);
+            entry.wanted = if entry.last_used == frame { entry.wanted.min(level) } else { level };
+        }
+        entry.last_used = frame;
+        entry.texture.as_ref()
+    }
+
+    /// Start a new frame: swap in decoded levels, start loading the ones requested last
+    /// frame and enforce the budget, destroying replaced textures the GPU is done with
+    pub fn update(&mut self, context: &VulkanContext) -> Result<()> {
+        self.frame += 1;
+        let frame = self.frame;
+        self.retired.retain(|(replaced, texture)| {
+            let done = frame - replaced > RETIRE_FRAMES;
+            if done {
+                texture.destroy(&context.device);
+            }
+            !done
+        });
+
+        self.finish_loads(context)?;
+        self.start_loads();
+        self.enforce_budget(context)
+    }
+
+    /// Upload the levels decoded since the last frame
+    fn finish_loads(&mut self, context: &VulkanContext) -> Result<()> {
+        for (path, entry) in &mut self.textures {
+            let Some(task) = entry.loading.take() else {
+                continue;
+            };
+            let decoded = match task.try_join() {
+                Ok(Ok(decoded)) => decoded,
+                Ok(Err(e)) => {
+                    log::warn!("{}", e);
+                    entry.failed = true;
+                    continue;
+                }
+                Err(task) => {
+                    entry.loading = Some(task);
+                    continue;
+                }
+            };
+            let texture = Texture::from_mips(
+                &decoded.levels,
+                &context.device,
+                &context.instance,
+                context.physical_device,
+                context.queue_family_indices[0],
+            )?;
+            if entry.level_count == 0 {
+                entry.width = decoded.width;
+                entry.height = decoded.height;
+                entry.level_count = mip_count(decoded.width, decoded.height);
+                entry.low_mips = decoded.levels;
+                entry.wanted = entry.low_level();
+            }
+            log::debug!("Streamed '{}' from mip {} ({}x{})", path, decoded.first, texture.width, texture.height);
+            self.resident_bytes += texture.allocation_size();
+            self.resident_bytes -= entry.resident_bytes();
+            if let Some(old) = entry.texture.replace(texture) {
+                self.retired.push((self.frame, old));
+            }
+            entry.resident = decoded.first;
+        }
+        Ok(())
+    }
+
+    /// Decode finer levels for textures used last frame that need them and fit the budget
+    fn start_loads(&mut self) {
+        let frame = self.frame;
+        let mut loads = self.loading_count();
+        // Budget left after what textures in use hold, the rest can be evicted for them
+        let mut available = self.budget.saturating_sub(
+            self.textures.values().filter(|entry| entry.last_used + 1 >= frame).map(StreamedMips::resident_bytes).sum(),
+        );
+
+        let mut candidates: Vec<(&String, &mut StreamedMips)> = self
+            .textures
+            .iter_mut()
+            .filter(|(_, entry)| {
+                entry.texture.is_some() && entry.loading.is_none() && !entry.failed && entry.last_used + 1 >= frame && entry.wanted < entry.resident
+            })
+            .collect();
+        // Most blurry first
+        candidates.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.resident - entry.wanted));
+        for (path, entry) in candidates {
+            if loads >= MAX_LOADS {
+                break;
+            }
+            let extra = entry.bytes_from(entry.wanted).saturating_sub(entry.resident_bytes());
+            if extra > available {
+                continue;
+            }
+            available -= extra;
+            loads += 1;
+            let owned = path.clone();
+            let first = entry.wanted;
+            entry.loading = Some(tasks::spawn(move || decode(&owned, Some(first))));
+        }
+    }
+
+    /// Drop textures back to their low mips until the resident ones fit the budget
+    fn enforce_budget(&mut self, context: &VulkanContext) -> Result<()> {
+        while self.resident_bytes > self.budget {
+            let frame = self.frame;
+            let victim = self
+                .textures
+                .iter()
+                .filter(|(_, entry)| entry.texture.is_some() && entry.resident < entry.low_level())
+                .filter(|(_, entry)| entry.last_used + 1 < frame || entry.resident < entry.wanted)
+                // Unused ones first, least recently used first among them
+                .min_by_key(|(_, entry)| (entry.last_used + 1 >= frame, entry.last_used))
+                .map(|(path, _)| path.clone());
+            let Some(path) = victim else {
+                log::warn!(
+                    "Streamed textures in use need {:.1} MB, over the {:.1} MB budget",
+                    self.resident_bytes as f64 / (1024.0 * 1024.0),
+                    self.budget as f64 / (1024.0 * 1024.0),
+                );
+                break;
+            };
+            let entry = self.textures.get_mut(&path).expect("Found above");
+            let texture = Texture::from_mips(
+                &entry.low_mips,
+                &context.device,
+                &context.instance,
+                context.physical_device,
+                context.queue_family_indices[0],
+            )?;
+            log::debug!("Evicted the fine mips of '{}'", path);
+            self.resident_bytes += texture.allocation_size();
+            self.resident_bytes -= entry.resident_bytes();
+            if let Some(old) = entry.texture.replace(texture) {
+                self.retired.push((self.frame, old));
+            }
+            entry.resident = entry.low_level();
+        }
+        Ok(())
+    }
+
+    /// Destroy every texture, the device must be idle
+    /// Levels still decoding are dropped when their task finishes.
+    pub fn destroy(&mut self, device: &ash::Device) {
+        for (_, entry) in self.textures.drain() {
+            if let Some(texture) = entry.texture {
+                texture.destroy(device);
+            }
+        }
+        for (_, texture) in self.retired.drain(..) {
+            texture.destroy(device);
+        }
+        self.resident_bytes = 0;
+    }
+}
+
+/// Pixels covered on screen by `world_size` units seen from `distance` through a perspective
+/// camera with vertical field of view `fov_y` (radians) in a viewport `viewport_height` tall
+pub fn projected_size(world_size: f32, distance: f32, fov_y: f32, viewport_height: f32) -> f32 {
+    world_size * viewport_height / (2.0 * distance.max(f32::EPSILON) * (fov_y / 2.0).tan())
+}
+
+/// Levels in the full mip chain of a `width` x `height` image
+fn mip_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+fn level_size(size: u32, level: u32) -> u32 {
+    (size >> level).max(1)
+}
+
+/// Coarsest level still at least `screen_size` texels across, level 0 when it needs more
+fn level_for(largest_side: u32, level_count: u32, screen_size: f32) -> u32 {
+    if screen_size <= 0.0 {
+        return level_count - 1;
+    }
+    let level = (largest_side as f32 / screen_size).log2().floor().max(0.0) as u32;
+    level.min(level_count - 1)
+}
+
+/// Decode an image and build its mip chain from level `first`, by default the low mips
+fn decode(path: &str, first: Option<u32>) -> Result<Decoded> {
+    let image = image::open(path).map_err(|e| anyhow!("Failed to load image '{}': {}", path, e))?.to_rgba8();
+    let (width, height) = image.dimensions();
+    let count = mip_count(width, height);
+    let low = (0..count).find(|&level| level_size(width.max(height), level) <= LOW_MIP_SIZE).unwrap_or(count - 1);
+    let first = first.unwrap_or(low).min(count - 1);
+
+    let mut levels = Vec::with_capacity((count - first) as usize);
+    let mut current = image;
+    for level in 0..count {
+        // Each level from the previous one, cheaper than from full size and close enough
+        let next = (level + 1 < count)
+            .then(|| imageops::resize(&current, level_size(width, level + 1), level_size(height, level + 1), FilterType::Triangle));
+        if level >= first {
+            levels.push(current);
+        }
+        match next {
+            Some(next) => current = next,
+            None => break,
+        }
+    }
+    Ok(Decoded { width, height, first, levels })
+}
@@ -1,6 +1,9 @@
 mod context;
 pub use context::VulkanContext;
 
+mod features;
+pub use features::DeviceFeatures;
+
 mod swapchain;
 pub use swapchain::Swapchain;
 
@@ -11,28 +14,62 @@ mod sync;
 pub use sync::FrameSynchronizer;
 
 mod buffer_utils;
+pub use buffer_utils::{MemoryCategory, MemoryStats};
 
 mod mesh;
 pub use mesh::{IndexBuffer, Mesh, PipelineBuilder, VertexBuffer};
 
+mod mesh_data;
+pub use mesh_data::{optimize_vertex_cache, MeshData};
+
 mod vertex;
-pub use vertex::{ColorVertex2D,ModelVertex3D, TexturedVertex2D, VertexFormat, PushConstants2D};
+pub use vertex::{ColorVertex2D,ModelVertex3D, TexturedVertex2D, VertexFormat};
+
+mod push_constants;
+pub use push_constants::{BackdropPushConstants, BloomPass, BloomPushConstants, FxaaPushConstants, Light2DPushConstants, PipelinePush, PushConstants2D, ScenePushConstants, TextPushConstants, MAX_PUSH_CONSTANTS_SIZE};
 
 mod pipeline_manager;
-pub use pipeline_manager::{PipelineId, PipelineManager};
+pub use pipeline_manager::{PipelineId, PipelineManager, ENTITY_ID_FORMAT};
 
 mod shader_manager;
 pub use shader_manager::{ShaderManager, ShaderId};
 
 mod font;
-pub use font::FontAtlas;
+pub use font::{Font, FontAtlas, FontManager};
 
 mod texture;
 pub use texture::Texture;
 
+mod texture_streamer;
+pub use texture_streamer::TextureStreamer;
+
+mod mip_streamer;
+pub use mip_streamer::{projected_size, MipStreamer};
+
 mod sampled_texture;
 pub use sampled_texture::{SampledTexture, SamplerConfig};
 
+mod error;
+pub use error::{Recovery, RendererError};
+
+mod backend;
+pub use backend::{
+    push_bytes, BufferHandle, BufferUsage, DrawCall, GraphicsBackend, PipelineHandle, TextureFormat, TextureHandle, TextureRegion,
+};
+
 mod renderer;
-pub use renderer::{RenderContext, Renderer};
-// pub use font::{Font, FontManager};
\ No newline at end of file
+pub use renderer::{ColorAttachment, RenderContext, RenderFrame, RenderStats, Renderer};
+
+mod camera;
+pub use camera::{Camera2D, ProjectionSpace};
+
+mod view;
+pub use view::View;
+
+mod render_queue;
+pub use render_queue::{DrawKey, RenderQueue, Renderable};
+
+mod debug_lines;
+pub use debug_lines::DebugLines;
+
+mod capture;
\ No newline at end of file
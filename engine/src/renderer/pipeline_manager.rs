@@ -5,10 +5,11 @@ use std::sync::Arc;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-use super::{PipelineBuilder, ShaderId, VertexFormat};
+use super::{BackdropPushConstants, BloomPushConstants, FxaaPushConstants, Light2DPushConstants, PipelineBuilder, PipelinePush, PushConstants2D, ScenePushConstants, ShaderId, TextPushConstants, VertexFormat};
 
 /// Predefined pipeline types in the engine
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+/// Ordered so draws can be sorted by pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter)]
 pub enum PipelineId {
     /// Basic colored triangle/geometry rendering
     BasicGeometry,
@@ -16,6 +17,44 @@ pub enum PipelineId {
     UI,
     /// Text rendering with font atlas
     Text,
+    /// Textured quads sampling a full color image (e.g. render targets)
+    Image,
+    /// Colored line segments for debug drawing and editor gizmos
+    DebugLines,
+    /// Colored line segments drawn with the UI, outside the scene pass
+    UILines,
+    /// Colored triangles over the entities of the scene pass, not pickable (trail ribbons)
+    Trails,
+    /// Entity quads of the editor scene pass, writing color and entity id
+    Scene,
+    /// One direction of a gaussian blur of an image, dimmed and desaturated (panel backdrops)
+    Backdrop,
+    /// Bright pass, downsampling and blur steps of the bloom chain (see `BloomPass`)
+    Bloom,
+    /// Bloom levels added onto the image they were taken from
+    BloomComposite,
+    /// Copy of an image with its edges smoothed (FXAA)
+    Fxaa,
+    /// Shadow geometry multiplied into a 2D light's shadow mask
+    Shadow2D,
+    /// One 2D point light added into the light map, through its shadow mask
+    Light2D,
+    /// Light map multiplied onto the image it lights
+    LightComposite,
+}
+
+/// Format of the entity id attachment of the scene pass (see `Renderer::pick`)
+pub const ENTITY_ID_FORMAT: vk::Format = vk::Format::R32_UINT;
+
+/// How a pipeline treats the entity id attachment of the scene pass
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EntityIds {
+    /// Not used in the scene pass
+    None,
+    /// Writes the id of the drawn entity (second fragment output)
+    Write,
+    /// Used in the scene pass without writing ids (drawn over entities but not pickable)
+    Keep,
 }
 
 /// Static metadata for pipeline configuration
@@ -25,6 +64,10 @@ struct PipelineMeta {
     vertex_format: VertexFormat,
     blend_enabled: bool,
     cull_mode: vk::CullModeFlags,
+    topology: vk::PrimitiveTopology,
+    /// Range of the pipeline's `PipelinePush` type
+    push_constants: vk::PushConstantRange,
+    entity_ids: EntityIds,
 }
 
 impl PipelineId {
@@ -36,6 +79,9 @@ impl PipelineId {
                 vertex_format: VertexFormat::ColorVertex2D,
                 blend_enabled: false,
                 cull_mode: vk::CullModeFlags::BACK,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: PushConstants2D::range(),
+                entity_ids: EntityIds::None,
             },
             PipelineId::UI => PipelineMeta {
                 vertex_shader: ShaderId::TriangleVertex,
@@ -43,6 +89,9 @@ impl PipelineId {
                 vertex_format: VertexFormat::ColorVertex2D,
                 blend_enabled: true,
                 cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: PushConstants2D::range(),
+                entity_ids: EntityIds::None,
             },
             PipelineId::Text => PipelineMeta {
                 vertex_shader: ShaderId::TextVertex,
@@ -50,14 +99,139 @@ impl PipelineId {
                 vertex_format: VertexFormat::TexturedVertex2D,
                 blend_enabled: true,
                 cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: TextPushConstants::range(),
+                entity_ids: EntityIds::None,
+            },
+            PipelineId::Image => PipelineMeta {
+                vertex_shader: ShaderId::ImageVertex,
+                fragment_shader: ShaderId::ImageFrag,
+                vertex_format: VertexFormat::TexturedVertex2D,
+                blend_enabled: true,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: PushConstants2D::range(),
+                entity_ids: EntityIds::None,
+            },
+            PipelineId::DebugLines => PipelineMeta {
+                vertex_shader: ShaderId::TriangleVertex,
+                fragment_shader: ShaderId::TriangleFrag,
+                vertex_format: VertexFormat::ColorVertex2D,
+                blend_enabled: false,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::LINE_LIST,
+                push_constants: PushConstants2D::range(),
+                entity_ids: EntityIds::Keep,
+            },
+            PipelineId::UILines => PipelineMeta {
+                vertex_shader: ShaderId::TriangleVertex,
+                fragment_shader: ShaderId::TriangleFrag,
+                vertex_format: VertexFormat::ColorVertex2D,
+                blend_enabled: false,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::LINE_LIST,
+                push_constants: PushConstants2D::range(),
+                entity_ids: EntityIds::None,
+            },
+            PipelineId::Trails => PipelineMeta {
+                vertex_shader: ShaderId::TriangleVertex,
+                fragment_shader: ShaderId::TriangleFrag,
+                vertex_format: VertexFormat::ColorVertex2D,
+                blend_enabled: false,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: PushConstants2D::range(),
+                entity_ids: EntityIds::Keep,
+            },
+            PipelineId::Scene => PipelineMeta {
+                vertex_shader: ShaderId::SceneVertex,
+                fragment_shader: ShaderId::SceneFrag,
+                vertex_format: VertexFormat::ColorVertex2D,
+                blend_enabled: true,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: ScenePushConstants::range(),
+                entity_ids: EntityIds::Write,
+            },
+            PipelineId::Backdrop => PipelineMeta {
+                vertex_shader: ShaderId::BackdropVertex,
+                fragment_shader: ShaderId::BackdropFrag,
+                vertex_format: VertexFormat::TexturedVertex2D,
+                blend_enabled: true,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: BackdropPushConstants::range(),
+                entity_ids: EntityIds::None,
+            },
+            PipelineId::Bloom => PipelineMeta {
+                vertex_shader: ShaderId::BloomVertex,
+                fragment_shader: ShaderId::BloomFrag,
+                vertex_format: VertexFormat::TexturedVertex2D,
+                blend_enabled: false,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: BloomPushConstants::range(),
+                entity_ids: EntityIds::None,
+            },
+            PipelineId::BloomComposite => PipelineMeta {
+                vertex_shader: ShaderId::BloomVertex,
+                fragment_shader: ShaderId::BloomFrag,
+                vertex_format: VertexFormat::TexturedVertex2D,
+                blend_enabled: true,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: BloomPushConstants::range(),
+                entity_ids: EntityIds::None,
+            },
+            PipelineId::Fxaa => PipelineMeta {
+                vertex_shader: ShaderId::FxaaVertex,
+                fragment_shader: ShaderId::FxaaFrag,
+                vertex_format: VertexFormat::TexturedVertex2D,
+                blend_enabled: false,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: FxaaPushConstants::range(),
+                entity_ids: EntityIds::None,
+            },
+            PipelineId::Shadow2D => PipelineMeta {
+                vertex_shader: ShaderId::TriangleVertex,
+                fragment_shader: ShaderId::TriangleFrag,
+                vertex_format: VertexFormat::ColorVertex2D,
+                blend_enabled: true,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: PushConstants2D::range(),
+                entity_ids: EntityIds::None,
+            },
+            PipelineId::Light2D => PipelineMeta {
+                vertex_shader: ShaderId::Light2DVertex,
+                fragment_shader: ShaderId::Light2DFrag,
+                vertex_format: VertexFormat::TexturedVertex2D,
+                blend_enabled: true,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: Light2DPushConstants::range(),
+                entity_ids: EntityIds::None,
+            },
+            PipelineId::LightComposite => PipelineMeta {
+                vertex_shader: ShaderId::ImageVertex,
+                fragment_shader: ShaderId::ImageFrag,
+                vertex_format: VertexFormat::TexturedVertex2D,
+                blend_enabled: true,
+                cull_mode: vk::CullModeFlags::NONE,
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                push_constants: PushConstants2D::range(),
+                entity_ids: EntityIds::None,
             },
         }
     }
 
-    /// Build the pipeline from metadata
-    pub fn build(&self, device: &Arc<Device>) -> Result<(vk::Pipeline, vk::PipelineLayout, Option<vk::DescriptorSetLayout>)> {
+    /// Build the pipeline from metadata for attachments of `color_format`
+    /// Scene pass pipelines also declare the entity id attachment after the color one
+    pub fn build(&self, device: &Arc<Device>, color_format: vk::Format) -> Result<(vk::Pipeline, vk::PipelineLayout, Option<vk::DescriptorSetLayout>)> {
         let meta = self.meta();
-        
+        self.validate_push_constants(&meta)?;
+
         let vert_code = meta.vertex_shader.load_shader_bytes()?;
         let frag_code = meta.fragment_shader.load_shader_bytes()?;
 
@@ -67,14 +241,25 @@ impl PipelineId {
 
         let mut builder = PipelineBuilder::new(vert_code, frag_code)
             .vertex_input(vertex_bindings, vertex_attributes)
-            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .topology(meta.topology)
             .polygon_mode(vk::PolygonMode::FILL)
             .cull_mode(meta.cull_mode, vk::FrontFace::COUNTER_CLOCKWISE)
-            .color_format(vk::Format::B8G8R8A8_SRGB)
-            .blending(meta.blend_enabled);
+            .color_format(color_format)
+            .blending(meta.blend_enabled)
+            .push_constant_range(meta.push_constants);
+        builder = match self {
+            PipelineId::BloomComposite | PipelineId::Light2D => builder.additive_blending(),
+            PipelineId::Shadow2D | PipelineId::LightComposite => builder.multiply_blending(),
+            _ => builder,
+        };
+        builder = match meta.entity_ids {
+            EntityIds::None => builder,
+            EntityIds::Write => builder.color_attachment(ENTITY_ID_FORMAT, false),
+            EntityIds::Keep => builder.unwritten_color_attachment(ENTITY_ID_FORMAT),
+        };
 
-        // Add descriptor sets for Text pipeline texture sampling
-        let descriptor_set_layout = if *self == PipelineId::Text {
+        // Add descriptor sets for texture sampling pipelines
+        let descriptor_set_layout = if matches!(self, PipelineId::Text | PipelineId::Image | PipelineId::Backdrop | PipelineId::Bloom | PipelineId::BloomComposite | PipelineId::Fxaa | PipelineId::Light2D | PipelineId::LightComposite) {
             let bindings = vec![
                 vk::DescriptorSetLayoutBinding::default()
                     .binding(0)
@@ -102,6 +287,36 @@ impl PipelineId {
         Ok((pipeline, layout, descriptor_set_layout))
     }
 
+    /// Check that every shader's push constant block matches the declared range
+    fn validate_push_constants(&self, meta: &PipelineMeta) -> Result<()> {
+        let range = meta.push_constants;
+        let shaders = [
+            (&meta.vertex_shader, vk::ShaderStageFlags::VERTEX),
+            (&meta.fragment_shader, vk::ShaderStageFlags::FRAGMENT),
+        ];
+        for (shader, stage) in shaders {
+            let Some(size) = shader.push_constant_size()? else {
+                continue;
+            };
+            if !range.stage_flags.contains(stage) {
+                anyhow::bail!("{:?}: {:?} reads push constants but the range is not visible to {:?}", self, shader, stage);
+            }
+            // Shader blocks start at offset 0, members before the range are padding
+            if size != range.offset + range.size {
+                anyhow::bail!(
+                    "{:?}: {:?} declares a {} byte push constant block, the pipeline pushes {} bytes at offset {}",
+                    self, shader, size, range.size, range.offset
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Push constant range of the pipeline's `PipelinePush` type
+    pub(crate) fn push_range(&self) -> vk::PushConstantRange {
+        self.meta().push_constants
+    }
+
     pub fn all() -> impl Iterator<Item = PipelineId> {
         PipelineId::iter()
     }
@@ -110,15 +325,18 @@ impl PipelineId {
 /// Manages all graphics pipelines with enum-based access
 pub struct PipelineManager {
     device: Arc<Device>,
+    /// Color attachment format every pipeline is built for (the swapchain format)
+    color_format: vk::Format,
     pipelines: HashMap<PipelineId, vk::Pipeline>,
     layouts: HashMap<PipelineId, vk::PipelineLayout>,
     descriptor_set_layouts: HashMap<PipelineId, vk::DescriptorSetLayout>,
 }
 
 impl PipelineManager {
-    pub fn new(device: Arc<Device>) -> Self {
+    pub fn new(device: Arc<Device>, color_format: vk::Format) -> Self {
         Self {
             device,
+            color_format,
             pipelines: HashMap::new(),
             layouts: HashMap::new(),
             descriptor_set_layouts: HashMap::new(),
@@ -139,7 +357,7 @@ impl PipelineManager {
             return Ok(());
         }
 
-        let (pipeline, layout, descriptor_set_layout) = id.build(&self.device)?;
+        let (pipeline, layout, descriptor_set_layout) = id.build(&self.device, self.color_format)?;
         self.pipelines.insert(id, pipeline);
         self.layouts.insert(id, layout);
         if let Some(dsl) = descriptor_set_layout {
@@ -157,6 +375,10 @@ impl PipelineManager {
         Ok(self.pipelines[&id])
     }
 
+    pub fn color_format(&self) -> vk::Format {
+        self.color_format
+    }
+
     /// Get a pipeline layout
     pub fn get_layout(&self, id: PipelineId) -> Option<vk::PipelineLayout> {
         self.layouts.get(&id).copied()
@@ -0,0 +1,320 @@
+use ash::vk;
+use glam::Mat4;
+
+/// Largest push constant block a pipeline may declare
+/// The spec only guarantees 128 bytes, the blocks the pipelines actually declare are checked
+/// against the device limit when the renderer is created.
+pub const MAX_PUSH_CONSTANTS_SIZE: u32 = 256;
+
+/// Size of a push constant block, failing the build if Vulkan would reject it
+const fn push_size<T>() -> u32 {
+    let size = std::mem::size_of::<T>();
+    assert!(size.is_multiple_of(4), "push constant blocks must be a multiple of 4 bytes");
+    assert!(size <= MAX_PUSH_CONSTANTS_SIZE as usize, "push constant block is too large");
+    size as u32
+}
+
+/// Typed push constant block of a pipeline, pushed with `RenderContext::push`
+///
+/// The struct must be `#[repr(C)]` and match the shader's `push_constant` block,
+/// `PipelineId::build` compares `SIZE` with the block size declared in the shaders.
+pub trait PipelinePush: Copy + 'static {
+    /// Shader stages that read the block, e.g. VERTEX | FRAGMENT for a tint read by the fragment shader
+    const STAGES: vk::ShaderStageFlags;
+    /// Byte offset of the block in the pipeline's push constant space
+    const OFFSET: u32 = 0;
+    /// Evaluated at compile time for every pushed type
+    const SIZE: u32 = push_size::<Self>();
+
+    /// Range to declare in the pipeline layout
+    fn range() -> vk::PushConstantRange {
+        vk::PushConstantRange::default().stage_flags(Self::STAGES).offset(Self::OFFSET).size(Self::SIZE)
+    }
+}
+
+/// Push constants of every 2D pipeline (projection + transform matrices + color modulation + opacity)
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct PushConstants2D {
+    pub projection: Mat4,
+    pub transform: Mat4,
+    pub color_modulation: [f32; 3],  // RGB multiplier (e.g., [0.8, 0.8, 0.8] = 20% darker)
+    pub opacity: f32,  // Alpha multiplier, packed into the vec3's padding like the shader block
+}
+
+impl PushConstants2D {
+    /// Block without color modulation, fully opaque
+    pub fn new(projection: Mat4, transform: Mat4) -> Self {
+        PushConstants2D {
+            projection,
+            transform,
+            color_modulation: [1.0, 1.0, 1.0],
+            opacity: 1.0,
+        }
+    }
+
+    pub fn with_modulation(mut self, color: [f32; 3]) -> Self {
+        self.color_modulation = color;
+        self
+    }
+
+    /// Only visible through pipelines with blending (UI, Text, Image)
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+}
+
+impl PipelinePush for PushConstants2D {
+    const STAGES: vk::ShaderStageFlags = vk::ShaderStageFlags::VERTEX;
+}
+
+/// Push constants of the text pipeline, `PushConstants2D` plus how glyph coverage is blended
+/// Read by the fragment shader too.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct TextPushConstants {
+    pub projection: Mat4,
+    pub transform: Mat4,
+    pub color_modulation: [f32; 3],
+    pub opacity: f32,
+    /// Gamma coverage is corrected for, 1.0 blends it as it is (see `Renderer::text_gamma`)
+    pub gamma: f32,
+    /// Non-zero samples coverage per color channel for LCD subpixel rendering
+    pub lcd: u32,
+    _padding: [f32; 2],
+}
+
+impl TextPushConstants {
+    pub fn new(projection: Mat4, transform: Mat4) -> Self {
+        TextPushConstants {
+            projection,
+            transform,
+            color_modulation: [1.0, 1.0, 1.0],
+            opacity: 1.0,
+            gamma: 1.0,
+            lcd: 0,
+            _padding: [0.0; 2],
+        }
+    }
+
+    pub fn with_modulation(mut self, color: [f32; 3]) -> Self {
+        self.color_modulation = color;
+        self
+    }
+
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn with_gamma(mut self, gamma: f32) -> Self {
+        self.gamma = gamma;
+        self
+    }
+
+    pub fn with_lcd(mut self, lcd: bool) -> Self {
+        self.lcd = lcd as u32;
+        self
+    }
+}
+
+impl PipelinePush for TextPushConstants {
+    const STAGES: vk::ShaderStageFlags = vk::ShaderStageFlags::from_raw(
+        vk::ShaderStageFlags::VERTEX.as_raw() | vk::ShaderStageFlags::FRAGMENT.as_raw(),
+    );
+}
+
+/// Push constants of the backdrop pipeline, `PushConstants2D` plus one blur pass and the
+/// color changes applied after it. Read by the fragment shader too.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct BackdropPushConstants {
+    pub projection: Mat4,
+    pub transform: Mat4,
+    pub color_modulation: [f32; 3],
+    pub opacity: f32,
+    /// One texel along the blur direction in uv units, e.g. `[1.0 / width, 0.0]`
+    pub texel_step: [f32; 2],
+    /// Standard deviation of the blur in texels, 0 doesn't blur
+    pub sigma: f32,
+    /// 0 keeps the brightness, 1 is black
+    pub dim: f32,
+    /// 1 keeps the colors, 0 is grayscale
+    pub saturation: f32,
+    _padding: [f32; 3],
+}
+
+impl BackdropPushConstants {
+    /// Block copying the image as it is
+    pub fn new(projection: Mat4, transform: Mat4) -> Self {
+        BackdropPushConstants {
+            projection,
+            transform,
+            color_modulation: [1.0, 1.0, 1.0],
+            opacity: 1.0,
+            texel_step: [0.0; 2],
+            sigma: 0.0,
+            dim: 0.0,
+            saturation: 1.0,
+            _padding: [0.0; 3],
+        }
+    }
+
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn with_blur(mut self, texel_step: [f32; 2], sigma: f32) -> Self {
+        self.texel_step = texel_step;
+        self.sigma = sigma;
+        self
+    }
+
+    pub fn with_color(mut self, dim: f32, saturation: f32) -> Self {
+        self.dim = dim;
+        self.saturation = saturation;
+        self
+    }
+}
+
+impl PipelinePush for BackdropPushConstants {
+    const STAGES: vk::ShaderStageFlags = vk::ShaderStageFlags::from_raw(
+        vk::ShaderStageFlags::VERTEX.as_raw() | vk::ShaderStageFlags::FRAGMENT.as_raw(),
+    );
+}
+
+/// Step of the bloom chain a `PipelineId::Bloom` draw performs, read by the fragment shader
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BloomPass {
+    /// Downsample keeping only what is brighter than the threshold
+    Prefilter = 0,
+    /// Downsample to half size
+    Downsample = 1,
+    /// One direction of a gaussian blur
+    Blur = 2,
+    /// Output the texture scaled by the intensity, for `PipelineId::BloomComposite`
+    Composite = 3,
+}
+
+/// Push constants of the bloom pipelines, a quad transform plus the step to run
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct BloomPushConstants {
+    pub projection: Mat4,
+    pub transform: Mat4,
+    /// One source texel in uv units, only along the blur direction for `BloomPass::Blur`
+    pub texel_step: [f32; 2],
+    /// Brightness (largest channel) where the prefilter starts keeping colors
+    pub threshold: f32,
+    /// Width of the soft transition below the threshold
+    pub knee: f32,
+    /// Scale of the composited color
+    pub intensity: f32,
+    pub pass: BloomPass,
+    _padding: [f32; 2],
+}
+
+impl BloomPushConstants {
+    pub fn new(projection: Mat4, transform: Mat4, pass: BloomPass, texel_step: [f32; 2]) -> Self {
+        BloomPushConstants { projection, transform, texel_step, threshold: 0.0, knee: 0.0, intensity: 1.0, pass, _padding: [0.0; 2] }
+    }
+
+    pub fn with_threshold(mut self, threshold: f32, knee: f32) -> Self {
+        self.threshold = threshold;
+        self.knee = knee;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+}
+
+impl PipelinePush for BloomPushConstants {
+    const STAGES: vk::ShaderStageFlags = vk::ShaderStageFlags::from_raw(
+        vk::ShaderStageFlags::VERTEX.as_raw() | vk::ShaderStageFlags::FRAGMENT.as_raw(),
+    );
+}
+
+/// Push constants of the FXAA pipeline, a quad transform plus the source's texel size
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FxaaPushConstants {
+    pub projection: Mat4,
+    pub transform: Mat4,
+    /// One source texel in uv units
+    pub texel_size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+impl FxaaPushConstants {
+    pub fn new(projection: Mat4, transform: Mat4, texel_size: [f32; 2]) -> Self {
+        FxaaPushConstants { projection, transform, texel_size, _padding: [0.0; 2] }
+    }
+}
+
+impl PipelinePush for FxaaPushConstants {
+    const STAGES: vk::ShaderStageFlags = vk::ShaderStageFlags::from_raw(
+        vk::ShaderStageFlags::VERTEX.as_raw() | vk::ShaderStageFlags::FRAGMENT.as_raw(),
+    );
+}
+
+/// Push constants of the 2D light pipeline, the light's quad plus its color and shadow mask
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Light2DPushConstants {
+    pub projection: Mat4,
+    pub transform: Mat4,
+    /// Linear color of the light
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// One light map texel in uv units, to find the shadow mask texel under a fragment
+    pub texel_size: [f32; 2],
+    /// Non-zero samples the shadow mask, 0 lights everything in reach
+    pub shadowed: u32,
+    _padding: f32,
+}
+
+impl Light2DPushConstants {
+    pub fn new(projection: Mat4, transform: Mat4, color: [f32; 3], intensity: f32) -> Self {
+        Light2DPushConstants { projection, transform, color, intensity, texel_size: [0.0; 2], shadowed: 0, _padding: 0.0 }
+    }
+
+    /// Multiply the light by the shadow mask, which has `texel_size` like the light map
+    pub fn with_shadows(mut self, texel_size: [f32; 2]) -> Self {
+        self.texel_size = texel_size;
+        self.shadowed = 1;
+        self
+    }
+}
+
+impl PipelinePush for Light2DPushConstants {
+    const STAGES: vk::ShaderStageFlags = vk::ShaderStageFlags::from_raw(
+        vk::ShaderStageFlags::VERTEX.as_raw() | vk::ShaderStageFlags::FRAGMENT.as_raw(),
+    );
+}
+
+/// Push constants of the editor scene pass, `PushConstants2D` plus the drawn entity
+/// The id is written to the entity id attachment for picking, 0 means no entity
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ScenePushConstants {
+    pub projection: Mat4,
+    pub transform: Mat4,
+    pub color_modulation: [f32; 3],
+    pub entity_id: u32,  // Packed into the vec3's padding, like the shader block
+}
+
+impl ScenePushConstants {
+    pub fn new(projection: Mat4, transform: Mat4, color_modulation: [f32; 3], entity_id: u32) -> Self {
+        ScenePushConstants { projection, transform, color_modulation, entity_id }
+    }
+}
+
+impl PipelinePush for ScenePushConstants {
+    const STAGES: vk::ShaderStageFlags = vk::ShaderStageFlags::VERTEX;
+}
@@ -0,0 +1,148 @@
+use anyhow::Result;
+use ash::vk;
+use glam::Mat4;
+
+use super::{PipelineId, ProjectionSpace, RenderContext, Renderer};
+
+/// 64-bit sort key of a draw, smaller keys are recorded first
+///
+/// Bits from most to least significant:
+/// - layer (8): lower layers draw underneath
+/// - translucent (1): opaque draws before blended ones
+/// - opaque: pipeline (7), material (16), depth (32) front to back
+/// - translucent: depth (32) back to front, pipeline (7), material (16)
+///
+/// Opaque draws are grouped by pipeline and material to minimize rebinds, blended
+/// draws are ordered by depth first so they composite correctly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DrawKey(pub u64);
+
+impl DrawKey {
+    pub fn opaque(layer: i8, pipeline: PipelineId, material: u16, depth: f32) -> Self {
+        DrawKey(
+            Self::layer_bits(layer)
+                | (pipeline as u64 & 0x7F) << 48
+                | (material as u64) << 32
+                | depth_bits(depth) as u64,
+        )
+    }
+
+    /// `depth` grows away from the camera, the farthest draw is recorded first
+    pub fn translucent(layer: i8, pipeline: PipelineId, material: u16, depth: f32) -> Self {
+        DrawKey(
+            Self::layer_bits(layer)
+                | 1 << 55
+                | (!depth_bits(depth) as u64) << 23
+                | (pipeline as u64 & 0x7F) << 16
+                | material as u64,
+        )
+    }
+
+    fn layer_bits(layer: i8) -> u64 {
+        ((layer as u8 ^ 0x80) as u64) << 56
+    }
+}
+
+/// Map a float to bits that sort in the same order as the float
+fn depth_bits(depth: f32) -> u32 {
+    let bits = depth.to_bits();
+    if bits & 0x8000_0000 != 0 { !bits } else { bits | 0x8000_0000 }
+}
+
+/// A draw the render queue can order and record
+pub trait Renderable {
+    /// Pipeline the draw needs, bound by the queue
+    fn pipeline(&self) -> PipelineId;
+
+    /// Position of the draw in the frame, see `DrawKey`
+    fn sort_key(&self) -> DrawKey {
+        DrawKey::opaque(0, self.pipeline(), 0, 0.0)
+    }
+
+    /// Descriptor set bound at set 0 before `record`, skipped if the previous draw used the same one
+    fn descriptor_set(&self) -> Option<vk::DescriptorSet> {
+        None
+    }
+
+    /// Record the draw, the pipeline and descriptor set are already bound with `layout`
+    /// `projection` is the queue's projection space resolved for this frame
+    fn record(&self, ctx: &RenderContext, layout: vk::PipelineLayout, projection: Mat4) -> Result<()>;
+}
+
+/// Collects renderables for one pass and records them sorted by `DrawKey`
+///
+/// Keys are taken at submission and sorted once per flush. Pipelines and descriptor
+/// sets are only bound when they change between consecutive draws, so callers never
+/// bind them themselves. Draws with equal keys keep their submission order.
+/// Every draw of a queue shares its projection space, UI pixels unless set otherwise.
+pub struct RenderQueue<'a> {
+    items: Vec<(DrawKey, &'a dyn Renderable)>,
+    space: ProjectionSpace,
+}
+
+impl<'a> RenderQueue<'a> {
+    pub fn new() -> Self {
+        Self::in_space(ProjectionSpace::Ui)
+    }
+
+    /// Queue whose draws are positioned in `space` (e.g. `World` for the game camera)
+    pub fn in_space(space: ProjectionSpace) -> Self {
+        RenderQueue { items: Vec::new(), space }
+    }
+
+    pub fn space(&self) -> ProjectionSpace {
+        self.space
+    }
+
+    pub fn submit(&mut self, renderable: &'a dyn Renderable) {
+        self.items.push((renderable.sort_key(), renderable));
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Record and clear all submitted renderables
+    pub fn flush(&mut self, ctx: &RenderContext, renderer: &mut Renderer) -> Result<()> {
+        crate::profile_scope!("render_queue");
+        self.items.sort_by_key(|(key, _)| *key);
+        let projection = renderer.projection_for(self.space);
+
+        let mut bound: Option<(PipelineId, vk::PipelineLayout)> = None;
+        let mut bound_set = None;
+        for (_, item) in self.items.drain(..) {
+            let id = item.pipeline();
+            let layout = match bound {
+                Some((bound_id, layout)) if bound_id == id => layout,
+                _ => {
+                    let pipeline = renderer.get_pipeline(id)?;
+                    let layout = renderer.get_pipeline_layout(id)
+                        .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for {:?} pipeline", id))?;
+                    ctx.bind_pipeline(pipeline);
+                    bound = Some((id, layout));
+                    // Sets may be disturbed by a layout change, bind again
+                    bound_set = None;
+                    layout
+                }
+            };
+            if let Some(set) = item.descriptor_set() {
+                if bound_set != Some(set) {
+                    ctx.bind_descriptor_set_at(layout, 0, set);
+                    bound_set = Some(set);
+                }
+            }
+            item.record(ctx, layout, projection)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for RenderQueue<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
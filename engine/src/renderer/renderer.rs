@@ -1,41 +1,124 @@
-use crate::renderer::{CommandPool, FrameSynchronizer, PipelineManager, Swapchain, VulkanContext};
+use crate::assets::Handle;
+#[cfg(feature = "ecs")]
+use crate::ecs::EntityId;
+use crate::math::{Color, Rect};
+use crate::renderer::{
+    Camera2D, CommandPool, Font, FontAtlas, FontManager, FrameSynchronizer, ProjectionSpace, View, MemoryStats, PipelineManager, PipelinePush, Recovery, RendererError, Swapchain, Texture, VulkanContext,
+};
+#[cfg(feature = "ecs")]
+use crate::renderer::ENTITY_ID_FORMAT;
+use super::backend::{BackendResources, BufferHandle, BufferUsage, DrawCall, GraphicsBackend, PipelineHandle, TextureFormat, TextureHandle, TextureRegion};
+use super::buffer_utils::{create_buffer_with_data, track_free, MemoryCategory};
+use super::capture::FrameCapture;
 use anyhow::Result;
-use ash::{vk, Device};
+use ash::{ext::debug_utils, vk, Device};
+use std::cell::Cell;
+use std::ffi::CString;
+use std::rc::Rc;
 use std::sync::Arc;
 
+/// Per-frame counters gathered while recording commands
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub triangles: u32,
+}
+
+/// Color attachment of a rendering pass and how it starts out
+#[derive(Clone, Copy)]
+pub struct ColorAttachment {
+    pub view: vk::ImageView,
+    pub load_op: vk::AttachmentLoadOp,
+    /// Only used with `AttachmentLoadOp::CLEAR`
+    pub clear_value: vk::ClearColorValue,
+}
+
+impl ColorAttachment {
+    /// Cleared to `color` (float and normalized formats)
+    pub fn clear(view: vk::ImageView, color: Color) -> Self {
+        Self::clear_value(view, vk::ClearColorValue { float32: color.to_array() })
+    }
+
+    /// Cleared to a raw value, e.g. `uint32` for integer formats
+    pub fn clear_value(view: vk::ImageView, clear_value: vk::ClearColorValue) -> Self {
+        ColorAttachment { view, load_op: vk::AttachmentLoadOp::CLEAR, clear_value }
+    }
+
+    /// Keeps the existing contents
+    pub fn load(view: vk::ImageView) -> Self {
+        ColorAttachment {
+            view,
+            load_op: vk::AttachmentLoadOp::LOAD,
+            clear_value: vk::ClearColorValue::default(),
+        }
+    }
+}
+
 /// High-level rendering context for command recording
 pub struct RenderContext {
     device: Arc<Device>,
     cmd_buffer: vk::CommandBuffer,
     extent: vk::Extent2D,
-
+    stats: Rc<Cell<RenderStats>>,
+    debug_utils: Option<debug_utils::Device>,
 }
 
 impl RenderContext {
-    fn new(device: Arc<Device>, cmd_buffer: vk::CommandBuffer, extent: vk::Extent2D) -> Self {
+    fn new(
+        device: Arc<Device>,
+        cmd_buffer: vk::CommandBuffer,
+        extent: vk::Extent2D,
+        stats: Rc<Cell<RenderStats>>,
+        debug_utils: Option<debug_utils::Device>,
+    ) -> Self {
         RenderContext {
             device,
             cmd_buffer,
             extent,
+            stats,
+            debug_utils,
         }
     }
 
+    /// Count a draw call (assumes triangle list topology)
+    fn record_draw(&self, vertex_count: u32, instance_count: u32) {
+        let mut stats = self.stats.get();
+        stats.draw_calls += 1;
+        stats.triangles += (vertex_count / 3) * instance_count;
+        self.stats.set(stats);
+    }
+
     /// Begin a rendering pass with a color attachment
-    pub fn begin_rendering(&self, image_view: vk::ImageView, clear_color: [f32; 4]) {
-        unsafe {
-            let color_attachment = vk::RenderingAttachmentInfo::default()
-                .image_view(image_view)
-                .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-                .load_op(vk::AttachmentLoadOp::CLEAR)
-                .store_op(vk::AttachmentStoreOp::STORE)
-                .clear_value(vk::ClearValue {
-                    color: vk::ClearColorValue { float32: clear_color },
-                });
+    pub fn begin_rendering(&self, image_view: vk::ImageView, clear_color: Color) {
+        self.begin_rendering_attachments(&[ColorAttachment::clear(image_view, clear_color)]);
+    }
 
+    /// Begin a rendering pass that keeps the existing attachment contents
+    pub fn resume_rendering(&self, image_view: vk::ImageView) {
+        self.begin_rendering_attachments(&[ColorAttachment::load(image_view)]);
+    }
+
+    /// Begin a rendering pass writing to several color attachments at once
+    /// Pipelines used in the pass must declare the same number of attachments with matching
+    /// formats (see `PipelineBuilder::color_attachment`).
+    pub fn begin_rendering_attachments(&self, attachments: &[ColorAttachment]) {
+        let color_attachments: Vec<_> = attachments
+            .iter()
+            .map(|attachment| {
+                vk::RenderingAttachmentInfo::default()
+                    .image_view(attachment.view)
+                    .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .load_op(attachment.load_op)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .clear_value(vk::ClearValue { color: attachment.clear_value })
+            })
+            .collect();
+
+        unsafe {
             let rendering_info = vk::RenderingInfo::default()
                 .render_area(vk::Rect2D::default().extent(self.extent))
                 .layer_count(1)
-                .color_attachments(std::slice::from_ref(&color_attachment));
+                .color_attachments(&color_attachments);
 
             self.device.cmd_begin_rendering(self.cmd_buffer, &rendering_info);
         }
@@ -51,6 +134,28 @@ impl RenderContext {
         }
     }
 
+    /// Open a named region of commands, shown as a marker in RenderDoc and other debuggers
+    /// Must be closed by `end_label` inside the same rendering pass, or outside of any.
+    /// Does nothing without VK_EXT_debug_utils.
+    pub fn begin_label(&self, name: &str) {
+        let Some(debug_utils) = &self.debug_utils else {
+            return;
+        };
+        let name = CString::new(name.replace('\0', "")).unwrap_or_default();
+        let label = vk::DebugUtilsLabelEXT::default().label_name(&name);
+        unsafe {
+            debug_utils.cmd_begin_debug_utils_label(self.cmd_buffer, &label);
+        }
+    }
+
+    pub fn end_label(&self) {
+        if let Some(debug_utils) = &self.debug_utils {
+            unsafe {
+                debug_utils.cmd_end_debug_utils_label(self.cmd_buffer);
+            }
+        }
+    }
+
     /// Transition image layout
     pub fn transition_image(
         &self,
@@ -67,10 +172,17 @@ impl RenderContext {
                 .src_access_mask(match old_layout {
                     vk::ImageLayout::UNDEFINED => vk::AccessFlags::empty(),
                     vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL => vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => vk::AccessFlags::SHADER_READ,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL => vk::AccessFlags::TRANSFER_READ,
                     _ => vk::AccessFlags::empty(),
                 })
                 .dst_access_mask(match new_layout {
-                    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL => vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    // Read for passes that load the previous contents
+                    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL => {
+                        vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    }
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => vk::AccessFlags::SHADER_READ,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL => vk::AccessFlags::TRANSFER_READ,
                     vk::ImageLayout::PRESENT_SRC_KHR => vk::AccessFlags::empty(),
                     _ => vk::AccessFlags::empty(),
                 })
@@ -94,17 +206,78 @@ impl RenderContext {
         }
     }
 
-    /// Set viewport to full extent
-    fn set_full_viewport(&self) {
+    /// Size of the attachments of the current pass
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// Map clip space to `rect` (pixels of the attachment) for the following draws
+    pub fn set_viewport(&self, rect: Rect) {
         let viewport = vk::Viewport::default()
-            .width(self.extent.width as f32)
-            .height(self.extent.height as f32)
+            .x(rect.x)
+            .y(rect.y)
+            .width(rect.width)
+            .height(rect.height)
             .max_depth(1.0);
         unsafe {
             self.device.cmd_set_viewport(self.cmd_buffer, 0, &[viewport]);
         }
     }
 
+    /// `rect` rounded to whole pixels and clamped to the attachment
+    fn pixel_rect(&self, rect: Rect) -> vk::Rect2D {
+        let full = Rect::from_size(self.extent.width as f32, self.extent.height as f32);
+        let clamped = rect.intersect(&full);
+        let min = clamped.min().round();
+        let max = clamped.max().round();
+        vk::Rect2D {
+            offset: vk::Offset2D { x: min.x as i32, y: min.y as i32 },
+            extent: vk::Extent2D { width: (max.x - min.x) as u32, height: (max.y - min.y) as u32 },
+        }
+    }
+
+    /// Discard fragments outside `rect`, clamped to the attachment
+    pub fn set_scissor(&self, rect: Rect) {
+        let scissor = self.pixel_rect(rect);
+        unsafe {
+            self.device.cmd_set_scissor(self.cmd_buffer, 0, &[scissor]);
+        }
+    }
+
+    /// Fill `rect` of the first color attachment with `color`, inside a rendering pass
+    pub fn clear_region(&self, rect: Rect, color: Color) {
+        let rect = self.pixel_rect(rect);
+        if rect.extent.width == 0 || rect.extent.height == 0 {
+            return;
+        }
+        let attachment = vk::ClearAttachment {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            color_attachment: 0,
+            clear_value: vk::ClearValue { color: vk::ClearColorValue { float32: color.to_array() } },
+        };
+        let clear_rect = vk::ClearRect { rect, base_array_layer: 0, layer_count: 1 };
+        unsafe {
+            self.device.cmd_clear_attachments(self.cmd_buffer, &[attachment], &[clear_rect]);
+        }
+    }
+
+    /// Restrict drawing to `rect`, viewport and scissor
+    pub fn set_region(&self, rect: Rect) {
+        self.set_viewport(rect);
+        self.set_scissor(rect);
+    }
+
+    /// Draw to the whole attachment again
+    pub fn reset_region(&self) {
+        self.set_full_viewport();
+        self.set_full_scissor();
+    }
+
+    /// Set viewport to full extent
+    fn set_full_viewport(&self) {
+        self.set_viewport(Rect::from_size(self.extent.width as f32, self.extent.height as f32));
+    }
+
     /// Set scissor to full extent
     fn set_full_scissor(&self) {
         let scissor = vk::Rect2D::default().extent(self.extent);
@@ -132,30 +305,66 @@ impl RenderContext {
     }
 
     /// Bind index buffer
-    pub fn bind_index_buffer(&self, buffer: vk::Buffer) {
+    pub fn bind_index_buffer(&self, buffer: vk::Buffer, index_type: vk::IndexType) {
         unsafe {
-            self.device.cmd_bind_index_buffer(self.cmd_buffer, buffer, 0, vk::IndexType::UINT32);
+            self.device.cmd_bind_index_buffer(self.cmd_buffer, buffer, 0, index_type);
         }
     }
 
-    /// Push constants (fast per-draw uniforms)
-    pub fn push_constants<T>(&self, layout: vk::PipelineLayout, data: &T) {
-        unsafe {
-            let bytes = std::slice::from_raw_parts(
+    /// Push a pipeline's typed constants, stages and offset come from `T`
+    /// `layout` must belong to a pipeline whose push constant type is `T`
+    pub fn push<T: PipelinePush>(&self, layout: vk::PipelineLayout, data: &T) {
+        // Referencing SIZE runs the compile-time size checks for T
+        debug_assert_eq!(std::mem::size_of::<T>(), T::SIZE as usize);
+        self.push_constants(layout, T::STAGES, T::OFFSET, data);
+    }
+
+    /// Push constants (fast per-draw uniforms) to `stages` at byte `offset`
+    /// The stages must match the range declared in the pipeline layout, or the values
+    /// are not visible to the shader (and the validation layers report an error)
+    pub fn push_constants<T: Copy>(&self, layout: vk::PipelineLayout, stages: vk::ShaderStageFlags, offset: u32, data: &T) {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
                 data as *const T as *const u8,
                 std::mem::size_of::<T>(),
-            );
+            )
+        };
+        self.push_bytes(layout, stages, offset, bytes);
+    }
+
+    /// `push_constants` with the block already in bytes
+    pub fn push_bytes(&self, layout: vk::PipelineLayout, stages: vk::ShaderStageFlags, offset: u32, bytes: &[u8]) {
+        debug_assert!(offset.is_multiple_of(4) && bytes.len().is_multiple_of(4), "push constants must be 4 byte aligned");
+        unsafe {
             self.device.cmd_push_constants(
                 self.cmd_buffer,
                 layout,
-                vk::ShaderStageFlags::VERTEX,
-                0,
+                stages,
+                offset,
                 bytes,
             );
         }
     }
 
-    /// Bind descriptor sets
+    /// Bind one graphics descriptor set at `set_index` (the `set = N` in the shader)
+    pub fn bind_descriptor_set_at(&self, layout: vk::PipelineLayout, set_index: u32, descriptor_set: vk::DescriptorSet) {
+        self.bind_descriptor_set_dynamic(layout, set_index, descriptor_set, &[]);
+    }
+
+    /// Like `bind_descriptor_set_at`, with one offset per dynamic buffer binding in the set
+    /// (e.g. this frame's slice of a per-frame uniform buffer)
+    pub fn bind_descriptor_set_dynamic(
+        &self,
+        layout: vk::PipelineLayout,
+        set_index: u32,
+        descriptor_set: vk::DescriptorSet,
+        dynamic_offsets: &[u32],
+    ) {
+        self.bind_descriptor_sets(vk::PipelineBindPoint::GRAPHICS, layout, set_index, &[descriptor_set], dynamic_offsets);
+    }
+
+    /// Bind consecutive descriptor sets starting at `first_set`
+    /// `dynamic_offsets` holds one entry per dynamic binding across all sets, in order
     pub fn bind_descriptor_sets(
         &self,
         pipeline_bind_point: vk::PipelineBindPoint,
@@ -178,6 +387,7 @@ impl RenderContext {
 
     /// Draw vertices
     pub fn draw(&self, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32) {
+        self.record_draw(vertex_count, instance_count);
         unsafe {
             self.device.cmd_draw(
                 self.cmd_buffer,
@@ -191,6 +401,7 @@ impl RenderContext {
 
     /// Draw indexed vertices
     pub fn draw_indexed(&self, index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32) {
+        self.record_draw(index_count, instance_count);
         unsafe {
             self.device.cmd_draw_indexed(
                 self.cmd_buffer,
@@ -214,10 +425,69 @@ pub struct Renderer {
     pipeline_manager: PipelineManager,
     graphics_queue: vk::Queue,
     needs_rebuild: bool,
+    /// No surface to draw to, see `suspend`
+    suspended: bool,
     current_frame: usize,
     width: u32,
     height: u32,
+    stats: Rc<Cell<RenderStats>>,
+    /// First error of the last submitted frame, reported by the next `begin_frame`
+    frame_error: Rc<Cell<Option<RendererError>>>,
+    /// Failed frames since the last successful one
+    failed_frames: u32,
+    /// Views drawn by `RenderFrame::render_views`, empty for a single full frame view
+    views: Vec<View>,
+    /// Pixel-space projection of the window, used by UI draws
     pub projection: glam::Mat4,
+    /// Opacity UI draws are multiplied by, set around a group of widgets to fade them
+    pub opacity: f32,
+    /// Gamma text coverage is corrected for, so glyphs blended on linear colors look as heavy
+    /// as when blended in gamma space. 1.0 turns the correction off, the default for formats
+    /// that already blend in gamma space.
+    pub text_gamma: f32,
+    /// Camera of world-space draws, see `world_projection`
+    pub camera: Camera2D,
+    /// Color the swapchain image is cleared to, `None` keeps the previous contents
+    clear_color: Option<Color>,
+    /// Swapchain images presented since the swapchain was (re)created, the others have
+    /// no contents to keep
+    presented: Vec<bool>,
+    capture: FrameCapture,
+    fonts: FontManager,
+    /// Buffers and textures created through `GraphicsBackend`
+    resources: BackendResources,
+}
+
+/// Consecutive failed frames after which `recover` gives up
+const MAX_FAILED_FRAMES: u32 = 3;
+
+/// `Renderer::text_gamma` of sRGB formats
+const SRGB_TEXT_GAMMA: f32 = 2.2;
+
+/// Formats the engine renders to, in order of preference
+/// Linear colors (see `math::Color`) are only displayed correctly by the sRGB ones
+const PREFERRED_SURFACE_FORMATS: [vk::Format; 2] = [vk::Format::B8G8R8A8_SRGB, vk::Format::R8G8B8A8_SRGB];
+
+pub fn is_srgb_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::B8G8R8A8_SRGB | vk::Format::R8G8B8A8_SRGB | vk::Format::A8B8G8R8_SRGB_PACK32
+    )
+}
+
+/// Pick an sRGB surface format if the surface supports one, otherwise the first available
+fn choose_surface_format(formats: &[vk::SurfaceFormatKHR]) -> vk::SurfaceFormatKHR {
+    PREFERRED_SURFACE_FORMATS
+        .iter()
+        .find_map(|&preferred| {
+            formats.iter().find(|f| f.format == preferred && f.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR)
+        })
+        .or_else(|| formats.first())
+        .copied()
+        .unwrap_or(vk::SurfaceFormatKHR {
+            format: vk::Format::B8G8R8A8_SRGB,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        })
 }
 
 impl Renderer {
@@ -228,29 +498,26 @@ impl Renderer {
         let surface_formats = unsafe {
             context.surface_loader.get_physical_device_surface_formats(
                 context.physical_device,
-                context.surface,
+                context.surface(),
             )?
         };
 
-        // Find SRGB format or fall back to first available
-        let surface_format = surface_formats
-            .iter()
-            .find(|fmt| fmt.format == vk::Format::B8G8R8A8_SRGB)
-            .or_else(|| surface_formats.first())
-            .copied()
-            .unwrap_or(vk::SurfaceFormatKHR {
-                format: vk::Format::B8G8R8A8_SRGB,
-                color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
-            });
-
-        println!("Selected surface format: {:?}", surface_format);
+        let surface_format = choose_surface_format(&surface_formats);
+        log::info!("Selected surface format: {:?}", surface_format);
+        if !is_srgb_format(surface_format.format) {
+            log::warn!(
+                "No sRGB swapchain format available, colors are written without gamma encoding and will look too dark"
+            );
+        }
         
         let swapchain = Swapchain::new(
             &context.device,
             &swapchain_loader,
+            &context.surface_loader,
+            context.physical_device,
             surface_format,
             vk::Extent2D { width, height },
-            context.surface,
+            context.surface(),
             vk::PresentModeKHR::FIFO,
             2,
             &context.queue_family_indices,
@@ -266,11 +533,26 @@ impl Renderer {
             context.device.get_device_queue(context.queue_family_indices[0], 0)
         };
 
+        // The spec only guarantees 128 bytes, check the largest block a pipeline declares
+        let max_push_constants_size = context.features().max_push_constants_size;
+        let largest = crate::renderer::PipelineId::all()
+            .map(|id| (id, id.push_range()))
+            .max_by_key(|(_, range)| range.offset + range.size);
+        if let Some((id, range)) = largest.filter(|(_, range)| range.offset + range.size > max_push_constants_size) {
+            anyhow::bail!(
+                "Device supports {} bytes of push constants, the {:?} pipeline needs {}",
+                max_push_constants_size,
+                id,
+                range.offset + range.size
+            );
+        }
+
         // Compile shaders and build all pipelines up front
         let shader_manager = crate::renderer::ShaderManager::new()?;
         shader_manager.compile_all_shaders()?;
         
-        let mut pipeline_manager = PipelineManager::new((*context.device).clone());
+        // Pipelines must be built for the format the swapchain actually uses
+        let mut pipeline_manager = PipelineManager::new((*context.device).clone(), surface_format.format);
         pipeline_manager.build_all()?;
 
         Ok(Self {
@@ -282,11 +564,23 @@ impl Renderer {
             pipeline_manager,
             graphics_queue,
             needs_rebuild: false,
+            suspended: false,
             current_frame: 0,
             width,
             height,
+            stats: Rc::new(Cell::new(RenderStats::default())),
+            frame_error: Rc::new(Cell::new(None)),
+            failed_frames: 0,
+            views: Vec::new(),
             projection: glam::Mat4::IDENTITY,
-
+            opacity: 1.0,
+            text_gamma: if is_srgb_format(surface_format.format) { SRGB_TEXT_GAMMA } else { 1.0 },
+            camera: Camera2D::new(),
+            clear_color: Some(Color::srgb(0.25, 0.1, 0.1)),
+            presented: vec![false; swapchain_image_count],
+            capture: FrameCapture::new(),
+            fonts: FontManager::new(),
+            resources: BackendResources::new(),
         })
     }
 
@@ -297,9 +591,11 @@ impl Renderer {
         self.width = width;
         self.height = height;
 
-        if width > 0 && height > 0 && (width != self.swapchain.extent.width || height != self.swapchain.extent.height) {
-            println!("Resizing swapchain: {}x{} -> {}x{}", self.swapchain.extent.width, self.swapchain.extent.height, width, height);
+        // The swapchain is created for the size at hand on `resume`
+        if !self.suspended && width > 0 && height > 0 && (width != self.swapchain.extent.width || height != self.swapchain.extent.height) {
+            log::info!("Resizing swapchain: {}x{} -> {}x{}", self.swapchain.extent.width, self.swapchain.extent.height, width, height);
             self.swapchain.recreate(vk::Extent2D { width, height });
+            self.match_swapchain_images();
         }
 
 
@@ -312,18 +608,136 @@ impl Renderer {
             1.0,
         );
 
-        println!("Updated projection matrix for new size: {:?}", self.projection);
+        log::debug!("Updated projection matrix for new size: {:?}", self.projection);
+    }
+
+    /// Release the swapchain and the surface, e.g. on `Event::Suspended` when a mobile app
+    /// goes to the background and its native window is destroyed. Frames are skipped until
+    /// `resume`; everything else (pipelines, textures, fonts) stays alive.
+    pub fn suspend(&mut self) {
+        if self.suspended {
+            return;
+        }
+        log::info!("Suspending the renderer, releasing the surface");
+        unsafe {
+            let _ = self.context.device.device_wait_idle();
+        }
+        self.swapchain.release();
+        self.context.destroy_surface();
+        self.suspended = true;
+    }
+
+    /// Create a surface and swapchain for `window` after `suspend`, e.g. on `Event::Resumed`
+    pub fn resume(&mut self, window: &winit::window::Window) -> Result<()> {
+        if !self.suspended {
+            return Ok(());
+        }
+        self.context.recreate_surface(window)?;
+        self.swapchain.set_surface(self.context.surface());
+        let size = window.inner_size();
+        let extent = vk::Extent2D { width: size.width.max(1), height: size.height.max(1) };
+        log::info!("Resuming the renderer at {}x{}", extent.width, extent.height);
+        self.swapchain.recreate(extent);
+        self.match_swapchain_images();
+        self.suspended = false;
+        self.needs_rebuild = false;
+        self.handle_resize(extent.width, extent.height, window.scale_factor() as f32);
+        Ok(())
+    }
+
+    /// Whether the renderer has no surface, between `suspend` and `resume`
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// The surface may hand out a different number of images after the swapchain is recreated
+    fn match_swapchain_images(&mut self) {
+        let image_count = self.swapchain.images.len();
+        self.presented = vec![false; image_count];
+        if image_count != self.frame_sync.images_in_flight.len() {
+            self.frame_sync = FrameSynchronizer::new(&self.context.device, self.frame_sync.max_frames_in_flight(), image_count);
+        }
+    }
+
+    /// Wait for vertical blank when presenting (FIFO), or present as soon as a frame is
+    /// ready without tearing (mailbox) or with it (immediate), whichever the surface has
+    pub fn set_vsync(&mut self, vsync: bool) {
+        if self.suspended {
+            log::warn!("Can't change vsync while the renderer is suspended");
+            return;
+        }
+        let present_mode = if vsync {
+            vk::PresentModeKHR::FIFO
+        } else {
+            let supported = unsafe {
+                self.context
+                    .surface_loader
+                    .get_physical_device_surface_present_modes(self.context.physical_device, self.context.surface())
+            }
+            .unwrap_or_default();
+            [vk::PresentModeKHR::MAILBOX, vk::PresentModeKHR::IMMEDIATE]
+                .into_iter()
+                .find(|mode| supported.contains(mode))
+                .unwrap_or(vk::PresentModeKHR::FIFO)
+        };
+        if present_mode != self.swapchain.present_mode() {
+            log::info!("Switching present mode to {:?}", present_mode);
+            self.swapchain.set_present_mode(present_mode);
+            self.match_swapchain_images();
+        }
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.swapchain.present_mode() == vk::PresentModeKHR::FIFO
+    }
+
+    /// Color every frame starts from, `None` keeps what the swapchain image held when it
+    /// was last presented, for painter-style redraws that only touch what changed
+    ///
+    /// With `None` the images of a swapchain are independent, so a frame builds on the one
+    /// presented two or three frames ago, not necessarily the last one.
+    pub fn set_clear_color(&mut self, color: Option<Color>) {
+        self.clear_color = color;
+    }
+
+    pub fn clear_color(&self) -> Option<Color> {
+        self.clear_color
+    }
+
+    /// Capture the next frame with RenderDoc, false (and a warning) when the engine was
+    /// built without the `renderdoc` feature or the application wasn't started from RenderDoc
+    pub fn trigger_capture(&mut self) -> bool {
+        self.capture.trigger()
+    }
+
+    /// Whether `trigger_capture` can work
+    pub fn can_capture(&self) -> bool {
+        self.capture.is_available()
     }
 
-    pub fn begin_frame(&mut self) -> Option<RenderFrame> {
+    /// Start recording a frame, `Ok(None)` skips it (e.g. while the swapchain is out of date)
+    /// Errors, including those from submitting the previous frame, should go to `recover`
+    pub fn begin_frame(&mut self) -> Result<Option<RenderFrame>, RendererError> {
+        crate::profile_scope!("begin_frame");
+
+        if let Some(error) = self.frame_error.take() {
+            return Err(error);
+        }
+        if self.suspended {
+            return Ok(None);
+        }
+        self.capture.poll();
+
         // Handle swapchain rebuild if needed
         if self.needs_rebuild {
             self.needs_rebuild = false;
-            return None;
+            return Ok(None);
         }
 
         // Wait for this frame's fence to be signaled (CPU-GPU sync)
-        self.frame_sync.wait_for_frame(self.current_frame).ok()?;
+        self.frame_sync.wait_for_frame(self.current_frame)?;
+        self.fonts.next_frame(&self.context.device);
+        self.resources.next_frame(&self.context.device);
 
         // Get acquire semaphore for this frame
         let image_available_sem = self.frame_sync.get_acquire_semaphore(self.current_frame);
@@ -340,11 +754,9 @@ impl Renderer {
             Ok((idx, _)) => idx,
             Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
                 self.needs_rebuild = true;
-                return None;
-            }
-            Err(_) => {
-                return None;
+                return Ok(None);
             }
+            Err(error) => return Err(error.into()),
         };
 
         // Get the render finished semaphore for THIS SPECIFIC IMAGE
@@ -353,7 +765,7 @@ impl Renderer {
         // Check if this image is still being used by a previous frame
         if let Some(image_fence) = self.frame_sync.images_in_flight[image_index as usize] {
             unsafe {
-                self.context.device.wait_for_fences(&[image_fence], true, u64::MAX).ok()?;
+                self.context.device.wait_for_fences(&[image_fence], true, u64::MAX)?;
             }
         }
         
@@ -361,43 +773,57 @@ impl Renderer {
         self.frame_sync.images_in_flight[image_index as usize] = Some(self.frame_sync.get_fence(self.current_frame));
 
         // Reset fence for this frame
-        self.frame_sync.reset_fence(self.current_frame).ok()?;
+        self.frame_sync.reset_fence(self.current_frame)?;
 
         let cmd_buffer = self.command_pool.buffers[self.current_frame];
 
-        // Reset and begin command buffer
+        // The fence wait above guarantees the frame's previous commands finished
+        self.command_pool.reset_frame(self.current_frame)?;
         unsafe {
-            self.context.device.reset_command_buffer(cmd_buffer, vk::CommandBufferResetFlags::RELEASE_RESOURCES).ok()?;
             let begin_info = vk::CommandBufferBeginInfo::default()
                 .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
-            self.context.device.begin_command_buffer(cmd_buffer, &begin_info).ok()?;
+            self.context.device.begin_command_buffer(cmd_buffer, &begin_info)?;
         }
 
+        self.stats.set(RenderStats::default());
         let render_ctx = RenderContext::new(
             Arc::clone(&self.context.device),
             cmd_buffer,
             self.swapchain.extent,
+            Rc::clone(&self.stats),
+            self.context.debug_utils().cloned(),
         );
+        // Closed when the frame is submitted, offscreen passes nest inside it
+        render_ctx.begin_label("Frame");
 
-        // Transition to render target
+        // Transition to render target, an image that was never presented has nothing to keep
+        let presented = std::mem::replace(&mut self.presented[image_index as usize], true);
+        let clear_color = if presented { self.clear_color } else { Some(self.clear_color.unwrap_or(Color::BLACK)) };
+        let old_layout = if clear_color.is_none() {
+            vk::ImageLayout::PRESENT_SRC_KHR
+        } else {
+            vk::ImageLayout::UNDEFINED
+        };
         render_ctx.transition_image(
             self.swapchain.images[image_index as usize],
-            vk::ImageLayout::UNDEFINED,
+            old_layout,
             vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
             vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
         );
 
         // Begin rendering
-        render_ctx.begin_rendering(
-            self.swapchain.image_views[image_index as usize],
-            [0.25, 0.1, 0.1, 1.0],
-        );
+        let image_view = self.swapchain.image_views[image_index as usize];
+        match clear_color {
+            Some(color) => render_ctx.begin_rendering(image_view, color),
+            None => render_ctx.resume_rendering(image_view),
+        }
 
         let frame = RenderFrame {
             render_ctx,
             swapchain: self.swapchain.swapchain,
             swapchain_image: self.swapchain.images[image_index as usize],
+            swapchain_image_view: self.swapchain.image_views[image_index as usize],
             swapchain_loader: self.swapchain_loader.clone(),
             graphics_queue: self.graphics_queue,
             device: Arc::clone(&self.context.device),
@@ -406,12 +832,278 @@ impl Renderer {
             wait_semaphore: image_available_sem,
             signal_semaphore: render_finished_sem,
             fence: self.frame_sync.get_fence(self.current_frame),
+            error: Rc::clone(&self.frame_error),
+            views: if self.views.is_empty() {
+                vec![View::new(Rect::from_size(self.width as f32, self.height as f32), self.projection)]
+            } else {
+                self.views.clone()
+            },
         };
 
         // Advance to next frame (modulo max_frames_in_flight, not swapchain image count)
         self.current_frame = (self.current_frame + 1) % self.frame_sync.max_frames_in_flight();
 
-        Some(frame)
+        self.failed_frames = 0;
+        Ok(Some(frame))
+    }
+
+    /// Recovery hook for errors from `begin_frame`
+    ///
+    /// Logs diagnostics, then resets the swapchain and frame synchronization so rendering
+    /// can continue. A lost device or surface can't be recovered here (everything created
+    /// from them would have to be rebuilt), and neither can repeated failures.
+    pub fn recover(&mut self, error: RendererError) -> Recovery {
+        self.failed_frames += 1;
+        log::error!("Renderer error: {}\n{}", error, self.diagnostics());
+
+        if matches!(error, RendererError::DeviceLost | RendererError::SurfaceLost) || self.failed_frames > MAX_FAILED_FRAMES {
+            return Recovery::Shutdown;
+        }
+
+        unsafe {
+            let _ = self.context.device.device_wait_idle();
+        }
+        // Fences of failed frames may never be signaled, start over with fresh ones
+        self.frame_sync = FrameSynchronizer::new(&self.context.device, self.frame_sync.max_frames_in_flight(), self.swapchain.images.len());
+        self.current_frame = 0;
+        self.swapchain.recreate(self.swapchain.extent);
+        self.match_swapchain_images();
+        Recovery::Retry
+    }
+
+    /// Device, memory and swapchain state for error reports
+    pub fn diagnostics(&self) -> String {
+        let properties = unsafe { self.context.instance.get_physical_device_properties(self.context.physical_device) };
+        let stats = self.stats.get();
+        let memory = self.memory_stats();
+        const MB: f64 = 1024.0 * 1024.0;
+        format!(
+            "Device: {} (driver {:#x}, API {}.{}.{})\nEngine allocations: {:.1} MB (buffers {:.1} MB, textures {:.1} MB, render targets {:.1} MB)\nSwapchain: {}x{} {:?}, {} images\nLast frame: {} draw calls, {} triangles",
+            VulkanContext::vk_to_string(&properties.device_name),
+            properties.driver_version,
+            vk::api_version_major(properties.api_version),
+            vk::api_version_minor(properties.api_version),
+            vk::api_version_patch(properties.api_version),
+            memory.total() as f64 / MB,
+            memory.buffers as f64 / MB,
+            memory.textures as f64 / MB,
+            memory.render_targets as f64 / MB,
+            self.swapchain.extent.width,
+            self.swapchain.extent.height,
+            self.swapchain.format,
+            self.swapchain.images.len(),
+            stats.draw_calls,
+            stats.triangles,
+        )
+    }
+
+    /// Rasterize the font at `path` for text of `size` pixels, the atlas lives as long as the
+    /// renderer or until `unload_font`
+    pub fn load_font(&mut self, path: &str, size: f32) -> Result<Handle<Font>> {
+        self.fonts.load(&self.context, path, size)
+    }
+
+    /// Atlas of a loaded font, `None` once it was unloaded
+    pub fn font(&self, handle: Handle<Font>) -> Option<Arc<FontAtlas>> {
+        self.fonts.atlas(handle)
+    }
+
+    /// Release a font, its atlas is destroyed once no text and no frame in flight uses it
+    pub fn unload_font(&mut self, handle: Handle<Font>) -> bool {
+        self.fonts.unload(handle)
+    }
+
+    pub fn fonts(&self) -> &FontManager {
+        &self.fonts
+    }
+
+    /// Draw statistics of the most recently recorded frame
+    pub fn stats(&self) -> RenderStats {
+        self.stats.get()
+    }
+
+    /// Bytes of device memory currently allocated by engine buffers and textures
+    pub fn gpu_memory_used(&self) -> u64 {
+        self.memory_stats().total()
+    }
+
+    /// Device memory currently allocated by the engine, per category
+    pub fn memory_stats(&self) -> MemoryStats {
+        super::buffer_utils::memory_stats()
+    }
+
+    /// Entity under pixel (`x`, `y`) of an entity id target (`ENTITY_ID_FORMAT`) rendered by the scene pass
+    ///
+    /// Waits for the GPU and reads back the pixel, meant for clicks rather than every frame.
+    /// Row 0 is the first row of the image, `None` for background pixels or outside the target.
+    #[cfg(feature = "ecs")]
+    pub fn pick(&self, ids: &Texture, x: u32, y: u32) -> Result<Option<EntityId>> {
+        if ids.format != ENTITY_ID_FORMAT {
+            anyhow::bail!("pick needs an {:?} target, got {:?}", ENTITY_ID_FORMAT, ids.format);
+        }
+        if x >= ids.width || y >= ids.height {
+            return Ok(None);
+        }
+
+        let device = &self.context.device;
+        let (buffer, memory, size) = create_buffer_with_data(
+            device,
+            self.context.physical_device,
+            &self.context.instance,
+            &[0u32],
+            vk::BufferUsageFlags::TRANSFER_DST,
+        )?;
+
+        let extent = vk::Extent3D { width: 1, height: 1, depth: 1 };
+        let result = unsafe { self.copy_region(ids, vk::Offset3D { x: x as i32, y: y as i32, z: 0 }, extent, buffer) }.map(|()| unsafe {
+            let ptr = device.map_memory(memory, 0, size, vk::MemoryMapFlags::empty())?;
+            let value = *(ptr as *const u32);
+            device.unmap_memory(memory);
+            Ok::<_, vk::Result>(value)
+        });
+
+        unsafe {
+            device.destroy_buffer(buffer, None);
+            device.free_memory(memory, None);
+        }
+        track_free(MemoryCategory::Buffers, size);
+
+        // Ids are written off by one so the cleared background reads as 0
+        let value = result??;
+        Ok(value.checked_sub(1).map(EntityId))
+    }
+
+    /// Read back a whole 8-bit color render target (e.g. `color_format`) as RGBA
+    ///
+    /// Waits for the GPU like `pick`, meant for screenshots and snapshot tests. BGRA
+    /// targets are swizzled, sRGB values are returned as stored.
+    pub fn read_pixels(&self, target: &Texture) -> Result<image::RgbaImage> {
+        let bgra = match target.format {
+            vk::Format::B8G8R8A8_SRGB | vk::Format::B8G8R8A8_UNORM => true,
+            vk::Format::R8G8B8A8_SRGB | vk::Format::R8G8B8A8_UNORM => false,
+            format => anyhow::bail!("read_pixels needs an 8-bit RGBA or BGRA target, got {:?}", format),
+        };
+
+        let device = &self.context.device;
+        let (buffer, memory, size) = create_buffer_with_data(
+            device,
+            self.context.physical_device,
+            &self.context.instance,
+            &vec![0u8; target.width as usize * target.height as usize * 4],
+            vk::BufferUsageFlags::TRANSFER_DST,
+        )?;
+
+        let extent = vk::Extent3D { width: target.width, height: target.height, depth: 1 };
+        let result = unsafe { self.copy_region(target, vk::Offset3D::default(), extent, buffer) }.map(|()| unsafe {
+            let ptr = device.map_memory(memory, 0, size, vk::MemoryMapFlags::empty())?;
+            let pixels = std::slice::from_raw_parts(ptr as *const u8, size as usize).to_vec();
+            device.unmap_memory(memory);
+            Ok::<_, vk::Result>(pixels)
+        });
+
+        unsafe {
+            device.destroy_buffer(buffer, None);
+            device.free_memory(memory, None);
+        }
+        track_free(MemoryCategory::Buffers, size);
+
+        let mut pixels = result??;
+        if bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        image::RgbaImage::from_raw(target.width, target.height, pixels)
+            .ok_or_else(|| anyhow::anyhow!("Read back {}x{} target into a buffer of the wrong size", target.width, target.height))
+    }
+
+    /// Copy a region of a sampled render target into `buffer` and wait for it
+    unsafe fn copy_region(&self, target: &Texture, offset: vk::Offset3D, extent: vk::Extent3D, buffer: vk::Buffer) -> Result<()> {
+        let device = &self.context.device;
+        // The target is written by frames in flight
+        device.device_wait_idle()?;
+
+        let pool = device.create_command_pool(
+            &vk::CommandPoolCreateInfo::default()
+                .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+                .queue_family_index(self.context.queue_family_indices[0]),
+            None,
+        )?;
+        let result = (|| {
+            let cmd_buffer = device.allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::default()
+                    .command_pool(pool)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )?[0];
+            device.begin_command_buffer(
+                cmd_buffer,
+                &vk::CommandBufferBeginInfo::default().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )?;
+
+            let ctx = RenderContext::new(Arc::clone(device), cmd_buffer, vk::Extent2D::default(), Rc::clone(&self.stats), None);
+            ctx.transition_image(
+                target.image,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+            );
+            let region = vk::BufferImageCopy::default()
+                .image_subresource(
+                    vk::ImageSubresourceLayers::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .layer_count(1),
+                )
+                .image_offset(offset)
+                .image_extent(extent);
+            device.cmd_copy_image_to_buffer(cmd_buffer, target.image, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, buffer, &[region]);
+            ctx.transition_image(
+                target.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            );
+            device.end_command_buffer(cmd_buffer)?;
+
+            let command_buffers = [cmd_buffer];
+            let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+            device.queue_submit(self.graphics_queue, &[submit_info], vk::Fence::null())?;
+            device.queue_wait_idle(self.graphics_queue)?;
+            Ok(())
+        })();
+        device.destroy_command_pool(pool, None);
+        result
+    }
+
+    /// Projection * view of `camera` over the whole window
+    pub fn world_projection(&self) -> glam::Mat4 {
+        self.camera.view_projection(glam::Vec2::new(self.width as f32, self.height as f32))
+    }
+
+    /// Projection of draws positioned in `space`
+    pub fn projection_for(&self, space: ProjectionSpace) -> glam::Mat4 {
+        match space {
+            ProjectionSpace::Ui => self.projection,
+            ProjectionSpace::World => self.world_projection(),
+            ProjectionSpace::Custom(projection) => projection,
+        }
+    }
+
+    /// Split the frame into views, each drawn with its own camera by `RenderFrame::render_views`
+    /// An empty list draws a single view covering the window with `projection`
+    pub fn set_views(&mut self, views: Vec<View>) {
+        self.views = views;
+    }
+
+    pub fn views(&self) -> &[View] {
+        &self.views
+    }
+
+    /// Format of the swapchain images, offscreen targets drawn with the same pipelines must match it
+    pub fn color_format(&self) -> vk::Format {
+        self.pipeline_manager.color_format()
     }
 
     /// Get a pipeline by ID
@@ -434,6 +1126,7 @@ pub struct RenderFrame {
     pub render_ctx: RenderContext,
     swapchain: vk::SwapchainKHR,
     swapchain_image: vk::Image,
+    swapchain_image_view: vk::ImageView,
     swapchain_loader: Arc<ash::khr::swapchain::Device>,
     graphics_queue: vk::Queue,
     device: Arc<Device>,
@@ -442,9 +1135,221 @@ pub struct RenderFrame {
     wait_semaphore: vk::Semaphore,
     signal_semaphore: vk::Semaphore,
     fence: vk::Fence,
+    /// Shared with the Renderer, submission errors surface in the next `begin_frame`
+    error: Rc<Cell<Option<RendererError>>>,
+    /// `Renderer::views` when the frame began
+    views: Vec<View>,
 }
 
 impl RenderFrame {
+    /// Keep the first error of the frame
+    fn report(&self, result: Result<(), vk::Result>) {
+        if let Err(error) = result {
+            if self.error.get().is_none() {
+                self.error.set(Some(error.into()));
+            }
+        }
+    }
+
+    /// Views of this frame, see `Renderer::set_views`
+    pub fn views(&self) -> &[View] {
+        &self.views
+    }
+
+    /// Call `draw` once per view with the viewport and scissor set to the view's rect
+    /// Views with a clear color are filled with it first. The full frame region is
+    /// restored afterwards.
+    pub fn render_views<F>(&self, mut draw: F) -> Result<()>
+    where
+        F: FnMut(&RenderContext, &View) -> Result<()>,
+    {
+        let mut result = Ok(());
+        for (index, view) in self.views.iter().enumerate() {
+            self.render_ctx.set_region(view.rect);
+            self.render_ctx.begin_label(&format!("View {}", index));
+            if let Some(color) = view.clear_color {
+                self.render_ctx.clear_region(view.rect, color);
+            }
+            result = draw(&self.render_ctx, view);
+            self.render_ctx.end_label();
+            if result.is_err() {
+                break;
+            }
+        }
+        self.render_ctx.reset_region();
+        result
+    }
+
+    /// Record a pass into an offscreen texture (see `Texture::render_target`)
+    ///
+    /// The swapchain pass is suspended while `draw` records into `target` and resumed
+    /// afterwards without clearing, so this can be called at any point of the frame.
+    /// The target is left in SHADER_READ_ONLY_OPTIMAL layout for sampling by later draws.
+    pub fn render_to_texture<F>(&self, target: &Texture, clear_color: Color, draw: F) -> Result<()>
+    where
+        F: FnOnce(&RenderContext) -> Result<()>,
+    {
+        self.render_to_targets(&[(target, Some(vk::ClearColorValue { float32: clear_color.to_array() }))], draw)
+    }
+
+    /// Record a pass into several offscreen textures of the same size (multiple render targets)
+    /// Each target is cleared to its value, or keeps what earlier passes drew into it when
+    /// the value is `None`. Pipelines drawing in the pass must declare the targets as color
+    /// attachments in the same order.
+    pub fn render_to_targets<F>(&self, targets: &[(&Texture, Option<vk::ClearColorValue>)], draw: F) -> Result<()>
+    where
+        F: FnOnce(&RenderContext) -> Result<()>,
+    {
+        let Some(&(first, _)) = targets.first() else {
+            return Err(anyhow::anyhow!("render_to_targets needs at least one target"));
+        };
+        if targets.iter().any(|(target, _)| target.width != first.width || target.height != first.height) {
+            return Err(anyhow::anyhow!("Render targets of a pass must have the same size"));
+        }
+
+        self.render_ctx.end_rendering();
+
+        let target_ctx = RenderContext::new(
+            Arc::clone(&self.device),
+            self.cmd_buffer,
+            vk::Extent2D { width: first.width, height: first.height },
+            Rc::clone(&self.render_ctx.stats),
+            self.render_ctx.debug_utils.clone(),
+        );
+        target_ctx.begin_label("Offscreen pass");
+
+        // Wait for sampling by the previous frame to finish, contents are only kept when loaded
+        for &(target, clear_value) in targets {
+            let old_layout = if clear_value.is_none() && target.is_rendered() {
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            } else {
+                vk::ImageLayout::UNDEFINED
+            };
+            target_ctx.transition_image(
+                target.image,
+                old_layout,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            );
+        }
+        let attachments: Vec<_> = targets
+            .iter()
+            .map(|&(target, clear_value)| match clear_value {
+                // Never rendered into, so there is nothing to keep
+                None if !target.is_rendered() => ColorAttachment::clear_value(target.image_view, vk::ClearColorValue::default()),
+                None => ColorAttachment::load(target.image_view),
+                Some(clear_value) => ColorAttachment::clear_value(target.image_view, clear_value),
+            })
+            .collect();
+        target_ctx.begin_rendering_attachments(&attachments);
+        let result = draw(&target_ctx);
+        target_ctx.end_rendering();
+        for (target, _) in targets {
+            target_ctx.transition_image(
+                target.image,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            );
+            target.set_rendered();
+        }
+        target_ctx.end_label();
+
+        self.render_ctx.resume_rendering(self.swapchain_image_view);
+        result
+    }
+}
+
+impl GraphicsBackend for Renderer {
+    type Commands = RenderContext;
+
+    fn name(&self) -> &'static str {
+        "Vulkan"
+    }
+
+    fn surface_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn create_buffer(&mut self, usage: BufferUsage, data: &[u8]) -> Result<BufferHandle> {
+        self.resources.create_buffer(&self.context, usage, data)
+    }
+
+    fn destroy_buffer(&mut self, buffer: BufferHandle) {
+        self.resources.destroy_buffer(buffer);
+    }
+
+    fn create_texture(&mut self, width: u32, height: u32, format: TextureFormat, data: &[u8]) -> Result<TextureHandle> {
+        // Every pipeline sampling one image declares the same set layout
+        let layout = self.pipeline_manager.get_descriptor_set_layout(crate::renderer::PipelineId::Image)
+            .ok_or_else(|| anyhow::anyhow!("Image pipeline should have a descriptor set layout"))?;
+        self.resources.create_texture(&self.context, layout, width, height, format, data)
+    }
+
+    fn update_texture(&mut self, texture: TextureHandle, region: TextureRegion, data: &[u8]) -> Result<()> {
+        let (texture, _) = self.resources.texture(texture)?;
+        texture.update_region(
+            data,
+            region.x,
+            region.y,
+            region.width,
+            region.height,
+            &self.context.device,
+            &self.context.instance,
+            self.context.physical_device,
+            self.context.queue_family_indices[0],
+        )
+    }
+
+    fn texture_size(&self, texture: TextureHandle) -> Option<(u32, u32)> {
+        self.resources.texture(texture).ok().map(|(texture, _)| (texture.width, texture.height))
+    }
+
+    fn destroy_texture(&mut self, texture: TextureHandle) {
+        self.resources.destroy_texture(texture);
+    }
+
+    fn pipeline(&mut self, id: crate::renderer::PipelineId) -> Result<PipelineHandle> {
+        self.get_pipeline(id)?;
+        Ok(PipelineHandle { id })
+    }
+
+    fn draw(&mut self, commands: &RenderContext, call: &DrawCall) -> Result<()> {
+        let id = call.pipeline.id;
+        let pipeline = self.get_pipeline(id)?;
+        let layout = self.get_pipeline_layout(id)
+            .ok_or_else(|| anyhow::anyhow!("Pipeline layout not found for {:?} pipeline", id))?;
+        let range = id.push_range();
+        if call.push.len() != range.size as usize {
+            anyhow::bail!("{:?} pipeline takes {} bytes of push constants, got {}", id, range.size, call.push.len());
+        }
+        let vertices = self.resources.buffer(call.vertices)?;
+        if vertices.usage != BufferUsage::Vertex {
+            anyhow::bail!("Buffer {:?} is not a vertex buffer", call.vertices);
+        }
+
+        commands.bind_pipeline(pipeline);
+        if let Some(texture) = call.texture {
+            let (_, sampled) = self.resources.texture(texture)?;
+            commands.bind_descriptor_set_at(layout, 0, sampled.descriptor_set);
+        }
+        commands.push_bytes(layout, range.stage_flags, range.offset, call.push);
+        commands.bind_vertex_buffer(vertices.buffer);
+        match call.indices {
+            Some(indices) => {
+                let indices = self.resources.buffer(indices)?;
+                if indices.usage != BufferUsage::Index {
+                    anyhow::bail!("Buffer {:?} is not an index buffer", call.indices);
+                }
+                commands.bind_index_buffer(indices.buffer, vk::IndexType::UINT32);
+                commands.draw_indexed(call.count, 1, 0, 0, 0);
+            }
+            None => commands.draw(call.count, 1, 0, 0),
+        }
+        Ok(())
+    }
 }
 
 impl Drop for Renderer {
@@ -452,6 +1357,8 @@ impl Drop for Renderer {
         unsafe {
             // Wait for all GPU work to complete
             let _ = self.context.device.device_wait_idle();
+            self.fonts.destroy(&self.context.device);
+            self.resources.destroy(&self.context.device);
             
             // Fields will be dropped in reverse order of declaration:
             // 1. current_frame (usize - no cleanup)
@@ -469,6 +1376,8 @@ impl Drop for Renderer {
 
 impl Drop for RenderFrame {
     fn drop(&mut self) {
+        crate::profile_scope!("submit");
+
         // End rendering
         self.render_ctx.end_rendering();
 
@@ -480,9 +1389,10 @@ impl Drop for RenderFrame {
             vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
             vk::PipelineStageFlags::BOTTOM_OF_PIPE,
         );
+        self.render_ctx.end_label();
 
         unsafe {
-            self.device.end_command_buffer(self.cmd_buffer).ok();
+            self.report(self.device.end_command_buffer(self.cmd_buffer));
 
             // Submit with fence for GPU-CPU synchronization
             let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
@@ -492,7 +1402,7 @@ impl Drop for RenderFrame {
                 .command_buffers(std::slice::from_ref(&self.cmd_buffer))
                 .signal_semaphores(std::slice::from_ref(&self.signal_semaphore));
 
-            self.device.queue_submit(self.graphics_queue, &[submit_info], self.fence).ok();
+            self.report(self.device.queue_submit(self.graphics_queue, &[submit_info], self.fence));
 
             // Present
             let swapchains = [self.swapchain];
@@ -502,7 +1412,11 @@ impl Drop for RenderFrame {
                 .swapchains(&swapchains)
                 .image_indices(&image_indices);
 
-            let _ = self.swapchain_loader.queue_present(self.graphics_queue, &present_info);
+            // Out of date swapchains are rebuilt on resize, not an error
+            match self.swapchain_loader.queue_present(self.graphics_queue, &present_info) {
+                Ok(_) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {}
+                Err(error) => self.report(Err(error)),
+            }
         }
     }
 }
@@ -1,8 +1,7 @@
 use anyhow::Result;
 use ash::vk;
-use std::sync::Arc;
 
-use super::Texture;
+use super::{DeviceFeatures, Texture, VulkanContext};
 
 /// A texture with sampler and descriptor sets ready for shader use
 /// This encapsulates all the Vulkan boilerplate for texture sampling
@@ -53,6 +52,17 @@ impl SamplerConfig {
             anisotropy: Some(16.0),  // Enable anisotropic filtering for 3D
         }
     }
+
+    /// Drop what the device can't do: anisotropic filtering needs `sampler_anisotropy` and
+    /// is clamped to the device's highest level
+    /// Applied by `SampledTexture` to every sampler it creates.
+    pub fn supported_by(mut self, features: &DeviceFeatures) -> Self {
+        self.anisotropy = self
+            .anisotropy
+            .filter(|_| features.sampler_anisotropy)
+            .map(|anisotropy| anisotropy.min(features.max_sampler_anisotropy).max(1.0));
+        self
+    }
 }
 
 impl SampledTexture {
@@ -68,8 +78,20 @@ impl SampledTexture {
         texture: &Texture,
         config: SamplerConfig,
         descriptor_set_layout: vk::DescriptorSetLayout,
-        device: &Arc<ash::Device>,
+        context: &VulkanContext,
+    ) -> Result<Self> {
+        Self::from_view(texture.image_view, config, descriptor_set_layout, context)
+    }
+
+    /// Sample an image view in SHADER_READ_ONLY_OPTIMAL layout, e.g. of a texture owned elsewhere
+    pub fn from_view(
+        image_view: vk::ImageView,
+        config: SamplerConfig,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        context: &VulkanContext,
     ) -> Result<Self> {
+        let device = &context.device;
+        let config = config.supported_by(context.features());
         unsafe {
             // Create sampler
             let sampler_info = vk::SamplerCreateInfo::default()
@@ -87,7 +109,8 @@ impl SampledTexture {
                 .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
                 .mip_lod_bias(0.0)
                 .min_lod(0.0)
-                .max_lod(0.0);
+                // Every level the view has, textures without mips only have level 0
+                .max_lod(vk::LOD_CLAMP_NONE);
             
             let sampler = device.create_sampler(&sampler_info, None)?;
 
@@ -120,7 +143,7 @@ impl SampledTexture {
             // Write descriptor set to bind the texture and sampler
             let image_info = [vk::DescriptorImageInfo::default()
                 .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                .image_view(texture.image_view)];
+                .image_view(image_view)];
 
             let sampler_info_write = [vk::DescriptorImageInfo::default()
                 .sampler(sampler)];
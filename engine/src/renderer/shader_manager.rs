@@ -19,6 +19,18 @@ pub enum ShaderId {
     TriangleFrag,
     TextVertex,
     TextFrag,
+    ImageVertex,
+    ImageFrag,
+    SceneVertex,
+    SceneFrag,
+    BackdropVertex,
+    BackdropFrag,
+    BloomVertex,
+    BloomFrag,
+    FxaaVertex,
+    FxaaFrag,
+    Light2DVertex,
+    Light2DFrag,
 }
 
 // Static metadata associated with each shader
@@ -47,6 +59,54 @@ impl ShaderId {
                 path: "text.frag",
                 stage: Fragment,
             },
+            ShaderId::ImageVertex => ShaderMeta {
+                path: "image.vert",
+                stage: Vertex,
+            },
+            ShaderId::ImageFrag => ShaderMeta {
+                path: "image.frag",
+                stage: Fragment,
+            },
+            ShaderId::SceneVertex => ShaderMeta {
+                path: "scene.vert",
+                stage: Vertex,
+            },
+            ShaderId::SceneFrag => ShaderMeta {
+                path: "scene.frag",
+                stage: Fragment,
+            },
+            ShaderId::BackdropVertex => ShaderMeta {
+                path: "backdrop.vert",
+                stage: Vertex,
+            },
+            ShaderId::BackdropFrag => ShaderMeta {
+                path: "backdrop.frag",
+                stage: Fragment,
+            },
+            ShaderId::BloomVertex => ShaderMeta {
+                path: "bloom.vert",
+                stage: Vertex,
+            },
+            ShaderId::BloomFrag => ShaderMeta {
+                path: "bloom.frag",
+                stage: Fragment,
+            },
+            ShaderId::FxaaVertex => ShaderMeta {
+                path: "fxaa.vert",
+                stage: Vertex,
+            },
+            ShaderId::FxaaFrag => ShaderMeta {
+                path: "fxaa.frag",
+                stage: Fragment,
+            },
+            ShaderId::Light2DVertex => ShaderMeta {
+                path: "light2d.vert",
+                stage: Vertex,
+            },
+            ShaderId::Light2DFrag => ShaderMeta {
+                path: "light2d.frag",
+                stage: Fragment,
+            },
         }
     }
 
@@ -70,6 +130,43 @@ impl ShaderId {
         Ok(aligned_bytes)
     }
 
+    /// Parse and validate the GLSL source
+    fn parse(&self) -> Result<(naga::Module, naga::valid::ModuleInfo)> {
+        let mut file = fs::File::open(self.path())?;
+        let mut source_bytes = Vec::new();
+        file.read_to_end(&mut source_bytes)?;
+        let mut cursor = Cursor::new(source_bytes);
+        let mut source_string = String::new();
+        cursor.read_to_string(&mut source_string)?;
+
+        // Parse GLSL with naga
+        let mut frontend = naga::front::glsl::Frontend::default();
+        let module = frontend.parse(
+            &naga::front::glsl::Options::from(self.meta().stage),
+            &source_string,
+        )?;
+
+        // Validate module
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)?;
+
+        Ok((module, info))
+    }
+
+    /// Size in bytes of the shader's `push_constant` block, `None` if it has none
+    pub fn push_constant_size(&self) -> Result<Option<u32>> {
+        let (module, _) = self.parse()?;
+        let size = module
+            .global_variables
+            .iter()
+            .find(|(_, var)| var.space == naga::AddressSpace::PushConstant)
+            .map(|(_, var)| module.types[var.ty].inner.size(module.to_ctx()));
+        Ok(size)
+    }
+
     pub fn stage(&self) -> naga::ShaderStage {
         self.meta().stage
     }
@@ -94,29 +191,7 @@ impl ShaderManager {
     }
 
     pub fn compile_shader(&self, shader_id: ShaderId) -> Result<String> {
-        let meta = shader_id.meta();
-        let shader_path = shader_id.path();
-
-        let mut file = fs::File::open(&shader_path)?;
-        let mut source_bytes = Vec::new();
-        file.read_to_end(&mut source_bytes)?;
-        let mut cursor = Cursor::new(source_bytes);
-        let mut source_string = String::new();
-        cursor.read_to_string(&mut source_string)?;
-
-        // Parse GLSL with naga
-        let mut frontend = naga::front::glsl::Frontend::default();
-        let module = frontend.parse(
-            &naga::front::glsl::Options::from(meta.stage),
-            &source_string,
-        )?;
-
-        // Validate module
-        let info = naga::valid::Validator::new(
-            naga::valid::ValidationFlags::all(),
-            naga::valid::Capabilities::all(),
-        )
-        .validate(&module)?;
+        let (module, info) = shader_id.parse()?;
 
         // Compile to SPIR-V
         let spirv = spv::write_vec(&module, &info, &spv::Options::default(), None)?;
@@ -125,7 +200,7 @@ impl ShaderManager {
         let spv_path = shader_id.compiled_path_str();
         fs::write(&spv_path, bytemuck::cast_slice::<u32, u8>(&spirv))?;
 
-        println!("Compiled shader {:?} -> {:?}", shader_id, spv_path);
+        log::debug!("Compiled shader {:?} -> {:?}", shader_id, spv_path);
         Ok(spv_path)
     }
 }
@@ -9,17 +9,89 @@ pub struct Swapchain {
 	pub extent: vk::Extent2D,
 	device: Arc<Device>,
 	swapchain_loader: Arc<ash::khr::swapchain::Device>,
+	surface_loader: ash::khr::surface::Instance,
+	physical_device: vk::PhysicalDevice,
 	surface: vk::SurfaceKHR,
 	surface_format: vk::SurfaceFormatKHR,
 	present_mode: vk::PresentModeKHR,
+	/// Requested image count, raised or lowered to what the surface allows
 	min_image_count: u32,
 	queue_family_indices: Vec<u32>,
 }
 
+/// Limits of the surface at swapchain creation, they change with the window
+struct SurfaceLimits {
+	extent: vk::Extent2D,
+	image_count: u32,
+	pre_transform: vk::SurfaceTransformFlagsKHR,
+	composite_alpha: vk::CompositeAlphaFlagsKHR,
+}
+
+impl SurfaceLimits {
+	/// Clamp the requested extent and image count to the surface capabilities
+	fn query(
+		surface_loader: &ash::khr::surface::Instance,
+		physical_device: vk::PhysicalDevice,
+		surface: vk::SurfaceKHR,
+		extent: vk::Extent2D,
+		min_image_count: u32,
+	) -> SurfaceLimits {
+		let capabilities = match unsafe {
+			surface_loader.get_physical_device_surface_capabilities(physical_device, surface)
+		} {
+			Ok(capabilities) => capabilities,
+			Err(e) => {
+				log::warn!("Could not query surface capabilities ({:?}), using the requested swapchain settings", e);
+				return SurfaceLimits {
+					extent,
+					image_count: min_image_count,
+					pre_transform: vk::SurfaceTransformFlagsKHR::IDENTITY,
+					composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
+				};
+			}
+		};
+
+		// u32::MAX means the surface takes its size from the swapchain (Wayland)
+		let extent = if capabilities.current_extent.width != u32::MAX {
+			capabilities.current_extent
+		} else {
+			vk::Extent2D {
+				width: extent.width.clamp(capabilities.min_image_extent.width, capabilities.max_image_extent.width),
+				height: extent.height.clamp(capabilities.min_image_extent.height, capabilities.max_image_extent.height),
+			}
+		};
+
+		// A max of 0 means no upper limit
+		let mut image_count = min_image_count.max(capabilities.min_image_count);
+		if capabilities.max_image_count > 0 {
+			image_count = image_count.min(capabilities.max_image_count);
+		}
+
+		let composite_alpha = [
+			vk::CompositeAlphaFlagsKHR::OPAQUE,
+			vk::CompositeAlphaFlagsKHR::INHERIT,
+			vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+			vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+		]
+		.into_iter()
+		.find(|&mode| capabilities.supported_composite_alpha.contains(mode))
+		.unwrap_or(vk::CompositeAlphaFlagsKHR::OPAQUE);
+
+		SurfaceLimits {
+			extent,
+			image_count,
+			pre_transform: capabilities.current_transform,
+			composite_alpha,
+		}
+	}
+}
+
 impl Swapchain {
 	pub fn new(
 		device: &Arc<Device>,
 		swapchain_loader: &ash::khr::swapchain::Device,
+		surface_loader: &ash::khr::surface::Instance,
+		physical_device: vk::PhysicalDevice,
 		surface_format: vk::SurfaceFormatKHR,
 		extent: vk::Extent2D,
 		surface: vk::SurfaceKHR,
@@ -30,6 +102,8 @@ impl Swapchain {
 		Self::create_swapchain_internal(
 			device,
 			swapchain_loader,
+			surface_loader,
+			physical_device,
 			surface_format,
 			extent,
 			surface,
@@ -40,6 +114,37 @@ impl Swapchain {
 		)
 	}
 
+	pub fn present_mode(&self) -> vk::PresentModeKHR {
+		self.present_mode
+	}
+
+	/// Recreate the swapchain with another present mode, e.g. to turn vsync on or off
+	pub fn set_present_mode(&mut self, present_mode: vk::PresentModeKHR) {
+		if present_mode != self.present_mode {
+			self.present_mode = present_mode;
+			self.recreate(self.extent);
+		}
+	}
+
+	/// Destroy the swapchain ahead of losing its surface, `set_surface` and `recreate`
+	/// build a new one
+	pub fn release(&mut self) {
+		unsafe {
+			let _ = self.device.device_wait_idle();
+			for image_view in self.image_views.drain(..) {
+				self.device.destroy_image_view(image_view, None);
+			}
+			self.swapchain_loader.destroy_swapchain(self.swapchain, None);
+		}
+		self.swapchain = vk::SwapchainKHR::null();
+		self.images.clear();
+	}
+
+	/// Surface the next `recreate` creates the swapchain for
+	pub fn set_surface(&mut self, surface: vk::SurfaceKHR) {
+		self.surface = surface;
+	}
+
 	pub fn recreate(
 		&mut self,
 		extent: vk::Extent2D,
@@ -56,6 +161,8 @@ impl Swapchain {
 		let new_swapchain = Self::create_swapchain_internal(
 			&self.device,
 			&self.swapchain_loader,
+			&self.surface_loader,
+			self.physical_device,
 			self.surface_format,
 			extent,
 			self.surface,
@@ -80,6 +187,7 @@ impl Swapchain {
 		unsafe {
 			std::ptr::drop_in_place(&mut new_swapchain.device);
 			std::ptr::drop_in_place(&mut new_swapchain.swapchain_loader);
+			std::ptr::drop_in_place(&mut new_swapchain.surface_loader);
 		}
 
 		// NOW destroy old resources after ensuring device is idle again
@@ -95,6 +203,8 @@ impl Swapchain {
 	fn create_swapchain_internal(
 		device: &Arc<Device>,
 		swapchain_loader: &ash::khr::swapchain::Device,
+		surface_loader: &ash::khr::surface::Instance,
+		physical_device: vk::PhysicalDevice,
 		surface_format: vk::SurfaceFormatKHR,
 		extent: vk::Extent2D,
 		surface: vk::SurfaceKHR,
@@ -103,11 +213,14 @@ impl Swapchain {
 		queue_family_indices: &[u32],
 		old_swapchain: vk::SwapchainKHR,
 	) -> Swapchain {
-		println!("Creating swapchain with format: {:?}, extent: {}x{}", surface_format.format, extent.width, extent.height);
+		// The window may have been resized again since `extent` was read
+		let limits = SurfaceLimits::query(surface_loader, physical_device, surface, extent, min_image_count);
+		let extent = limits.extent;
+		log::info!("Creating swapchain with format: {:?}, extent: {}x{}", surface_format.format, extent.width, extent.height);
 		
 		let swapchain_create_info = vk::SwapchainCreateInfoKHR {
 			surface,
-			min_image_count,
+			min_image_count: limits.image_count,
 			image_format: surface_format.format,
 			image_color_space: surface_format.color_space,
 			image_extent: extent,
@@ -120,8 +233,8 @@ impl Swapchain {
 			} else {
 				std::ptr::null()
 			},
-			pre_transform: vk::SurfaceTransformFlagsKHR::IDENTITY,
-			composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
+			pre_transform: limits.pre_transform,
+			composite_alpha: limits.composite_alpha,
 			present_mode,
 			clipped: vk::TRUE,
 			old_swapchain,
@@ -178,6 +291,8 @@ impl Swapchain {
 			extent,
 			device: device.clone(),
 			swapchain_loader: Arc::new(swapchain_loader.clone()),
+			surface_loader: surface_loader.clone(),
+			physical_device,
 			surface,
 			surface_format,
 			present_mode,
@@ -8,9 +8,10 @@ use ash::vk::{
     CommandBufferBeginInfo, ImageMemoryBarrier, AccessFlags, PipelineStageFlags,
     DeviceMemory,
 };
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use super::buffer_utils::find_memory_type;
+use super::buffer_utils::{find_memory_type, track_allocation, track_free, MemoryCategory};
 
 /// Represents a GPU texture with its image and view
 pub struct Texture {
@@ -20,6 +21,12 @@ pub struct Texture {
     pub width: u32,
     pub height: u32,
     pub format: Format,
+    /// Levels in the mip chain, level 0 is `width` x `height`
+    mip_levels: u32,
+    allocation_size: u64,
+    category: MemoryCategory,
+    /// Set once a pass has drawn into the texture, so later passes can keep its contents
+    rendered: AtomicBool,
 }
 
 impl Texture {
@@ -45,36 +52,61 @@ impl Texture {
         queue_family_index: u32,
     ) -> Result<Self> {
         unsafe {
-            // Create staging buffer
-            let buffer_size = data.len() as u64;
-            let staging_buffer_info = ash::vk::BufferCreateInfo::default()
-                .size(buffer_size)
-                .usage(ash::vk::BufferUsageFlags::TRANSFER_SRC)
-                .sharing_mode(SharingMode::EXCLUSIVE);
-            
-            let staging_buffer = device.create_buffer(&staging_buffer_info, None)?;
-            let staging_mem_req = device.get_buffer_memory_requirements(staging_buffer);
-            
-            let staging_mem_type = find_memory_type(
-                instance,
-                physical_device,
-                &staging_mem_req,
-                MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
-            )?;
-            
-            let staging_alloc_info = ash::vk::MemoryAllocateInfo::default()
-                .allocation_size(staging_mem_req.size)
-                .memory_type_index(staging_mem_type);
-            
-            let staging_memory = device.allocate_memory(&staging_alloc_info, None)?;
-            device.bind_buffer_memory(staging_buffer, staging_memory, 0)?;
-            
-            // Copy pixel data to staging buffer
-            let ptr = device.map_memory(staging_memory, 0, buffer_size, ash::vk::MemoryMapFlags::empty())?;
-            std::ptr::copy_nonoverlapping(data.as_ptr(), ptr as *mut u8, data.len());
-            device.unmap_memory(staging_memory);
-            
-            // Create optimal tiled image
+            let (staging_buffer, staging_memory) = Self::staging_buffer(data, device, instance, physical_device)?;
+            let (image, memory, allocation_size) = Self::sampled_image(width, height, format, 1, device, instance, physical_device)?;
+
+            // Transfer image data using a one-time command buffer
+            let result = Self::transition_and_copy_image(
+                device,
+                queue_family_index,
+                image,
+                staging_buffer,
+                ImageLayout::UNDEFINED,
+                1,
+                &[Self::copy_region(0, 0, ash::vk::Offset3D { x: 0, y: 0, z: 0 }, Extent3D { width, height, depth: 1 })],
+            );
+
+            // Clean up staging resources
+            device.destroy_buffer(staging_buffer, None);
+            device.free_memory(staging_memory, None);
+            let image_view = result.and_then(|()| Self::sampled_view(image, format, 1, device));
+            let image_view = match image_view {
+                Ok(view) => view,
+                Err(e) => {
+                    Self::free_image(image, memory, allocation_size, device);
+                    return Err(e);
+                }
+            };
+
+            Ok(Texture {
+                image,
+                image_view,
+                memory,
+                width,
+                height,
+                format,
+                mip_levels: 1,
+                allocation_size,
+                category: MemoryCategory::Textures,
+                rendered: AtomicBool::new(false),
+            })
+        }
+    }
+
+    /// Create an empty texture that can be rendered into and then sampled
+    ///
+    /// The image starts in UNDEFINED layout, `RenderFrame::render_to_texture` handles the transitions.
+    /// `format` must match the color format of the pipelines drawing into it.
+    /// Pixels can be read back with `Renderer::pick`.
+    pub fn render_target(
+        width: u32,
+        height: u32,
+        format: Format,
+        device: &Arc<ash::Device>,
+        instance: &ash::Instance,
+        physical_device: ash::vk::PhysicalDevice,
+    ) -> Result<Self> {
+        unsafe {
             let image_info = ImageCreateInfo::default()
                 .image_type(ImageType::TYPE_2D)
                 .format(format)
@@ -87,7 +119,7 @@ impl Texture {
                 .array_layers(1)
                 .samples(SampleCountFlags::TYPE_1)
                 .tiling(ImageTiling::OPTIMAL)
-                .usage(ImageUsageFlags::TRANSFER_DST | ImageUsageFlags::SAMPLED)
+                .usage(ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_SRC)
                 .sharing_mode(SharingMode::EXCLUSIVE)
                 .initial_layout(ImageLayout::UNDEFINED);
 
@@ -106,34 +138,15 @@ impl Texture {
                 .memory_type_index(mem_type);
 
             let memory = device.allocate_memory(&alloc_info, None)?;
+            track_allocation(MemoryCategory::RenderTargets, mem_req.size);
             device.bind_image_memory(image, memory, 0)?;
 
-            // Transfer image data using a one-time command buffer
-            Self::transition_and_copy_image(
-                device,
-                queue_family_index,
-                image,
-                staging_buffer,
-                width,
-                height,
-            )?;
-
-            // Clean up staging resources
-            device.destroy_buffer(staging_buffer, None);
-            device.free_memory(staging_memory, None);
-            
-            // Create image view
             let image_view = device.create_image_view(
                 &ImageViewCreateInfo::default()
                     .image(image)
                     .view_type(ImageViewType::TYPE_2D)
                     .format(format)
-                    .components(ComponentMapping {
-                        r: ash::vk::ComponentSwizzle::IDENTITY,
-                        g: ash::vk::ComponentSwizzle::IDENTITY,
-                        b: ash::vk::ComponentSwizzle::IDENTITY,
-                        a: ash::vk::ComponentSwizzle::IDENTITY,
-                    })
+                    .components(ComponentMapping::default())
                     .subresource_range(ImageSubresourceRange {
                         aspect_mask: ImageAspectFlags::COLOR,
                         base_mip_level: 0,
@@ -151,19 +164,137 @@ impl Texture {
                 width,
                 height,
                 format,
+                mip_levels: 1,
+                allocation_size: mem_req.size,
+                category: MemoryCategory::RenderTargets,
+                rendered: AtomicBool::new(false),
             })
         }
     }
 
+    /// Whether a render pass has drawn into this texture yet
+    pub fn is_rendered(&self) -> bool {
+        self.rendered.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_rendered(&self) {
+        self.rendered.store(true, Ordering::Relaxed);
+    }
+
+    /// Replace the texels of a `width` x `height` region at (`x`, `y`), row 0 at the top,
+    /// with `data` in the texture's format, rows tightly packed
+    ///
+    /// For textures created from bytes or images, e.g. video frames or a changing atlas.
+    /// Blocks until the copy is done; draws submitted earlier still sample the old texels.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_region(
+        &self,
+        data: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        device: &Arc<ash::Device>,
+        instance: &ash::Instance,
+        physical_device: ash::vk::PhysicalDevice,
+        queue_family_index: u32,
+    ) -> Result<()> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        if x + width > self.width || y + height > self.height {
+            anyhow::bail!(
+                "Region {}x{} at ({}, {}) is outside the {}x{} texture",
+                width, height, x, y, self.width, self.height
+            );
+        }
+        let texels = width as usize * height as usize;
+        if data.is_empty() || !data.len().is_multiple_of(texels) {
+            anyhow::bail!("{} bytes don't fill a {}x{} region", data.len(), width, height);
+        }
+        unsafe {
+            let (staging_buffer, staging_memory) = Self::staging_buffer(data, device, instance, physical_device)?;
+            let result = Self::transition_and_copy_image(
+                device,
+                queue_family_index,
+                self.image,
+                staging_buffer,
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                self.mip_levels,
+                &[Self::copy_region(0, 0, ash::vk::Offset3D { x: x as i32, y: y as i32, z: 0 }, Extent3D { width, height, depth: 1 })],
+            );
+            device.destroy_buffer(staging_buffer, None);
+            device.free_memory(staging_memory, None);
+            result
+        }
+    }
+
+    /// Host visible buffer holding a copy of `data`, the caller destroys both
+    unsafe fn staging_buffer(
+        data: &[u8],
+        device: &Arc<ash::Device>,
+        instance: &ash::Instance,
+        physical_device: ash::vk::PhysicalDevice,
+    ) -> Result<(ash::vk::Buffer, DeviceMemory)> {
+        let buffer_size = data.len() as u64;
+        let staging_buffer_info = ash::vk::BufferCreateInfo::default()
+            .size(buffer_size)
+            .usage(ash::vk::BufferUsageFlags::TRANSFER_SRC)
+            .sharing_mode(SharingMode::EXCLUSIVE);
+
+        let staging_buffer = device.create_buffer(&staging_buffer_info, None)?;
+        let staging_mem_req = device.get_buffer_memory_requirements(staging_buffer);
+
+        let staging_mem_type = find_memory_type(
+            instance,
+            physical_device,
+            &staging_mem_req,
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+
+        let staging_alloc_info = ash::vk::MemoryAllocateInfo::default()
+            .allocation_size(staging_mem_req.size)
+            .memory_type_index(staging_mem_type);
+
+        let staging_memory = device.allocate_memory(&staging_alloc_info, None)?;
+        device.bind_buffer_memory(staging_buffer, staging_memory, 0)?;
+
+        // Copy pixel data to staging buffer
+        let ptr = device.map_memory(staging_memory, 0, buffer_size, ash::vk::MemoryMapFlags::empty())?;
+        std::ptr::copy_nonoverlapping(data.as_ptr(), ptr as *mut u8, data.len());
+        device.unmap_memory(staging_memory);
+        Ok((staging_buffer, staging_memory))
+    }
+
+    /// Copy of `extent` texels at `offset` of mip `level` from `buffer_offset` in a staging buffer
+    fn copy_region(level: u32, buffer_offset: u64, offset: ash::vk::Offset3D, extent: Extent3D) -> ash::vk::BufferImageCopy {
+        ash::vk::BufferImageCopy::default()
+            .buffer_offset(buffer_offset)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(
+                ash::vk::ImageSubresourceLayers::default()
+                    .aspect_mask(ImageAspectFlags::COLOR)
+                    .mip_level(level)
+                    .base_array_layer(0)
+                    .layer_count(1)
+            )
+            .image_offset(offset)
+            .image_extent(extent)
+    }
+
     /// Transition image layout and copy from staging buffer
-    /// This is the reusable "barrier transition" logic
+    /// This is the reusable "barrier transition" logic. `old_layout` is UNDEFINED for new
+    /// images, SHADER_READ_ONLY_OPTIMAL keeps the texels outside the copied regions.
+    /// All `level_count` mip levels are transitioned.
     unsafe fn transition_and_copy_image(
         device: &Arc<ash::Device>,
         queue_family_index: u32,
         image: Image,
         staging_buffer: ash::vk::Buffer,
-        width: u32,
-        height: u32,
+        old_layout: ImageLayout,
+        level_count: u32,
+        regions: &[ash::vk::BufferImageCopy],
     ) -> Result<()> {
         // Create temporary command pool for one-time commands
         let pool_create_info = ash::vk::CommandPoolCreateInfo::default()
@@ -184,10 +315,16 @@ impl Texture {
             .flags(ash::vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
         device.begin_command_buffer(cmd_buffer, &begin_info)?;
         
-        // BARRIER 1: Transition UNDEFINED → TRANSFER_DST_OPTIMAL
-        // This prepares the image to receive data from the staging buffer
+        // BARRIER 1: Transition UNDEFINED (or SHADER_READ_ONLY) → TRANSFER_DST_OPTIMAL
+        // This prepares the image to receive data from the staging buffer, after earlier
+        // submissions are done sampling it
+        let (src_access, src_stage) = if old_layout == ImageLayout::UNDEFINED {
+            (AccessFlags::empty(), PipelineStageFlags::TOP_OF_PIPE)
+        } else {
+            (AccessFlags::SHADER_READ, PipelineStageFlags::FRAGMENT_SHADER)
+        };
         let barrier = ImageMemoryBarrier::default()
-            .old_layout(ImageLayout::UNDEFINED)
+            .old_layout(old_layout)
             .new_layout(ImageLayout::TRANSFER_DST_OPTIMAL)
             .src_queue_family_index(ash::vk::QUEUE_FAMILY_IGNORED)
             .dst_queue_family_index(ash::vk::QUEUE_FAMILY_IGNORED)
@@ -196,16 +333,16 @@ impl Texture {
                 ImageSubresourceRange::default()
                     .aspect_mask(ImageAspectFlags::COLOR)
                     .base_mip_level(0)
-                    .level_count(1)
+                    .level_count(level_count)
                     .base_array_layer(0)
                     .layer_count(1)
             )
-            .src_access_mask(AccessFlags::empty())
+            .src_access_mask(src_access)
             .dst_access_mask(AccessFlags::TRANSFER_WRITE);
         
         device.cmd_pipeline_barrier(
             cmd_buffer,
-            PipelineStageFlags::TOP_OF_PIPE,  // Wait for nothing (we just created it)
+            src_stage,                         // Wait for nothing new, or for earlier sampling
             PipelineStageFlags::TRANSFER,      // Block transfer operations until transition completes
             ash::vk::DependencyFlags::empty(),
             &[],
@@ -214,26 +351,12 @@ impl Texture {
         );
         
         // Copy buffer to image
-        let region = ash::vk::BufferImageCopy::default()
-            .buffer_offset(0)
-            .buffer_row_length(0)
-            .buffer_image_height(0)
-            .image_subresource(
-                ash::vk::ImageSubresourceLayers::default()
-                    .aspect_mask(ImageAspectFlags::COLOR)
-                    .mip_level(0)
-                    .base_array_layer(0)
-                    .layer_count(1)
-            )
-            .image_offset(ash::vk::Offset3D { x: 0, y: 0, z: 0 })
-            .image_extent(Extent3D { width, height, depth: 1 });
-        
         device.cmd_copy_buffer_to_image(
             cmd_buffer,
             staging_buffer,
             image,
             ImageLayout::TRANSFER_DST_OPTIMAL,
-            &[region],
+            regions,
         );
         
         // BARRIER 2: Transition TRANSFER_DST_OPTIMAL → SHADER_READ_ONLY_OPTIMAL
@@ -248,7 +371,7 @@ impl Texture {
                 ImageSubresourceRange::default()
                     .aspect_mask(ImageAspectFlags::COLOR)
                     .base_mip_level(0)
-                    .level_count(1)
+                    .level_count(level_count)
                     .base_array_layer(0)
                     .layer_count(1)
             )
@@ -294,12 +417,20 @@ impl Texture {
         let img = image::open(path)
             .map_err(|e| anyhow::anyhow!("Failed to load image '{}': {}", path, e))?;
         
-        let img_rgba = img.to_rgba8();
-        let (width, height) = img_rgba.dimensions();
-        let data = img_rgba.as_raw();
-        
+        Self::from_image(&img.to_rgba8(), device, instance, physical_device, queue_family_index)
+    }
+
+    /// Upload an already decoded image, e.g. one decoded on a worker by `assets::LoadBatch`
+    pub fn from_image(
+        image: &image::RgbaImage,
+        device: &Arc<ash::Device>,
+        instance: &ash::Instance,
+        physical_device: ash::vk::PhysicalDevice,
+        queue_family_index: u32,
+    ) -> Result<Self> {
+        let (width, height) = image.dimensions();
         Self::from_bytes(
-            data,
+            image.as_raw(),
             width,
             height,
             Format::R8G8B8A8_SRGB,  // Standard RGBA format
@@ -310,6 +441,173 @@ impl Texture {
         )
     }
 
+    /// Upload a mip chain, `levels[0]` is the largest and each next one half its size
+    /// (rounded down, at least 1), e.g. the resident part of a texture streamed by `MipStreamer`
+    pub fn from_mips(
+        levels: &[image::RgbaImage],
+        device: &Arc<ash::Device>,
+        instance: &ash::Instance,
+        physical_device: ash::vk::PhysicalDevice,
+        queue_family_index: u32,
+    ) -> Result<Self> {
+        let Some(first) = levels.first() else {
+            anyhow::bail!("A mip chain needs at least one level");
+        };
+        let (width, height) = first.dimensions();
+        for (level, image) in levels.iter().enumerate() {
+            let expected = ((width >> level).max(1), (height >> level).max(1));
+            if image.dimensions() != expected {
+                anyhow::bail!("Mip {} is {:?}, expected {:?}", level, image.dimensions(), expected);
+            }
+        }
+
+        // Levels one after another in the staging buffer, RGBA8 keeps the offsets 4 byte aligned
+        let mut data = Vec::with_capacity(levels.iter().map(|level| level.as_raw().len()).sum());
+        let mut regions = Vec::with_capacity(levels.len());
+        for (level, image) in levels.iter().enumerate() {
+            let extent = Extent3D { width: image.width(), height: image.height(), depth: 1 };
+            regions.push(Self::copy_region(level as u32, data.len() as u64, ash::vk::Offset3D::default(), extent));
+            data.extend_from_slice(image.as_raw());
+        }
+
+        let format = Format::R8G8B8A8_SRGB;
+        let mip_levels = levels.len() as u32;
+        unsafe {
+            let (staging_buffer, staging_memory) = Self::staging_buffer(&data, device, instance, physical_device)?;
+            let (image, memory, allocation_size) = Self::sampled_image(width, height, format, mip_levels, device, instance, physical_device)?;
+            let result = Self::transition_and_copy_image(
+                device,
+                queue_family_index,
+                image,
+                staging_buffer,
+                ImageLayout::UNDEFINED,
+                mip_levels,
+                &regions,
+            );
+            device.destroy_buffer(staging_buffer, None);
+            device.free_memory(staging_memory, None);
+            let image_view = match result.and_then(|()| Self::sampled_view(image, format, mip_levels, device)) {
+                Ok(view) => view,
+                Err(e) => {
+                    Self::free_image(image, memory, allocation_size, device);
+                    return Err(e);
+                }
+            };
+
+            Ok(Texture {
+                image,
+                image_view,
+                memory,
+                width,
+                height,
+                format,
+                mip_levels,
+                allocation_size,
+                category: MemoryCategory::Textures,
+                rendered: AtomicBool::new(false),
+            })
+        }
+    }
+
+    /// Device local image with `mip_levels` levels to upload to and sample, and its memory
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn sampled_image(
+        width: u32,
+        height: u32,
+        format: Format,
+        mip_levels: u32,
+        device: &Arc<ash::Device>,
+        instance: &ash::Instance,
+        physical_device: ash::vk::PhysicalDevice,
+    ) -> Result<(Image, DeviceMemory, u64)> {
+        // Create optimal tiled image
+        let image_info = ImageCreateInfo::default()
+            .image_type(ImageType::TYPE_2D)
+            .format(format)
+            .extent(Extent3D {
+                width,
+                height,
+                depth: 1,
+            })
+            .mip_levels(mip_levels)
+            .array_layers(1)
+            .samples(SampleCountFlags::TYPE_1)
+            .tiling(ImageTiling::OPTIMAL)
+            .usage(ImageUsageFlags::TRANSFER_DST | ImageUsageFlags::SAMPLED)
+            .sharing_mode(SharingMode::EXCLUSIVE)
+            .initial_layout(ImageLayout::UNDEFINED);
+
+        let image = device.create_image(&image_info, None)?;
+        let mem_req = device.get_image_memory_requirements(image);
+
+        let memory = find_memory_type(
+            instance,
+            physical_device,
+            &mem_req,
+            MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+        .and_then(|mem_type| {
+            let alloc_info = ash::vk::MemoryAllocateInfo::default()
+                .allocation_size(mem_req.size)
+                .memory_type_index(mem_type);
+            Ok(device.allocate_memory(&alloc_info, None)?)
+        });
+        let memory = match memory {
+            Ok(memory) => memory,
+            Err(e) => {
+                device.destroy_image(image, None);
+                return Err(e);
+            }
+        };
+        track_allocation(MemoryCategory::Textures, mem_req.size);
+        if let Err(e) = device.bind_image_memory(image, memory, 0) {
+            Self::free_image(image, memory, mem_req.size, device);
+            return Err(e.into());
+        }
+        Ok((image, memory, mem_req.size))
+    }
+
+    /// View of all `mip_levels` levels of a color image
+    unsafe fn sampled_view(image: Image, format: Format, mip_levels: u32, device: &Arc<ash::Device>) -> Result<ImageView> {
+        Ok(device.create_image_view(
+            &ImageViewCreateInfo::default()
+                .image(image)
+                .view_type(ImageViewType::TYPE_2D)
+                .format(format)
+                .components(ComponentMapping {
+                    r: ash::vk::ComponentSwizzle::IDENTITY,
+                    g: ash::vk::ComponentSwizzle::IDENTITY,
+                    b: ash::vk::ComponentSwizzle::IDENTITY,
+                    a: ash::vk::ComponentSwizzle::IDENTITY,
+                })
+                .subresource_range(ImageSubresourceRange {
+                    aspect_mask: ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: mip_levels,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                }),
+            None,
+        )?)
+    }
+
+    /// Free an image made by `sampled_image` that never became a Texture
+    unsafe fn free_image(image: Image, memory: DeviceMemory, allocation_size: u64, device: &ash::Device) {
+        device.destroy_image(image, None);
+        device.free_memory(memory, None);
+        track_free(MemoryCategory::Textures, allocation_size);
+    }
+
+    /// Levels in the mip chain
+    pub fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+
+    /// Bytes of device memory backing the image
+    pub fn allocation_size(&self) -> u64 {
+        self.allocation_size
+    }
+
     /// Manually destroy Vulkan resources
     pub fn destroy(&self, device: &ash::Device) {
         unsafe {
@@ -317,5 +615,6 @@ impl Texture {
             device.destroy_image(self.image, None);
             device.free_memory(self.memory, None);
         }
+        track_free(self.category, self.allocation_size);
     }
 }
\ No newline at end of file
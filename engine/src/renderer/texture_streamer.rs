@@ -0,0 +1,174 @@
+use anyhow::Result;
+use ash::vk;
+use std::collections::HashMap;
+
+use super::{Texture, VulkanContext};
+
+/// Frames an evicted texture is kept alive, the GPU may still be sampling it
+const RETIRE_FRAMES: u64 = 3;
+
+struct StreamedTexture {
+    texture: Texture,
+    last_used: u64,
+}
+
+/// Textures loaded from disk on first use and evicted least recently used first
+///
+/// Call `get` every frame a texture is drawn, a texture not requested for a while is
+/// the first to go once the resident textures exceed the budget. Textures used in the
+/// current frame are never evicted, so the budget can be exceeded for a frame that
+/// needs more than it allows. When the device runs out of memory while loading, unused
+/// textures are destroyed right away and the load is retried.
+///
+/// Views and descriptor sets created from a streamed texture are only valid for the
+/// frame it was returned in.
+pub struct TextureStreamer {
+    budget: u64,
+    resident: HashMap<String, StreamedTexture>,
+    resident_bytes: u64,
+    /// Evicted textures and the frame they were evicted in
+    retired: Vec<(u64, Texture)>,
+    frame: u64,
+}
+
+impl TextureStreamer {
+    /// `budget` is in bytes of device memory
+    pub fn new(budget: u64) -> Self {
+        TextureStreamer {
+            budget,
+            resident: HashMap::new(),
+            resident_bytes: 0,
+            retired: Vec::new(),
+            frame: 0,
+        }
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    /// Change the budget, evicting right away if the resident textures no longer fit
+    pub fn set_budget(&mut self, budget: u64) {
+        self.budget = budget;
+        self.enforce_budget();
+    }
+
+    /// Bytes of device memory held by resident textures (evicted ones waiting for the GPU excluded)
+    pub fn resident_bytes(&self) -> u64 {
+        self.resident_bytes
+    }
+
+    pub fn resident_count(&self) -> usize {
+        self.resident.len()
+    }
+
+    pub fn is_resident(&self, path: &str) -> bool {
+        self.resident.contains_key(path)
+    }
+
+    /// Start a new frame, destroying evicted textures the GPU is done with
+    pub fn next_frame(&mut self, device: &ash::Device) {
+        self.frame += 1;
+        let frame = self.frame;
+        self.retired.retain(|(evicted, texture)| {
+            let done = frame - evicted > RETIRE_FRAMES;
+            if done {
+                texture.destroy(device);
+            }
+            !done
+        });
+    }
+
+    /// The texture at `path`, loading it if it isn't resident
+    pub fn get(&mut self, path: &str, context: &VulkanContext) -> Result<&Texture> {
+        if !self.resident.contains_key(path) {
+            let texture = self.load(path, context)?;
+            self.resident_bytes += texture.allocation_size();
+            self.resident.insert(path.to_string(), StreamedTexture { texture, last_used: self.frame });
+            self.enforce_budget();
+        }
+
+        let entry = self.resident.get_mut(path).unwrap();
+        entry.last_used = self.frame;
+        Ok(&entry.texture)
+    }
+
+    /// Load a texture, freeing unused ones while the device is out of memory
+    fn load(&mut self, path: &str, context: &VulkanContext) -> Result<Texture> {
+        loop {
+            let result = Texture::from_file(
+                path,
+                &context.device,
+                &context.instance,
+                context.physical_device,
+                context.queue_family_indices[0],
+            );
+            let out_of_memory = matches!(
+                result.as_ref().err().and_then(|e| e.downcast_ref::<vk::Result>()),
+                Some(&vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            );
+            if !out_of_memory || !self.free_for_oom(&context.device)? {
+                return result;
+            }
+            log::warn!("Out of device memory loading '{}', evicted textures and retrying", path);
+        }
+    }
+
+    /// Destroy retired textures and the least recently used resident one immediately
+    /// Returns false when nothing could be freed
+    fn free_for_oom(&mut self, device: &ash::Device) -> Result<bool> {
+        let victim = self.least_recently_used();
+        if self.retired.is_empty() && victim.is_none() {
+            return Ok(false);
+        }
+
+        unsafe { device.device_wait_idle()? };
+        for (_, texture) in self.retired.drain(..) {
+            texture.destroy(device);
+        }
+        if let Some(path) = victim {
+            let evicted = self.resident.remove(&path).unwrap();
+            self.resident_bytes -= evicted.texture.allocation_size();
+            evicted.texture.destroy(device);
+        }
+        Ok(true)
+    }
+
+    /// Retire least recently used textures until the resident ones fit the budget
+    fn enforce_budget(&mut self) {
+        while self.resident_bytes > self.budget {
+            let Some(path) = self.least_recently_used() else {
+                log::warn!(
+                    "Textures used this frame need {:.1} MB, over the {:.1} MB budget",
+                    self.resident_bytes as f64 / (1024.0 * 1024.0),
+                    self.budget as f64 / (1024.0 * 1024.0),
+                );
+                break;
+            };
+            let evicted = self.resident.remove(&path).unwrap();
+            self.resident_bytes -= evicted.texture.allocation_size();
+            log::debug!("Evicted streamed texture '{}'", path);
+            self.retired.push((self.frame, evicted.texture));
+        }
+    }
+
+    /// Resident texture unused for the longest, textures used this frame excluded
+    fn least_recently_used(&self) -> Option<String> {
+        self.resident
+            .iter()
+            .filter(|(_, entry)| entry.last_used < self.frame)
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(path, _)| path.clone())
+    }
+
+    /// Destroy every texture, the device must be idle
+    pub fn destroy(&mut self, device: &ash::Device) {
+        for (_, entry) in self.resident.drain() {
+            entry.texture.destroy(device);
+        }
+        for (_, texture) in self.retired.drain(..) {
+            texture.destroy(device);
+        }
+        self.resident_bytes = 0;
+    }
+}
@@ -25,15 +25,13 @@ pub struct ModelVertex3D {
     pub uv: [f32; 2],
 }
 
-/// Push constants for rendering (projection + transform matrices + color modulation)
-#[repr(C)]
-#[derive(Clone, Copy, Debug)]
-pub struct PushConstants2D {
-    pub projection: glam::Mat4,
-    pub transform: glam::Mat4,
-    pub color_modulation: [f32; 3],  // RGB multiplier (e.g., [0.8, 0.8, 0.8] = 20% darker)
-    pub _padding: f32,  // Align to 16 bytes for uniform buffer rules
-}
+// Plain f32 fields without padding, so vertices can be compared and hashed as bytes
+unsafe impl bytemuck::Zeroable for ColorVertex2D {}
+unsafe impl bytemuck::Pod for ColorVertex2D {}
+unsafe impl bytemuck::Zeroable for TexturedVertex2D {}
+unsafe impl bytemuck::Pod for TexturedVertex2D {}
+unsafe impl bytemuck::Zeroable for ModelVertex3D {}
+unsafe impl bytemuck::Pod for ModelVertex3D {}
 
 /// Vertex format descriptor for pipeline creation
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -0,0 +1,26 @@
+use glam::Mat4;
+
+use crate::math::{Color, Rect};
+
+/// Region of the frame drawn from its own camera (split-screen players, editor panes)
+///
+/// `rect` is in framebuffer pixels, the same space as `Renderer::projection`.
+/// Draws of a view use `view_projection` and are clipped to the rect.
+#[derive(Clone, Copy, Debug)]
+pub struct View {
+    pub rect: Rect,
+    pub view_projection: Mat4,
+    /// Background filled in before the view is drawn, `None` draws over the frame's clear color
+    pub clear_color: Option<Color>,
+}
+
+impl View {
+    pub fn new(rect: Rect, view_projection: Mat4) -> Self {
+        View { rect, view_projection, clear_color: None }
+    }
+
+    pub fn with_clear_color(mut self, color: Color) -> Self {
+        self.clear_color = Some(color);
+        self
+    }
+}
@@ -0,0 +1,199 @@
+//! Recording and playback of gameplay input.
+//! A `Replay` is the seed of a session and, for every frame, the time step the game
+//! advanced by and the `InputState` it saw when that changed. Games that take their
+//! randomness from the seed (see `Replay::rng`) and their input and time only from the
+//! frames replay a session bit for bit, to debug desyncs, reproduce crashes or run
+//! automated smoke tests. `ReplayRecorder` writes one while playing, `ReplayPlayer`
+//! feeds it back.
+
+use anyhow::{anyhow, bail, Result};
+use std::path::Path;
+
+use crate::gui::InputState;
+use crate::math::rng::Rng;
+use crate::net::{ByteReader, ByteWriter};
+
+/// Start of replay files, followed by the format version
+const MAGIC: &[u8; 4] = b"RPLY";
+const VERSION: u16 = 1;
+
+const LEFT: u8 = 1;
+const MIDDLE: u8 = 2;
+const RIGHT: u8 = 4;
+
+/// One recorded frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayFrame {
+    /// Seconds the game advanced by
+    pub dt: f32,
+    /// Input from this frame on, `None` if it didn't change since the previous frame
+    pub input: Option<InputState>,
+}
+
+/// A recorded session, see the module docs
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Self {
+        Replay { seed, frames: Vec::new() }
+    }
+
+    /// The session's generator for `stream`, the same sequence when recording and replaying
+    /// Give every system that needs random numbers its own stream so they don't depend on
+    /// each other's call counts.
+    pub fn rng(&self, stream: u64) -> Rng {
+        Rng::with_stream(self.seed, stream)
+    }
+
+    /// Seconds the whole session took
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.dt).sum()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+        writer.raw(MAGIC);
+        writer.u16(VERSION);
+        writer.u64(self.seed);
+        writer.u32(self.frames.len() as u32);
+        for frame in &self.frames {
+            writer.f32(frame.dt);
+            writer.bool(frame.input.is_some());
+            if let Some(input) = frame.input {
+                writer.vec2(input.mouse);
+                writer.u8(
+                    if input.left { LEFT } else { 0 } | if input.middle { MIDDLE } else { 0 } | if input.right { RIGHT } else { 0 },
+                );
+            }
+        }
+        writer.into_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.raw(MAGIC.len()) != Some(&MAGIC[..]) {
+            bail!("Not a replay");
+        }
+        let version = reader.u16().ok_or_else(|| anyhow!("Replay is truncated"))?;
+        if version != VERSION {
+            bail!("Replay format {} is not supported, expected {}", version, VERSION);
+        }
+        Self::read_frames(&mut reader).ok_or_else(|| anyhow!("Replay is truncated"))
+    }
+
+    fn read_frames(reader: &mut ByteReader) -> Option<Self> {
+        let seed = reader.u64()?;
+        let count = reader.u32()? as usize;
+        // Each frame takes at least 5 bytes, a corrupt count can't allocate much
+        let mut frames = Vec::with_capacity(count.min(reader.remaining() / 5));
+        for _ in 0..count {
+            let dt = reader.f32()?;
+            let input = if reader.bool()? {
+                let mouse = reader.vec2()?;
+                let buttons = reader.u8()?;
+                Some(InputState { mouse, left: buttons & LEFT != 0, middle: buttons & MIDDLE != 0, right: buttons & RIGHT != 0 })
+            } else {
+                None
+            };
+            frames.push(ReplayFrame { dt, input });
+        }
+        Some(Replay { seed, frames })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| anyhow!("Failed to write '{}': {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).map_err(|e| anyhow!("Failed to read '{}': {}", path.display(), e))?;
+        Self::from_bytes(&bytes).map_err(|e| anyhow!("{} in '{}'", e, path.display()))
+    }
+}
+
+/// Records the frames of a session as it is played
+pub struct ReplayRecorder {
+    replay: Replay,
+    last_input: Option<InputState>,
+}
+
+impl ReplayRecorder {
+    /// Start a session, `seed` is where all its randomness comes from
+    pub fn new(seed: u64) -> Self {
+        ReplayRecorder { replay: Replay::new(seed), last_input: None }
+    }
+
+    /// A session seeded from the clock
+    pub fn from_time() -> Self {
+        Self::new(Rng::from_time().next_u64())
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.replay.seed
+    }
+
+    /// See `Replay::rng`
+    pub fn rng(&self, stream: u64) -> Rng {
+        self.replay.rng(stream)
+    }
+
+    /// A frame that advanced the game by `dt` with `input`, call once per game update
+    pub fn record(&mut self, dt: f32, input: &InputState) {
+        let changed = self.last_input != Some(*input);
+        self.last_input = Some(*input);
+        self.replay.frames.push(ReplayFrame { dt, input: changed.then_some(*input) });
+    }
+
+    /// The session so far, e.g. to save it when the game crashes
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn finish(self) -> Replay {
+        self.replay
+    }
+}
+
+/// Feeds a recorded session back one frame at a time
+pub struct ReplayPlayer {
+    replay: Replay,
+    next: usize,
+    input: InputState,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayer { replay, next: 0, input: InputState::new() }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// See `Replay::rng`
+    pub fn rng(&self, stream: u64) -> Rng {
+        self.replay.rng(stream)
+    }
+
+    /// Time step and input of the next frame, `None` once every frame was played
+    pub fn next_frame(&mut self) -> Option<(f32, InputState)> {
+        let frame = self.replay.frames.get(self.next)?;
+        self.next += 1;
+        if let Some(input) = frame.input {
+            self.input = input;
+        }
+        Some((frame.dt, self.input))
+    }
+
+    /// Frames played so far
+    pub fn position(&self) -> usize {
+        self.next
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.replay.frames.len()
+    }
+}
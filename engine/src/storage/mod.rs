@@ -0,0 +1,88 @@
+//! Files a game keeps between runs.
+//! `config_dir` and `data_dir` resolve the per-user directories of the platform
+//! (XDG on Linux, Application Support on macOS, AppData on Windows), `Settings` holds
+//! the user's preferences and `SaveGame` is a key-value store for save slots. Both are
+//! written as RON text, through a temporary file so a crash never leaves half a file.
+//! Hand-edited configuration can be TOML instead, see `read_toml`.
+
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+mod ron;
+pub use ron::Value;
+
+mod toml;
+pub use toml::parse_toml;
+
+mod settings;
+pub use settings::Settings;
+
+mod save;
+pub use save::SaveGame;
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env_dir("HOME").or_else(|| env_dir("USERPROFILE"))
+}
+
+/// Base directory for settings, before the app name is added
+fn config_base() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library/Application Support"))
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| home_dir().map(|home| home.join(".config")))
+    }
+}
+
+/// Base directory for saves and other data, before the app name is added
+fn data_base() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        env_dir("LOCALAPPDATA").or_else(|| env_dir("APPDATA"))
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library/Application Support"))
+    } else {
+        env_dir("XDG_DATA_HOME").or_else(|| home_dir().map(|home| home.join(".local/share")))
+    }
+}
+
+/// Directory for an app's settings, created if missing
+/// Falls back to the working directory when the platform's can't be found.
+pub fn config_dir(app: &str) -> Result<PathBuf> {
+    let dir = config_base().unwrap_or_else(|| PathBuf::from(".")).join(app);
+    std::fs::create_dir_all(&dir).map_err(|e| anyhow!("Failed to create '{}': {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Directory for an app's saves and other data, created if missing
+pub fn data_dir(app: &str) -> Result<PathBuf> {
+    let dir = data_base().unwrap_or_else(|| PathBuf::from(".")).join(app);
+    std::fs::create_dir_all(&dir).map_err(|e| anyhow!("Failed to create '{}': {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Replace a file's contents, writing a temporary file first and renaming it over
+/// the old one so readers see either the old or the new contents
+pub fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    std::fs::write(&temp, contents).map_err(|e| anyhow!("Failed to write '{}': {}", temp.display(), e))?;
+    std::fs::rename(&temp, path).map_err(|e| anyhow!("Failed to replace '{}': {}", path.display(), e))
+}
+
+/// Read and parse a RON file
+pub fn read_ron(path: &Path) -> Result<Value> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read '{}': {}", path.display(), e))?;
+    Value::parse(&text).ok_or_else(|| anyhow!("Invalid RON in '{}'", path.display()))
+}
+
+/// Read and parse a TOML file, see `parse_toml`
+pub fn read_toml(path: &Path) -> Result<Value> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read '{}': {}", path.display(), e))?;
+    parse_toml(&text).map_err(|e| anyhow!("Invalid TOML in '{}': {}", path.display(), e))
+}
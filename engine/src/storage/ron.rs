@@ -0,0 +1,224 @@
+use std::fmt::Write;
+
+/// A value of the RON subset used by settings and saves: numbers, booleans, strings,
+/// string-keyed maps and structs with named fields (optionally prefixed by a type name)
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Map(Vec<(String, Value)>),
+    Struct(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Entries of a map or fields of a struct
+    pub fn entries(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Map(entries) | Value::Struct(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries()?.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Write the value as indented RON, numbers that aren't finite as 0
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            // NaN and infinities have no text `parse` reads back, 0 keeps the document loadable
+            Value::Number(n) if !n.is_finite() => out.push('0'),
+            Value::Number(n) => {
+                // Whole numbers without a fraction so integer fields read back as written
+                if n.fract() == 0.0 && n.abs() < 1e15 {
+                    let _ = write!(out, "{}", *n as i64);
+                } else {
+                    let _ = write!(out, "{}", n);
+                }
+            }
+            Value::String(s) => write_string(out, s),
+            Value::Map(entries) | Value::Struct(entries) => {
+                let is_map = matches!(self, Value::Map(_));
+                let (open, close) = if is_map { ('{', '}') } else { ('(', ')') };
+                if entries.is_empty() {
+                    out.push(open);
+                    out.push(close);
+                    return;
+                }
+                out.push(open);
+                out.push('\n');
+                for (key, value) in entries {
+                    out.push_str(&"    ".repeat(indent + 1));
+                    if is_map {
+                        write_string(out, key);
+                    } else {
+                        out.push_str(key);
+                    }
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                    out.push_str(",\n");
+                }
+                out.push_str(&"    ".repeat(indent));
+                out.push(close);
+            }
+        }
+    }
+
+    /// Parse a document holding one value, `None` on any syntax error
+    pub fn parse(text: &str) -> Option<Value> {
+        let mut parser = Parser { chars: text.char_indices().peekable(), text };
+        let value = parser.value()?;
+        parser.skip_blank();
+        parser.chars.peek().is_none().then_some(value)
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    text: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    /// Skip whitespace and `//` comments
+    fn skip_blank(&mut self) {
+        while let Some(&(i, c)) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else if self.text[i..].starts_with("//") {
+                while self.chars.next_if(|&(_, c)| c != '\n').is_some() {}
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_blank();
+        self.chars.next_if(|&(_, c)| c == expected).is_some()
+    }
+
+    fn identifier(&mut self) -> Option<&'a str> {
+        self.skip_blank();
+        let &(start, _) = self.chars.peek()?;
+        let mut end = start;
+        while let Some((i, c)) = self.chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '_') {
+            end = i + c.len_utf8();
+        }
+        (end > start).then(|| &self.text[start..end])
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if !self.eat('"') {
+            return None;
+        }
+        let mut s = String::new();
+        loop {
+            match self.chars.next()?.1 {
+                '"' => return Some(s),
+                '\\' => s.push(match self.chars.next()?.1 {
+                    'n' => '\n',
+                    't' => '\t',
+                    c @ ('"' | '\\') => c,
+                    _ => return None,
+                }),
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        self.skip_blank();
+        let &(start, _) = self.chars.peek()?;
+        let mut end = start;
+        while let Some((i, c)) = self.chars.next_if(|&(_, c)| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+            end = i + c.len_utf8();
+        }
+        self.text[start..end].parse().ok()
+    }
+
+    /// Comma separated `key: value` entries up to `close`, a trailing comma is allowed
+    fn entries(&mut self, close: char, mut key: impl FnMut(&mut Self) -> Option<String>) -> Option<Vec<(String, Value)>> {
+        let mut entries = Vec::new();
+        loop {
+            if self.eat(close) {
+                return Some(entries);
+            }
+            let k = key(self)?;
+            if !self.eat(':') {
+                return None;
+            }
+            entries.push((k, self.value()?));
+            if !self.eat(',') {
+                return self.eat(close).then_some(entries);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.skip_blank();
+        let &(_, c) = self.chars.peek()?;
+        match c {
+            '"' => self.string().map(Value::String),
+            '{' => {
+                self.chars.next();
+                self.entries('}', Self::string).map(Value::Map)
+            }
+            '(' => {
+                self.chars.next();
+                self.entries(')', |p| p.identifier().map(str::to_string)).map(Value::Struct)
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => self.number().map(Value::Number),
+            _ => match self.identifier()? {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                // A named struct, e.g. `Settings(...)`
+                _ if self.eat('(') => self.entries(')', |p| p.identifier().map(str::to_string)).map(Value::Struct),
+                _ => None,
+            },
+        }
+    }
+}
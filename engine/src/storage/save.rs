@@ -0,0 +1,103 @@
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::storage::{data_dir, read_ron, write_atomic, Value};
+
+/// Key-value save data, one file per slot in the app's data directory
+///
+/// Values are stored as text and converted on access, so anything with `Display`
+/// and `FromStr` (numbers, booleans, strings) can be saved directly.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SaveGame {
+    values: BTreeMap<String, String>,
+}
+
+impl SaveGame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    /// The value of a key, `None` if it is missing or doesn't parse as `T`
+    pub fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.values.get(key)?.parse().ok()
+    }
+
+    pub fn get_or<T: FromStr>(&self, key: &str, default: T) -> T {
+        self.get(key).unwrap_or(default)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.values.remove(key).is_some()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// File of a save slot, slot names are limited to letters, digits, '-' and '_'
+    pub fn path(app: &str, slot: &str) -> Result<PathBuf> {
+        if slot.is_empty() || !slot.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(anyhow!("Invalid save slot name '{}'", slot));
+        }
+        Ok(data_dir(app)?.join("saves").join(format!("{}.ron", slot)))
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let value = read_ron(path)?;
+        let entries = value.entries().ok_or_else(|| anyhow!("Save '{}' is not a map", path.display()))?;
+        let values = entries
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+            .collect();
+        Ok(SaveGame { values })
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| anyhow!("Failed to create '{}': {}", dir.display(), e))?;
+        }
+        let value = Value::Map(self.values.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect());
+        write_atomic(path, &value.to_text())
+    }
+
+    /// Load a slot, `Ok(None)` if it was never saved
+    pub fn load(app: &str, slot: &str) -> Result<Option<Self>> {
+        let path = Self::path(app, slot)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::load_from(&path).map(Some)
+    }
+
+    pub fn save(&self, app: &str, slot: &str) -> Result<()> {
+        self.save_to(&Self::path(app, slot)?)
+    }
+
+    /// Delete a slot's file, false if there was none
+    pub fn delete(app: &str, slot: &str) -> Result<bool> {
+        let path = Self::path(app, slot)?;
+        if !path.exists() {
+            return Ok(false);
+        }
+        std::fs::remove_file(&path).map_err(|e| anyhow!("Failed to delete '{}': {}", path.display(), e))?;
+        Ok(true)
+    }
+}
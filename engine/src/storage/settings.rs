@@ -0,0 +1,172 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::storage::{config_dir, read_ron, write_atomic, Value};
+
+const FILE_NAME: &str = "settings.ron";
+
+/// Through the shortest text of the f32, so 0.35 isn't written as 0.3499999940395355
+fn float(value: f32) -> Value {
+    Value::Number(value.to_string().parse().unwrap_or(value as f64))
+}
+
+/// User preferences loaded at startup and saved when they change or on exit
+///
+/// Missing or invalid fields keep their defaults, so older files keep loading as
+/// fields are added.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub window_width: u32,
+    pub window_height: u32,
+    pub vsync: bool,
+    /// Volumes from 0 to 1, the effective volume of a sound is master times its group's
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub effects_volume: f32,
+    /// Action name to key name, compared case-insensitively, e.g. "gizmo_rotate" to "E"
+    pub bindings: BTreeMap<String, String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let bindings = [
+            ("move_up", "W"),
+            ("move_left", "A"),
+            ("move_down", "S"),
+            ("move_right", "D"),
+            ("gizmo_translate", "W"),
+            ("gizmo_rotate", "E"),
+            ("gizmo_scale", "R"),
+        ];
+        Settings {
+            window_width: 1280,
+            window_height: 720,
+            vsync: true,
+            master_volume: 1.0,
+            music_volume: 0.8,
+            effects_volume: 1.0,
+            bindings: bindings.into_iter().map(|(action, key)| (action.to_string(), key.to_string())).collect(),
+        }
+    }
+}
+
+impl Settings {
+    /// Key bound to an action
+    pub fn binding(&self, action: &str) -> Option<&str> {
+        self.bindings.get(action).map(String::as_str)
+    }
+
+    pub fn bind(&mut self, action: &str, key: &str) {
+        self.bindings.insert(action.to_string(), key.to_string());
+    }
+
+    /// Whether `key` (a key name or typed character) triggers `action`
+    pub fn is_bound(&self, action: &str, key: &str) -> bool {
+        self.binding(action).is_some_and(|bound| bound.eq_ignore_ascii_case(key))
+    }
+
+    /// Read settings from a RON value, keeping defaults for anything missing or not finite
+    pub fn from_value(value: &Value) -> Self {
+        let mut settings = Settings::default();
+        let number = |key: &str| value.get(key).and_then(Value::as_f64).filter(|n| n.is_finite());
+        if let Some(width) = number("window_width") {
+            settings.window_width = width.max(1.0) as u32;
+        }
+        if let Some(height) = number("window_height") {
+            settings.window_height = height.max(1.0) as u32;
+        }
+        if let Some(vsync) = value.get("vsync").and_then(Value::as_bool) {
+            settings.vsync = vsync;
+        }
+        for (key, volume) in [
+            ("master_volume", &mut settings.master_volume),
+            ("music_volume", &mut settings.music_volume),
+            ("effects_volume", &mut settings.effects_volume),
+        ] {
+            if let Some(v) = number(key) {
+                *volume = (v as f32).clamp(0.0, 1.0);
+            }
+        }
+        // Stored bindings override the defaults one by one, new actions keep theirs
+        for (action, key) in value.get("bindings").and_then(Value::entries).unwrap_or_default() {
+            if let Some(key) = key.as_str() {
+                settings.bind(action, key);
+            }
+        }
+        settings
+    }
+
+    pub fn to_value(&self) -> Value {
+        Value::Struct(vec![
+            ("window_width".to_string(), Value::Number(self.window_width as f64)),
+            ("window_height".to_string(), Value::Number(self.window_height as f64)),
+            ("vsync".to_string(), Value::Bool(self.vsync)),
+            ("master_volume".to_string(), float(self.master_volume)),
+            ("music_volume".to_string(), float(self.music_volume)),
+            ("effects_volume".to_string(), float(self.effects_volume)),
+            (
+                "bindings".to_string(),
+                Value::Map(self.bindings.iter().map(|(action, key)| (action.clone(), Value::String(key.clone()))).collect()),
+            ),
+        ])
+    }
+
+    /// Path of an app's settings file
+    pub fn path(app: &str) -> Result<PathBuf> {
+        Ok(config_dir(app)?.join(FILE_NAME))
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        Ok(Self::from_value(&read_ron(path)?))
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        write_atomic(path, &self.to_value().to_text())
+    }
+
+    /// Load an app's settings, falling back to the defaults when there is no usable file
+    pub fn load(app: &str) -> Self {
+        let path = match Self::path(app) {
+            Ok(path) => path,
+            Err(e) => {
+                log::warn!("{}, using default settings", e);
+                return Self::default();
+            }
+        };
+        if !path.exists() {
+            log::info!("No settings at '{}' yet, using defaults", path.display());
+            return Self::default();
+        }
+        match Self::load_from(&path) {
+            Ok(settings) => {
+                log::info!("Loaded settings from '{}'", path.display());
+                settings
+            }
+            Err(e) => {
+                log::warn!("{}, using default settings", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, app: &str) -> Result<()> {
+        self.save_to(&Self::path(app)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_finite_volumes_keep_defaults() {
+        let settings = Settings { master_volume: f32::NAN, music_volume: f32::INFINITY, ..Settings::default() };
+        let text = settings.to_value().to_text();
+        let loaded = Settings::from_value(&Value::parse(&text).unwrap());
+        assert_eq!(loaded.music_volume, 0.0);
+
+        let value = Value::Struct(vec![("master_volume".to_string(), Value::Number(f64::NAN))]);
+        assert_eq!(Settings::from_value(&value).master_volume, Settings::default().master_volume);
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::{anyhow, bail, Result};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::Value;
+
+/// Parse the TOML subset configuration files use: `[table]` headers and `key = value`
+/// pairs with bare, quoted or dotted keys, strings, numbers and booleans
+/// Tables become structs. Arrays, inline tables, dates and multi-line strings are errors.
+pub fn parse_toml(text: &str) -> Result<Value> {
+    let mut root = Vec::new();
+    let mut table = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        parse_line(line, &mut root, &mut table).map_err(|e| anyhow!("Line {}: {}", number + 1, e))?;
+    }
+    Ok(Value::Struct(root))
+}
+
+/// One line, `table` is the path of the last `[table]` header
+fn parse_line(line: &str, root: &mut Vec<(String, Value)>, table: &mut Vec<String>) -> Result<()> {
+    let mut cursor = Cursor { chars: line.chars().peekable() };
+    if cursor.at_end() {
+        return Ok(());
+    }
+    if cursor.eat('[') {
+        let path = cursor.key_path()?;
+        if !cursor.eat(']') {
+            bail!("Expected ] after the table name");
+        }
+        cursor.end()?;
+        table_mut(root, &path)?;
+        *table = path;
+        return Ok(());
+    }
+    let mut path = cursor.key_path()?;
+    if !cursor.eat('=') {
+        bail!("Expected = after the key");
+    }
+    let value = cursor.value()?;
+    cursor.end()?;
+    let key = path.pop().expect("Paths have at least one key");
+    let entries = table_mut(root, &[table.as_slice(), &path].concat())?;
+    if entries.iter().any(|(existing, _)| *existing == key) {
+        bail!("{} is set twice", key);
+    }
+    entries.push((key, value));
+    Ok(())
+}
+
+/// Entries of the table at `path`, created where missing
+fn table_mut<'a>(mut entries: &'a mut Vec<(String, Value)>, path: &[String]) -> Result<&'a mut Vec<(String, Value)>> {
+    for name in path {
+        let index = match entries.iter().position(|(key, _)| key == name) {
+            Some(index) => index,
+            None => {
+                entries.push((name.clone(), Value::Struct(Vec::new())));
+                entries.len() - 1
+            }
+        };
+        entries = match &mut entries[index].1 {
+            Value::Struct(fields) => fields,
+            _ => bail!("{} is a value, not a table", name),
+        };
+    }
+    Ok(entries)
+}
+
+struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Cursor<'_> {
+    fn skip_space(&mut self) {
+        while self.chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+    }
+
+    /// Whether only blanks and a comment are left
+    fn at_end(&mut self) -> bool {
+        self.skip_space();
+        matches!(self.chars.peek(), None | Some('#'))
+    }
+
+    fn end(&mut self) -> Result<()> {
+        if !self.at_end() {
+            bail!("Unexpected text after the value");
+        }
+        Ok(())
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_space();
+        self.chars.next_if_eq(&expected).is_some()
+    }
+
+    fn key(&mut self) -> Result<String> {
+        self.skip_space();
+        match self.chars.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let mut key = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-') {
+                    key.push(c);
+                }
+                if key.is_empty() {
+                    bail!("Expected a key");
+                }
+                Ok(key)
+            }
+        }
+    }
+
+    /// A key and the keys it is dotted with
+    fn key_path(&mut self) -> Result<Vec<String>> {
+        let mut keys = vec![self.key()?];
+        while self.eat('.') {
+            keys.push(self.key()?);
+        }
+        Ok(keys)
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_space();
+        match self.chars.peek() {
+            Some('"') => return self.basic_string().map(Value::String),
+            Some('\'') => return self.literal_string().map(Value::String),
+            Some('[' | '{') => bail!("Arrays and inline tables aren't supported"),
+            _ => {}
+        }
+        let mut word = String::new();
+        while let Some(c) = self.chars.next_if(|c| !c.is_whitespace() && *c != '#') {
+            word.push(c);
+        }
+        match word.as_str() {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            // Underscores group digits, as in 1_000
+            _ if word.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+') => word
+                .replace('_', "")
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| anyhow!("Invalid number '{}'", word)),
+            "" => bail!("Expected a value"),
+            _ => bail!("Invalid value '{}', strings need quotes", word),
+        }
+    }
+
+    /// A "string" with escapes
+    fn basic_string(&mut self) -> Result<String> {
+        self.chars.next();
+        let mut text = String::new();
+        loop {
+            match self.chars.next() {
+                None => bail!("Unterminated string"),
+                Some('"') => return Ok(text),
+                Some('\\') => {
+                    let escaped = match self.chars.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('u') => {
+                            let hex: String = (0..4).filter_map(|_| self.chars.next()).collect();
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| anyhow!("Invalid escape \\u{}", hex))?
+                        }
+                        Some(c) => bail!("Invalid escape \\{}", c),
+                        None => bail!("Unterminated string"),
+                    };
+                    text.push(escaped);
+                }
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    /// A 'string' taken as written, e.g. Windows paths
+    fn literal_string(&mut self) -> Result<String> {
+        self.chars.next();
+        let mut text = String::new();
+        loop {
+            match self.chars.next() {
+                None => bail!("Unterminated string"),
+                Some('\'') => return Ok(text),
+                Some(c) => text.push(c),
+            }
+        }
+    }
+}
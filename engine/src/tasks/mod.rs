@@ -0,0 +1,359 @@
+//! Work-stealing thread pool for engine work that can run off the main thread.
+//! `spawn` runs a closure in the background and returns a `Task` to join,
+//! `scope` runs closures borrowing local data and waits for all of them, and
+//! `spawn_frame` queues work that must be done before the frame ends, see `wait_frame`.
+//!
+//! Every worker has its own queue: jobs spawned from a worker go to the back of its
+//! queue and it takes from the back (newest first), idle workers take the oldest job
+//! from the shared queue or steal from the front of another worker's queue. Threads
+//! waiting on a task run queued jobs meanwhile, so joining from inside a job is fine.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::crash::panic_message;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+type Panic = Box<dyn Any + Send + 'static>;
+
+/// How long a waiting thread sleeps before checking the queues for new jobs again
+const HELP_INTERVAL: Duration = Duration::from_millis(1);
+
+thread_local! {
+    /// Pool (address of its shared state) and queue index of the current worker thread
+    static WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// Counts unfinished jobs and wakes waiters when the count reaches zero
+struct Latch {
+    pending: Mutex<usize>,
+    done: Condvar,
+}
+
+impl Latch {
+    fn new(pending: usize) -> Self {
+        Latch { pending: Mutex::new(pending), done: Condvar::new() }
+    }
+
+    fn add(&self) {
+        *self.pending.lock().unwrap() += 1;
+    }
+
+    fn release(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        *pending -= 1;
+        if *pending == 0 {
+            self.done.notify_all();
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        *self.pending.lock().unwrap() == 0
+    }
+
+    /// Block until done or `HELP_INTERVAL` passed
+    fn wait_briefly(&self) {
+        let pending = self.pending.lock().unwrap();
+        if *pending > 0 {
+            let _ = self.done.wait_timeout(pending, HELP_INTERVAL);
+        }
+    }
+}
+
+/// Jobs waited on together, keeps the first panic to report once they are done
+struct Group {
+    latch: Latch,
+    panic: Mutex<Option<Panic>>,
+}
+
+impl Group {
+    fn new() -> Self {
+        Group { latch: Latch::new(0), panic: Mutex::new(None) }
+    }
+
+    fn run(&self, job: impl FnOnce()) {
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(job)) {
+            self.panic.lock().unwrap().get_or_insert(payload);
+        }
+        self.latch.release();
+    }
+
+    fn take_panic(&self) -> Option<Panic> {
+        self.panic.lock().unwrap().take()
+    }
+}
+
+struct Shared {
+    /// Jobs spawned from outside the pool
+    injector: Mutex<VecDeque<Job>>,
+    /// One queue per worker
+    locals: Vec<Mutex<VecDeque<Job>>>,
+    /// Jobs in any queue
+    queued: AtomicUsize,
+    /// Set on shutdown, also the lock idle workers sleep on
+    shutdown: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl Shared {
+    fn id(self: &Arc<Self>) -> usize {
+        Arc::as_ptr(self) as usize
+    }
+
+    /// Queue index of the current thread if it is one of this pool's workers
+    fn local_index(self: &Arc<Self>) -> Option<usize> {
+        WORKER.with(Cell::get).filter(|&(pool, _)| pool == self.id()).map(|(_, index)| index)
+    }
+
+    fn push(self: &Arc<Self>, job: Job) {
+        match self.local_index() {
+            Some(index) => self.locals[index].lock().unwrap().push_back(job),
+            None => self.injector.lock().unwrap().push_back(job),
+        }
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        // Notify under the lock so a worker about to sleep can't miss the job
+        let _shutdown = self.shutdown.lock().unwrap();
+        self.wake.notify_one();
+    }
+
+    /// Newest job of the own queue, else the oldest shared one, else steal the oldest of another worker
+    fn find_job(self: &Arc<Self>) -> Option<Job> {
+        let local = self.local_index();
+        let job = local
+            .and_then(|index| self.locals[index].lock().unwrap().pop_back())
+            .or_else(|| self.injector.lock().unwrap().pop_front())
+            .or_else(|| {
+                let start = local.map_or(0, |index| index + 1);
+                (0..self.locals.len())
+                    .map(|offset| (start + offset) % self.locals.len())
+                    .filter(|&index| Some(index) != local)
+                    .find_map(|index| self.locals[index].lock().unwrap().pop_front())
+            });
+        if job.is_some() {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+        }
+        job
+    }
+
+    /// Run queued jobs on this thread until the latch is done
+    fn wait(self: &Arc<Self>, latch: &Latch) {
+        while !latch.is_done() {
+            match self.find_job() {
+                Some(job) => job(),
+                None => latch.wait_briefly(),
+            }
+        }
+    }
+
+    fn worker_loop(self: Arc<Self>, index: usize) {
+        WORKER.with(|worker| worker.set(Some((self.id(), index))));
+        loop {
+            if let Some(job) = self.find_job() {
+                job();
+                continue;
+            }
+            let shutdown = self.shutdown.lock().unwrap();
+            if *shutdown {
+                return;
+            }
+            if self.queued.load(Ordering::SeqCst) == 0 {
+                let _shutdown = self.wake.wait(shutdown).unwrap();
+            }
+        }
+    }
+}
+
+/// Result of a job started with `spawn`
+///
+/// Dropping a task detaches it, the job still runs.
+pub struct Task<T> {
+    result: Arc<Mutex<Option<thread::Result<T>>>>,
+    latch: Arc<Latch>,
+    shared: Arc<Shared>,
+}
+
+impl<T> Task<T> {
+    pub fn is_finished(&self) -> bool {
+        self.latch.is_done()
+    }
+
+    /// Wait for the result, running other queued jobs meanwhile
+    /// A panic in the job is resumed on this thread
+    pub fn join(self) -> T {
+        match self.join_caught() {
+            Ok(value) => value,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// `join`, returning the payload of a panic in the job instead of resuming it
+    pub fn join_caught(self) -> thread::Result<T> {
+        self.shared.wait(&self.latch);
+        self.result.lock().unwrap().take().expect("task finished without a result")
+    }
+
+    /// The result if the job is done, the task back otherwise
+    pub fn try_join(self) -> Result<T, Self> {
+        if self.is_finished() {
+            Ok(self.join())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// Spawns jobs that may borrow from outside the `TaskPool::scope` call
+pub struct Scope<'scope> {
+    group: Arc<Group>,
+    shared: Arc<Shared>,
+    /// Invariant in 'scope, like `std::thread::Scope`
+    _marker: PhantomData<&'scope mut &'scope ()>,
+}
+
+impl<'scope> Scope<'scope> {
+    pub fn spawn<F: FnOnce() + Send + 'scope>(&self, job: F) {
+        self.group.latch.add();
+        let group = self.group.clone();
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || group.run(job));
+        // SAFETY: `TaskPool::scope` waits for every job of the group before returning,
+        // so nothing borrowed for 'scope is used after it ends
+        let job: Job = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, Job>(job) };
+        self.shared.push(job);
+    }
+}
+
+/// Worker threads plus the queues they take jobs from
+pub struct TaskPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+    /// Jobs that must finish before `wait_frame` returns
+    frame: Arc<Group>,
+}
+
+impl TaskPool {
+    /// Pool with `threads` workers (at least one)
+    pub fn new(threads: usize) -> Self {
+        let threads = threads.max(1);
+        let shared = Arc::new(Shared {
+            injector: Mutex::new(VecDeque::new()),
+            locals: (0..threads).map(|_| Mutex::new(VecDeque::new())).collect(),
+            queued: AtomicUsize::new(0),
+            shutdown: Mutex::new(false),
+            wake: Condvar::new(),
+        });
+        let workers = (0..threads)
+            .map(|index| {
+                let shared = shared.clone();
+                thread::Builder::new()
+                    .name(format!("task-worker-{}", index))
+                    .spawn(move || shared.worker_loop(index))
+                    .expect("failed to spawn task worker thread")
+            })
+            .collect();
+        TaskPool { shared, workers, frame: Arc::new(Group::new()) }
+    }
+
+    /// One worker per core, minus the main thread which helps while waiting
+    pub fn with_default_threads() -> Self {
+        let cores = thread::available_parallelism().map(|count| count.get()).unwrap_or(2);
+        Self::new(cores.saturating_sub(1))
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Run `job` on a worker, join the returned task for its result
+    pub fn spawn<T, F>(&self, job: F) -> Task<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let result = Arc::new(Mutex::new(None));
+        let latch = Arc::new(Latch::new(1));
+        let (job_result, job_latch) = (result.clone(), latch.clone());
+        self.shared.push(Box::new(move || {
+            *job_result.lock().unwrap() = Some(panic::catch_unwind(AssertUnwindSafe(job)));
+            job_latch.release();
+        }));
+        Task { result, latch, shared: self.shared.clone() }
+    }
+
+    /// Run `body`, which can spawn jobs borrowing local data, and wait for all of them
+    /// A panic in a job is resumed here once every job is done
+    pub fn scope<'scope, R>(&self, body: impl FnOnce(&Scope<'scope>) -> R) -> R {
+        let scope = Scope { group: Arc::new(Group::new()), shared: self.shared.clone(), _marker: PhantomData };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| body(&scope)));
+        self.shared.wait(&scope.group.latch);
+        let result = result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+        if let Some(payload) = scope.group.take_panic() {
+            panic::resume_unwind(payload);
+        }
+        result
+    }
+
+    /// Run `job` on a worker, it must finish before the next `wait_frame` returns
+    pub fn spawn_frame(&self, job: impl FnOnce() + Send + 'static) {
+        self.frame.latch.add();
+        let frame = self.frame.clone();
+        self.shared.push(Box::new(move || frame.run(job)));
+    }
+
+    /// Frame boundary: wait for every job queued with `spawn_frame`, helping meanwhile
+    /// A panicked frame job is logged rather than taking the main loop down
+    pub fn wait_frame(&self) {
+        crate::profile_scope!("wait_tasks");
+        self.shared.wait(&self.frame.latch);
+        if let Some(payload) = self.frame.take_panic() {
+            log::error!("Frame task panicked: {}", panic_message(payload.as_ref()));
+        }
+    }
+}
+
+impl Drop for TaskPool {
+    /// Finish the queued jobs and stop the workers
+    fn drop(&mut self) {
+        *self.shared.shutdown.lock().unwrap() = true;
+        self.shared.wake.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Engine-wide pool, created on first use with `TaskPool::with_default_threads`
+pub fn pool() -> &'static TaskPool {
+    static POOL: OnceLock<TaskPool> = OnceLock::new();
+    POOL.get_or_init(TaskPool::with_default_threads)
+}
+
+/// `TaskPool::spawn` on the engine pool
+pub fn spawn<T, F>(job: F) -> Task<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    pool().spawn(job)
+}
+
+/// `TaskPool::scope` on the engine pool
+pub fn scope<'scope, R>(body: impl FnOnce(&Scope<'scope>) -> R) -> R {
+    pool().scope(body)
+}
+
+/// `TaskPool::spawn_frame` on the engine pool
+pub fn spawn_frame(job: impl FnOnce() + Send + 'static) {
+    pool().spawn_frame(job)
+}
+
+/// `TaskPool::wait_frame` on the engine pool, call once per frame
+pub fn wait_frame() {
+    pool().wait_frame()
+}
@@ -0,0 +1,343 @@
+//! Video playback into textures, e.g. for menu backgrounds and cutscenes.
+//! A `VideoDecoder` turns a file into RGBA frames. `VideoTexture` runs one on a worker
+//! thread, keeps a few frames decoded ahead and copies each into a texture when its time
+//! comes. `gui::VideoComponent` shows one in the UI.
+//!
+//! The engine ships a decoder for uncompressed YUV4MPEG2 (.y4m) files. Compressed formats
+//! (VP9, AV1, MPEG) come from a codec library wrapped in a `VideoDecoder` by the application.
+
+use anyhow::{bail, Result};
+use ash::vk;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use crate::renderer::{Texture, VulkanContext};
+
+mod y4m;
+pub use y4m::Y4mDecoder;
+
+/// Frames decoded ahead of the one shown
+const FRAMES_AHEAD: usize = 3;
+
+/// Size and timing of a video
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VideoInfo {
+    pub width: u32,
+    pub height: u32,
+    /// Frames per second
+    pub frame_rate: f64,
+    /// Length in seconds, `None` when the decoder can't tell
+    pub duration: Option<f64>,
+}
+
+/// A decoded frame
+pub struct VideoFrame {
+    /// Seconds from the start of the video at which the frame is shown
+    pub time: f64,
+    /// `width * height` RGBA texels (sRGB), rows from the top
+    pub pixels: Vec<u8>,
+}
+
+/// Decodes the frames of one video, on `VideoTexture`'s worker thread
+pub trait VideoDecoder: Send {
+    fn info(&self) -> VideoInfo;
+    /// The next frame in presentation order, `None` after the last one
+    fn next_frame(&mut self) -> Result<Option<VideoFrame>>;
+    /// Continue with the frame shown at `time` seconds
+    fn seek(&mut self, time: f64) -> Result<()>;
+}
+
+/// Decoder for a file, picked by its extension
+pub fn open_decoder(path: impl AsRef<Path>) -> Result<Box<dyn VideoDecoder>> {
+    let path = path.as_ref();
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("y4m") => Ok(Box::new(Y4mDecoder::open(path)?)),
+        _ => bail!(
+            "No decoder for {}, only .y4m is built in; pass a VideoDecoder for other formats to VideoTexture::new",
+            path.display()
+        ),
+    }
+}
+
+/// Seek request to the worker, frames decoded before it carry an older generation
+struct Seek {
+    time: f64,
+    generation: u64,
+}
+
+/// A frame (or the end of the video, or an error) from the worker
+struct Decoded {
+    generation: u64,
+    frame: Result<Option<VideoFrame>>,
+}
+
+/// A video decoded on a worker thread into a texture
+///
+/// Call `update` every frame with the elapsed time, then `upload` to copy the frame due by
+/// then into `texture`. Dropping it stops the worker, `destroy` frees the texture.
+pub struct VideoTexture {
+    info: VideoInfo,
+    texture: Texture,
+    seeks: Sender<Seek>,
+    frames: Receiver<Decoded>,
+    generation: u64,
+    /// Playback position in seconds
+    time: f64,
+    playing: bool,
+    looping: bool,
+    /// The decoder passed its last frame
+    ended: bool,
+    /// Received from the worker but not due yet
+    pending: Option<VideoFrame>,
+    /// Due and not uploaded yet
+    due: Option<VideoFrame>,
+}
+
+impl VideoTexture {
+    /// Play the file at `path` with a decoder from `open_decoder`
+    pub fn open(context: &Arc<VulkanContext>, path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(context, open_decoder(path)?)
+    }
+
+    /// Start decoding with `decoder`, paused at the first frame
+    pub fn new(context: &Arc<VulkanContext>, decoder: Box<dyn VideoDecoder>) -> Result<Self> {
+        let info = decoder.info();
+        if info.width == 0 || info.height == 0 {
+            bail!("Video has no pixels ({}x{})", info.width, info.height);
+        }
+        // Opaque black until the first frame arrives
+        let black: Vec<u8> = [0, 0, 0, 255].repeat(info.width as usize * info.height as usize);
+        let texture = Texture::from_bytes(
+            &black,
+            info.width,
+            info.height,
+            vk::Format::R8G8B8A8_SRGB,
+            &context.device,
+            &context.instance,
+            context.physical_device,
+            context.queue_family_indices[0],
+        )?;
+
+        let (seeks, seek_receiver) = mpsc::channel();
+        let (frame_sender, frames) = mpsc::sync_channel(FRAMES_AHEAD);
+        thread::Builder::new()
+            .name("video-decode".to_string())
+            .spawn(move || decode(decoder, seek_receiver, frame_sender))?;
+
+        Ok(VideoTexture {
+            info,
+            texture,
+            seeks,
+            frames,
+            generation: 0,
+            time: 0.0,
+            playing: false,
+            looping: false,
+            ended: false,
+            pending: None,
+            due: None,
+        })
+    }
+
+    pub fn info(&self) -> VideoInfo {
+        self.info
+    }
+
+    /// Texture the frames are copied into, `info().width` x `info().height` and R8G8B8A8_SRGB
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Playback position in seconds
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Whether playback stopped at the end of a video that doesn't loop
+    pub fn is_finished(&self) -> bool {
+        self.ended && !self.looping && self.pending.is_none()
+    }
+
+    /// Continue playing, from the start if the video finished
+    pub fn play(&mut self) {
+        if self.is_finished() {
+            self.seek(0.0);
+        }
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.playing {
+            self.pause();
+        } else {
+            self.play();
+        }
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Start over after the last frame instead of stopping, e.g. for menu backgrounds
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Jump to `time` seconds, the frame there shows once the worker decoded it
+    pub fn seek(&mut self, time: f64) {
+        let time = match self.info.duration {
+            Some(duration) => time.clamp(0.0, duration),
+            None => time.max(0.0),
+        };
+        self.generation += 1;
+        self.time = time;
+        self.ended = false;
+        self.pending = None;
+        // The worker only stops when the texture is dropped
+        let _ = self.seeks.send(Seek { time, generation: self.generation });
+    }
+
+    /// Advance playback by `dt` seconds and take the frames due by then from the worker
+    /// Returns whether a new frame is waiting for `upload`. Frames the worker delivers
+    /// late are skipped rather than slowing playback down.
+    pub fn update(&mut self, dt: f32) -> bool {
+        if self.playing {
+            self.time += dt as f64;
+            if let Some(duration) = self.info.duration {
+                self.time = self.time.min(duration);
+            }
+        }
+        let mut new_frame = false;
+        loop {
+            if self.pending.is_none() {
+                match self.frames.try_recv() {
+                    Ok(decoded) if decoded.generation != self.generation => continue,
+                    Ok(Decoded { frame: Ok(Some(frame)), .. }) => self.pending = Some(frame),
+                    Ok(Decoded { frame: Ok(None), .. }) => {
+                        self.ended = true;
+                        break;
+                    }
+                    Ok(Decoded { frame: Err(e), .. }) => {
+                        log::error!("Video decoding failed: {:#}", e);
+                        self.ended = true;
+                        break;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.ended = true;
+                        break;
+                    }
+                }
+            }
+            match self.pending.take() {
+                Some(frame) if frame.time <= self.time => {
+                    self.due = Some(frame);
+                    new_frame = true;
+                }
+                frame => {
+                    self.pending = frame;
+                    break;
+                }
+            }
+        }
+        if self.ended && self.pending.is_none() {
+            if self.looping && self.playing {
+                self.seek(0.0);
+            } else if self.playing {
+                self.playing = false;
+            }
+        }
+        new_frame
+    }
+
+    /// Seconds until the next frame is due, `None` while nothing will change by itself
+    pub fn next_frame_in(&self) -> Option<f32> {
+        if self.due.is_some() {
+            return Some(0.0);
+        }
+        if !self.playing {
+            return None;
+        }
+        let next = match &self.pending {
+            Some(frame) => frame.time,
+            // Not decoded yet, check again after a frame's time
+            None => self.time + 1.0 / self.info.frame_rate.max(1.0),
+        };
+        Some((next - self.time).max(0.0) as f32)
+    }
+
+    /// Copy the latest due frame into the texture, returns whether there was one
+    pub fn upload(&mut self, context: &Arc<VulkanContext>) -> Result<bool> {
+        let Some(frame) = self.due.take() else {
+            return Ok(false);
+        };
+        self.texture.update_region(
+            &frame.pixels,
+            0,
+            0,
+            self.info.width,
+            self.info.height,
+            &context.device,
+            &context.instance,
+            context.physical_device,
+            context.queue_family_indices[0],
+        )?;
+        Ok(true)
+    }
+
+    /// Free the texture, the device must be done with it
+    pub fn destroy(&self, device: &ash::Device) {
+        self.texture.destroy(device);
+    }
+}
+
+/// Worker loop: decode ahead until the channel is full, restart on seeks and wait for
+/// one after the last frame. Ends when the `VideoTexture` is dropped.
+fn decode(mut decoder: Box<dyn VideoDecoder>, seeks: Receiver<Seek>, frames: SyncSender<Decoded>) {
+    let mut generation = 0;
+    let mut ended = false;
+    loop {
+        let mut seek = if ended {
+            match seeks.recv() {
+                Ok(seek) => Some(seek),
+                Err(_) => return,
+            }
+        } else {
+            None
+        };
+        // Only the latest of several queued seeks matters
+        loop {
+            match seeks.try_recv() {
+                Ok(next) => seek = Some(next),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+        if let Some(seek) = seek {
+            generation = seek.generation;
+            if let Err(e) = decoder.seek(seek.time) {
+                ended = true;
+                if frames.send(Decoded { generation, frame: Err(e) }).is_err() {
+                    return;
+                }
+                continue;
+            }
+        }
+
+        let frame = decoder.next_frame();
+        ended = !matches!(frame, Ok(Some(_)));
+        if frames.send(Decoded { generation, frame }).is_err() {
+            return;
+        }
+    }
+}
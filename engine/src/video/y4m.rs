@@ -0,0 +1,236 @@
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use super::{VideoDecoder, VideoFrame, VideoInfo};
+
+/// Longest header or frame header line accepted
+const MAX_LINE: usize = 1024;
+/// Largest width or height accepted, larger headers are treated as corrupt
+const MAX_DIMENSION: u32 = 16384;
+/// Header of a frame without parameters
+const FRAME_HEADER: &[u8] = b"FRAME\n";
+
+/// Chroma planes of a frame, relative to the luma plane
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Chroma {
+    /// Half width and height
+    C420,
+    /// Half width
+    C422,
+    C444,
+    /// No chroma planes
+    Mono,
+}
+
+/// Decoder for YUV4MPEG2 (.y4m) files: uncompressed 8-bit YUV frames, e.g. exported with
+/// `ffmpeg -i intro.webm -pix_fmt yuv420p intro.y4m`
+///
+/// Frames are converted with the BT.601 limited range matrix. Seeking assumes frame headers
+/// without parameters, as encoders write them.
+pub struct Y4mDecoder {
+    reader: BufReader<File>,
+    info: VideoInfo,
+    chroma: Chroma,
+    /// File offset of the first frame header
+    data_start: u64,
+    /// Bytes of the planes of one frame
+    frame_size: usize,
+    /// Index of the frame `next_frame` reads
+    next_index: u64,
+    planes: Vec<u8>,
+}
+
+impl Y4mDecoder {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|| format!("Opening {}", path.display()))?;
+        let file_size = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let header = read_line(&mut reader).with_context(|| format!("Reading the header of {}", path.display()))?;
+        // Bytes read, the header may not be valid UTF-8
+        let data_start = reader.stream_position()?;
+        let Some(params) = header.strip_prefix("YUV4MPEG2") else {
+            bail!("{} is not a YUV4MPEG2 file", path.display());
+        };
+
+        let (mut width, mut height, mut frame_rate, mut chroma) = (0, 0, 25.0, Chroma::C420);
+        for param in params.split_whitespace() {
+            let mut chars = param.chars();
+            let (tag, value) = (chars.next(), chars.as_str());
+            match tag {
+                Some('W') => width = value.parse().context("Invalid width")?,
+                Some('H') => height = value.parse().context("Invalid height")?,
+                Some('F') => {
+                    let (num, den) = value.split_once(':').context("Invalid frame rate")?;
+                    let (num, den): (f64, f64) = (num.parse()?, den.parse()?);
+                    if num > 0.0 && den > 0.0 {
+                        frame_rate = num / den;
+                    }
+                }
+                Some('C') => {
+                    chroma = match value {
+                        "420" | "420jpeg" | "420paldv" | "420mpeg2" => Chroma::C420,
+                        "422" => Chroma::C422,
+                        "444" => Chroma::C444,
+                        "mono" => Chroma::Mono,
+                        _ => bail!("Unsupported color space C{} in {}, only 8-bit 420, 422, 444 and mono", value, path.display()),
+                    }
+                }
+                // Interlacing, pixel aspect and extensions don't change decoding
+                _ => {}
+            }
+        }
+        if width == 0 || height == 0 {
+            bail!("{} has no frame size", path.display());
+        }
+        if width > MAX_DIMENSION || height > MAX_DIMENSION {
+            bail!("{} has a {}x{} frame size, at most {} is supported", path.display(), width, height, MAX_DIMENSION);
+        }
+
+        let luma = width as usize * height as usize;
+        let (chroma_width, chroma_height) = chroma_size(chroma, width, height);
+        let frame_size = luma + 2 * chroma_width * chroma_height;
+        if data_start + (FRAME_HEADER.len() + frame_size) as u64 > file_size {
+            bail!("{} is shorter than one {}x{} frame", path.display(), width, height);
+        }
+        let frames = file_size.saturating_sub(data_start) / (FRAME_HEADER.len() + frame_size) as u64;
+        Ok(Y4mDecoder {
+            reader,
+            info: VideoInfo { width, height, frame_rate, duration: Some(frames as f64 / frame_rate) },
+            chroma,
+            data_start,
+            frame_size,
+            next_index: 0,
+            planes: vec![0; frame_size],
+        })
+    }
+}
+
+impl VideoDecoder for Y4mDecoder {
+    fn info(&self) -> VideoInfo {
+        self.info
+    }
+
+    fn next_frame(&mut self) -> Result<Option<VideoFrame>> {
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let header = read_line(&mut self.reader)?;
+        if !header.starts_with("FRAME") {
+            bail!("Expected a FRAME header, found {:?}", header);
+        }
+        self.reader.read_exact(&mut self.planes).context("Truncated frame")?;
+        let time = self.next_index as f64 / self.info.frame_rate;
+        self.next_index += 1;
+        Ok(Some(VideoFrame { time, pixels: self.to_rgba() }))
+    }
+
+    fn seek(&mut self, time: f64) -> Result<()> {
+        let index = (time.max(0.0) * self.info.frame_rate + 1e-6).floor() as u64;
+        let offset = self.data_start + index * (FRAME_HEADER.len() + self.frame_size) as u64;
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.next_index = index;
+        Ok(())
+    }
+}
+
+impl Y4mDecoder {
+    /// Convert the planes read last to RGBA
+    fn to_rgba(&self) -> Vec<u8> {
+        let (width, height) = (self.info.width as usize, self.info.height as usize);
+        let (chroma_width, chroma_height) = chroma_size(self.chroma, self.info.width, self.info.height);
+        let (shift_x, shift_y) = match self.chroma {
+            Chroma::C420 => (1, 1),
+            Chroma::C422 => (1, 0),
+            Chroma::C444 | Chroma::Mono => (0, 0),
+        };
+        let (y_plane, chroma_planes) = self.planes.split_at(width * height);
+        let (u_plane, v_plane) = chroma_planes.split_at(chroma_width * chroma_height);
+
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for row in 0..height {
+            for column in 0..width {
+                let y = y_plane[row * width + column] as f32;
+                let (u, v) = if self.chroma == Chroma::Mono {
+                    (128.0, 128.0)
+                } else {
+                    let i = (row >> shift_y) * chroma_width + (column >> shift_x);
+                    (u_plane[i] as f32, v_plane[i] as f32)
+                };
+                let (y, u, v) = (1.164 * (y - 16.0), u - 128.0, v - 128.0);
+                let channel = |c: f32| c.round().clamp(0.0, 255.0) as u8;
+                pixels.extend_from_slice(&[
+                    channel(y + 1.596 * v),
+                    channel(y - 0.392 * u - 0.813 * v),
+                    channel(y + 2.017 * u),
+                    255,
+                ]);
+            }
+        }
+        pixels
+    }
+}
+
+/// Size of each chroma plane
+fn chroma_size(chroma: Chroma, width: u32, height: u32) -> (usize, usize) {
+    let (width, height) = (width as usize, height as usize);
+    match chroma {
+        Chroma::C420 => (width.div_ceil(2), height.div_ceil(2)),
+        Chroma::C422 => (width.div_ceil(2), height),
+        Chroma::C444 => (width, height),
+        Chroma::Mono => (0, 0),
+    }
+}
+
+/// A header line without its newline
+fn read_line(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = Vec::new();
+    reader.take(MAX_LINE as u64).read_until(b'\n', &mut line)?;
+    if line.pop() != Some(b'\n') {
+        bail!("Header line missing or longer than {} bytes", MAX_LINE);
+    }
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("engine-y4m-{}-{}.y4m", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn rejects_oversized_headers_before_allocating() {
+        let path = write_temp("huge", b"YUV4MPEG2 W100000 H100000 F25:1\nFRAME\n");
+        let error = Y4mDecoder::open(&path).err().unwrap().to_string();
+        assert!(error.contains("100000x100000"), "{}", error);
+        let _ = std::fs::remove_file(&path);
+
+        let path = write_temp("short", b"YUV4MPEG2 W64 H64 F25:1\nFRAME\n");
+        assert!(Y4mDecoder::open(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn frames_start_after_non_utf8_headers() {
+        // A 2x2 mono frame after a header with an invalid UTF-8 extension
+        let mut bytes = b"YUV4MPEG2 W2 H2 F1:1 Cmono X\xff\xfe\n".to_vec();
+        bytes.extend_from_slice(b"FRAME\n");
+        bytes.extend_from_slice(&[16, 235, 16, 235]);
+        let path = write_temp("utf8", &bytes);
+        let mut decoder = Y4mDecoder::open(&path).unwrap();
+        assert_eq!(decoder.info().duration, Some(1.0));
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(&frame.pixels[0..4], &[0, 0, 0, 255]);
+        assert_eq!(&frame.pixels[4..8], &[255, 255, 255, 255]);
+
+        decoder.seek(0.0).unwrap();
+        assert!(decoder.next_frame().unwrap().is_some());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,69 @@
+use winit::event::Event;
+
+/// What an application does about a lifecycle event, see `Lifecycle::handle`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LifecycleChange {
+    /// The first `Resumed`: the window can be drawn to, create the renderer now. On Android
+    /// there is no native window before this.
+    Started,
+    /// The native window is going away, call `Renderer::suspend`
+    Suspended,
+    /// There is a native window again, call `Renderer::resume`
+    Resumed,
+}
+
+/// Tracks whether an application is in the foreground with a surface to draw to
+///
+/// Desktop platforms resume once at startup and never suspend. Android suspends when the
+/// app goes to the background and destroys the native window, which takes the Vulkan
+/// surface with it; iOS suspends too but keeps its window. Feed it every event and draw
+/// only while `is_active`.
+pub struct Lifecycle {
+    started: bool,
+    suspended: bool,
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        Lifecycle { started: false, suspended: false }
+    }
+
+    /// The change an event makes, `None` for events that aren't about the lifecycle
+    pub fn handle<T>(&mut self, event: &Event<T>) -> Option<LifecycleChange> {
+        match event {
+            Event::Resumed if !self.started => {
+                self.started = true;
+                Some(LifecycleChange::Started)
+            }
+            Event::Resumed if self.suspended => {
+                self.suspended = false;
+                Some(LifecycleChange::Resumed)
+            }
+            Event::Suspended if self.started && !self.suspended => {
+                self.suspended = true;
+                Some(LifecycleChange::Suspended)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the first `Resumed` arrived
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Started and not suspended, frames can be drawn
+    pub fn is_active(&self) -> bool {
+        self.started && !self.suspended
+    }
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1 +1,7 @@
 pub use winit::event_loop::EventLoop;
+
+mod resize;
+pub use resize::ResizeTracker;
+
+mod lifecycle;
+pub use lifecycle::{Lifecycle, LifecycleChange};
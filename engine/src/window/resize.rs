@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+/// Shortest time between two sizes applied during a live resize
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(33);
+/// A resize is over when no resize event came for this long
+const DEFAULT_SETTLE: Duration = Duration::from_millis(200);
+
+/// Coalesces the resize events of a window dragged to a new size
+///
+/// Feed it every `WindowEvent::Resized` and `take` the size to apply when drawing. During
+/// a live resize sizes are handed out at most every `interval`, so the swapchain and the
+/// layout aren't rebuilt for every intermediate size; the last one is always applied.
+/// `is_resizing` tells the application a resize is in progress, e.g. to skip costly work.
+pub struct ResizeTracker {
+    /// Latest size not handed out yet
+    pending: Option<(u32, u32)>,
+    last_event: Option<Instant>,
+    last_applied: Option<Instant>,
+    interval: Duration,
+    settle: Duration,
+}
+
+impl ResizeTracker {
+    pub fn new() -> Self {
+        ResizeTracker {
+            pending: None,
+            last_event: None,
+            last_applied: None,
+            interval: DEFAULT_INTERVAL,
+            settle: DEFAULT_SETTLE,
+        }
+    }
+
+    /// Hand out sizes at most this often while resizing, zero for every frame
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Time without resize events after which a resize counts as finished
+    pub fn with_settle(mut self, settle: Duration) -> Self {
+        self.settle = settle;
+        self
+    }
+
+    /// A resize event with the new inner size in pixels
+    pub fn resized(&mut self, width: u32, height: u32) {
+        self.pending = Some((width, height));
+        self.last_event = Some(Instant::now());
+    }
+
+    /// The size to apply now, `None` if it didn't change or the last one was applied less
+    /// than `interval` ago (then draw again after `next_update`)
+    pub fn take(&mut self) -> Option<(u32, u32)> {
+        let now = Instant::now();
+        if self.last_applied.is_some_and(|applied| now.duration_since(applied) < self.interval) {
+            return None;
+        }
+        let size = self.pending.take()?;
+        self.last_applied = Some(now);
+        Some(size)
+    }
+
+    /// Whether the window was resized within the last `settle` or a size is still pending
+    pub fn is_resizing(&self) -> bool {
+        self.pending.is_some() || self.last_event.is_some_and(|event| event.elapsed() < self.settle)
+    }
+
+    /// Seconds until a held back size can be applied or the resize settles, `None` when
+    /// nothing is waiting
+    pub fn next_update(&self) -> Option<f32> {
+        let wait = |since: Option<Instant>, duration: Duration| {
+            since.map_or(Duration::ZERO, |since| duration.saturating_sub(since.elapsed())).as_secs_f32()
+        };
+        if self.pending.is_some() {
+            return Some(wait(self.last_applied, self.interval));
+        }
+        self.is_resizing().then(|| wait(self.last_event, self.settle))
+    }
+}
+
+impl Default for ResizeTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,142 @@
+//! Golden file checks that need no GPU: layout snapshots and image comparisons
+
+use anyhow::Result;
+use engine::gui::{
+    assert_image_golden, GUIComponent, HAlign, ImageDiff, ImageTolerance, LayoutSnapshot, LayoutSpec, Rect, RowSpec, SizeSpec,
+    Transform, UISystem, VAlign, Vec2,
+};
+use engine::renderer::{RenderContext, Renderer};
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+/// A component asking for a fixed size, laid out without any GPU resources
+struct Probe {
+    transform: Transform,
+    size: Vec2,
+}
+
+impl Probe {
+    fn new(width: f32, height: f32) -> Self {
+        Probe { transform: Transform::new(), size: Vec2::new(width, height) }
+    }
+}
+
+impl GUIComponent for Probe {
+    fn render(&self, _ctx: &RenderContext, _renderer: &mut Renderer) -> Result<()> { Ok(()) }
+    fn transform(&self) -> &Transform { &self.transform }
+    fn transform_mut(&mut self) -> &mut Transform { &mut self.transform }
+    fn measure(&self, constraints: Vec2) -> Vec2 { self.size.min(constraints) }
+    fn destroy(&self, _device: &ash::Device) {}
+}
+
+/// Update runs write every golden file instead of comparing, the mismatch tests don't apply
+fn updating() -> bool {
+    std::env::var_os("UPDATE_SNAPSHOTS").is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+fn golden(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(name)
+}
+
+/// Toolbar, a sidebar next to the content and a status bar
+fn editor_like_ui() -> UISystem {
+    let mut ui = UISystem::new();
+    let toolbar = ui.grid.add_row_with_spec(RowSpec::new(SizeSpec::Fixed(24.0)).with_margin(4.0));
+    let main = ui.grid.add_row_with_spec(RowSpec::new(SizeSpec::Fraction(1.0)).with_padding(4.0).with_margin(4.0));
+    let status = ui.grid.add_row_with_spec(RowSpec::new(SizeSpec::Auto).with_h_align(HAlign::Right));
+
+    let button = LayoutSpec::new(SizeSpec::Fixed(70.0), SizeSpec::Percent(1.0));
+    for _ in 0..3 {
+        ui.grid.add(toolbar, Probe::new(0.0, 0.0), button).unwrap();
+    }
+    ui.grid.add(main, Probe::new(0.0, 0.0), LayoutSpec::new(SizeSpec::Percent(0.25), SizeSpec::Percent(1.0))).unwrap();
+    ui.grid.add(main, Probe::new(0.0, 0.0), LayoutSpec::new(SizeSpec::Fraction(1.0), SizeSpec::Percent(1.0))).unwrap();
+    let label = LayoutSpec::new(SizeSpec::Auto, SizeSpec::Auto).with_v_align(VAlign::Bottom);
+    ui.grid.add(status, Probe::new(120.0, 18.0), label).unwrap();
+    ui
+}
+
+#[test]
+fn layout_matches_golden() -> Result<()> {
+    let mut ui = editor_like_ui();
+    let snapshot = LayoutSnapshot::capture(&mut ui, 800.0, 600.0);
+    snapshot.assert_golden(golden("editor_layout_800x600.txt"), 0.5)?;
+
+    // The sidebar keeps its share of the padded row when the window shrinks
+    let snapshot = LayoutSnapshot::capture(&mut ui, 400.0, 300.0);
+    snapshot.assert_rect("3", Rect::new(4.0, 25.0, 98.0, 244.0), 0.5)?;
+    snapshot.assert_golden(golden("editor_layout_400x300.txt"), 0.5)
+}
+
+#[test]
+fn layout_differences_are_reported() {
+    if updating() {
+        return;
+    }
+    // A golden file of its own, so an update run doesn't write the changed layout
+    let path = std::env::temp_dir().join(format!("engine-layout-{}.txt", std::process::id()));
+    let mut ui = editor_like_ui();
+    let mut snapshot = LayoutSnapshot::capture(&mut ui, 800.0, 600.0);
+    std::fs::write(&path, snapshot.to_text()).unwrap();
+
+    snapshot.entries[0].1.width += 10.0;
+    let error = snapshot.assert_golden(&path, 0.5).unwrap_err().to_string();
+    assert!(error.contains("0: expected"), "{}", error);
+    // Within tolerance
+    assert!(snapshot.assert_golden(&path, 10.0).is_ok());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn layout_text_round_trips() {
+    let mut ui = editor_like_ui();
+    let snapshot = LayoutSnapshot::capture(&mut ui, 800.0, 600.0);
+    assert_eq!(LayoutSnapshot::from_text(&snapshot.to_text()).unwrap(), snapshot);
+    assert!(LayoutSnapshot::from_text("0 1 2 3").is_err());
+}
+
+fn checkerboard(size: u32, light: u8) -> RgbaImage {
+    RgbaImage::from_fn(size, size, |x, y| {
+        let value = if (x + y) % 2 == 0 { light } else { 20 };
+        Rgba([value, value, value, 255])
+    })
+}
+
+#[test]
+fn image_diff_counts_pixels_past_the_tolerance() {
+    let expected = checkerboard(4, 200);
+    let mut actual = expected.clone();
+    actual.put_pixel(0, 0, Rgba([203, 200, 200, 255]));
+    actual.put_pixel(1, 1, Rgba([201, 200, 200, 255]));
+
+    let diff = ImageDiff::compare(&actual, &expected, 2).unwrap();
+    assert_eq!(diff.mismatched, 1);
+    assert_eq!(diff.max_delta, 3);
+    assert_eq!(*diff.image.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+    assert_eq!(*diff.image.get_pixel(1, 1), Rgba([50, 50, 50, 255]));
+
+    assert!(ImageDiff::compare(&checkerboard(2, 200), &expected, 2).is_err());
+}
+
+#[test]
+fn image_golden_writes_diff_on_mismatch() {
+    if updating() {
+        return;
+    }
+    let dir = std::env::temp_dir().join(format!("engine-snapshots-{}", std::process::id()));
+    let path = dir.join("board.png");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // The first run writes the golden image
+    assert_image_golden(&checkerboard(8, 200), &path, ImageTolerance::default()).unwrap();
+    assert!(path.exists());
+    assert_image_golden(&checkerboard(8, 201), &path, ImageTolerance::default()).unwrap();
+
+    let error = assert_image_golden(&checkerboard(8, 150), &path, ImageTolerance::default()).unwrap_err().to_string();
+    assert!(error.contains("32 pixels differ"), "{}", error);
+    assert!(dir.join("board.actual.png").exists());
+    assert!(dir.join("board.diff.png").exists());
+    // Half the pixels may differ
+    assert!(assert_image_golden(&checkerboard(8, 150), &path, ImageTolerance { channel: 2, pixels: 0.5 }).is_ok());
+    let _ = std::fs::remove_dir_all(&dir);
+}
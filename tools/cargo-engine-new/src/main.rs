@@ -0,0 +1,139 @@
+//! `cargo engine-new <name>` scaffolds a game project using the engine: a Cargo.toml with
+//! the features games need, engine.toml, a starter scene under assets/, the engine's
+//! shaders and a main.rs built on `Engine::run`.
+
+use anyhow::{anyhow, bail, Result};
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "\
+Usage: cargo engine-new <name> [--path DIR] [--engine PATH]
+
+  --path DIR      Create the project in DIR instead of ./<name>
+  --engine PATH   Engine crate the project depends on, defaults to this checkout's";
+
+/// Files written as they are, relative to the project directory
+const FILES: &[(&str, &str)] = &[
+    ("assets/scenes/main.ron", include_str!("../templates/main.ron")),
+    (".gitignore", include_str!("../templates/gitignore")),
+    // The renderer compiles these from the working directory at startup
+    ("shaders/triangle.vert", include_str!("../../../engine/shaders/triangle.vert")),
+    ("shaders/triangle.frag", include_str!("../../../engine/shaders/triangle.frag")),
+    ("shaders/text.vert", include_str!("../../../engine/shaders/text.vert")),
+    ("shaders/text.frag", include_str!("../../../engine/shaders/text.frag")),
+    ("shaders/image.vert", include_str!("../../../engine/shaders/image.vert")),
+    ("shaders/image.frag", include_str!("../../../engine/shaders/image.frag")),
+    ("shaders/scene.vert", include_str!("../../../engine/shaders/scene.vert")),
+    ("shaders/scene.frag", include_str!("../../../engine/shaders/scene.frag")),
+    ("shaders/backdrop.vert", include_str!("../../../engine/shaders/backdrop.vert")),
+    ("shaders/backdrop.frag", include_str!("../../../engine/shaders/backdrop.frag")),
+    ("shaders/bloom.vert", include_str!("../../../engine/shaders/bloom.vert")),
+    ("shaders/bloom.frag", include_str!("../../../engine/shaders/bloom.frag")),
+    ("shaders/fxaa.vert", include_str!("../../../engine/shaders/fxaa.vert")),
+    ("shaders/fxaa.frag", include_str!("../../../engine/shaders/fxaa.frag")),
+    ("shaders/light2d.vert", include_str!("../../../engine/shaders/light2d.vert")),
+    ("shaders/light2d.frag", include_str!("../../../engine/shaders/light2d.frag")),
+];
+
+/// Files with `{{name}}` and `{{engine_path}}` filled in
+const TEMPLATES: &[(&str, &str)] = &[
+    ("Cargo.toml", include_str!("../templates/Cargo.toml.in")),
+    ("engine.toml", include_str!("../templates/engine.toml.in")),
+    ("src/main.rs", include_str!("../templates/main.rs.in")),
+];
+
+struct Options {
+    name: String,
+    dir: PathBuf,
+    engine: PathBuf,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options> {
+    let mut args = args.into_iter().peekable();
+    // Cargo passes the subcommand's name first
+    args.next_if(|arg| arg == "engine-new");
+    let mut name = None;
+    let mut dir = None;
+    let mut engine = None;
+    while let Some(arg) = args.next() {
+        let mut operand = || args.next().ok_or_else(|| anyhow!("{} needs a value\n{}", arg, USAGE));
+        match arg.as_str() {
+            "--path" => dir = Some(PathBuf::from(operand()?)),
+            "--engine" => engine = Some(PathBuf::from(operand()?)),
+            "-h" | "--help" => bail!("{}", USAGE),
+            _ if arg.starts_with('-') => bail!("Unknown option '{}'\n{}", arg, USAGE),
+            _ if name.is_none() => name = Some(arg),
+            _ => bail!("Unexpected argument '{}'\n{}", arg, USAGE),
+        }
+    }
+    let name = name.ok_or_else(|| anyhow!("Missing the project name\n{}", USAGE))?;
+    if !is_crate_name(&name) {
+        bail!("'{}' is not a valid crate name, use letters, digits, - and _", name);
+    }
+    let engine = match engine {
+        Some(engine) => engine,
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).ancestors().nth(2).expect("Built inside the workspace").join("engine"),
+    };
+    if !engine.join("Cargo.toml").is_file() {
+        bail!("No engine crate at '{}'", engine.display());
+    }
+    let engine = std::path::absolute(&engine)?;
+    Ok(Options { dir: dir.unwrap_or_else(|| PathBuf::from(&name)), name, engine })
+}
+
+fn is_crate_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn write(dir: &Path, path: &str, contents: &str) -> Result<()> {
+    let path = dir.join(path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| anyhow!("Failed to create '{}': {}", parent.display(), e))?;
+    }
+    std::fs::write(&path, contents).map_err(|e| anyhow!("Failed to write '{}': {}", path.display(), e))
+}
+
+fn generate(options: &Options) -> Result<()> {
+    if options.dir.exists() && options.dir.read_dir()?.next().is_some() {
+        bail!("'{}' already exists and isn't empty", options.dir.display());
+    }
+    // TOML strings escape backslashes, Windows paths work with forward slashes too
+    let engine_path = options.engine.to_string_lossy().replace('\\', "/");
+    for (path, template) in TEMPLATES {
+        let contents = template.replace("{{name}}", &options.name).replace("{{engine_path}}", &engine_path);
+        write(&options.dir, path, &contents)?;
+    }
+    for (path, contents) in FILES {
+        write(&options.dir, path, contents)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let result = parse_args(std::env::args().skip(1)).and_then(|options| {
+        generate(&options)?;
+        println!("Created '{}' in {}", options.name, options.dir.display());
+        println!("Run it with: cd {} && cargo run", options.dir.display());
+        Ok(())
+    });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The renderer compiles every shader at startup, a project missing one doesn't start
+    #[test]
+    fn scaffolds_every_engine_shader() {
+        let shaders = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../engine/shaders");
+        for entry in std::fs::read_dir(shaders).unwrap() {
+            let name = entry.unwrap().file_name().into_string().unwrap();
+            let path = format!("shaders/{}", name);
+            assert!(FILES.iter().any(|(file, _)| *file == path), "{} is not scaffolded", path);
+        }
+    }
+}